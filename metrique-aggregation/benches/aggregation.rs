@@ -25,7 +25,7 @@ fn add<S: AggregationStrategy + Default + Send>(bencher: Bencher, items: usize)
         .counter(items)
         .with_inputs(|| {
             let values: Vec<f64> = {
-                let mut rng = ChaCha8Rng::seed_from_u64(0 as u64);
+                let mut rng = ChaCha8Rng::seed_from_u64(0_u64);
                 (0..items).map(|_| rng.random_range(0.0..1000.0)).collect()
             };
             let histogram = Arc::new(Mutex::new(Histogram::<f64, S>::new(S::default())));
@@ -70,7 +70,7 @@ fn add_atomic<S: SharedAggregationStrategy + Default + Send + Sync>(
         .counter(items)
         .with_inputs(|| {
             let values: Vec<f64> = {
-                let mut rng = ChaCha8Rng::seed_from_u64(0 as u64);
+                let mut rng = ChaCha8Rng::seed_from_u64(0_u64);
                 (0..items).map(|_| rng.random_range(0.0..1000.0)).collect()
             };
             let histogram = Arc::new(SharedHistogram::<f64, S>::new(S::default()));
@@ -97,7 +97,7 @@ async fn execute_distributed_query(query: &str, sink: BoxEntrySink) {
             requests_made: 1,
             latency,
             errors: if result.is_err() { 1 } else { 0 },
-            error_message: result.err().map(|err| format!("{err}")),
+            error_message: result.err().map(|err| err.to_string()),
             query_id: Arc::clone(&query_id),
         };
         if should_sample {
@@ -42,30 +42,33 @@ where
     pub fn new(mut inner: Inner, flush_interval: Duration) -> Self {
         let (sender, receiver) = channel();
 
-        let handle = thread::spawn(move || {
-            let mut last_flush = Instant::now();
-            loop {
-                let time_until_flush = flush_interval.saturating_sub(last_flush.elapsed());
-                match receiver.recv_timeout(time_until_flush) {
-                    Ok(QueueMessage::Entry(entry)) => {
-                        inner.merge(entry);
-                        if last_flush.elapsed() >= flush_interval {
+        let handle = thread::Builder::new()
+            .name("metrique-aggregation-worker".into())
+            .spawn(move || {
+                let mut last_flush = Instant::now();
+                loop {
+                    let time_until_flush = flush_interval.saturating_sub(last_flush.elapsed());
+                    match receiver.recv_timeout(time_until_flush) {
+                        Ok(QueueMessage::Entry(entry)) => {
+                            inner.merge(entry);
+                            if last_flush.elapsed() >= flush_interval {
+                                inner.flush();
+                                last_flush = Instant::now();
+                            }
+                        }
+                        Ok(QueueMessage::Flush(sender)) => {
+                            inner.flush();
+                            last_flush = Instant::now();
+                            let _ = sender.send(());
+                        }
+                        Err(_) => {
                             inner.flush();
                             last_flush = Instant::now();
                         }
-                    }
-                    Ok(QueueMessage::Flush(sender)) => {
-                        inner.flush();
-                        last_flush = Instant::now();
-                        let _ = sender.send(());
-                    }
-                    Err(_) => {
-                        inner.flush();
-                        last_flush = Instant::now();
                     }
                 }
-            }
-        });
+            })
+            .expect("failed to spawn the aggregation worker thread");
 
         Self {
             sender,
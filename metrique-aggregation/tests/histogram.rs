@@ -38,7 +38,7 @@ fn units_correctly_emitted() {
     check!(
         entry.metrics["HighPrecision"].distribution
             == vec![Observation::Repeated {
-                total: 2_000_000 as f64,
+                total: 2_000_000_f64,
                 occurrences: 2
             }]
     );
@@ -430,7 +430,7 @@ fn test_histogram_accuracy<S: AggregationStrategy>(
 
 #[track_caller]
 fn check_accuracy(expected: f64, buckets: &[Observation], percentile: f64, error_bound: f64) {
-    let actual = calculate_percentile_from_buckets(&buckets, percentile);
+    let actual = calculate_percentile_from_buckets(buckets, percentile);
     let error_pct = ((actual - expected).abs() / expected) * 100.0;
     check!(
         error_pct <= error_bound,
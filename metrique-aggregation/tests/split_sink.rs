@@ -1,5 +1,7 @@
 //! Test demonstrating SplitSink pattern
 
+#![allow(clippy::owned_cow)]
+
 use assert2::check;
 use metrique::unit_of_work::metrics;
 use metrique_aggregation::aggregate;
@@ -19,24 +19,24 @@ impl Counter {
 
     /// Add 1 to this counter
     pub fn increment(&self) {
-        self.0.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        self.0.fetch_add(1, core::sync::atomic::Ordering::Relaxed);
     }
 
     /// Increments the count by 1, returning a guard that decrements the count
     /// on drop, and the new value. Useful for tracking in-flight operations.
     pub fn increment_scoped(&self) -> (CounterGuard<'_>, u64) {
-        let count = self.0.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1;
+        let count = self.0.fetch_add(1, core::sync::atomic::Ordering::Relaxed) + 1;
         (CounterGuard(&self.0), count)
     }
 
     /// Increase the value of this counter by `i`
     pub fn add(&self, i: u64) {
-        self.0.fetch_add(i, std::sync::atomic::Ordering::Relaxed);
+        self.0.fetch_add(i, core::sync::atomic::Ordering::Relaxed);
     }
 
     /// Set this counter to `i`, discarding the previous value
     pub fn set(&self, i: u64) {
-        self.0.store(i, std::sync::atomic::Ordering::SeqCst);
+        self.0.store(i, core::sync::atomic::Ordering::SeqCst);
     }
 }
 
@@ -49,8 +49,8 @@ impl Drop for CounterGuard<'_> {
     fn drop(&mut self) {
         self.0
             .fetch_update(
-                std::sync::atomic::Ordering::Relaxed,
-                std::sync::atomic::Ordering::Relaxed,
+                core::sync::atomic::Ordering::Relaxed,
+                core::sync::atomic::Ordering::Relaxed,
                 |v| Some(v.saturating_sub(1)),
             )
             .ok();
@@ -62,7 +62,7 @@ impl CloseValue for &CounterGuard<'_> {
     type Closed = u64;
 
     fn close(self) -> Self::Closed {
-        self.0.load(std::sync::atomic::Ordering::Relaxed)
+        self.0.load(core::sync::atomic::Ordering::Relaxed)
     }
 }
 
@@ -97,7 +97,7 @@ macro_rules! close_value_atomic {
             type Closed = $inner;
 
             fn close(self) -> Self::Closed {
-                self.load(std::sync::atomic::Ordering::Relaxed)
+                self.load(core::sync::atomic::Ordering::Relaxed)
             }
         }
 
@@ -105,7 +105,7 @@ macro_rules! close_value_atomic {
             type Closed = $inner;
 
             fn close(self) -> Self::Closed {
-                self.load(std::sync::atomic::Ordering::Relaxed)
+                self.load(core::sync::atomic::Ordering::Relaxed)
             }
         }
     };
@@ -129,7 +129,7 @@ mod tests {
         let (guard, count) = counter.increment_scoped();
         assert_eq!(count, 1);
         drop(guard);
-        assert_eq!(counter.0.load(std::sync::atomic::Ordering::Relaxed), 0);
+        assert_eq!(counter.0.load(core::sync::atomic::Ordering::Relaxed), 0);
     }
 
     #[test]
@@ -138,7 +138,7 @@ mod tests {
         let (guard, count) = COUNTER.increment_scoped();
         assert_eq!(count, 1);
         drop(guard);
-        assert_eq!(COUNTER.0.load(std::sync::atomic::Ordering::Relaxed), 0);
+        assert_eq!(COUNTER.0.load(core::sync::atomic::Ordering::Relaxed), 0);
     }
 
     #[test]
@@ -149,6 +149,6 @@ mod tests {
         assert_eq!((&guard).close(), 1);
         // Guard still decrements on drop.
         drop(guard);
-        assert_eq!(counter.0.load(std::sync::atomic::Ordering::Relaxed), 0);
+        assert_eq!(counter.0.load(core::sync::atomic::Ordering::Relaxed), 0);
     }
 }
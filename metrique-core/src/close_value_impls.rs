@@ -3,15 +3,26 @@
 
 //! All default implementations of CloseValue, grouped for clarity
 
+use alloc::{
+    borrow::{Cow, ToOwned},
+    boxed::Box,
+    string::String,
+    sync::Arc,
+};
+use core::marker::PhantomData;
+use core::num::{NonZeroU8, NonZeroU16, NonZeroU32, NonZeroU64, NonZeroUsize};
 use core::time::Duration;
-use std::marker::PhantomData;
-use std::sync::{Arc, MutexGuard};
-use std::time::SystemTime;
-use std::{borrow::Cow, sync::Mutex};
-
-use metrique_writer_core::EntryWriter;
+#[cfg(feature = "std")]
+use std::ops::Range;
+#[cfg(feature = "std")]
+use std::sync::{Mutex, MutexGuard};
+#[cfg(feature = "std")]
+use std::time::{Instant, SystemTime};
+
+use metrique_writer_core::unit::WithUnit;
 use metrique_writer_core::value::WithDimensions;
 use metrique_writer_core::value::{FlagConstructor, ForceFlag};
+use metrique_writer_core::{EntryWriter, MetricValue};
 
 use crate::{CloseValue, CloseValueRef, InflectableEntry};
 
@@ -51,11 +62,49 @@ macro_rules! close_value {
 // This allows us to have specific impls for things like `WithDimensions`
 
 close_value_ref!(
-    bool, Duration, f32, f64, u16, u32, u64, u8, usize, SystemTime
+    bool,
+    Duration,
+    f32,
+    f64,
+    u16,
+    u32,
+    u64,
+    u8,
+    u128,
+    usize,
+    NonZeroU8,
+    NonZeroU16,
+    NonZeroU32,
+    NonZeroU64,
+    NonZeroUsize
 );
 
+#[cfg(feature = "std")]
+close_value_ref!(SystemTime);
+
 close_value!(String);
 
+/// A range of instants closes into the time between them, saturating to zero
+#[cfg(feature = "std")]
+#[diagnostic::do_not_recommend]
+impl CloseValue for Range<Instant> {
+    type Closed = Duration;
+
+    fn close(self) -> Self::Closed {
+        self.end.saturating_duration_since(self.start)
+    }
+}
+
+#[cfg(feature = "std")]
+#[diagnostic::do_not_recommend]
+impl CloseValue for &'_ Range<Instant> {
+    type Closed = Duration;
+
+    fn close(self) -> Self::Closed {
+        self.end.saturating_duration_since(self.start)
+    }
+}
+
 #[diagnostic::do_not_recommend]
 impl<'a> CloseValue for &'a str {
     type Closed = &'a str;
@@ -74,6 +123,24 @@ impl<'a> CloseValue for &&'a str {
     }
 }
 
+#[diagnostic::do_not_recommend]
+impl<'a> CloseValue for &'a [u8] {
+    type Closed = &'a [u8];
+
+    fn close(self) -> Self::Closed {
+        self
+    }
+}
+
+#[diagnostic::do_not_recommend]
+impl<'a> CloseValue for &&'a [u8] {
+    type Closed = &'a [u8];
+
+    fn close(self) -> Self::Closed {
+        *self
+    }
+}
+
 #[diagnostic::do_not_recommend]
 impl CloseValue for &Arc<String> {
     type Closed = Arc<String>;
@@ -91,6 +158,35 @@ impl CloseValue for Arc<String> {
     }
 }
 
+// `Arc<str>` and borrowed `Cow`s can be closed by reference without allocating (cloning a
+// `Cow::Owned` does allocate), which allows using them in `#[metrics(subfield)]` structs.
+
+#[diagnostic::do_not_recommend]
+impl CloseValue for &Arc<str> {
+    type Closed = Arc<str>;
+
+    fn close(self) -> Self::Closed {
+        self.clone()
+    }
+}
+
+impl CloseValue for Arc<str> {
+    type Closed = Arc<str>;
+
+    fn close(self) -> Self::Closed {
+        self
+    }
+}
+
+#[diagnostic::do_not_recommend]
+impl<'a, T: ToOwned + ?Sized> CloseValue for &Cow<'a, T> {
+    type Closed = Cow<'a, T>;
+
+    fn close(self) -> Self::Closed {
+        self.clone()
+    }
+}
+
 #[diagnostic::do_not_recommend]
 impl<'a, T: ToOwned + ?Sized> CloseValue for Cow<'a, T> {
     type Closed = Cow<'a, T>;
@@ -124,6 +220,31 @@ where
     }
 }
 
+// `Box` forwards to its contents, so that large `#[metrics(flatten)]` subfields can be boxed to
+// keep their parent small.
+
+#[diagnostic::do_not_recommend]
+impl<T, C> CloseValue for &'_ Box<T>
+where
+    T: CloseValueRef<Closed = C>,
+{
+    type Closed = C;
+
+    fn close(self) -> Self::Closed {
+        T::close_ref(self)
+    }
+}
+
+#[diagnostic::do_not_recommend]
+impl<T: CloseValue> CloseValue for Box<T> {
+    type Closed = T::Closed;
+
+    fn close(self) -> Self::Closed {
+        (*self).close()
+    }
+}
+
+#[cfg(feature = "std")]
 #[diagnostic::do_not_recommend]
 impl<T, C> CloseValue for &'_ std::sync::OnceLock<T>
 where
@@ -136,6 +257,7 @@ where
     }
 }
 
+#[cfg(feature = "std")]
 #[diagnostic::do_not_recommend]
 impl<T: CloseValue> CloseValue for std::sync::OnceLock<T> {
     type Closed = Option<T::Closed>;
@@ -145,6 +267,7 @@ impl<T: CloseValue> CloseValue for std::sync::OnceLock<T> {
     }
 }
 
+#[cfg(feature = "std")]
 #[diagnostic::do_not_recommend]
 impl<T, C> CloseValue for &'_ MutexGuard<'_, T>
 where
@@ -157,6 +280,7 @@ where
     }
 }
 
+#[cfg(feature = "std")]
 #[diagnostic::do_not_recommend]
 impl<T, C> CloseValue for MutexGuard<'_, T>
 where
@@ -169,6 +293,7 @@ where
     }
 }
 
+#[cfg(feature = "std")]
 #[diagnostic::do_not_recommend]
 impl<T, C> CloseValue for Mutex<T>
 where
@@ -181,6 +306,7 @@ where
     }
 }
 
+#[cfg(feature = "std")]
 #[diagnostic::do_not_recommend]
 impl<T, C> CloseValue for &'_ Mutex<T>
 where
@@ -225,6 +351,30 @@ impl<T: CloseValue, const N: usize> CloseValue for WithDimensions<T, N> {
 
 // no by-ref impl for WithDimensions due to not wanting to implicitly clone the dimensions
 
+#[diagnostic::do_not_recommend]
+impl<T: CloseValue, U> CloseValue for WithUnit<T, U>
+where
+    T::Closed: MetricValue,
+{
+    type Closed = WithUnit<T::Closed, U>;
+
+    fn close(self) -> Self::Closed {
+        self.into_inner().close().into()
+    }
+}
+
+#[diagnostic::do_not_recommend]
+impl<'a, T, U> CloseValue for &'a WithUnit<T, U>
+where
+    &'a T: CloseValue<Closed: MetricValue>,
+{
+    type Closed = WithUnit<<&'a T as CloseValue>::Closed, U>;
+
+    fn close(self) -> Self::Closed {
+        (&**self).close().into()
+    }
+}
+
 #[diagnostic::do_not_recommend]
 impl<T: CloseValue, F: FlagConstructor> CloseValue for ForceFlag<T, F> {
     type Closed = ForceFlag<T::Closed, F>;
@@ -242,13 +392,14 @@ struct ForceFlagEntryWriter<'a, W, FLAGS: FlagConstructor> {
 impl<'a, W: EntryWriter<'a>, FLAGS: FlagConstructor> EntryWriter<'a>
     for ForceFlagEntryWriter<'_, W, FLAGS>
 {
-    fn timestamp(&mut self, timestamp: std::time::SystemTime) {
+    #[cfg(feature = "std")]
+    fn timestamp(&mut self, timestamp: SystemTime) {
         self.writer.timestamp(timestamp)
     }
 
     fn value(
         &mut self,
-        name: impl Into<std::borrow::Cow<'a, str>>,
+        name: impl Into<Cow<'a, str>>,
         value: &(impl metrique_writer_core::Value + ?Sized),
     ) {
         self.writer.value(name, &ForceFlag::<_, FLAGS>::from(value))
@@ -281,6 +432,14 @@ impl<NS: crate::NameStyle, T: InflectableEntry<NS>, F: FlagConstructor> Inflecta
             },
         );
     }
+
+    fn describe(f: &mut dyn FnMut(crate::FieldDescriptor)) {
+        <T as InflectableEntry<NS>>::describe(f)
+    }
+
+    fn schema_version() -> Option<u64> {
+        <T as InflectableEntry<NS>>::schema_version()
+    }
 }
 
 #[diagnostic::do_not_recommend]
@@ -290,10 +449,19 @@ impl<NS: crate::NameStyle, T: InflectableEntry<NS>, const N: usize> InflectableE
     fn write<'a>(&'a self, writer: &mut impl metrique_writer_core::EntryWriter<'a>) {
         <T as InflectableEntry<NS>>::write(self, &mut self.entry_writer_wrapper(writer))
     }
+
+    fn describe(f: &mut dyn FnMut(crate::FieldDescriptor)) {
+        <T as InflectableEntry<NS>>::describe(f)
+    }
+
+    fn schema_version() -> Option<u64> {
+        <T as InflectableEntry<NS>>::schema_version()
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::borrow::Cow;
     use std::sync::{Arc, Mutex};
 
     use crate::CloseValue;
@@ -317,6 +485,17 @@ mod tests {
         }
     }
 
+    #[test]
+    fn close_shared_strings_by_ref() {
+        let arc: Arc<str> = Arc::from("shared");
+        let closed = (&arc).close();
+        assert!(Arc::ptr_eq(&arc, &closed));
+        assert_eq!(arc.close(), Arc::from("shared"));
+
+        let cow: Cow<'static, str> = Cow::Borrowed("borrowed");
+        assert!(matches!((&cow).close(), Cow::Borrowed("borrowed")));
+    }
+
     #[test]
     fn close_option() {
         let x = Some(Closeable);
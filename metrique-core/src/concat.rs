@@ -2,10 +2,77 @@
 //!
 //! This exists because const generics is insufficiently useful. It should
 //! be greatly simplified once const generics is better.
+//!
+//! ## Inflecting names in manual implementations
+//!
+//! Hand-written [`InflectableEntry`] impls can inflect and prefix their names like the ones
+//! generated by `#[metrics]`, without runtime string operations. Declare every name as an
+//! [`InflectedName`], and write it with [`inflected_name`]. Nested entries are written with the
+//! name style [`Prefixed`] (or [`ExactPrefixed`]) to add a prefix to their names.
+//!
+//! ```
+//! use metrique::concat::{self, InflectedName};
+//! use metrique::test_util::test_metric;
+//! use metrique::unit_of_work::metrics;
+//! use metrique::writer::EntryWriter;
+//! use metrique::{CloseValue, InflectableEntry, NameStyle};
+//!
+//! struct RetryCount;
+//! impl InflectedName for RetryCount {
+//!     const PRESERVE: &'static str = "retry_count";
+//!     const PASCAL_CASE: &'static str = "RetryCount";
+//!     const SNAKE_CASE: &'static str = "retry_count";
+//!     const KEBAB_CASE: &'static str = "retry-count";
+//! }
+//!
+//! struct ClientPrefix;
+//! impl InflectedName for ClientPrefix {
+//!     const PRESERVE: &'static str = "client_";
+//!     const PASCAL_CASE: &'static str = "Client";
+//!     const SNAKE_CASE: &'static str = "client_";
+//!     const KEBAB_CASE: &'static str = "client-";
+//! }
+//!
+//! #[metrics(subfield)]
+//! struct Inner {
+//!     latency_ms: u64,
+//! }
+//!
+//! struct RetriesEntry {
+//!     retries: u64,
+//!     inner: InnerEntry,
+//! }
+//!
+//! impl<NS: NameStyle> InflectableEntry<NS> for RetriesEntry {
+//!     fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+//!         writer.value(concat::inflected_name::<NS, RetryCount>(), &self.retries);
+//!         InflectableEntry::<concat::Prefixed<NS, ClientPrefix>>::write(&self.inner, writer);
+//!     }
+//! }
+//!
+//! #[metrics(rename_all = "PascalCase")]
+//! struct RequestMetrics {
+//!     #[metrics(flatten, no_close)]
+//!     retries: RetriesEntry,
+//! }
+//!
+//! let entry = test_metric(RequestMetrics {
+//!     retries: RetriesEntry {
+//!         retries: 2,
+//!         inner: Inner { latency_ms: 5 }.close(),
+//!     },
+//! });
+//! assert_eq!(entry.metrics["RetryCount"], 2);
+//! assert_eq!(entry.metrics["ClientLatencyMs"], 5);
+//! ```
+//!
+//! [`InflectableEntry`]: crate::InflectableEntry
 
-use std::{borrow::Cow, marker::PhantomData};
+use alloc::{borrow::Cow, string::String};
+use core::marker::PhantomData;
 
 use self::private::SealedMaybeConstStr;
+use crate::NameStyle;
 
 /// A trait representing a constant string lifted to a constant
 pub trait ConstStr {
@@ -96,7 +163,7 @@ impl<S: MaybeConstStr, T: MaybeConstStr, const N: usize> MaybeConstStr
     const MAYBE_VAL: &str = const {
         let buf =
             const { &concatenate_strings::<N>(S::MAYBE_VAL.as_bytes(), T::MAYBE_VAL.as_bytes()) };
-        match std::str::from_utf8(buf) {
+        match core::str::from_utf8(buf) {
             Ok(res) => res,
             Err(_) => panic!(),
         }
@@ -244,6 +311,70 @@ pub fn const_str_value<S: MaybeConstStr>() -> Cow<'static, str> {
     }
 }
 
+/// A name spelled in every case of the name styles, see the [module docs](self).
+///
+/// The spellings should be derived from a `snake_case` name. Names used as prefixes should end
+/// with a separator, like `"client_"` (`"Client"` in `PascalCase`).
+pub trait InflectedName {
+    /// The name, used when no `rename_all` is set
+    const PRESERVE: &'static str;
+    /// The name in `PascalCase`
+    const PASCAL_CASE: &'static str;
+    /// The name in `snake_case`
+    const SNAKE_CASE: &'static str;
+    /// The name in `kebab-case`
+    const KEBAB_CASE: &'static str;
+}
+
+/// The [`InflectedName::PRESERVE`] spelling of `N`, as a [`ConstStr`]
+pub struct PreserveCase<N>(PhantomData<N>);
+impl<N: InflectedName> ConstStr for PreserveCase<N> {
+    const VAL: &'static str = N::PRESERVE;
+}
+
+/// The [`InflectedName::PASCAL_CASE`] spelling of `N`, as a [`ConstStr`]
+pub struct PascalCase<N>(PhantomData<N>);
+impl<N: InflectedName> ConstStr for PascalCase<N> {
+    const VAL: &'static str = N::PASCAL_CASE;
+}
+
+/// The [`InflectedName::SNAKE_CASE`] spelling of `N`, as a [`ConstStr`]
+pub struct SnakeCase<N>(PhantomData<N>);
+impl<N: InflectedName> ConstStr for SnakeCase<N> {
+    const VAL: &'static str = N::SNAKE_CASE;
+}
+
+/// The [`InflectedName::KEBAB_CASE`] spelling of `N`, as a [`ConstStr`]
+pub struct KebabCase<N>(PhantomData<N>);
+impl<N: InflectedName> ConstStr for KebabCase<N> {
+    const VAL: &'static str = N::KEBAB_CASE;
+}
+
+/// The name `N` inflected to the case of the name style `NS`, after the prefixes of `NS`
+pub type Inflected<NS, N> =
+    <NS as NameStyle>::Inflect<PreserveCase<N>, PascalCase<N>, SnakeCase<N>, KebabCase<N>>;
+
+/// The name `N` inflected to the case of the name style `NS`, without the prefixes of `NS`
+pub type InflectedAffix<NS, N> =
+    <NS as NameStyle>::InflectAffix<PreserveCase<N>, PascalCase<N>, SnakeCase<N>, KebabCase<N>>;
+
+/// The name style `NS`, with the prefix `N` inflected to its case appended to its prefixes.
+///
+/// This is the name style of an entry nested with `#[metrics(flatten, prefix = ...)]`.
+pub type Prefixed<NS, N> = <NS as NameStyle>::AppendPrefix<InflectedAffix<NS, N>>;
+
+/// The name style `NS`, with the prefix `P` appended to its prefixes as is.
+///
+/// This is the name style of an entry nested with `#[metrics(flatten, exact_prefix = ...)]`.
+pub type ExactPrefixed<NS, P> = <NS as NameStyle>::AppendPrefix<P>;
+
+/// The name `N` inflected (and prefixed) according to the name style `NS`.
+///
+/// This does not allocate unless the prefixed name is longer than 100 bytes.
+pub fn inflected_name<NS: NameStyle, N: InflectedName>() -> Cow<'static, str> {
+    const_str_value::<Inflected<NS, N>>()
+}
+
 #[cfg(test)]
 mod test {
     use std::borrow::Cow;
@@ -304,4 +435,44 @@ mod test {
             _ => panic!(),
         };
     }
+
+    struct RequestCount;
+    impl super::InflectedName for RequestCount {
+        const PRESERVE: &'static str = "request_count";
+        const PASCAL_CASE: &'static str = "RequestCount";
+        const SNAKE_CASE: &'static str = "request_count";
+        const KEBAB_CASE: &'static str = "request-count";
+    }
+
+    struct ApiPrefix;
+    impl super::InflectedName for ApiPrefix {
+        const PRESERVE: &'static str = "api_";
+        const PASCAL_CASE: &'static str = "Api";
+        const SNAKE_CASE: &'static str = "api_";
+        const KEBAB_CASE: &'static str = "api-";
+    }
+
+    #[test]
+    fn inflected_names() {
+        use super::{ExactPrefixed, Prefixed, inflected_name};
+        use crate::namestyle::{Identity, KebabCase, PascalCase, SnakeCase};
+
+        assert_eq!(inflected_name::<Identity, RequestCount>(), "request_count");
+        assert_eq!(inflected_name::<PascalCase, RequestCount>(), "RequestCount");
+        assert_eq!(inflected_name::<SnakeCase, RequestCount>(), "request_count");
+        assert_eq!(inflected_name::<KebabCase, RequestCount>(), "request-count");
+
+        assert_eq!(
+            inflected_name::<Prefixed<PascalCase, ApiPrefix>, RequestCount>(),
+            "ApiRequestCount"
+        );
+        assert_eq!(
+            inflected_name::<Prefixed<Prefixed<KebabCase, ApiPrefix>, ApiPrefix>, RequestCount>(),
+            "api-api-request-count"
+        );
+        assert!(matches!(
+            inflected_name::<ExactPrefixed<PascalCase, ConstFoo>, RequestCount>(),
+            Cow::Borrowed("Foo_RequestCount")
+        ));
+    }
 }
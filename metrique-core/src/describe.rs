@@ -0,0 +1,30 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use alloc::borrow::Cow;
+
+/// Metadata about a metric field, for exporters that can surface it (for example as Prometheus
+/// `HELP` text or an OpenTelemetry instrument description).
+///
+/// Descriptors are produced by [`InflectableEntry::describe`], which `#[metrics]` implements
+/// from the doc comments of the fields when `#[metrics(doc_as_description)]` is set.
+///
+/// [`InflectableEntry::describe`]: crate::InflectableEntry::describe
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct FieldDescriptor {
+    /// The name of the field as it is emitted, after renames and prefixes
+    pub name: Cow<'static, str>,
+    /// The description of the field
+    pub description: &'static str,
+}
+
+impl FieldDescriptor {
+    /// Create a new [`FieldDescriptor`]
+    pub fn new(name: impl Into<Cow<'static, str>>, description: &'static str) -> Self {
+        Self {
+            name: name.into(),
+            description,
+        }
+    }
+}
@@ -0,0 +1,151 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Metric values for types from the [`http`] crate.
+//!
+//! With the `http` feature, [`http::StatusCode`] and [`http::Method`] can be used directly as
+//! fields (and as `sample_group`s), and are emitted as string properties like `"404"` and
+//! `"GET"`.
+//!
+//! An [`http::Uri`] closes into its [sanitized path](sanitize_path), so that it can be emitted as
+//! a property without leaking query strings or creating a distinct value for every resource id.
+//!
+//! ```
+//! # use metrique::unit_of_work::metrics;
+//! #[metrics(rename_all = "PascalCase")]
+//! struct RequestMetrics {
+//!     #[metrics(sample_group)]
+//!     method: http::Method,
+//!     #[metrics(sample_group)]
+//!     status: http::StatusCode,
+//!     path: http::Uri,
+//! }
+//!
+//! let entry = metrique::test_util::test_metric(RequestMetrics {
+//!     method: http::Method::GET,
+//!     status: http::StatusCode::NOT_FOUND,
+//!     path: "https://example.com/users/1234/orders?token=secret".parse().unwrap(),
+//! });
+//! assert_eq!(entry.values["Method"], "GET");
+//! assert_eq!(entry.values["Status"], "404");
+//! assert_eq!(entry.values["Path"], "/users/{id}/orders");
+//! ```
+
+use crate::CloseValue;
+
+/// The placeholder [`sanitize_path`] substitutes for identifier-like path segments
+pub const ID_PLACEHOLDER: &str = "{id}";
+
+/// Return the path of `uri`, with identifier-like segments replaced by [`ID_PLACEHOLDER`]
+///
+/// The scheme, authority, query string and fragment are dropped. A segment is considered an
+/// identifier if it is made only of digits, is a UUID, or is a hexadecimal string of at least
+/// 16 characters (like a hash or an object id).
+pub fn sanitize_path(uri: &http::Uri) -> String {
+    let path = uri.path();
+    let mut sanitized = String::with_capacity(path.len());
+    for (i, segment) in path.split('/').enumerate() {
+        if i > 0 {
+            sanitized.push('/');
+        }
+        if is_identifier(segment) {
+            sanitized.push_str(ID_PLACEHOLDER);
+        } else {
+            sanitized.push_str(segment);
+        }
+    }
+    sanitized
+}
+
+fn is_identifier(segment: &str) -> bool {
+    if segment.is_empty() {
+        return false;
+    }
+    if segment.bytes().all(|b| b.is_ascii_digit()) {
+        return true;
+    }
+    if segment.len() >= 16 && segment.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return true;
+    }
+    is_uuid(segment)
+}
+
+fn is_uuid(segment: &str) -> bool {
+    let groups: Vec<&str> = segment.split('-').collect();
+    groups.len() == 5
+        && groups
+            .iter()
+            .zip([8, 4, 4, 4, 12])
+            .all(|(group, len)| group.len() == len && group.bytes().all(|b| b.is_ascii_hexdigit()))
+}
+
+impl CloseValue for http::StatusCode {
+    type Closed = http::StatusCode;
+
+    fn close(self) -> Self::Closed {
+        self
+    }
+}
+
+impl CloseValue for &'_ http::StatusCode {
+    type Closed = http::StatusCode;
+
+    fn close(self) -> Self::Closed {
+        *self
+    }
+}
+
+impl CloseValue for http::Method {
+    type Closed = http::Method;
+
+    fn close(self) -> Self::Closed {
+        self
+    }
+}
+
+impl CloseValue for &'_ http::Method {
+    type Closed = http::Method;
+
+    fn close(self) -> Self::Closed {
+        self.clone()
+    }
+}
+
+impl CloseValue for http::Uri {
+    type Closed = String;
+
+    fn close(self) -> Self::Closed {
+        sanitize_path(&self)
+    }
+}
+
+impl CloseValue for &'_ http::Uri {
+    type Closed = String;
+
+    fn close(self) -> Self::Closed {
+        sanitize_path(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sanitize_path;
+
+    #[test]
+    fn sanitizes_identifiers() {
+        for (uri, expected) in [
+            ("/", "/"),
+            ("/v1/users", "/v1/users"),
+            ("/users/42/", "/users/{id}/"),
+            ("http://example.com/a/12?x=1#frag", "/a/{id}"),
+            (
+                "/objects/123e4567-e89b-12d3-a456-426614174000/versions/0123456789abcdef",
+                "/objects/{id}/versions/{id}",
+            ),
+            ("/objects/cafe/beef-1", "/objects/cafe/beef-1"),
+            ("example.com:443", ""),
+        ] {
+            assert_eq!(sanitize_path(&uri.parse().unwrap()), expected, "{uri}");
+        }
+    }
+}
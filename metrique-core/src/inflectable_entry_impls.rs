@@ -3,11 +3,16 @@
 
 // Delegate Entry impls for references and standard containers
 
-use std::{borrow::Cow, sync::Arc};
+use alloc::{
+    borrow::{Cow, ToOwned},
+    boxed::Box,
+    sync::Arc,
+    vec::Vec,
+};
 
 use metrique_writer_core::{EntryWriter, entry::SampleGroupElement};
 
-use crate::{InflectableEntry, namestyle::NameStyle};
+use crate::{FieldDescriptor, InflectableEntry, namestyle::NameStyle};
 
 impl<NS: NameStyle, T: InflectableEntry<NS>> InflectableEntry<NS> for &T {
     fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
@@ -17,6 +22,14 @@ impl<NS: NameStyle, T: InflectableEntry<NS>> InflectableEntry<NS> for &T {
     fn sample_group(&self) -> impl Iterator<Item = SampleGroupElement> {
         (**self).sample_group()
     }
+
+    fn describe(f: &mut dyn FnMut(FieldDescriptor)) {
+        T::describe(f)
+    }
+
+    fn schema_version() -> Option<u64> {
+        T::schema_version()
+    }
 }
 
 impl<NS: NameStyle, T: InflectableEntry<NS>> InflectableEntry<NS> for Option<T> {
@@ -33,6 +46,14 @@ impl<NS: NameStyle, T: InflectableEntry<NS>> InflectableEntry<NS> for Option<T>
             itertools::Either::Right([].into_iter())
         }
     }
+
+    fn describe(f: &mut dyn FnMut(FieldDescriptor)) {
+        T::describe(f)
+    }
+
+    fn schema_version() -> Option<u64> {
+        T::schema_version()
+    }
 }
 
 impl<NS: NameStyle, T: InflectableEntry<NS> + ?Sized> InflectableEntry<NS> for Box<T> {
@@ -43,6 +64,14 @@ impl<NS: NameStyle, T: InflectableEntry<NS> + ?Sized> InflectableEntry<NS> for B
     fn sample_group(&self) -> impl Iterator<Item = SampleGroupElement> {
         (**self).sample_group()
     }
+
+    fn describe(f: &mut dyn FnMut(FieldDescriptor)) {
+        T::describe(f)
+    }
+
+    fn schema_version() -> Option<u64> {
+        T::schema_version()
+    }
 }
 
 impl<NS: NameStyle, T: InflectableEntry<NS> + ?Sized> InflectableEntry<NS> for Arc<T> {
@@ -53,6 +82,14 @@ impl<NS: NameStyle, T: InflectableEntry<NS> + ?Sized> InflectableEntry<NS> for A
     fn sample_group(&self) -> impl Iterator<Item = SampleGroupElement> {
         (**self).sample_group()
     }
+
+    fn describe(f: &mut dyn FnMut(FieldDescriptor)) {
+        T::describe(f)
+    }
+
+    fn schema_version() -> Option<u64> {
+        T::schema_version()
+    }
 }
 
 impl<NS: NameStyle, T: InflectableEntry<NS> + ToOwned + ?Sized> InflectableEntry<NS>
@@ -65,4 +102,75 @@ impl<NS: NameStyle, T: InflectableEntry<NS> + ToOwned + ?Sized> InflectableEntry
     fn sample_group(&self) -> impl Iterator<Item = SampleGroupElement> {
         (**self).sample_group()
     }
+
+    fn describe(f: &mut dyn FnMut(FieldDescriptor)) {
+        T::describe(f)
+    }
+
+    fn schema_version() -> Option<u64> {
+        T::schema_version()
+    }
+}
+
+// Tuples, `Vec`s and arrays of entries write each of their entries, in order
+
+macro_rules! tuple_inflectable_entry {
+    ($($name:ident $idx:tt),+) => {
+        impl<NS: NameStyle, $($name: InflectableEntry<NS>),+> InflectableEntry<NS> for ($($name,)+) {
+            fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+                $(self.$idx.write(writer);)+
+            }
+
+            fn sample_group(&self) -> impl Iterator<Item = SampleGroupElement> {
+                let group = core::iter::empty();
+                $(let group = group.chain(self.$idx.sample_group());)+
+                group
+            }
+
+            fn describe(f: &mut dyn FnMut(FieldDescriptor)) {
+                $($name::describe(f);)+
+            }
+        }
+    };
+}
+
+tuple_inflectable_entry!(A 0);
+tuple_inflectable_entry!(A 0, B 1);
+tuple_inflectable_entry!(A 0, B 1, C 2);
+tuple_inflectable_entry!(A 0, B 1, C 2, D 3);
+tuple_inflectable_entry!(A 0, B 1, C 2, D 3, E 4);
+tuple_inflectable_entry!(A 0, B 1, C 2, D 3, E 4, F 5);
+tuple_inflectable_entry!(A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+tuple_inflectable_entry!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+
+impl<NS: NameStyle, T: InflectableEntry<NS>> InflectableEntry<NS> for Vec<T> {
+    fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+        for entry in self {
+            entry.write(writer);
+        }
+    }
+
+    fn sample_group(&self) -> impl Iterator<Item = SampleGroupElement> {
+        self.iter().flat_map(|entry| entry.sample_group())
+    }
+
+    fn describe(f: &mut dyn FnMut(FieldDescriptor)) {
+        T::describe(f)
+    }
+}
+
+impl<NS: NameStyle, T: InflectableEntry<NS>, const N: usize> InflectableEntry<NS> for [T; N] {
+    fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+        for entry in self {
+            entry.write(writer);
+        }
+    }
+
+    fn sample_group(&self) -> impl Iterator<Item = SampleGroupElement> {
+        self.iter().flat_map(|entry| entry.sample_group())
+    }
+
+    fn describe(f: &mut dyn FnMut(FieldDescriptor)) {
+        T::describe(f)
+    }
 }
@@ -4,16 +4,23 @@
 #![deny(missing_docs)]
 #![doc = include_str!("../README.md")]
 #![cfg_attr(docsrs, feature(doc_cfg))]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
 
 use metrique_writer_core::{EntryWriter, entry::SampleGroupElement};
 
 mod atomics;
 mod close_value_impls;
 pub mod concat;
+mod describe;
+#[cfg(feature = "http")]
+pub mod http;
 mod inflectable_entry_impls;
 mod namestyle;
 
 pub use atomics::{Counter, CounterGuard};
+pub use describe::FieldDescriptor;
 pub use namestyle::NameStyle;
 
 /// Close a given value
@@ -230,10 +237,12 @@ impl<T: ?Sized + CloseValue<Closed: InflectableEntry>> CloseEntry for T {}
 ///
 /// ## Manual Implementations
 ///
-/// Currently, there is no (stable) non-macro way of generating an [`InflectableEntry`]
-/// that actually inflects names. If you want to make a manual entry, it is recommended
-/// to implement the [`Entry`] trait, then use a field with `#[metrics(flatten_entry)]`
-/// as follows - though note that this will ignore inflections:
+/// To implement [`InflectableEntry`] by hand and inflect names like `#[metrics]` does, use
+/// the names and name styles of the [`concat`](mod@crate::concat) module, which has an example.
+///
+/// If the entry does not need inflection, it is simpler to implement the [`Entry`] trait, then
+/// use a field with `#[metrics(flatten_entry)]` as follows - though note that this will ignore
+/// inflections:
 ///
 /// ```
 /// use metrique::unit_of_work::metrics;
@@ -263,6 +272,19 @@ pub trait InflectableEntry<NS: namestyle::NameStyle = namestyle::Identity> {
     fn write<'a>(&'a self, w: &mut impl EntryWriter<'a>);
     /// Sample group
     fn sample_group(&self) -> impl Iterator<Item = SampleGroupElement> {
-        vec![].into_iter()
+        core::iter::empty()
+    }
+    /// Call `f` with the [`FieldDescriptor`] of every field of this entry that has a description.
+    ///
+    /// The default implementation describes no fields.
+    fn describe(f: &mut dyn FnMut(FieldDescriptor)) {
+        let _ = f;
+    }
+    /// The schema version of this entry, set with `#[metrics(version = N)]`, which `#[metrics]`
+    /// also writes as the `schema_version` property of every entry.
+    ///
+    /// The default implementation has no version.
+    fn schema_version() -> Option<u64> {
+        None
     }
 }
@@ -3,7 +3,7 @@
 
 //! Contains various name styles
 
-use std::marker::PhantomData;
+use core::marker::PhantomData;
 
 use crate::concat::{Concatenated, EmptyConstStr, MaybeConstStr};
 
@@ -14,7 +14,8 @@ pub(crate) mod private {
 
 /// This trait is used to describe name styles for [`InflectableEntry`].
 ///
-/// The exact implementation of this trait is currently unstable.
+/// The exact implementation of this trait is currently unstable. Use the names and name styles
+/// of [`concat`](mod@crate::concat) to inflect names in manual [`InflectableEntry`] impls.
 ///
 /// [`InflectableEntry`]: crate::InflectableEntry
 pub trait NameStyle: private::NameStyleInternal {
@@ -30,6 +31,10 @@ pub trait NameStyle: private::NameStyleInternal {
     #[doc(hidden)]
     type AppendPrefix<T: MaybeConstStr>: NameStyle;
 
+    /// This name style, ignoring any `rename_all` of nested entries
+    #[doc(hidden)]
+    type Fixed: NameStyle;
+
     /// Inflect the name, adding prefixes
     #[doc(hidden)]
     type Inflect<ID: MaybeConstStr, PASCAL: MaybeConstStr, SNAKE: MaybeConstStr, KEBAB: MaybeConstStr>: MaybeConstStr;
@@ -47,6 +52,7 @@ impl<PREFIX: MaybeConstStr> NameStyle for Identity<PREFIX> {
     type PascalCase = PascalCase<PREFIX>;
     type SnakeCase = SnakeCase<PREFIX>;
     type AppendPrefix<P: MaybeConstStr> = Identity<Concatenated<PREFIX, P>>;
+    type Fixed = Fixed<Self>;
     type Inflect<
         ID: MaybeConstStr,
         PASCAL: MaybeConstStr,
@@ -69,6 +75,7 @@ impl<PREFIX: MaybeConstStr> NameStyle for PascalCase<PREFIX> {
     type PascalCase = PascalCase<PREFIX>;
     type SnakeCase = SnakeCase<PREFIX>;
     type AppendPrefix<P: MaybeConstStr> = PascalCase<Concatenated<PREFIX, P>>;
+    type Fixed = Fixed<Self>;
     type Inflect<
         ID: MaybeConstStr,
         PASCAL: MaybeConstStr,
@@ -91,6 +98,7 @@ impl<PREFIX: MaybeConstStr> NameStyle for SnakeCase<PREFIX> {
     type PascalCase = PascalCase<PREFIX>;
     type SnakeCase = SnakeCase<PREFIX>;
     type AppendPrefix<P: MaybeConstStr> = SnakeCase<Concatenated<PREFIX, P>>;
+    type Fixed = Fixed<Self>;
     type Inflect<
         ID: MaybeConstStr,
         PASCAL: MaybeConstStr,
@@ -113,6 +121,7 @@ impl<PREFIX: MaybeConstStr> NameStyle for KebabCase<PREFIX> {
     type PascalCase = PascalCase<PREFIX>;
     type SnakeCase = SnakeCase<PREFIX>;
     type AppendPrefix<P: MaybeConstStr> = KebabCase<Concatenated<PREFIX, P>>;
+    type Fixed = Fixed<Self>;
     type Inflect<
         ID: MaybeConstStr,
         PASCAL: MaybeConstStr,
@@ -126,3 +135,27 @@ impl<PREFIX: MaybeConstStr> NameStyle for KebabCase<PREFIX> {
         KEBAB: MaybeConstStr,
     > = KEBAB;
 }
+
+/// Keeps the name style `NS`, even if a nested entry asks for a different one. Used by
+/// `#[metrics(flatten, rename_all = "...")]`.
+pub struct Fixed<NS: NameStyle>(PhantomData<NS>);
+impl<NS: NameStyle> private::NameStyleInternal for Fixed<NS> {}
+impl<NS: NameStyle> NameStyle for Fixed<NS> {
+    type KebabCase = Self;
+    type PascalCase = Self;
+    type SnakeCase = Self;
+    type AppendPrefix<P: MaybeConstStr> = Fixed<NS::AppendPrefix<P>>;
+    type Fixed = Self;
+    type Inflect<
+        ID: MaybeConstStr,
+        PASCAL: MaybeConstStr,
+        SNAKE: MaybeConstStr,
+        KEBAB: MaybeConstStr,
+    > = NS::Inflect<ID, PASCAL, SNAKE, KEBAB>;
+    type InflectAffix<
+        ID: MaybeConstStr,
+        PASCAL: MaybeConstStr,
+        SNAKE: MaybeConstStr,
+        KEBAB: MaybeConstStr,
+    > = NS::InflectAffix<ID, PASCAL, SNAKE, KEBAB>;
+}
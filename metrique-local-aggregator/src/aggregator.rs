@@ -0,0 +1,312 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    io::{self, BufRead, BufReader, Read},
+    mem,
+    os::unix::{
+        fs::FileTypeExt,
+        net::{UnixListener, UnixStream},
+    },
+    path::Path,
+    sync::{Arc, Mutex, MutexGuard},
+    thread,
+    time::Duration,
+};
+
+use metrique_writer::{
+    EntryIoStream, EntrySink, Observation, Unit,
+    entry::EntrySnapshot,
+    error_handler::{self, PipelineError},
+    sink::{DeduplicateSink, FlushWait},
+};
+
+/// The default interval at which merged entries are written, see
+/// [`LocalAggregator::flush_interval`]
+pub const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+// the name of the aggregator in the errors passed to the error handler
+const SINK_NAME: &str = "metrique-local-aggregator";
+
+// the longest line read from a worker, longer lines are skipped without buffering them
+const MAX_LINE_BYTES: usize = 1024 * 1024;
+
+// the most distinct values of a metric in a merged entry, since CloudWatch rejects EMF metrics with
+// more of them
+const MAX_DISTINCT_VALUES: usize = 100;
+
+/// A per-host aggregator, which receives the entries of many worker processes over a Unix domain
+/// socket and writes them to a single output.
+///
+/// Workers forward their entries with a [`ForwardFormat`](crate::ForwardFormat). The aggregator
+/// reads them from every connection, optionally drops duplicates (see
+/// [`LocalAggregator::deduplicate_on`]), and every [flush interval](LocalAggregator::flush_interval)
+/// writes them to its output.
+///
+/// Within a flush interval, entries with the same properties and the same metric names, units and
+/// dimensions are merged into one entry, whose metrics hold the observations of all of them. With
+/// EMF, equal observations are written once with their count, so many small entries become one
+/// compact record. Entries with a unique property, like a request ID, are never merged. The merged
+/// entry keeps the earliest timestamp. Once a metric of a merged entry would have more than 100
+/// distinct values, which CloudWatch doesn't accept, a new merged entry is started.
+///
+/// Lines that are not forwarded entries, or that are longer than 1 MiB, are skipped. Entries that the output rejects, and errors
+/// writing the output, are reported to the [error handler](metrique_writer::error_handler).
+///
+/// ```no_run
+/// use metrique_local_aggregator::LocalAggregator;
+/// use metrique_writer::FormatExt;
+/// use metrique_writer_format_emf::Emf;
+///
+/// let output = Emf::builder("MyApp".into(), vec![vec![]])
+///     .build()
+///     .output_to(std::io::stdout());
+/// LocalAggregator::bind("/run/metrique.sock")?.run(output);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct LocalAggregator {
+    listener: UnixListener,
+    flush_interval: Duration,
+    deduplicate: Option<(Cow<'static, str>, Duration)>,
+}
+
+impl LocalAggregator {
+    /// Listen for workers on the Unix domain socket at `path`.
+    ///
+    /// A socket left at `path` by an aggregator that exited is replaced. Other files are not.
+    pub fn bind(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        if fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+            fs::remove_file(path)?;
+        }
+        Ok(Self {
+            listener: UnixListener::bind(path)?,
+            flush_interval: DEFAULT_FLUSH_INTERVAL,
+            deduplicate: None,
+        })
+    }
+
+    /// Set the interval at which merged entries are written to the output. Defaults to
+    /// [`DEFAULT_FLUSH_INTERVAL`].
+    ///
+    /// Longer intervals merge more entries, and delay them more.
+    pub fn flush_interval(mut self, flush_interval: Duration) -> Self {
+        self.flush_interval = flush_interval;
+        self
+    }
+
+    /// Drop entries whose string property `key_field` was already seen within `window`, across
+    /// all workers, like a [`DeduplicateSink`].
+    pub fn deduplicate_on(
+        mut self,
+        key_field: impl Into<Cow<'static, str>>,
+        window: Duration,
+    ) -> Self {
+        self.deduplicate = Some((key_field.into(), window));
+        self
+    }
+
+    /// Accept workers and write their entries to `output`, forever.
+    ///
+    /// Every worker connection is read on its own thread, and `output` is only written from the
+    /// calling thread.
+    pub fn run(self, mut output: impl EntryIoStream) -> ! {
+        let pending = PendingEntries::default();
+        let ingest = match self.deduplicate {
+            Some((key_field, window)) => {
+                Ingest::Deduplicated(DeduplicateSink::new(pending.clone(), key_field, window))
+            }
+            None => Ingest::Direct(pending.clone()),
+        };
+        let listener = self.listener;
+        thread::Builder::new()
+            .name("metrique-local-aggregator-accept".into())
+            .spawn(move || accept(listener, ingest))
+            .expect("failed to spawn the accept thread");
+
+        loop {
+            thread::sleep(self.flush_interval);
+            for merged in pending.take().into_values().flatten() {
+                if let Err(err) = output.next(&merged.entry) {
+                    error_handler::report_error(&PipelineError::from_stream_error(SINK_NAME, &err));
+                }
+            }
+            if let Err(error) = output.flush() {
+                error_handler::report_error(&PipelineError::Io {
+                    sink: SINK_NAME,
+                    error: &error,
+                });
+            }
+        }
+    }
+}
+
+fn accept(listener: UnixListener, ingest: Ingest) {
+    for connection in listener.incoming() {
+        // errors here are about a single connection, like a worker that hung up early
+        let Ok(connection) = connection else {
+            continue;
+        };
+        let ingest = ingest.clone();
+        let spawned = thread::Builder::new()
+            .name("metrique-local-aggregator-worker".into())
+            .spawn(move || read_entries(connection, ingest));
+        // if the thread can't be spawned, the connection is dropped and the worker reconnects
+        drop(spawned);
+    }
+}
+
+fn read_entries(connection: UnixStream, ingest: Ingest) {
+    let mut reader = BufReader::new(connection);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        let limit = MAX_LINE_BYTES as u64 + 1;
+        match (&mut reader).take(limit).read_until(b'\n', &mut line) {
+            Ok(0) | Err(_) => return,
+            Ok(_) => {}
+        }
+        if line.len() > MAX_LINE_BYTES {
+            // skip the rest of the line
+            match reader.skip_until(b'\n') {
+                Ok(0) | Err(_) => return,
+                Ok(_) => continue,
+            }
+        }
+        let snapshot = serde_json::from_slice(&line)
+            .ok()
+            .and_then(|json| EntrySnapshot::from_json(&json));
+        if let Some(snapshot) = snapshot {
+            ingest.append(snapshot);
+        }
+    }
+}
+
+#[derive(Clone)]
+enum Ingest {
+    Direct(PendingEntries),
+    Deduplicated(DeduplicateSink<PendingEntries>),
+}
+
+impl Ingest {
+    fn append(&self, entry: EntrySnapshot) {
+        match self {
+            Self::Direct(sink) => sink.append(entry),
+            Self::Deduplicated(sink) => sink.append(entry),
+        }
+    }
+}
+
+// Entries are merged when everything but their observations and timestamp is equal
+#[derive(PartialEq, Eq, Hash)]
+struct MergeKey {
+    properties: BTreeMap<String, String>,
+    metrics: Vec<MetricKey>,
+}
+
+// The name, unit and dimensions of a metric
+type MetricKey = (String, Unit, Vec<(String, String)>);
+
+impl MergeKey {
+    fn new(entry: &EntrySnapshot) -> Self {
+        Self {
+            properties: entry.properties.clone(),
+            metrics: entry
+                .metrics
+                .iter()
+                .map(|(name, metric)| (name.clone(), metric.unit, metric.dimensions.clone()))
+                .collect(),
+        }
+    }
+}
+
+// A key that is equal for observations that EMF writes as the same value
+type ValueKey = (bool, u64);
+
+fn value_key(observation: &Observation) -> ValueKey {
+    observation.mean().key()
+}
+
+/// An entry merged from the entries with the same [`MergeKey`], and the distinct values of each of
+/// its metrics
+struct Merged {
+    entry: EntrySnapshot,
+    values: HashMap<String, HashSet<ValueKey>>,
+}
+
+impl Merged {
+    fn new(entry: EntrySnapshot) -> Self {
+        let values = entry
+            .metrics
+            .iter()
+            .map(|(name, metric)| {
+                let values = metric.observations.iter().map(value_key).collect();
+                (name.clone(), values)
+            })
+            .collect();
+        Self { entry, values }
+    }
+
+    /// Whether merging `entry` keeps every metric within [`MAX_DISTINCT_VALUES`]
+    fn can_merge(&self, entry: &EntrySnapshot) -> bool {
+        entry.metrics.iter().all(|(name, metric)| {
+            let Some(values) = self.values.get(name) else {
+                return true;
+            };
+            let new = (metric.observations.iter().map(value_key))
+                .filter(|key| !values.contains(key))
+                .collect::<HashSet<_>>();
+            values.len() + new.len() <= MAX_DISTINCT_VALUES
+        })
+    }
+
+    fn merge(&mut self, entry: EntrySnapshot) {
+        self.entry.timestamp = match (self.entry.timestamp, entry.timestamp) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        for (name, metric) in entry.metrics {
+            if let Some(into) = self.entry.metrics.get_mut(&name) {
+                let values = self.values.entry(name).or_default();
+                values.extend(metric.observations.iter().map(value_key));
+                into.observations.extend(metric.observations);
+            }
+        }
+    }
+}
+
+/// The entries received since the last flush, merged. Entries with the same [`MergeKey`] are
+/// merged into the last of their merged entries, until it is full.
+#[derive(Clone, Default)]
+struct PendingEntries {
+    entries: Arc<Mutex<HashMap<MergeKey, Vec<Merged>>>>,
+}
+
+impl PendingEntries {
+    fn lock(&self) -> MutexGuard<'_, HashMap<MergeKey, Vec<Merged>>> {
+        // entries are merged in place without panicking, so it is fine to ignore poisoning
+        self.entries.lock().unwrap_or_else(|p| p.into_inner())
+    }
+
+    fn take(&self) -> HashMap<MergeKey, Vec<Merged>> {
+        mem::take(&mut *self.lock())
+    }
+}
+
+impl EntrySink<EntrySnapshot> for PendingEntries {
+    fn append(&self, entry: EntrySnapshot) {
+        let mut entries = self.lock();
+        let merged = entries.entry(MergeKey::new(&entry)).or_default();
+        match merged.last_mut() {
+            Some(last) if last.can_merge(&entry) => last.merge(entry),
+            _ => merged.push(Merged::new(entry)),
+        }
+    }
+
+    fn flush_async(&self) -> FlushWait {
+        FlushWait::ready()
+    }
+}
@@ -0,0 +1,48 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::io;
+
+use metrique_writer::{Entry, IoStreamError, entry::EntrySnapshot, format::Format};
+
+/// The [`Format`] that worker processes use to forward their entries to a
+/// [`LocalAggregator`](crate::LocalAggregator).
+///
+/// Each entry is written as a single line holding the JSON of its [`EntrySnapshot`], which keeps
+/// the typed values, units and dimensions of the entry so that the aggregator can merge and
+/// re-format it. Output it to a [`SocketWriter::unix`] connected to the socket of the aggregator:
+///
+/// ```no_run
+/// use metrique_local_aggregator::ForwardFormat;
+/// use metrique_writer::{BoxEntry, FormatExt, sink::BackgroundQueue, socket::SocketWriter};
+///
+/// let stream = ForwardFormat::new().output_to(SocketWriter::unix("/run/metrique.sock"));
+/// let (queue, _join) = BackgroundQueue::<BoxEntry>::new(stream);
+/// ```
+///
+/// [`SocketWriter::unix`]: metrique_writer::socket::SocketWriter::unix
+#[derive(Debug, Default, Clone)]
+#[non_exhaustive]
+pub struct ForwardFormat {}
+
+impl ForwardFormat {
+    /// Create a new [`ForwardFormat`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Format for ForwardFormat {
+    fn format(
+        &mut self,
+        entry: &impl Entry,
+        output: &mut impl io::Write,
+    ) -> Result<(), IoStreamError> {
+        let snapshot = EntrySnapshot::new(entry)?;
+        // serialize into a buffer first, so that a failed write never sends half a line
+        let mut line = serde_json::to_vec(&snapshot.to_json()).map_err(io::Error::from)?;
+        line.push(b'\n');
+        output.write_all(&line)?;
+        Ok(())
+    }
+}
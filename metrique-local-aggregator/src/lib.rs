@@ -0,0 +1,14 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+#![deny(missing_docs)]
+#![doc = include_str!("../README.md")]
+#![cfg_attr(docsrs, feature(doc_cfg))]
+
+#[cfg(unix)]
+mod aggregator;
+mod format;
+
+#[cfg(unix)]
+pub use aggregator::{DEFAULT_FLUSH_INTERVAL, LocalAggregator};
+pub use format::ForwardFormat;
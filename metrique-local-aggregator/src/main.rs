@@ -0,0 +1,100 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! The `metrique-local-aggregator` binary, see the README of the crate for its options.
+
+#[cfg(unix)]
+fn main() {
+    use std::{fs::OpenOptions, io, process, time::Duration};
+
+    use metrique_local_aggregator::LocalAggregator;
+    use metrique_writer::FormatExt;
+    use metrique_writer_format_emf::Emf;
+
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("metrique-local-aggregator: {message}\n\n{USAGE}");
+            process::exit(2);
+        }
+    };
+    let fail = |message: String| -> ! {
+        eprintln!("metrique-local-aggregator: {message}");
+        process::exit(1);
+    };
+
+    let mut aggregator = LocalAggregator::bind(&options.socket)
+        .unwrap_or_else(|err| fail(format!("can't listen on {}: {err}", options.socket)))
+        .flush_interval(Duration::from_millis(options.flush_interval_ms));
+    if let Some(key) = options.dedup_key {
+        aggregator = aggregator.deduplicate_on(key, Duration::from_secs(options.dedup_window_secs));
+    }
+
+    let format = Emf::builder(options.namespace, vec![vec![]]).build();
+    match options.output {
+        Some(path) => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .unwrap_or_else(|err| fail(format!("can't open {path}: {err}")));
+            aggregator.run(format.output_to(file))
+        }
+        None => aggregator.run(format.output_to(io::stdout())),
+    }
+}
+
+#[cfg(not(unix))]
+fn main() {
+    eprintln!("metrique-local-aggregator: only Unix domain sockets are supported");
+    std::process::exit(1);
+}
+
+#[cfg(unix)]
+const USAGE: &str = "\
+usage: metrique-local-aggregator --socket <PATH> --namespace <NAMESPACE> [--output <PATH>]
+       [--flush-interval-ms <MILLIS>] [--dedup-key <PROPERTY>] [--dedup-window-secs <SECS>]";
+
+#[cfg(unix)]
+struct Options {
+    socket: String,
+    namespace: String,
+    output: Option<String>,
+    flush_interval_ms: u64,
+    dedup_key: Option<String>,
+    dedup_window_secs: u64,
+}
+
+#[cfg(unix)]
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let (mut socket, mut namespace, mut output, mut dedup_key) = (None, None, None, None);
+        let mut flush_interval_ms = 1000;
+        let mut dedup_window_secs = 300;
+        while let Some(option) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("{option} needs a value"));
+            let number = |value: String| {
+                value
+                    .parse()
+                    .map_err(|_| format!("{option} must be a number, not `{value}`"))
+            };
+            match option.as_str() {
+                "--socket" => socket = Some(value()?),
+                "--namespace" => namespace = Some(value()?),
+                "--output" => output = Some(value()?),
+                "--flush-interval-ms" => flush_interval_ms = number(value()?)?,
+                "--dedup-key" => dedup_key = Some(value()?),
+                "--dedup-window-secs" => dedup_window_secs = number(value()?)?,
+                _ => return Err(format!("unknown option `{option}`")),
+            }
+        }
+        Ok(Self {
+            socket: socket.ok_or("--socket is required")?,
+            namespace: namespace.ok_or("--namespace is required")?,
+            output,
+            flush_interval_ms,
+            dedup_key,
+            dedup_window_secs,
+        })
+    }
+}
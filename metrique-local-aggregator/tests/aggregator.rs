@@ -0,0 +1,231 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(unix)]
+
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use metrique::unit_of_work::metrics;
+use metrique::writer::entry::EntrySnapshot;
+use metrique::writer::format::Format;
+use metrique::writer::socket::SocketWriter;
+use metrique::writer::{Entry, EntryIoStream, FormatExt, IoStreamError, Observation};
+use metrique::{CloseValue, RootEntry};
+use metrique_local_aggregator::{ForwardFormat, LocalAggregator};
+
+#[metrics(rename_all = "PascalCase")]
+struct WorkItem {
+    operation: &'static str,
+    message_id: Option<String>,
+    processed: u64,
+}
+
+/// Collects the entries written by the aggregator
+#[derive(Clone, Default)]
+struct Collected(Arc<Mutex<Vec<EntrySnapshot>>>);
+
+impl Collected {
+    fn total(&self, metric: &str) -> u64 {
+        let entries = self.0.lock().unwrap();
+        let observations = entries.iter().flat_map(|e| &e.metrics[metric].observations);
+        observations
+            .map(|o| match *o {
+                Observation::Unsigned(v) => v,
+                other => panic!("unexpected observation {other:?}"),
+            })
+            .sum()
+    }
+
+    fn wait_for_total(&self, metric: &str, total: u64) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while self.total(metric) < total {
+            assert!(Instant::now() < deadline, "timed out waiting for entries");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
+
+impl EntryIoStream for Collected {
+    fn next(&mut self, entry: &impl Entry) -> Result<(), IoStreamError> {
+        self.0.lock().unwrap().push(EntrySnapshot::new(entry)?);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn spawn(aggregator: LocalAggregator) -> Collected {
+    let collected = Collected::default();
+    let output = collected.clone();
+    thread::spawn(move || aggregator.run(output));
+    collected
+}
+
+fn forward(socket: &Path, items: impl IntoIterator<Item = WorkItem>) {
+    let mut stream = ForwardFormat::new().output_to(SocketWriter::unix(socket));
+    for item in items {
+        stream.next(&RootEntry::new(item.close())).unwrap();
+    }
+    stream.flush().unwrap();
+}
+
+#[test]
+fn entries_of_many_workers_are_merged() {
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("metrique.sock");
+    let collected = spawn(
+        LocalAggregator::bind(&socket)
+            .unwrap()
+            .flush_interval(Duration::from_millis(200)),
+    );
+
+    let workers: Vec<_> = (0..4)
+        .map(|_| {
+            let socket = socket.clone();
+            thread::spawn(move || {
+                forward(
+                    &socket,
+                    (0..5).map(|_| WorkItem {
+                        operation: "Poll",
+                        message_id: None,
+                        processed: 1,
+                    }),
+                )
+            })
+        })
+        .collect();
+    for worker in workers {
+        worker.join().unwrap();
+    }
+
+    collected.wait_for_total("Processed", 20);
+    let entries = collected.0.lock().unwrap();
+    assert!(
+        entries.len() < 20,
+        "{} entries were not merged",
+        entries.len()
+    );
+    assert!(entries.iter().all(|e| e.properties["Operation"] == "Poll"));
+}
+
+#[test]
+fn merged_entries_hold_at_most_100_distinct_values() {
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("metrique.sock");
+    let collected = spawn(
+        LocalAggregator::bind(&socket)
+            .unwrap()
+            .flush_interval(Duration::from_millis(500)),
+    );
+
+    let items = (1..=250).map(|processed| WorkItem {
+        operation: "Poll",
+        message_id: None,
+        processed,
+    });
+    forward(&socket, items);
+
+    collected.wait_for_total("Processed", (1..=250).sum());
+    let entries = collected.0.lock().unwrap();
+    assert!(entries.len() >= 3, "{} entries", entries.len());
+    for entry in entries.iter() {
+        let observations = &entry.metrics["Processed"].observations;
+        assert!(observations.len() <= 100, "{} values", observations.len());
+    }
+}
+
+#[test]
+fn long_lines_are_skipped() {
+    use std::io::Write;
+    use std::os::unix::net::UnixStream;
+
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("metrique.sock");
+    let collected = spawn(
+        LocalAggregator::bind(&socket)
+            .unwrap()
+            .flush_interval(Duration::from_millis(20)),
+    );
+
+    let mut entry = Vec::new();
+    let item = WorkItem {
+        operation: "Poll",
+        message_id: None,
+        processed: 3,
+    };
+    ForwardFormat::new()
+        .format(&RootEntry::new(item.close()), &mut entry)
+        .unwrap();
+    let mut connection = UnixStream::connect(&socket).unwrap();
+    let long_line = vec![b'x'; 3 * 1024 * 1024];
+    connection.write_all(&long_line).unwrap();
+    connection.write_all(b"\n").unwrap();
+    connection.write_all(&entry).unwrap();
+    connection.flush().unwrap();
+
+    collected.wait_for_total("Processed", 3);
+}
+
+#[test]
+fn duplicates_across_workers_are_dropped() {
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("metrique.sock");
+    let collected = spawn(
+        LocalAggregator::bind(&socket)
+            .unwrap()
+            .flush_interval(Duration::from_millis(20))
+            .deduplicate_on("MessageId", Duration::from_secs(60)),
+    );
+
+    let item = |id: &str, processed| WorkItem {
+        operation: "Poll",
+        message_id: Some(id.to_owned()),
+        processed,
+    };
+    forward(&socket, [item("a", 1), item("b", 2)]);
+    // a redelivery of `a`, processed by another worker
+    forward(&socket, [item("a", 100), item("c", 4)]);
+
+    collected.wait_for_total("Processed", 7);
+    thread::sleep(Duration::from_millis(100));
+    assert_eq!(collected.total("Processed"), 7);
+}
+
+#[test]
+fn stale_socket_is_replaced() {
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("metrique.sock");
+    drop(LocalAggregator::bind(&socket).unwrap());
+    assert!(socket.exists());
+    let collected = spawn(
+        LocalAggregator::bind(&socket)
+            .unwrap()
+            .flush_interval(Duration::from_millis(20)),
+    );
+
+    forward(
+        &socket,
+        [WorkItem {
+            operation: "Poll",
+            message_id: None,
+            processed: 3,
+        }],
+    );
+    collected.wait_for_total("Processed", 3);
+}
+
+#[test]
+fn other_files_are_not_replaced() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("metrics.log");
+    std::fs::write(&path, "keep me").unwrap();
+    let err = LocalAggregator::bind(&path).err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::AddrInUse);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "keep me");
+}
@@ -0,0 +1,110 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Compile-time detection of metrics that flatten themselves, like a tree node that flattens
+//! `Option<Box<Self>>`. Their entry would contain itself, which rustc reports as an opaque query
+//! cycle or trait overflow, far from the field that causes it.
+//!
+//! Only what the macro can see is checked: fields whose type names the metric itself (or `Self`).
+//! Cycles through other types (`A` flattens `B`, which flattens `A`) are left to rustc.
+
+use syn::{GenericArgument, Ident, PathArguments, Result, Type};
+
+use crate::MetricsFieldKind;
+
+/// Check that no flattened field of the metric `name` contains the metric itself.
+pub(crate) fn check_flatten_cycles<'a>(
+    name: &Ident,
+    fields: impl IntoIterator<Item = (&'a Type, &'a MetricsFieldKind)>,
+) -> Result<()> {
+    let mut errors: Option<syn::Error> = None;
+    for (ty, kind) in fields {
+        let flattened = match kind {
+            // `with` closes the field into the entry of the module, not into this metric
+            MetricsFieldKind::Flatten { with, .. } => with.is_none(),
+            MetricsFieldKind::FlattenEntry(_) => true,
+            _ => false,
+        };
+        if !flattened || !mentions(ty, name) {
+            continue;
+        }
+        let error = syn::Error::new_spanned(
+            ty,
+            format!(
+                "`{name}` can't flatten itself: its entry would contain itself, even behind a \
+                 `Box` or an `Option`. Flattened metrics have a fixed set of fields, so emit the \
+                 nested metrics as separate entries instead"
+            ),
+        );
+        match &mut errors {
+            Some(errors) => errors.combine(error),
+            None => errors = Some(error),
+        }
+    }
+    errors.map_or(Ok(()), Err)
+}
+
+/// Returns true if `ty` is, or has a type argument that is, `Self` or the metric `name`.
+///
+/// Only single-segment paths are matched, since a longer path (`other::Node`) can name another
+/// type, while a single segment can only name this metric within its own module.
+fn mentions(ty: &Type, name: &Ident) -> bool {
+    match ty {
+        Type::Path(path) => {
+            if path.qself.is_none()
+                && path.path.segments.len() == 1
+                && (path.path.segments[0].ident == *name || path.path.segments[0].ident == "Self")
+            {
+                return true;
+            }
+            path.path
+                .segments
+                .iter()
+                .any(|segment| match &segment.arguments {
+                    PathArguments::AngleBracketed(args) => args.args.iter().any(|arg| match arg {
+                        GenericArgument::Type(ty) => mentions(ty, name),
+                        _ => false,
+                    }),
+                    _ => false,
+                })
+        }
+        Type::Reference(reference) => mentions(&reference.elem, name),
+        Type::Array(array) => mentions(&array.elem, name),
+        Type::Slice(slice) => mentions(&slice.elem, name),
+        Type::Tuple(tuple) => tuple.elems.iter().any(|ty| mentions(ty, name)),
+        Type::Group(group) => mentions(&group.elem, name),
+        Type::Paren(paren) => mentions(&paren.elem, name),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use syn::{Ident, Type, parse_quote};
+
+    use super::mentions;
+
+    #[test]
+    fn mentions_the_metric_in_type_arguments() {
+        let name: Ident = parse_quote!(Node);
+        let yes: [Type; 5] = [
+            parse_quote!(Node),
+            parse_quote!(Self),
+            parse_quote!(Option<Box<Node>>),
+            parse_quote!(std::boxed::Box<Node<T>>),
+            parse_quote!(Vec<(u64, Box<Self>)>),
+        ];
+        let no: [Type; 4] = [
+            parse_quote!(Leaf),
+            parse_quote!(other::Node),
+            parse_quote!(Option<Box<NodeStats>>),
+            parse_quote!(<T as Trait>::Node),
+        ];
+        for ty in yes {
+            assert!(mentions(&ty, &name), "{}", quote::quote!(#ty));
+        }
+        for ty in no {
+            assert!(!mentions(&ty, &name), "{}", quote::quote!(#ty));
+        }
+    }
+}
@@ -11,7 +11,7 @@ pub(crate) struct DimensionSet {
     pub(crate) dimensions: Vec<Expr>,
 }
 
-impl DimensionSet {
+impl FromMeta for DimensionSet {
     fn from_expr(expr: &syn::Expr) -> darling::Result<Self> {
         match expr {
             syn::Expr::Array(array) => Ok(DimensionSet {
@@ -38,7 +38,7 @@ impl FromMeta for DimensionSets {
                 let sets = array
                     .elems
                     .iter()
-                    .flat_map(|expr| accum.handle(DimensionSet::from_expr(expr)))
+                    .flat_map(|expr| accum.handle(<DimensionSet as FromMeta>::from_expr(expr)))
                     .collect();
                 accum.finish_with(DimensionSets { sets })
             }
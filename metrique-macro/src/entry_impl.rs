@@ -35,6 +35,32 @@ pub(crate) fn mixed_site_self() -> Ident {
     format_ident!("__metrique_self", span = proc_macro2::Span::mixed_site())
 }
 
+/// Write the `schema_version` property of `#[metrics(version = N)]`, if set
+fn generate_version_write(root_attrs: &RootAttributes) -> Option<Ts2> {
+    let version = root_attrs.version.as_ref()?;
+    let span = version.span();
+    let writer_ident = mixed_site_writer();
+    let (extra, name) = make_inflect(&make_ns(root_attrs.rename_all, span), span, |style| {
+        style.apply(&root_attrs.rename_all.apply("schema_version"))
+    });
+    let value = version.to_string();
+    Some(quote_spanned! {span=>
+        #extra
+        ::metrique::writer::EntryWriter::value(#writer_ident, ::metrique::concat::const_str_value::<#name>(), #value);
+    })
+}
+
+/// Implement `InflectableEntry::schema_version` for `#[metrics(version = N)]`, if set
+fn generate_schema_version_fn(root_attrs: &RootAttributes) -> Option<Ts2> {
+    let version = root_attrs.version.as_ref()?;
+    let value = **version;
+    Some(quote_spanned! {version.span()=>
+        fn schema_version() -> ::std::option::Option<u64> {
+            ::std::option::Option::Some(#value)
+        }
+    })
+}
+
 fn make_ns(ns: NameStyle, span: proc_macro2::Span) -> Ts2 {
     match ns {
         NameStyle::PascalCase => quote_spanned! {span=> NS::PascalCase },
@@ -44,6 +70,22 @@ fn make_ns(ns: NameStyle, span: proc_macro2::Span) -> Ts2 {
     }
 }
 
+/// The name style of a flattened field. A field-level `rename_all` replaces the container's
+/// style and pins it, so that `rename_all` on the nested entry is ignored.
+pub(crate) fn make_flatten_ns(
+    container_style: NameStyle,
+    field_style: Option<NameStyle>,
+    span: proc_macro2::Span,
+) -> Ts2 {
+    match field_style {
+        None => make_ns(container_style, span),
+        Some(style) => {
+            let ns = make_ns(style, span);
+            quote_spanned! {span=> <#ns as ::metrique::NameStyle>::Fixed }
+        }
+    }
+}
+
 /// Generate a ConstStr struct with the given identifier and value.
 /// Used to create compile-time constant strings for metric names and prefixes.
 fn const_str(ident: &syn::Ident, value: &str) -> Ts2 {
@@ -209,7 +251,51 @@ fn generate_field_writes(
                     ::metrique::writer::Entry::write(#field_access, #writer_ident);
                 }
             }
-            MetricsFieldKind::Flatten { span, prefix } => {
+            MetricsFieldKind::Flatten {
+                span,
+                prefix,
+                group,
+                rename_all,
+                ..
+            } => {
+                let parent_ns = make_flatten_ns(root_attrs.rename_all, *rename_all, field_span);
+                let (extra, ns) = match prefix {
+                    None => (quote!(), parent_ns.clone()),
+                    Some(prefix) => prefix.append_to(&parent_ns, field_span),
+                };
+                let field_access = field_access(&field.ident);
+                let write = quote_spanned! {*span=>
+                    ::metrique::InflectableEntry::<#ns>::write(#field_access, #writer_ident);
+                };
+                match group {
+                    None => quote_spanned! {*span=>
+                        #extra
+                        #write
+                    },
+                    Some(group) => {
+                        let (group_extra, group_name) =
+                            make_inflect(&parent_ns, field_span, |style| style.apply(group));
+                        let empty = quote!(::metrique::concat::EmptyConstStr);
+                        quote_spanned! {*span=>
+                            #extra
+                            ::metrique::writer::EntryWriter::begin_group(
+                                #writer_ident,
+                                {
+                                    #group_extra
+                                    ::metrique::concat::const_str_value::<#group_name>()
+                                },
+                                // the full prefix of the fields of the group
+                                ::metrique::concat::const_str_value::<
+                                    <#ns as ::metrique::NameStyle>::Inflect<#empty, #empty, #empty, #empty>,
+                                >(),
+                            );
+                            #write
+                            ::metrique::writer::EntryWriter::end_group(#writer_ident);
+                        }
+                    }
+                }
+            }
+            MetricsFieldKind::Error { span, prefix, .. } => {
                 let (extra, ns) = match prefix {
                     None => (quote!(), ns),
                     Some(prefix) => prefix.append_to(&ns, field_span),
@@ -220,13 +306,15 @@ fn generate_field_writes(
                     ::metrique::InflectableEntry::<#ns>::write(#field_access, #writer_ident);
                 }
             }
-            MetricsFieldKind::Ignore(_) => {
+            MetricsFieldKind::Ignore(_) | MetricsFieldKind::Field { no_emit: true, .. } => {
                 continue;
             }
-            MetricsFieldKind::Field { format, .. } => {
+            MetricsFieldKind::Field { .. } => {
                 let (extra, name) = make_inflect_metric_name(root_attrs, field);
                 let field_access = field_access(&field.ident);
-                let value = crate::value_impl::format_value(format, field_span, field_access);
+                let format = crate::value_impl::field_format(field, root_attrs);
+                let value = crate::value_impl::format_value(&format, field_span, field_access);
+                let value = crate::value_impl::truncate_value(field, value);
                 quote_spanned! {field_span=>
                     ::metrique::writer::EntryWriter::value(#writer_ident,
                         {
@@ -278,6 +366,100 @@ fn make_inflect_metric_name(root_attrs: &RootAttributes, field: &MetricsField) -
     )
 }
 
+/// The doc comment of a field, with the leading space of each line removed, or `None` if the
+/// field has no (non-empty) doc comment.
+fn doc_comment(field: &MetricsField) -> Option<String> {
+    let lines: Vec<String> = field
+        .external_attrs
+        .iter()
+        .filter_map(|attr| match &attr.meta {
+            syn::Meta::NameValue(syn::MetaNameValue {
+                path,
+                value:
+                    syn::Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Str(doc),
+                        ..
+                    }),
+                ..
+            }) if path.is_ident("doc") => Some(doc.value()),
+            _ => None,
+        })
+        .map(|line| {
+            line.strip_prefix(' ')
+                .unwrap_or(&line)
+                .trim_end()
+                .to_owned()
+        })
+        .collect();
+    let doc = lines.join("\n").trim().to_owned();
+    (!doc.is_empty()).then_some(doc)
+}
+
+/// Generate the statements of `InflectableEntry::describe`, which call `describe_ident` with the
+/// descriptor of every documented field (if `doc_as_description` is set) and delegate to
+/// flattened fields.
+fn generate_field_descriptions(
+    fields: &[MetricsField],
+    root_attrs: &RootAttributes,
+    describe_ident: &Ident,
+) -> Vec<Ts2> {
+    let mut descriptions = Vec::new();
+    for field in fields {
+        let field_span = field.span;
+        let description = match &field.attrs.kind {
+            MetricsFieldKind::Field { .. } if root_attrs.doc_as_description => {
+                let Some(doc) = doc_comment(field) else {
+                    continue;
+                };
+                let (extra, name) = make_inflect_metric_name(root_attrs, field);
+                quote_spanned! {field_span=>
+                    #describe_ident(::metrique::FieldDescriptor::new(
+                        {
+                            #extra
+                            ::metrique::concat::const_str_value::<#name>()
+                        },
+                        #doc,
+                    ));
+                }
+            }
+            MetricsFieldKind::Flatten {
+                span,
+                prefix,
+                rename_all,
+                ..
+            } => {
+                let ns = make_flatten_ns(root_attrs.rename_all, *rename_all, field_span);
+                let (extra, ns) = match prefix {
+                    None => (quote!(), ns),
+                    Some(prefix) => prefix.append_to(&ns, field_span),
+                };
+                let ty = &field.ty;
+                let closed_ty = if let Some(with) = field.with() {
+                    quote_spanned! {*span=> #with::Closed }
+                } else if field.attrs.close {
+                    quote_spanned! {*span=> <#ty as ::metrique::CloseValue>::Closed }
+                } else {
+                    quote_spanned! {*span=> #ty }
+                };
+                quote_spanned! {*span=>
+                    {
+                        #extra
+                        <#closed_ty as ::metrique::InflectableEntry<#ns>>::describe(#describe_ident);
+                    }
+                }
+            }
+            _ => continue,
+        };
+        let cfg_attrs: Vec<_> = field.cfg_attrs().collect();
+        if cfg_attrs.is_empty() {
+            descriptions.push(description);
+        } else {
+            descriptions.push(quote! { #(#cfg_attrs)* { #description } });
+        }
+    }
+    descriptions
+}
+
 /// Collect sample group iterators from a field, returning (field_ident, iterator_expr) for fields that have sample groups.
 /// The `field_access` closure determines how to access the field (e.g., `#field_ident` or `&__metrique_self.#field_ident`).
 ///
@@ -293,8 +475,10 @@ fn collect_field_sample_group<'a>(
     let field_ident = &field.ident;
     let cfg_attrs: Vec<_> = field.cfg_attrs().collect();
     let inner = match &field.attrs.kind {
-        MetricsFieldKind::Flatten { span, .. } => {
-            let ns = make_ns(root_attrs.rename_all, field.span);
+        MetricsFieldKind::Flatten {
+            span, rename_all, ..
+        } => {
+            let ns = make_flatten_ns(root_attrs.rename_all, *rename_all, field.span);
             let access = field_access(field_ident);
             quote_spanned!(*span=>
                 ::metrique::InflectableEntry::<#ns>::sample_group(#access)
@@ -326,6 +510,7 @@ fn collect_field_sample_group<'a>(
             sample_group: None, ..
         }
         | MetricsFieldKind::Ignore(_)
+        | MetricsFieldKind::Error { .. }
         | MetricsFieldKind::Timestamp(_) => return None,
     };
     if cfg_attrs.is_empty() {
@@ -43,11 +43,14 @@ pub(crate) fn generate_enum_entry_impl(
     let mixed = proc_macro2::Span::mixed_site();
     let writer_ident = mixed_site_writer();
     let self_ident = mixed_site_self();
+    let version_write = generate_version_write(root_attrs);
+    let schema_version_fn = generate_schema_version_fn(root_attrs);
 
     // Macro hygiene pattern: see `mixed_site_writer` / `mixed_site_self` docs in `entry_impl.rs`.
     let write_fn = quote_spanned! {mixed=>
         fn write<'__metrique_write>(&'__metrique_write self, #writer_ident: &mut impl ::metrique::writer::EntryWriter<'__metrique_write>) {
             let #self_ident = self;
+            #version_write
             #[allow(deprecated)]
             match #self_ident {
                 #(#write_arms)*
@@ -72,6 +75,7 @@ pub(crate) fn generate_enum_entry_impl(
             impl #impl_generics ::metrique::InflectableEntry<NS> for #entry_name #ty_generics #where_clause {
                 #write_fn
                 #sample_group_fn
+                #schema_version_fn
             }
         };
     }
@@ -162,8 +166,13 @@ fn generate_tuple_writes(
         .map(|(idx, td)| {
             let binding = quote::format_ident!("v{}", idx);
             let write = match &td.kind {
-                MetricsFieldKind::Flatten { span, prefix } => {
-                    let base_ns = make_ns(root_attrs.rename_all, *span);
+                MetricsFieldKind::Flatten {
+                    span,
+                    prefix,
+                    rename_all,
+                    ..
+                } => {
+                    let base_ns = make_flatten_ns(root_attrs.rename_all, *rename_all, *span);
                     let (extra, ns) = match prefix {
                         None => (quote!(), base_ns),
                         Some(prefix) => prefix.append_to(&base_ns, variant_span),
@@ -179,9 +188,11 @@ fn generate_tuple_writes(
                     )
                 }
                 MetricsFieldKind::Ignore(_) => quote!(),
-                MetricsFieldKind::Timestamp(_) | MetricsFieldKind::Field { .. } => {
+                MetricsFieldKind::Timestamp(_)
+                | MetricsFieldKind::Error { .. }
+                | MetricsFieldKind::Field { .. } => {
                     unreachable!(
-                        "timestamp/plain fields are rejected earlier in tuple variant parsing"
+                        "timestamp/error/plain fields are rejected earlier in tuple variant parsing"
                     )
                 }
             };
@@ -303,8 +314,10 @@ fn collect_tuple_sample_group(
     binding: &Ident,
 ) -> Option<Ts2> {
     match kind {
-        MetricsFieldKind::Flatten { span, .. } => {
-            let ns = make_ns(root_attrs.rename_all, *span);
+        MetricsFieldKind::Flatten {
+            span, rename_all, ..
+        } => {
+            let ns = make_flatten_ns(root_attrs.rename_all, *rename_all, *span);
             Some(quote_spanned!(*span=>
                 ::metrique::InflectableEntry::<#ns>::sample_group(#binding)
             ))
@@ -313,8 +326,12 @@ fn collect_tuple_sample_group(
             ::metrique::writer::Entry::sample_group(#binding)
         )),
         MetricsFieldKind::Ignore(_) => None,
-        MetricsFieldKind::Timestamp(_) | MetricsFieldKind::Field { .. } => {
-            unreachable!("timestamp/plain fields are rejected earlier in tuple variant parsing")
+        MetricsFieldKind::Timestamp(_)
+        | MetricsFieldKind::Error { .. }
+        | MetricsFieldKind::Field { .. } => {
+            unreachable!(
+                "timestamp/error/plain fields are rejected earlier in tuple variant parsing"
+            )
         }
     }
 }
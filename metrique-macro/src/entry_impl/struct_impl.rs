@@ -36,6 +36,20 @@ pub(crate) fn generate_struct_entry_impl(
         }
     };
 
+    let describe_ident = format_ident!("__metrique_describe", span = mixed);
+    let descriptions = generate_field_descriptions(fields, root_attrs, &describe_ident);
+    let describe_fn = if descriptions.is_empty() {
+        quote!()
+    } else {
+        quote_spanned! {mixed=>
+            fn describe(#describe_ident: &mut dyn ::std::ops::FnMut(::metrique::FieldDescriptor)) {
+                #(#descriptions)*
+            }
+        }
+    };
+
+    let schema_version_fn = generate_schema_version_fn(root_attrs);
+
     // we generate one entry impl for each namestyle. This will then allow the parent to
     // transitively set the namestyle
     quote! {
@@ -44,13 +58,15 @@ pub(crate) fn generate_struct_entry_impl(
             impl #impl_generics ::metrique::InflectableEntry<NS> for #entry_name #ty_generics #where_clause {
                 #write_fn
                 #sample_group_fn
+                #describe_fn
+                #schema_version_fn
             }
         };
     }
 }
 
 fn generate_write_statements(fields: &[MetricsField], root_attrs: &RootAttributes) -> Vec<Ts2> {
-    let mut writes = Vec::new();
+    let mut writes: Vec<_> = generate_version_write(root_attrs).into_iter().collect();
     let writer_ident = mixed_site_writer();
     let self_ident = mixed_site_self();
 
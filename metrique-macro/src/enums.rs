@@ -123,12 +123,41 @@ fn parse_variant_data(fields: &syn::Fields) -> Result<Option<VariantData>> {
                 .map(|field| {
                     let raw_attrs = RawMetricsFieldAttrs::from_field(field)?;
                     let attrs = raw_attrs.validate()?;
+                    if let Some(span) = attrs.verbose {
+                        return Err(syn::Error::new(
+                            span,
+                            "`verbose` is not supported on tuple variant fields",
+                        ));
+                    }
+                    if let Some(default) = &attrs.default {
+                        return Err(syn::Error::new(
+                            default.span(),
+                            "`default` is not supported on enum variant fields",
+                        ));
+                    }
+                    if let Some(span) = attrs.handle {
+                        return Err(syn::Error::new(
+                            span,
+                            "`handle` is not supported on enum variant fields",
+                        ));
+                    }
+                    if let MetricsFieldKind::Flatten {
+                        with: Some(with), ..
+                    } = &attrs.kind
+                    {
+                        return Err(syn::Error::new_spanned(
+                            with,
+                            "`with` is not supported on tuple variant fields",
+                        ));
+                    }
 
                     match &attrs.kind {
                         MetricsFieldKind::Flatten { .. }
                         | MetricsFieldKind::FlattenEntry(_)
                         | MetricsFieldKind::Ignore(_) => {}
-                        MetricsFieldKind::Timestamp(_) | MetricsFieldKind::Field { .. } => {
+                        MetricsFieldKind::Timestamp(_)
+                        | MetricsFieldKind::Error { .. }
+                        | MetricsFieldKind::Field { .. } => {
                             return Err(syn::Error::new_spanned(
                                 field,
                                 "tuple variant fields must use #[metrics(flatten)], #[metrics(flatten_entry)], or #[metrics(ignore)]",
@@ -148,6 +177,21 @@ fn parse_variant_data(fields: &syn::Fields) -> Result<Option<VariantData>> {
         }
         syn::Fields::Named(fields) => {
             let parsed_fields = parse_metric_fields(&fields.named)?;
+            if let Some(default) = parsed_fields
+                .iter()
+                .find_map(|field| field.attrs.default.as_ref())
+            {
+                return Err(syn::Error::new(
+                    default.span(),
+                    "`default` is not supported on enum variant fields",
+                ));
+            }
+            if let Some(span) = parsed_fields.iter().find_map(|field| field.attrs.handle) {
+                return Err(syn::Error::new(
+                    span,
+                    "`handle` is not supported on enum variant fields",
+                ));
+            }
             Ok(Some(VariantData::Struct(parsed_fields)))
         }
     }
@@ -237,6 +281,24 @@ pub(crate) fn generate_metrics_for_enum(
     };
     let guard_name = quote::format_ident!("{}Guard", enum_name);
     let handle_name = quote::format_ident!("{}Handle", enum_name);
+    for variant in variants {
+        match &variant.data {
+            Some(VariantData::Struct(fields)) => {
+                crate::limits::check_limits(&root_attrs, variant.ident.span(), fields)?;
+                crate::cycles::check_flatten_cycles(
+                    enum_name,
+                    fields.iter().map(|field| (&field.ty, &field.attrs.kind)),
+                )?;
+            }
+            Some(VariantData::Tuple(fields)) => {
+                crate::cycles::check_flatten_cycles(
+                    enum_name,
+                    fields.iter().map(|field| (&field.ty, &field.kind)),
+                )?;
+            }
+            None => {}
+        }
+    }
 
     // For value(string) enums, auto-derive Debug, Clone, Copy on the generated Value enum only.
     // The base enum keeps whatever the user provides — no stripping, no injection.
@@ -257,15 +319,29 @@ pub(crate) fn generate_metrics_for_enum(
     );
     let warnings = root_attrs.warnings();
 
-    let entry_enum = generate_entry_enum(&entry_name, &input.generics, variants, &entry_attrs)?;
+    let entry_enum = generate_entry_enum(
+        &entry_name,
+        &root_attrs.entry_vis(),
+        &input.generics,
+        variants,
+        &entry_attrs,
+    )?;
 
     let inner_impl = match root_attrs.mode {
-        MetricMode::ValueString => value_impl::generate_value_impl_for_enum(
-            &root_attrs,
-            &entry_name,
-            &input.generics,
-            variants,
-        )?,
+        MetricMode::ValueString => {
+            let value_impl = value_impl::generate_value_impl_for_enum(
+                &root_attrs,
+                &entry_name,
+                &input.generics,
+                variants,
+            )?;
+            let display_and_from_str = value_impl::generate_display_and_from_str_for_enum(
+                &root_attrs,
+                enum_name,
+                variants,
+            );
+            quote!(#value_impl #display_and_from_str)
+        }
         _ => crate::entry_impl::generate_enum_entry_impl(
             &entry_name,
             &input.generics,
@@ -307,7 +383,7 @@ pub(crate) fn generate_metrics_for_enum(
     let root_entry_specifics = match root_attrs.mode {
         MetricMode::RootEntry => {
             let on_drop_wrapper = generate_on_drop_wrapper(
-                vis,
+                root_attrs.guard_vis(vis),
                 &guard_name,
                 enum_name,
                 &entry_name,
@@ -356,6 +432,7 @@ pub(crate) fn generate_base_enum(
 
 fn generate_entry_enum(
     name: &Ident,
+    vis: &Visibility,
     generics: &Generics,
     variants: &[MetricsVariant],
     attrs: &[Attribute],
@@ -368,7 +445,7 @@ fn generate_entry_enum(
     Ok(quote! {
         #[doc(hidden)]
         #(#attrs)*
-        pub enum #name #generics {
+        #vis enum #name #generics {
             #data
         }
     })
@@ -415,13 +492,7 @@ fn generate_close_value_impl_for_enum(
             Some(VariantData::Struct(fields)) => {
                 // Struct variant: Enum::Variant { fields } => Entry::Variant { closed_fields }
                 let field_names: Vec<_> = fields.iter().map(|f| &f.ident).collect();
-                let closed_fields: Vec<_> = fields
-                    .iter()
-                    .map(|f| {
-                        let ident: &Ts2 = &f.ident;
-                        f.close_field_expr(quote::quote_spanned! {f.span=> #ident })
-                    })
-                    .collect();
+                let closed_fields: Vec<_> = fields.iter().map(|f| f.close_binding_expr()).collect();
                 quote::quote_spanned!(variant.ident.span()=>
                     #enum_name::#variant_ident { #(#field_names),* } => #entry_name::#variant_ident { #(#closed_fields),* }
                 )
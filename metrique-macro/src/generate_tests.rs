@@ -0,0 +1,110 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Support for `#[metrics(generate_tests(...))]`, which emits a `#[cfg(test)]` module checking
+//! the final metric names, units and dimensions of a root metric against an expected table.
+
+use darling::FromMeta;
+use proc_macro2::TokenStream as Ts2;
+use quote::{format_ident, quote};
+use syn::{Generics, Ident};
+
+use crate::emf::{DimensionSet, DimensionSets};
+
+#[derive(Debug, FromMeta)]
+pub(crate) struct GenerateTests {
+    /// Function returning the sample value to check. Defaults to `Default::default`.
+    #[darling(default)]
+    sample: Option<syn::Path>,
+
+    #[darling(multiple, rename = "metric")]
+    metrics: Vec<ExpectedMetric>,
+
+    #[darling(multiple, rename = "property")]
+    properties: Vec<String>,
+
+    #[darling(default)]
+    dimension_sets: Option<DimensionSets>,
+}
+
+#[derive(Debug, FromMeta)]
+struct ExpectedMetric {
+    name: String,
+    #[darling(default)]
+    unit: Option<syn::Path>,
+    #[darling(default)]
+    dimensions: Option<DimensionSet>,
+}
+
+pub(crate) fn generate_tests(
+    tests: &GenerateTests,
+    base_ident: &Ident,
+    generics: &Generics,
+) -> syn::Result<Ts2> {
+    if let Some(param) = generics.type_params().next() {
+        return Err(syn::Error::new_spanned(
+            param,
+            "generate_tests does not support generic metrics, write the test by hand using `EntrySchema`",
+        ));
+    }
+    if let Some(param) = generics.const_params().next() {
+        return Err(syn::Error::new_spanned(
+            param,
+            "generate_tests does not support generic metrics, write the test by hand using `EntrySchema`",
+        ));
+    }
+
+    let mod_name = format_ident!("__metrique_generated_tests_{}", base_ident);
+    let sample = match &tests.sample {
+        Some(sample) => quote! { #sample() },
+        None => quote! { ::core::default::Default::default() },
+    };
+    let metrics = tests.metrics.iter().map(|metric| {
+        let name = &metric.name;
+        let unit = match &metric.unit {
+            Some(unit) => quote! { <#unit as ::metrique::writer::unit::UnitTag>::UNIT },
+            None => quote! { ::metrique::writer::Unit::None },
+        };
+        let dimensions = metric
+            .dimensions
+            .as_ref()
+            .map(|d| d.dimensions.as_slice())
+            .unwrap_or_default();
+        quote! { .metric(#name, #unit, &[#(#dimensions),*]) }
+    });
+    let properties = tests.properties.iter().map(|p| quote! { .property(#p) });
+    let dimension_sets = tests
+        .dimension_sets
+        .iter()
+        .flat_map(|sets| &sets.sets)
+        .map(|set| {
+            let dimensions = &set.dimensions;
+            quote! { .dimension_set(&[#(#dimensions),*]) }
+        });
+    let message = format!(
+        "the metric names, units or dimensions of `{base_ident}` changed, which can break dashboards and alarms. \
+        If this is intended, update the `generate_tests` table"
+    );
+
+    Ok(quote! {
+        #[cfg(test)]
+        #[allow(non_snake_case)]
+        mod #mod_name {
+            #[allow(unused_imports)]
+            use super::*;
+
+            #[test]
+            fn metric_names_units_and_dimensions() {
+                let sample: #base_ident = #sample;
+                let actual = ::metrique::test_util::EntrySchema::of(::metrique::RootEntry::new(
+                    ::metrique::CloseValue::close(sample),
+                ));
+                let expected = ::metrique::test_util::EntrySchema::new()
+                    #(#metrics)*
+                    #(#properties)*
+                    #(#dimension_sets)*;
+                ::core::assert_eq!(actual, expected, #message);
+            }
+        }
+    })
+}
@@ -6,21 +6,26 @@
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
 mod aggregate;
+mod cycles;
 mod derive_utils;
 mod emf;
 mod entry_impl;
 mod enums;
+mod generate_tests;
 mod inflect;
+mod limits;
 mod structs;
 mod value_impl;
 
 use darling::{
     FromField, FromMeta,
     ast::NestedMeta,
-    util::{Flag, SpannedValue},
+    util::{Flag, Override, SpannedValue},
 };
 use emf::DimensionSets;
+use generate_tests::GenerateTests;
 use inflect::NameStyle;
+use limits::Limits;
 use proc_macro::TokenStream;
 use proc_macro2::{Span, TokenStream as Ts2};
 use quote::{ToTokens, quote, quote_spanned};
@@ -47,25 +52,50 @@ use crate::inflect::{name_contains_dot, name_contains_uninflectables, name_ends_
 /// | - `sample_group` | Flag | Include tag in sample group | `#[metrics(tag(name = "op", sample_group))]` |
 /// | `subfield` | Flag | When set, this metric can only be used when nested within other metrics, and can be consumed by reference (has both `impl CloseValue for &MyStruct` and `impl CloseValue for MyStruct`). It cannot be added to a sink directly. | `#[metrics(subfield)]` |
 /// | `subfield_owned` | Flag | When set, this metric can only be used when nested within other metrics. It cannot be added to a sink directly. | `#[metrics(subfield_owned)]` |
-/// | `value` | Flag | Used for *structs*. Makes the struct a value newtype | `#[metrics(value)]` |
+/// | `value` | Flag | Used for *structs*. Makes the struct a value newtype. Other fields can be kept with `#[metrics(ignore)]` | `#[metrics(value)]` |
+/// | `value(field = N)` | Integer | On tuple structs, selects field `N` as the value and ignores the other fields | `#[metrics(value(field = 1))]` |
 /// | `value(string)` | Flag | Used for *enums*. Transforms the enum into a string value. Automatically derives `Debug`, `Clone`, and `Copy` on the generated Value enum. The base enum is left untouched — derive what you need on it yourself. | `#[metrics(value(string))]` |
+/// | `value(string, display, from_str)` | Flags | Also implements `Display` and/or `FromStr` on the enum, using the metric names | `#[metrics(value(string, display))]` |
 /// | `sample_group` | Flag | On `#[metrics(value)]`, forwards `sample_group` to the inner field | `#[metrics(value, sample_group)]` |
+/// | `doc_as_description` | Flag | On structs, uses the doc comments of fields as their descriptions, which exporters that support metadata can surface. See [Field Descriptions](#field-descriptions) | `#[metrics(doc_as_description)]` |
+/// | `generate_tests` | Nested | On root metrics, emits a `#[cfg(test)]` module checking the final metric names, units and dimensions against an expected table. See [Generated tests](#generated-tests) | `#[metrics(generate_tests(metric(name = "Latency", unit = Millisecond)))]` |
+/// | `entry_vis` | String | Visibility of the generated `*Entry`, `*Guard` and `*Handle` types, so they don't become part of a library's public API. Defaults to `pub` for the entry and to the type's own visibility for the guard and handle. Since the entry is the type's `CloseValue::Closed`, it must be at least as visible as the type itself | `#[metrics(entry_vis = "pub(crate)")]` |
+/// | `version` | Integer | On root metrics, writes `version` as the `schema_version` property of every entry (inflected by `rename_all`, without `prefix`), so consumers can tell schemas apart across deployments. Also returned by [`schema_version`](https://docs.rs/metrique/latest/metrique/fn.schema_version.html) | `#[metrics(version = 2)]` |
+/// | `bool_as` | String | `"property"` emits the fields declared as `bool` (or `Option<bool>`, `Box<bool>`, ...) as `"true"`/`"false"` string properties instead of `0`/`1` metrics. Fields with a `format` or their own `bool_as` are left alone. Type aliases of `bool` are not recognized, set `bool_as` on those fields instead | `#[metrics(bool_as = "property")]` |
+/// | `none_as` | String | How the fields declared as `Option<_>` are emitted when they are `None`: not at all (`"omit"`, the default), as a `0` metric in the unit of the field (`"zero"`) or as a `"null"` string property (`"null"`). `"zero"` needs metric values, set `none_as = "omit"` on `Option<String>` fields. Fields with a `format` or their own `none_as` are left alone | `#[metrics(none_as = "zero")]` |
+/// | `omit_zeros` | Flag | Skips the fields whose value is a metric with only zero observations, such as error counters that were never incremented, to keep wide entries small. Fields with a `format`, and fields that `bool_as` or `none_as` emit as properties or zeros, are always written, and flattened fields only skip zeros if their own type sets `omit_zeros` | `#[metrics(omit_zeros)]` |
+/// | `limits` | Nested | On root metrics, overrides the limits that the macro checks at compile time: the number of fields declared on the metric (`max_fields`, 100 by default, the maximum number of metrics in a CloudWatch EMF record) and the length of their final names (`max_name_len`, 255 by default). Fields of flattened metrics and fields known to be properties, like string fields, are not counted. Raise them for metrics that are not written as EMF | `#[metrics(limits(max_fields = 200, max_name_len = 1024))]` |
+/// | `debug_expand` | Flag | Writes the pretty-printed expansion of this type to `$OUT_DIR/metrique-expand/<Type>.rs` (or, without `OUT_DIR`, into a compiler warning) to inspect the generated code. Remove it when done | `#[metrics(debug_expand)]` |
 ///
 /// # Field Attributes
 ///
 /// | Attribute | Type | Description | Example |
 /// |-----------|------|-------------|---------|
 /// | `name` | String | Overrides the field name in metrics | `#[metrics(name = "CustomName")]` |
-/// | `unit` | Path | Specifies the unit for the metric value | `#[metrics(unit = Millisecond)]` |
+/// | `unit` | Path | Specifies the unit for the metric value. `Custom("...")` attaches a user-defined unit label to a unitless value, which is dropped by formats that don't support arbitrary units, like EMF | `#[metrics(unit = Millisecond)]`, `#[metrics(unit = Custom("Widgets"))]` |
 /// | `format` | Path | Specifies the formatter (`ValueFormatter`) for the metric value | `#[metrics(format=EpochSeconds)]` |
-/// | `timestamp` | Flag | Marks a field as the canonical timestamp | `#[metrics(timestamp)]` |
+/// | `bool_as` | String | Emits a `bool` field as a `"true"`/`"false"` string property (`"property"`) or as a `0`/`1` metric (`"metric"`, the default, to override a top-level `bool_as`). Can't be combined with `format` or `unit` | `#[metrics(bool_as = "property")]` |
+/// | `none_as` | String | Emits an `Option` field that is `None` as nothing (`"omit"`, the default, to override a top-level `none_as`), as a `0` metric in the unit of the field (`"zero"`) or as a `"null"` string property (`"null"`). Can't be combined with `format`, `bool_as` or `verbose` | `#[metrics(none_as = "zero")]` |
+/// | `timestamp` | Flag | Marks a field as the canonical timestamp. At most one field can be the canonical timestamp | `#[metrics(timestamp)]` |
+/// | `timestamp(property)` | Flag | Emits a secondary timestamp (e.g. a start time) as a property formatted like the canonical timestamp, in epoch milliseconds unless `format` is set | `#[metrics(timestamp(property), format = EpochSeconds)]` |
 /// | `sample_group` | Flag | Marks a field as a sample group - it will still be emitted as a value | `#[metrics(sample_group)]` |
+/// | `no_emit` | Flag | With `sample_group`, the field is only part of the sample group, and is not emitted as a value | `#[metrics(sample_group, no_emit)]` |
+/// | `max_len` | Integer | Truncates the strings the field writes, like error messages, to at most this many characters, ending with `...` when truncated. Can't be combined with `unit` or `clamp`. See [`metrique::writer::value::Truncated`](https://docs.rs/metrique/latest/metrique/writer/value/struct.Truncated.html) | `#[metrics(max_len = 256)]` |
+/// | `truncation_marker` | String | With `max_len`, ends truncated strings with this marker instead of `...` (`""` for none). The marker counts towards `max_len` | `#[metrics(max_len = 256, truncation_marker = "[truncated]")]` |
+/// | `clamp` | Nested | Clamps the closed value to `min` and/or `max` (expressions of the closed type). With `out_of_range = "drop"`, out-of-range values are not emitted instead. See [`metrique::clamp`](https://docs.rs/metrique/latest/metrique/clamp/index.html) | `#[metrics(clamp(max = 60_000))]` |
 /// | `prefix` | String | Adds a prefix to flattened entries. Prefix will get inflected to the right case style | `#[metrics(flatten, prefix="prefix-")]` |
 /// | `exact_prefix` | String | Adds a prefix to flattened entries without inflection | `#[metrics(flatten, exact_prefix="API_")]` |
-/// | `flatten` | Flag | Flattens nested `CloseEntry` metric structs | `#[metrics(flatten)]` |
-/// | `flatten_entry` | Flag | Flattens nested `CloseValue<Closed: Entry>` metric structs, with no prefix or inflection | `#[metrics(flatten_entry)]` |
+/// | `group` | String | Groups the fields of a flattened entry. Formats that support nesting, like JSON, write them in a nested object named after the group (inflected), others flatten them with the group as their prefix, like `prefix = "group_"`. Can't be combined with `prefix` | `#[metrics(flatten, group = "cache")]` |
+/// | `flatten` | Flag | Flattens nested `CloseEntry` metric structs, which can be boxed (`Box<Subfield>`) to keep large subfields out of the parent | `#[metrics(flatten)]` |
+/// | `rename_all` | String | With `flatten`, forces a case style on all metrics in the flattened field, overriding any `rename_all` inside it | `#[metrics(flatten, rename_all = "PascalCase")]` |
+/// | `with` | Path | With `flatten`, closes a foreign type through a module providing `close(&T) -> Closed`, where `Closed` is an entry type, instead of through `CloseValue` | `#[metrics(flatten, with = peer_addr)]` |
+/// | `flatten_entry` | Flag | Flattens nested `CloseValue<Closed: Entry>` metric structs, with no prefix or inflection. This is also how operation-specific metrics selected at runtime, `Option<Box<dyn OperationDetail>>`, are flattened, see [`metrique::operation_detail`](https://docs.rs/metrique/latest/metrique/operation_detail/index.html) | `#[metrics(flatten_entry)]` |
 /// | `no_close` | Flag | Use the entry directly instead of closing it | `#[metrics(no_close)]` |
 /// | `ignore` | Flag | Excludes the field from metrics | `#[metrics(ignore)]` |
+/// | `default` | Flag or Expr | Generates a `Default` impl for the struct, where this field is initialized with the expression (or `Default::default()`) and the fields without `default` with `Default::default()`. Don't also derive `Default`. See [Default values](#default-values) | `#[metrics(default = Timer::start_now())]` |
+/// | `verbose` | Flag | Only emits the field when verbose metrics are enabled at close time, through the `METRIQUE_VERBOSE` environment variable or [`metrique::verbose::set_enabled`](https://docs.rs/metrique/latest/metrique/verbose/fn.set_enabled.html). Works on regular, `flatten` and `flatten_entry` fields | `#[metrics(verbose)]` |
+/// | `handle` | Flag | Adds a [`FieldHandle`](https://docs.rs/metrique/latest/metrique/field_handle/struct.FieldHandle.html) for the field to the generated `MyMetricsHandles` struct, returned by `handles()` on the guard and on its handle, so that async tasks can update the field (typically a `Counter` or an atomic) without holding the whole entry. Only on the named fields of non-generic root metrics | `#[metrics(handle)]` |
+/// | `error` | Flag or Nested | On an `Option<E>` or `Result<T, E>` field (`E: Display`), records a `Failure` count (0/1) and an `ErrorType` property. Use `error(fault)` to record `Fault` instead, and `error(message)` or `error(message_max_len = N)` to also record a truncated `ErrorMessage`. Can be combined with `prefix`. See [`metrique::error`](https://docs.rs/metrique/latest/metrique/error/index.html) | `#[metrics(error(message))]` |
 ///
 /// # Variant Attributes
 ///
@@ -196,6 +226,77 @@ use crate::inflect::{name_contains_dot, name_contains_uninflectables, name_ends_
 /// assert_eq!(entry.metrics["waterfowl_NDucks"], 0);
 /// ```
 ///
+/// A flattened field normally takes the case style of its container, unless the nested struct
+/// sets its own `rename_all`. To force a case style on everything inside a flattened field,
+/// for example while migrating legacy metric names, use `rename_all` on the field itself:
+///
+/// ```rust
+/// # use metrique::unit_of_work::metrics;
+/// #[metrics(subfield, rename_all = "snake_case")]
+/// struct Legacy {
+///     request_count: u32,
+/// }
+///
+/// #[metrics(rename_all = "kebab-case")]
+/// struct Base {
+///     #[metrics(flatten, rename_all = "PascalCase")]
+///     legacy: Legacy,
+/// }
+///
+/// let entry = metrique::test_util::test_metric(Base { legacy: Legacy { request_count: 1 } });
+/// assert_eq!(entry.metrics["RequestCount"], 1);
+/// ```
+///
+/// Types from other crates (status codes, SDK errors, addresses...) can't implement `CloseValue`
+/// outside of their crate. To flatten them anyway, point `with` at a module (like serde's `remote`
+/// and `with`) that provides a `Closed` entry type and a `close` function taking the field by
+/// reference:
+///
+/// ```rust
+/// # use metrique::unit_of_work::metrics;
+/// use std::net::SocketAddr;
+///
+/// mod peer_addr {
+///     use metrique::CloseValue;
+///     use metrique::unit_of_work::metrics;
+///     use std::net::SocketAddr;
+///
+///     #[metrics(subfield_owned)]
+///     pub struct Peer {
+///         ip: String,
+///         port: u16,
+///     }
+///
+///     pub type Closed = <Peer as CloseValue>::Closed;
+///
+///     pub fn close(addr: &SocketAddr) -> Closed {
+///         Peer { ip: addr.ip().to_string(), port: addr.port() }.close()
+///     }
+/// }
+///
+/// #[metrics(rename_all = "PascalCase")]
+/// struct RequestMetrics {
+///     #[metrics(flatten, with = peer_addr, prefix = "peer_")]
+///     peer: SocketAddr,
+/// }
+///
+/// let entry = metrique::test_util::test_metric(RequestMetrics {
+///     peer: "127.0.0.1:8080".parse().unwrap(),
+/// });
+/// assert_eq!(entry.values["PeerIp"], "127.0.0.1");
+/// assert_eq!(entry.metrics["PeerPort"], 8080);
+/// ```
+///
+/// A metric can't flatten itself, even behind a `Box` or an `Option`, since its entry would
+/// contain itself. The macro rejects fields that flatten `Self` or the metric's own name; cycles
+/// through other types (`A` flattens `B`, which flattens `A`) are reported by rustc as a query
+/// cycle or an overflow. Emit recursive structures as separate entries instead.
+///
+/// Flattened fields can be nested about 60 levels deep, the depth up to which rustc resolves the
+/// nested entry types with the default `recursion_limit` of 128. Deeper nesting fails with
+/// "overflow evaluating the requirement" or "reached the recursion limit"; raise the limit with
+/// `#![recursion_limit = "256"]` in the crate that defines the root metric if you really need it.
+///
 /// # Example
 ///
 /// ```rust
@@ -280,15 +381,38 @@ use crate::inflect::{name_contains_dot, name_contains_uninflectables, name_ends_
 /// }
 /// ```
 ///
+/// Add `display` and/or `from_str` to also implement [`Display`](std::fmt::Display) and
+/// [`FromStr`](std::str::FromStr) on the enum with the same names, so that logs, APIs and metrics agree
+/// on the spelling. Parsing an unknown name returns a [`metrique::UnknownVariantError`].
+///
+/// [`metrique::UnknownVariantError`]: https://docs.rs/metrique/latest/metrique/struct.UnknownVariantError.html
+///
+/// ```rust
+/// # use metrique::unit_of_work::metrics;
+/// #[metrics(value(string, display, from_str), rename_all = "PascalCase")]
+/// #[derive(Debug, PartialEq)]
+/// enum Operation {
+///     ReadData,
+///     #[metrics(name = "Write")]
+///     WriteData,
+/// }
+///
+/// assert_eq!(Operation::ReadData.to_string(), "ReadData");
+/// assert_eq!("Write".parse::<Operation>().unwrap(), Operation::WriteData);
+/// assert!("write_data".parse::<Operation>().is_err());
+/// ```
+///
 /// ## Entry Enums
 ///
 /// Entry enums allow different metric fields per variant. Contained fields respect container and
 /// field attributes as used by structs.
 ///
 /// Variants can be tuple variants (which must use flatten/flatten_entry/ignore attributes, since
-/// their fields are unnamed). Or, they can use struct variants with named fields and the full
-/// range of field attributes available. (Unit variants are also supported but don't do much unless
-/// used with a `tag` field; see the following `Tag field` section.)
+/// their fields are unnamed, and emit the union of the metrics of all of their fields). Or, they
+/// can use struct variants with named fields and the full range of field attributes available.
+/// Unit variants are also supported: they emit nothing, or only the `tag` field (see the following
+/// `Tag field` section), which is useful for the "no extra data" states of a state machine that
+/// is flattened into other metrics.
 ///
 /// ```rust
 /// # use metrique::unit_of_work::metrics;
@@ -300,9 +424,14 @@ use crate::inflect::{name_contains_dot, name_contains_uninflectables, name_ends_
 ///     bytes_read: usize,
 /// }
 ///
+/// #[metrics(subfield)]
+/// struct CacheMetrics {
+///     cache_hit: bool,
+/// }
+///
 /// #[metrics(rename_all = "PascalCase")]
 /// enum Operation {
-///     Read(#[metrics(flatten)] ReadMetrics),
+///     Read(#[metrics(flatten)] ReadMetrics, #[metrics(flatten)] CacheMetrics),
 ///     Write {
 ///         #[metrics(unit = Millisecond)]
 ///         latency: Duration,
@@ -311,9 +440,10 @@ use crate::inflect::{name_contains_dot, name_contains_uninflectables, name_ends_
 /// }
 ///
 /// let entry = metrique::test_util::test_metric(
-///     Operation::Read(ReadMetrics { bytes_read: 1024 })
+///     Operation::Read(ReadMetrics { bytes_read: 1024 }, CacheMetrics { cache_hit: true })
 /// );
 /// assert_eq!(entry.metrics["BytesRead"], 1024);
+/// assert_eq!(entry.metrics["CacheHit"], 1);
 ///
 /// let entry = metrique::test_util::test_metric(
 ///     Operation::Write { latency: Duration::from_millis(5), bytes_written: 2048 }
@@ -367,6 +497,120 @@ use crate::inflect::{name_contains_dot, name_contains_uninflectables, name_ends_
 /// // The tag field "Operation" with value "Read" is included in sample_group
 /// ```
 ///
+/// # Generated Tests
+///
+/// Renaming a metric, or changing its unit or dimensions, can silently break the dashboards and
+/// alarms that use it. `generate_tests` emits a `#[cfg(test)]` module asserting that the final
+/// metric names, units and dimensions of a root metric match an expected table, so that such
+/// changes fail the owning crate's tests:
+///
+/// ```rust
+/// # use metrique::unit_of_work::metrics;
+/// # use metrique::unit::Millisecond;
+/// # use std::time::Duration;
+/// #[metrics(
+///     rename_all = "PascalCase",
+///     emf::dimension_sets = [["Operation"]],
+///     generate_tests(
+///         metric(name = "Latency", unit = Millisecond),
+///         metric(name = "NumberOfDucks"),
+///         property = "Operation",
+///         dimension_sets = [["Operation"]],
+///     )
+/// )]
+/// #[derive(Default)]
+/// struct RequestMetrics {
+///     operation: &'static str,
+///     latency: Duration,
+///     number_of_ducks: usize,
+/// }
+/// ```
+///
+/// The table supports:
+/// - `metric(name = "...", unit = Unit, dimensions = ["..."])`: a metric, its unit (defaults to no unit)
+///   and the names of its dimensions (defaults to none).
+/// - `property = "..."`: a string property.
+/// - `dimension_sets = [["..."]]`: the entry-level dimension sets.
+///
+/// The check is run against `Default::default()`, or against the value returned by the function
+/// passed as `sample = make_sample`. Fields that are not emitted by the sample (e.g. `None`) are
+/// not checked, so the sample should fill them in. The generated test uses [`EntrySchema`], which
+/// requires the `test-util` feature of `metrique` in your dev-dependencies.
+///
+/// [`EntrySchema`]: https://docs.rs/metrique/latest/metrique/test_util/struct.EntrySchema.html
+///
+/// # Field Descriptions
+///
+/// Some exporters can attach a description to a metric, for example Prometheus `HELP` text or
+/// OpenTelemetry instrument descriptions. With `#[metrics(doc_as_description)]`, the doc comments
+/// of the fields become their descriptions, which can be listed with [`describe_fields`]:
+///
+/// ```rust
+/// # use metrique::unit_of_work::metrics;
+/// # use std::time::Duration;
+/// #[metrics(rename_all = "PascalCase", doc_as_description)]
+/// struct RequestMetrics {
+///     /// Time spent waiting for the backend
+///     backend_latency: Duration,
+///     #[metrics(flatten, prefix = "cache_")]
+///     cache: CacheMetrics,
+/// }
+///
+/// #[metrics(subfield, doc_as_description)]
+/// struct CacheMetrics {
+///     /// Number of cache lookups that found the item
+///     hits: usize,
+/// }
+///
+/// let fields = metrique::describe_fields::<RequestMetrics>();
+/// assert_eq!(fields[0].name, "BackendLatency");
+/// assert_eq!(fields[0].description, "Time spent waiting for the backend");
+/// assert_eq!(fields[1].name, "CacheHits");
+/// ```
+///
+/// Names are the final emitted names, including renames and prefixes. Fields without a doc comment
+/// are not described, and descriptions of flattened fields are included if the flattened struct
+/// also has `doc_as_description`.
+///
+/// [`describe_fields`]: https://docs.rs/metrique/latest/metrique/fn.describe_fields.html
+///
+/// # Default Values
+///
+/// If a field of a struct has `#[metrics(default)]` or `#[metrics(default = expr)]`, the macro
+/// generates a `Default` impl for the struct, so that structs with many counters can be created
+/// without listing every field. Fields with `default = expr` are initialized with `expr`, and the
+/// other fields with `Default::default()`:
+///
+/// ```rust
+/// # use metrique::unit_of_work::metrics;
+/// #[metrics(value(string))]
+/// enum Operation {
+///     Get,
+///     Put,
+/// }
+///
+/// #[metrics(rename_all = "PascalCase")]
+/// struct RequestMetrics {
+///     // `Operation` does not implement `Default`
+///     #[metrics(default = Operation::Get)]
+///     operation: Operation,
+///     #[metrics(default = 1)]
+///     attempts: u64,
+///     cache_hits: u64,
+///     cache_misses: u64,
+/// }
+///
+/// let entry = metrique::test_util::test_metric(RequestMetrics {
+///     cache_hits: 1,
+///     ..Default::default()
+/// });
+/// assert_eq!(entry.values["Operation"], "Get");
+/// assert_eq!(entry.metrics["Attempts"], 1);
+/// assert_eq!(entry.metrics["CacheMisses"], 0);
+/// ```
+///
+/// `default` is not supported on the fields of enum variants.
+///
 /// # Generated Types
 ///
 /// For a struct or entry enum named `MyMetrics`, the macro generates:
@@ -375,6 +619,7 @@ use crate::inflect::{name_contains_dot, name_contains_uninflectables, name_ends_
 ///   A type alias to ``AppendAndCloseOnDrop`.
 /// - `MyMetricsHandle`: A shareable handle for concurrent access to the metrics.
 ///   A type alias to ``AppendAndCloseOnDropHandle`.
+/// - `MyMetricsHandles`: Only if some fields have `#[metrics(handle)]`, a struct with a `FieldHandle` for each of them.
 ///
 /// Value enums do not have new types generated, only trait implementations (`From<&MyEnum> for &'static str`, `SampleGroup`, `Value`).
 #[proc_macro_attribute]
@@ -597,6 +842,9 @@ enum OwnershipKind {
 #[darling(from_word = Self::from_word)]
 struct ValueAttributes {
     string: Flag,
+    display: Flag,
+    from_str: Flag,
+    field: Option<SpannedValue<usize>>,
 }
 
 impl ValueAttributes {
@@ -710,6 +958,24 @@ struct RawRootAttributes {
     #[darling(rename = "sample_group")]
     sample_group: Flag,
     value: Option<ValueAttributes>,
+
+    generate_tests: Option<SpannedValue<GenerateTests>>,
+
+    doc_as_description: Flag,
+
+    entry_vis: Option<SpannedKv<String>>,
+
+    version: Option<SpannedValue<u64>>,
+
+    bool_as: Option<SpannedKv<BoolAs>>,
+
+    none_as: Option<SpannedKv<NoneAs>>,
+
+    omit_zeros: Flag,
+
+    limits: Option<SpannedValue<Limits>>,
+
+    debug_expand: Flag,
 }
 
 #[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
@@ -734,13 +1000,73 @@ struct RootAttributes {
 
     sample_group: bool,
 
+    generate_tests: Option<GenerateTests>,
+
+    /// `value(string, display)`: implement `Display` on the enum using the metric names
+    value_display: bool,
+
+    /// `value(string, from_str)`: implement `FromStr` on the enum using the metric names
+    value_from_str: bool,
+
+    /// `value(field = N)`: the index of the value field of a tuple struct, the other fields are
+    /// ignored
+    value_field: Option<SpannedValue<usize>>,
+
+    /// `doc_as_description`: describe fields with their doc comments
+    doc_as_description: bool,
+
+    /// `entry_vis`: visibility of the generated entry, guard and handle types
+    entry_vis: Option<Visibility>,
+
+    /// `version = N`: the schema version written as the `schema_version` property
+    version: Option<SpannedValue<u64>>,
+
+    /// `bool_as = ...`: how fields declared as `bool` are emitted, unless they set `bool_as`
+    /// themselves
+    bool_as: Option<BoolAs>,
+
+    /// `none_as = ...`: how fields declared as `Option` are emitted when `None`, unless they set
+    /// `none_as` themselves
+    none_as: Option<NoneAs>,
+
+    /// `omit_zeros`: don't write the metric fields whose observations are all zero
+    omit_zeros: bool,
+
+    /// `limits(...)`: overrides the EMF limits that the fields of a root metric are checked against
+    limits: Option<Limits>,
+
+    /// `debug_expand`: dump the expansion, reported at this span
+    debug_expand: Option<Span>,
+
     mode: MetricMode,
 }
 
 impl RawRootAttributes {
     fn validate(self) -> darling::Result<RootAttributes> {
         let mut out: Option<(MetricMode, &'static str)> = None;
+        let (mut value_display, mut value_from_str) = (false, false);
+        let mut value_field = None;
         if let Some(value_attrs) = self.value {
+            if let (Some(field), true) = (&value_attrs.field, value_attrs.string.is_present()) {
+                return Err(
+                    darling::Error::custom("`field` can't be used with `value(string)`")
+                        .with_span(&field.span()),
+                );
+            }
+            value_field = value_attrs.field;
+            for (flag, name) in [
+                (&value_attrs.display, "display"),
+                (&value_attrs.from_str, "from_str"),
+            ] {
+                if flag.is_present() && !value_attrs.string.is_present() {
+                    return Err(darling::Error::custom(format!(
+                        "`{name}` can only be used with `value(string)`"
+                    ))
+                    .with_span(&flag.span()));
+                }
+            }
+            value_display = value_attrs.display.is_present();
+            value_from_str = value_attrs.from_str.is_present();
             if value_attrs.string.is_present() {
                 out = set_exclusive(
                     |_| MetricMode::ValueString,
@@ -790,6 +1116,72 @@ impl RawRootAttributes {
                 .with_span(&tag.span())),
             })
             .transpose()?;
+        let generate_tests = self
+            .generate_tests
+            .map(|tests| match &mode {
+                MetricMode::RootEntry => Ok(tests.into_inner()),
+                _ => Err(darling::Error::custom(
+                    "generate_tests is only supported on root metrics, use it on the metric that contains this one",
+                )
+                .with_span(&tests.span())),
+            })
+            .transpose()?;
+        let limits = self
+            .limits
+            .map(|limits| match &mode {
+                MetricMode::RootEntry => Ok(limits.into_inner()),
+                _ => Err(darling::Error::custom(
+                    "limits is only supported on root metrics, use it on the metric that contains this one",
+                )
+                .with_span(&limits.span())),
+            })
+            .transpose()?;
+        if let (Some(version), false) = (&self.version, mode == MetricMode::RootEntry) {
+            return Err(darling::Error::custom(
+                "version is only supported on root metrics, use it on the metric that contains this one",
+            )
+            .with_span(&version.span()));
+        }
+        for (name, key_span) in [
+            ("bool_as", self.bool_as.as_ref().map(|kv| kv.key_span)),
+            ("none_as", self.none_as.as_ref().map(|kv| kv.key_span)),
+        ] {
+            if let (MetricMode::Value | MetricMode::ValueString, Some(key_span)) = (mode, key_span)
+            {
+                return Err(darling::Error::custom(format!(
+                    "value and value(string) do not support a top-level {name}, set it on the field instead"
+                ))
+                .with_span(&key_span));
+            }
+        }
+        if let (MetricMode::Value | MetricMode::ValueString, true) =
+            (mode, self.doc_as_description.is_present())
+        {
+            return Err(darling::Error::custom(
+                "value and value(string) do not support doc_as_description",
+            )
+            .with_span(&self.doc_as_description.span()));
+        }
+        if let (MetricMode::Value | MetricMode::ValueString, true) =
+            (mode, self.omit_zeros.is_present())
+        {
+            return Err(darling::Error::custom(
+                "value and value(string) do not support omit_zeros",
+            )
+            .with_span(&self.omit_zeros.span()));
+        }
+        let entry_vis = self
+            .entry_vis
+            .map(|vis| {
+                syn::parse_str::<Visibility>(&vis.value).map_err(|_| {
+                    darling::Error::custom(format!(
+                        "invalid visibility `{}`, expected e.g. `pub(crate)`",
+                        vis.value
+                    ))
+                    .with_span(&vis.value_span)
+                })
+            })
+            .transpose()?;
 
         Ok(RootAttributes {
             prefix: Prefix::from_inflectable_and_exact(
@@ -802,12 +1194,40 @@ impl RawRootAttributes {
             emf_dimensions: self.emf_dimensions,
             tag,
             sample_group,
+            generate_tests,
+            value_display,
+            value_from_str,
+            value_field,
+            doc_as_description: self.doc_as_description.is_present(),
+            entry_vis,
+            version: self.version,
+            bool_as: self.bool_as.map(|bool_as| bool_as.value),
+            none_as: self.none_as.map(|none_as| none_as.value),
+            omit_zeros: self.omit_zeros.is_present(),
+            limits,
+            debug_expand: self
+                .debug_expand
+                .is_present()
+                .then(|| self.debug_expand.span()),
             mode,
         })
     }
 }
 
 impl RootAttributes {
+    /// The visibility of the generated entry type, `pub` unless set with `entry_vis`
+    fn entry_vis(&self) -> Visibility {
+        self.entry_vis
+            .clone()
+            .unwrap_or_else(|| syn::parse_quote!(pub))
+    }
+
+    /// The visibility of the generated guard and handle types, the base type's visibility unless
+    /// set with `entry_vis`
+    fn guard_vis<'a>(&'a self, base_vis: &'a Visibility) -> &'a Visibility {
+        self.entry_vis.as_ref().unwrap_or(base_vis)
+    }
+
     fn configuration_field_names(&self) -> Vec<Ts2> {
         if let Some(_dims) = &self.emf_dimensions {
             vec![quote! { __config__ }]
@@ -858,18 +1278,39 @@ struct RawMetricsFieldAttrs {
 
     no_close: Flag,
 
-    timestamp: Flag,
+    #[darling(default)]
+    timestamp: Option<SpannedValue<Override<TimestampAttrs>>>,
 
     sample_group: Flag,
 
+    no_emit: Flag,
+
     ignore: Flag,
 
     #[darling(default)]
-    unit: Option<SpannedKv<syn::Path>>,
+    error: Option<SpannedValue<Override<ErrorAttrs>>>,
+
+    #[darling(default)]
+    unit: Option<SpannedKv<UnitAttr>>,
 
     #[darling(default)]
     format: Option<SpannedKv<syn::Path>>,
 
+    #[darling(default)]
+    bool_as: Option<SpannedKv<BoolAs>>,
+
+    #[darling(default)]
+    none_as: Option<SpannedKv<NoneAs>>,
+
+    #[darling(default)]
+    clamp: Option<SpannedValue<ClampAttrs>>,
+
+    #[darling(default)]
+    max_len: Option<SpannedKv<usize>>,
+
+    #[darling(default)]
+    truncation_marker: Option<SpannedKv<String>>,
+
     #[darling(default)]
     name: Option<SpannedKv<String>>,
 
@@ -878,13 +1319,135 @@ struct RawMetricsFieldAttrs {
 
     #[darling(default)]
     exact_prefix: Option<SpannedKv<String>>,
+
+    #[darling(default)]
+    group: Option<SpannedKv<String>>,
+
+    #[darling(default)]
+    rename_all: Option<SpannedKv<NameStyle>>,
+
+    #[darling(default)]
+    with: Option<SpannedKv<syn::Path>>,
+
+    verbose: Flag,
+
+    #[darling(default)]
+    default: Option<SpannedValue<Override<DefaultExpr>>>,
+
+    handle: Flag,
+}
+
+/// The expression of `#[metrics(default = expr)]`.
+///
+/// Unlike `syn::Expr`, string literals are kept as literals rather than parsed as expressions, so
+/// that `default = "value"` works for `&'static str` fields.
+#[derive(Debug, Clone)]
+struct DefaultExpr(syn::Expr);
+
+impl FromMeta for DefaultExpr {
+    fn from_expr(expr: &syn::Expr) -> darling::Result<Self> {
+        Ok(Self(expr.clone()))
+    }
+}
+
+/// Options for `#[metrics(error(...))]`
+#[derive(Debug, Default, FromMeta)]
+struct ErrorAttrs {
+    fault: Flag,
+    message: Flag,
+    #[darling(default)]
+    message_max_len: Option<usize>,
+}
+
+/// Options for `#[metrics(timestamp(...))]`
+#[derive(Debug, Default, FromMeta)]
+struct TimestampAttrs {
+    property: Flag,
+}
+
+/// Options for `#[metrics(clamp(...))]`
+#[derive(Debug, Clone, FromMeta)]
+struct ClampAttrs {
+    #[darling(default)]
+    min: Option<syn::Expr>,
+    #[darling(default)]
+    max: Option<syn::Expr>,
+    #[darling(default)]
+    out_of_range: OutOfRange,
+}
+
+/// How `#[metrics(bool_as = ...)]` emits `bool` fields
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromMeta)]
+enum BoolAs {
+    /// A `0`/`1` metric, the default
+    #[darling(rename = "metric")]
+    Metric,
+    /// A `"true"`/`"false"` string property
+    #[darling(rename = "property")]
+    Property,
+}
+
+/// How `#[metrics(none_as = ...)]` emits `Option` fields that are `None`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromMeta)]
+enum NoneAs {
+    /// Nothing is written, the default
+    #[darling(rename = "omit")]
+    Omit,
+    /// A `0` metric, in the unit of the field
+    #[darling(rename = "zero")]
+    Zero,
+    /// The string property `"null"`
+    #[darling(rename = "null")]
+    Null,
+}
+
+/// What to do with a value outside of the `clamp` bounds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, FromMeta)]
+enum OutOfRange {
+    /// Replace the value with the nearest bound
+    #[default]
+    #[darling(rename = "clamp")]
+    Clamp,
+    /// Do not emit the value
+    #[darling(rename = "drop")]
+    Drop,
+}
+
+/// The value of `#[metrics(unit = ...)]`
+#[derive(Debug, Clone)]
+enum UnitAttr {
+    /// A `UnitTag` type, like `Millisecond`
+    Tag(syn::Path),
+    /// A user-defined unit label, `Custom("Widgets")`
+    Custom(syn::LitStr),
+}
+
+impl FromMeta for UnitAttr {
+    fn from_expr(expr: &syn::Expr) -> darling::Result<Self> {
+        match expr {
+            syn::Expr::Call(call) if matches!(&*call.func, syn::Expr::Path(func) if func.path.is_ident("Custom")) => {
+                match call.args.iter().collect::<Vec<_>>().as_slice() {
+                    [
+                        syn::Expr::Lit(syn::ExprLit {
+                            lit: syn::Lit::Str(label),
+                            ..
+                        }),
+                    ] if !label.value().is_empty() => Ok(UnitAttr::Custom(label.clone())),
+                    _ => Err(darling::Error::custom(
+                        r#"expected a non-empty unit label like `Custom("Widgets")`"#,
+                    )
+                    .with_span(&call.span())),
+                }
+            }
+            _ => syn::Path::from_expr(expr).map(UnitAttr::Tag),
+        }
+    }
 }
 
 /// Wrapper type to allow recovering both the key and value span when parsing an attribute
 #[derive(Debug)]
 pub(crate) struct SpannedKv<T> {
     pub(crate) key_span: Span,
-    #[allow(dead_code)]
     pub(crate) value_span: Span,
     pub(crate) value: T,
 }
@@ -910,6 +1473,7 @@ pub(crate) fn parse_metric_fields(
 ) -> Result<Vec<MetricsField>> {
     let mut parsed_fields = vec![];
     let mut errors = darling::Error::accumulator();
+    let mut canonical_timestamp = None;
 
     for (i, field) in fields.iter().enumerate() {
         let i = syn::Index::from(i);
@@ -927,6 +1491,18 @@ pub(crate) fn parse_metric_fields(
             }
         };
 
+        if let MetricsFieldKind::Timestamp(span) = attrs.kind {
+            match canonical_timestamp {
+                Some(_) => errors.push(
+                    darling::Error::custom(
+                        "only one field can be `#[metrics(timestamp)]`. Use `#[metrics(timestamp(property))]` to emit other timestamps as properties",
+                    )
+                    .with_span(&span),
+                ),
+                None => canonical_timestamp = Some(span),
+            }
+        }
+
         parsed_fields.push(MetricsField {
             ident,
             name,
@@ -993,7 +1569,13 @@ impl RawMetricsFieldAttrs {
     fn validate(self) -> darling::Result<MetricsFieldAttrs> {
         let mut out: Option<(MetricsFieldKind, &'static str)> = None;
         out = set_exclusive(
-            |span| MetricsFieldKind::Flatten { span, prefix: None },
+            |span| MetricsFieldKind::Flatten {
+                span,
+                prefix: None,
+                group: None,
+                rename_all: None,
+                with: None,
+            },
             "flatten",
             out,
             &self.flatten,
@@ -1004,23 +1586,165 @@ impl RawMetricsFieldAttrs {
             out,
             &self.flatten_entry,
         )?;
-        out = set_exclusive(
-            MetricsFieldKind::Timestamp,
-            "timestamp",
-            out,
-            &self.timestamp,
-        )?;
+        // `timestamp(property)` is a regular field, only `timestamp` is exclusive
+        let mut timestamp_property = None;
+        if let Some(timestamp) = &self.timestamp {
+            let span = timestamp.span();
+            match &**timestamp {
+                Override::Explicit(TimestampAttrs { property }) if property.is_present() => {
+                    timestamp_property = Some(span);
+                }
+                _ => {
+                    if let Some((_, other)) = &out {
+                        return Err(cannot_combine_error(other, "timestamp", span));
+                    }
+                    out = Some((MetricsFieldKind::Timestamp(span), "timestamp"));
+                }
+            }
+        }
         out = set_exclusive(MetricsFieldKind::Ignore, "ignore", out, &self.ignore)?;
+        if let Some(error) = &self.error {
+            let span = error.span();
+            if let Some((_, other)) = &out {
+                return Err(cannot_combine_error(other, "error", span));
+            }
+            let attrs = match &**error {
+                Override::Inherit => &ErrorAttrs::default(),
+                Override::Explicit(attrs) => attrs,
+            };
+            let message_max_len = match (attrs.message.is_present(), attrs.message_max_len) {
+                (_, Some(max_len)) => Some(max_len),
+                (true, None) => Some(DEFAULT_ERROR_MESSAGE_MAX_LEN),
+                (false, None) => None,
+            };
+            out = Some((
+                MetricsFieldKind::Error {
+                    span,
+                    prefix: None,
+                    fault: attrs.fault.is_present(),
+                    message_max_len,
+                },
+                "error",
+            ));
+        }
 
         let name = self.name.map(validate_name).transpose()?;
         let name = get_field_option("name", &out, &name)?;
         let unit = get_field_option("unit", &out, &self.unit)?;
         let format = get_field_option("format", &out, &self.format)?;
+        let bool_as = get_field_option("bool_as", &out, &self.bool_as)?.copied();
+        if let Some(bool_as_kv) = &self.bool_as {
+            for (present, other) in [
+                (unit.is_some(), "unit"),
+                (format.is_some(), "format"),
+                (self.clamp.is_some(), "clamp"),
+                (self.timestamp.is_some(), "timestamp(property)"),
+            ] {
+                if present {
+                    return Err(cannot_combine_error(other, "bool_as", bool_as_kv.key_span));
+                }
+            }
+        }
+        let none_as = get_field_option("none_as", &out, &self.none_as)?.copied();
+        if let Some(none_as_kv) = &self.none_as {
+            for (present, other) in [
+                (format.is_some(), "format"),
+                (bool_as.is_some(), "bool_as"),
+                (self.timestamp.is_some(), "timestamp(property)"),
+                (self.verbose.is_present(), "verbose"),
+            ] {
+                if present {
+                    return Err(cannot_combine_error(other, "none_as", none_as_kv.key_span));
+                }
+            }
+        }
         let sample_group = get_field_flag("sample_group", &out, &self.sample_group)?;
+        let no_emit = if self.no_emit.is_present() {
+            if sample_group.is_none() {
+                return Err(darling::Error::custom(
+                    "`no_emit` can only be used with `sample_group`, use `ignore` to neither emit nor sample a field",
+                )
+                .with_span(&self.no_emit.span()));
+            }
+            for (present, other) in [
+                (unit.is_some(), "unit"),
+                (format.is_some(), "format"),
+                (bool_as.is_some(), "bool_as"),
+                (none_as.is_some(), "none_as"),
+                (self.clamp.is_some(), "clamp"),
+            ] {
+                if present {
+                    return Err(cannot_combine_error(other, "no_emit", self.no_emit.span()));
+                }
+            }
+            true
+        } else {
+            false
+        };
+        let clamp = match (&self.clamp, &out) {
+            (Some(clamp), Some((_, other))) => {
+                return Err(cannot_combine_error(other, "clamp", clamp.span()));
+            }
+            (Some(clamp), None) if clamp.min.is_none() && clamp.max.is_none() => {
+                return Err(darling::Error::custom(
+                    "`clamp` requires at least one of `min` or `max`",
+                )
+                .with_span(&clamp.span()));
+            }
+            (clamp, _) => clamp.as_ref().map(|clamp| Box::new((**clamp).clone())),
+        };
+        let timestamp_property = match timestamp_property {
+            Some(span) => {
+                if let Some((_, other)) = &out {
+                    return Err(cannot_combine_error(other, "timestamp(property)", span));
+                }
+                for (present, other) in [
+                    (unit.is_some(), "unit"),
+                    (sample_group.is_some(), "sample_group"),
+                    (clamp.is_some(), "clamp"),
+                ] {
+                    if present {
+                        return Err(cannot_combine_error(other, "timestamp(property)", span));
+                    }
+                }
+                Some(span)
+            }
+            None => None,
+        };
+        let truncate = match (&self.max_len, &self.truncation_marker) {
+            (None, Some(marker)) => {
+                return Err(
+                    darling::Error::custom("`truncation_marker` requires `max_len`")
+                        .with_span(&marker.key_span),
+                );
+            }
+            (Some(max_len), marker) => {
+                if let Some((_, other)) = &out {
+                    return Err(cannot_combine_error(other, "max_len", max_len.key_span));
+                }
+                for (present, other) in [
+                    (unit.is_some(), "unit"),
+                    (no_emit, "no_emit"),
+                    (clamp.is_some(), "clamp"),
+                ] {
+                    if present {
+                        return Err(cannot_combine_error(other, "max_len", max_len.key_span));
+                    }
+                }
+                Some(Truncate {
+                    max_len: max_len.value,
+                    marker: marker.as_ref().map(|marker| marker.value.clone()),
+                })
+            }
+            (None, None) => None,
+        };
         let close = !self.no_close.is_present();
         if let (false, Some((MetricsFieldKind::Ignore(span), _))) = (close, &out) {
             return Err(cannot_combine_error("no_close", "ignore", *span));
         }
+        if let (false, Some((MetricsFieldKind::Error { span, .. }, _))) = (close, &out) {
+            return Err(cannot_combine_error("no_close", "error", *span));
+        }
 
         let prefix = Prefix::from_inflectable_and_exact(
             &self.prefix,
@@ -1029,33 +1753,129 @@ impl RawMetricsFieldAttrs {
         )?;
         if let Some(prefix_) = prefix {
             match &mut out {
-                Some((MetricsFieldKind::Flatten { prefix, .. }, _)) => {
+                Some((MetricsFieldKind::Flatten { prefix, .. }, _))
+                | Some((MetricsFieldKind::Error { prefix, .. }, _)) => {
                     *prefix = Some(prefix_.into_inner());
                 }
+                _ => {
+                    return Err(darling::Error::custom(
+                        "prefix can only be used with `flatten` or `error`",
+                    )
+                    .with_span(&prefix_.span()));
+                }
+            }
+        }
+
+        if let Some(field_group) = self.group {
+            let field_group = validate_name(field_group)?;
+            match &mut out {
+                Some((MetricsFieldKind::Flatten { prefix, group, .. }, _)) => {
+                    if prefix.is_some() {
+                        return Err(cannot_combine_error(
+                            "prefix",
+                            "group",
+                            field_group.key_span,
+                        ));
+                    }
+                    // the fields of the group are flattened with the group as their prefix
+                    *prefix = Some(Prefix::Inflectable {
+                        prefix: format!("{}_", field_group.value),
+                    });
+                    *group = Some(field_group.value);
+                }
                 _ => {
                     return Err(
-                        darling::Error::custom("prefix can only be used with `flatten`")
-                            .with_span(&prefix_.span()),
+                        darling::Error::custom("`group` can only be used with `flatten`")
+                            .with_span(&field_group.key_span),
                     );
                 }
             }
         }
 
-        Ok(MetricsFieldAttrs {
-            close,
-            kind: match out {
-                Some((out, _)) => out,
-                None => MetricsFieldKind::Field {
+        if let Some(field_rename_all) = self.rename_all {
+            match (&mut out, field_rename_all.value) {
+                (_, NameStyle::Preserve) => {
+                    return Err(darling::Error::custom(
+                        "field-level `rename_all` must be one of \"PascalCase\", \"snake_case\" or \"kebab-case\"",
+                    )
+                    .with_span(&field_rename_all.value_span));
+                }
+                (Some((MetricsFieldKind::Flatten { rename_all, .. }, _)), style) => {
+                    *rename_all = Some(style);
+                }
+                _ => {
+                    return Err(darling::Error::custom(
+                        "field-level `rename_all` can only be used with `flatten`",
+                    )
+                    .with_span(&field_rename_all.key_span));
+                }
+            }
+        }
+
+        if let Some(field_with) = self.with {
+            match &mut out {
+                Some((MetricsFieldKind::Flatten { with, .. }, _)) => {
+                    if !close {
+                        return Err(cannot_combine_error(
+                            "no_close",
+                            "with",
+                            field_with.key_span,
+                        ));
+                    }
+                    *with = Some(field_with.value);
+                }
+                _ => {
+                    return Err(
+                        darling::Error::custom("`with` can only be used with `flatten`")
+                            .with_span(&field_with.key_span),
+                    );
+                }
+            }
+        }
+
+        let verbose = if self.verbose.is_present() {
+            let span = self.verbose.span();
+            match &out {
+                None
+                | Some((MetricsFieldKind::Flatten { .. }, _))
+                | Some((MetricsFieldKind::FlattenEntry(_), _)) => {}
+                Some((_, other)) => return Err(cannot_combine_error(other, "verbose", span)),
+            }
+            if sample_group.is_some() {
+                return Err(cannot_combine_error("sample_group", "verbose", span));
+            }
+            Some(span)
+        } else {
+            None
+        };
+
+        Ok(MetricsFieldAttrs {
+            close,
+            verbose,
+            default: self.default,
+            handle: self.handle.is_present().then(|| self.handle.span()),
+            kind: match out {
+                Some((out, _)) => out,
+                None => MetricsFieldKind::Field {
                     sample_group,
+                    no_emit,
                     name: name.cloned(),
                     unit: unit.cloned(),
                     format: format.cloned(),
+                    bool_as,
+                    none_as,
+                    clamp,
+                    timestamp_property,
+                    truncate,
                 },
             },
         })
     }
 }
 
+/// Must match `metrique::error::DEFAULT_MESSAGE_MAX_LEN`
+const DEFAULT_ERROR_MESSAGE_MAX_LEN: usize = 256;
+
 fn validate_name(name: SpannedKv<String>) -> darling::Result<SpannedKv<String>> {
     match validate_name_inner(&name.value) {
         Ok(_) => Ok(name),
@@ -1077,6 +1897,13 @@ fn validate_name_inner(name: &str) -> std::result::Result<(), &'static str> {
 #[derive(Debug, Clone)]
 struct MetricsFieldAttrs {
     close: bool,
+    /// Set by `#[metrics(verbose)]`: the field is only emitted when verbose metrics are enabled
+    verbose: Option<Span>,
+    /// Set by `#[metrics(default)]` or `#[metrics(default = expr)]`: the value of the field in
+    /// the generated `Default` impl
+    default: Option<SpannedValue<Override<DefaultExpr>>>,
+    /// Set by `#[metrics(handle)]`: the field gets a `FieldHandle` in the generated `*Handles`
+    handle: Option<Span>,
     kind: MetricsFieldKind,
 }
 
@@ -1119,21 +1946,49 @@ impl MetricsField {
     }
 
     fn entry_field(&self, named: bool) -> Option<Ts2> {
-        if let MetricsFieldKind::Ignore(_span) = self.attrs.kind {
-            return None;
+        if let MetricsFieldKind::Ignore(span) = self.attrs.kind {
+            // keep the positions of the fields of tuple structs
+            return (!named).then(|| quote_spanned! {span=> #[doc(hidden)] () });
         }
         let MetricsField {
             ident, ty, span, ..
         } = self;
-        let mut base_type = if self.attrs.close {
+        let mut base_type = if let MetricsFieldKind::Error { .. } = self.attrs.kind {
+            quote_spanned! { *span=> ::metrique::error::ErrorEntry }
+        } else if let Some(with) = self.with() {
+            quote_spanned! { with.span()=> #with::Closed }
+        } else if self.attrs.close {
             quote_spanned! { *span=> <#ty as metrique::CloseValue>::Closed }
         } else {
             quote_spanned! { *span=>#ty }
         };
-        if let Some(expr) = self.unit() {
-            base_type = quote_spanned! { expr.span()=>
-                <#base_type as ::metrique::unit::AttachUnit>::Output<#expr>
+        if let Some(ClampAttrs {
+            out_of_range: OutOfRange::Drop,
+            ..
+        }) = self.clamp()
+        {
+            base_type = quote_spanned! { *span=> ::std::option::Option<#base_type> };
+        }
+        if let Some(span) = self.timestamp_property() {
+            base_type = quote_spanned! { span=>
+                <#base_type as ::metrique::timers::IntoTimestampValue>::Output
+            };
+        }
+        match self.unit() {
+            Some(UnitAttr::Tag(expr)) => {
+                base_type = quote_spanned! { expr.span()=>
+                    <#base_type as ::metrique::unit::AttachUnit>::Output<#expr>
+                }
+            }
+            Some(UnitAttr::Custom(label)) => {
+                base_type = quote_spanned! { label.span()=>
+                    ::metrique::unit::WithCustomUnit<#base_type>
+                }
             }
+            None => {}
+        }
+        if let Some(span) = self.attrs.verbose {
+            base_type = quote_spanned! { span=> ::std::option::Option<#base_type> };
         }
         let inner = if named {
             quote! { #ident: #base_type }
@@ -1149,13 +2004,36 @@ impl MetricsField {
         })
     }
 
-    fn unit(&self) -> Option<&syn::Path> {
+    fn unit(&self) -> Option<&UnitAttr> {
         match &self.attrs.kind {
             MetricsFieldKind::Field { unit, .. } => unit.as_ref(),
             _ => None,
         }
     }
 
+    pub(crate) fn with(&self) -> Option<&syn::Path> {
+        match &self.attrs.kind {
+            MetricsFieldKind::Flatten { with, .. } => with.as_ref(),
+            _ => None,
+        }
+    }
+
+    fn clamp(&self) -> Option<&ClampAttrs> {
+        match &self.attrs.kind {
+            MetricsFieldKind::Field { clamp, .. } => clamp.as_deref(),
+            _ => None,
+        }
+    }
+
+    fn timestamp_property(&self) -> Option<Span> {
+        match &self.attrs.kind {
+            MetricsFieldKind::Field {
+                timestamp_property, ..
+            } => *timestamp_property,
+            _ => None,
+        }
+    }
+
     pub(crate) fn close_value(&self, ownership_kind: OwnershipKind) -> Ts2 {
         let ident = &self.ident;
         let span = self.span;
@@ -1169,15 +2047,71 @@ impl MetricsField {
     pub(crate) fn close_field_expr(&self, field_expr: Ts2) -> Ts2 {
         let ident = &self.ident;
         let span = self.span;
-        let base = if self.attrs.close {
+        let base = if let MetricsFieldKind::Error {
+            span,
+            fault,
+            message_max_len,
+            ..
+        } = &self.attrs.kind
+        {
+            let count = if *fault {
+                quote_spanned! {*span=> ::metrique::error::ErrorCount::Fault }
+            } else {
+                quote_spanned! {*span=> ::metrique::error::ErrorCount::Failure }
+            };
+            let message_max_len = match message_max_len {
+                Some(max_len) => quote! { ::std::option::Option::Some(#max_len) },
+                None => quote! { ::std::option::Option::None },
+            };
+            quote_spanned! {*span=>
+                ::metrique::error::ErrorEntry::new(&#field_expr, #count, #message_max_len)
+            }
+        } else if let Some(with) = self.with() {
+            quote_spanned! {with.span()=> #with::close(&#field_expr) }
+        } else if self.attrs.close {
             quote_spanned! {span=> metrique::CloseValue::close(#field_expr) }
         } else {
             field_expr
         };
 
-        let base = if let Some(unit) = self.unit() {
-            quote_spanned! { unit.span() =>
+        let base = if let Some(clamp) = self.clamp() {
+            let bound = |bound: &Option<syn::Expr>| match bound {
+                Some(bound) => quote_spanned! {bound.span()=> ::std::option::Option::Some(#bound) },
+                None => quote! { ::std::option::Option::None },
+            };
+            let (min, max) = (bound(&clamp.min), bound(&clamp.max));
+            let function = match clamp.out_of_range {
+                OutOfRange::Clamp => quote_spanned! {span=> ::metrique::clamp::clamp },
+                OutOfRange::Drop => quote_spanned! {span=> ::metrique::clamp::drop_out_of_range },
+            };
+            quote_spanned! {span=> #function(#base, #min, #max) }
+        } else {
+            base
+        };
+
+        let base = if let Some(span) = self.timestamp_property() {
+            quote_spanned! {span=> ::metrique::timers::IntoTimestampValue::into_timestamp_value(#base) }
+        } else {
+            base
+        };
+
+        let base = match self.unit() {
+            Some(UnitAttr::Tag(unit)) => quote_spanned! { unit.span() =>
                 #base.into()
+            },
+            Some(UnitAttr::Custom(label)) => quote_spanned! { label.span() =>
+                ::metrique::unit::WithCustomUnit::new(#base, #label)
+            },
+            None => base,
+        };
+
+        let base = if let Some(span) = self.attrs.verbose {
+            quote_spanned! {span=>
+                if ::metrique::verbose::is_enabled() {
+                    ::std::option::Option::Some(#base)
+                } else {
+                    ::std::option::Option::None
+                }
             }
         } else {
             base
@@ -1186,6 +2120,27 @@ impl MetricsField {
         let cfg_attrs = self.cfg_attrs();
         quote! { #(#cfg_attrs)* #ident: #base }
     }
+
+    /// Like [`Self::close_field_expr`], for a field that is bound by name in a pattern
+    /// (e.g. in a struct enum variant).
+    ///
+    /// Uses field init shorthand when the value is passed through unchanged, since
+    /// `field: field` would trigger `clippy::redundant_field_names` in user code.
+    pub(crate) fn close_binding_expr(&self) -> Ts2 {
+        let ident = &self.ident;
+        if !self.attrs.close
+            && self.unit().is_none()
+            && self.clamp().is_none()
+            && self.timestamp_property().is_none()
+            && self.attrs.verbose.is_none()
+            && !matches!(self.attrs.kind, MetricsFieldKind::Error { .. })
+        {
+            let cfg_attrs = self.cfg_attrs();
+            quote_spanned! {self.span=> #(#cfg_attrs)* #ident }
+        } else {
+            self.close_field_expr(quote_spanned! {self.span=> #ident })
+        }
+    }
 }
 
 pub(crate) struct TupleData {
@@ -1322,22 +2277,50 @@ enum MetricsFieldKind {
     Flatten {
         span: Span,
         prefix: Option<Prefix>,
+        /// `group = "..."`: the flattened fields are written in a group with this (inflected)
+        /// name, and prefixed by it
+        group: Option<String>,
+        /// Field-level `rename_all`, which overrides the name style of the whole flattened subtree
+        rename_all: Option<NameStyle>,
+        /// `with = module`: close the field through `module::close` into `module::Closed`
+        with: Option<syn::Path>,
     },
     FlattenEntry(Span),
     Timestamp(Span),
+    Error {
+        span: Span,
+        prefix: Option<Prefix>,
+        fault: bool,
+        message_max_len: Option<usize>,
+    },
     Field {
-        unit: Option<syn::Path>,
+        unit: Option<UnitAttr>,
         name: Option<String>,
         format: Option<syn::Path>,
+        /// `bool_as = ...`: how the field is emitted if it is a `bool`
+        bool_as: Option<BoolAs>,
+        /// `none_as = ...`: how the field is emitted if it is `None`
+        none_as: Option<NoneAs>,
         sample_group: Option<Span>,
+        /// `sample_group, no_emit`: the field is part of the sample group, but not written
+        no_emit: bool,
+        clamp: Option<Box<ClampAttrs>>,
+        /// `timestamp(property)`: emit the (closed) timestamp as a property
+        timestamp_property: Option<Span>,
+        /// `max_len = ...`: truncate the strings the field writes
+        truncate: Option<Truncate>,
     },
 }
 
+/// `#[metrics(max_len = ..., truncation_marker = "...")]`
+#[derive(Debug, Clone, PartialEq)]
+struct Truncate {
+    max_len: usize,
+    /// `None` for `metrique::writer::value::DEFAULT_TRUNCATION_MARKER`
+    marker: Option<String>,
+}
+
 // produce a warning that the user can see
-//
-// currently, we do not have any logic that produces warnings, but leave this
-// in for the next time
-#[allow(unused)]
 fn proc_macro_warning(span: Span, warning: &str) -> Ts2 {
     quote_spanned! {span=>
         const _: () = {
@@ -1353,7 +2336,7 @@ fn parse_root_attrs(attr: TokenStream) -> Result<RootAttributes> {
     Ok(RawRootAttributes::from_list(&nested_meta)?.validate()?)
 }
 
-fn generate_metrics(root_attributes: RootAttributes, input: DeriveInput) -> Result<Ts2> {
+fn generate_metrics(mut root_attributes: RootAttributes, input: DeriveInput) -> Result<Ts2> {
     // Check if #[aggregate] attribute is present
     if input
         .attrs
@@ -1366,6 +2349,13 @@ fn generate_metrics(root_attributes: RootAttributes, input: DeriveInput) -> Resu
         ));
     }
 
+    let debug_expand = root_attributes.debug_expand;
+    let tests = root_attributes
+        .generate_tests
+        .take()
+        .map(|tests| generate_tests::generate_tests(&tests, &input.ident, &input.generics))
+        .transpose()?;
+
     let output = match root_attributes.mode {
         MetricMode::RootEntry | MetricMode::Subfield | MetricMode::SubfieldOwned => {
             match &input.data {
@@ -1388,6 +2378,12 @@ fn generate_metrics(root_attributes: RootAttributes, input: DeriveInput) -> Resu
                     structs::generate_metrics_for_struct(root_attributes, &input, fields)?
                 }
                 Data::Enum(data_enum) => {
+                    if root_attributes.doc_as_description {
+                        return Err(Error::new_spanned(
+                            &input,
+                            "`doc_as_description` is only supported on structs",
+                        ));
+                    }
                     let variants =
                         enums::parse_enum_variants(&data_enum.variants, enums::VariantMode::Entry)?;
                     enums::generate_metrics_for_enum(root_attributes, &input, &variants)?
@@ -1435,12 +2431,50 @@ fn generate_metrics(root_attributes: RootAttributes, input: DeriveInput) -> Resu
             enums::generate_metrics_for_enum(root_attributes, &input, &variants)?
         }
     };
+    let output = quote! {
+        #output
+        #tests
+    };
 
-    if std::env::var("MACRO_DEBUG").is_ok() {
-        eprintln!("{}", &output);
-    }
+    Ok(match debug_expand {
+        Some(span) => {
+            let warning = proc_macro_warning(span, &debug_expand_output(&input.ident, &output));
+            quote! {
+                #output
+                #warning
+            }
+        }
+        None => output,
+    })
+}
 
-    Ok(output)
+/// Write the pretty-printed `output` for `#[metrics(debug_expand)]` to
+/// `$OUT_DIR/metrique-expand/<ident>.rs`, and return the message of the warning pointing to it.
+///
+/// `OUT_DIR` is only set for crates with a build script, so otherwise the expansion is put in
+/// the warning itself.
+fn debug_expand_output(ident: &Ident, output: &Ts2) -> String {
+    let expansion = match syn::parse2::<syn::File>(output.clone()) {
+        Ok(file) => prettyplease::unparse(&file),
+        Err(_) => output.to_string(),
+    };
+    let written = std::env::var_os("OUT_DIR").map(|out_dir| {
+        let dir = std::path::Path::new(&out_dir).join("metrique-expand");
+        let path = dir.join(format!("{ident}.rs"));
+        std::fs::create_dir_all(&dir)
+            .and_then(|()| std::fs::write(&path, &expansion))
+            .map(|()| path)
+    });
+    match written {
+        Some(Ok(path)) => format!(
+            "`#[metrics(debug_expand)]`: the expansion of `{ident}` was written to {}",
+            path.display()
+        ),
+        Some(Err(err)) => format!(
+            "`#[metrics(debug_expand)]`: failed to write the expansion of `{ident}`: {err}\n{expansion}"
+        ),
+        None => format!("`#[metrics(debug_expand)]`: the expansion of `{ident}`:\n{expansion}"),
+    }
 }
 
 /// Generates `Ident<'static, 'static, T, N, ...>` with all lifetimes replaced by 'static
@@ -1512,15 +2546,17 @@ fn generate_close_value_impls(
             // for a by-ref ownership, also add a proxy impl for by-value
             quote!(impl #impl_generics metrique::CloseValue for #base_ty #ty_generics #where_clause {
                 type Closed = #closed_ty #ty_generics;
-                fn close(self) -> Self::Closed {
+                fn close(self) -> #closed_ty #ty_generics {
                     <&Self>::close(&self)
                 }
             }),
         ),
     };
 
+    // name the closed type rather than `Self::Closed`, which is ambiguous with a variant named
+    // `Closed` in enums
     let close_fn = quote! {
-        fn close(self) -> Self::Closed {
+        fn close(self) -> #closed_ty #ty_generics {
             // `self` is expanded from macro_rules! input in some callers
             // Routing receiver access through a local macro preserves hygiene in those cases,
             // while avoiding the extra diagnostic site caused by other approaches like rebinding self.
@@ -1601,10 +2637,13 @@ mod tests {
     use darling::FromMeta;
     use insta::assert_snapshot;
     use proc_macro2::TokenStream as Ts2;
-    use quote::quote;
+    use quote::{format_ident, quote};
     use syn::{parse_quote, parse2};
 
-    use crate::RawRootAttributes;
+    use crate::{
+        ClampAttrs, DEFAULT_ERROR_MESSAGE_MAX_LEN, MetricsFieldKind, OutOfRange,
+        RawMetricsFieldAttrs, RawRootAttributes, Truncate, UnitAttr,
+    };
 
     // Helper function to convert proc_macro::TokenStream to proc_macro2::TokenStream
     // This allows us to test the macro without needing to use the proc_macro API directly
@@ -1645,6 +2684,631 @@ mod tests {
         .unwrap();
     }
 
+    #[test]
+    fn test_entry_vis_root_attr() {
+        use darling::FromMeta;
+        let root = |meta: syn::Meta| RawRootAttributes::from_meta(&meta).unwrap().validate();
+        let base_vis: syn::Visibility = parse_quote!(pub(super));
+        let vis = |attrs: &crate::RootAttributes| {
+            let (entry, guard) = (attrs.entry_vis(), attrs.guard_vis(&base_vis));
+            (quote!(#entry).to_string(), quote!(#guard).to_string())
+        };
+        let attrs = root(parse_quote!(metrics(entry_vis = "pub(crate)"))).unwrap();
+        assert_eq!(vis(&attrs), ("pub (crate)".into(), "pub (crate)".into()));
+        let attrs = root(parse_quote!(metrics())).unwrap();
+        assert_eq!(vis(&attrs), ("pub".into(), "pub (super)".into()));
+        root(parse_quote!(metrics(entry_vis = "crate only"))).unwrap_err();
+    }
+
+    #[test]
+    fn test_version_root_attr() {
+        use darling::FromMeta;
+        let root = |meta: syn::Meta| RawRootAttributes::from_meta(&meta).unwrap().validate();
+        let attrs = root(parse_quote!(metrics(version = 2))).unwrap();
+        assert_eq!(attrs.version.as_deref(), Some(&2));
+        let err = root(parse_quote!(metrics(subfield, version = 2))).unwrap_err();
+        assert!(err.to_string().contains("only supported on root metrics"));
+    }
+
+    #[test]
+    fn test_clamp_field_attrs() {
+        use darling::FromField;
+        let field =
+            |field: syn::Field| RawMetricsFieldAttrs::from_field(&field).unwrap().validate();
+        let attrs = field(parse_quote! {
+            #[metrics(clamp(max = 100, out_of_range = "drop"), unit = Millisecond)]
+            latency: u64
+        })
+        .unwrap();
+        assert!(matches!(
+            attrs.kind,
+            MetricsFieldKind::Field {
+                clamp: Some(ref clamp),
+                unit: Some(_),
+                ..
+            } if matches!(
+                **clamp,
+                ClampAttrs {
+                    min: None,
+                    max: Some(_),
+                    out_of_range: OutOfRange::Drop,
+                }
+            )
+        ));
+        field(parse_quote! {
+            #[metrics(clamp())]
+            latency: u64
+        })
+        .unwrap_err();
+        field(parse_quote! {
+            #[metrics(clamp(max = 1), flatten)]
+            latency: u64
+        })
+        .unwrap_err();
+        RawMetricsFieldAttrs::from_field(&parse_quote! {
+            #[metrics(clamp(max = 1, out_of_range = "reject"))]
+            latency: u64
+        })
+        .unwrap_err();
+    }
+
+    #[test]
+    fn test_max_len_field_attrs() {
+        use darling::FromField;
+        let field =
+            |field: syn::Field| RawMetricsFieldAttrs::from_field(&field).unwrap().validate();
+        let attrs = field(parse_quote! {
+            #[metrics(max_len = 64, format = ToString)]
+            message: String
+        })
+        .unwrap();
+        assert!(matches!(
+            attrs.kind,
+            MetricsFieldKind::Field {
+                truncate: Some(Truncate {
+                    max_len: 64,
+                    marker: None
+                }),
+                format: Some(_),
+                ..
+            }
+        ));
+        let attrs = field(parse_quote! {
+            #[metrics(max_len = 64, truncation_marker = "[truncated]")]
+            message: String
+        })
+        .unwrap();
+        assert!(matches!(
+            attrs.kind,
+            MetricsFieldKind::Field {
+                truncate: Some(Truncate { max_len: 64, marker: Some(ref marker) }),
+                ..
+            } if marker == "[truncated]"
+        ));
+        for invalid in [
+            parse_quote! {
+                #[metrics(truncation_marker = "...")]
+                message: String
+            },
+            parse_quote! {
+                #[metrics(max_len = 64, flatten)]
+                message: String
+            },
+            parse_quote! {
+                #[metrics(max_len = 64, unit = Millisecond)]
+                message: String
+            },
+            parse_quote! {
+                #[metrics(max_len = 64, clamp(max = 1))]
+                message: String
+            },
+        ] {
+            field(invalid).unwrap_err();
+        }
+    }
+
+    #[test]
+    fn test_no_emit_field_attrs() {
+        use darling::FromField;
+        let field =
+            |field: syn::Field| RawMetricsFieldAttrs::from_field(&field).unwrap().validate();
+        let attrs = field(parse_quote! {
+            #[metrics(sample_group, no_emit, name = "Op")]
+            operation: &'static str
+        })
+        .unwrap();
+        assert!(matches!(
+            attrs.kind,
+            MetricsFieldKind::Field {
+                sample_group: Some(_),
+                no_emit: true,
+                name: Some(_),
+                ..
+            }
+        ));
+        field(parse_quote! {
+            #[metrics(no_emit)]
+            operation: &'static str
+        })
+        .unwrap_err();
+        field(parse_quote! {
+            #[metrics(sample_group, no_emit, unit = Millisecond)]
+            operation: u64
+        })
+        .unwrap_err();
+    }
+
+    #[test]
+    fn test_custom_unit_field_attrs() {
+        use darling::FromField;
+        let unit =
+            |field: syn::Field| match RawMetricsFieldAttrs::from_field(&field)?.validate()?.kind {
+                MetricsFieldKind::Field { unit, .. } => Ok::<_, darling::Error>(unit),
+                _ => unreachable!(),
+            };
+        assert!(matches!(
+            unit(parse_quote! {
+                #[metrics(unit = Custom("Widgets"))]
+                widgets: u64
+            }),
+            Ok(Some(UnitAttr::Custom(label))) if label.value() == "Widgets"
+        ));
+        assert!(matches!(
+            unit(parse_quote! {
+                #[metrics(unit = metrique::unit::Millisecond)]
+                latency: u64
+            }),
+            Ok(Some(UnitAttr::Tag(_)))
+        ));
+        for bad in [
+            parse_quote! {
+                #[metrics(unit = Custom(""))]
+                widgets: u64
+            },
+            parse_quote! {
+                #[metrics(unit = Custom(Widgets))]
+                widgets: u64
+            },
+            parse_quote! {
+                #[metrics(unit = Custom("Widgets", "Gadgets"))]
+                widgets: u64
+            },
+        ] {
+            unit(bad).unwrap_err();
+        }
+    }
+
+    #[test]
+    fn test_verbose_field_attrs() {
+        use darling::FromField;
+        let field =
+            |field: syn::Field| RawMetricsFieldAttrs::from_field(&field).unwrap().validate();
+        for ok in [
+            parse_quote! {
+                #[metrics(verbose, unit = Millisecond)]
+                latency: u64
+            },
+            parse_quote! {
+                #[metrics(verbose, flatten, prefix = "cache_")]
+                cache: Cache
+            },
+            parse_quote! {
+                #[metrics(verbose, flatten_entry)]
+                debug: Debug
+            },
+        ] {
+            assert!(field(ok).unwrap().verbose.is_some());
+        }
+        for err in [
+            parse_quote! {
+                #[metrics(verbose, timestamp)]
+                start: Timestamp
+            },
+            parse_quote! {
+                #[metrics(verbose, ignore)]
+                ignored: u64
+            },
+            parse_quote! {
+                #[metrics(verbose, error)]
+                error: Option<String>
+            },
+            parse_quote! {
+                #[metrics(verbose, sample_group)]
+                operation: &'static str
+            },
+        ] {
+            field(err).unwrap_err();
+        }
+    }
+
+    #[test]
+    fn test_flatten_rename_all_field_attrs() {
+        use darling::FromField;
+        let field =
+            |field: syn::Field| RawMetricsFieldAttrs::from_field(&field).unwrap().validate();
+        let attrs = field(parse_quote! {
+            #[metrics(flatten, rename_all = "PascalCase", prefix = "legacy_")]
+            legacy: Legacy
+        })
+        .unwrap();
+        assert!(matches!(
+            attrs.kind,
+            MetricsFieldKind::Flatten {
+                rename_all: Some(crate::NameStyle::PascalCase),
+                prefix: Some(_),
+                ..
+            }
+        ));
+        field(parse_quote! {
+            #[metrics(rename_all = "PascalCase")]
+            count: u64
+        })
+        .unwrap_err();
+        field(parse_quote! {
+            #[metrics(flatten_entry, rename_all = "PascalCase")]
+            legacy: Legacy
+        })
+        .unwrap_err();
+        field(parse_quote! {
+            #[metrics(flatten, rename_all = "preserve")]
+            legacy: Legacy
+        })
+        .unwrap_err();
+    }
+
+    #[test]
+    fn test_flatten_group_field_attrs() {
+        use darling::FromField;
+        let field =
+            |field: syn::Field| RawMetricsFieldAttrs::from_field(&field).unwrap().validate();
+        let attrs = field(parse_quote! {
+            #[metrics(flatten, group = "cache")]
+            cache: Cache
+        })
+        .unwrap();
+        assert!(matches!(
+            attrs.kind,
+            MetricsFieldKind::Flatten {
+                group: Some(ref group),
+                prefix: Some(crate::Prefix::Inflectable { ref prefix }),
+                ..
+            } if group == "cache" && prefix == "cache_"
+        ));
+        for err in [
+            parse_quote! {
+                #[metrics(group = "cache")]
+                hits: u64
+            },
+            parse_quote! {
+                #[metrics(flatten_entry, group = "cache")]
+                cache: Cache
+            },
+            parse_quote! {
+                #[metrics(flatten, group = "cache", prefix = "cache_")]
+                cache: Cache
+            },
+            parse_quote! {
+                #[metrics(flatten, group = "")]
+                cache: Cache
+            },
+        ] {
+            field(err).unwrap_err();
+        }
+    }
+
+    #[test]
+    fn test_flatten_with_field_attrs() {
+        use darling::FromField;
+        let field =
+            |field: syn::Field| RawMetricsFieldAttrs::from_field(&field).unwrap().validate();
+        let attrs = field(parse_quote! {
+            #[metrics(flatten, with = remote::status, prefix = "http_")]
+            status: StatusCode
+        })
+        .unwrap();
+        assert!(matches!(
+            attrs.kind,
+            MetricsFieldKind::Flatten {
+                with: Some(ref with),
+                prefix: Some(_),
+                ..
+            } if with.segments.len() == 2
+        ));
+        for err in [
+            parse_quote! {
+                #[metrics(with = status)]
+                status: StatusCode
+            },
+            parse_quote! {
+                #[metrics(flatten_entry, with = status)]
+                status: StatusCode
+            },
+            parse_quote! {
+                #[metrics(flatten, no_close, with = status)]
+                status: StatusCode
+            },
+        ] {
+            field(err).unwrap_err();
+        }
+    }
+
+    #[test]
+    fn test_bool_as_field_attrs() {
+        use darling::FromField;
+        let field =
+            |field: syn::Field| RawMetricsFieldAttrs::from_field(&field).unwrap().validate();
+        let attrs = field(parse_quote! {
+            #[metrics(bool_as = "property", name = "CacheHit")]
+            cache_hit: bool
+        })
+        .unwrap();
+        assert!(matches!(
+            attrs.kind,
+            MetricsFieldKind::Field {
+                bool_as: Some(crate::BoolAs::Property),
+                ..
+            }
+        ));
+        for err in [
+            parse_quote! {
+                #[metrics(bool_as = "property", format = ToString)]
+                cache_hit: bool
+            },
+            parse_quote! {
+                #[metrics(bool_as = "property", unit = Count)]
+                cache_hit: bool
+            },
+            parse_quote! {
+                #[metrics(flatten, bool_as = "property")]
+                nested: Nested
+            },
+            parse_quote! {
+                #[metrics(sample_group, no_emit, bool_as = "metric")]
+                cache_hit: bool
+            },
+        ] {
+            field(err).unwrap_err();
+        }
+        RawMetricsFieldAttrs::from_field(&parse_quote! {
+            #[metrics(bool_as = "string")]
+            cache_hit: bool
+        })
+        .unwrap_err();
+    }
+
+    #[test]
+    fn test_none_as_field_attrs() {
+        use darling::FromField;
+        let field =
+            |field: syn::Field| RawMetricsFieldAttrs::from_field(&field).unwrap().validate();
+        let attrs = field(parse_quote! {
+            #[metrics(none_as = "zero", unit = Millisecond)]
+            latency: Option<Duration>
+        })
+        .unwrap();
+        assert!(matches!(
+            attrs.kind,
+            MetricsFieldKind::Field {
+                none_as: Some(crate::NoneAs::Zero),
+                unit: Some(_),
+                ..
+            }
+        ));
+        for err in [
+            parse_quote! {
+                #[metrics(none_as = "null", format = ToString)]
+                retries: Option<u64>
+            },
+            parse_quote! {
+                #[metrics(none_as = "null", bool_as = "property")]
+                cache_hit: Option<bool>
+            },
+            parse_quote! {
+                #[metrics(none_as = "zero", verbose)]
+                retries: Option<u64>
+            },
+            parse_quote! {
+                #[metrics(flatten, none_as = "zero")]
+                nested: Option<Nested>
+            },
+            parse_quote! {
+                #[metrics(sample_group, no_emit, none_as = "null")]
+                operation: Option<&'static str>
+            },
+        ] {
+            field(err).unwrap_err();
+        }
+        RawMetricsFieldAttrs::from_field(&parse_quote! {
+            #[metrics(none_as = "empty")]
+            retries: Option<u64>
+        })
+        .unwrap_err();
+    }
+
+    #[test]
+    fn test_timestamp_field_attrs() {
+        use darling::FromField;
+        let field =
+            |field: syn::Field| RawMetricsFieldAttrs::from_field(&field).unwrap().validate();
+        let attrs = field(parse_quote! {
+            #[metrics(timestamp)]
+            timestamp: Timestamp
+        })
+        .unwrap();
+        assert!(matches!(attrs.kind, MetricsFieldKind::Timestamp(_)));
+        let attrs = field(parse_quote! {
+            #[metrics(timestamp(property), format = EpochSeconds, name = "StartTime")]
+            start: Timestamp
+        })
+        .unwrap();
+        assert!(matches!(
+            attrs.kind,
+            MetricsFieldKind::Field {
+                timestamp_property: Some(_),
+                format: Some(_),
+                ..
+            }
+        ));
+        field(parse_quote! {
+            #[metrics(timestamp(property), unit = Millisecond)]
+            start: Timestamp
+        })
+        .unwrap_err();
+        field(parse_quote! {
+            #[metrics(timestamp(property), flatten)]
+            start: Timestamp
+        })
+        .unwrap_err();
+        field(parse_quote! {
+            #[metrics(timestamp, format = EpochSeconds)]
+            start: Timestamp
+        })
+        .unwrap_err();
+
+        let fields: syn::FieldsNamed = parse_quote! {{
+            #[metrics(timestamp)]
+            a: Timestamp,
+            #[metrics(timestamp(property))]
+            b: Timestamp,
+            #[metrics(timestamp)]
+            c: Timestamp,
+        }};
+        assert!(crate::parse_metric_fields(&fields.named).is_err());
+    }
+
+    #[test]
+    fn test_error_field_attrs() {
+        use darling::FromField;
+        let field =
+            |field: syn::Field| RawMetricsFieldAttrs::from_field(&field).unwrap().validate();
+        let attrs = field(parse_quote! {
+            #[metrics(error(fault, message_max_len = 10), prefix = "dependency_")]
+            error: Option<String>
+        })
+        .unwrap();
+        assert!(matches!(
+            attrs.kind,
+            MetricsFieldKind::Error {
+                fault: true,
+                message_max_len: Some(10),
+                prefix: Some(_),
+                ..
+            }
+        ));
+        let attrs = field(parse_quote! {
+            #[metrics(error(message))]
+            error: Option<String>
+        })
+        .unwrap();
+        assert!(matches!(
+            attrs.kind,
+            MetricsFieldKind::Error {
+                fault: false,
+                message_max_len: Some(DEFAULT_ERROR_MESSAGE_MAX_LEN),
+                prefix: None,
+                ..
+            }
+        ));
+        field(parse_quote! {
+            #[metrics(error, flatten)]
+            error: Option<String>
+        })
+        .unwrap_err();
+        field(parse_quote! {
+            #[metrics(error, unit = Millisecond)]
+            error: Option<String>
+        })
+        .unwrap_err();
+    }
+
+    #[test]
+    fn test_generate_tests_requires_root_entry() {
+        use darling::FromMeta;
+        let attrs = |input: Ts2| {
+            RawRootAttributes::from_meta(&parse_quote!(metrics(#input)))
+                .unwrap()
+                .validate()
+        };
+        attrs(quote!(generate_tests(
+            metric(
+                name = "Latency",
+                unit = Millisecond,
+                dimensions = ["Operation"]
+            ),
+            property = "Operation",
+            dimension_sets = [["Operation"]],
+        )))
+        .unwrap();
+        attrs(quote!(subfield, generate_tests(metric(name = "Latency")))).unwrap_err();
+    }
+
+    #[test]
+    fn test_doc_as_description_requires_entry() {
+        use darling::FromMeta;
+        let attrs = |input: Ts2| {
+            RawRootAttributes::from_meta(&parse_quote!(metrics(#input)))
+                .unwrap()
+                .validate()
+        };
+        assert!(
+            attrs(quote!(doc_as_description))
+                .unwrap()
+                .doc_as_description
+        );
+        assert!(
+            attrs(quote!(subfield, doc_as_description))
+                .unwrap()
+                .doc_as_description
+        );
+        attrs(quote!(value, doc_as_description)).unwrap_err();
+        attrs(quote!(value(string), doc_as_description)).unwrap_err();
+    }
+
+    #[test]
+    fn test_omit_zeros_requires_entry() {
+        use darling::FromMeta;
+        let attrs = |input: Ts2| {
+            RawRootAttributes::from_meta(&parse_quote!(metrics(#input)))
+                .unwrap()
+                .validate()
+        };
+        assert!(attrs(quote!(omit_zeros)).unwrap().omit_zeros);
+        assert!(attrs(quote!(subfield, omit_zeros)).unwrap().omit_zeros);
+        assert!(!attrs(quote!()).unwrap().omit_zeros);
+        attrs(quote!(value, omit_zeros)).unwrap_err();
+    }
+
+    #[test]
+    fn test_value_string_display_from_str() {
+        use darling::FromMeta;
+        let attrs = |input: Ts2| {
+            RawRootAttributes::from_meta(&parse_quote!(metrics(#input)))
+                .unwrap()
+                .validate()
+        };
+        let root = attrs(quote!(value(string, display, from_str))).unwrap();
+        assert!(root.value_display && root.value_from_str);
+        let root = attrs(quote!(value(string))).unwrap();
+        assert!(!root.value_display && !root.value_from_str);
+        attrs(quote!(value(display))).unwrap_err();
+        attrs(quote!(value(from_str))).unwrap_err();
+    }
+
+    #[test]
+    fn test_value_field() {
+        use darling::FromMeta;
+        let attrs = |input: Ts2| {
+            RawRootAttributes::from_meta(&parse_quote!(metrics(#input)))
+                .unwrap()
+                .validate()
+        };
+        let root = attrs(quote!(value(field = 1))).unwrap();
+        assert_eq!(root.mode, super::MetricMode::Value);
+        assert_eq!(root.value_field.as_deref(), Some(&1));
+        assert!(attrs(quote!(value)).unwrap().value_field.is_none());
+        attrs(quote!(value(string, field = 1))).unwrap_err();
+    }
+
     #[test]
     fn test_simple_metrics_struct() {
         let input = quote! {
@@ -1776,6 +3440,169 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_emf_limits() {
+        let generate = |attrs: Ts2, input: Ts2| {
+            let root_attrs = RawRootAttributes::from_meta(&parse_quote!(metrics(#attrs)))
+                .unwrap()
+                .validate()
+                .unwrap();
+            super::generate_metrics(root_attrs, syn::parse2(input).unwrap())
+        };
+        let fields: Vec<_> = (0..101).map(|i| format_ident!("counter_{i}")).collect();
+        let wide = quote!(struct Wide { #(#fields: usize),* });
+        let err = generate(quote!(), wide.clone()).unwrap_err();
+        assert!(err.to_string().contains("writes 101 fields"), "{err}");
+        generate(quote!(limits(max_fields = 200)), wide.clone()).unwrap();
+        generate(quote!(subfield), wide).unwrap();
+
+        // string fields are properties, which don't count towards the limit
+        let properties: Vec<_> = (0..60).map(|i| format_ident!("property_{i}")).collect();
+        let mixed = quote!(
+            struct Mixed {
+                #(#fields: usize,)*
+                #(#properties: &'static str,)*
+                owned: String,
+                optional: Option<String>,
+                cow: std::borrow::Cow<'static, str>,
+                shared: std::sync::Arc<str>,
+            }
+        );
+        let err = generate(quote!(), mixed).unwrap_err();
+        assert!(err.to_string().contains("writes 101 fields"), "{err}");
+        let counters = &fields[..60];
+        let mixed = quote!(
+            struct Mixed {
+                #(#counters: usize,)*
+                #(#properties: &'static str,)*
+                owned: String,
+                optional: Option<String>,
+                cow: std::borrow::Cow<'static, str>,
+                shared: std::sync::Arc<str>,
+            }
+        );
+        generate(quote!(), mixed).unwrap();
+
+        let long_name = "a".repeat(256);
+        let long = quote!(
+            struct Long {
+                #[metrics(name = #long_name)]
+                latency: usize,
+            }
+        );
+        let err = generate(quote!(), long).unwrap_err();
+        assert!(err.to_string().contains("is 256 characters long"), "{err}");
+        let long_field = format_ident!("{}", "b".repeat(250));
+        let long = quote!(struct Long { #long_field: usize });
+        generate(quote!(), long.clone()).unwrap();
+        let err = generate(quote!(prefix = "request_"), long.clone()).unwrap_err();
+        assert!(err.to_string().contains("is 258 characters long"), "{err}");
+        generate(
+            quote!(prefix = "request_", limits(max_name_len = 1024)),
+            long,
+        )
+        .unwrap();
+
+        RawRootAttributes::from_meta(&parse_quote!(metrics(subfield, limits(max_fields = 200))))
+            .unwrap()
+            .validate()
+            .unwrap_err();
+    }
+
+    #[test]
+    fn test_field_handles() {
+        let generate = |attrs: Ts2, input: Ts2| {
+            let root_attrs = RawRootAttributes::from_meta(&parse_quote!(metrics(#attrs)))
+                .unwrap()
+                .validate()
+                .unwrap();
+            super::generate_metrics(root_attrs, syn::parse2(input).unwrap())
+        };
+        let counters = quote!(
+            struct Counters {
+                #[metrics(handle)]
+                hits: Counter,
+                misses: Counter,
+            }
+        );
+        let generated = generate(quote!(), counters.clone()).unwrap().to_string();
+        assert!(generated.contains("struct CountersHandles"), "{generated}");
+        assert!(!generated.contains("misses : :: metrique :: FieldHandle"));
+
+        let err = generate(quote!(subfield), counters).unwrap_err();
+        assert!(
+            err.to_string().contains("only be used on root metrics"),
+            "{err}"
+        );
+        let err = generate(
+            quote!(),
+            quote!(
+                struct Counters<T> {
+                    #[metrics(handle)]
+                    hits: Counter,
+                    value: T,
+                }
+            ),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("generic metrics"), "{err}");
+        let err = generate(
+            quote!(),
+            quote!(
+                struct Counters(#[metrics(handle)] Counter);
+            ),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("named fields"), "{err}");
+        let err = generate(
+            quote!(),
+            quote!(
+                enum Outcome {
+                    Failed {
+                        #[metrics(handle)]
+                        retries: Counter,
+                    },
+                }
+            ),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("enum variant fields"), "{err}");
+    }
+
+    #[test]
+    fn test_operation_detail_needs_flatten_entry() {
+        let generate = |input: Ts2| {
+            let root_attrs = RawRootAttributes::from_meta(&parse_quote!(metrics()))
+                .unwrap()
+                .validate()
+                .unwrap();
+            super::generate_metrics(root_attrs, syn::parse2(input).unwrap())
+        };
+        let err = generate(quote!(
+            struct RequestMetrics {
+                #[metrics(flatten)]
+                detail: Option<Box<dyn metrique::operation_detail::OperationDetail>>,
+            }
+        ))
+        .unwrap_err();
+        assert!(err.to_string().contains("flatten_entry"), "{err}");
+        let err = generate(quote!(
+            struct RequestMetrics {
+                #[metrics(flatten)]
+                detail: Option<Box<dyn OperationDetail>>,
+            }
+        ))
+        .unwrap_err();
+        assert!(err.to_string().contains("flatten_entry"), "{err}");
+        generate(quote!(
+            struct RequestMetrics {
+                #[metrics(flatten_entry)]
+                detail: Option<Box<dyn OperationDetail>>,
+            }
+        ))
+        .unwrap();
+    }
+
     #[test]
     fn test_metrics_with_lifetime() {
         let input = quote! {
@@ -1890,7 +3717,7 @@ mod tests {
                 struct Metadata {
                     #[metrics(sample_group)]
                     operation: Operation,
-                    request_id: String,
+                    request_id: &'static str,
                 }
             },
             quote!(metrics(subfield)),
@@ -1989,4 +3816,23 @@ mod tests {
         let parsed_file = metrics_impl_string(input, quote!(metrics(value(string))));
         assert_snapshot!("debug_derive_passthrough_enum", parsed_file);
     }
+
+    #[test]
+    fn test_debug_expand() {
+        let input = quote! {
+            struct Metrics {
+                field: usize,
+            }
+        };
+        let plain = metrics_impl_string(input.clone(), quote!(metrics()));
+        let expanded = metrics_impl_string(input, quote!(metrics(debug_expand)));
+        // the expansion is unchanged, except for the warning
+        assert!(expanded.starts_with(&plain), "{expanded}");
+        let warning = &expanded[plain.len()..];
+        assert!(warning.contains("#[deprecated("), "{warning}");
+        assert!(
+            warning.contains("`#[metrics(debug_expand)]`: the expansion of `Metrics`"),
+            "{warning}"
+        );
+    }
 }
@@ -0,0 +1,97 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Compile-time checks that the fields of a root metric fit the limits of CloudWatch EMF, which
+//! rejects records with more than 100 metrics and metric names longer than 255 characters.
+//!
+//! Only what the macro can see is checked: the fields declared on the root metric itself (not the
+//! ones of flattened fields), with the names they get from `name`, `rename_all` and `prefix`.
+//! Fields that are known to be written as properties, like string fields, are not counted.
+
+use darling::FromMeta;
+use proc_macro2::Span;
+use syn::Result;
+
+use crate::{
+    BoolAs, MetricMode, MetricsField, MetricsFieldKind, RootAttributes, inflect::metric_name,
+    structs::is_string_type,
+};
+
+/// The maximum number of metrics in an EMF record
+const MAX_FIELDS: usize = 100;
+/// The maximum length of an EMF metric name
+const MAX_NAME_LEN: usize = 255;
+
+/// `#[metrics(limits(max_fields = .., max_name_len = ..))]`, to raise (or lower) the limits for
+/// metrics that are not written as EMF
+#[derive(Debug, Clone, Copy, Default, FromMeta)]
+pub(crate) struct Limits {
+    #[darling(default)]
+    max_fields: Option<usize>,
+    #[darling(default)]
+    max_name_len: Option<usize>,
+}
+
+/// Check the fields of a root metric (or of one variant of a root enum) against the limits.
+///
+/// `span` is where to report too many fields, the name of the struct or variant.
+pub(crate) fn check_limits(
+    root_attrs: &RootAttributes,
+    span: Span,
+    fields: &[MetricsField],
+) -> Result<()> {
+    if root_attrs.mode != MetricMode::RootEntry {
+        return Ok(());
+    }
+    let limits = root_attrs.limits.unwrap_or_default();
+    let max_fields = limits.max_fields.unwrap_or(MAX_FIELDS);
+    let max_name_len = limits.max_name_len.unwrap_or(MAX_NAME_LEN);
+
+    // fields that are known to be properties don't count towards the metrics of a record
+    let written: Vec<&MetricsField> = fields
+        .iter()
+        .filter(|field| {
+            matches!(
+                field.attrs.kind,
+                MetricsFieldKind::Field {
+                    no_emit: false,
+                    timestamp_property: None,
+                    bool_as: None | Some(BoolAs::Metric),
+                    ..
+                }
+            ) && !is_string_type(&field.ty)
+        })
+        .collect();
+
+    let mut errors: Option<syn::Error> = None;
+    let mut push = |error: syn::Error| match &mut errors {
+        Some(errors) => errors.combine(error),
+        None => errors = Some(error),
+    };
+    if written.len() > max_fields {
+        push(syn::Error::new(
+            span,
+            format!(
+                "this metric writes {} fields, more than the {max_fields} metrics that a CloudWatch EMF \
+                 record can hold. Split it into several metrics, or set \
+                 `#[metrics(limits(max_fields = ..))]` if it is not written as EMF",
+                written.len(),
+            ),
+        ));
+    }
+    for field in written {
+        let name = metric_name(root_attrs, root_attrs.rename_all, field);
+        if name.len() > max_name_len {
+            push(syn::Error::new(
+                field.span,
+                format!(
+                    "the name `{name}` is {} characters long, more than the {max_name_len} \
+                     characters of a CloudWatch EMF metric name. Rename the field, or set \
+                     `#[metrics(limits(max_name_len = ..))]` if it is not written as EMF",
+                    name.len(),
+                ),
+            ));
+        }
+    }
+    errors.map_or(Ok(()), Err)
+}
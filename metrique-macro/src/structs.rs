@@ -1,15 +1,17 @@
 // Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: Apache-2.0
 
+use darling::util::Override;
 use proc_macro2::TokenStream as Ts2;
-use quote::{format_ident, quote};
+use quote::{format_ident, quote, quote_spanned};
 use syn::{
     Attribute, DeriveInput, FieldsNamed, FieldsUnnamed, Generics, Ident, Result, Visibility,
+    spanned::Spanned,
 };
 
 use crate::{
-    MetricMode, MetricsField, MetricsFieldKind, RootAttributes, clean_attrs, entry_impl,
-    generate_on_drop_wrapper, parse_metric_fields, value_impl,
+    DefaultExpr, MetricMode, MetricsField, MetricsFieldKind, RootAttributes, clean_attrs,
+    entry_impl, generate_on_drop_wrapper, parse_metric_fields, value_impl,
 };
 
 pub(crate) fn generate_metrics_for_struct(
@@ -26,7 +28,18 @@ pub(crate) fn generate_metrics_for_struct(
     let guard_name = format_ident!("{}Guard", struct_name);
     let handle_name = format_ident!("{}Handle", struct_name);
 
-    let parsed_fields = parse_metric_fields(fields)?;
+    let mut parsed_fields = parse_metric_fields(fields)?;
+    check_known_field_types(root_attributes.mode, &parsed_fields)?;
+    crate::limits::check_limits(&root_attributes, struct_name.span(), &parsed_fields)?;
+    crate::cycles::check_flatten_cycles(
+        struct_name,
+        parsed_fields
+            .iter()
+            .map(|field| (&field.ty, &field.attrs.kind)),
+    )?;
+    if let Some(value_field) = &root_attributes.value_field {
+        value_impl::select_value_field(value_field, &mut parsed_fields)?;
+    }
 
     let base_struct = generate_base_struct(
         struct_name,
@@ -36,6 +49,7 @@ pub(crate) fn generate_metrics_for_struct(
         &parsed_fields,
     )?;
     let warnings = root_attributes.warnings();
+    let default_impl = generate_default_impl(struct_name, &input.generics, &parsed_fields);
 
     let entry_struct = generate_entry_struct(
         &entry_name,
@@ -79,27 +93,41 @@ pub(crate) fn generate_metrics_for_struct(
     let root_entry_specifics = match root_attributes.mode {
         MetricMode::RootEntry => {
             let on_drop_wrapper = generate_on_drop_wrapper(
-                vis,
+                root_attributes.guard_vis(vis),
                 &guard_name,
                 struct_name,
                 &entry_name,
                 &handle_name,
                 &input.generics,
             );
+            let field_handles = generate_field_handles(
+                root_attributes.guard_vis(vis),
+                struct_name,
+                &input.generics,
+                &parsed_fields,
+            )?;
             quote! {
                 #on_drop_wrapper
+                #field_handles
             }
         }
         MetricMode::Subfield
         | MetricMode::SubfieldOwned
         | MetricMode::ValueString
         | MetricMode::Value => {
+            if let Some(span) = parsed_fields.iter().find_map(|field| field.attrs.handle) {
+                return Err(syn::Error::new(
+                    span,
+                    "`handle` can only be used on root metrics, which are appended with `append_on_drop`",
+                ));
+            }
             quote! {}
         }
     };
 
     Ok(quote! {
         #base_struct
+        #default_impl
         #warnings
         #entry_struct
         #inner_impl
@@ -108,6 +136,254 @@ pub(crate) fn generate_metrics_for_struct(
     })
 }
 
+/// Generate the `*Handles` struct and the `FieldHandles` impl if any field has
+/// `#[metrics(handle)]`
+fn generate_field_handles(
+    vis: &Visibility,
+    name: &Ident,
+    generics: &Generics,
+    fields: &[MetricsField],
+) -> Result<Ts2> {
+    let handle_fields: Vec<&MetricsField> = fields
+        .iter()
+        .filter(|field| field.attrs.handle.is_some())
+        .collect();
+    let Some(first) = handle_fields.first() else {
+        return Ok(quote! {});
+    };
+    if !generics.params.is_empty() {
+        return Err(syn::Error::new(
+            first.attrs.handle.unwrap(),
+            "`handle` is not supported on generic metrics",
+        ));
+    }
+    if let Some(field) = handle_fields.iter().find(|field| field.name.is_none()) {
+        return Err(syn::Error::new(
+            field.attrs.handle.unwrap(),
+            "`handle` is only supported on named fields",
+        ));
+    }
+
+    let handles_name = format_ident!("{}Handles", name);
+    let name_str = name.to_string();
+    let declarations = handle_fields.iter().map(|field| {
+        let MetricsField { vis, ident, ty, .. } = field;
+        let cfg_attrs = field.cfg_attrs();
+        let doc = format!("Handle to [`{name}::{ident}`]");
+        quote! { #(#cfg_attrs)* #[doc = #doc] #vis #ident: ::metrique::FieldHandle<#ty> }
+    });
+    let initializers = handle_fields.iter().map(|field| {
+        let ident = &field.ident;
+        let cfg_attrs = field.cfg_attrs();
+        quote! { #(#cfg_attrs)* #ident: handle.project(|metrics: &Self| &metrics.#ident) }
+    });
+    Ok(quote! {
+        #[doc = concat!("Handles to the `#[metrics(handle)]` fields of [`", #name_str, "`], returned by `handles()` on its guard and handle.")]
+        #[derive(Clone)]
+        #vis struct #handles_name {
+            #(#declarations,)*
+        }
+
+        impl ::metrique::FieldHandles for #name {
+            type Handles = #handles_name;
+
+            fn field_handles<S: ::metrique::writer::EntrySink<::metrique::RootMetric<Self>> + Send + Sync + 'static>(
+                handle: &::metrique::AppendAndCloseOnDropHandle<Self, S>,
+            ) -> #handles_name {
+                #handles_name {
+                    #(#initializers,)*
+                }
+            }
+        }
+    })
+}
+
+/// Generate a `Default` impl if any field has `#[metrics(default)]`
+fn generate_default_impl(name: &Ident, generics: &Generics, fields: &[MetricsField]) -> Ts2 {
+    if fields.iter().all(|field| field.attrs.default.is_none()) {
+        return quote! {};
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let fields = fields.iter().map(|field| {
+        let ident = &field.ident;
+        let cfg_attrs = field.cfg_attrs();
+        let value = match field.attrs.default.as_deref() {
+            Some(Override::Explicit(DefaultExpr(expr))) => quote_spanned! {expr.span()=> #expr },
+            Some(Override::Inherit) | None => {
+                quote_spanned! {field.span=> ::std::default::Default::default() }
+            }
+        };
+        quote! { #(#cfg_attrs)* #ident: #value }
+    });
+    quote! {
+        impl #impl_generics ::std::default::Default for #name #ty_generics #where_clause {
+            fn default() -> Self {
+                Self { #(#fields,)* }
+            }
+        }
+    }
+}
+
+/// Report common field types that can never be closed with a targeted error at the field,
+/// instead of the trait-resolution errors that the generated code would produce.
+fn check_known_field_types(mode: MetricMode, fields: &[MetricsField]) -> Result<()> {
+    let mut errors: Option<syn::Error> = None;
+    for field in fields {
+        if let MetricsFieldKind::Flatten { span, .. } = field.attrs.kind
+            && mentions_operation_detail(&field.ty)
+        {
+            let error = syn::Error::new(
+                span,
+                "`dyn OperationDetail` is written with its own names, so it can't be inflected \
+                 by its parent. Use `#[metrics(flatten_entry)]` instead of `flatten`",
+            );
+            match &mut errors {
+                Some(errors) => errors.combine(error),
+                None => errors = Some(error),
+            }
+            continue;
+        }
+        if !field.attrs.close || !matches!(field.attrs.kind, MetricsFieldKind::Field { .. }) {
+            continue;
+        }
+        if let Some(message) = known_field_type_error(mode, &field.ty) {
+            let error = syn::Error::new_spanned(&field.ty, message);
+            match &mut errors {
+                Some(errors) => errors.combine(error),
+                None => errors = Some(error),
+            }
+        }
+    }
+    errors.map_or(Ok(()), Err)
+}
+
+fn known_field_type_error(mode: MetricMode, ty: &syn::Type) -> Option<String> {
+    let ty = option_inner_type(ty).unwrap_or(ty);
+    match ty {
+        syn::Type::Path(path) if path.qself.is_none() => {
+            let segments = path_idents(&path.path);
+            let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+            match segments[..] {
+                [signed @ ("i8" | "i16" | "i32" | "i64" | "i128" | "isize")] => Some(format!(
+                    "`{signed}` can't be used as a metric: signed integers are not metric values. \
+                     Use an unsigned integer like `u64`, or `f64` if the value can be negative"
+                )),
+                ref string if is_string_path(string) && mode == MetricMode::Subfield => Some(
+                    "`String` can't be used in a `#[metrics(subfield)]`, which is closed by \
+                         reference. Use `#[metrics(subfield_owned)]` on the struct, or a field \
+                         type that can be closed by reference like `Arc<str>` or `&'static str`"
+                        .to_owned(),
+                ),
+                _ => None,
+            }
+        }
+        syn::Type::Reference(reference)
+            if reference.lifetime.is_none()
+                && mode == MetricMode::RootEntry
+                && matches!(&*reference.elem, syn::Type::Path(p) if p.path.is_ident("str")) =>
+        {
+            Some(
+                "`&str` fields need a lifetime. Use `&'static str` for string literals, or \
+                 `String` for strings built at runtime"
+                    .to_owned(),
+            )
+        }
+        _ => None,
+    }
+}
+
+/// Returns true if `ty` is, or has a type argument that is, `dyn OperationDetail` (by its bare
+/// name, or a path ending in it).
+fn mentions_operation_detail(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::TraitObject(object) => object.bounds.iter().any(|bound| match bound {
+            syn::TypeParamBound::Trait(bound) => bound
+                .path
+                .segments
+                .last()
+                .is_some_and(|segment| segment.ident == "OperationDetail"),
+            _ => false,
+        }),
+        syn::Type::Path(path) => {
+            path.path
+                .segments
+                .iter()
+                .any(|segment| match &segment.arguments {
+                    syn::PathArguments::AngleBracketed(args) => {
+                        args.args.iter().any(|arg| match arg {
+                            syn::GenericArgument::Type(ty) => mentions_operation_detail(ty),
+                            _ => false,
+                        })
+                    }
+                    _ => false,
+                })
+        }
+        syn::Type::Reference(reference) => mentions_operation_detail(&reference.elem),
+        syn::Type::Paren(paren) => mentions_operation_detail(&paren.elem),
+        syn::Type::Group(group) => mentions_operation_detail(&group.elem),
+        _ => false,
+    }
+}
+
+fn path_idents(path: &syn::Path) -> Vec<String> {
+    path.segments
+        .iter()
+        .map(|segment| segment.ident.to_string())
+        .collect()
+}
+
+fn is_string_path(segments: &[&str]) -> bool {
+    matches!(segments, ["String"] | ["std" | "alloc", "string", "String"])
+}
+
+/// Returns true if `ty` is a string type, which is written as a property rather than a metric:
+/// `String`, `&str`, `Cow<str>`, `Arc<str>`, `Rc<str>` or `Box<str>`, or an `Option` of one.
+pub(crate) fn is_string_type(ty: &syn::Type) -> bool {
+    let is_str = |ty: &syn::Type| matches!(ty, syn::Type::Path(p) if p.path.is_ident("str"));
+    let ty = option_inner_type(ty).unwrap_or(ty);
+    match ty {
+        syn::Type::Path(path) if path.qself.is_none() => {
+            let segments = path_idents(&path.path);
+            let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+            if is_string_path(&segments) {
+                return true;
+            }
+            let Some(syn::PathArguments::AngleBracketed(args)) =
+                path.path.segments.last().map(|segment| &segment.arguments)
+            else {
+                return false;
+            };
+            let mut types = args.args.iter().filter_map(|arg| match arg {
+                syn::GenericArgument::Type(ty) => Some(ty),
+                _ => None,
+            });
+            matches!(segments.last(), Some(&("Cow" | "Arc" | "Rc" | "Box")))
+                && types.next().is_some_and(is_str)
+                && types.next().is_none()
+        }
+        syn::Type::Reference(reference) => is_str(&reference.elem),
+        _ => false,
+    }
+}
+
+/// Returns `T` if `ty` is `Option<T>`
+fn option_inner_type(ty: &syn::Type) -> Option<&syn::Type> {
+    let syn::Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        syn::GenericArgument::Type(inner) if args.args.len() == 1 => Some(inner),
+        _ => None,
+    }
+}
+
 fn generate_base_struct(
     name: &Ident,
     vis: &Visibility,
@@ -147,12 +423,13 @@ fn generate_entry_struct(
     let body = wrap_fields_into_struct_decl(has_named_fields, config.into_iter().chain(fields));
 
     let allowed_derives = crate::derive_utils::extract_allowed_derives(base_attrs);
+    let vis = root_attrs.entry_vis();
 
     Ok(quote!(
         #[doc(hidden)]
         #[allow(clippy::type_complexity)]
         #(#allowed_derives)*
-        pub struct #name #generics #body
+        #vis struct #name #generics #body
     ))
 }
 
@@ -163,10 +440,15 @@ fn generate_close_value_impls_for_struct(
     fields: &[MetricsField],
     root_attrs: &RootAttributes,
 ) -> Ts2 {
-    let fields = fields
-        .iter()
-        .filter(|f| !matches!(f.attrs.kind, MetricsFieldKind::Ignore(_)))
-        .map(|f| f.close_value(root_attrs.ownership_kind()));
+    let has_named_fields = fields.iter().any(|f| f.name.is_some());
+    let fields = fields.iter().filter_map(|f| match f.attrs.kind {
+        // the placeholder of an ignored field of a tuple struct
+        MetricsFieldKind::Ignore(span) => (!has_named_fields).then(|| {
+            let ident = &f.ident;
+            quote_spanned! {span=> #ident: () }
+        }),
+        _ => Some(f.close_value(root_attrs.ownership_kind())),
+    });
     let config: Vec<Ts2> = root_attrs.create_configuration();
 
     let impl_body = quote! {
@@ -1,9 +1,53 @@
-use crate::{MetricsField, MetricsFieldKind, NameStyle, RootAttributes, enums::MetricsVariant};
+use crate::{
+    BoolAs, MetricsField, MetricsFieldKind, NameStyle, NoneAs, RootAttributes, Truncate,
+    enums::MetricsVariant,
+};
 
+use darling::util::SpannedValue;
 use proc_macro2::{Span, TokenStream as Ts2};
 use quote::{quote, quote_spanned};
 use syn::Ident;
 
+/// Generate the `Display` and `FromStr` impls requested by `value(string, display, from_str)`
+/// on the base enum
+pub(crate) fn generate_display_and_from_str_for_enum(
+    root_attrs: &RootAttributes,
+    enum_name: &Ident,
+    parsed_variants: &[MetricsVariant],
+) -> Ts2 {
+    let display = root_attrs.value_display.then(|| {
+        quote! {
+            impl ::std::fmt::Display for #enum_name {
+                fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                    f.pad(::std::convert::Into::<&str>::into(self))
+                }
+            }
+        }
+    });
+    let from_str = root_attrs.value_from_str.then(|| {
+        let arms = parsed_variants.iter().map(|variant| {
+            let variant_ident = &variant.ident;
+            let metric_name = crate::inflect::inflect_no_prefix(root_attrs, variant);
+            quote_spanned!(variant.ident.span()=> #metric_name => ::std::result::Result::Ok(#enum_name::#variant_ident))
+        });
+        let type_name = enum_name.to_string();
+        quote! {
+            impl ::std::str::FromStr for #enum_name {
+                type Err = ::metrique::UnknownVariantError;
+
+                fn from_str(s: &str) -> ::std::result::Result<Self, Self::Err> {
+                    #[allow(deprecated)]
+                    match s {
+                        #(#arms,)*
+                        _ => ::std::result::Result::Err(::metrique::UnknownVariantError::new(#type_name, s)),
+                    }
+                }
+            }
+        }
+    });
+    quote!(#display #from_str)
+}
+
 pub(crate) fn generate_value_impl_for_enum(
     root_attrs: &RootAttributes,
     value_name: &Ident,
@@ -34,6 +78,70 @@ pub(crate) fn generate_value_impl_for_enum(
     ))
 }
 
+/// Apply `#[metrics(value(field = N))]`: field `N` of the tuple struct is the value, and the
+/// other fields are ignored
+pub(crate) fn select_value_field(
+    value_field: &SpannedValue<usize>,
+    parsed_fields: &mut [MetricsField],
+) -> Result<(), syn::Error> {
+    if parsed_fields.iter().any(|f| f.name.is_some()) {
+        return Err(syn::Error::new(
+            value_field.span(),
+            "`field` is only supported on tuple structs, ignore the other fields with #[metrics(ignore)]",
+        ));
+    }
+    let index = **value_field;
+    match parsed_fields.get(index).map(|f| &f.attrs.kind) {
+        None => {
+            return Err(syn::Error::new(
+                value_field.span(),
+                format!(
+                    "field {index} does not exist, the struct has {} fields",
+                    parsed_fields.len()
+                ),
+            ));
+        }
+        Some(MetricsFieldKind::Ignore(span)) => {
+            return Err(syn::Error::new(
+                *span,
+                "the field selected by `value(field = ..)` can't be ignored",
+            ));
+        }
+        Some(_) => {}
+    }
+    for (i, field) in parsed_fields.iter_mut().enumerate() {
+        if i == index {
+            continue;
+        }
+        // don't silently drop attributes of the fields that are not the value
+        let plain = matches!(
+            &field.attrs.kind,
+            MetricsFieldKind::Field {
+                unit: None,
+                name: None,
+                format: None,
+                bool_as: None,
+                none_as: None,
+                sample_group: None,
+                no_emit: false,
+                clamp: None,
+                timestamp_property: None,
+                truncate: None,
+            }
+        );
+        if (!plain && !matches!(field.attrs.kind, MetricsFieldKind::Ignore(_)))
+            || field.attrs.verbose.is_some()
+        {
+            return Err(syn::Error::new(
+                field.span,
+                "only the field selected by `value(field = ..)` can have metrics attributes",
+            ));
+        }
+        field.attrs.kind = MetricsFieldKind::Ignore(field.span);
+    }
+    Ok(())
+}
+
 pub fn validate_value_impl_for_struct(
     root_attrs: &RootAttributes,
     value_name: &Ident,
@@ -53,8 +161,14 @@ pub fn validate_value_impl_for_struct(
         if let MetricsFieldKind::Field {
             unit: _,
             sample_group,
+            no_emit: _,
             name,
             format: _,
+            bool_as: _,
+            none_as: _,
+            clamp: _,
+            timestamp_property: _,
+            truncate: _,
         } = &field.attrs.kind
         {
             if sample_group.is_some() {
@@ -99,6 +213,90 @@ pub fn validate_value_impl_for_struct(
     Ok(())
 }
 
+/// The formatter of a plain field: its `format`, or the formatter of its `bool_as` or `none_as`,
+/// or `OmitZero` if the container has `omit_zeros` and none of these apply.
+///
+/// Fields without a `format` inherit the container's `bool_as` if they are declared as a `bool`,
+/// and its `none_as` if they are declared as an `Option` (and are neither `verbose` nor a
+/// `timestamp(property)`).
+pub(crate) fn field_format(field: &MetricsField, root_attrs: &RootAttributes) -> Option<syn::Path> {
+    let MetricsFieldKind::Field {
+        format,
+        bool_as,
+        none_as,
+        timestamp_property,
+        ..
+    } = &field.attrs.kind
+    else {
+        return None;
+    };
+    let bool_as = bool_as.or(root_attrs
+        .bool_as
+        .filter(|_| format.is_none() && is_bool_type(&field.ty)));
+    let none_as = none_as.or(root_attrs.none_as.filter(|_| {
+        format.is_none()
+            && timestamp_property.is_none()
+            && field.attrs.verbose.is_none()
+            && is_option_type(&field.ty)
+    }));
+    match (bool_as, none_as) {
+        (Some(BoolAs::Property), _) => {
+            Some(syn::parse_quote!(::metrique::writer::value::BoolAsProperty))
+        }
+        (_, Some(NoneAs::Zero)) => Some(syn::parse_quote!(::metrique::writer::value::NoneAsZero)),
+        (_, Some(NoneAs::Null)) => Some(syn::parse_quote!(::metrique::writer::value::NoneAsNull)),
+        _ if format.is_none() && root_attrs.omit_zeros => {
+            Some(syn::parse_quote!(::metrique::writer::value::OmitZero))
+        }
+        _ => format.clone(),
+    }
+}
+
+/// Returns true for `Option<_>`.
+///
+/// This is syntactic, so type aliases of `Option` are not recognized.
+fn is_option_type(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Paren(paren) => is_option_type(&paren.elem),
+        syn::Type::Path(path) if path.qself.is_none() => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Option"),
+        _ => false,
+    }
+}
+
+/// Returns true for `bool`, and for `bool` behind `Option`, `Box`, `Arc`, `Rc` or a reference.
+///
+/// This is syntactic, so type aliases of `bool` are not recognized.
+fn is_bool_type(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Reference(reference) => is_bool_type(&reference.elem),
+        syn::Type::Paren(paren) => is_bool_type(&paren.elem),
+        syn::Type::Path(path) if path.qself.is_none() => {
+            let Some(segment) = path.path.segments.last() else {
+                return false;
+            };
+            match &segment.arguments {
+                syn::PathArguments::None => segment.ident == "bool",
+                syn::PathArguments::AngleBracketed(args)
+                    if ["Option", "Box", "Arc", "Rc"]
+                        .iter()
+                        .any(|wrapper| segment.ident == wrapper) =>
+                {
+                    matches!(
+                        args.args.first(),
+                        Some(syn::GenericArgument::Type(inner)) if args.args.len() == 1 && is_bool_type(inner)
+                    )
+                }
+                _ => false,
+            }
+        }
+        _ => false,
+    }
+}
+
 pub(crate) fn format_value(format: &Option<syn::Path>, span: Span, field: Ts2) -> Ts2 {
     if let Some(format) = format {
         quote_spanned! { span=> &::metrique::format::FormattedValue::<_, #format, _>::new(#field)}
@@ -107,6 +305,22 @@ pub(crate) fn format_value(format: &Option<syn::Path>, span: Span, field: Ts2) -
     }
 }
 
+/// Wraps the (formatted) value of a `max_len` field in `Truncated`.
+pub(crate) fn truncate_value(field: &MetricsField, value: Ts2) -> Ts2 {
+    let MetricsFieldKind::Field {
+        truncate: Some(Truncate { max_len, marker }),
+        ..
+    } = &field.attrs.kind
+    else {
+        return value;
+    };
+    let span = field.span;
+    let marker = marker
+        .as_ref()
+        .map(|marker| quote_spanned! {span=> .with_marker(#marker) });
+    quote_spanned! {span=> &::metrique::writer::value::Truncated::new(#value, #max_len) #marker }
+}
+
 pub(crate) fn generate_value_impl_for_struct(
     root_attrs: &RootAttributes,
     value_name: &Ident,
@@ -127,15 +341,23 @@ pub(crate) fn generate_value_impl_for_struct(
             MetricsFieldKind::Field {
                 unit: _,
                 sample_group: _,
+                no_emit: _,
                 name: _,
-                format,
+                format: _,
+                bool_as: _,
+                none_as: _,
+                clamp: _,
+                timestamp_property: _,
+                truncate: _,
             } => {
                 let ident = &field.ident;
+                let format = field_format(field, root_attrs);
                 let value = format_value(
-                    format,
+                    &format,
                     field.span,
                     quote_spanned! {field.span=> &self.#ident },
                 );
+                let value = truncate_value(field, value);
                 let sample_group_impl = if root_attrs.sample_group {
                     // SampleGroup impl is only valid if there is a field
                     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
@@ -1,6 +1,8 @@
 use std::{collections::HashMap, hash::Hash, marker::PhantomData};
 
-use crate::{MetricAccumulatorEntry, MetricRecorder};
+use metrique_writer_core::Observation;
+
+use crate::{MetricAccumulatorEntry, MetricRecorder, mirror::MetricKind};
 
 mod private {
     pub trait Sealed {}
@@ -43,6 +45,13 @@ impl<M> MetricsRsVersion for YouMustSpecifyAMetricsRsVersion<M> {
         vec![]
     }
     fn set_global_recorder(_recorder: MetricRecorder<Self>) {}
+    fn record_mirrored(
+        _name: &str,
+        _labels: &[(String, String)],
+        _kind: MetricKind,
+        _observation: Observation,
+    ) {
+    }
 }
 #[diagnostic::do_not_recommend]
 impl<M> ParametricRecorder<YouMustSpecifyAMetricsRsVersion<M>>
@@ -81,15 +90,24 @@ pub trait MetricsRsVersion: 'static + private::Sealed {
     fn key_labels(key: &Self::Key) -> Vec<(&str, &str)>;
     #[doc(hidden)]
     fn set_global_recorder(recorder: MetricRecorder<Self>);
+    #[doc(hidden)]
+    fn record_mirrored(
+        name: &str,
+        labels: &[(String, String)],
+        kind: MetricKind,
+        observation: Observation,
+    );
 }
 
 #[cfg(feature = "metrics-rs-024")]
 mod impls {
     use std::{collections::HashMap, sync::atomic::Ordering};
 
+    use metrique_writer_core::Observation;
+
     use crate::{
         MetricAccumulatorEntry, MetricRecorder, MetricsRsVersion, ParametricRecorder,
-        accumulator::AtomicStorageWithHistogram,
+        accumulator::AtomicStorageWithHistogram, mirror::MetricKind,
     };
 
     impl MetricsRsVersion for dyn metrics_024::Recorder {
@@ -149,6 +167,51 @@ mod impls {
         fn set_global_recorder(recorder: MetricRecorder<Self>) {
             metrics_024::set_global_recorder(recorder).expect("failed to set global recorder");
         }
+        fn record_mirrored(
+            name: &str,
+            labels: &[(String, String)],
+            kind: MetricKind,
+            observation: Observation,
+        ) {
+            let name = name.to_owned();
+            let labels: Vec<metrics_024::Label> = labels
+                .iter()
+                .map(|(key, value)| metrics_024::Label::new(key.clone(), value.clone()))
+                .collect();
+            match kind {
+                MetricKind::Counter => {
+                    let value = match observation {
+                        Observation::Unsigned(value) => value,
+                        Observation::Floating(value) => value as u64,
+                        Observation::Repeated { total, .. } => total as u64,
+                        _ => return,
+                    };
+                    metrics_024::counter!(name, labels).increment(value);
+                }
+                MetricKind::Gauge => {
+                    let value = match observation {
+                        Observation::Unsigned(value) => value as f64,
+                        Observation::Floating(value) => value,
+                        Observation::Repeated { total, .. } => total,
+                        _ => return,
+                    };
+                    metrics_024::gauge!(name, labels).set(value);
+                }
+                MetricKind::Histogram => match observation {
+                    Observation::Unsigned(value) => {
+                        metrics_024::histogram!(name, labels).record(value as f64)
+                    }
+                    Observation::Floating(value) => {
+                        metrics_024::histogram!(name, labels).record(value)
+                    }
+                    Observation::Repeated { total, occurrences } if occurrences > 0 => {
+                        metrics_024::histogram!(name, labels)
+                            .record_many(total / occurrences as f64, occurrences as usize)
+                    }
+                    _ => {}
+                },
+            }
+        }
     }
 
     impl<R: metrics_024::Recorder> ParametricRecorder<dyn metrics_024::Recorder> for R {
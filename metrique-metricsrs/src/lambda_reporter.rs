@@ -339,11 +339,11 @@ pub async fn flush_metrics() -> Result<(), IoStreamError> {
 }
 
 #[cfg(test)]
+#[allow(clippy::unused_io_amount)] // these tests exercise `write` directly
 mod tests {
     use super::BufferingStdoutWriter;
     use metrique_writer_core::test_stream::TestSink;
     use std::cell::Cell;
-    use std::io::ErrorKind;
     use std::io::Write;
 
     fn check_buffering_stdout_writer(err_on_flush: bool) {
@@ -356,12 +356,12 @@ mod tests {
                 if self.err_on_flush {
                     Ok(buf.len())
                 } else {
-                    Err(std::io::Error::new(ErrorKind::Other, ""))
+                    Err(std::io::Error::other(""))
                 }
             }
 
             fn flush(&mut self) -> std::io::Result<()> {
-                Err(std::io::Error::new(ErrorKind::Other, ""))
+                Err(std::io::Error::other(""))
             }
         }
         let is_error = Cell::new(false);
@@ -10,6 +10,7 @@ pub mod capture;
 mod generic;
 pub mod lambda_reporter;
 pub mod metrics_histogram;
+pub mod mirror;
 mod reporter;
 mod unit;
 
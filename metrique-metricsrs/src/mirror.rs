@@ -0,0 +1,360 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Mirror the metrics of metrique entries into the installed [`metrics::Recorder`].
+//!
+//! This is the other direction of the metrics.rs bridge: rather than capturing metrics.rs
+//! metrics into metrique entries, [`MirrorSink`] records the numeric fields of every entry
+//! appended to it into metrics.rs, then forwards the entry to the sink it wraps. This keeps
+//! exporters driven by metrics.rs (like a Prometheus exporter) working while code migrates to
+//! metrique.
+//!
+//! Every numeric field is recorded under its name (as written, so after `rename_all` and
+//! prefixes) as a histogram by default, or as a counter or gauge if configured with
+//! [`MirrorSink::counter`] or [`MirrorSink::gauge`]. The per-value dimensions of a field, and the
+//! string fields configured with [`MirrorSink::label`], become labels. Other string fields are
+//! not mirrored.
+//!
+//! You must pass `dyn metrics::Recorder` as the first type parameter, to ensure metrics are
+//! recorded into the right metrics.rs version, for example:
+//!
+//! ```
+//! # use metrics_024 as metrics;
+//! use metrique_metricsrs::capture;
+//! use metrique_metricsrs::mirror::MirrorSink;
+//! use metrique_writer::{Entry, EntrySink, sink::VecEntrySink};
+//!
+//! #[derive(Entry)]
+//! #[entry(rename_all = "PascalCase")]
+//! struct RequestMetrics {
+//!     operation: &'static str,
+//!     requests: u64,
+//!     items: u64,
+//! }
+//!
+//! let inner = VecEntrySink::new();
+//! let sink = MirrorSink::<dyn metrics::Recorder, _>::new(inner.clone())
+//!     .counter("Requests")
+//!     .label("Operation");
+//!
+//! let (metrics, _) = capture::capture_metrics::<dyn metrics::Recorder, _, _>(|| {
+//!     sink.append(RequestMetrics { operation: "Get", requests: 1, items: 20 });
+//!     sink.append(RequestMetrics { operation: "Get", requests: 1, items: 5 });
+//! });
+//! assert_eq!(metrics.counter_value("Requests"), Some(2));
+//! assert_eq!(metrics.histogram_value("Items"), vec![5, 20]);
+//! // the entries are still written to the wrapped sink
+//! assert_eq!(inner.drain().len(), 2);
+//! ```
+//!
+//! [`metrics::Recorder`]: metrics_024::Recorder
+
+use std::{
+    borrow::Cow, collections::HashMap, fmt, marker::PhantomData, sync::Arc, time::SystemTime,
+};
+
+use metrique_writer::sink::{AppendWait, FlushWait, TryAppendError};
+use metrique_writer_core::{
+    Entry, EntryConfig, EntrySink, EntryWriter, MetricFlags, Observation, Unit, ValidationError,
+    Value, ValueWriter,
+};
+
+use crate::MetricsRsVersion;
+
+/// How a field is recorded into metrics.rs by a [`MirrorSink`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MetricKind {
+    /// Record every observation into a histogram. This is the default.
+    Histogram,
+    /// Increment a counter by the value of the field
+    Counter,
+    /// Set a gauge to the value of the field
+    Gauge,
+}
+
+/// An [`EntrySink`] that mirrors the numeric fields of entries into metrics.rs, see the
+/// [module docs](self).
+///
+/// Cloning a [`MirrorSink`] clones the wrapped sink and shares the configuration.
+pub struct MirrorSink<V: ?Sized, S> {
+    sink: S,
+    config: Arc<MirrorConfig>,
+    marker: PhantomData<fn(&V)>,
+}
+
+#[derive(Debug, Default, Clone)]
+struct MirrorConfig {
+    kinds: HashMap<Cow<'static, str>, MetricKind>,
+    labels: Vec<Cow<'static, str>>,
+}
+
+impl<V: MetricsRsVersion + ?Sized, S> MirrorSink<V, S> {
+    /// Wrap `sink`, mirroring the numeric fields of the entries appended to it as histograms
+    pub fn new(sink: S) -> Self {
+        Self {
+            sink,
+            config: Default::default(),
+            marker: PhantomData,
+        }
+    }
+
+    /// Record the field `name` as a counter, incremented by its value
+    pub fn counter(self, name: impl Into<Cow<'static, str>>) -> Self {
+        self.kind(name, MetricKind::Counter)
+    }
+
+    /// Record the field `name` as a gauge, set to its value
+    pub fn gauge(self, name: impl Into<Cow<'static, str>>) -> Self {
+        self.kind(name, MetricKind::Gauge)
+    }
+
+    /// Record the field `name` as `kind`
+    pub fn kind(mut self, name: impl Into<Cow<'static, str>>, kind: MetricKind) -> Self {
+        Arc::make_mut(&mut self.config)
+            .kinds
+            .insert(name.into(), kind);
+        self
+    }
+
+    /// Use the string field `name` as a label of every mirrored metric of the entry, for example
+    /// the name of the operation. Entries that don't have the field are mirrored without the label.
+    pub fn label(mut self, name: impl Into<Cow<'static, str>>) -> Self {
+        Arc::make_mut(&mut self.config).labels.push(name.into());
+        self
+    }
+
+    /// Return the wrapped sink
+    pub fn into_inner(self) -> S {
+        self.sink
+    }
+
+    fn mirror(&self, entry: &impl Entry) {
+        let mut writer = MirrorWriter {
+            config: &self.config,
+            labels: Vec::new(),
+            metrics: Vec::new(),
+        };
+        entry.write(&mut writer);
+        for metric in writer.metrics {
+            let kind = self
+                .config
+                .kinds
+                .get(metric.name.as_str())
+                .copied()
+                .unwrap_or(MetricKind::Histogram);
+            let mut labels = writer.labels.clone();
+            labels.extend(metric.dimensions);
+            for observation in metric.observations {
+                V::record_mirrored(&metric.name, &labels, kind, observation);
+            }
+        }
+    }
+}
+
+impl<V: ?Sized, S: Clone> Clone for MirrorSink<V, S> {
+    fn clone(&self) -> Self {
+        Self {
+            sink: self.sink.clone(),
+            config: Arc::clone(&self.config),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<V: ?Sized, S: fmt::Debug> fmt::Debug for MirrorSink<V, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MirrorSink")
+            .field("sink", &self.sink)
+            .field("kinds", &self.config.kinds)
+            .field("labels", &self.config.labels)
+            .finish()
+    }
+}
+
+impl<V: MetricsRsVersion + ?Sized, E: Entry, S: EntrySink<E>> EntrySink<E> for MirrorSink<V, S> {
+    fn append(&self, entry: E) {
+        self.mirror(&entry);
+        self.sink.append(entry);
+    }
+
+    fn try_append(&self, entry: E) -> Result<(), TryAppendError> {
+        self.mirror(&entry);
+        self.sink.try_append(entry)
+    }
+
+    fn append_async(&self, entry: E) -> AppendWait {
+        self.mirror(&entry);
+        self.sink.append_async(entry)
+    }
+
+    fn flush_async(&self) -> FlushWait {
+        self.sink.flush_async()
+    }
+}
+
+struct MirroredMetric {
+    name: String,
+    observations: Vec<Observation>,
+    dimensions: Vec<(String, String)>,
+}
+
+/// Collects the labels and numeric fields of an entry
+struct MirrorWriter<'c> {
+    config: &'c MirrorConfig,
+    labels: Vec<(String, String)>,
+    metrics: Vec<MirroredMetric>,
+}
+
+impl<'a> EntryWriter<'a> for MirrorWriter<'_> {
+    fn timestamp(&mut self, _timestamp: SystemTime) {}
+
+    fn value(&mut self, name: impl Into<Cow<'a, str>>, value: &(impl Value + ?Sized)) {
+        let name = name.into();
+        let is_label = self.config.labels.iter().any(|label| **label == *name);
+        value.write(MirrorValueWriter {
+            name: &name,
+            is_label,
+            writer: self,
+        });
+    }
+
+    fn config(&mut self, _config: &'a dyn EntryConfig) {}
+}
+
+struct MirrorValueWriter<'n, 'w, 'c> {
+    name: &'n str,
+    is_label: bool,
+    writer: &'w mut MirrorWriter<'c>,
+}
+
+impl ValueWriter for MirrorValueWriter<'_, '_, '_> {
+    fn string(self, value: &str) {
+        if self.is_label {
+            self.writer
+                .labels
+                .push((self.name.to_owned(), value.to_owned()));
+        }
+    }
+
+    fn metric<'a>(
+        self,
+        distribution: impl IntoIterator<Item = Observation>,
+        _unit: Unit,
+        dimensions: impl IntoIterator<Item = (&'a str, &'a str)>,
+        _flags: MetricFlags<'_>,
+    ) {
+        self.writer.metrics.push(MirroredMetric {
+            name: self.name.to_owned(),
+            observations: distribution.into_iter().collect(),
+            dimensions: dimensions
+                .into_iter()
+                .map(|(key, value)| (key.to_owned(), value.to_owned()))
+                .collect(),
+        });
+    }
+
+    // invalid values are reported by the wrapped sink
+    fn error(self, _error: ValidationError) {}
+}
+
+#[cfg(test)]
+mod test {
+    use metrique_writer::sink::VecEntrySink;
+    use metrique_writer_core::{
+        Entry, EntrySink, EntryWriter, MetricFlags, Observation, Unit, Value, ValueWriter,
+    };
+
+    use super::MirrorSink;
+    use crate::{MetricsRsVersion, capture::capture_metrics};
+
+    type Recorder = dyn metrics_024::Recorder;
+
+    struct Request {
+        operation: Option<&'static str>,
+        latency: Observation,
+        in_flight: u64,
+    }
+
+    impl Entry for Request {
+        fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+            writer.value("Latency", &self.latency);
+            writer.value("InFlight", &self.in_flight);
+            writer.value("Bytes", &WithHost(512));
+            if let Some(operation) = self.operation {
+                writer.value("Operation", operation);
+            }
+            writer.value("RequestId", "abc");
+        }
+    }
+
+    struct WithHost(u64);
+
+    impl Value for WithHost {
+        fn write(&self, writer: impl ValueWriter) {
+            writer.metric(
+                [Observation::Unsigned(self.0)],
+                Unit::None,
+                [("Host", "h1")],
+                MetricFlags::empty(),
+            );
+        }
+    }
+
+    #[test]
+    fn mirrors_with_labels_and_kinds() {
+        let inner = VecEntrySink::new();
+        let sink = MirrorSink::<Recorder, _>::new(inner.clone())
+            .gauge("InFlight")
+            .label("Operation");
+        let (metrics, _) = capture_metrics::<Recorder, _, _>(|| {
+            sink.append(Request {
+                operation: Some("Get"),
+                latency: Observation::Repeated {
+                    total: 30.0,
+                    occurrences: 3,
+                },
+                in_flight: 4,
+            });
+            sink.append(Request {
+                operation: None,
+                latency: Observation::Floating(7.0),
+                in_flight: 2,
+            });
+        });
+        assert_eq!(inner.drain().len(), 2);
+
+        assert_eq!(metrics.histogram_value("Latency"), vec![7, 10, 10, 10]);
+        let labels = |name: &str| -> Vec<Vec<(String, String)>> {
+            let mut labels: Vec<Vec<(String, String)>> = metrics
+                .histograms
+                .iter()
+                .filter(|(key, _)| Recorder::key_name(key) == name)
+                .map(|(key, _)| {
+                    Recorder::key_labels(key)
+                        .into_iter()
+                        .map(|(key, value)| (key.to_owned(), value.to_owned()))
+                        .collect()
+                })
+                .collect();
+            labels.sort();
+            labels
+        };
+        let get = ("Operation".to_owned(), "Get".to_owned());
+        let host = ("Host".to_owned(), "h1".to_owned());
+        assert_eq!(labels("Latency"), vec![vec![], vec![get.clone()]]);
+        assert_eq!(labels("Bytes"), vec![vec![host.clone()], vec![get, host]]);
+        // string fields that are not labels are not mirrored
+        assert!(labels("RequestId").is_empty());
+
+        // one gauge per label set
+        let mut gauges: Vec<f64> = metrics
+            .gauges
+            .iter()
+            .filter(|(key, _)| Recorder::key_name(key) == "InFlight")
+            .map(|(_, value)| *value)
+            .collect();
+        gauges.sort_by(f64::total_cmp);
+        assert_eq!(gauges, vec![2.0, 4.0]);
+    }
+}
@@ -584,13 +584,10 @@ mod test {
         assert_eq!(inspector.entries().len(), 2);
         assert_eq!(entries[1].metrics["counter_2"], 1);
         // counter_1 is 0
-        assert_eq!(entries[1].metrics.contains_key("counter_1"), false);
+        assert!(!entries[1].metrics.contains_key("counter_1"));
         reporter.shutdown().await;
-        assert_eq!(shutdown.load(std::sync::atomic::Ordering::Relaxed), true);
-        assert_eq!(
-            async_shutdown.load(std::sync::atomic::Ordering::Relaxed),
-            false
-        );
+        assert!(shutdown.load(std::sync::atomic::Ordering::Relaxed));
+        assert!(!async_shutdown.load(std::sync::atomic::Ordering::Relaxed));
     }
 
     #[tokio::test(start_paused = true)]
@@ -602,17 +599,11 @@ mod test {
             .metrics_sink_async_shutdown(sink, async move { shutdown_hook.shutdown().await })
             .metrics_rs_version::<dyn metrics_024::Recorder>();
         let (reporter, _recorder) = MetricReporter::new(builder);
-        assert_eq!(shutdown.load(std::sync::atomic::Ordering::Relaxed), false);
-        assert_eq!(
-            async_shutdown.load(std::sync::atomic::Ordering::Relaxed),
-            false
-        );
+        assert!(!shutdown.load(std::sync::atomic::Ordering::Relaxed));
+        assert!(!async_shutdown.load(std::sync::atomic::Ordering::Relaxed));
         reporter.shutdown().await;
         // this isn't done explicitly, just a side effect of dropping the handle.
-        assert_eq!(shutdown.load(std::sync::atomic::Ordering::Relaxed), true);
-        assert_eq!(
-            async_shutdown.load(std::sync::atomic::Ordering::Relaxed),
-            true
-        );
+        assert!(shutdown.load(std::sync::atomic::Ordering::Relaxed));
+        assert!(async_shutdown.load(std::sync::atomic::Ordering::Relaxed));
     }
 }
@@ -0,0 +1,467 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Contains [`ServiceMetricsBuilder`], which attaches an EMF destination to [`ServiceMetrics`].
+
+use std::{borrow::Cow, io, path::PathBuf, time::Duration};
+
+use metrique_writer::{
+    EntryIoStream, FormatExt,
+    sink::{AttachGlobalEntrySink, AttachHandle, BackgroundQueueBuilder, IoErrorPolicy},
+};
+use metrique_writer_format_emf::Emf;
+use smallvec::SmallVec;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+
+use crate::ServiceMetrics;
+
+/// Environment variable overriding the EMF namespace used by [`ServiceMetricsBuilder`] presets.
+pub const NAMESPACE_ENV: &str = "METRIQUE_NAMESPACE";
+
+/// Environment variable containing global dimensions for [`ServiceMetricsBuilder`] presets,
+/// as a comma-separated list of `Name=Value` pairs (e.g. `Stage=prod,Cell=3`).
+///
+/// Malformed pairs (missing a `=` or with an empty name) are ignored.
+pub const DIMENSIONS_ENV: &str = "METRIQUE_DIMENSIONS";
+
+/// Environment variable that, when set, makes [`ServiceMetricsBuilder`] presets write to hourly
+/// rotated files in the given directory instead of to stdout.
+pub const LOG_DIR_ENV: &str = "METRIQUE_LOG_DIR";
+
+/// Environment variable overriding the number of entries the background queue of
+/// [`ServiceMetricsBuilder`] presets can hold.
+///
+/// Values that are not a positive integer are ignored.
+pub const QUEUE_CAPACITY_ENV: &str = "METRIQUE_QUEUE_CAPACITY";
+
+/// Environment variable overriding how often, in milliseconds, [`ServiceMetricsBuilder`] presets
+/// flush the destination.
+///
+/// Values that are not a positive integer are ignored.
+pub const FLUSH_INTERVAL_MS_ENV: &str = "METRIQUE_FLUSH_INTERVAL_MS";
+
+const DEFAULT_NAMESPACE: &str = "ServiceMetrics";
+const LOG_FILE_PREFIX: &str = "service_log.log";
+
+/// Where [`ServiceMetricsBuilder`] writes formatted entries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetricsDestination(DestinationKind);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum DestinationKind {
+    Stdout,
+    RollingFile {
+        directory: PathBuf,
+        file_name_prefix: String,
+    },
+}
+
+impl MetricsDestination {
+    /// Write entries to stdout. This is the destination picked up by the CloudWatch agent
+    /// in Lambda, and by container log drivers in ECS and EKS.
+    pub fn stdout() -> Self {
+        Self(DestinationKind::Stdout)
+    }
+
+    /// Write entries to files in `directory` that are rotated hourly.
+    pub fn rolling_file(
+        directory: impl Into<PathBuf>,
+        file_name_prefix: impl Into<String>,
+    ) -> Self {
+        Self(DestinationKind::RollingFile {
+            directory: directory.into(),
+            file_name_prefix: file_name_prefix.into(),
+        })
+    }
+}
+
+/// Builder that attaches an EMF destination to [`ServiceMetrics`].
+///
+/// Most applications should start from one of the presets ([`lambda`], [`ecs`], [`eks`]
+/// or [`local_dev`]), which pick queue sizing and flush intervals suited to the environment
+/// and read the following environment variables:
+///
+/// - [`METRIQUE_NAMESPACE`](NAMESPACE_ENV): the EMF namespace.
+/// - [`METRIQUE_DIMENSIONS`](DIMENSIONS_ENV): global dimensions added to every metric.
+/// - [`METRIQUE_LOG_DIR`](LOG_DIR_ENV): write to rotated files in this directory instead of stdout.
+/// - [`METRIQUE_QUEUE_CAPACITY`](QUEUE_CAPACITY_ENV): the number of entries the queue can hold.
+/// - [`METRIQUE_FLUSH_INTERVAL_MS`](FLUSH_INTERVAL_MS_ENV): how often the destination is flushed.
+///
+/// Any setting can then be overridden by calling the corresponding builder method.
+///
+/// ## Example
+///
+/// ```rust
+/// use metrique::unit_of_work::metrics;
+/// use metrique::ServiceMetrics;
+/// use metrique_service_metrics::ServiceMetricsBuilder;
+/// use metrique_writer::GlobalEntrySink;
+///
+/// #[metrics(rename_all = "PascalCase")]
+/// struct RequestMetrics {
+///     number_of_ducks: usize,
+/// }
+///
+/// let _handle = ServiceMetricsBuilder::local_dev()
+///     .namespace("DuckService")
+///     .global_dimension("Stage", "dev")
+///     .init();
+///
+/// let mut metrics = RequestMetrics { number_of_ducks: 0 }.append_on_drop(ServiceMetrics::sink());
+/// metrics.number_of_ducks = 5;
+/// ```
+///
+/// [`lambda`]: ServiceMetricsBuilder::lambda
+/// [`ecs`]: ServiceMetricsBuilder::ecs
+/// [`eks`]: ServiceMetricsBuilder::eks
+/// [`local_dev`]: ServiceMetricsBuilder::local_dev
+#[derive(Debug, Clone)]
+pub struct ServiceMetricsBuilder {
+    destination: MetricsDestination,
+    namespace: String,
+    dimension_sets: Vec<Vec<String>>,
+    global_dimensions: Vec<(String, String)>,
+    queue_capacity: usize,
+    flush_interval: Duration,
+    max_batch_entries: Option<usize>,
+    max_batch_bytes: Option<u64>,
+    io_error_policy: IoErrorPolicy,
+    shutdown_timeout: Duration,
+}
+
+impl Default for ServiceMetricsBuilder {
+    fn default() -> Self {
+        Self {
+            destination: MetricsDestination::stdout(),
+            namespace: DEFAULT_NAMESPACE.into(),
+            dimension_sets: vec![vec![]],
+            global_dimensions: vec![],
+            queue_capacity: 64 * 1024,
+            flush_interval: Duration::from_secs(1),
+            max_batch_entries: None,
+            max_batch_bytes: None,
+            io_error_policy: IoErrorPolicy::Drop,
+            shutdown_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+impl ServiceMetricsBuilder {
+    /// Create a new [`ServiceMetricsBuilder`] writing to stdout with the default configuration.
+    ///
+    /// Unlike the presets, this does not read any environment variables.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Preset for AWS Lambda.
+    ///
+    /// Writes to stdout with a small queue and a short flush interval, since the execution
+    /// environment can be frozen soon after an invocation returns. The namespace defaults to
+    /// the function name (`AWS_LAMBDA_FUNCTION_NAME`).
+    pub fn lambda() -> Self {
+        Self::lambda_from(&env_var)
+    }
+
+    /// Preset for Amazon ECS.
+    ///
+    /// Writes to stdout, which is collected by the container log driver, with the default
+    /// queue sizing.
+    pub fn ecs() -> Self {
+        Self::container_from(&env_var)
+    }
+
+    /// Preset for Amazon EKS.
+    ///
+    /// Writes to stdout, which is collected by the cluster log agent, with the default
+    /// queue sizing.
+    pub fn eks() -> Self {
+        Self::container_from(&env_var)
+    }
+
+    /// Preset for local development.
+    ///
+    /// Writes to stdout with a small queue and a short flush interval so that entries show up
+    /// quickly, and shuts down quickly.
+    pub fn local_dev() -> Self {
+        Self::local_dev_from(&env_var)
+    }
+
+    fn lambda_from(env: &dyn Fn(&str) -> Option<String>) -> Self {
+        let mut builder = Self {
+            queue_capacity: 1024,
+            flush_interval: Duration::from_millis(100),
+            shutdown_timeout: Duration::from_secs(2),
+            ..Self::default()
+        };
+        if let Some(function_name) = env("AWS_LAMBDA_FUNCTION_NAME") {
+            builder.namespace = function_name;
+        }
+        builder.apply_env(env)
+    }
+
+    /// The ECS and EKS presets, which both write to stdout with the default queue sizing
+    fn container_from(env: &dyn Fn(&str) -> Option<String>) -> Self {
+        Self::default().apply_env(env)
+    }
+
+    fn local_dev_from(env: &dyn Fn(&str) -> Option<String>) -> Self {
+        Self {
+            queue_capacity: 1024,
+            flush_interval: Duration::from_millis(100),
+            shutdown_timeout: Duration::from_secs(1),
+            ..Self::default()
+        }
+        .apply_env(env)
+    }
+
+    fn apply_env(mut self, env: &dyn Fn(&str) -> Option<String>) -> Self {
+        if let Some(namespace) = env(NAMESPACE_ENV).filter(|ns| !ns.is_empty()) {
+            self.namespace = namespace;
+        }
+        if let Some(dimensions) = env(DIMENSIONS_ENV) {
+            self.global_dimensions.extend(parse_dimensions(&dimensions));
+        }
+        if let Some(directory) = env(LOG_DIR_ENV).filter(|dir| !dir.is_empty()) {
+            self.destination = MetricsDestination::rolling_file(directory, LOG_FILE_PREFIX);
+        }
+        let capacity = env(QUEUE_CAPACITY_ENV).and_then(|capacity| capacity.trim().parse().ok());
+        if let Some(capacity) = capacity.filter(|&capacity| capacity > 0) {
+            self.queue_capacity = capacity;
+        }
+        let flush_interval_ms = env(FLUSH_INTERVAL_MS_ENV).and_then(|ms| ms.trim().parse().ok());
+        if let Some(flush_interval_ms) = flush_interval_ms.filter(|&ms| ms > 0) {
+            self.flush_interval = Duration::from_millis(flush_interval_ms);
+        }
+        self
+    }
+
+    /// Sets the destination entries are written to.
+    pub fn destination(mut self, destination: MetricsDestination) -> Self {
+        self.destination = destination;
+        self
+    }
+
+    /// Sets the EMF namespace.
+    pub fn namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = namespace.into();
+        self
+    }
+
+    /// Sets the default EMF dimension sets. Defaults to a single empty dimension set.
+    ///
+    /// See [`Emf::builder`] for details.
+    pub fn dimension_sets(mut self, dimension_sets: Vec<Vec<String>>) -> Self {
+        self.dimension_sets = dimension_sets;
+        self
+    }
+
+    /// Adds a dimension that is added to every metric of every entry.
+    pub fn global_dimension(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.global_dimensions.push((name.into(), value.into()));
+        self
+    }
+
+    /// Sets the number of entries the background queue can hold.
+    ///
+    /// See [`BackgroundQueueBuilder::capacity`].
+    pub fn queue_capacity(mut self, capacity: usize) -> Self {
+        assert!(capacity > 0);
+        self.queue_capacity = capacity;
+        self
+    }
+
+    /// Sets approximately how frequently the destination is flushed.
+    ///
+    /// See [`BackgroundQueueBuilder::flush_interval`].
+    pub fn flush_interval(mut self, flush_interval: Duration) -> Self {
+        self.flush_interval = flush_interval;
+        self
+    }
+
+    /// Flushes the destination as soon as `max_entries` entries were written since the last flush.
+    ///
+    /// See [`BackgroundQueueBuilder::max_batch_entries`].
+    pub fn max_batch_entries(mut self, max_entries: usize) -> Self {
+        self.max_batch_entries = Some(max_entries);
+        self
+    }
+
+    /// Flushes the destination as soon as `max_bytes` bytes were written since the last flush.
+    ///
+    /// See [`BackgroundQueueBuilder::max_batch_bytes`].
+    pub fn max_batch_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_batch_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Sets what happens when writing to the destination fails with an IO error.
+    ///
+    /// See [`BackgroundQueueBuilder::io_error_policy`].
+    pub fn io_error_policy(mut self, policy: IoErrorPolicy) -> Self {
+        self.io_error_policy = policy;
+        self
+    }
+
+    /// Sets how long the background thread will try to drain remaining entries when shutting down.
+    ///
+    /// See [`BackgroundQueueBuilder::shutdown_timeout`].
+    pub fn shutdown_timeout(mut self, shutdown_timeout: Duration) -> Self {
+        self.shutdown_timeout = shutdown_timeout;
+        self
+    }
+
+    /// Attach the configured destination to [`ServiceMetrics`].
+    ///
+    /// When the returned [`AttachHandle`] is dropped, remaining entries are flushed and the
+    /// destination is detached.
+    ///
+    /// # Panics
+    /// Panics if a sink is already attached to [`ServiceMetrics`].
+    pub fn init(self) -> AttachHandle {
+        let global_dimensions: SmallVec<[(Cow<'static, str>, Cow<'static, str>); 4]> = self
+            .global_dimensions
+            .into_iter()
+            .map(|(name, value)| (name.into(), value.into()))
+            .collect();
+        let format = Emf::builder(self.namespace, self.dimension_sets)
+            .build()
+            .merge_global_dimensions(global_dimensions, None);
+        let mut queue = BackgroundQueueBuilder::new()
+            .thread_name("service-metrics")
+            .capacity(self.queue_capacity)
+            .flush_interval(self.flush_interval)
+            .io_error_policy(self.io_error_policy)
+            .shutdown_timeout(self.shutdown_timeout);
+        if let Some(max_entries) = self.max_batch_entries {
+            queue = queue.max_batch_entries(max_entries);
+        }
+        if let Some(max_bytes) = self.max_batch_bytes {
+            queue = queue.max_batch_bytes(max_bytes);
+        }
+
+        match self.destination.0 {
+            DestinationKind::Stdout => attach(queue, format.output_to(io::stdout())),
+            DestinationKind::RollingFile {
+                directory,
+                file_name_prefix,
+            } => attach(
+                queue,
+                format.output_to(RollingFileAppender::new(
+                    Rotation::HOURLY,
+                    directory,
+                    file_name_prefix,
+                )),
+            ),
+        }
+    }
+}
+
+fn attach(
+    queue: BackgroundQueueBuilder,
+    stream: impl EntryIoStream + Send + 'static,
+) -> AttachHandle {
+    ServiceMetrics::attach(queue.build_boxed(stream))
+}
+
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+fn parse_dimensions(dimensions: &str) -> impl Iterator<Item = (String, String)> + '_ {
+    dimensions.split(',').filter_map(|pair| {
+        let (name, value) = pair.split_once('=')?;
+        let name = name.trim();
+        (!name.is_empty()).then(|| (name.to_string(), value.trim().to_string()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, time::Duration};
+
+    use super::{MetricsDestination, ServiceMetricsBuilder};
+
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    #[test]
+    fn lambda_uses_function_name_as_namespace() {
+        let builder = ServiceMetricsBuilder::lambda_from(&env(&[(
+            "AWS_LAMBDA_FUNCTION_NAME",
+            "my-function",
+        )]));
+        assert_eq!(builder.namespace, "my-function");
+        assert_eq!(builder.destination, MetricsDestination::stdout());
+        assert_eq!(builder.queue_capacity, 1024);
+        assert_eq!(builder.flush_interval, Duration::from_millis(100));
+    }
+
+    #[test]
+    fn env_overrides_preset_defaults() {
+        let builder = ServiceMetricsBuilder::lambda_from(&env(&[
+            ("AWS_LAMBDA_FUNCTION_NAME", "my-function"),
+            ("METRIQUE_NAMESPACE", "MyNamespace"),
+            (
+                "METRIQUE_DIMENSIONS",
+                "Stage=prod, Cell = 3,malformed,=empty",
+            ),
+            ("METRIQUE_LOG_DIR", "/var/log/metrics"),
+            ("METRIQUE_QUEUE_CAPACITY", "4096"),
+            ("METRIQUE_FLUSH_INTERVAL_MS", " 250 "),
+        ]));
+        assert_eq!(builder.namespace, "MyNamespace");
+        assert_eq!(builder.queue_capacity, 4096);
+        assert_eq!(builder.flush_interval, Duration::from_millis(250));
+        assert_eq!(
+            builder.global_dimensions,
+            vec![
+                ("Stage".to_string(), "prod".to_string()),
+                ("Cell".to_string(), "3".to_string()),
+            ]
+        );
+        assert_eq!(
+            builder.destination,
+            MetricsDestination::rolling_file("/var/log/metrics", "service_log.log")
+        );
+    }
+
+    #[test]
+    fn malformed_queue_settings_are_ignored() {
+        let builder = ServiceMetricsBuilder::container_from(&env(&[
+            ("METRIQUE_QUEUE_CAPACITY", "0"),
+            ("METRIQUE_FLUSH_INTERVAL_MS", "1s"),
+        ]));
+        assert_eq!(builder.queue_capacity, 64 * 1024);
+        assert_eq!(builder.flush_interval, Duration::from_secs(1));
+
+        // a zero flush interval would flush continuously, the preset default is kept
+        let builder =
+            ServiceMetricsBuilder::lambda_from(&env(&[("METRIQUE_FLUSH_INTERVAL_MS", "0")]));
+        assert_eq!(builder.flush_interval, Duration::from_millis(100));
+    }
+
+    #[test]
+    fn presets_without_env() {
+        let builder = ServiceMetricsBuilder::container_from(&env(&[]));
+        assert_eq!(builder.namespace, "ServiceMetrics");
+        assert_eq!(builder.destination, MetricsDestination::stdout());
+        assert_eq!(builder.queue_capacity, 64 * 1024);
+        assert!(builder.global_dimensions.is_empty());
+
+        let builder = ServiceMetricsBuilder::local_dev_from(&env(&[]))
+            .namespace("Explicit")
+            .global_dimension("Stage", "dev");
+        assert_eq!(builder.namespace, "Explicit");
+        assert_eq!(builder.flush_interval, Duration::from_millis(100));
+        assert_eq!(
+            builder.global_dimensions,
+            vec![("Stage".to_string(), "dev".to_string())]
+        );
+    }
+}
@@ -4,6 +4,15 @@
 
 use metrique_writer::sink::global_entry_sink;
 
+#[cfg(feature = "emf")]
+mod builder;
+
+#[cfg(feature = "emf")]
+pub use builder::{
+    DIMENSIONS_ENV, FLUSH_INTERVAL_MS_ENV, LOG_DIR_ENV, MetricsDestination, NAMESPACE_ENV,
+    QUEUE_CAPACITY_ENV, ServiceMetricsBuilder,
+};
+
 global_entry_sink! {
     /// A global metric sink that can be used for application-wide
     /// metrics.
@@ -59,5 +68,9 @@ global_entry_sink! {
     /// let mut metrics = RequestMetrics::init();
     /// metrics.number_of_ducks = 5;
     /// ```
+    ///
+    /// With the `emf` feature, `ServiceMetricsBuilder` provides presets for common
+    /// environments (Lambda, ECS, EKS, local development) that replace the
+    /// initialization function above.
     ServiceMetrics
 }
@@ -0,0 +1,149 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    sync::{
+        Arc, OnceLock, Weak,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant as StdInstant, SystemTime as StdSystemTime},
+};
+
+use crate::{Time, TimeSource};
+
+/// The default resolution of [`TimeSource::coarse`]
+pub const DEFAULT_RESOLUTION: Duration = Duration::from_millis(1);
+
+/// A cached clock, which is updated by a background thread every `resolution`
+///
+/// Reading the time from a `CoarseTime` is a single atomic load, which is cheaper than reading
+/// the system clock. This is useful for workloads that read the time so often (for example,
+/// several timers per request) that reading the clock shows up in profiles. In exchange, times
+/// are only accurate to within `resolution`, so a `CoarseTime` is not suitable for measuring
+/// durations shorter than a few multiples of `resolution`.
+///
+/// The background thread stops shortly after the last clone of the [`TimeSource`] holding the
+/// `CoarseTime` is dropped.
+///
+/// This requires that the `coarse` feature be enabled.
+///
+/// ```
+/// use std::time::Duration;
+/// use metrique_timesource::{TimeSource, coarse::CoarseTime};
+///
+/// let ts = TimeSource::custom(CoarseTime::new(Duration::from_millis(5)));
+/// let start = ts.instant();
+/// let elapsed = start.elapsed();
+/// ```
+#[derive(Debug, Clone)]
+pub struct CoarseTime {
+    clock: Arc<CachedClock>,
+}
+
+#[derive(Debug)]
+struct CachedClock {
+    base_instant: StdInstant,
+    base_system_time: StdSystemTime,
+    // nanoseconds since `base_instant`, as of the last update
+    offset_nanos: AtomicU64,
+}
+
+impl CachedClock {
+    fn update(&self) {
+        let offset = u64::try_from(self.base_instant.elapsed().as_nanos()).unwrap_or(u64::MAX);
+        // `fetch_max`, so the clock never goes backwards
+        self.offset_nanos.fetch_max(offset, Ordering::Relaxed);
+    }
+
+    fn offset(&self) -> Duration {
+        Duration::from_nanos(self.offset_nanos.load(Ordering::Relaxed))
+    }
+}
+
+impl CoarseTime {
+    /// Create a new cached clock that is updated every `resolution`, spawning its background thread
+    pub fn new(resolution: Duration) -> Self {
+        let clock = Arc::new(CachedClock {
+            base_instant: StdInstant::now(),
+            base_system_time: StdSystemTime::now(),
+            offset_nanos: AtomicU64::new(0),
+        });
+        let weak = Arc::downgrade(&clock);
+        std::thread::Builder::new()
+            .name("metrique-coarse-clock".into())
+            .spawn(move || upkeep(weak, resolution))
+            .expect("failed to spawn the coarse clock thread");
+        Self { clock }
+    }
+}
+
+fn upkeep(clock: Weak<CachedClock>, resolution: Duration) {
+    while let Some(clock) = clock.upgrade() {
+        clock.update();
+        drop(clock);
+        std::thread::sleep(resolution);
+    }
+}
+
+impl Time for CoarseTime {
+    fn now(&self) -> StdSystemTime {
+        self.clock.base_system_time + self.clock.offset()
+    }
+
+    fn instant(&self) -> StdInstant {
+        self.clock.base_instant + self.clock.offset()
+    }
+}
+
+impl TimeSource {
+    /// A process-wide [`CoarseTime`] time source, with a resolution of [`DEFAULT_RESOLUTION`]
+    ///
+    /// The background thread of the shared clock is spawned on the first call, and runs until the
+    /// process exits.
+    ///
+    /// This requires that the `coarse` feature be enabled.
+    ///
+    /// ```
+    /// use metrique_timesource::TimeSource;
+    ///
+    /// let start = TimeSource::coarse().instant();
+    /// // elapsed is measured with the coarse clock too
+    /// let elapsed = start.elapsed();
+    /// ```
+    pub fn coarse() -> Self {
+        static COARSE: OnceLock<TimeSource> = OnceLock::new();
+        COARSE
+            .get_or_init(|| TimeSource::custom(CoarseTime::new(DEFAULT_RESOLUTION)))
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coarse_clock_advances() {
+        let ts = TimeSource::custom(CoarseTime::new(Duration::from_millis(1)));
+        let start = ts.instant();
+        let start_time = ts.system_time();
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(start.elapsed() >= Duration::from_millis(40));
+        assert!(ts.system_time().duration_since(start_time).unwrap() >= Duration::from_millis(40));
+    }
+
+    #[test]
+    fn upkeep_thread_stops_with_clock() {
+        let time = CoarseTime::new(Duration::from_millis(1));
+        let weak = Arc::downgrade(&time.clock);
+        drop(time);
+        // the thread only holds a strong reference while updating
+        for _ in 0..100 {
+            if weak.strong_count() == 0 {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        panic!("the clock was not dropped");
+    }
+}
@@ -19,10 +19,36 @@ use std::{
 #[cfg(feature = "test-util")]
 pub mod fakes;
 
+/// A cached, low-overhead time source
+///
+/// To enable this module, you must enable the `coarse` feature.
+#[cfg(feature = "coarse")]
+pub mod coarse;
+
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+mod wasm;
+
+#[cfg(not(all(feature = "wasm", target_arch = "wasm32")))]
+use std::time::Instant as RawInstant;
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+use wasm::Instant as RawInstant;
+
+#[cfg(not(all(feature = "wasm", target_arch = "wasm32")))]
+fn system_time_now() -> StdSystemTime {
+    StdSystemTime::now()
+}
+
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+use wasm::system_time_now;
+
 /// Trait for providing custom time sources
 ///
 /// Implementors of this trait can be used to provide custom time behavior
 /// for testing or specialized use cases.
+///
+/// With the `wasm` feature on `wasm32` targets, where a `std::time::Instant` can't be created,
+/// [`Instant`]s of custom time sources are derived from [`Time::now`] and [`Time::instant`] is
+/// not called.
 pub trait Time: Send + Sync + Debug {
     /// Get the current system time
     fn now(&self) -> StdSystemTime;
@@ -424,7 +450,7 @@ impl TimeSource {
     /// ```
     pub fn system_time(&self) -> SystemTime {
         match self {
-            Self::System => SystemTime::new(StdSystemTime::now(), self),
+            Self::System => SystemTime::new(system_time_now(), self),
             #[cfg(feature = "custom-timesource")]
             Self::Custom(ts) => SystemTime::new(ts.now(), self),
         }
@@ -449,9 +475,17 @@ impl TimeSource {
     /// ```
     pub fn instant(&self) -> Instant {
         match self {
-            Self::System => Instant::new(StdInstant::now(), self),
-            #[cfg(feature = "custom-timesource")]
+            Self::System => Instant::new(RawInstant::now(), self),
+            #[cfg(all(
+                feature = "custom-timesource",
+                not(all(feature = "wasm", target_arch = "wasm32"))
+            ))]
             Self::Custom(ts) => Instant::new(ts.instant(), self),
+            #[cfg(all(
+                feature = "custom-timesource",
+                all(feature = "wasm", target_arch = "wasm32")
+            ))]
+            Self::Custom(ts) => Instant::new(RawInstant::from_system_time(ts.now()), self),
         }
     }
 
@@ -598,14 +632,18 @@ pub fn time_source() -> TimeSource {
 ///
 /// When `custom-timesource` is not enabled, this is exactly the same size as `Instant`. When `custom-timesource` _is_ enabled, it retains a pointer
 /// to the timesource it came from to allow `elapsed()` to work properly.
+///
+/// With the `wasm` feature on `wasm32` targets, this is read from `performance.now()` and can't be
+/// converted into a `std::time::Instant`.
 #[derive(Clone)]
 #[cfg_attr(not(feature = "custom-timesource"), derive(Copy), repr(transparent))]
 pub struct Instant {
-    value: StdInstant,
+    value: RawInstant,
     #[cfg(feature = "custom-timesource")]
     time_source: TimeSource,
 }
 
+#[cfg(not(all(feature = "wasm", target_arch = "wasm32")))]
 impl From<Instant> for StdInstant {
     fn from(instant: Instant) -> std::time::Instant {
         instant.as_std()
@@ -666,7 +704,24 @@ impl Instant {
         #[cfg(feature = "custom-timesource")]
         let ts = &self.time_source;
 
-        ts.instant().as_std() - self.value
+        ts.instant().value - self.value
+    }
+
+    /// Returns the amount of time elapsed from `earlier` to this instant, or zero if `earlier`
+    /// is later than this instant
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use metrique_timesource::time_source;
+    ///
+    /// let ts = time_source();
+    /// let start = ts.instant();
+    /// let end = ts.instant();
+    /// assert_eq!(start.saturating_duration_since(&end).as_nanos(), 0);
+    /// ```
+    pub fn saturating_duration_since(&self, earlier: &Instant) -> Duration {
+        self.value.saturating_duration_since(earlier.value)
     }
 
     /// Convert this Instant to a std::time::Instant
@@ -679,11 +734,12 @@ impl Instant {
     ///
     /// After conversion, elapsed() will no longer respect custom time sources
     /// if they were being used.
+    #[cfg(not(all(feature = "wasm", target_arch = "wasm32")))]
     pub fn as_std(&self) -> StdInstant {
         self.value
     }
 
-    fn new(std: StdInstant, ts: &TimeSource) -> Self {
+    fn new(std: RawInstant, ts: &TimeSource) -> Self {
         #[cfg(not(feature = "custom-timesource"))]
         let _ = ts;
         Self {
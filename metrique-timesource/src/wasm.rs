@@ -0,0 +1,55 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! The system clock on `wasm32` targets, where `std::time::Instant::now()` and
+//! `std::time::SystemTime::now()` panic.
+
+use std::{
+    ops::Sub,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use wasm_bindgen::prelude::wasm_bindgen;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = performance, js_name = now)]
+    fn performance_now() -> f64;
+}
+
+/// A monotonic instant, stored as the time since the time origin of the JS context
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) struct Instant(Duration);
+
+impl Instant {
+    pub(crate) fn now() -> Self {
+        Self(from_millis(performance_now()))
+    }
+
+    /// Instants of custom time sources are derived from their system time, since there is no way
+    /// to create a `std::time::Instant` on `wasm32`.
+    #[cfg(feature = "custom-timesource")]
+    pub(crate) fn from_system_time(time: SystemTime) -> Self {
+        Self(time.duration_since(UNIX_EPOCH).unwrap_or_default())
+    }
+
+    pub(crate) fn saturating_duration_since(&self, earlier: Self) -> Duration {
+        self.0.saturating_sub(earlier.0)
+    }
+}
+
+impl Sub for Instant {
+    type Output = Duration;
+
+    fn sub(self, rhs: Self) -> Duration {
+        self.saturating_duration_since(rhs)
+    }
+}
+
+pub(crate) fn system_time_now() -> SystemTime {
+    UNIX_EPOCH + from_millis(js_sys::Date::now())
+}
+
+fn from_millis(millis: f64) -> Duration {
+    Duration::from_secs_f64(millis.max(0.0) / 1000.0)
+}
@@ -0,0 +1,442 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Instrumentation for HTTP clients.
+//!
+//! [`HttpClientMetrics`] is a handle that is flattened into the unit-of-work entry of the caller,
+//! and attached to the outgoing request as an [extension](http::Extensions). The
+//! [`HttpClientMetricsLayer`] middleware finds it there and records every attempt that goes
+//! through it, so when it is placed *below* a retry layer, every retry is recorded. When the
+//! entry closes, the handle emits:
+//!
+//! - `Attempts` and `Retries`: the number of attempts, and the number of attempts after the first
+//! - `AttemptLatency`: the latency of every attempt, as a distribution in milliseconds
+//! - `Status`: the status code of the last response, if one was received
+//! - `DnsTime` and `ConnectTime`: the time spent resolving names and opening connections, if
+//!   the connector of the client was wrapped in [`Timed::dns`] and [`Timed::connect`]
+//!
+//! Requests that don't carry a handle go through the middleware without being recorded.
+//!
+//! ```
+//! use metrique::unit_of_work::metrics;
+//! use metrique_tower::client::{HttpClientMetrics, HttpClientMetricsLayer};
+//! use tower::{Service, ServiceBuilder, ServiceExt};
+//!
+//! #[metrics(rename_all = "PascalCase")]
+//! struct RequestMetrics {
+//!     #[metrics(flatten, prefix = "downstream_")]
+//!     downstream: HttpClientMetrics,
+//! }
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() {
+//! // in a real service, the inner service is an HTTP client like `hyper_util`'s
+//! let mut client = ServiceBuilder::new()
+//!     .layer(HttpClientMetricsLayer::new())
+//!     .service_fn(|_request: http::Request<()>| async {
+//!         Ok::<_, std::convert::Infallible>(http::Response::new(()))
+//!     });
+//!
+//! let metrics = RequestMetrics { downstream: HttpClientMetrics::new() };
+//! let mut request = http::Request::new(());
+//! request.extensions_mut().insert(metrics.downstream.clone());
+//! client.ready().await.unwrap().call(request).await.unwrap();
+//!
+//! let entry = metrique::test_util::test_metric(metrics);
+//! assert_eq!(entry.metrics["DownstreamAttempts"], 1);
+//! assert_eq!(entry.values["DownstreamStatus"], "200");
+//! # }
+//! ```
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use metrique::timers::Timer;
+use metrique::unit_of_work::metrics;
+use metrique::writer::unit::NegativeScale;
+use metrique::writer::{MetricFlags, MetricValue, Observation, Unit, Value, ValueWriter};
+use metrique::{CloseValue, CloseValueRef};
+use pin_project::pin_project;
+use tokio::task::futures::TaskLocalFuture;
+
+tokio::task_local! {
+    /// The handle of the attempt in progress, read by [`Timed`]
+    static CURRENT_ATTEMPT: HttpClientMetrics;
+}
+
+/// A handle recording the HTTP calls made on behalf of a unit of work, see the
+/// [module docs](self).
+///
+/// Clones of the handle record into the same metrics.
+#[derive(Debug, Clone, Default)]
+pub struct HttpClientMetrics {
+    state: Arc<Mutex<ClientState>>,
+}
+
+#[derive(Debug, Default)]
+struct ClientState {
+    attempt_latencies: Vec<Duration>,
+    status: Option<http::StatusCode>,
+    dns_time: Option<Duration>,
+    connect_time: Option<Duration>,
+}
+
+impl HttpClientMetrics {
+    /// Create a handle with no recorded attempts
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of attempts that were recorded, including attempts still in progress
+    pub fn attempts(&self) -> usize {
+        self.lock().attempt_latencies.len()
+    }
+
+    /// Start recording an attempt. The latency is recorded when the returned guard is finished
+    /// or dropped.
+    ///
+    /// [`HttpClientMetricsLayer`] does this for every request, this is only needed to instrument
+    /// a client that is not a [`tower`](tower_service::Service) service.
+    pub fn start_attempt(&self) -> AttemptGuard {
+        let index = {
+            let mut state = self.lock();
+            state.attempt_latencies.push(Duration::ZERO);
+            state.attempt_latencies.len() - 1
+        };
+        AttemptGuard {
+            metrics: self.clone(),
+            index,
+            timer: Timer::start_now(),
+        }
+    }
+
+    /// Run `future` with this handle as the current attempt, so that [`Timed`] services called
+    /// by it record into this handle
+    pub fn scope<F: Future>(&self, future: F) -> TaskLocalFuture<HttpClientMetrics, F> {
+        CURRENT_ATTEMPT.scope(self.clone(), future)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ClientState> {
+        // the state stays consistent even if a thread panicked while holding the lock
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Records the latency of an attempt when finished or dropped, see
+/// [`HttpClientMetrics::start_attempt`]
+#[derive(Debug)]
+pub struct AttemptGuard {
+    metrics: HttpClientMetrics,
+    index: usize,
+    timer: Timer,
+}
+
+impl AttemptGuard {
+    /// Finish the attempt, which received a response with `status`
+    pub fn finish(self, status: http::StatusCode) {
+        self.metrics.lock().status = Some(status);
+    }
+}
+
+impl Drop for AttemptGuard {
+    fn drop(&mut self) {
+        let latency = self.timer.stop();
+        self.metrics.lock().attempt_latencies[self.index] = latency;
+    }
+}
+
+/// The closed value of [`HttpClientMetrics`]
+#[metrics(subfield_owned)]
+pub struct HttpClientSummary {
+    attempts: usize,
+    retries: usize,
+    attempt_latency: AttemptLatencies,
+    status: Option<http::StatusCode>,
+    dns_time: Option<Duration>,
+    connect_time: Option<Duration>,
+}
+
+impl CloseValue for &'_ HttpClientMetrics {
+    type Closed = <HttpClientSummary as CloseValue>::Closed;
+
+    fn close(self) -> Self::Closed {
+        let state = self.lock();
+        let attempts = state.attempt_latencies.len();
+        HttpClientSummary {
+            attempts,
+            retries: attempts.saturating_sub(1),
+            attempt_latency: AttemptLatencies(state.attempt_latencies.clone()),
+            status: state.status,
+            dns_time: state.dns_time,
+            connect_time: state.connect_time,
+        }
+        .close()
+    }
+}
+
+impl CloseValue for HttpClientMetrics {
+    type Closed = <HttpClientSummary as CloseValue>::Closed;
+
+    fn close(self) -> Self::Closed {
+        self.close_ref()
+    }
+}
+
+/// The latencies of every attempt, emitted as a distribution in milliseconds
+#[derive(Debug, Clone, Default)]
+pub struct AttemptLatencies(Vec<Duration>);
+
+impl CloseValue for AttemptLatencies {
+    type Closed = Self;
+
+    fn close(self) -> Self::Closed {
+        self
+    }
+}
+
+impl Value for AttemptLatencies {
+    fn write(&self, writer: impl ValueWriter) {
+        writer.metric(
+            self.0
+                .iter()
+                .map(|latency| Observation::Floating(latency.as_secs_f64() * 1000.0)),
+            Unit::Second(NegativeScale::Milli),
+            [],
+            MetricFlags::empty(),
+        )
+    }
+}
+
+impl MetricValue for AttemptLatencies {
+    type Unit = metrique::unit::Millisecond;
+}
+
+/// A [`tower_layer::Layer`] recording every request that carries an [`HttpClientMetrics`]
+/// extension, see the [module docs](self)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HttpClientMetricsLayer {
+    _private: (),
+}
+
+impl HttpClientMetricsLayer {
+    /// Create the layer
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<S> tower_layer::Layer<S> for HttpClientMetricsLayer {
+    type Service = HttpClientMetricsService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        HttpClientMetricsService { inner }
+    }
+}
+
+/// The service created by [`HttpClientMetricsLayer`]
+#[derive(Debug, Clone)]
+pub struct HttpClientMetricsService<S> {
+    inner: S,
+}
+
+impl<S, ReqBody, ResBody> tower_service::Service<http::Request<ReqBody>>
+    for HttpClientMetricsService<S>
+where
+    S: tower_service::Service<http::Request<ReqBody>, Response = http::Response<ResBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<ReqBody>) -> Self::Future {
+        match request.extensions().get::<HttpClientMetrics>().cloned() {
+            Some(metrics) => {
+                let attempt = metrics.start_attempt();
+                let future = metrics.scope(self.inner.call(request));
+                ResponseFuture {
+                    inner: ResponseFutureInner::Recorded { future },
+                    attempt: Some(attempt),
+                }
+            }
+            None => ResponseFuture {
+                inner: ResponseFutureInner::Passthrough {
+                    future: self.inner.call(request),
+                },
+                attempt: None,
+            },
+        }
+    }
+}
+
+/// The future returned by [`HttpClientMetricsService`]
+#[pin_project]
+pub struct ResponseFuture<F: Future> {
+    #[pin]
+    inner: ResponseFutureInner<F>,
+    attempt: Option<AttemptGuard>,
+}
+
+#[pin_project(project = ResponseFutureInnerProj)]
+enum ResponseFutureInner<F: Future> {
+    Recorded {
+        #[pin]
+        future: TaskLocalFuture<HttpClientMetrics, F>,
+    },
+    Passthrough {
+        #[pin]
+        future: F,
+    },
+}
+
+impl<F, ResBody, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<http::Response<ResBody>, E>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let output = match this.inner.project() {
+            ResponseFutureInnerProj::Recorded { future } => future.poll(cx),
+            ResponseFutureInnerProj::Passthrough { future } => future.poll(cx),
+        };
+        if let Poll::Ready(result) = &output {
+            if let (Some(attempt), Ok(response)) = (this.attempt.take(), result) {
+                attempt.finish(response.status());
+            }
+            // on error, dropping the guard records the latency of the failed attempt
+            this.attempt.take();
+        }
+        output
+    }
+}
+
+/// Which time a [`Timed`] service records
+#[derive(Debug, Clone, Copy)]
+enum Phase {
+    Dns,
+    Connect,
+}
+
+/// Wraps the DNS resolver or the connector of an HTTP client, adding the time spent in every
+/// call to the [`HttpClientMetrics`] of the attempt in progress.
+///
+/// Attempts are tracked through a task-local set by [`HttpClientMetricsLayer`], so only the
+/// calls made from the task sending the request are recorded. Connections that are reused from
+/// the pool of the client, or opened in the background, are not.
+///
+/// With `hyper_util`, the resolver and the connector are both `tower` services:
+///
+/// ```ignore
+/// let resolver = Timed::dns(GaiResolver::new());
+/// let connector = Timed::connect(HttpConnector::new_with_resolver(resolver));
+/// let client = Client::builder(TokioExecutor::new()).build(connector);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Timed<S> {
+    inner: S,
+    phase: Phase,
+}
+
+impl<S> Timed<S> {
+    /// Record the time spent in `resolver` as `DnsTime`
+    pub fn dns(resolver: S) -> Self {
+        Self {
+            inner: resolver,
+            phase: Phase::Dns,
+        }
+    }
+
+    /// Record the time spent in `connector` as `ConnectTime`
+    pub fn connect(connector: S) -> Self {
+        Self {
+            inner: connector,
+            phase: Phase::Connect,
+        }
+    }
+
+    pub(crate) fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Start timing a call, if it is made on behalf of an attempt
+    pub(crate) fn start(&self) -> Option<PhaseGuard> {
+        CURRENT_ATTEMPT
+            .try_with(|metrics| PhaseGuard {
+                metrics: metrics.clone(),
+                phase: self.phase,
+                timer: Timer::start_now(),
+            })
+            .ok()
+    }
+}
+
+/// Adds the time until it is dropped to the current attempt
+pub(crate) struct PhaseGuard {
+    metrics: HttpClientMetrics,
+    phase: Phase,
+    timer: Timer,
+}
+
+impl Drop for PhaseGuard {
+    fn drop(&mut self) {
+        let elapsed = self.timer.stop();
+        let mut state = self.metrics.lock();
+        let total = match self.phase {
+            Phase::Dns => &mut state.dns_time,
+            Phase::Connect => &mut state.connect_time,
+        };
+        *total = Some(total.unwrap_or_default() + elapsed);
+    }
+}
+
+impl<S, Request> tower_service::Service<Request> for Timed<S>
+where
+    S: tower_service::Service<Request>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = TimedFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        TimedFuture {
+            guard: self.start(),
+            future: self.inner.call(request),
+        }
+    }
+}
+
+/// The future returned by [`Timed`]. If it is dropped before completing, the time until then
+/// is still recorded.
+#[pin_project]
+pub struct TimedFuture<F> {
+    #[pin]
+    future: F,
+    guard: Option<PhaseGuard>,
+}
+
+impl<F: Future> Future for TimedFuture<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let output = this.future.poll(cx);
+        if output.is_ready() {
+            this.guard.take();
+        }
+        output
+    }
+}
@@ -0,0 +1,137 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! [`reqwest_middleware`] adapter for [`HttpClientMetrics`], enabled by the `reqwest` feature.
+//!
+//! Add [`HttpClientMetricsMiddleware`] to the client, after any retry middleware so that every
+//! attempt is recorded, and attach the handle to each request with
+//! [`with_extension`](reqwest_middleware::RequestBuilder::with_extension):
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use metrique::unit_of_work::metrics;
+//! use metrique_tower::client::{HttpClientMetrics, Timed};
+//! use metrique_tower::reqwest::{HttpClientMetricsMiddleware, SystemResolver, TimedConnectorLayer};
+//!
+//! #[metrics(rename_all = "PascalCase")]
+//! struct RequestMetrics {
+//!     #[metrics(flatten, prefix = "downstream_")]
+//!     downstream: HttpClientMetrics,
+//! }
+//!
+//! # async fn example() -> Result<(), reqwest_middleware::Error> {
+//! let client = reqwest::Client::builder()
+//!     // optional, records `DnsTime`
+//!     .dns_resolver(Arc::new(Timed::dns(SystemResolver)))
+//!     // optional, records `ConnectTime`
+//!     .connector_layer(TimedConnectorLayer::new())
+//!     .build()?;
+//! let client = reqwest_middleware::ClientBuilder::new(client)
+//!     .with(HttpClientMetricsMiddleware::new())
+//!     .build();
+//!
+//! let metrics = RequestMetrics { downstream: HttpClientMetrics::new() };
+//! client
+//!     .get("http://example.com")
+//!     .with_extension(metrics.downstream.clone())
+//!     .send()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`HttpClientMetrics`]: crate::client::HttpClientMetrics
+
+use std::net::SocketAddr;
+
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest_middleware::Next;
+
+use crate::client::{HttpClientMetrics, Timed};
+
+/// A [`reqwest_middleware::Middleware`] recording every request that carries an
+/// [`HttpClientMetrics`] extension, see the [module docs](self)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HttpClientMetricsMiddleware {
+    _private: (),
+}
+
+impl HttpClientMetricsMiddleware {
+    /// Create the middleware
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait::async_trait]
+impl reqwest_middleware::Middleware for HttpClientMetricsMiddleware {
+    async fn handle(
+        &self,
+        request: reqwest::Request,
+        extensions: &mut http::Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<reqwest::Response> {
+        let Some(metrics) = extensions.get::<HttpClientMetrics>().cloned() else {
+            return next.run(request, extensions).await;
+        };
+        let attempt = metrics.start_attempt();
+        let result = metrics.scope(next.run(request, extensions)).await;
+        if let Ok(response) = &result {
+            attempt.finish(response.status());
+        }
+        result
+    }
+}
+
+/// A [`tower_layer::Layer`] for [`reqwest::ClientBuilder::connector_layer`] recording the time
+/// spent opening connections as `ConnectTime`, see [`Timed::connect`]
+///
+/// This includes resolving the name, so `ConnectTime` contains `DnsTime`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TimedConnectorLayer {
+    _private: (),
+}
+
+impl TimedConnectorLayer {
+    /// Create the layer
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<S> tower_layer::Layer<S> for TimedConnectorLayer {
+    type Service = Timed<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Timed::connect(inner)
+    }
+}
+
+/// Records the time spent resolving names as `DnsTime`, see [`Timed`]
+impl<R: Resolve + 'static> Resolve for Timed<R> {
+    fn resolve(&self, name: Name) -> Resolving {
+        let guard = self.start();
+        let resolving = self.inner().resolve(name);
+        Box::pin(async move {
+            let addrs = resolving.await;
+            drop(guard);
+            addrs
+        })
+    }
+}
+
+/// Resolves names with the resolver of the operating system, like the default resolver of
+/// `reqwest`, so that it can be wrapped in [`Timed::dns`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemResolver;
+
+impl Resolve for SystemResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_owned();
+        Box::pin(async move {
+            // the port is replaced by the port of the URL
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, 0)).await?.collect();
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
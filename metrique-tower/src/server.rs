@@ -0,0 +1,366 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Instrumentation for gRPC servers.
+//!
+//! [`RpcMetricsLayer`] creates a unit-of-work entry for every RPC, and appends it to a sink once
+//! the RPC completes. The entry is a [`metrics`] struct defined by the application, which
+//! flattens an [`RpcMetrics`] handle. When the entry closes, the handle emits:
+//!
+//! - `Service` and `Method`: the gRPC service and method, from the request path
+//!   `/<Service>/<Method>`
+//! - `Status`: the name of the gRPC status code of the RPC, like `Ok` or `NotFound`. RPCs that
+//!   are cancelled before they complete are recorded as `Cancelled`, and RPCs that end without a
+//!   status, or fail in the transport, are recorded as `Unknown`.
+//! - `Latency`: the time until the response was complete, including streamed messages
+//!
+//! A [handle](metrique::AppendAndCloseOnDropHandle) to the entry is inserted in the
+//! [extensions](http::Extensions) of the request, so handlers can add their own metrics to it.
+//! The entry is appended once the RPC completed and every clone of the handle was dropped.
+//!
+//! The layer works with any gRPC server built on [`tower`](tower_service::Service), like
+//! `tonic`:
+//!
+//! ```ignore
+//! use metrique::unit_of_work::metrics;
+//! use metrique::writer::GlobalEntrySink;
+//! use metrique::{Counter, ServiceMetrics};
+//! use metrique_tower::server::{RpcMetrics, RpcMetricsLayer};
+//!
+//! #[metrics(rename_all = "PascalCase")]
+//! struct RpcEntry {
+//!     #[metrics(flatten)]
+//!     rpc: RpcMetrics,
+//!     items_returned: Counter,
+//! }
+//!
+//! Server::builder()
+//!     .layer(RpcMetricsLayer::new(ServiceMetrics::sink(), |rpc| RpcEntry {
+//!         rpc,
+//!         items_returned: Counter::default(),
+//!     }))
+//!     .add_service(GreeterServer::new(MyGreeter))
+//!     .serve(addr)
+//!     .await?;
+//!
+//! // in the handler
+//! async fn say_hello(&self, request: Request<HelloRequest>) -> Result<Response<HelloReply>, Status> {
+//!     if let Some(metrics) = request.extensions().get::<RpcEntryHandle>() {
+//!         metrics.items_returned.increment();
+//!     }
+//!     // ...
+//! }
+//! ```
+//!
+//! [`metrics`]: metrique::unit_of_work::metrics
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll, ready};
+use std::time::Duration;
+
+use metrique::timers::Timer;
+use metrique::unit_of_work::metrics;
+use metrique::writer::EntrySink;
+use metrique::{CloseValue, CloseValueRef, InflectableEntry, RootMetric};
+use pin_project::pin_project;
+
+/// The names of the gRPC status codes, indexed by code
+const CODE_NAMES: [&str; 17] = [
+    "Ok",
+    "Cancelled",
+    "Unknown",
+    "InvalidArgument",
+    "DeadlineExceeded",
+    "NotFound",
+    "AlreadyExists",
+    "PermissionDenied",
+    "ResourceExhausted",
+    "FailedPrecondition",
+    "Aborted",
+    "OutOfRange",
+    "Unimplemented",
+    "Internal",
+    "Unavailable",
+    "DataLoss",
+    "Unauthenticated",
+];
+
+const CANCELLED: &str = CODE_NAMES[1];
+const UNKNOWN: &str = CODE_NAMES[2];
+
+/// The name of the gRPC status code in the `grpc-status` header of `headers`, if there is one
+fn grpc_status(headers: &http::HeaderMap) -> Option<&'static str> {
+    let code = headers.get("grpc-status")?;
+    let name = std::str::from_utf8(code.as_bytes())
+        .ok()
+        .and_then(|code| code.parse::<usize>().ok())
+        .and_then(|code| CODE_NAMES.get(code).copied());
+    Some(name.unwrap_or(UNKNOWN))
+}
+
+/// A handle recording the service, method, status and latency of an RPC, see the
+/// [module docs](self).
+///
+/// Clones of the handle record into the same metrics.
+#[derive(Debug, Clone)]
+pub struct RpcMetrics {
+    state: Arc<Mutex<RpcState>>,
+}
+
+#[derive(Debug)]
+struct RpcState {
+    service: String,
+    method: String,
+    status: Option<&'static str>,
+    latency: Option<Duration>,
+}
+
+impl RpcMetrics {
+    /// Create a handle for the RPC with the request path `path`, like
+    /// `/helloworld.Greeter/SayHello`.
+    ///
+    /// [`RpcMetricsLayer`] does this for every request, this is only needed to create entries in
+    /// tests.
+    pub fn new(path: &str) -> Self {
+        let (service, method) = path
+            .trim_start_matches('/')
+            .split_once('/')
+            .unwrap_or(("", path));
+        Self {
+            state: Arc::new(Mutex::new(RpcState {
+                service: service.to_owned(),
+                method: method.to_owned(),
+                status: None,
+                latency: None,
+            })),
+        }
+    }
+
+    /// The name of the gRPC status code of the RPC, if it completed
+    pub fn status(&self) -> Option<&'static str> {
+        self.lock().status
+    }
+
+    fn lock(&self) -> MutexGuard<'_, RpcState> {
+        // the state stays consistent even if a thread panicked while holding the lock
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// The closed value of [`RpcMetrics`]
+#[metrics(subfield_owned)]
+pub struct RpcSummary {
+    service: String,
+    method: String,
+    status: Option<&'static str>,
+    latency: Option<Duration>,
+}
+
+impl CloseValue for &'_ RpcMetrics {
+    type Closed = <RpcSummary as CloseValue>::Closed;
+
+    fn close(self) -> Self::Closed {
+        let state = self.lock();
+        RpcSummary {
+            service: state.service.clone(),
+            method: state.method.clone(),
+            status: state.status,
+            latency: state.latency,
+        }
+        .close()
+    }
+}
+
+impl CloseValue for RpcMetrics {
+    type Closed = <RpcSummary as CloseValue>::Closed;
+
+    fn close(self) -> Self::Closed {
+        self.close_ref()
+    }
+}
+
+/// An RPC in progress. Dropping it records the latency, and records the RPC as `Cancelled` if
+/// it has no status yet.
+struct InFlight {
+    rpc: RpcMetrics,
+    timer: Timer,
+    // the handle to the entry, which is appended once every handle is dropped
+    _entry: Box<dyn Send + Sync>,
+}
+
+impl InFlight {
+    fn finish(self, status: &'static str) {
+        self.rpc.lock().status = Some(status);
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        let latency = self.timer.stop();
+        let mut state = self.rpc.lock();
+        state.latency = Some(latency);
+        state.status.get_or_insert(CANCELLED);
+    }
+}
+
+impl std::fmt::Debug for InFlight {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InFlight")
+            .field("rpc", &self.rpc)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A [`tower_layer::Layer`] creating a unit-of-work entry for every RPC, see the
+/// [module docs](self)
+#[derive(Debug, Clone)]
+pub struct RpcMetricsLayer<Q, F> {
+    sink: Q,
+    make_entry: F,
+}
+
+impl<Q, F> RpcMetricsLayer<Q, F> {
+    /// Create the layer. For every RPC, `make_entry` is called with the [`RpcMetrics`] of the
+    /// RPC to create the entry, which is appended to `sink`.
+    pub fn new(sink: Q, make_entry: F) -> Self {
+        Self { sink, make_entry }
+    }
+}
+
+impl<S, Q: Clone, F: Clone> tower_layer::Layer<S> for RpcMetricsLayer<Q, F> {
+    type Service = RpcMetricsService<S, Q, F>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RpcMetricsService {
+            inner,
+            sink: self.sink.clone(),
+            make_entry: self.make_entry.clone(),
+        }
+    }
+}
+
+/// The service created by [`RpcMetricsLayer`]
+#[derive(Debug, Clone)]
+pub struct RpcMetricsService<S, Q, F> {
+    inner: S,
+    sink: Q,
+    make_entry: F,
+}
+
+impl<S, Q, F, M, ReqBody, ResBody> tower_service::Service<http::Request<ReqBody>>
+    for RpcMetricsService<S, Q, F>
+where
+    S: tower_service::Service<http::Request<ReqBody>, Response = http::Response<ResBody>>,
+    Q: EntrySink<RootMetric<M>> + Clone + Send + Sync + 'static,
+    F: Fn(RpcMetrics) -> M,
+    M: CloseValue<Closed: InflectableEntry> + Send + Sync + 'static,
+{
+    type Response = http::Response<RpcBody<ResBody>>;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: http::Request<ReqBody>) -> Self::Future {
+        let rpc = RpcMetrics::new(request.uri().path());
+        let entry =
+            metrique::append_and_close((self.make_entry)(rpc.clone()), self.sink.clone()).handle();
+        request.extensions_mut().insert(entry.clone());
+        ResponseFuture {
+            future: self.inner.call(request),
+            in_flight: Some(InFlight {
+                rpc,
+                timer: Timer::start_now(),
+                _entry: Box::new(entry),
+            }),
+        }
+    }
+}
+
+/// The future returned by [`RpcMetricsService`]
+#[pin_project]
+#[derive(Debug)]
+pub struct ResponseFuture<F> {
+    #[pin]
+    future: F,
+    in_flight: Option<InFlight>,
+}
+
+impl<F, ResBody, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<http::Response<ResBody>, E>>,
+{
+    type Output = Result<http::Response<RpcBody<ResBody>>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let result = ready!(this.future.poll(cx));
+        let in_flight = this.in_flight.take();
+        Poll::Ready(match result {
+            Ok(response) => {
+                let in_flight = match (in_flight, grpc_status(response.headers())) {
+                    // a trailers-only response, the RPC is complete
+                    (Some(in_flight), Some(status)) => {
+                        in_flight.finish(status);
+                        None
+                    }
+                    (in_flight, _) => in_flight,
+                };
+                Ok(response.map(|inner| RpcBody { inner, in_flight }))
+            }
+            Err(error) => {
+                if let Some(in_flight) = in_flight {
+                    in_flight.finish(UNKNOWN);
+                }
+                Err(error)
+            }
+        })
+    }
+}
+
+/// The response body of [`RpcMetricsService`], recording the status from the trailers of the
+/// response when it is complete
+#[pin_project]
+#[derive(Debug)]
+pub struct RpcBody<B> {
+    #[pin]
+    inner: B,
+    in_flight: Option<InFlight>,
+}
+
+impl<B: http_body::Body> http_body::Body for RpcBody<B> {
+    type Data = B::Data;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<http_body::Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        let frame = ready!(this.inner.poll_frame(cx));
+        let status = match &frame {
+            Some(Ok(frame)) => frame.trailers_ref().and_then(grpc_status),
+            Some(Err(_)) | None => Some(UNKNOWN),
+        };
+        if let Some(status) = status
+            && let Some(in_flight) = this.in_flight.take()
+        {
+            in_flight.finish(status);
+        }
+        Poll::Ready(frame)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
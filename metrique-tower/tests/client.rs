@@ -0,0 +1,131 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::convert::Infallible;
+use std::time::{Duration, UNIX_EPOCH};
+
+use metrique::test_util::test_metric;
+use metrique::unit_of_work::metrics;
+use metrique::writer::Observation;
+use metrique_timesource::{TimeSource, set_time_source};
+use metrique_tower::client::{HttpClientMetrics, HttpClientMetricsLayer, Timed};
+use tower::{Service, ServiceBuilder, ServiceExt, service_fn};
+
+#[metrics(rename_all = "PascalCase")]
+struct RequestMetrics {
+    #[metrics(flatten, prefix = "downstream_")]
+    downstream: HttpClientMetrics,
+}
+
+async fn resolve(_name: String) -> Result<(), Infallible> {
+    tokio::time::sleep(Duration::from_millis(2)).await;
+    Ok(())
+}
+
+async fn connect(_uri: http::Uri) -> Result<(), Infallible> {
+    tokio::time::sleep(Duration::from_millis(5)).await;
+    Ok(())
+}
+
+/// A fake client that connects for every request, and fails its first request
+fn client() -> impl Service<http::Request<()>, Response = http::Response<()>, Error = Infallible> {
+    let mut calls = 0;
+    ServiceBuilder::new()
+        .layer(HttpClientMetricsLayer::new())
+        .service_fn(move |request: http::Request<()>| {
+            calls += 1;
+            let first = calls == 1;
+            async move {
+                Timed::dns(service_fn(resolve))
+                    .oneshot("example.com".to_string())
+                    .await
+                    .unwrap();
+                Timed::connect(service_fn(connect))
+                    .oneshot(request.uri().clone())
+                    .await
+                    .unwrap();
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                let status = if first {
+                    http::StatusCode::SERVICE_UNAVAILABLE
+                } else {
+                    http::StatusCode::OK
+                };
+                let mut response = http::Response::new(());
+                *response.status_mut() = status;
+                Ok(response)
+            }
+        })
+}
+
+#[tokio::test(start_paused = true)]
+async fn records_every_attempt() {
+    let _mock_time = set_time_source(TimeSource::tokio(UNIX_EPOCH));
+    let metrics = RequestMetrics {
+        downstream: HttpClientMetrics::new(),
+    };
+    let mut client = client();
+    for _ in 0..2 {
+        let mut request = http::Request::new(());
+        request.extensions_mut().insert(metrics.downstream.clone());
+        client.ready().await.unwrap().call(request).await.unwrap();
+    }
+    assert_eq!(metrics.downstream.attempts(), 2);
+
+    let entry = test_metric(metrics);
+    assert_eq!(entry.metrics["DownstreamAttempts"], 2);
+    assert_eq!(entry.metrics["DownstreamRetries"], 1);
+    assert_eq!(entry.values["DownstreamStatus"], "200");
+    assert_eq!(
+        entry.metrics["DownstreamAttemptLatency"].distribution,
+        vec![Observation::Floating(17.0), Observation::Floating(17.0)]
+    );
+    assert_eq!(entry.metrics["DownstreamDnsTime"], 4);
+    assert_eq!(entry.metrics["DownstreamConnectTime"], 10);
+}
+
+#[tokio::test(start_paused = true)]
+async fn requests_without_a_handle_are_not_recorded() {
+    let _mock_time = set_time_source(TimeSource::tokio(UNIX_EPOCH));
+    let metrics = HttpClientMetrics::new();
+    let mut client = client();
+    client
+        .ready()
+        .await
+        .unwrap()
+        .call(http::Request::new(()))
+        .await
+        .unwrap();
+    assert_eq!(metrics.attempts(), 0);
+
+    let entry = test_metric(RequestMetrics {
+        downstream: metrics,
+    });
+    assert_eq!(entry.metrics["DownstreamAttempts"], 0);
+    assert!(!entry.values.contains_key("DownstreamStatus"));
+    assert!(!entry.metrics.contains_key("DownstreamDnsTime"));
+}
+
+#[tokio::test(start_paused = true)]
+async fn cancelled_attempts_record_their_latency() {
+    let _mock_time = set_time_source(TimeSource::tokio(UNIX_EPOCH));
+    let metrics = HttpClientMetrics::new();
+    let mut client = ServiceBuilder::new()
+        .layer(HttpClientMetricsLayer::new())
+        .service_fn(|_request: http::Request<()>| async {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            Ok::<_, Infallible>(http::Response::new(()))
+        });
+    let mut request = http::Request::new(());
+    request.extensions_mut().insert(metrics.clone());
+    let call = client.ready().await.unwrap().call(request);
+    tokio::time::timeout(Duration::from_millis(100), call)
+        .await
+        .unwrap_err();
+
+    let entry = test_metric(RequestMetrics {
+        downstream: metrics,
+    });
+    assert_eq!(entry.metrics["DownstreamAttempts"], 1);
+    assert_eq!(entry.metrics["DownstreamAttemptLatency"], 100);
+    assert!(!entry.values.contains_key("DownstreamStatus"));
+}
@@ -0,0 +1,74 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(feature = "reqwest")]
+
+use std::sync::Arc;
+
+use metrique::test_util::test_metric;
+use metrique::unit_of_work::metrics;
+use metrique_tower::client::{HttpClientMetrics, Timed};
+use metrique_tower::reqwest::{HttpClientMetricsMiddleware, SystemResolver, TimedConnectorLayer};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+#[metrics(rename_all = "PascalCase")]
+struct RequestMetrics {
+    #[metrics(flatten, prefix = "downstream_")]
+    downstream: HttpClientMetrics,
+}
+
+/// Answers each connection with the next status, closing the connection afterwards
+async fn serve(listener: TcpListener, statuses: Vec<u16>) {
+    for status in statuses {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buf = [0; 4096];
+        let _ = stream.read(&mut buf).await.unwrap();
+        let response =
+            format!("HTTP/1.1 {status} X\r\ncontent-length: 0\r\nconnection: close\r\n\r\n");
+        stream.write_all(response.as_bytes()).await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn middleware_records_attempts() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = tokio::spawn(serve(listener, vec![503, 204]));
+
+    let client = reqwest::Client::builder()
+        .dns_resolver(Arc::new(Timed::dns(SystemResolver)))
+        .connector_layer(TimedConnectorLayer::new())
+        .build()
+        .unwrap();
+    let client = reqwest_middleware::ClientBuilder::new(client)
+        .with(HttpClientMetricsMiddleware::new())
+        .build();
+
+    let metrics = RequestMetrics {
+        downstream: HttpClientMetrics::new(),
+    };
+    for expected in [503, 204] {
+        let response = client
+            .get(format!("http://localhost:{port}/"))
+            .with_extension(metrics.downstream.clone())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), expected);
+    }
+    // not recorded
+    let _ = client.get(format!("http://localhost:{port}/")).send().await;
+    server.await.unwrap();
+
+    let entry = test_metric(metrics);
+    assert_eq!(entry.metrics["DownstreamAttempts"], 2);
+    assert_eq!(entry.metrics["DownstreamRetries"], 1);
+    assert_eq!(entry.values["DownstreamStatus"], "204");
+    assert_eq!(
+        entry.metrics["DownstreamAttemptLatency"].distribution.len(),
+        2
+    );
+    assert!(entry.metrics.contains_key("DownstreamDnsTime"));
+    assert!(entry.metrics.contains_key("DownstreamConnectTime"));
+}
@@ -0,0 +1,126 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::convert::Infallible;
+use std::time::{Duration, UNIX_EPOCH};
+
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use metrique::Counter;
+use metrique::test_util::{TestEntrySink, test_entry_sink};
+use metrique::unit_of_work::metrics;
+use metrique_timesource::{TimeSource, set_time_source};
+use metrique_tower::server::{RpcMetrics, RpcMetricsLayer};
+use tower::{ServiceBuilder, ServiceExt};
+
+#[metrics(rename_all = "PascalCase")]
+struct RpcEntry {
+    #[metrics(flatten)]
+    rpc: RpcMetrics,
+    items_returned: Counter,
+}
+
+fn rpc_entry(rpc: RpcMetrics) -> RpcEntry {
+    RpcEntry {
+        rpc,
+        items_returned: Counter::default(),
+    }
+}
+
+fn grpc_request(path: &str) -> http::Request<()> {
+    http::Request::builder().uri(path).body(()).unwrap()
+}
+
+fn grpc_status(code: u16) -> http::HeaderMap {
+    let mut headers = http::HeaderMap::new();
+    headers.insert("grpc-status", code.into());
+    headers
+}
+
+#[tokio::test(start_paused = true)]
+async fn records_status_from_trailers_and_handler_metrics() {
+    let _mock_time = set_time_source(TimeSource::tokio(UNIX_EPOCH));
+    let TestEntrySink { inspector, sink } = test_entry_sink();
+    let service = ServiceBuilder::new()
+        .layer(RpcMetricsLayer::new(sink, rpc_entry))
+        .service_fn(|request: http::Request<()>| async move {
+            let metrics = request.extensions().get::<RpcEntryHandle>().unwrap();
+            metrics.items_returned.add(3);
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            let body = Full::new(Bytes::from_static(b"reply")).with_trailers(async {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                Some(Ok(grpc_status(0)))
+            });
+            Ok::<_, Infallible>(http::Response::new(body))
+        });
+
+    let response = service
+        .oneshot(grpc_request("/helloworld.Greeter/SayHello"))
+        .await
+        .unwrap();
+    // the entry is appended once the response is complete
+    assert!(inspector.entries().is_empty());
+    let body = response.into_body().collect().await.unwrap();
+    assert_eq!(body.trailers().unwrap()["grpc-status"], "0");
+
+    let entries = inspector.entries();
+    assert_eq!(entries.len(), 1);
+    let entry = &entries[0];
+    assert_eq!(entry.values["Service"], "helloworld.Greeter");
+    assert_eq!(entry.values["Method"], "SayHello");
+    assert_eq!(entry.values["Status"], "Ok");
+    assert_eq!(entry.metrics["Latency"], 15);
+    assert_eq!(entry.metrics["ItemsReturned"], 3);
+}
+
+#[tokio::test(start_paused = true)]
+async fn records_status_of_trailers_only_responses() {
+    let _mock_time = set_time_source(TimeSource::tokio(UNIX_EPOCH));
+    let TestEntrySink { inspector, sink } = test_entry_sink();
+    let service = ServiceBuilder::new()
+        .layer(RpcMetricsLayer::new(sink, rpc_entry))
+        .service_fn(|_request: http::Request<()>| async {
+            tokio::time::sleep(Duration::from_millis(2)).await;
+            let mut response = http::Response::new(Full::new(Bytes::new()));
+            *response.headers_mut() = grpc_status(5);
+            Ok::<_, Infallible>(response)
+        });
+
+    let response = service
+        .oneshot(grpc_request("/store.Inventory/GetItem"))
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    drop(response);
+
+    let entries = inspector.entries();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].values["Service"], "store.Inventory");
+    assert_eq!(entries[0].values["Method"], "GetItem");
+    assert_eq!(entries[0].values["Status"], "NotFound");
+    assert_eq!(entries[0].metrics["Latency"], 2);
+    assert_eq!(entries[0].metrics["ItemsReturned"], 0);
+}
+
+#[tokio::test(start_paused = true)]
+async fn records_cancelled_rpcs() {
+    let _mock_time = set_time_source(TimeSource::tokio(UNIX_EPOCH));
+    let TestEntrySink { inspector, sink } = test_entry_sink();
+    let service = ServiceBuilder::new()
+        .layer(RpcMetricsLayer::new(sink, rpc_entry))
+        .service_fn(|_request: http::Request<()>| async {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            Ok::<_, Infallible>(http::Response::new(Full::new(Bytes::new())))
+        });
+
+    let call = service.oneshot(grpc_request("/store.Inventory/ListItems"));
+    tokio::time::timeout(Duration::from_millis(100), call)
+        .await
+        .unwrap_err();
+
+    let entries = inspector.entries();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].values["Method"], "ListItems");
+    assert_eq!(entries[0].values["Status"], "Cancelled");
+    assert_eq!(entries[0].metrics["Latency"], 100);
+}
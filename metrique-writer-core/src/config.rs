@@ -5,7 +5,8 @@
 //!
 //! The configurations are in this crate in the interest of interoperability
 
-use std::{borrow::Cow, slice};
+use alloc::borrow::Cow;
+use core::slice;
 
 use crate::EntryConfig;
 
@@ -80,6 +81,132 @@ impl crate::Entry for MetriqueValidationError<'_> {
     }
 }
 
+/// Hints at the CloudWatch destination of an entry, for processes that emit entries intended
+/// for more than one account, region or log group.
+///
+/// Putting this config on an entry overrides the destination configured on the formatter for
+/// the fields that are set. The EMF formatter writes the hints in the `_aws` metadata of the
+/// entry, where the CloudWatch Agent reads `LogGroupName` when receiving entries over TCP or UDP.
+/// Nothing in CloudWatch reads the account id and region, so entries for other accounts or
+/// regions should also be routed to an agent configured for them, for example with
+/// `metrique_writer::sink::DestinationRouter`.
+///
+/// ## Example
+///
+/// ```
+/// # use metrique_writer_core::config::EntryDestination;
+/// # use metrique_writer_core::{Entry, EntryWriter};
+/// struct TenantEntry {
+///     destination: EntryDestination,
+///     requests: u64,
+/// }
+///
+/// impl Entry for TenantEntry {
+///     fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+///         writer.config(&self.destination);
+///         writer.value("Requests", &self.requests);
+///     }
+/// }
+///
+/// let entry = TenantEntry {
+///     destination: EntryDestination::new()
+///         .with_account_id("111122223333")
+///         .with_region("eu-west-1")
+///         .with_log_group_name("tenant-metrics"),
+///     requests: 1,
+/// };
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct EntryDestination {
+    account_id: Option<Cow<'static, str>>,
+    region: Option<Cow<'static, str>>,
+    log_group_name: Option<Cow<'static, str>>,
+}
+
+impl EntryDestination {
+    /// Create a new [EntryDestination] without any hints
+    pub const fn new() -> Self {
+        Self {
+            account_id: None,
+            region: None,
+            log_group_name: None,
+        }
+    }
+
+    /// Set the id of the AWS account the entry is intended for
+    pub fn with_account_id(mut self, account_id: impl Into<Cow<'static, str>>) -> Self {
+        self.account_id = Some(account_id.into());
+        self
+    }
+
+    /// Set the AWS region the entry is intended for
+    pub fn with_region(mut self, region: impl Into<Cow<'static, str>>) -> Self {
+        self.region = Some(region.into());
+        self
+    }
+
+    /// Set the CloudWatch Logs log group the entry is intended for
+    pub fn with_log_group_name(mut self, log_group_name: impl Into<Cow<'static, str>>) -> Self {
+        self.log_group_name = Some(log_group_name.into());
+        self
+    }
+
+    /// The id of the AWS account the entry is intended for, if set
+    pub fn account_id(&self) -> Option<&str> {
+        self.account_id.as_deref()
+    }
+
+    /// The AWS region the entry is intended for, if set
+    pub fn region(&self) -> Option<&str> {
+        self.region.as_deref()
+    }
+
+    /// The CloudWatch Logs log group the entry is intended for, if set
+    pub fn log_group_name(&self) -> Option<&str> {
+        self.log_group_name.as_deref()
+    }
+
+    /// Returns true if no hints are set
+    pub fn is_empty(&self) -> bool {
+        self.account_id.is_none() && self.region.is_none() && self.log_group_name.is_none()
+    }
+
+    /// Returns a destination with the hints of `overrides`, and the hints of `self` for the
+    /// fields `overrides` doesn't set
+    pub fn with_overrides(&self, overrides: &EntryDestination) -> EntryDestination {
+        EntryDestination {
+            account_id: overrides.account_id.clone().or(self.account_id.clone()),
+            region: overrides.region.clone().or(self.region.clone()),
+            log_group_name: overrides
+                .log_group_name
+                .clone()
+                .or(self.log_group_name.clone()),
+        }
+    }
+
+    /// Returns true if every hint set on `self` is equal to the same hint of `other`
+    pub fn matches(&self, other: &EntryDestination) -> bool {
+        fn field_matches(
+            expected: &Option<Cow<'static, str>>,
+            actual: &Option<Cow<'static, str>>,
+        ) -> bool {
+            expected.is_none() || expected == actual
+        }
+        field_matches(&self.account_id, &other.account_id)
+            && field_matches(&self.region, &other.region)
+            && field_matches(&self.log_group_name, &other.log_group_name)
+    }
+}
+
+impl EntryConfig for EntryDestination {}
+
+#[diagnostic::do_not_recommend]
+impl crate::Entry for EntryDestination {
+    fn write<'a>(&'a self, writer: &mut impl crate::EntryWriter<'a>) {
+        writer.config(self);
+    }
+}
+
 /// This struct is mostly useful for the EMF internal implementation
 pub struct DimensionsIterator<'a> {
     inner: slice::Iter<'a, Cow<'static, str>>,
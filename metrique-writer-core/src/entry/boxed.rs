@@ -1,7 +1,10 @@
 // Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: Apache-2.0
 
-use std::{any::Any, borrow::Cow, time::SystemTime};
+use alloc::{borrow::Cow, boxed::Box};
+use core::any::Any;
+#[cfg(feature = "std")]
+use std::time::SystemTime;
 
 use smallvec::SmallVec;
 
@@ -9,7 +12,7 @@ use crate::{
     Entry, EntryWriter, Observation, Unit, ValidationError, Value, ValueWriter, value::MetricFlags,
 };
 
-use super::EntryConfig;
+use super::{EntryConfig, Priority};
 
 /// A heap-allocated [`Entry`] wrapper that uses dynamic dispatch.
 ///
@@ -48,6 +51,10 @@ impl Entry for BoxEntry {
     fn sample_group(&self) -> impl Iterator<Item = (Cow<'static, str>, Cow<'static, str>)> {
         self.0.sample_group().into_iter()
     }
+
+    fn priority(&self) -> Priority {
+        self.0.priority()
+    }
 }
 
 // Each Dyn* trait is the object-safe equivalent of its partner
@@ -55,12 +62,16 @@ impl Entry for BoxEntry {
 trait DynEntry: Any + Send + 'static {
     fn write<'a>(&'a self, writer: &mut dyn DynEntryWriter<'a>);
     fn sample_group(&self) -> SmallVec<[(Cow<'static, str>, Cow<'static, str>); 2]>;
+    fn priority(&self) -> Priority;
 }
 
 trait DynEntryWriter<'a> {
+    #[cfg(feature = "std")]
     fn timestamp(&mut self, timestamp: SystemTime);
     fn value(&mut self, name: Cow<'a, str>, value: &dyn DynValue);
     fn config(&mut self, config: &'a dyn EntryConfig);
+    fn begin_group(&mut self, name: Cow<'a, str>, prefix: Cow<'a, str>);
+    fn end_group(&mut self);
 }
 
 trait DynValue {
@@ -89,12 +100,17 @@ impl<E: Entry + Send + 'static> DynEntry for E {
     fn sample_group(&self) -> SmallVec<[(Cow<'static, str>, Cow<'static, str>); 2]> {
         Entry::sample_group(self).collect()
     }
+
+    fn priority(&self) -> Priority {
+        Entry::priority(self)
+    }
 }
 
 struct EntryWriterToDyn<W>(W);
 struct EntryWriterFromDyn<'a, 'w>(&'w mut dyn DynEntryWriter<'a>);
 
 impl<'a, W: EntryWriter<'a>> DynEntryWriter<'a> for EntryWriterToDyn<W> {
+    #[cfg(feature = "std")]
     fn timestamp(&mut self, timestamp: SystemTime) {
         self.0.timestamp(timestamp)
     }
@@ -106,9 +122,18 @@ impl<'a, W: EntryWriter<'a>> DynEntryWriter<'a> for EntryWriterToDyn<W> {
     fn config(&mut self, config: &'a dyn EntryConfig) {
         self.0.config(config);
     }
+
+    fn begin_group(&mut self, name: Cow<'a, str>, prefix: Cow<'a, str>) {
+        self.0.begin_group(name, prefix);
+    }
+
+    fn end_group(&mut self) {
+        self.0.end_group();
+    }
 }
 
 impl<'a> EntryWriter<'a> for EntryWriterFromDyn<'a, '_> {
+    #[cfg(feature = "std")]
     fn timestamp(&mut self, timestamp: SystemTime) {
         self.0.timestamp(timestamp)
     }
@@ -120,6 +145,14 @@ impl<'a> EntryWriter<'a> for EntryWriterFromDyn<'a, '_> {
     fn config(&mut self, config: &'a dyn EntryConfig) {
         self.0.config(config)
     }
+
+    fn begin_group(&mut self, name: impl Into<Cow<'a, str>>, prefix: impl Into<Cow<'a, str>>) {
+        self.0.begin_group(name.into(), prefix.into())
+    }
+
+    fn end_group(&mut self) {
+        self.0.end_group()
+    }
 }
 
 struct ValueToDyn<'a, V: ?Sized>(&'a V);
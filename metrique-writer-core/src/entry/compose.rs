@@ -0,0 +1,74 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+// Entry impls that write several entries into the same written entry, in order. Slices of
+// entries are not covered, since `[(K, V)]` is already an entry of (name, value) pairs.
+
+use alloc::vec::Vec;
+
+use crate::entry::{Priority, SampleGroupElement};
+
+use super::{Entry, EntryWriter};
+
+macro_rules! tuple_entry {
+    ($($name:ident $idx:tt),+) => {
+        impl<$($name: Entry),+> Entry for ($($name,)+) {
+            fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+                $(self.$idx.write(writer);)+
+            }
+
+            fn sample_group(&self) -> impl Iterator<Item = SampleGroupElement> {
+                let group = core::iter::empty();
+                $(let group = group.chain(self.$idx.sample_group());)+
+                group
+            }
+
+            fn priority(&self) -> Priority {
+                let priority = Priority::Low;
+                $(let priority = priority.max(self.$idx.priority());)+
+                priority
+            }
+        }
+    };
+}
+
+tuple_entry!(A 0);
+tuple_entry!(A 0, B 1);
+tuple_entry!(A 0, B 1, C 2);
+tuple_entry!(A 0, B 1, C 2, D 3);
+tuple_entry!(A 0, B 1, C 2, D 3, E 4);
+tuple_entry!(A 0, B 1, C 2, D 3, E 4, F 5);
+tuple_entry!(A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+tuple_entry!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+
+impl<T: Entry> Entry for Vec<T> {
+    fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+        for entry in self {
+            entry.write(writer);
+        }
+    }
+
+    fn sample_group(&self) -> impl Iterator<Item = SampleGroupElement> {
+        self.iter().flat_map(|entry| entry.sample_group())
+    }
+
+    fn priority(&self) -> Priority {
+        self.iter().map(Entry::priority).max().unwrap_or_default()
+    }
+}
+
+impl<T: Entry, const N: usize> Entry for [T; N] {
+    fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+        for entry in self {
+            entry.write(writer);
+        }
+    }
+
+    fn sample_group(&self) -> impl Iterator<Item = SampleGroupElement> {
+        self.iter().flat_map(|entry| entry.sample_group())
+    }
+
+    fn priority(&self) -> Priority {
+        self.iter().map(Entry::priority).max().unwrap_or_default()
+    }
+}
@@ -1,10 +1,13 @@
 // Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: Apache-2.0
 
-use std::collections::{BTreeMap, HashMap};
+use alloc::collections::BTreeMap;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
 
 use crate::{Entry, EntryWriter, Value};
 
+#[cfg(feature = "std")]
 impl<K: AsRef<str>, V: Value, S> Entry for HashMap<K, V, S> {
     fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
         for (k, v) in self {
@@ -1,7 +1,7 @@
 // Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::entry::SampleGroupElement;
+use crate::entry::{Priority, SampleGroupElement};
 
 use super::{Entry, EntryWriter};
 
@@ -18,6 +18,10 @@ impl<E1: Entry, E2: Entry> Entry for Merged<E1, E2> {
     fn sample_group(&self) -> impl Iterator<Item = SampleGroupElement> {
         self.0.sample_group().chain(self.1.sample_group())
     }
+
+    fn priority(&self) -> Priority {
+        self.0.priority().max(self.1.priority())
+    }
 }
 
 /// Merges 2 [Entry] objects by reference. See [Entry::merge_by_ref].
@@ -33,6 +37,10 @@ impl<E1: Entry + ?Sized, E2: Entry + ?Sized> Entry for MergedRef<'_, E1, E2> {
     fn sample_group(&self) -> impl Iterator<Item = SampleGroupElement> {
         self.0.sample_group().chain(self.1.sample_group())
     }
+
+    fn priority(&self) -> Priority {
+        self.0.priority().max(self.1.priority())
+    }
 }
 
 impl<E1: ?Sized, E2: ?Sized> Clone for MergedRef<'_, E1, E2> {
@@ -4,11 +4,19 @@
 //! This module contains the [Entry] trait, which represents an entry that can be written to
 //! an [EntryWriter] in order to emit metrics
 
-use std::{any::Any, borrow::Cow, sync::Arc, time::SystemTime};
+use alloc::{
+    borrow::{Cow, ToOwned},
+    boxed::Box,
+    sync::Arc,
+};
+use core::any::Any;
+#[cfg(feature = "std")]
+use std::time::SystemTime;
 
 mod boxed;
 pub use boxed::BoxEntry;
 
+mod compose;
 mod map;
 
 mod merged;
@@ -170,9 +178,25 @@ pub trait Entry {
         [].into_iter()
     }
 
+    /// How important the entry is, for queues that have to drop entries when they fall behind.
+    /// Defaults to [`Priority::Normal`].
+    ///
+    /// Queues with priority lanes, like a [`BackgroundQueue`] built with
+    /// [`priority_lanes`], write [`Priority::High`] entries first and drop [`Priority::Low`]
+    /// entries first. A typical choice is to make the entries of failed requests high priority,
+    /// since losing error telemetry during an overload is the worst outcome. Other sinks ignore
+    /// the priority.
+    ///
+    /// [`BackgroundQueue`]: https://docs.rs/metrique-writer/0.1/metrique_writer/sink/struct.BackgroundQueue.html
+    /// [`priority_lanes`]: https://docs.rs/metrique-writer/0.1/metrique_writer/sink/struct.BackgroundQueueBuilder.html#method.priority_lanes
+    fn priority(&self) -> Priority {
+        Priority::Normal
+    }
+
     /// Create a new entry that writes all the contents of this entry and then all of the contents of `other`.
     ///
-    /// Useful to merge in global constants or metrics collected by different subsystems.
+    /// Useful to merge in global constants or metrics collected by different subsystems. Tuples,
+    /// `Vec`s and arrays of entries are entries too, which write each of their entries in order.
     fn merge<E>(self, other: E) -> Merged<Self, E>
     where
         Self: Sized,
@@ -199,6 +223,21 @@ pub trait Entry {
 /// A `(key, value)` pair, part of a sample group
 pub type SampleGroupElement = (Cow<'static, str>, Cow<'static, str>);
 
+/// How important an [`Entry`] is, see [`Entry::priority`].
+///
+/// Entries that combine several entries, like [`Merged`] or tuples, have the highest priority of
+/// their parts.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Dropped first when a queue falls behind, for example the entries of health checks
+    Low,
+    /// The default
+    #[default]
+    Normal,
+    /// Written first and dropped last, for example the entries of failed requests
+    High,
+}
+
 /// [`Entry`] that will write no fields.
 ///
 /// Useful for specifying empty globals when attaching [`crate::global`] sinks.
@@ -210,7 +249,7 @@ impl Entry for EmptyEntry {
 }
 
 /// Trait for format-specific Entry configuration, formats will downcast this to the specific config
-pub trait EntryConfig: Any + std::fmt::Debug {}
+pub trait EntryConfig: Any + core::fmt::Debug {}
 
 /// Provided by a format for each atomic entry that will be written to the metric destination.
 ///
@@ -223,6 +262,7 @@ pub trait EntryWriter<'a> {
     ///
     /// This must never panic, but if invoked twice may result in a validation panic on [`crate::EntrySink::append()`]
     /// for test sinks or a `tracing` event on production queues.
+    #[cfg(feature = "std")]
     fn timestamp(&mut self, timestamp: SystemTime);
 
     /// Record a metric [`Value`] in the entry. Each format may have more specific requirements, but typically each
@@ -234,9 +274,24 @@ pub trait EntryWriter<'a> {
 
     /// Pass format-specific entry configuration. Formatters should ignore configuration they are unaware of.
     fn config(&mut self, config: &'a dyn EntryConfig);
+
+    /// Start a group of related values, like the fields of a `#[metrics(flatten, group = "...")]`
+    /// field, which ends at the matching [`EntryWriter::end_group`]. Groups can be nested.
+    ///
+    /// The values of the group are still written with their full names, which normally start with
+    /// `prefix`. Formats that support nesting, like JSON, can write them in a nested object called
+    /// `name`, with `prefix` removed from their names. By default, groups are ignored, so the
+    /// values are written flattened, as formats like EMF require.
+    fn begin_group(&mut self, name: impl Into<Cow<'a, str>>, prefix: impl Into<Cow<'a, str>>) {
+        let _ = (name, prefix);
+    }
+
+    /// End the group started by the last unmatched [`EntryWriter::begin_group`]
+    fn end_group(&mut self) {}
 }
 
 impl<'a, W: EntryWriter<'a>> EntryWriter<'a> for &mut W {
+    #[cfg(feature = "std")]
     fn timestamp(&mut self, timestamp: SystemTime) {
         (**self).timestamp(timestamp)
     }
@@ -248,6 +303,14 @@ impl<'a, W: EntryWriter<'a>> EntryWriter<'a> for &mut W {
     fn config(&mut self, config: &'a dyn EntryConfig) {
         (**self).config(config)
     }
+
+    fn begin_group(&mut self, name: impl Into<Cow<'a, str>>, prefix: impl Into<Cow<'a, str>>) {
+        (**self).begin_group(name, prefix)
+    }
+
+    fn end_group(&mut self) {
+        (**self).end_group()
+    }
 }
 
 impl<T: Entry + ?Sized> Entry for &T {
@@ -258,6 +321,10 @@ impl<T: Entry + ?Sized> Entry for &T {
     fn sample_group(&self) -> impl Iterator<Item = SampleGroupElement> {
         (**self).sample_group()
     }
+
+    fn priority(&self) -> Priority {
+        (**self).priority()
+    }
 }
 
 impl<T: Entry> Entry for Option<T> {
@@ -274,6 +341,10 @@ impl<T: Entry> Entry for Option<T> {
             itertools::Either::Right([].into_iter())
         }
     }
+
+    fn priority(&self) -> Priority {
+        self.as_ref().map_or(Priority::Normal, Entry::priority)
+    }
 }
 
 impl<T: Entry + ?Sized> Entry for Box<T> {
@@ -284,6 +355,10 @@ impl<T: Entry + ?Sized> Entry for Box<T> {
     fn sample_group(&self) -> impl Iterator<Item = SampleGroupElement> {
         (**self).sample_group()
     }
+
+    fn priority(&self) -> Priority {
+        (**self).priority()
+    }
 }
 
 impl<T: Entry + ?Sized> Entry for Arc<T> {
@@ -294,6 +369,10 @@ impl<T: Entry + ?Sized> Entry for Arc<T> {
     fn sample_group(&self) -> impl Iterator<Item = SampleGroupElement> {
         (**self).sample_group()
     }
+
+    fn priority(&self) -> Priority {
+        (**self).priority()
+    }
 }
 
 impl<T: Entry + ToOwned + ?Sized> Entry for Cow<'_, T> {
@@ -304,4 +383,8 @@ impl<T: Entry + ToOwned + ?Sized> Entry for Cow<'_, T> {
     fn sample_group(&self) -> impl Iterator<Item = SampleGroupElement> {
         (**self).sample_group()
     }
+
+    fn priority(&self) -> Priority {
+        (**self).priority()
+    }
 }
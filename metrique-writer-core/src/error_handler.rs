@@ -0,0 +1,127 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! A process-wide handler for the errors that sinks run into while writing entries.
+//!
+//! Sinks can't return errors to the code that appends entries, so by default they only emit
+//! (rate-limited) [`tracing`] events and count them. [`set_error_handler`] lets an application
+//! route these errors to its own alerting as well:
+//!
+//! ```
+//! use std::sync::atomic::{AtomicU64, Ordering};
+//!
+//! use metrique_writer_core::error_handler::{self, PipelineError};
+//!
+//! static DROPPED_ENTRIES: AtomicU64 = AtomicU64::new(0);
+//!
+//! error_handler::set_error_handler(|error: &PipelineError<'_>| {
+//!     if error.drops_entry() {
+//!         DROPPED_ENTRIES.fetch_add(1, Ordering::Relaxed);
+//!     }
+//! });
+//! ```
+//!
+//! Sinks report their errors with [`report_error`].
+//!
+//! [`tracing`]: https://docs.rs/tracing
+
+use std::{
+    fmt, io,
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, RwLock},
+};
+
+use crate::{IoStreamError, ValidationError};
+
+type Handler = Arc<dyn Fn(&PipelineError<'_>) + Send + Sync>;
+
+static HANDLER: RwLock<Option<Handler>> = RwLock::new(None);
+
+/// An error that a sink ran into while writing entries, passed to the handler set with
+/// [`set_error_handler`]
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub enum PipelineError<'a> {
+    /// An entry failed validation while being formatted, and was dropped
+    Validation {
+        /// The name of the sink
+        sink: &'a str,
+        /// The validation error
+        error: &'a ValidationError,
+    },
+    /// Writing an entry to, or flushing, the output stream failed
+    Io {
+        /// The name of the sink
+        sink: &'a str,
+        /// The IO error
+        error: &'a io::Error,
+    },
+    /// The queue of the sink was full, and its oldest entry was dropped
+    QueueFull {
+        /// The name of the sink
+        sink: &'a str,
+    },
+}
+
+impl<'a> PipelineError<'a> {
+    /// The error of a sink that failed to write to an [`EntryIoStream`](crate::EntryIoStream)
+    pub fn from_stream_error(sink: &'a str, error: &'a IoStreamError) -> Self {
+        match error {
+            IoStreamError::Validation(error) => Self::Validation { sink, error },
+            IoStreamError::Io(error) => Self::Io { sink, error },
+        }
+    }
+
+    /// The name of the sink that ran into the error
+    pub fn sink(&self) -> &'a str {
+        match self {
+            Self::Validation { sink, .. } | Self::Io { sink, .. } | Self::QueueFull { sink } => {
+                sink
+            }
+        }
+    }
+
+    /// Returns true if an entry was dropped because of this error.
+    ///
+    /// IO errors might have lost entries too, but can't tell how many.
+    pub fn drops_entry(&self) -> bool {
+        matches!(self, Self::Validation { .. } | Self::QueueFull { .. })
+    }
+}
+
+impl fmt::Display for PipelineError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Validation { sink, error } => {
+                write!(f, "`{sink}` dropped an invalid entry: {error}")
+            }
+            Self::Io { sink, error } => write!(f, "`{sink}` couldn't write its output: {error}"),
+            Self::QueueFull { sink } => write!(f, "`{sink}` dropped an entry, its queue is full"),
+        }
+    }
+}
+
+/// Set the handler that is called with every error that a sink runs into, replacing the previous
+/// one.
+///
+/// The handler is called on the thread that ran into the error, which for a background queue is
+/// the queue's own thread, so it should return quickly. Panics of the handler are ignored.
+pub fn set_error_handler(handler: impl Fn(&PipelineError<'_>) + Send + Sync + 'static) {
+    *HANDLER.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(handler));
+}
+
+/// Remove the handler set with [`set_error_handler`]
+pub fn clear_error_handler() {
+    *HANDLER.write().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// Report an error to the handler set with [`set_error_handler`], if any.
+///
+/// Sinks call this in addition to their own logging when they drop an entry or fail to write.
+pub fn report_error(error: &PipelineError<'_>) {
+    let handler = HANDLER.read().unwrap_or_else(|e| e.into_inner()).clone();
+    if let Some(handler) = handler {
+        // a broken handler must not take the sink (and its thread) down with it
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| handler(error)));
+    }
+}
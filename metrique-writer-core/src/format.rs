@@ -1,7 +1,7 @@
 // Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: Apache-2.0
 
-//! Contains the [`Format`] trait.
+//! Contains the [`Format`] trait and [`FloatPrecision`], which formats use to round floats.
 
 use crate::{Entry, stream::IoStreamError};
 use std::io;
@@ -18,3 +18,63 @@ pub trait Format {
         output: &mut impl io::Write,
     ) -> Result<(), IoStreamError>;
 }
+
+/// How a [`Format`] writes floating-point metric values.
+///
+/// By default, formats write the shortest representation that round-trips to the same `f64`,
+/// which can take up to 17 significant digits. Dashboards and alarms rarely need that much
+/// precision, so rounding values can noticeably shrink the formatted entries.
+///
+/// Rounding happens before the value is formatted, so the written value is the shortest
+/// representation of the rounded `f64`. Integer observations are never rounded.
+///
+/// ```
+/// # use metrique_writer_core::format::FloatPrecision;
+/// assert_eq!(FloatPrecision::RoundTrip.round(0.1 + 0.2), 0.30000000000000004);
+/// assert_eq!(FloatPrecision::DecimalPlaces(2).round(1.23456), 1.23);
+/// assert_eq!(FloatPrecision::SignificantDigits(3).round(123456.7), 123000.0);
+/// assert_eq!(FloatPrecision::SignificantDigits(3).round(0.00123456), 0.00123);
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FloatPrecision {
+    /// Write the shortest representation that round-trips to the same `f64`
+    #[default]
+    RoundTrip,
+    /// Round to at most this many digits after the decimal point
+    DecimalPlaces(u8),
+    /// Round to at most this many significant digits (at least 1)
+    SignificantDigits(u8),
+}
+
+impl FloatPrecision {
+    /// Round `value` to this precision.
+    ///
+    /// Non-finite values, and values that are already more precise than requested, are
+    /// returned unchanged.
+    pub fn round(self, value: f64) -> f64 {
+        if !value.is_finite() || value == 0.0 {
+            return value;
+        }
+        let decimals = match self {
+            Self::RoundTrip => return value,
+            Self::DecimalPlaces(places) => i32::from(places),
+            Self::SignificantDigits(digits) => {
+                let magnitude = value.abs().log10().floor() as i32;
+                i32::from(digits.max(1)) - 1 - magnitude
+            }
+        };
+        if decimals >= 0 {
+            let scale = 10f64.powi(decimals);
+            let scaled = value * scale;
+            // beyond 2^52, every f64 is already an integer at this scale
+            if !scaled.is_finite() || scaled.abs() >= (1u64 << 52) as f64 {
+                return value;
+            }
+            scaled.round() / scale
+        } else {
+            let scale = 10f64.powi(-decimals);
+            (value / scale).round() * scale
+        }
+    }
+}
@@ -755,6 +755,39 @@ macro_rules! global_entry_sink {
     };
 }
 pub use global_entry_sink;
+// Helper macro that conditionally expands based on the test-util feature
+// This is checked at macro expansion time in the metrique-writer-core crate
+
+/// Expands the given block of code when `metrique-writer-core` is compiled with the `test-util` feature.
+#[doc(hidden)]
+#[macro_export]
+#[cfg(feature = "test-util")]
+macro_rules! __test_util {
+    ($($tt:tt)*) => { $($tt)* };
+}
+
+/// Does not expand the given block of code when `metrique-writer-core` is compiled without the `test-util` feature.
+#[doc(hidden)]
+#[macro_export]
+#[cfg(not(feature = "test-util"))]
+macro_rules! __test_util {
+    ($($tt:tt)*) => {};
+}
+
+// the __macro_doctest attribute is used to make sure our doctests are not compiled
+// in customer crates, since customer crates getting compilation errors on our doctests is
+// very annoying.
+
+/// Expands to ```rust to run doctests the given block of code when `metrique-writer-core`
+/// is compiled with the `private-test-util` feature, for our internal testing
+#[doc(hidden)]
+#[macro_export]
+#[cfg(feature = "private-test-util")]
+macro_rules! __macro_doctest {
+    () => {
+        "```rust"
+    };
+}
 
 #[cfg(test)]
 mod tests {
@@ -955,7 +988,7 @@ mod tests {
 
         let rt = tokio::runtime::Runtime::new().unwrap();
         let TestEntrySink { inspector, sink } = test_entry_sink();
-        let _guard = ServiceMetrics::set_test_sink_for_tokio_runtime(&rt.handle(), sink);
+        let _guard = ServiceMetrics::set_test_sink_for_tokio_runtime(rt.handle(), sink);
 
         rt.block_on(async {
             ServiceMetrics::append(TestEntry);
@@ -1113,7 +1146,7 @@ mod tests {
         let lazy_sink = ServiceMetrics::sink_or_discard();
         let mut flush = std::pin::pin!(AnyEntrySink::flush_async(&lazy_sink));
         let waker = std::task::Waker::noop();
-        let mut cx = std::task::Context::from_waker(&waker);
+        let mut cx = std::task::Context::from_waker(waker);
         assert!(flush.as_mut().poll(&mut cx).is_ready());
     }
 
@@ -1146,39 +1179,6 @@ mod tests {
         assert_eq!(inspector.entries().len(), 1);
     }
 }
-// Helper macro that conditionally expands based on the test-util feature
-// This is checked at macro expansion time in the metrique-writer-core crate
-
-/// Expands the given block of code when `metrique-writer-core` is compiled with the `test-util` feature.
-#[doc(hidden)]
-#[macro_export]
-#[cfg(feature = "test-util")]
-macro_rules! __test_util {
-    ($($tt:tt)*) => { $($tt)* };
-}
-
-/// Does not expand the given block of code when `metrique-writer-core` is compiled without the `test-util` feature.
-#[doc(hidden)]
-#[macro_export]
-#[cfg(not(feature = "test-util"))]
-macro_rules! __test_util {
-    ($($tt:tt)*) => {};
-}
-
-// the __macro_doctest attribute is used to make sure our doctests are not compiled
-// in customer crates, since customer crates getting compilation errors on our doctests is
-// very annoying.
-
-/// Expands to ```rust to run doctests the given block of code when `metrique-writer-core`
-/// is compiled with the `private-test-util` feature, for our internal testing
-#[doc(hidden)]
-#[macro_export]
-#[cfg(feature = "private-test-util")]
-macro_rules! __macro_doctest {
-    () => {
-        "```rust"
-    };
-}
 
 /// Does not expand the given block of code when `metrique-writer-core` is compiled without the `test-util` feature.
 #[doc(hidden)]
@@ -4,24 +4,38 @@
 #![doc = include_str!("../README.md")]
 #![deny(missing_docs)]
 #![cfg_attr(docsrs, feature(doc_cfg))]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
 
 pub use crate::entry::{BoxEntry, Entry, EntryConfig, EntryWriter};
+#[cfg(feature = "std")]
 pub use crate::global::GlobalEntrySink;
 pub use crate::sample::SampleGroup;
-pub use crate::sink::{AnyEntrySink, BoxEntrySink, EntrySink};
+#[cfg(feature = "std")]
+pub use crate::sink::{AnyEntrySink, BoxEntrySink, DynEntrySink, EntrySink};
+#[cfg(feature = "std")]
 pub use crate::stream::{EntryIoStream, IoStreamError};
 pub use crate::unit::{Convert, Unit};
 pub use crate::validate::{ValidationError, ValidationErrorBuilder};
-pub use crate::value::{Distribution, MetricFlags, MetricValue, Observation, Value, ValueWriter};
+pub use crate::value::{
+    Distribution, MetricFlags, MetricValue, Observation, ObservedNumber, Value, ValueWriter,
+};
 
-pub(crate) type CowStr = std::borrow::Cow<'static, str>;
+pub(crate) type CowStr = alloc::borrow::Cow<'static, str>;
 
 pub mod config;
 pub mod entry;
+#[cfg(feature = "std")]
+pub mod error_handler;
+#[cfg(feature = "std")]
 pub mod format;
+#[cfg(feature = "std")]
 pub mod global;
 pub mod sample;
+#[cfg(feature = "std")]
 pub mod sink;
+#[cfg(feature = "std")]
 pub mod stream;
 pub mod unit;
 mod validate;
@@ -3,13 +3,17 @@
 
 //! Defines the [`SampledFormat`] trait, which allows for formats that can be sampled.
 
-use std::{borrow::Cow, io};
+use alloc::borrow::Cow;
+#[cfg(feature = "std")]
+use std::io;
 
+#[cfg(feature = "std")]
 use crate::{Entry, IoStreamError, format::Format};
 
 /// Allows for sampleable formats, with a "sample rate" that will automatically compensate for entries that
 /// were sampled by that fraction. This allow services to trade a lower-accuracy metric for reduced time emitting and
 /// processing metrics.
+#[cfg(feature = "std")]
 pub trait SampledFormat: Format {
     /// Like [`Format::format()`], but also associate the entry with a sample rate in the range `(0, 1]`.
     ///
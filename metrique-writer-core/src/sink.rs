@@ -31,6 +31,26 @@ pub trait EntrySink<E: Entry> {
     /// valuable for monitoring service health.
     fn append(&self, entry: E);
 
+    /// Try to append the `entry` to the in-memory buffer without displacing any other entry, returning
+    /// [`TryAppendError`] if it was not accepted (in which case it is dropped).
+    ///
+    /// Like [`EntrySink::append()`], this must never block and must never panic. This allows bursty producers to
+    /// shed load instead of dropping older entries. Sinks that don't have a bounded buffer always accept the entry.
+    fn try_append(&self, entry: E) -> Result<(), TryAppendError> {
+        self.append(entry);
+        Ok(())
+    }
+
+    /// Append the `entry` to the in-memory buffer, waiting for capacity to become available if the buffer is full
+    /// instead of dropping older entries.
+    ///
+    /// If the sink is shut down while waiting, the entry is dropped and the returned [`AppendWait`] completes.
+    /// Sinks that don't have a bounded buffer append the entry immediately.
+    fn append_async(&self, entry: E) -> AppendWait {
+        self.append(entry);
+        AppendWait::ready()
+    }
+
     /// Request the sink to flush its contents to some sort of persistent storage. The returned
     /// `FlushWait` can be used to tell when the sink is flushed.
     ///
@@ -82,6 +102,18 @@ pub trait AnyEntrySink {
     /// Generic version of [`EntrySink::append()`] with the same contract.
     fn append_any(&self, entry: impl Entry + Send + 'static);
 
+    /// Generic version of [`EntrySink::try_append()`] with the same contract.
+    fn try_append_any(&self, entry: impl Entry + Send + 'static) -> Result<(), TryAppendError> {
+        self.append_any(entry);
+        Ok(())
+    }
+
+    /// Generic version of [`EntrySink::append_async()`] with the same contract.
+    fn append_any_async(&self, entry: impl Entry + Send + 'static) -> AppendWait {
+        self.append_any(entry);
+        AppendWait::ready()
+    }
+
     /// Request the sink to flush its contents and wait until they are flushed.
     ///
     /// In synchronous code, you can use `pollster::block_on` or `futures::executor::block_on` to
@@ -105,6 +137,14 @@ impl<T: AnyEntrySink, E: Entry + Send + 'static> EntrySink<E> for T {
     fn append(&self, entry: E) {
         self.append_any(entry)
     }
+
+    fn try_append(&self, entry: E) -> Result<(), TryAppendError> {
+        self.try_append_any(entry)
+    }
+
+    fn append_async(&self, entry: E) -> AppendWait {
+        self.append_any_async(entry)
+    }
 }
 
 /// A type-erased [`EntrySink`], that can sink a [`BoxEntry`] (which can contain
@@ -123,6 +163,14 @@ impl AnyEntrySink for BoxEntrySink {
         self.0.append(entry.boxed())
     }
 
+    fn try_append_any(&self, entry: impl Entry + Send + 'static) -> Result<(), TryAppendError> {
+        self.0.try_append(entry.boxed())
+    }
+
+    fn append_any_async(&self, entry: impl Entry + Send + 'static) -> AppendWait {
+        self.0.append_async(entry.boxed())
+    }
+
     fn flush_async(&self) -> FlushWait {
         self.0.flush_async()
     }
@@ -144,6 +192,72 @@ impl BoxEntrySink {
     }
 }
 
+/// A type-erased [`EntrySink`] for entries of type `E`.
+///
+/// Unlike [`BoxEntrySink`], the entries are not boxed, so this can erase sinks that only accept
+/// one entry type, like a `BackgroundQueue<E>`. Code that stores sinks (or guards that append to
+/// them) can name `DynEntrySink<E>` instead of carrying the type of the sink around.
+///
+/// Cloning is cheap and still appends to the same sink.
+///
+/// # Example
+/// ```
+/// # use metrique_writer::{Entry, EntrySink, sink::{AppendOnDrop, DynEntrySink, VecEntrySink}};
+/// #[derive(Entry)]
+/// struct MyEntry {
+///     counter: u64,
+/// }
+///
+/// struct Handler {
+///     metrics: AppendOnDrop<MyEntry, DynEntrySink<MyEntry>>,
+/// }
+///
+/// let sink = VecEntrySink::default();
+/// let handler = Handler {
+///     metrics: DynEntrySink::new(sink.clone()).append_on_drop(MyEntry { counter: 1 }),
+/// };
+/// drop(handler);
+/// assert_eq!(sink.drain().len(), 1);
+/// ```
+pub struct DynEntrySink<E>(Arc<dyn EntrySink<E> + Send + Sync + 'static>);
+
+impl<E> Clone for DynEntrySink<E> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl<E> Debug for DynEntrySink<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("DynEntrySink").finish()
+    }
+}
+
+impl<E: Entry> DynEntrySink<E> {
+    /// Create a new [`DynEntrySink`]
+    pub fn new(sink: impl EntrySink<E> + Send + Sync + 'static) -> Self {
+        Self(Arc::new(sink))
+    }
+}
+
+impl<E: Entry> EntrySink<E> for DynEntrySink<E> {
+    fn append(&self, entry: E) {
+        self.0.append(entry)
+    }
+
+    fn try_append(&self, entry: E) -> Result<(), TryAppendError> {
+        self.0.try_append(entry)
+    }
+
+    fn append_async(&self, entry: E) -> AppendWait {
+        self.0.append_async(entry)
+    }
+
+    fn flush_async(&self) -> FlushWait {
+        self.0.flush_async()
+    }
+}
+
 struct LazySink(Arc<dyn Fn() -> Option<BoxEntrySink> + Send + Sync>);
 
 impl EntrySink<BoxEntry> for LazySink {
@@ -153,6 +267,20 @@ impl EntrySink<BoxEntry> for LazySink {
         }
     }
 
+    fn try_append(&self, entry: BoxEntry) -> Result<(), TryAppendError> {
+        match (self.0)() {
+            Some(sink) => sink.0.try_append(entry),
+            None => Ok(()),
+        }
+    }
+
+    fn append_async(&self, entry: BoxEntry) -> AppendWait {
+        match (self.0)() {
+            Some(sink) => sink.0.append_async(entry),
+            None => AppendWait::ready(),
+        }
+    }
+
     fn flush_async(&self) -> FlushWait {
         match (self.0)() {
             Some(sink) => sink.0.flush_async(),
@@ -191,6 +319,60 @@ impl FlushWait {
     }
 }
 
+/// Error returned by [`EntrySink::try_append()`] when an entry was not accepted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TryAppendError {
+    /// The sink's in-memory buffer is full.
+    Full,
+}
+
+impl std::fmt::Display for TryAppendError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Full => f.write_str("entry sink is full"),
+        }
+    }
+}
+
+impl std::error::Error for TryAppendError {}
+
+/// This struct contains a future that can be used to wait for an entry passed to
+/// [`EntrySink::append_async()`] to be appended
+#[must_use = "future does nothing unless polled"]
+pub struct AppendWait(Pin<Box<dyn std::future::Future<Output = ()> + Send + 'static>>);
+
+impl Future for AppendWait {
+    type Output = ();
+
+    fn poll(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Self::Output> {
+        self.0.as_mut().poll(cx)
+    }
+}
+
+impl Debug for AppendWait {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("AppendWait").finish()
+    }
+}
+
+impl AppendWait {
+    /// Return an AppendWait that is ready immediately, for sinks that appended the entry synchronously
+    pub fn ready() -> Self {
+        Self(Box::pin(std::future::poll_fn(|_| {
+            std::task::Poll::Ready(())
+        })))
+    }
+
+    /// Create an AppendWait that returns when a future is ready
+    pub fn from_future(f: impl std::future::Future<Output = ()> + Send + 'static) -> Self {
+        Self(Box::pin(f))
+    }
+}
+
 /// Smart pointer that will append the wrapped entry to a sink when dropped.
 #[derive(Debug, Clone)]
 pub struct AppendOnDrop<E: Entry, Q: EntrySink<E>> {
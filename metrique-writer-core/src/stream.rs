@@ -69,4 +69,12 @@ pub trait EntryIoStream {
     /// Note that some writers rely on regular flush
     /// calls to interleave IO operations that won't tear across entries.
     fn flush(&mut self) -> io::Result<()>;
+
+    /// The total number of bytes written to the underlying output so far, if this stream keeps track of it.
+    ///
+    /// This is used for reporting, for example by the self-metrics of the background queue. The default
+    /// implementation returns `None`.
+    fn bytes_written(&self) -> Option<u64> {
+        None
+    }
 }
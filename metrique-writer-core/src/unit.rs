@@ -33,7 +33,8 @@
 //! };
 //! ```
 
-use std::{
+use alloc::format;
+use core::{
     cmp::Ordering,
     fmt::{self, Debug, Display},
     hash::{Hash, Hasher},
@@ -119,6 +120,40 @@ impl Unit {
             Self::Custom(unit) => unit,
         }
     }
+
+    /// The unit whose [`Unit::name`] is `name`, if any. [`Unit::Custom`] units are never
+    /// returned, since their name isn't `'static`.
+    ///
+    /// ```
+    /// # use metrique_writer_core::unit::{NegativeScale, Unit};
+    /// assert_eq!(Unit::from_name("Milliseconds"), Some(Unit::Second(NegativeScale::Milli)));
+    /// assert_eq!(Unit::from_name("Furlongs"), None);
+    /// ```
+    pub fn from_name(name: &str) -> Option<Self> {
+        const POSITIVE_SCALES: [PositiveScale; 5] = [
+            PositiveScale::One,
+            PositiveScale::Kilo,
+            PositiveScale::Mega,
+            PositiveScale::Giga,
+            PositiveScale::Tera,
+        ];
+
+        let units = [Self::None, Self::Count, Self::Percent]
+            .into_iter()
+            .chain(
+                [
+                    NegativeScale::Micro,
+                    NegativeScale::Milli,
+                    NegativeScale::One,
+                ]
+                .map(Self::Second),
+            )
+            .chain(POSITIVE_SCALES.map(Self::Byte))
+            .chain(POSITIVE_SCALES.map(Self::BytePerSecond))
+            .chain(POSITIVE_SCALES.map(Self::Bit))
+            .chain(POSITIVE_SCALES.map(Self::BitPerSecond));
+        units.into_iter().find(|unit| unit.name() == name)
+    }
 }
 
 impl fmt::Debug for Unit {
@@ -304,6 +339,21 @@ impl<U: UnitTag> Convert<U> for None {
 unit_tag!(Count, AsCount, Unit::Count);
 unit_tag!(Percent, AsPercent, Unit::Percent);
 
+// Ratios are unitless, but converting one to `Percent` scales it
+unit_tag!(Ratio, AsRatio, Unit::None);
+
+impl Convert<Ratio> for Ratio {
+    const RATIO: f64 = 1.0;
+}
+
+impl Convert<None> for Ratio {
+    const RATIO: f64 = 1.0;
+}
+
+impl Convert<Percent> for Ratio {
+    const RATIO: f64 = 100.0;
+}
+
 // Time units
 
 trait TimeTag: UnitTag {
@@ -565,6 +615,119 @@ where
     type Unit = U;
 }
 
+/// Attaches a user-defined unit label, [`Unit::Custom`], to a unitless value in [`crate::Value::write()`].
+///
+/// This is useful for domain-specific units (like `Widgets` or `Requests`) that are not modeled by [`Unit`].
+/// Formats that allow arbitrary unit strings pass the label through, while formats that only support a fixed
+/// set of units (like EMF) emit the value without a unit.
+///
+/// This is what `#[metrics(unit = Custom("Widgets"))]` expands to.
+///
+/// ```
+/// # use metrique_writer::unit::WithCustomUnit;
+/// # use metrique_writer::Entry;
+/// #[derive(Entry)]
+/// struct MyEntry {
+///     widgets_sold: WithCustomUnit<u64>,
+/// }
+///
+/// MyEntry {
+///     widgets_sold: WithCustomUnit::new(3, "Widgets"),
+/// };
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WithCustomUnit<V> {
+    value: V,
+    unit: &'static str,
+}
+
+impl<V> WithCustomUnit<V> {
+    /// Attach the unit label `unit` to `value`
+    pub fn new(value: V, unit: &'static str) -> Self {
+        Self { value, unit }
+    }
+
+    /// Return the wrapped value
+    pub fn into_inner(self) -> V {
+        self.value
+    }
+
+    /// Return the unit label
+    pub fn unit(&self) -> Unit {
+        Unit::Custom(self.unit)
+    }
+}
+
+impl<V> Deref for WithCustomUnit<V> {
+    type Target = V;
+
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl<V> DerefMut for WithCustomUnit<V> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.value
+    }
+}
+
+impl<V: Debug> fmt::Debug for WithCustomUnit<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WithCustomUnit")
+            .field("value", &self.value)
+            .field("unit", &self.unit)
+            .finish()
+    }
+}
+
+impl<V: Display> fmt::Display for WithCustomUnit<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.value, self.unit)
+    }
+}
+
+impl<V: MetricValue<Unit = None>> Value for WithCustomUnit<V> {
+    fn write(&self, writer: impl ValueWriter) {
+        struct Wrapper<W> {
+            writer: W,
+            unit: &'static str,
+        }
+
+        impl<W: ValueWriter> ValueWriter for Wrapper<W> {
+            fn string(self, _value: &str) {
+                self.invalid("can't apply a unit to a string value");
+            }
+
+            fn metric<'a>(
+                self,
+                distribution: impl IntoIterator<Item = Observation>,
+                unit: Unit,
+                dimensions: impl IntoIterator<Item = (&'a str, &'a str)>,
+                flags: MetricFlags<'_>,
+            ) {
+                if unit != Unit::None {
+                    self.invalid(format!(
+                        "value promised to write unit `None` but wrote `{unit}` instead"
+                    ));
+                } else {
+                    self.writer
+                        .metric(distribution, Unit::Custom(self.unit), dimensions, flags)
+                }
+            }
+
+            fn error(self, error: ValidationError) {
+                self.writer.error(error)
+            }
+        }
+
+        self.value.write(Wrapper {
+            writer,
+            unit: self.unit,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::time::Duration;
@@ -582,6 +745,11 @@ mod tests {
         assert_eq!(<None as Convert<Count>>::RATIO, 1.0);
         assert_eq!(<None as Convert<None>>::RATIO, 1.0);
 
+        // Ratio conversions
+        assert_eq!(<Ratio as Convert<Percent>>::RATIO, 100.0);
+        assert_eq!(<Ratio as Convert<Ratio>>::RATIO, 1.0);
+        assert_eq!(<Ratio as Convert<None>>::RATIO, 1.0);
+
         // Time conversions
         assert_eq!(<Second as Convert<Millisecond>>::RATIO, 1_000.0);
         assert_eq!(<Millisecond as Convert<Second>>::RATIO, 1.0 / 1_000.0);
@@ -1,7 +1,8 @@
 // Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: Apache-2.0
 
-use std::fmt;
+use alloc::{format, string::String, vec, vec::Vec};
+use core::fmt;
 
 /// An error type that describes why an [`crate::Entry`] isn't valid. This can be because it violated general contracts
 /// (e.g. writing multiple values with the same name) or because it violated a format-specific contract (e.g. using a
@@ -51,7 +52,7 @@ impl fmt::Display for ValidationError {
     }
 }
 
-impl std::error::Error for ValidationError {}
+impl core::error::Error for ValidationError {}
 
 /// Builder to record validation failures over time and bundle them into a single [`ValidationError`] Note that if no
 /// validation failures are added to the builder, [`ValidationErrorBuilder::build()`] will return [`None`], which is
@@ -1,11 +1,10 @@
 // Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: Apache-2.0
 
-use std::{
-    borrow::Cow,
-    ops::{Deref, DerefMut},
-    time::SystemTime,
-};
+use alloc::borrow::Cow;
+use core::ops::{Deref, DerefMut};
+#[cfg(feature = "std")]
+use std::time::SystemTime;
 
 use smallvec::SmallVec;
 
@@ -280,6 +279,7 @@ struct Wrapper<'a, V> {
 }
 
 impl<'a, W: EntryWriter<'a>> EntryWriter<'a> for Wrapper<'_, W> {
+    #[cfg(feature = "std")]
     fn timestamp(&mut self, timestamp: SystemTime) {
         self.value.timestamp(timestamp);
     }
@@ -297,6 +297,14 @@ impl<'a, W: EntryWriter<'a>> EntryWriter<'a> for Wrapper<'_, W> {
     fn config(&mut self, config: &'a dyn EntryConfig) {
         self.value.config(config);
     }
+
+    fn begin_group(&mut self, name: impl Into<Cow<'a, str>>, prefix: impl Into<Cow<'a, str>>) {
+        self.value.begin_group(name, prefix);
+    }
+
+    fn end_group(&mut self) {
+        self.value.end_group();
+    }
 }
 
 impl<V: Value> Value for Wrapper<'_, V> {
@@ -1,8 +1,8 @@
 // Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: Apache-2.0
 
-use std::any::Any;
-use std::fmt::Debug;
+use core::any::Any;
+use core::fmt::Debug;
 
 /// A trait to define options that can be passed to a metric. This
 /// is basically a fancier `Any`, the formatter implementation should downcast
@@ -108,7 +108,6 @@ mod test {
                             .order
                             == 1
                 );
-                Some(&MyOptions { order: 2 });
                 None
             }
         }
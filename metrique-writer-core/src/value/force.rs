@@ -1,18 +1,18 @@
 // Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: Apache-2.0
 
-use std::{
-    io,
+use core::{
     marker::PhantomData,
     ops::{Deref, DerefMut},
 };
+#[cfg(feature = "std")]
+use std::io;
 
 use derive_where::derive_where;
 
-use crate::{
-    Entry, EntryIoStream, EntryWriter, IoStreamError, Observation, Unit, ValidationError,
-    ValueWriter,
-};
+use crate::{Entry, EntryWriter, Observation, Unit, ValidationError, ValueWriter};
+#[cfg(feature = "std")]
+use crate::{EntryIoStream, IoStreamError};
 
 use super::{MetricFlags, MetricValue, Value};
 
@@ -143,13 +143,14 @@ struct ForceFlagEntryWriter<'a, W, FLAGS: FlagConstructor> {
 impl<'a, W: EntryWriter<'a>, FLAGS: FlagConstructor> EntryWriter<'a>
     for ForceFlagEntryWriter<'_, W, FLAGS>
 {
+    #[cfg(feature = "std")]
     fn timestamp(&mut self, timestamp: std::time::SystemTime) {
         self.writer.timestamp(timestamp)
     }
 
     fn value(
         &mut self,
-        name: impl Into<std::borrow::Cow<'a, str>>,
+        name: impl Into<alloc::borrow::Cow<'a, str>>,
         value: &(impl crate::Value + ?Sized),
     ) {
         self.writer.value(name, &ForceFlag::<_, FLAGS>::from(value))
@@ -169,6 +170,7 @@ impl<E: Entry, FLAGS: FlagConstructor> Entry for ForceFlag<E, FLAGS> {
     }
 }
 
+#[cfg(feature = "std")]
 impl<S: EntryIoStream, FLAGS: FlagConstructor> EntryIoStream for ForceFlag<S, FLAGS> {
     fn next(&mut self, entry: &impl Entry) -> Result<(), IoStreamError> {
         self.0.next(&ForceFlag(entry, self.1))
@@ -177,4 +179,8 @@ impl<S: EntryIoStream, FLAGS: FlagConstructor> EntryIoStream for ForceFlag<S, FL
     fn flush(&mut self) -> io::Result<()> {
         self.0.flush()
     }
+
+    fn bytes_written(&self) -> Option<u64> {
+        self.0.bytes_written()
+    }
 }
@@ -1,9 +1,18 @@
 // Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: Apache-2.0
 
-use std::{borrow::Cow, fmt::Display, marker::PhantomData, sync::Arc};
+use alloc::{
+    borrow::{Cow, ToOwned},
+    boxed::Box,
+    sync::Arc,
+};
+use core::{fmt::Display, marker::PhantomData};
 
-use super::ValueWriter;
+use super::{MetricFlags, MetricValue, Observation, Value, ValueWriter};
+use crate::{
+    Unit, ValidationError,
+    unit::{UnitTag, WithCustomUnit, WithUnit},
+};
 
 mod private {
     pub trait Sealed {}
@@ -96,10 +105,206 @@ pub struct ToString;
 
 impl<T: Display + ?Sized> ValueFormatter<T, NotLifted> for ToString {
     fn format_value(writer: impl ValueWriter, value: &T) {
-        writer.string(&value.to_string());
+        writer.string(&alloc::string::ToString::to_string(value));
     }
 }
 
+/// A `ValueFormatter` that writes a `bool` as a `"true"` or `"false"` string property, instead
+/// of the default `0`/`1` metric.
+///
+/// Unlike [`ToString`], this is lifted over types such as [`Option`]. `#[metrics]` uses it for
+/// `#[metrics(bool_as = "property")]`.
+///
+/// ```
+/// # use metrique_writer::Entry;
+/// # use metrique_writer::value::BoolAsProperty;
+/// #[derive(Entry)]
+/// struct MyMetric {
+///     #[entry(format = BoolAsProperty)]
+///     cache_hit: Option<bool>, // "true", "false", or not written
+/// }
+/// ```
+pub struct BoolAsProperty;
+
+impl ValueFormatter<bool> for BoolAsProperty {
+    fn format_value(writer: impl ValueWriter, value: &bool) {
+        writer.string(if *value { "true" } else { "false" });
+    }
+}
+
+/// A value that may be `None`, formatted by [`NoneAsZero`] and [`NoneAsNull`].
+///
+/// Implemented for [`Option`], and for an [`Option`] behind a reference, [`Box`], [`Arc`] or unit
+/// wrapper, which is what `#[metrics]` closes `Option` fields into.
+pub trait OptionalValue {
+    /// Returns true if the value is `None`
+    fn is_none(&self) -> bool;
+}
+
+impl<T> OptionalValue for Option<T> {
+    fn is_none(&self) -> bool {
+        Option::is_none(self)
+    }
+}
+
+impl<T: OptionalValue + ?Sized> OptionalValue for &T {
+    fn is_none(&self) -> bool {
+        (**self).is_none()
+    }
+}
+
+impl<T: OptionalValue + ?Sized> OptionalValue for Box<T> {
+    fn is_none(&self) -> bool {
+        (**self).is_none()
+    }
+}
+
+impl<T: OptionalValue + ?Sized> OptionalValue for Arc<T> {
+    fn is_none(&self) -> bool {
+        (**self).is_none()
+    }
+}
+
+impl<V: OptionalValue, U> OptionalValue for WithUnit<V, U> {
+    fn is_none(&self) -> bool {
+        (**self).is_none()
+    }
+}
+
+impl<V: OptionalValue> OptionalValue for WithCustomUnit<V> {
+    fn is_none(&self) -> bool {
+        (**self).is_none()
+    }
+}
+
+/// A `ValueFormatter` that writes a `0` metric when the value is `None`.
+///
+/// The `0` is written with the unit of the value's [`MetricValue`]. `#[metrics]` uses it for
+/// `#[metrics(none_as = "zero")]`.
+///
+/// ```
+/// # use metrique_writer::Entry;
+/// # use metrique_writer::value::NoneAsZero;
+/// #[derive(Entry)]
+/// struct MyMetric {
+///     #[entry(format = NoneAsZero)]
+///     retries: Option<u64>, // `0` if `None`
+/// }
+/// ```
+pub struct NoneAsZero;
+
+impl<V: Value + MetricValue + OptionalValue + ?Sized> ValueFormatter<V, NotLifted> for NoneAsZero {
+    fn format_value(writer: impl ValueWriter, value: &V) {
+        if value.is_none() {
+            writer.metric(
+                [Observation::Unsigned(0)],
+                <V::Unit as UnitTag>::UNIT,
+                [],
+                MetricFlags::empty(),
+            );
+        } else {
+            value.write(writer);
+        }
+    }
+}
+
+/// A `ValueFormatter` that writes the string property `"null"` when the value is `None`, so that a
+/// missing value is explicit in the formatted entry.
+///
+/// `#[metrics]` uses it for `#[metrics(none_as = "null")]`.
+///
+/// ```
+/// # use metrique_writer::Entry;
+/// # use metrique_writer::value::NoneAsNull;
+/// #[derive(Entry)]
+/// struct MyMetric {
+///     #[entry(format = NoneAsNull)]
+///     retries: Option<u64>, // `"null"` if `None`
+/// }
+/// ```
+pub struct NoneAsNull;
+
+impl<V: Value + OptionalValue + ?Sized> ValueFormatter<V, NotLifted> for NoneAsNull {
+    fn format_value(writer: impl ValueWriter, value: &V) {
+        if value.is_none() {
+            writer.string("null");
+        } else {
+            value.write(writer);
+        }
+    }
+}
+
+/// A `ValueFormatter` that writes nothing when the value is a metric whose observations are all
+/// zero, such as a counter that was never incremented. Other values are written unchanged.
+///
+/// `#[metrics]` uses it for the fields of a `#[metrics(omit_zeros)]` container.
+///
+/// ```
+/// # use metrique_writer::Entry;
+/// # use metrique_writer::value::OmitZero;
+/// #[derive(Entry)]
+/// struct MyMetric {
+///     #[entry(format = OmitZero)]
+///     throttles: u64, // not written if `0`
+/// }
+/// ```
+pub struct OmitZero;
+
+impl<V: Value + ?Sized> ValueFormatter<V, NotLifted> for OmitZero {
+    fn format_value(writer: impl ValueWriter, value: &V) {
+        if probe(value) != Written::Zero {
+            value.write(writer);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Written {
+    Nothing,
+    Zero,
+    Something,
+}
+
+/// Write `value` to a writer that only records what it was given
+fn probe(value: &(impl Value + ?Sized)) -> Written {
+    struct Probe<'a>(&'a mut Written);
+
+    impl ValueWriter for Probe<'_> {
+        fn string(self, _value: &str) {
+            *self.0 = Written::Something;
+        }
+
+        fn metric<'a>(
+            self,
+            distribution: impl IntoIterator<Item = Observation>,
+            _unit: Unit,
+            _dimensions: impl IntoIterator<Item = (&'a str, &'a str)>,
+            _flags: MetricFlags<'_>,
+        ) {
+            let zero = distribution
+                .into_iter()
+                .all(|observation| match observation {
+                    Observation::Unsigned(value) => value == 0,
+                    Observation::Floating(value) => value == 0.0,
+                    Observation::Repeated { total, .. } => total == 0.0,
+                });
+            *self.0 = if zero {
+                Written::Zero
+            } else {
+                Written::Something
+            };
+        }
+
+        fn error(self, _error: ValidationError) {
+            *self.0 = Written::Something;
+        }
+    }
+
+    let mut written = Written::Nothing;
+    value.write(Probe(&mut written));
+    written
+}
+
 impl<V: ?Sized, F: ?Sized> ValueFormatter<&V> for F
 where
     F: ValueFormatter<V>,
@@ -0,0 +1,47 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! [`Value`] and [`SampleGroup`] implementations for types from the [`http`] crate.
+//!
+//! Status codes and methods are written as string properties, like `"404"` and `"GET"`, since
+//! they are usually used as dimensions rather than aggregated.
+
+use std::borrow::Cow;
+
+use super::{Value, ValueWriter};
+use crate::sample::SampleGroup;
+
+impl Value for http::StatusCode {
+    fn write(&self, writer: impl ValueWriter) {
+        writer.string(self.as_str())
+    }
+}
+
+impl SampleGroup for http::StatusCode {
+    fn as_sample_group(&self) -> Cow<'static, str> {
+        Cow::Owned(self.as_str().to_owned())
+    }
+}
+
+impl Value for http::Method {
+    fn write(&self, writer: impl ValueWriter) {
+        writer.string(self.as_str())
+    }
+}
+
+impl SampleGroup for http::Method {
+    fn as_sample_group(&self) -> Cow<'static, str> {
+        match *self {
+            http::Method::GET => Cow::Borrowed("GET"),
+            http::Method::POST => Cow::Borrowed("POST"),
+            http::Method::PUT => Cow::Borrowed("PUT"),
+            http::Method::DELETE => Cow::Borrowed("DELETE"),
+            http::Method::HEAD => Cow::Borrowed("HEAD"),
+            http::Method::OPTIONS => Cow::Borrowed("OPTIONS"),
+            http::Method::CONNECT => Cow::Borrowed("CONNECT"),
+            http::Method::PATCH => Cow::Borrowed("PATCH"),
+            http::Method::TRACE => Cow::Borrowed("TRACE"),
+            _ => Cow::Owned(self.as_str().to_owned()),
+        }
+    }
+}
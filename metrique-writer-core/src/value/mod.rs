@@ -10,14 +10,26 @@ mod dimensions;
 mod flags;
 mod force;
 mod formatter;
+#[cfg(feature = "http")]
+mod http;
 mod primitive;
+mod truncate;
 
+use alloc::{
+    borrow::{Cow, ToOwned},
+    boxed::Box,
+    string::String,
+    sync::Arc,
+};
 pub use dimensions::{WithDimension, WithDimensions, WithVecDimensions};
 pub use force::{FlagConstructor, ForceFlag};
-pub use formatter::{FormattedValue, Lifted, NotLifted, ToString, ValueFormatter};
-use std::{borrow::Cow, sync::Arc};
+pub use formatter::{
+    BoolAsProperty, FormattedValue, Lifted, NoneAsNull, NoneAsZero, NotLifted, OmitZero,
+    OptionalValue, ToString, ValueFormatter,
+};
 
 pub use flags::{Distribution, MetricFlags, MetricOptions};
+pub use truncate::{DEFAULT_TRUNCATION_MARKER, Truncated, truncate};
 
 use crate::{
     CowStr, Unit, ValidationError,
@@ -107,6 +119,62 @@ pub enum Observation {
     },
 }
 
+impl Observation {
+    /// The sum of the observed values: the value itself, or the `total` of a
+    /// [`Repeated`](Observation::Repeated) observation
+    pub fn total(&self) -> f64 {
+        match *self {
+            Observation::Unsigned(value) => value as f64,
+            Observation::Floating(value) => value,
+            Observation::Repeated { total, .. } => total,
+        }
+    }
+
+    /// The number of observed values: 1, or the `occurrences` of a
+    /// [`Repeated`](Observation::Repeated) observation
+    pub fn occurrences(&self) -> u64 {
+        match *self {
+            Observation::Unsigned(_) | Observation::Floating(_) => 1,
+            Observation::Repeated { occurrences, .. } => occurrences,
+        }
+    }
+
+    /// The value of every occurrence: the value itself, or the mean of a
+    /// [`Repeated`](Observation::Repeated) observation (0 if it has no occurrences)
+    pub fn mean(&self) -> ObservedNumber {
+        match *self {
+            Observation::Unsigned(value) => ObservedNumber::Unsigned(value),
+            Observation::Floating(value) => ObservedNumber::Floating(value),
+            Observation::Repeated { occurrences: 0, .. } => ObservedNumber::Floating(0.0),
+            Observation::Repeated { total, occurrences } => {
+                ObservedNumber::Floating(total / occurrences as f64)
+            }
+        }
+    }
+}
+
+/// A single number of an [`Observation`], see [`Observation::mean`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ObservedNumber {
+    /// An unsigned integer
+    Unsigned(u64),
+    /// A floating point number
+    Floating(f64),
+}
+
+impl ObservedNumber {
+    /// A key that is equal for equal numbers (so `0.0` and `-0.0` have the same key), for formats
+    /// that write equal values once along with their count
+    pub fn key(self) -> (bool, u64) {
+        match self {
+            ObservedNumber::Unsigned(value) => (false, value),
+            // also matches -0.0
+            ObservedNumber::Floating(0.0) => (true, 0),
+            ObservedNumber::Floating(value) => (true, value.to_bits()),
+        }
+    }
+}
+
 impl Value for Observation {
     fn write(&self, writer: impl ValueWriter) {
         writer.metric([*self], unit::None::UNIT, [], MetricFlags::empty())
@@ -175,13 +243,13 @@ impl<T: Value> Value for Option<T> {
     }
 }
 
-impl<T: Value> Value for Box<T> {
+impl<T: Value + ?Sized> Value for Box<T> {
     fn write(&self, writer: impl ValueWriter) {
         (**self).write(writer)
     }
 }
 
-impl<T: Value> Value for Arc<T> {
+impl<T: Value + ?Sized> Value for Arc<T> {
     fn write(&self, writer: impl ValueWriter) {
         (**self).write(writer)
     }
@@ -201,14 +269,60 @@ impl<T: MetricValue> MetricValue for Option<T> {
     type Unit = T::Unit;
 }
 
-impl<T: MetricValue> MetricValue for Box<T> {
+impl<T: MetricValue + ?Sized> MetricValue for Box<T> {
     type Unit = T::Unit;
 }
 
-impl<T: MetricValue> MetricValue for Arc<T> {
+impl<T: MetricValue + ?Sized> MetricValue for Arc<T> {
     type Unit = T::Unit;
 }
 
 impl<T: MetricValue + ToOwned + ?Sized> MetricValue for Cow<'_, T> {
     type Unit = T::Unit;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Observation, ObservedNumber};
+
+    #[test]
+    fn observation_totals_and_means() {
+        let repeated = Observation::Repeated {
+            total: 6.0,
+            occurrences: 4,
+        };
+        assert_eq!(repeated.total(), 6.0);
+        assert_eq!(repeated.occurrences(), 4);
+        assert_eq!(repeated.mean(), ObservedNumber::Floating(1.5));
+        assert_eq!(Observation::Unsigned(3).total(), 3.0);
+        assert_eq!(Observation::Floating(0.5).occurrences(), 1);
+
+        let empty = Observation::Repeated {
+            total: 1.0,
+            occurrences: 0,
+        };
+        assert_eq!(empty.occurrences(), 0);
+        assert_eq!(empty.mean(), ObservedNumber::Floating(0.0));
+    }
+
+    #[test]
+    fn observed_number_keys() {
+        assert_eq!(
+            ObservedNumber::Floating(0.0).key(),
+            ObservedNumber::Floating(-0.0).key()
+        );
+        assert_ne!(
+            ObservedNumber::Unsigned(1).key(),
+            ObservedNumber::Floating(1.0).key()
+        );
+        assert_eq!(
+            ObservedNumber::Floating(2.5).key(),
+            Observation::Repeated {
+                total: 5.0,
+                occurrences: 2
+            }
+            .mean()
+            .key()
+        );
+    }
+}
@@ -1,7 +1,9 @@
 // Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: Apache-2.0
 
-use std::time::Duration;
+use alloc::string::String;
+use core::num::{NonZeroU8, NonZeroU16, NonZeroU32, NonZeroU64, NonZeroUsize};
+use core::time::Duration;
 
 use super::{MetricValue, Observation, Value, ValueWriter};
 use crate::{
@@ -32,6 +34,14 @@ impl Value for String {
     }
 }
 
+/// Bytes are written as a string, replacing invalid UTF-8 with `U+FFFD`
+impl Value for [u8] {
+    #[inline]
+    fn write(&self, writer: impl ValueWriter) {
+        writer.string(&String::from_utf8_lossy(self))
+    }
+}
+
 macro_rules! counter {
     ($t:ty) => {
         impl Value for $t {
@@ -74,6 +84,41 @@ impl MetricValue for usize {
     type Unit = unit::None;
 }
 
+impl Value for u128 {
+    #[inline]
+    fn write(&self, writer: impl ValueWriter) {
+        // values that don't fit in a u64 lose precision rather than being truncated
+        let observation = match u64::try_from(*self) {
+            Ok(value) => Observation::Unsigned(value),
+            Err(_) => Observation::Floating(*self as f64),
+        };
+        writer.metric([observation], Unit::None, [], MetricFlags::empty())
+    }
+}
+
+impl MetricValue for u128 {
+    type Unit = unit::None;
+}
+
+macro_rules! non_zero {
+    ($($t:ty),+) => {
+        $(
+            impl Value for $t {
+                #[inline]
+                fn write(&self, writer: impl ValueWriter) {
+                    self.get().write(writer)
+                }
+            }
+
+            impl MetricValue for $t {
+                type Unit = unit::None;
+            }
+        )+
+    };
+}
+
+non_zero!(NonZeroU8, NonZeroU16, NonZeroU32, NonZeroU64, NonZeroUsize);
+
 macro_rules! float {
     ($t:ty) => {
         impl Value for $t {
@@ -0,0 +1,124 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use alloc::{borrow::Cow, string::String};
+
+use crate::{MetricFlags, Observation, Unit, ValidationError, Value, ValueWriter};
+
+/// The marker that ends truncated strings, unless changed with [`Truncated::with_marker`].
+pub const DEFAULT_TRUNCATION_MARKER: &str = "...";
+
+/// A [`Value`] that writes the strings of another value truncated to at most `max_len`
+/// characters, ending with a marker when they were truncated. Metrics are written unchanged.
+///
+/// This keeps a single huge property, like an error message, from blowing the size limit of the
+/// entry. `#[metrics]` uses it for `#[metrics(max_len = ...)]`, and the `TruncateStrings`
+/// processor of `metrique-writer` applies it to every property of an entry.
+///
+/// ```
+/// # use metrique_writer::Entry;
+/// # use metrique_writer::value::Truncated;
+/// # use metrique_writer::test_util::to_test_entry;
+/// struct Request {
+///     message: String,
+/// }
+///
+/// impl Entry for Request {
+///     fn write<'a>(&'a self, writer: &mut impl metrique_writer::EntryWriter<'a>) {
+///         writer.value("Message", &Truncated::new(&self.message, 8));
+///     }
+/// }
+///
+/// let entry = to_test_entry(Request { message: "connection reset by peer".into() });
+/// assert_eq!(entry.values["Message"], "conne...");
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Truncated<'a, V: ?Sized> {
+    value: &'a V,
+    max_len: usize,
+    marker: &'a str,
+}
+
+impl<'a, V: ?Sized> Truncated<'a, V> {
+    /// Truncate the strings of `value` to at most `max_len` characters, including the
+    /// [`DEFAULT_TRUNCATION_MARKER`]
+    pub fn new(value: &'a V, max_len: usize) -> Self {
+        Self {
+            value,
+            max_len,
+            marker: DEFAULT_TRUNCATION_MARKER,
+        }
+    }
+
+    /// End truncated strings with `marker` instead of [`DEFAULT_TRUNCATION_MARKER`]. Use `""` to
+    /// truncate without a marker.
+    pub fn with_marker(mut self, marker: &'a str) -> Self {
+        self.marker = marker;
+        self
+    }
+}
+
+impl<V: Value + ?Sized> Value for Truncated<'_, V> {
+    fn write(&self, writer: impl ValueWriter) {
+        struct ValueWriterWrapper<'m, W> {
+            writer: W,
+            max_len: usize,
+            marker: &'m str,
+        }
+
+        impl<W: ValueWriter> ValueWriter for ValueWriterWrapper<'_, W> {
+            fn string(self, value: &str) {
+                self.writer
+                    .string(&truncate(value, self.max_len, self.marker))
+            }
+
+            fn metric<'a>(
+                self,
+                distribution: impl IntoIterator<Item = Observation>,
+                unit: Unit,
+                dimensions: impl IntoIterator<Item = (&'a str, &'a str)>,
+                flags: MetricFlags<'_>,
+            ) {
+                self.writer.metric(distribution, unit, dimensions, flags)
+            }
+
+            fn error(self, error: ValidationError) {
+                self.writer.error(error)
+            }
+        }
+
+        self.value.write(ValueWriterWrapper {
+            writer,
+            max_len: self.max_len,
+            marker: self.marker,
+        })
+    }
+}
+
+/// Truncate `value` to at most `max_len` characters, replacing its end with `marker` if it is
+/// longer. If `marker` itself is longer than `max_len`, it is truncated too.
+///
+/// ```
+/// # use metrique_writer_core::value::truncate;
+/// assert_eq!(truncate("héllo wörld", 8, "..."), "héllo...");
+/// assert_eq!(truncate("héllo", 8, "..."), "héllo");
+/// assert_eq!(truncate("héllo", 2, "..."), "..");
+/// ```
+pub fn truncate<'s>(value: &'s str, max_len: usize, marker: &str) -> Cow<'s, str> {
+    let Some((end, _)) = value.char_indices().nth(max_len) else {
+        return Cow::Borrowed(value);
+    };
+    let marker_len = marker.chars().count();
+    if marker_len >= max_len {
+        return Cow::Owned(marker.chars().take(max_len).collect());
+    }
+    // `end` is the byte index of the first character past `max_len`, so this never panics
+    let keep = value[..end]
+        .char_indices()
+        .nth(max_len - marker_len)
+        .map_or(end, |(index, _)| index);
+    let mut truncated = String::with_capacity(keep + marker.len());
+    truncated.push_str(&value[..keep]);
+    truncated.push_str(marker);
+    Cow::Owned(truncated)
+}
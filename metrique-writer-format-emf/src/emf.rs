@@ -7,12 +7,12 @@ use itertools::Itertools;
 use metrique_writer::sample::DefaultRng;
 use metrique_writer::value::{FlagConstructor, ForceFlag, MetricOptions};
 use metrique_writer_core::config::AllowUnroutableEntries;
-use metrique_writer_core::format::Format;
+use metrique_writer_core::format::{FloatPrecision, Format};
 use metrique_writer_core::sample::SampledFormat;
 use metrique_writer_core::stream::IoStreamError;
 use metrique_writer_core::{
-    Entry, EntryConfig, MetricFlags, Observation, Unit, ValidationError, ValidationErrorBuilder,
-    Value,
+    Distribution, Entry, EntryConfig, MetricFlags, Observation, ObservedNumber, Unit,
+    ValidationError, ValidationErrorBuilder, Value,
 };
 use rand::rngs::ThreadRng;
 use rand::{Rng, RngCore};
@@ -90,7 +90,14 @@ struct Validation {
 ///
 /// The "histogram" form allows for emitting metrics with a large `Count` using O(1) cost. It is used
 /// Therefore, it is used when there are multiple [`Observation`]s for a single metric, an
-/// [`Observation::Repeated`], or [sampling].
+/// [`Observation::Repeated`], or [sampling]. Metrics flagged as a
+/// [`Distribution`](metrique_writer_core::Distribution), like histograms, use it even when they only
+/// have a single observation.
+///
+/// Observations with equal values are written once, with the sum of their counts, which is how EMF
+/// compresses repeated samples: 3 observations of `5` and one of `8` are written as
+/// `{ "Values": [5, 8], "Counts": [3, 1] }`. Values are written in the order they were first
+/// observed.
 ///
 /// CloudWatch EMF and CloudWatch Metrics support both forms equally well and "native" CloudWatch Metrics
 /// statistics like sums, averages, and percentiles will work the same in both forms, with no extra
@@ -167,7 +174,9 @@ pub struct Emf {
 struct State {
     namespaces: Vec<JsonEncodedString>,
     each_dimensions_str: Vec<JsonEncodedArray>,
-    log_group_and_timestamp: LogGroupNameAndTimestampString,
+    // the destination hints of the formatter, which entries can override
+    destination: EntryDestination,
+    destination_and_timestamp: DestinationAndTimestampString,
     dimension_set_map: hashbrown::HashMap<DimensionSet, MetricsForDimensionSet>,
 
     // buf that string fields can be added to
@@ -185,6 +194,7 @@ struct State {
     // buf of extra declarations
     decl_buf: PrefixedStringBuf,
     allow_ignored_dimensions: bool,
+    float_precision: FloatPrecision,
 }
 
 /// Serde declaration of EMF's MetricDirective type
@@ -284,37 +294,41 @@ impl Display for JsonEncodedArray {
     }
 }
 
-/// Contains the log group name and timestamp string
-/// It is either
-/// `],"LogGroupName":"log group","Timestamp":`
-/// or
+/// Contains the destination hints and timestamp string, for example
+/// `],"LogGroupName":"log group","AccountId":"111122223333","Timestamp":`
+/// or, without destination hints,
 /// `],"Timestamp":`
 #[derive(Clone)]
-struct LogGroupNameAndTimestampString {
+struct DestinationAndTimestampString {
     encoded: String,
 }
 
-impl LogGroupNameAndTimestampString {
-    fn new(log_group: Option<String>) -> Self {
-        LogGroupNameAndTimestampString {
-            encoded: if let Some(log_group) = log_group {
-                format!(
-                    r#"],"LogGroupName":{},"Timestamp":"#,
-                    serde_json::to_string(&log_group).expect("everything here is valid")
-                )
-            } else {
-                r#"],"Timestamp":"#.to_string()
-            },
+impl DestinationAndTimestampString {
+    fn new(destination: &EntryDestination) -> Self {
+        let mut encoded = String::from("]");
+        for (key, value) in [
+            ("LogGroupName", destination.log_group_name()),
+            ("AccountId", destination.account_id()),
+            ("Region", destination.region()),
+        ] {
+            if let Some(value) = value {
+                encoded.push_str(&format!(
+                    r#","{key}":{}"#,
+                    serde_json::to_string(value).expect("everything here is valid")
+                ));
+            }
         }
+        encoded.push_str(r#","Timestamp":"#);
+        DestinationAndTimestampString { encoded }
     }
 }
 
 trait PushJsonSafeString {
     fn push_json_safe_string<'a>(&'a mut self, s: &JsonEncodedString) -> &'a mut Self;
     fn push_json_safe_array<'a>(&'a mut self, s: &JsonEncodedArray) -> &'a mut Self;
-    fn push_json_safe_log_group_and_timestamp<'a>(
+    fn push_json_safe_destination_and_timestamp<'a>(
         &'a mut self,
-        s: &LogGroupNameAndTimestampString,
+        s: &DestinationAndTimestampString,
         timestamp_str: &str,
     ) -> &'a mut Self;
 }
@@ -328,9 +342,9 @@ impl PushJsonSafeString for PrefixedStringBuf {
         self.push_raw_str(&s.encoded_array)
     }
 
-    fn push_json_safe_log_group_and_timestamp<'a>(
+    fn push_json_safe_destination_and_timestamp<'a>(
         &'a mut self,
-        s: &LogGroupNameAndTimestampString,
+        s: &DestinationAndTimestampString,
         timestamp_str: &str,
     ) -> &'a mut Self {
         self.push_raw_str(&s.encoded).push_raw_str(timestamp_str)
@@ -360,7 +374,8 @@ impl Emf {
             default_dimensions,
             allow_ignored_dimensions: false,
             extra_directives: String::new(),
-            log_group_name: None,
+            destination: EntryDestination::new(),
+            float_precision: FloatPrecision::default(),
             #[cfg(debug_assertions)]
             validation: Validation::default(),
             #[cfg(not(debug_assertions))]
@@ -417,6 +432,7 @@ impl Emf {
                 self.validation_map_base.clone()
             },
             entry_dimensions: None,
+            entry_destination: None,
             state: &mut self.state,
             multiplicity,
             timestamp: None,
@@ -514,7 +530,8 @@ pub struct EmfBuilder {
     namespaces: Vec<String>,
     validation: Validation,
     allow_ignored_dimensions: bool,
-    log_group_name: Option<String>,
+    destination: EntryDestination,
+    float_precision: FloatPrecision,
 }
 
 impl EmfBuilder {
@@ -617,7 +634,9 @@ impl EmfBuilder {
                 metrics_buf: PrefixedStringBuf::new(r#"],"Metrics":["#, 2048),
                 decl_buf: PrefixedStringBuf::new(&self.extra_directives, 256),
                 allow_ignored_dimensions: self.allow_ignored_dimensions,
-                log_group_and_timestamp: LogGroupNameAndTimestampString::new(self.log_group_name),
+                float_precision: self.float_precision,
+                destination_and_timestamp: DestinationAndTimestampString::new(&self.destination),
+                destination: self.destination,
             },
             validation_map_base: validation_map,
             validation: self.validation,
@@ -1018,7 +1037,95 @@ impl EmfBuilder {
     /// );
     /// ```
     pub fn log_group_name(mut self, log_group_name: impl Into<String>) -> Self {
-        self.log_group_name = Some(log_group_name.into());
+        self.destination = self.destination.with_log_group_name(log_group_name.into());
+        self
+    }
+
+    /// Set the destination hints (account id, region and log group name) of every entry.
+    ///
+    /// The hints are written in the `_aws` metadata as `AccountId`, `Region` and `LogGroupName`.
+    /// Entries can override them with an [`EntryDestination`] config, see its documentation for
+    /// how the hints are used.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// # use metrique_writer::{Entry, format::Format as _};
+    /// # use metrique_writer_format_emf::{Emf, EntryDestination};
+    /// # use std::time::SystemTime;
+    /// #[derive(Entry)]
+    /// #[entry(rename_all = "PascalCase")]
+    /// struct MyMetrics {
+    ///     #[entry(timestamp)]
+    ///     start: SystemTime,
+    ///     my_field: u32,
+    /// }
+    ///
+    /// let mut emf = Emf::builder("MyApp".to_string(), vec![vec![]])
+    ///     .destination(EntryDestination::new().with_account_id("111122223333").with_region("us-west-2"))
+    ///     .build();
+    /// let mut output = Vec::new();
+    ///
+    /// emf.format(&MyMetrics {
+    ///     start: SystemTime::UNIX_EPOCH, // use SystemTime::now() in the real world
+    ///     my_field: 4,
+    /// }, &mut output).unwrap();
+    ///
+    /// let output = String::from_utf8(output).unwrap();
+    /// assert_json_diff::assert_json_eq!(serde_json::from_str::<serde_json::Value>(&output).unwrap(),
+    ///     serde_json::json!({
+    ///         "_aws": {
+    ///             "CloudWatchMetrics": [
+    ///                  {"Namespace": "MyApp", "Dimensions": [[]], "Metrics": [{"Name": "MyField"}]},
+    ///             ],
+    ///             "AccountId": "111122223333",
+    ///             "Region": "us-west-2",
+    ///             "Timestamp": 0,
+    ///         },
+    ///         "MyField": 4,
+    ///     })
+    /// );
+    /// ```
+    pub fn destination(mut self, destination: EntryDestination) -> Self {
+        self.destination = destination;
+        self
+    }
+
+    /// Round floating-point metric values to `precision` before writing them.
+    ///
+    /// By default, floats are written with the shortest representation that round-trips to the
+    /// same `f64`, which can bloat entries with digits that are meaningless on a dashboard.
+    /// Integer metric values, and the counts of repeated observations, are never rounded.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use metrique_writer::{Entry, format::{FloatPrecision, Format as _}};
+    /// # use metrique_writer_format_emf::Emf;
+    /// # use std::time::SystemTime;
+    /// #[derive(Entry)]
+    /// #[entry(rename_all = "PascalCase")]
+    /// struct MyMetrics {
+    ///     #[entry(timestamp)]
+    ///     start: SystemTime,
+    ///     cpu_percent: f64,
+    /// }
+    ///
+    /// let mut emf = Emf::builder("MyApp".to_string(), vec![vec![]])
+    ///     .float_precision(FloatPrecision::DecimalPlaces(2))
+    ///     .build();
+    /// let mut output = Vec::new();
+    ///
+    /// emf.format(&MyMetrics {
+    ///     start: SystemTime::UNIX_EPOCH, // use SystemTime::now() in the real world
+    ///     cpu_percent: 100.0 / 3.0,
+    /// }, &mut output).unwrap();
+    ///
+    /// let output = String::from_utf8(output).unwrap();
+    /// assert!(output.contains(r#""CpuPercent":33.33}"#), "{output}");
+    /// ```
+    pub fn float_precision(mut self, precision: FloatPrecision) -> Self {
+        self.float_precision = precision;
         self
     }
 }
@@ -1186,12 +1293,13 @@ impl MetricsForDimensionSet {
     }
 }
 
-pub use metrique_writer_core::config::{AllowSplitEntries, EntryDimensions};
+pub use metrique_writer_core::config::{AllowSplitEntries, EntryDestination, EntryDimensions};
 
 struct EntryWriter<'a> {
     validation_map: hashbrown::HashMap<SCow<'a>, LineData>,
     state: &'a mut State,
     entry_dimensions: Option<Vec<JsonEncodedArray>>,
+    entry_destination: Option<&'a EntryDestination>,
     validations: &'a Validation,
     timestamp: Option<SystemTime>,
     multiplicity: Option<u64>,
@@ -1277,6 +1385,12 @@ impl<'a> metrique_writer_core::EntryWriter<'a> for EntryWriter<'a> {
                 .collect();
             self.entry_dimensions = Some(dimensions);
         }
+        if let Some(destination) = (config as &dyn Any).downcast_ref::<EntryDestination>()
+            && self.entry_destination.replace(destination).is_some()
+        {
+            self.error
+                .invalid_mut("entry destination cannot be set twice");
+        }
         if (config as &dyn Any)
             .downcast_ref::<AllowSplitEntries>()
             .is_some()
@@ -1313,13 +1427,21 @@ impl EntryWriter<'_> {
         let mut timestamp_buf = itoa::Buffer::new();
         let timestamp_str = timestamp_buf.format(unix.as_millis());
         self.error.build()?;
+        let entry_destination = self
+            .entry_destination
+            .filter(|destination| !destination.is_empty())
+            .map(|destination| {
+                DestinationAndTimestampString::new(
+                    &self.state.destination.with_overrides(destination),
+                )
+            });
+        let destination_and_timestamp = entry_destination
+            .as_ref()
+            .unwrap_or(&self.state.destination_and_timestamp);
         self.state
             .decl_buf
             // safe because timestamp is a number
-            .push_json_safe_log_group_and_timestamp(
-                &self.state.log_group_and_timestamp,
-                timestamp_str,
-            );
+            .push_json_safe_destination_and_timestamp(destination_and_timestamp, timestamp_str);
         self.state.string_fields_buf.push_raw_str("}\n");
 
         let mut emitted_any_dimension_metrics = false;
@@ -1337,10 +1459,7 @@ impl EntryWriter<'_> {
             entry
                 .metrics_buf
                 // safe because timestamp is a number
-                .push_json_safe_log_group_and_timestamp(
-                    &self.state.log_group_and_timestamp,
-                    timestamp_str,
-                );
+                .push_json_safe_destination_and_timestamp(destination_and_timestamp, timestamp_str);
             let buf: SmallVec<[_; 3]> = smallvec![
                 entry.metrics_buf.as_ref(),
                 entry.fields_buf.as_ref(),
@@ -1416,8 +1535,54 @@ impl EntryWriter<'_> {
     }
 }
 
+/// The units accepted by CloudWatch
+const CLOUDWATCH_UNITS: &[&str] = &[
+    "Seconds",
+    "Microseconds",
+    "Milliseconds",
+    "Bytes",
+    "Kilobytes",
+    "Megabytes",
+    "Gigabytes",
+    "Terabytes",
+    "Bits",
+    "Kilobits",
+    "Megabits",
+    "Gigabits",
+    "Terabits",
+    "Percent",
+    "Count",
+    "Bytes/Second",
+    "Kilobytes/Second",
+    "Megabytes/Second",
+    "Gigabytes/Second",
+    "Terabytes/Second",
+    "Bits/Second",
+    "Kilobits/Second",
+    "Megabits/Second",
+    "Gigabits/Second",
+    "Terabits/Second",
+    "Count/Second",
+];
+
+/// The `Unit` to declare for a metric, or `None` to leave it out.
+///
+/// CloudWatch rejects units outside of a fixed set, so custom units that aren't CloudWatch units
+/// (like `Widgets`) are left out, which CloudWatch treats as `None`.
+fn emf_unit_name(unit: Unit) -> Option<&'static str> {
+    match unit {
+        Unit::None => None,
+        Unit::Custom(name) if !CLOUDWATCH_UNITS.contains(&name) => None,
+        unit => Some(unit.name()),
+    }
+}
+
 struct FiniteFloat(f64);
 
+/// The number of distinct values up to which equal values are found by a linear search, rather
+/// than through an index
+const LINEAR_SEARCH_VALUES: usize = 16;
+
 fn clamp_to_finite(float: f64, name_for_log: &str) -> Option<FiniteFloat> {
     let float = float.clamp(-f64::MAX, f64::MAX);
     if !float.is_finite() {
@@ -1442,8 +1607,9 @@ struct ValueWriter<'a, 'e> {
 }
 
 impl ValueWriter<'_, '_> {
-    fn write_float(buf: &mut PrefixedStringBuf, v: FiniteFloat) {
+    fn write_float(buf: &mut PrefixedStringBuf, v: FiniteFloat, precision: FloatPrecision) {
         assert!(v.0.is_finite(), "should be checked by the caller");
+        let v = FiniteFloat(precision.round(v.0));
         // We use `dtoa` over `ryu` because `dtoa` always emits decimal notation
         // (no scientific notation), which is easier to script against and more portable
         // across downstream metric consumers.
@@ -1453,62 +1619,26 @@ impl ValueWriter<'_, '_> {
         buf.push_raw_str(as_str.strip_suffix(".0").unwrap_or(as_str));
     }
 
-    // return Err(MetricSkipped) if the observation has been skipped due to being NaN
-    fn write_observation(
-        buf: &mut PrefixedStringBuf,
-        counts: &mut PrefixedStringBuf,
+    // return None if the observation has been skipped due to being NaN
+    fn observation_value(
         observation: Observation,
-        multiplicity: Option<u64>,
+        multiplicity: u64,
         // used purely for logging if there is a NaN
         name_for_log: &str,
-    ) -> Result<(), MetricSkipped> {
-        let multiplicity = multiplicity.unwrap_or(1);
-        match observation {
-            Observation::Unsigned(v) => {
-                buf.push_integer(v);
-                counts.push_integer(multiplicity);
-                Ok(())
-            }
-            Observation::Floating(v) => {
-                if let Some(v) = clamp_to_finite(v, name_for_log) {
-                    Self::write_float(buf, v);
-                    counts.push_integer(multiplicity);
-                    Ok(())
-                } else {
-                    Err(MetricSkipped)
-                }
-            }
-            Observation::Repeated { total, occurrences } => {
-                let mean = if occurrences == 0 {
-                    0.0
-                } else {
-                    total / occurrences as f64
-                };
-                if let Some(mean) = clamp_to_finite(mean, name_for_log) {
-                    Self::write_float(buf, mean);
-                    counts.push_integer(occurrences.saturating_mul(multiplicity));
-                    Ok(())
-                } else {
-                    Err(MetricSkipped)
-                }
-            }
-            _ => {
-                // shouldn't actually happen unless there is a version mismatch,
-                // but Observation is `#[non_exhaustive]`. Do something reasonable.
-                rate_limited!(
-                    Duration::from_secs(1),
-                    tracing::error!(
-                        message="skipping emitting metric due to unknown observation type",
-                        metric=%name_for_log,
-                    )
-                );
-                Err(MetricSkipped)
+    ) -> Option<(ObservedNumber, u64)> {
+        let count = observation.occurrences().saturating_mul(multiplicity);
+        match observation.mean() {
+            ObservedNumber::Unsigned(v) => Some((ObservedNumber::Unsigned(v), count)),
+            ObservedNumber::Floating(v) => {
+                let v = clamp_to_finite(v, name_for_log)?;
+                Some((ObservedNumber::Floating(v.0), count))
             }
         }
     }
 
     // return Err(MetricSkipped) and writes only to `buf` and `counts_buf`
     // (not touching `fields_buf`) if the metric is NaN
+    #[allow(clippy::too_many_arguments)]
     fn write_metric_value(
         name: &str,
         fields_buf: &mut PrefixedStringBuf,
@@ -1516,53 +1646,86 @@ impl ValueWriter<'_, '_> {
         first: Observation,
         mut distribution: impl Iterator<Item = Observation>,
         multiplicity: Option<u64>,
+        is_distribution: bool,
+        precision: FloatPrecision,
     ) -> Result<(), MetricSkipped> {
         let buf: &mut PrefixedStringBuf = fields_buf;
         buf.push(',').json_string(name).push(':');
         match (first, distribution.next()) {
-            (Observation::Unsigned(v), None) if multiplicity.is_none() => {
+            (Observation::Unsigned(v), None) if multiplicity.is_none() && !is_distribution => {
                 buf.push_integer(v);
                 Ok(())
             }
-            (Observation::Floating(v), None) if multiplicity.is_none() => {
+            (Observation::Floating(v), None) if multiplicity.is_none() && !is_distribution => {
                 if let Some(v) = clamp_to_finite(v, name) {
-                    Self::write_float(buf, v);
+                    Self::write_float(buf, v, precision);
                     Ok(())
                 } else {
                     Err(MetricSkipped)
                 }
             }
             (first, second) => {
+                // Equal values are written once, with the sum of their counts, which is how EMF
+                // compresses repeated samples. This doesn't enforce the limit of CloudWatch, which
+                // rejects metrics with more than 100 distinct values, but it keeps compressing past
+                // it, using an index once linear search gets expensive.
+                let multiplicity = multiplicity.unwrap_or(1);
+                let mut values: SmallVec<[(ObservedNumber, u64); 4]> = SmallVec::new();
+                let mut index: Option<hashbrown::HashMap<(bool, u64), usize>> = None;
+                for observation in iter::once(first).chain(second).chain(distribution) {
+                    let Some((value, count)) =
+                        Self::observation_value(observation, multiplicity, name)
+                    else {
+                        continue;
+                    };
+                    let existing = match &index {
+                        Some(index) => index.get(&value.key()).copied(),
+                        None => values.iter().position(|(existing, _)| *existing == value),
+                    };
+                    match existing {
+                        Some(i) => values[i].1 = values[i].1.saturating_add(count),
+                        None => {
+                            if let Some(index) = &mut index {
+                                index.insert(value.key(), values.len());
+                            }
+                            values.push((value, count));
+                            if index.is_none() && values.len() > LINEAR_SEARCH_VALUES {
+                                index = Some(
+                                    (values.iter().enumerate())
+                                        .map(|(i, (value, _))| (value.key(), i))
+                                        .collect(),
+                                );
+                            }
+                        }
+                    }
+                }
+                if values.is_empty() {
+                    return Err(MetricSkipped);
+                }
+
                 let counts = counts_buf;
                 buf.push_raw_str(r#"{"Values":["#);
-                let mut wrote_anything = false;
                 counts.clear(); // clear before to make sure there is no risk
-                for observation in iter::once(first).chain(second).chain(distribution) {
-                    let prev_buf_len = buf.as_str().len();
-                    let prev_counts_len = counts.as_str().len();
-                    if wrote_anything {
+                for (i, (value, count)) in values.into_iter().enumerate() {
+                    if i > 0 {
                         buf.push(',');
                         counts.push(',');
                     }
-                    if Self::write_observation(buf, counts, observation, multiplicity, name).is_ok()
-                    {
-                        wrote_anything = true;
-                    } else {
-                        // Restore state if this observation was skipped (e.g. NaN) so we
-                        // never leave trailing separators behind.
-                        buf.truncate(prev_buf_len);
-                        counts.truncate(prev_counts_len);
+                    match value {
+                        ObservedNumber::Unsigned(v) => {
+                            buf.push_integer(v);
+                        }
+                        ObservedNumber::Floating(v) => {
+                            Self::write_float(buf, FiniteFloat(v), precision)
+                        }
                     }
+                    counts.push_integer(count);
                 }
                 // injection-safe because this is a comma-separated list of numbers
                 buf.push_raw_str(counts.as_str());
                 counts.clear(); // clear after to ensure this is not too big
                 buf.push_raw_str("]}");
-                if wrote_anything {
-                    Ok(())
-                } else {
-                    Err(MetricSkipped)
-                }
+                Ok(())
             }
         }
     }
@@ -1577,6 +1740,7 @@ impl ValueWriter<'_, '_> {
         unit: Unit,
         flags: MetricFlags<'_>,
         multiplicity: Option<u64>,
+        precision: FloatPrecision,
     ) -> Result<(), ValidationError> {
         let mut distribution = distribution.into_iter();
         let Some(first) = distribution.next() else {
@@ -1599,6 +1763,8 @@ impl ValueWriter<'_, '_> {
             first,
             distribution,
             multiplicity,
+            flags.downcast::<Distribution>().is_some(),
+            precision,
         ) {
             // skipping this metric, truncate the metric name
             fields_buf.truncate(fields_buf_index);
@@ -1621,10 +1787,8 @@ impl ValueWriter<'_, '_> {
             metrics_buf.push(',');
         }
         metrics_buf.push_raw_str(r#"{"Name":"#).json_string(name);
-        if unit != Unit::None {
-            metrics_buf
-                .push_raw_str(r#","Unit":"#)
-                .json_string(unit.name());
+        if let Some(unit) = emf_unit_name(unit) {
+            metrics_buf.push_raw_str(r#","Unit":"#).json_string(unit);
         }
         if let Some(EmfOptions {
             storage_mode: StorageMode::HighStorageResolution,
@@ -1770,6 +1934,7 @@ impl metrique_writer_core::ValueWriter for ValueWriter<'_, '_> {
             unit,
             flags,
             self.entry.multiplicity,
+            self.entry.state.float_precision,
         ) {
             self.error(err);
         }
@@ -2381,7 +2546,7 @@ mod tests {
             .unwrap();
         // more than 1 billion milliseconds difference = probably not a flaky test
         if now.abs_diff(json_now) > 1_000_000_000 {
-            assert!(false, "time is not sane {now} {json_now}");
+            panic!("time is not sane {now} {json_now}");
         }
     }
 
@@ -2702,7 +2867,7 @@ mod tests {
                 assert_eq!(output.pop().unwrap(), b"");
                 assert_eq!(output.len(), 4);
                 output.sort();
-                let json: serde_json::Value = serde_json::from_slice(&output[0]).unwrap();
+                let json: serde_json::Value = serde_json::from_slice(output[0]).unwrap();
 
                 let expected = r#"
                 {
@@ -2741,10 +2906,10 @@ mod tests {
                 "#;
                 assert_json_diff::assert_json_eq!(
                     json,
-                    serde_json::from_str::<serde_json::Value>(&expected).unwrap()
+                    serde_json::from_str::<serde_json::Value>(expected).unwrap()
                 );
 
-                let json: serde_json::Value = serde_json::from_slice(&output[1]).unwrap();
+                let json: serde_json::Value = serde_json::from_slice(output[1]).unwrap();
                 let expected: &str = r#"
             {
                 "API": "MyAPI",
@@ -2783,7 +2948,7 @@ mod tests {
             "#;
                 assert_json_diff::assert_json_eq!(
                     json,
-                    serde_json::from_str::<serde_json::Value>(&expected).unwrap()
+                    serde_json::from_str::<serde_json::Value>(expected).unwrap()
                 );
                 let expected: &str = r#"
             {
@@ -2817,11 +2982,11 @@ mod tests {
                 }
             }
             "#;
-                let json: serde_json::Value = serde_json::from_slice(&output[2]).unwrap();
+                let json: serde_json::Value = serde_json::from_slice(output[2]).unwrap();
 
                 assert_json_diff::assert_json_eq!(
                     json,
-                    serde_json::from_str::<serde_json::Value>(&expected).unwrap()
+                    serde_json::from_str::<serde_json::Value>(expected).unwrap()
                 );
 
                 let expected = format!(
@@ -2863,7 +3028,7 @@ mod tests {
             }
             "#
                 );
-                let json: serde_json::Value = serde_json::from_slice(&output[3]).unwrap();
+                let json: serde_json::Value = serde_json::from_slice(output[3]).unwrap();
                 assert_json_diff::assert_json_eq!(
                     json,
                     serde_json::from_str::<serde_json::Value>(&expected).unwrap()
@@ -3005,7 +3170,7 @@ mod tests {
             }
             "#
                 );
-                let json: serde_json::Value = serde_json::from_slice(&output[0]).unwrap();
+                let json: serde_json::Value = serde_json::from_slice(output[0]).unwrap();
 
                 assert_json_diff::assert_json_eq!(
                     json,
@@ -3049,7 +3214,7 @@ mod tests {
             }
             "#
                 );
-                let json: serde_json::Value = serde_json::from_slice(&output[1]).unwrap();
+                let json: serde_json::Value = serde_json::from_slice(output[1]).unwrap();
                 assert_json_diff::assert_json_eq!(
                     json,
                     serde_json::from_str::<serde_json::Value>(&expected).unwrap()
@@ -3136,6 +3301,64 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_entry_destination() {
+        struct TestEntry(Vec<EntryDestination>);
+        impl Entry for TestEntry {
+            fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+                writer.timestamp(SystemTime::UNIX_EPOCH);
+                for destination in &self.0 {
+                    writer.config(destination);
+                }
+                writer.value("Count", &1u64);
+            }
+        }
+
+        let mut emf = Emf::builder("TestNS".to_string(), vec![vec![]])
+            .destination(
+                EntryDestination::new()
+                    .with_log_group_name("Default")
+                    .with_account_id("111111111111"),
+            )
+            .build();
+        let mut format = |entry: TestEntry| {
+            let mut output = vec![];
+            emf.format(&entry, &mut output)?;
+            let output: serde_json::Value = serde_json::from_slice(&output).unwrap();
+            Ok::<_, IoStreamError>(output["_aws"].clone())
+        };
+
+        let aws = format(TestEntry(vec![])).unwrap();
+        assert_eq!(aws["LogGroupName"], "Default");
+        assert_eq!(aws["AccountId"], "111111111111");
+        assert!(aws.get("Region").is_none());
+
+        let aws = format(TestEntry(vec![
+            EntryDestination::new()
+                .with_account_id("222222222222")
+                .with_region("eu-west-1"),
+        ]))
+        .unwrap();
+        assert_eq!(aws["LogGroupName"], "Default");
+        assert_eq!(aws["AccountId"], "222222222222");
+        assert_eq!(aws["Region"], "eu-west-1");
+
+        // the formatter's destination is used again for the next entry
+        let aws = format(TestEntry(vec![EntryDestination::new()])).unwrap();
+        assert_eq!(aws["AccountId"], "111111111111");
+
+        let err = format(TestEntry(vec![
+            EntryDestination::new().with_region("eu-west-1"),
+            EntryDestination::new().with_region("us-east-1"),
+        ]))
+        .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("entry destination cannot be set twice"),
+            "{err}"
+        );
+    }
+
     #[rstest]
     #[case(false)]
     #[case(true)]
@@ -3208,12 +3431,11 @@ mod tests {
                 }
             }
             "#;
-                    let json: serde_json::Value =
-                        serde_json::from_slice(&output.remove(0)).unwrap();
+                    let json: serde_json::Value = serde_json::from_slice(output.remove(0)).unwrap();
 
                     assert_json_diff::assert_json_eq!(
                         json,
-                        serde_json::from_str::<serde_json::Value>(&expected).unwrap()
+                        serde_json::from_str::<serde_json::Value>(expected).unwrap()
                     );
                 }
                 let expected = format!(
@@ -3248,7 +3470,7 @@ mod tests {
             }
             "#
                 );
-                let json: serde_json::Value = serde_json::from_slice(&output[0]).unwrap();
+                let json: serde_json::Value = serde_json::from_slice(output[0]).unwrap();
                 assert_json_diff::assert_json_eq!(
                     json,
                     serde_json::from_str::<serde_json::Value>(&expected).unwrap()
@@ -3332,8 +3554,8 @@ mod tests {
                 assert_eq!(output.pop().unwrap(), b"");
                 assert_eq!(output.len(), 1);
                 output.sort();
-                eprintln!("{}", str::from_utf8(&output[0]).unwrap());
-                let json: serde_json::Value = serde_json::from_slice(&output[0]).unwrap();
+                eprintln!("{}", str::from_utf8(output[0]).unwrap());
+                let json: serde_json::Value = serde_json::from_slice(output[0]).unwrap();
 
                 let expected = r#"
                 {
@@ -3367,7 +3589,7 @@ mod tests {
                 "#;
                 assert_json_diff::assert_json_eq!(
                     json,
-                    serde_json::from_str::<serde_json::Value>(&expected).unwrap()
+                    serde_json::from_str::<serde_json::Value>(expected).unwrap()
                 );
             }
         }
@@ -3414,7 +3636,7 @@ mod tests {
                 assert_eq!(output.pop().unwrap(), b"");
                 assert_eq!(output.len(), 1);
                 output.sort();
-                let json: serde_json::Value = serde_json::from_slice(&output[0]).unwrap();
+                let json: serde_json::Value = serde_json::from_slice(output[0]).unwrap();
 
                 let expected = r#"
                 {
@@ -3443,7 +3665,7 @@ mod tests {
                 "#;
                 assert_eq!(
                     json,
-                    serde_json::from_str::<serde_json::Value>(&expected).unwrap()
+                    serde_json::from_str::<serde_json::Value>(expected).unwrap()
                 );
             }
         }
@@ -3482,7 +3704,7 @@ mod tests {
                 assert_eq!(output.pop().unwrap(), b"");
                 assert_eq!(output.len(), 1);
                 output.sort();
-                let json: serde_json::Value = serde_json::from_slice(&output[0]).unwrap();
+                let json: serde_json::Value = serde_json::from_slice(output[0]).unwrap();
 
                 let expected = r#"
                 {
@@ -3500,7 +3722,7 @@ mod tests {
                 "#;
                 assert_json_diff::assert_json_eq!(
                     json,
-                    serde_json::from_str::<serde_json::Value>(&expected).unwrap()
+                    serde_json::from_str::<serde_json::Value>(expected).unwrap()
                 );
             }
         }
@@ -3510,10 +3732,10 @@ mod tests {
         check(Emf::all_validations("TestNS".to_string(), vec![vec![]]));
     }
 
-    const STORAGE_HIRES: &'static EmfOptions = &EmfOptions {
+    const STORAGE_HIRES: &EmfOptions = &EmfOptions {
         storage_mode: StorageMode::HighStorageResolution,
     };
-    const STORAGE_NO_METRIC: &'static EmfOptions = &EmfOptions {
+    const STORAGE_NO_METRIC: &EmfOptions = &EmfOptions {
         storage_mode: StorageMode::NoMetric,
     };
 
@@ -3666,7 +3888,7 @@ mod tests {
         "#;
         assert_json_diff::assert_json_eq!(
             json,
-            serde_json::from_str::<serde_json::Value>(&expected).unwrap()
+            serde_json::from_str::<serde_json::Value>(expected).unwrap()
         );
     }
 
@@ -3753,4 +3975,118 @@ mod tests {
             })
         );
     }
+
+    #[test]
+    fn equal_observations_are_compressed() {
+        struct SingleDistribution;
+        impl Value for SingleDistribution {
+            fn write(&self, writer: impl metrique_writer::ValueWriter) {
+                writer.metric(
+                    [Observation::Unsigned(7)],
+                    Unit::None,
+                    [],
+                    MetricFlags::upcast(&metrique_writer_core::Distribution),
+                );
+            }
+        }
+
+        struct TestEntry;
+        impl Entry for TestEntry {
+            fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+                writer.timestamp(SystemTime::UNIX_EPOCH);
+                writer.value(
+                    "Latency",
+                    &Distribution::<u64>::from_iter([5, 8, 5, 5, 8, 13]),
+                );
+                writer.value(
+                    "Mixed",
+                    &Distribution::<f64>::from_iter([2.5, f64::NAN, 2.5]),
+                );
+                writer.value("Single", &SingleDistribution);
+            }
+        }
+
+        let mut emf = Emf::builder("TestNS".to_string(), vec![vec![]]).build();
+        let mut output = Vec::new();
+        emf.format(&TestEntry, &mut output).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&output).unwrap();
+
+        assert_json_eq!(
+            json["Latency"],
+            serde_json::json!({ "Values": [5, 8, 13], "Counts": [3, 2, 1] })
+        );
+        assert_json_eq!(
+            json["Mixed"],
+            serde_json::json!({ "Values": [2.5], "Counts": [2] })
+        );
+        // a distribution keeps the histogram form with a single observation
+        assert_json_eq!(
+            json["Single"],
+            serde_json::json!({ "Values": [7], "Counts": [1] })
+        );
+    }
+
+    #[test]
+    fn observations_are_compressed_past_100_values() {
+        struct TestEntry;
+        impl Entry for TestEntry {
+            fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+                writer.timestamp(SystemTime::UNIX_EPOCH);
+                let values = (0..200).chain(0..200);
+                writer.value("Latency", &Distribution::<u64>::from_iter(values));
+                writer.value(
+                    "Signed",
+                    &Distribution::<f64>::from_iter((0..40).map(|_| 0.0).chain([-0.0])),
+                );
+            }
+        }
+
+        let mut emf = Emf::builder("TestNS".to_string(), vec![vec![]]).build();
+        let mut output = Vec::new();
+        emf.format(&TestEntry, &mut output).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&output).unwrap();
+
+        let values: Vec<u64> = (0..200).collect();
+        assert_json_eq!(
+            json["Latency"],
+            serde_json::json!({ "Values": values, "Counts": vec![2; values.len()] })
+        );
+        assert_json_eq!(
+            json["Signed"],
+            serde_json::json!({ "Values": [0], "Counts": [41] })
+        );
+    }
+
+    #[test]
+    fn float_precision_rounds_floats_but_not_integers() {
+        struct TestEntry;
+        impl Entry for TestEntry {
+            fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+                writer.timestamp(SystemTime::UNIX_EPOCH);
+                writer.value("Float", &1.23456f64);
+                writer.value("Integer", &123456u64);
+                writer.value(
+                    "Distribution",
+                    &Distribution::<f64>::from_iter([0.123456, 98765.4321]),
+                );
+            }
+        }
+
+        let mut emf = Emf::builder("TestNS".to_string(), vec![vec![]])
+            .float_precision(FloatPrecision::SignificantDigits(3))
+            .build();
+        let mut output = Vec::new();
+        emf.format(&TestEntry, &mut output).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&output).unwrap();
+
+        assert_eq!(json["Float"], serde_json::json!(1.23));
+        assert_eq!(json["Integer"], serde_json::json!(123456));
+        assert_json_eq!(
+            json["Distribution"],
+            serde_json::json!({
+                "Values": [0.123, 98800],
+                "Counts": [1, 1],
+            })
+        );
+    }
 }
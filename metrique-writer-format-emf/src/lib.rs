@@ -9,9 +9,11 @@ mod buf;
 mod emf;
 mod json_string;
 mod rate_limit;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
 
 pub use emf::{
-    AllowSplitEntries, Emf, EmfBuilder, EntryDimensions, HighStorageResolution,
+    AllowSplitEntries, Emf, EmfBuilder, EntryDestination, EntryDimensions, HighStorageResolution,
     HighStorageResolutionCtor, MetricDefinition, MetricDirective, NoMetric, NoMetricCtor,
     SampledEmf, StorageResolution,
 };
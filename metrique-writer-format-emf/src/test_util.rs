@@ -0,0 +1,385 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Parse EMF output back into [`TestEntry`]s, to validate the actual wire format in tests.
+//!
+//! This requires that the `test-util` feature be enabled.
+//!
+//! ```
+//! use std::time::Duration;
+//! use metrique_writer::{Entry, EntryIoStream, FormatExt, Unit};
+//! use metrique_writer::unit::{Millisecond, UnitTag};
+//! use metrique_writer_format_emf::Emf;
+//! use metrique_writer_format_emf::test_util::parse_emf_lines;
+//!
+//! #[derive(Entry)]
+//! struct MyEntry {
+//!     operation: &'static str,
+//!     latency: Duration,
+//! }
+//!
+//! let mut output = vec![];
+//! let mut stream = Emf::all_validations("MyApp".into(), vec![vec!["operation".into()]])
+//!     .output_to(&mut output);
+//! stream.next(&MyEntry { operation: "Get", latency: Duration::from_millis(5) }).unwrap();
+//! stream.flush().unwrap();
+//! drop(stream);
+//!
+//! let entries = parse_emf_lines(std::str::from_utf8(&output).unwrap()).unwrap();
+//! assert_eq!(entries.len(), 1);
+//! assert_eq!(entries[0].values["operation"], "Get");
+//! assert_eq!(entries[0].metrics["latency"], 5);
+//! assert_eq!(entries[0].metrics["latency"].unit, Millisecond::UNIT);
+//! assert_eq!(
+//!     entries[0].metrics["latency"].dimensions,
+//!     [("operation".to_string(), "Get".to_string())]
+//! );
+//! ```
+
+use std::{
+    fmt,
+    time::{Duration, SystemTime},
+};
+
+use metrique_writer::{
+    Entry, EntryWriter, MetricFlags, Observation, Unit, Value, ValueWriter,
+    test_util::{TestEntry, to_test_entry},
+    unit::{NegativeScale, PositiveScale},
+};
+use serde_json::{Map, Value as JsonValue};
+
+/// The error returned when EMF output can't be parsed
+#[derive(Debug, Clone)]
+pub struct EmfParseError(String);
+
+impl fmt::Display for EmfParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid EMF: {}", self.0)
+    }
+}
+
+impl std::error::Error for EmfParseError {}
+
+fn err(message: impl Into<String>) -> EmfParseError {
+    EmfParseError(message.into())
+}
+
+/// Parse every non-empty line of `output` with [`parse_emf`]
+pub fn parse_emf_lines(output: &str) -> Result<Vec<TestEntry>, EmfParseError> {
+    output
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(parse_emf)
+        .collect()
+}
+
+/// Parse a single EMF JSON object into a [`TestEntry`].
+///
+/// - Fields declared in a `CloudWatchMetrics` directive become metrics, with the declared unit.
+///   `{"Values": [...], "Counts": [...]}` distributions become one observation per value, or a
+///   repeated observation if its count is not 1.
+/// - The dimensions of a metric are the (deduplicated) names of all the dimension sets of the
+///   directives declaring it, with their values taken from the corresponding properties. Note that
+///   this includes entry-level dimensions, which are not part of a [`TestEntry`] built in memory.
+/// - All other fields become properties. Non-string properties are kept as their JSON text.
+/// - The `Timestamp`, in milliseconds, becomes the timestamp of the entry.
+pub fn parse_emf(line: &str) -> Result<TestEntry, EmfParseError> {
+    let JsonValue::Object(mut object) =
+        serde_json::from_str(line).map_err(|e| err(e.to_string()))?
+    else {
+        return Err(err("expected a JSON object"));
+    };
+    let Some(JsonValue::Object(aws)) = object.remove("_aws") else {
+        return Err(err("missing `_aws` object"));
+    };
+    let timestamp = match aws.get("Timestamp") {
+        Some(timestamp) => Some(
+            timestamp
+                .as_u64()
+                .map(|ms| SystemTime::UNIX_EPOCH + Duration::from_millis(ms))
+                .ok_or_else(|| err("`Timestamp` must be an integer"))?,
+        ),
+        None => None,
+    };
+
+    // metric name => (unit, dimension names), in declaration order
+    let mut declared: Vec<(String, Unit, Vec<String>)> = vec![];
+    for directive in array(&aws, "CloudWatchMetrics")? {
+        let directive = directive
+            .as_object()
+            .ok_or_else(|| err("`CloudWatchMetrics` entries must be objects"))?;
+        let mut dimensions: Vec<String> = vec![];
+        for set in array(directive, "Dimensions")? {
+            for dimension in set
+                .as_array()
+                .ok_or_else(|| err("dimension sets must be arrays"))?
+            {
+                let dimension = dimension
+                    .as_str()
+                    .ok_or_else(|| err("dimension names must be strings"))?;
+                if !dimensions.iter().any(|d| d == dimension) {
+                    dimensions.push(dimension.to_owned());
+                }
+            }
+        }
+        for metric in array(directive, "Metrics")? {
+            let name = metric
+                .get("Name")
+                .and_then(JsonValue::as_str)
+                .ok_or_else(|| err("metric definitions must have a `Name`"))?;
+            let unit = match metric.get("Unit") {
+                Some(unit) => parse_unit(
+                    unit.as_str()
+                        .ok_or_else(|| err("`Unit` must be a string"))?,
+                ),
+                None => Unit::None,
+            };
+            match declared.iter_mut().find(|(n, ..)| n == name) {
+                Some((_, _, existing)) => {
+                    for dimension in &dimensions {
+                        if !existing.contains(dimension) {
+                            existing.push(dimension.clone());
+                        }
+                    }
+                }
+                None => declared.push((name.to_owned(), unit, dimensions.clone())),
+            }
+        }
+    }
+
+    let mut metrics = vec![];
+    for (name, unit, dimensions) in declared {
+        let value = object
+            .get(&name)
+            .ok_or_else(|| err(format!("metric `{name}` is declared but not present")))?;
+        let dimensions = dimensions
+            .into_iter()
+            .map(|dimension| {
+                let value = object
+                    .get(&dimension)
+                    .and_then(JsonValue::as_str)
+                    .ok_or_else(|| {
+                        err(format!("dimension `{dimension}` is not a string property"))
+                    })?;
+                Ok((dimension, value.to_owned()))
+            })
+            .collect::<Result<_, EmfParseError>>()?;
+        let distribution = parse_distribution(&name, value)?;
+        metrics.push((
+            name,
+            ParsedMetric {
+                distribution,
+                unit,
+                dimensions,
+            },
+        ));
+    }
+    let properties = object
+        .into_iter()
+        .filter(|(name, _)| !metrics.iter().any(|(metric, _)| metric == name))
+        .map(|(name, value)| match value {
+            JsonValue::String(value) => (name, value),
+            value => (name, value.to_string()),
+        })
+        .collect();
+
+    Ok(to_test_entry(ParsedEntry {
+        timestamp,
+        properties,
+        metrics,
+    }))
+}
+
+fn array<'a>(
+    object: &'a Map<String, JsonValue>,
+    key: &str,
+) -> Result<&'a [JsonValue], EmfParseError> {
+    object
+        .get(key)
+        .and_then(JsonValue::as_array)
+        .map(Vec::as_slice)
+        .ok_or_else(|| err(format!("missing `{key}` array")))
+}
+
+fn parse_observation(value: &JsonValue) -> Option<Observation> {
+    match value.as_u64() {
+        Some(v) => Some(Observation::Unsigned(v)),
+        None => value.as_f64().map(Observation::Floating),
+    }
+}
+
+fn parse_distribution(name: &str, value: &JsonValue) -> Result<Vec<Observation>, EmfParseError> {
+    let invalid = || {
+        err(format!(
+            "metric `{name}` must be a number or a Values/Counts object"
+        ))
+    };
+    if let Some(observation) = parse_observation(value) {
+        return Ok(vec![observation]);
+    }
+    let values = value
+        .get("Values")
+        .and_then(JsonValue::as_array)
+        .ok_or_else(invalid)?;
+    let counts = match value.get("Counts") {
+        Some(counts) => counts.as_array().ok_or_else(invalid)?.clone(),
+        None => vec![JsonValue::from(1); values.len()],
+    };
+    if values.len() != counts.len() {
+        return Err(err(format!(
+            "metric `{name}` has {} values but {} counts",
+            values.len(),
+            counts.len()
+        )));
+    }
+    values
+        .iter()
+        .zip(&counts)
+        .map(|(value, count)| {
+            let observation = parse_observation(value).ok_or_else(invalid)?;
+            match count.as_u64().ok_or_else(invalid)? {
+                1 => Ok(observation),
+                occurrences => {
+                    let value = match observation {
+                        Observation::Unsigned(v) => v as f64,
+                        Observation::Floating(v) => v,
+                        _ => unreachable!("only parsed as Unsigned or Floating"),
+                    };
+                    Ok(Observation::Repeated {
+                        total: value * occurrences as f64,
+                        occurrences,
+                    })
+                }
+            }
+        })
+        .collect()
+}
+
+fn parse_unit(name: &str) -> Unit {
+    const NEGATIVE: [NegativeScale; 3] = [
+        NegativeScale::Micro,
+        NegativeScale::Milli,
+        NegativeScale::One,
+    ];
+    const POSITIVE: [PositiveScale; 5] = [
+        PositiveScale::One,
+        PositiveScale::Kilo,
+        PositiveScale::Mega,
+        PositiveScale::Giga,
+        PositiveScale::Tera,
+    ];
+    [Unit::None, Unit::Count, Unit::Percent]
+        .into_iter()
+        .chain(NEGATIVE.map(Unit::Second))
+        .chain(POSITIVE.map(Unit::Byte))
+        .chain(POSITIVE.map(Unit::BytePerSecond))
+        .chain(POSITIVE.map(Unit::Bit))
+        .chain(POSITIVE.map(Unit::BitPerSecond))
+        .find(|unit| unit.name() == name)
+        // custom units need a `&'static str`. Leaking is fine since this is only used in tests.
+        .unwrap_or_else(|| Unit::Custom(Box::leak(name.to_owned().into_boxed_str())))
+}
+
+struct ParsedEntry {
+    timestamp: Option<SystemTime>,
+    properties: Vec<(String, String)>,
+    metrics: Vec<(String, ParsedMetric)>,
+}
+
+struct ParsedMetric {
+    distribution: Vec<Observation>,
+    unit: Unit,
+    dimensions: Vec<(String, String)>,
+}
+
+impl Entry for ParsedEntry {
+    fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+        if let Some(timestamp) = self.timestamp {
+            writer.timestamp(timestamp);
+        }
+        for (name, value) in &self.properties {
+            writer.value(name.as_str(), value.as_str());
+        }
+        for (name, metric) in &self.metrics {
+            writer.value(name.as_str(), metric);
+        }
+    }
+}
+
+impl Value for ParsedMetric {
+    fn write(&self, writer: impl ValueWriter) {
+        writer.metric(
+            self.distribution.iter().copied(),
+            self.unit,
+            self.dimensions
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str())),
+            MetricFlags::empty(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_metrics_properties_and_dimensions() {
+        let entry = parse_emf(
+            r#"{"_aws":{"CloudWatchMetrics":[
+                {"Namespace":"MyApp","Dimensions":[["Operation"],[]],"Metrics":[{"Name":"Time","Unit":"Milliseconds"}]},
+                {"Namespace":"MyApp","Dimensions":[[]],"Metrics":[{"Name":"Sizes","Unit":"Kilobytes"},{"Name":"Custom","Unit":"Furlongs"}]}
+            ],"Timestamp":1500},
+            "Operation":"Get","Time":4.5,"Sizes":{"Values":[1,2.5],"Counts":[1,4]},"Custom":3,"Retries":2}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            entry.timestamp,
+            Some(SystemTime::UNIX_EPOCH + Duration::from_millis(1500))
+        );
+        assert_eq!(entry.values["Operation"], "Get");
+        // undeclared numbers are properties in EMF
+        assert_eq!(entry.values["Retries"], "2");
+        assert_eq!(entry.metrics["Time"], 4.5);
+        assert_eq!(
+            entry.metrics["Time"].unit,
+            Unit::Second(NegativeScale::Milli)
+        );
+        assert_eq!(
+            entry.metrics["Time"].dimensions,
+            [("Operation".to_string(), "Get".to_string())]
+        );
+        assert_eq!(
+            entry.metrics["Sizes"].distribution,
+            [
+                Observation::Unsigned(1),
+                Observation::Repeated {
+                    total: 10.0,
+                    occurrences: 4
+                }
+            ]
+        );
+        assert_eq!(entry.metrics["Sizes"].unit, Unit::Byte(PositiveScale::Kilo));
+        assert_eq!(entry.metrics["Custom"].unit.name(), "Furlongs");
+    }
+
+    #[test]
+    fn rejects_invalid_emf() {
+        for (line, message) in [
+            ("[]", "expected a JSON object"),
+            ("{}", "missing `_aws` object"),
+            (r#"{"_aws":{}}"#, "missing `CloudWatchMetrics` array"),
+            (
+                r#"{"_aws":{"CloudWatchMetrics":[{"Dimensions":[[]],"Metrics":[{"Name":"A"}]}]}}"#,
+                "metric `A` is declared but not present",
+            ),
+            (
+                r#"{"_aws":{"CloudWatchMetrics":[{"Dimensions":[["D"]],"Metrics":[{"Name":"A"}]}]},"A":1}"#,
+                "dimension `D` is not a string property",
+            ),
+        ] {
+            let error = parse_emf(line).unwrap_err().to_string();
+            assert!(error.contains(message), "{error} should contain {message}");
+        }
+    }
+}
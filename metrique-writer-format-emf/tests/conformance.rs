@@ -0,0 +1,27 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use metrique_writer::test_util::conformance::FormatConformance;
+use metrique_writer_format_emf::{Emf, test_util::parse_emf_lines};
+
+fn emf() -> Emf {
+    Emf::all_validations("Conformance".into(), vec![vec![]])
+}
+
+#[test]
+fn emf_conforms() {
+    FormatConformance::new(emf)
+        // per-metric dimensions require entries to allow split entries
+        .skip("dimensions")
+        .run();
+}
+
+#[test]
+fn emf_round_trips() {
+    FormatConformance::new(emf)
+        .parse_with(|output| parse_emf_lines(&String::from_utf8_lossy(output)))
+        .skip("dimensions")
+        // parse_emf can't rebuild custom units
+        .skip("custom_unit")
+        .run();
+}
@@ -154,10 +154,8 @@ impl DimensionCountTestEntry {
                 .collect();
 
             // Create a single dimension set containing all the dimension names
-            let dimension_set: Vec<Cow<'static, str>> = dimension_names
-                .into_iter()
-                .map(|name| Cow::Owned(name))
-                .collect();
+            let dimension_set: Vec<Cow<'static, str>> =
+                dimension_names.into_iter().map(Cow::Owned).collect();
 
             Some(EntryDimensions::new(Cow::Owned(vec![Cow::Owned(
                 dimension_set,
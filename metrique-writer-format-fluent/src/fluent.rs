@@ -0,0 +1,313 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::borrow::Cow;
+use std::io;
+use std::time::SystemTime;
+
+use metrique_writer_core::entry::EntryConfig;
+use metrique_writer_core::format::Format;
+use metrique_writer_core::stream::IoStreamError;
+use metrique_writer_core::value::{MetricFlags, Observation, Value, ValueWriter};
+use metrique_writer_core::{Entry, EntryWriter, Unit, ValidationError, ValidationErrorBuilder};
+
+use crate::msgpack;
+
+// Maximum buffer size before shrinking on clear. Prevents one large entry from
+// permanently bloating memory.
+const MAX_BUF_RETAIN: usize = 1024 * 1024;
+
+/// A formatter for the Fluent Bit / Fluentd [forward protocol].
+///
+/// Each entry is written as a MessagePack "Message mode" event, `[tag, time, record]`, where
+/// `time` is an `EventTime` and `record` has the same structure as the output of the
+/// `metrique-writer-format-json` formatter:
+/// ```json
+/// {
+///   "metrics": {
+///     "Latency": { "value": 42.5, "unit": "Milliseconds" },
+///     "ResponseTimes": { "values": [1, 2, 3], "unit": "Milliseconds" },
+///     "BackendLatency": { "value": { "total": 150, "count": 3 }, "unit": "Milliseconds" }
+///   },
+///   "properties": {
+///     "Operation": "GetItem"
+///   }
+/// }
+/// ```
+///
+/// This format can be used with any [`io::Write`] that is connected to a forward input, but it
+/// does not request acknowledgements. Use [`FluentForward`] to batch entries and wait for the
+/// server to acknowledge them.
+///
+/// ```
+/// use metrique_writer_format_fluent::Fluent;
+///
+/// let format = Fluent::new("myapp.metrics");
+/// ```
+///
+/// [forward protocol]: https://github.com/fluent/fluentd/wiki/Forward-Protocol-Specification-v1.5
+/// [`FluentForward`]: crate::FluentForward
+#[derive(Debug)]
+pub struct Fluent {
+    tag: String,
+    encoder: RecordEncoder,
+    buf: Vec<u8>,
+}
+
+impl Fluent {
+    /// Create a new formatter that tags every event with `tag`.
+    pub fn new(tag: impl Into<String>) -> Self {
+        Self {
+            tag: tag.into(),
+            encoder: RecordEncoder::default(),
+            buf: Vec::with_capacity(2048),
+        }
+    }
+}
+
+impl Format for Fluent {
+    fn format(
+        &mut self,
+        entry: &impl Entry,
+        output: &mut impl io::Write,
+    ) -> Result<(), IoStreamError> {
+        self.buf.clear();
+        self.buf.shrink_to(MAX_BUF_RETAIN);
+        msgpack::push_array_len(&mut self.buf, 3);
+        msgpack::push_str(&mut self.buf, &self.tag);
+        self.encoder.encode(entry, &mut self.buf)?;
+        output.write_all(&self.buf)?;
+        Ok(())
+    }
+}
+
+/// Encodes entries as a `time, record` pair. Reuses its buffers between entries.
+#[derive(Debug, Default)]
+pub(crate) struct RecordEncoder {
+    metrics: Vec<u8>,
+    metric_count: usize,
+    properties: Vec<u8>,
+    property_count: usize,
+    observations: Vec<Observation>,
+}
+
+impl RecordEncoder {
+    /// Append the `time` and `record` of `entry` to `out`. Nothing is appended on error.
+    pub(crate) fn encode(
+        &mut self,
+        entry: &impl Entry,
+        out: &mut Vec<u8>,
+    ) -> Result<(), ValidationError> {
+        for buf in [&mut self.metrics, &mut self.properties] {
+            buf.clear();
+            buf.shrink_to(MAX_BUF_RETAIN);
+        }
+        self.metric_count = 0;
+        self.property_count = 0;
+
+        let mut writer = FluentEntryWriter {
+            timestamp: None,
+            encoder: self,
+            error: ValidationErrorBuilder::default(),
+        };
+        entry.write(&mut writer);
+        let timestamp = writer.timestamp;
+        writer.error.build()?;
+
+        msgpack::push_event_time(out, timestamp.unwrap_or_else(SystemTime::now));
+        let sections = [
+            ("metrics", self.metric_count, &self.metrics),
+            ("properties", self.property_count, &self.properties),
+        ];
+        msgpack::push_map_len(out, sections.iter().filter(|s| s.1 > 0).count());
+        for (name, count, buf) in sections {
+            if count > 0 {
+                msgpack::push_str(out, name);
+                msgpack::push_map_len(out, count);
+                out.extend_from_slice(buf);
+            }
+        }
+        Ok(())
+    }
+}
+
+struct FluentEntryWriter<'e> {
+    timestamp: Option<SystemTime>,
+    encoder: &'e mut RecordEncoder,
+    error: ValidationErrorBuilder,
+}
+
+impl<'a> EntryWriter<'a> for FluentEntryWriter<'_> {
+    fn timestamp(&mut self, timestamp: SystemTime) {
+        if self.timestamp.is_some() {
+            self.error.invalid_mut("timestamp set more than once");
+        }
+        self.timestamp = Some(timestamp);
+    }
+
+    fn value(&mut self, name: impl Into<Cow<'a, str>>, value: &(impl Value + ?Sized)) {
+        let name = name.into();
+        if name.is_empty() {
+            self.error
+                .extend_mut(ValidationError::invalid("name can't be empty").for_field(""));
+            return;
+        }
+        value.write(FluentValueWriter {
+            name: &name,
+            encoder: self.encoder,
+            error: &mut self.error,
+        });
+    }
+
+    fn config(&mut self, _config: &'a dyn EntryConfig) {
+        // There's currently no EntryConfig that is relevant to the forward protocol.
+    }
+}
+
+struct FluentValueWriter<'w> {
+    name: &'w str,
+    encoder: &'w mut RecordEncoder,
+    error: &'w mut ValidationErrorBuilder,
+}
+
+impl ValueWriter for FluentValueWriter<'_> {
+    fn string(self, value: &str) {
+        let buf = &mut self.encoder.properties;
+        msgpack::push_str(buf, self.name);
+        msgpack::push_str(buf, value);
+        self.encoder.property_count += 1;
+    }
+
+    fn metric<'a>(
+        self,
+        distribution: impl IntoIterator<Item = Observation>,
+        unit: Unit,
+        _dimensions: impl IntoIterator<Item = (&'a str, &'a str)>,
+        _flags: MetricFlags<'_>,
+    ) {
+        // Like the JSON format, per-metric dimensions and metric flags are ignored.
+        let encoder = self.encoder;
+        encoder.observations.clear();
+        encoder.observations.extend(distribution);
+        let buf = &mut encoder.metrics;
+        let observations = &encoder.observations[..];
+        if observations.is_empty() {
+            return; // no observations, skip metric
+        }
+
+        msgpack::push_str(buf, self.name);
+        msgpack::push_map_len(buf, if unit == Unit::None { 1 } else { 2 });
+        if let [observation] = observations {
+            msgpack::push_str(buf, "value");
+            push_observation(buf, *observation);
+        } else {
+            msgpack::push_str(buf, "values");
+            msgpack::push_array_len(buf, observations.len());
+            for observation in observations {
+                push_observation(buf, *observation);
+            }
+        }
+        if unit != Unit::None {
+            msgpack::push_str(buf, "unit");
+            msgpack::push_str(buf, unit.name());
+        }
+        encoder.metric_count += 1;
+    }
+
+    fn error(self, error: ValidationError) {
+        self.error.extend_mut(error.for_field(self.name));
+    }
+}
+
+fn push_observation(buf: &mut Vec<u8>, observation: Observation) {
+    match observation {
+        Observation::Unsigned(v) => msgpack::push_uint(buf, v),
+        Observation::Floating(v) => msgpack::push_f64(buf, v),
+        Observation::Repeated { total, occurrences } => {
+            msgpack::push_map_len(buf, 2);
+            msgpack::push_str(buf, "total");
+            msgpack::push_f64(buf, total);
+            msgpack::push_str(buf, "count");
+            msgpack::push_uint(buf, occurrences);
+        }
+        _ => msgpack::push_nil(buf),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use metrique_writer::value::WithDimension;
+    use rmpv::Value as MsgValue;
+    use std::time::Duration;
+
+    struct TestEntry;
+    impl Entry for TestEntry {
+        fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+            writer.timestamp(SystemTime::UNIX_EPOCH + Duration::from_millis(1705312800500));
+            writer.value("Latency", &Duration::from_micros(42500));
+            writer.value("Count", &WithDimension::new(10u64, "Region", "us-east-1"));
+            writer.value("Operation", "GetItem");
+            writer.value("Missing", &None::<u64>);
+        }
+    }
+
+    fn lookup<'v>(map: &'v MsgValue, key: &str) -> &'v MsgValue {
+        map.as_map()
+            .unwrap()
+            .iter()
+            .find(|(k, _)| k.as_str() == Some(key))
+            .map(|(_, v)| v)
+            .unwrap_or_else(|| panic!("missing {key}"))
+    }
+
+    #[test]
+    fn fluent_conforms() {
+        metrique_writer::test_util::conformance::FormatConformance::new(|| Fluent::new("app"))
+            .run();
+    }
+
+    #[test]
+    fn formats_message_mode_event() {
+        let mut output = vec![];
+        let mut format = Fluent::new("app.metrics");
+        format.format(&TestEntry, &mut output).unwrap();
+
+        let event = rmpv::decode::read_value(&mut &output[..]).unwrap();
+        let [tag, time, record] = event.as_array().unwrap().as_slice() else {
+            panic!("expected [tag, time, record], got {event}");
+        };
+        assert_eq!(tag.as_str(), Some("app.metrics"));
+        let MsgValue::Ext(0, time) = time else {
+            panic!("expected an EventTime, got {time}");
+        };
+        assert_eq!(time[..4], 1705312800u32.to_be_bytes());
+        assert_eq!(time[4..], 500_000_000u32.to_be_bytes());
+
+        let metrics = lookup(record, "metrics");
+        let latency = lookup(metrics, "Latency");
+        assert_eq!(lookup(latency, "value").as_f64(), Some(42.5));
+        assert_eq!(lookup(latency, "unit").as_str(), Some("Milliseconds"));
+        assert_eq!(lookup(lookup(metrics, "Count"), "value").as_u64(), Some(10));
+        assert_eq!(metrics.as_map().unwrap().len(), 2);
+        assert_eq!(
+            lookup(lookup(record, "properties"), "Operation").as_str(),
+            Some("GetItem")
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_entries() {
+        struct Invalid;
+        impl Entry for Invalid {
+            fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+                writer.value("", "empty name");
+            }
+        }
+
+        let mut output = vec![];
+        let mut format = Fluent::new("app.metrics");
+        assert!(format.format(&Invalid, &mut output).is_err());
+        assert!(output.is_empty());
+    }
+}
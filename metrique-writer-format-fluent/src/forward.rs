@@ -0,0 +1,475 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    fmt,
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::{Duration, Instant},
+};
+
+#[cfg(unix)]
+use std::{os::unix::net::UnixStream, path::PathBuf};
+
+use metrique_writer_core::{
+    Entry,
+    stream::{EntryIoStream, IoStreamError},
+};
+use rand::RngCore;
+
+use crate::{fluent::RecordEncoder, msgpack};
+
+/// The default time to wait for the server to acknowledge a batch, see
+/// [`FluentForward::with_ack_timeout`]
+pub const DEFAULT_ACK_TIMEOUT: Duration = Duration::from_secs(10);
+/// The default total time spent delivering a batch, see [`FluentForward::with_send_timeout`]
+pub const DEFAULT_SEND_TIMEOUT: Duration = Duration::from_secs(10);
+/// The default size of a batch after which it is sent without waiting for a flush, see
+/// [`FluentForward::with_max_batch_bytes`]
+pub const DEFAULT_MAX_BATCH_BYTES: usize = 1024 * 1024;
+
+/// An [`EntryIoStream`] that delivers entries to a Fluent Bit or Fluentd forward input, with
+/// acknowledgements.
+///
+/// Entries are batched and sent in the "Forward mode" of the [forward protocol] when the stream
+/// is flushed (a [`BackgroundQueue`] flushes periodically), or when the batch grows past
+/// [`max_batch_bytes`](Self::with_max_batch_bytes). Each batch carries a unique `chunk` ID, and the
+/// stream waits for the server to acknowledge it. If sending or acknowledging a batch fails, the
+/// stream reconnects and sends it once more. If that fails too, the batch is dropped and the error
+/// is returned. Connecting, sending, retrying and waiting for the acknowledgement together take at
+/// most the [send timeout](Self::with_send_timeout), so an unresponsive server doesn't stall the
+/// thread writing the entries.
+///
+/// Unlike scraping stdout, the forward protocol preserves the order of entries and never truncates
+/// long entries.
+///
+/// Records have the same structure as with the [`Fluent`] format. The connection is established
+/// lazily, on the first batch.
+///
+/// ```no_run
+/// use metrique_writer::{BoxEntry, sink::BackgroundQueue};
+/// use metrique_writer_format_fluent::FluentForward;
+///
+/// let stream = FluentForward::tcp("127.0.0.1:24224", "myapp.metrics");
+/// let (queue, _join) = BackgroundQueue::<BoxEntry>::new(stream);
+/// ```
+///
+/// [forward protocol]: https://github.com/fluent/fluentd/wiki/Forward-Protocol-Specification-v1.5
+/// [`BackgroundQueue`]: https://docs.rs/metrique-writer/latest/metrique_writer/sink/struct.BackgroundQueue.html
+/// [`Fluent`]: crate::Fluent
+pub struct FluentForward {
+    address: Address,
+    tag: String,
+    connection: Option<Connection>,
+    encoder: RecordEncoder,
+    batch: Vec<u8>,
+    batch_entries: usize,
+    message: Vec<u8>,
+    ack_timeout: Option<Duration>,
+    send_timeout: Duration,
+    max_batch_bytes: usize,
+    bytes_written: u64,
+}
+
+#[derive(Debug, Clone)]
+enum Address {
+    Tcp(String),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+enum Connection {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl FluentForward {
+    /// Send entries to the forward input listening on the TCP socket at `address`, for example
+    /// `"127.0.0.1:24224"`, tagged with `tag`.
+    pub fn tcp(address: impl Into<String>, tag: impl Into<String>) -> Self {
+        Self::new(Address::Tcp(address.into()), tag.into())
+    }
+
+    /// Send entries to the forward input listening on the Unix domain socket at `path`, tagged
+    /// with `tag`.
+    #[cfg(unix)]
+    pub fn unix(path: impl Into<PathBuf>, tag: impl Into<String>) -> Self {
+        Self::new(Address::Unix(path.into()), tag.into())
+    }
+
+    fn new(address: Address, tag: String) -> Self {
+        Self {
+            address,
+            tag,
+            connection: None,
+            encoder: RecordEncoder::default(),
+            batch: Vec::new(),
+            batch_entries: 0,
+            message: Vec::new(),
+            ack_timeout: Some(DEFAULT_ACK_TIMEOUT),
+            send_timeout: DEFAULT_SEND_TIMEOUT,
+            max_batch_bytes: DEFAULT_MAX_BATCH_BYTES,
+            bytes_written: 0,
+        }
+    }
+
+    /// Set the time to wait for the server to acknowledge a batch. The wait is also bounded by the
+    /// [send timeout](Self::with_send_timeout).
+    ///
+    /// `None` disables acknowledgements, in which case a batch is considered delivered as soon
+    /// as it is written to the socket.
+    ///
+    /// Defaults to [`DEFAULT_ACK_TIMEOUT`].
+    pub fn with_ack_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.ack_timeout = timeout;
+        self
+    }
+
+    /// Set the total time spent delivering a batch, including connecting, retrying and waiting
+    /// for the acknowledgement. The batch is dropped if it isn't delivered in time.
+    ///
+    /// Defaults to [`DEFAULT_SEND_TIMEOUT`].
+    pub fn with_send_timeout(mut self, timeout: Duration) -> Self {
+        self.send_timeout = timeout;
+        self
+    }
+
+    /// Send the current batch as soon as it grows past `max_batch_bytes`, rather than waiting
+    /// for the next flush.
+    ///
+    /// Defaults to [`DEFAULT_MAX_BATCH_BYTES`].
+    pub fn with_max_batch_bytes(mut self, max_batch_bytes: usize) -> Self {
+        self.max_batch_bytes = max_batch_bytes;
+        self
+    }
+
+    fn send_batch(&mut self) -> io::Result<()> {
+        if self.batch_entries == 0 {
+            return Ok(());
+        }
+        let chunk = self.ack_timeout.map(|_| new_chunk_id());
+
+        self.message.clear();
+        msgpack::push_array_len(&mut self.message, if chunk.is_some() { 3 } else { 2 });
+        msgpack::push_str(&mut self.message, &self.tag);
+        msgpack::push_array_len(&mut self.message, self.batch_entries);
+        self.message.extend_from_slice(&self.batch);
+        if let Some(chunk) = &chunk {
+            msgpack::push_map_len(&mut self.message, 1);
+            msgpack::push_str(&mut self.message, "chunk");
+            msgpack::push_str(&mut self.message, chunk);
+        }
+
+        // the batch is dropped even if delivery fails, to keep memory bounded
+        self.batch.clear();
+        self.batch.shrink_to(self.max_batch_bytes.saturating_mul(2));
+        self.batch_entries = 0;
+
+        let deadline = Instant::now() + self.send_timeout;
+        let result = match self.try_send(chunk.as_deref(), deadline) {
+            Ok(()) => Ok(()),
+            // the connection might have been closed by the server since the last batch, so
+            // retry once with a new connection
+            Err(_) => self.try_send(chunk.as_deref(), deadline),
+        };
+        if result.is_ok() {
+            self.bytes_written += self.message.len() as u64;
+        }
+        result
+    }
+
+    fn try_send(&mut self, chunk: Option<&str>, deadline: Instant) -> io::Result<()> {
+        let result = self.try_send_inner(chunk, deadline);
+        if result.is_err() {
+            self.connection = None;
+        }
+        result
+    }
+
+    fn try_send_inner(&mut self, chunk: Option<&str>, deadline: Instant) -> io::Result<()> {
+        let connection = match &mut self.connection {
+            Some(connection) => connection,
+            None => self.connection.insert(self.address.connect(deadline)?),
+        };
+        connection.set_write_timeout(remaining(deadline)?)?;
+        connection.write_all(&self.message)?;
+        connection.flush()?;
+        if let Some(chunk) = chunk {
+            let timeout = remaining(deadline)?;
+            let timeout = self.ack_timeout.map_or(timeout, |ack| ack.min(timeout));
+            connection.set_read_timeout(timeout)?;
+            let ack = msgpack::read_ack(connection)?;
+            if ack != chunk {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("expected ack for chunk `{chunk}`, got `{ack}`"),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl EntryIoStream for FluentForward {
+    fn next(&mut self, entry: &impl Entry) -> Result<(), IoStreamError> {
+        msgpack::push_array_len(&mut self.batch, 2);
+        let start = self.batch.len() - 1;
+        if let Err(err) = self.encoder.encode(entry, &mut self.batch) {
+            self.batch.truncate(start);
+            return Err(err.into());
+        }
+        self.batch_entries += 1;
+        if self.batch.len() >= self.max_batch_bytes {
+            self.send_batch()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.send_batch()
+    }
+
+    fn bytes_written(&self) -> Option<u64> {
+        Some(self.bytes_written)
+    }
+}
+
+impl fmt::Debug for FluentForward {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FluentForward")
+            .field("address", &self.address)
+            .field("tag", &self.tag)
+            .field("connected", &self.connection.is_some())
+            .field("batch_entries", &self.batch_entries)
+            .finish_non_exhaustive()
+    }
+}
+
+fn new_chunk_id() -> String {
+    let mut id = [0u8; 16];
+    rand::rng().fill_bytes(&mut id);
+    id.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// The time left until `deadline`, or a `TimedOut` error if it passed
+fn remaining(deadline: Instant) -> io::Result<Duration> {
+    deadline
+        .checked_duration_since(Instant::now())
+        .filter(|remaining| !remaining.is_zero())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::TimedOut, "timed out sending the batch"))
+}
+
+impl Address {
+    fn connect(&self, deadline: Instant) -> io::Result<Connection> {
+        match self {
+            Address::Tcp(address) => {
+                let mut last_err = None;
+                for addr in address.to_socket_addrs()? {
+                    match TcpStream::connect_timeout(&addr, remaining(deadline)?) {
+                        Ok(stream) => {
+                            stream.set_nodelay(true)?;
+                            return Ok(Connection::Tcp(stream));
+                        }
+                        Err(err) => last_err = Some(err),
+                    }
+                }
+                Err(last_err.unwrap_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("`{address}` did not resolve to any address"),
+                    )
+                }))
+            }
+            #[cfg(unix)]
+            Address::Unix(path) => Ok(Connection::Unix(UnixStream::connect(path)?)),
+        }
+    }
+}
+
+impl Connection {
+    fn set_read_timeout(&self, timeout: Duration) -> io::Result<()> {
+        match self {
+            Connection::Tcp(stream) => stream.set_read_timeout(Some(timeout)),
+            #[cfg(unix)]
+            Connection::Unix(stream) => stream.set_read_timeout(Some(timeout)),
+        }
+    }
+
+    fn set_write_timeout(&self, timeout: Duration) -> io::Result<()> {
+        match self {
+            Connection::Tcp(stream) => stream.set_write_timeout(Some(timeout)),
+            #[cfg(unix)]
+            Connection::Unix(stream) => stream.set_write_timeout(Some(timeout)),
+        }
+    }
+}
+
+impl Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Connection::Tcp(stream) => stream.read(buf),
+            #[cfg(unix)]
+            Connection::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Connection::Tcp(stream) => stream.write(buf),
+            #[cfg(unix)]
+            Connection::Unix(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Connection::Tcp(stream) => stream.flush(),
+            #[cfg(unix)]
+            Connection::Unix(stream) => stream.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use metrique_writer_core::EntryWriter;
+    use rmpv::Value as MsgValue;
+    use std::net::TcpListener;
+    use std::thread;
+
+    struct Request(u64);
+    impl Entry for Request {
+        fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+            writer.value("Id", &self.0);
+        }
+    }
+
+    fn lookup<'v>(map: &'v MsgValue, key: &str) -> Option<&'v MsgValue> {
+        map.as_map()?
+            .iter()
+            .find(|(k, _)| k.as_str() == Some(key))
+            .map(|(_, v)| v)
+    }
+
+    /// Accept one connection, read `messages` forward messages, and acknowledge them if requested
+    fn serve(listener: TcpListener, messages: usize) -> thread::JoinHandle<Vec<MsgValue>> {
+        thread::spawn(move || {
+            let (mut peer, _) = listener.accept().unwrap();
+            (0..messages)
+                .map(|_| {
+                    let message = rmpv::decode::read_value(&mut peer).unwrap();
+                    if let Some(chunk) = message.as_array().unwrap().get(2) {
+                        let chunk = lookup(chunk, "chunk").unwrap().as_str().unwrap();
+                        let mut ack = vec![];
+                        msgpack::push_map_len(&mut ack, 1);
+                        msgpack::push_str(&mut ack, "ack");
+                        msgpack::push_str(&mut ack, chunk);
+                        peer.write_all(&ack).unwrap();
+                    }
+                    message
+                })
+                .collect()
+        })
+    }
+
+    fn ids(message: &MsgValue) -> Vec<u64> {
+        let [_tag, entries, ..] = message.as_array().unwrap().as_slice() else {
+            panic!("expected a forward mode message");
+        };
+        entries
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| {
+                let record = &entry.as_array().unwrap()[1];
+                let metric = lookup(lookup(record, "metrics").unwrap(), "Id").unwrap();
+                lookup(metric, "value").unwrap().as_u64().unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sends_acknowledged_batches() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = serve(listener, 2);
+
+        let mut stream = FluentForward::tcp(address, "app.metrics");
+        stream.flush().unwrap(); // empty batches are not sent
+        for id in 0..3 {
+            stream.next(&Request(id)).unwrap();
+        }
+        stream.flush().unwrap();
+        stream.next(&Request(3)).unwrap();
+        stream.flush().unwrap();
+
+        let messages = server.join().unwrap();
+        assert_eq!(
+            messages[0].as_array().unwrap()[0].as_str(),
+            Some("app.metrics")
+        );
+        assert_eq!(ids(&messages[0]), [0, 1, 2]);
+        assert_eq!(ids(&messages[1]), [3]);
+        assert!(stream.bytes_written().unwrap() > 0);
+    }
+
+    #[test]
+    fn sends_full_batches_without_acks() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = serve(listener, 1);
+
+        let mut stream = FluentForward::tcp(address, "app.metrics")
+            .with_ack_timeout(None)
+            .with_max_batch_bytes(1);
+        // the batch is full, so it is sent without a flush
+        stream.next(&Request(7)).unwrap();
+
+        let messages = server.join().unwrap();
+        assert_eq!(messages[0].as_array().unwrap().len(), 2);
+        assert_eq!(ids(&messages[0]), [7]);
+    }
+
+    #[test]
+    fn drops_batch_when_unacknowledged() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        // never acknowledges, and closes every connection
+        let server = thread::spawn(move || {
+            for _ in 0..2 {
+                let (mut peer, _) = listener.accept().unwrap();
+                rmpv::decode::read_value(&mut peer).unwrap();
+            }
+        });
+
+        let mut stream = FluentForward::tcp(address, "app.metrics")
+            .with_ack_timeout(Some(Duration::from_secs(5)));
+        stream.next(&Request(1)).unwrap();
+        assert!(stream.flush().is_err());
+        server.join().unwrap();
+        // the batch was dropped
+        assert_eq!(stream.batch_entries, 0);
+        assert_eq!(stream.bytes_written(), Some(0));
+    }
+
+    #[test]
+    fn send_timeout_bounds_delivery() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        let mut stream = FluentForward::tcp(address, "app.metrics")
+            .with_ack_timeout(Some(Duration::from_secs(60)))
+            .with_send_timeout(Duration::from_millis(200));
+        stream.next(&Request(1)).unwrap();
+        // the connection is accepted by the OS, but the server never reads or acknowledges
+        let start = Instant::now();
+        let err = stream.flush().unwrap_err();
+        assert!(start.elapsed() < Duration::from_secs(5), "{err}");
+        assert_eq!(stream.batch_entries, 0);
+        drop(listener);
+    }
+}
@@ -0,0 +1,15 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+#![deny(missing_docs)]
+#![doc = include_str!("../README.md")]
+#![cfg_attr(docsrs, feature(doc_cfg))]
+
+mod fluent;
+mod forward;
+mod msgpack;
+
+pub use fluent::Fluent;
+pub use forward::{
+    DEFAULT_ACK_TIMEOUT, DEFAULT_MAX_BATCH_BYTES, DEFAULT_SEND_TIMEOUT, FluentForward,
+};
@@ -0,0 +1,222 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! The small subset of MessagePack needed by the forward protocol.
+//!
+//! See <https://github.com/msgpack/msgpack/blob/master/spec.md>.
+
+use std::{
+    io::{self, Read},
+    time::SystemTime,
+};
+
+pub(crate) fn push_str(buf: &mut Vec<u8>, s: &str) {
+    let len = s.len();
+    if len < 32 {
+        buf.push(0xa0 | len as u8);
+    } else if len <= u8::MAX as usize {
+        buf.extend_from_slice(&[0xd9, len as u8]);
+    } else if len <= u16::MAX as usize {
+        buf.push(0xda);
+        buf.extend_from_slice(&(len as u16).to_be_bytes());
+    } else {
+        buf.push(0xdb);
+        buf.extend_from_slice(&(len as u32).to_be_bytes());
+    }
+    buf.extend_from_slice(s.as_bytes());
+}
+
+pub(crate) fn push_array_len(buf: &mut Vec<u8>, len: usize) {
+    push_len(buf, len, 0x90, 0xdc, 0xdd);
+}
+
+pub(crate) fn push_map_len(buf: &mut Vec<u8>, len: usize) {
+    push_len(buf, len, 0x80, 0xde, 0xdf);
+}
+
+fn push_len(buf: &mut Vec<u8>, len: usize, fix: u8, marker16: u8, marker32: u8) {
+    if len < 16 {
+        buf.push(fix | len as u8);
+    } else if len <= u16::MAX as usize {
+        buf.push(marker16);
+        buf.extend_from_slice(&(len as u16).to_be_bytes());
+    } else {
+        buf.push(marker32);
+        buf.extend_from_slice(&(len as u32).to_be_bytes());
+    }
+}
+
+pub(crate) fn push_uint(buf: &mut Vec<u8>, v: u64) {
+    if v < 128 {
+        buf.push(v as u8);
+    } else if v <= u8::MAX as u64 {
+        buf.extend_from_slice(&[0xcc, v as u8]);
+    } else if v <= u16::MAX as u64 {
+        buf.push(0xcd);
+        buf.extend_from_slice(&(v as u16).to_be_bytes());
+    } else if v <= u32::MAX as u64 {
+        buf.push(0xce);
+        buf.extend_from_slice(&(v as u32).to_be_bytes());
+    } else {
+        buf.push(0xcf);
+        buf.extend_from_slice(&v.to_be_bytes());
+    }
+}
+
+pub(crate) fn push_f64(buf: &mut Vec<u8>, v: f64) {
+    buf.push(0xcb);
+    buf.extend_from_slice(&v.to_be_bytes());
+}
+
+pub(crate) fn push_nil(buf: &mut Vec<u8>) {
+    buf.push(0xc0);
+}
+
+/// Push a Fluent `EventTime`, which is the extension type 0 with big-endian seconds and
+/// nanoseconds since the Unix epoch.
+pub(crate) fn push_event_time(buf: &mut Vec<u8>, time: SystemTime) {
+    let since_epoch = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+    // fixext 8, type 0
+    buf.extend_from_slice(&[0xd7, 0x00]);
+    buf.extend_from_slice(&(since_epoch.as_secs() as u32).to_be_bytes());
+    buf.extend_from_slice(&since_epoch.subsec_nanos().to_be_bytes());
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn read_u8(reader: &mut impl Read) -> io::Result<u8> {
+    let mut byte = [0];
+    reader.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+fn read_be_len<const N: usize>(reader: &mut impl Read) -> io::Result<usize> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes.iter().fold(0, |len, b| (len << 8) | *b as usize))
+}
+
+/// The longest string accepted in a response. Acks only carry a chunk ID, so this is generous while
+/// keeping a malformed or hostile response from allocating gigabytes.
+const MAX_RESPONSE_STR_LEN: usize = 1024;
+
+fn read_str(reader: &mut impl Read) -> io::Result<String> {
+    let len = match read_u8(reader)? {
+        marker @ 0xa0..=0xbf => (marker & 0x1f) as usize,
+        0xd9 => read_be_len::<1>(reader)?,
+        0xda => read_be_len::<2>(reader)?,
+        0xdb => read_be_len::<4>(reader)?,
+        _ => return Err(invalid_data("expected a string")),
+    };
+    if len > MAX_RESPONSE_STR_LEN {
+        return Err(invalid_data("string in response is too long"));
+    }
+    let mut bytes = vec![0; len];
+    reader.read_exact(&mut bytes)?;
+    String::from_utf8(bytes).map_err(|_| invalid_data("expected a UTF-8 string"))
+}
+
+/// Read a forward protocol acknowledgement, `{"ack": <chunk>}`, and return the chunk ID.
+pub(crate) fn read_ack(reader: &mut impl Read) -> io::Result<String> {
+    let len = match read_u8(reader)? {
+        marker @ 0x80..=0x8f => (marker & 0x0f) as usize,
+        0xde => read_be_len::<2>(reader)?,
+        0xdf => read_be_len::<4>(reader)?,
+        _ => return Err(invalid_data("expected an ack map")),
+    };
+    let mut ack = None;
+    for _ in 0..len {
+        let key = read_str(reader)?;
+        let value = read_str(reader)?;
+        if key == "ack" {
+            ack = Some(value);
+        }
+    }
+    ack.ok_or_else(|| invalid_data("response is missing `ack`"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn decode(buf: &[u8]) -> rmpv::Value {
+        let mut reader = buf;
+        let value = rmpv::decode::read_value(&mut reader).unwrap();
+        assert!(reader.is_empty(), "trailing bytes");
+        value
+    }
+
+    #[test]
+    fn encodes_values_of_every_size() {
+        for len in [0, 31, 32, 255, 256, 65535, 65536] {
+            let s = "x".repeat(len);
+            let mut buf = vec![];
+            push_str(&mut buf, &s);
+            assert_eq!(decode(&buf).as_str(), Some(&*s));
+
+            let mut buf = vec![];
+            push_array_len(&mut buf, len);
+            for _ in 0..len {
+                push_nil(&mut buf);
+            }
+            assert_eq!(decode(&buf).as_array().unwrap().len(), len);
+        }
+        for v in [
+            0,
+            127,
+            128,
+            255,
+            256,
+            65535,
+            65536,
+            u32::MAX as u64,
+            u64::MAX,
+        ] {
+            let mut buf = vec![];
+            push_uint(&mut buf, v);
+            assert_eq!(decode(&buf).as_u64(), Some(v));
+        }
+        let mut buf = vec![];
+        push_f64(&mut buf, 1.5);
+        assert_eq!(decode(&buf).as_f64(), Some(1.5));
+    }
+
+    #[test]
+    fn encodes_event_time() {
+        let mut buf = vec![];
+        push_event_time(
+            &mut buf,
+            SystemTime::UNIX_EPOCH + Duration::new(1705312800, 123),
+        );
+        let rmpv::Value::Ext(0, bytes) = decode(&buf) else {
+            panic!("expected an EventTime");
+        };
+        assert_eq!(bytes[..4], 1705312800u32.to_be_bytes());
+        assert_eq!(bytes[4..], 123u32.to_be_bytes());
+    }
+
+    #[test]
+    fn reads_ack() {
+        let mut buf = vec![];
+        push_map_len(&mut buf, 1);
+        push_str(&mut buf, "ack");
+        push_str(&mut buf, "chunk-1");
+        assert_eq!(read_ack(&mut &buf[..]).unwrap(), "chunk-1");
+
+        let mut buf = vec![];
+        push_map_len(&mut buf, 0);
+        assert!(read_ack(&mut &buf[..]).is_err());
+
+        // a 4 GiB string is rejected before allocating it
+        let mut buf = vec![];
+        push_map_len(&mut buf, 1);
+        buf.extend_from_slice(&[0xdb, 0xff, 0xff, 0xff, 0xff]);
+        let err = read_ack(&mut &buf[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -3,11 +3,12 @@
 
 use std::borrow::Cow;
 use std::io;
+use std::ops::Range;
 use std::time::SystemTime;
 
 use metrique_writer::sample::DefaultRng;
 use metrique_writer_core::entry::EntryConfig;
-use metrique_writer_core::format::Format;
+use metrique_writer_core::format::{FloatPrecision, Format};
 use metrique_writer_core::sample::SampledFormat;
 use metrique_writer_core::stream::IoStreamError;
 use metrique_writer_core::value::{MetricFlags, Observation, Value, ValueWriter};
@@ -58,6 +59,26 @@ const MAX_BUF_RETAIN: usize = 1024 * 1024;
 ///   data point for debugging while staying within valid JSON. Note that this means the output value
 ///   is technically different from the input.
 /// - **NaN** observations are serialized as JSON `null`.
+///
+/// ## Groups
+///
+/// The fields of a group, like a `#[metrics(flatten, group = "cache")]` field, are written in a
+/// nested object named after the group, within `metrics` and `properties`, without the group
+/// prefix in their names. Formats that don't support nesting, like EMF, write them with their
+/// prefixed names instead:
+/// ```json
+/// {
+///   "timestamp": 1705312800000,
+///   "metrics": {
+///     "Latency": { "value": 42.5, "unit": "Milliseconds" },
+///     "Cache": { "Hits": { "value": 3 }, "Misses": { "value": 1 } }
+///   }
+/// }
+/// ```
+///
+/// An entry in which a group would be written with the same key as another group or value of the
+/// same object, like two `cache` groups, or a `cache` group next to a `Cache` field once
+/// inflected, is rejected with a validation error rather than written with duplicate keys.
 #[derive(Debug)]
 pub struct Json {
     // Reusable string buffers, cleared between entries, capacity stays warm.
@@ -65,6 +86,8 @@ pub struct Json {
     // stripped when assembling the final output.
     metrics_buf: String,
     properties_buf: String,
+    groups: Groups,
+    float_precision: FloatPrecision,
 }
 
 impl Json {
@@ -73,9 +96,28 @@ impl Json {
         Self {
             metrics_buf: String::with_capacity(2048),
             properties_buf: String::with_capacity(2048),
+            groups: Groups::default(),
+            float_precision: FloatPrecision::default(),
         }
     }
 
+    /// Round floating-point metric values to `precision` before writing them.
+    ///
+    /// By default, floats are written with the shortest representation that round-trips to the
+    /// same `f64`. Integer metric values, and the counts of repeated observations, are never
+    /// rounded.
+    ///
+    /// ```
+    /// use metrique_writer::format::FloatPrecision;
+    /// use metrique_writer_format_json::Json;
+    ///
+    /// let format = Json::new().float_precision(FloatPrecision::SignificantDigits(4));
+    /// ```
+    pub fn float_precision(mut self, precision: FloatPrecision) -> Self {
+        self.float_precision = precision;
+        self
+    }
+
     /// Wrap this formatter with support for sampling using the default RNG.
     ///
     /// When sampling is active, metrics are emitted with a multiplicity that
@@ -105,10 +147,16 @@ impl Json {
             metrics_buf: &mut self.metrics_buf,
             properties_buf: &mut self.properties_buf,
             multiplicity,
+            float_precision: self.float_precision,
             error: ValidationErrorBuilder::default(),
+            groups: &mut self.groups,
         };
 
         entry.write(&mut writer);
+        // close the groups that the entry didn't end
+        while !writer.groups.stack.is_empty() {
+            EntryWriter::end_group(&mut writer);
+        }
 
         let timestamp = writer.timestamp;
         let error = writer.error;
@@ -150,6 +198,7 @@ impl Json {
         self.metrics_buf.shrink_to(MAX_BUF_RETAIN);
         self.properties_buf.truncate(0);
         self.properties_buf.shrink_to(MAX_BUF_RETAIN);
+        self.groups.clear();
     }
 }
 
@@ -174,7 +223,171 @@ struct JsonEntryWriter<'b> {
     metrics_buf: &'b mut String,
     properties_buf: &'b mut String,
     multiplicity: Option<u64>,
+    float_precision: FloatPrecision,
     error: ValidationErrorBuilder,
+    groups: &'b mut Groups,
+}
+
+const METRICS: usize = 0;
+const PROPERTIES: usize = 1;
+
+/// The groups that the next value is written in, from the outermost
+#[derive(Debug, Default)]
+struct Groups {
+    stack: Vec<Group>,
+    /// The keys of the groups, and of the values written in the objects of open groups, to reject
+    /// a group whose key is already used
+    keys: Vec<Key>,
+    /// The names of `keys`, in one buffer that keeps its capacity between entries
+    names: String,
+    /// Where the keys of the values outside of any group start in the `metrics` and `properties`
+    /// buffers. Their names aren't copied, since they are only read when a group is started next
+    /// to them.
+    top_level: [Vec<usize>; 2],
+}
+
+#[derive(Debug)]
+struct Group {
+    /// The key of the nested object, relative to the enclosing group
+    key: String,
+    /// The full prefix of the names of the values in the group
+    prefix: String,
+    /// Whether the nested object was started in `metrics` and in `properties`. Objects are only
+    /// started when a value is written in them, so that empty groups aren't written.
+    open: [bool; 2],
+}
+
+#[derive(Debug)]
+struct Key {
+    /// The object the key is written in: 0 for `metrics` or `properties`, `n` for the object of
+    /// `stack[n - 1]`
+    depth: usize,
+    section: usize,
+    /// Whether the key is the key of a group
+    group: bool,
+    name: Range<usize>,
+}
+
+impl Key {
+    fn record(
+        keys: &mut Vec<Key>,
+        names: &mut String,
+        depth: usize,
+        section: usize,
+        group: bool,
+        name: &str,
+    ) {
+        let start = names.len();
+        names.push_str(name);
+        keys.push(Key {
+            depth,
+            section,
+            group,
+            name: start..names.len(),
+        });
+    }
+}
+
+impl Groups {
+    fn clear(&mut self) {
+        self.stack.clear();
+        self.keys.clear();
+        self.names.truncate(0);
+        self.names.shrink_to(MAX_BUF_RETAIN);
+        for top_level in &mut self.top_level {
+            top_level.clear();
+        }
+    }
+
+    /// Returns true if `name` was written in the object at `depth`. Groups can only collide with
+    /// a group or value written in the same object, so values are only checked against groups.
+    fn contains(&self, depth: usize, section: usize, name: &str, groups_only: bool) -> bool {
+        self.keys.iter().any(|key| {
+            key.depth == depth
+                && key.section == section
+                && (key.group || !groups_only)
+                && self.names[key.name.clone()] == *name
+        })
+    }
+
+    /// Returns true if `name` is the key of a value written outside of any group in `buf`
+    fn top_level_contains(&self, section: usize, name: &str, buf: &str) -> bool {
+        if self.top_level[section].is_empty() {
+            return false;
+        }
+        let mut key = String::with_capacity(name.len() + 2);
+        push_json_string(&mut key, name);
+        self.top_level[section]
+            .iter()
+            .any(|&start| buf[start..].starts_with(&*key))
+    }
+
+    /// The name of `name` within the innermost group
+    fn strip<'n>(&self, name: &'n str) -> &'n str {
+        match self.stack.last() {
+            Some(group) => match name.strip_prefix(&*group.prefix) {
+                Some(stripped) if !stripped.is_empty() => stripped,
+                _ => name,
+            },
+            None => name,
+        }
+    }
+
+    /// Start the nested objects of `section` that the value `name` is written in, and push the
+    /// separator before the value.
+    ///
+    /// Returns the key that is already used in its object, without writing anything, if a group
+    /// collides with another group or value.
+    fn push_separator(
+        &mut self,
+        section: usize,
+        name: &str,
+        buf: &mut String,
+    ) -> Result<(), String> {
+        // the open groups are a prefix of the stack, and the objects of the others are empty
+        let first_closed = self.stack.iter().position(|group| !group.open[section]);
+        match first_closed {
+            Some(depth)
+                if self.contains(depth, section, &self.stack[depth].key, false)
+                    || (depth == 0
+                        && self.top_level_contains(section, &self.stack[0].key, buf)) =>
+            {
+                return Err(self.stack[depth].key.clone());
+            }
+            None if self.contains(self.stack.len(), section, name, true) => {
+                return Err(name.to_owned());
+            }
+            _ => {}
+        }
+
+        let mut first_in_object = false;
+        for depth in first_closed.unwrap_or(self.stack.len())..self.stack.len() {
+            if !first_in_object {
+                buf.push(',');
+            }
+            let group = &mut self.stack[depth];
+            push_json_string(buf, &group.key);
+            buf.push_str(":{");
+            group.open[section] = true;
+            first_in_object = true;
+            Key::record(
+                &mut self.keys,
+                &mut self.names,
+                depth,
+                section,
+                true,
+                &group.key,
+            );
+        }
+        if !first_in_object {
+            buf.push(',');
+        }
+        match self.stack.len() {
+            0 => self.top_level[section].push(buf.len()),
+            depth => Key::record(&mut self.keys, &mut self.names, depth, section, false, name),
+        }
+        Ok(())
+    }
 }
 
 impl<'a, 'b> EntryWriter<'a> for JsonEntryWriter<'b> {
@@ -193,11 +406,13 @@ impl<'a, 'b> EntryWriter<'a> for JsonEntryWriter<'b> {
             return;
         }
         let writer = JsonValueWriter {
-            name: name.as_ref(),
+            name: self.groups.strip(&name),
             metrics_buf: self.metrics_buf,
             properties_buf: self.properties_buf,
             multiplicity: self.multiplicity,
+            float_precision: self.float_precision,
             error: &mut self.error,
+            groups: self.groups,
         };
         value.write(writer);
     }
@@ -205,6 +420,30 @@ impl<'a, 'b> EntryWriter<'a> for JsonEntryWriter<'b> {
     fn config(&mut self, _config: &'a dyn EntryConfig) {
         // Currently there's no EntryConfig that is JSON-specific or relevant to the JSON format.
     }
+
+    fn begin_group(&mut self, name: impl Into<Cow<'a, str>>, prefix: impl Into<Cow<'a, str>>) {
+        let name = name.into();
+        let key = self.groups.strip(&name).to_owned();
+        self.groups.stack.push(Group {
+            key,
+            prefix: prefix.into().into_owned(),
+            open: [false; 2],
+        });
+    }
+
+    fn end_group(&mut self) {
+        let Some(group) = self.groups.stack.pop() else {
+            return;
+        };
+        let depth = self.groups.stack.len();
+        self.groups.keys.retain(|key| key.depth <= depth);
+        if group.open[METRICS] {
+            self.metrics_buf.push('}');
+        }
+        if group.open[PROPERTIES] {
+            self.properties_buf.push('}');
+        }
+    }
 }
 
 struct JsonValueWriter<'b, 'c> {
@@ -212,13 +451,19 @@ struct JsonValueWriter<'b, 'c> {
     metrics_buf: &'b mut String,
     properties_buf: &'b mut String,
     multiplicity: Option<u64>,
+    float_precision: FloatPrecision,
     error: &'b mut ValidationErrorBuilder,
+    groups: &'b mut Groups,
 }
 
 impl<'b, 'c> ValueWriter for JsonValueWriter<'b, 'c> {
     fn string(self, value: &str) {
         let buf = self.properties_buf;
-        buf.push(',');
+        if let Err(key) = self.groups.push_separator(PROPERTIES, self.name, buf) {
+            self.error
+                .extend_mut(duplicate_key(&key).for_field(self.name));
+            return;
+        }
         push_json_string(buf, self.name);
         buf.push(':');
         push_json_string(buf, value);
@@ -235,6 +480,10 @@ impl<'b, 'c> ValueWriter for JsonValueWriter<'b, 'c> {
         // EMF interprets these for CloudWatch-specific behavior, while pure JSON
         // currently focuses on value/unit serialization only.
         let buf = self.metrics_buf;
+        let format = ObservationFormat {
+            multiplicity: self.multiplicity,
+            float_precision: self.float_precision,
+        };
         let mut obs = distribution.into_iter();
 
         let Some(first) = obs.next() else {
@@ -242,21 +491,25 @@ impl<'b, 'c> ValueWriter for JsonValueWriter<'b, 'c> {
         };
 
         // Write ,"MetricName":{
-        buf.push(',');
+        if let Err(key) = self.groups.push_separator(METRICS, self.name, buf) {
+            self.error
+                .extend_mut(duplicate_key(&key).for_field(self.name));
+            return;
+        }
         push_json_string(buf, self.name);
         buf.push_str(":{");
 
         if let Some(second) = obs.next() {
             buf.push_str("\"values\":[");
-            push_observation(buf, first, self.multiplicity);
-            push_observation_comma(buf, second, self.multiplicity);
+            push_observation(buf, first, format);
+            push_observation_comma(buf, second, format);
             for ob in obs {
-                push_observation_comma(buf, ob, self.multiplicity);
+                push_observation_comma(buf, ob, format);
             }
             buf.push(']');
         } else {
             buf.push_str("\"value\":");
-            push_observation(buf, first, self.multiplicity);
+            push_observation(buf, first, format);
         }
 
         if unit != Unit::None {
@@ -272,25 +525,38 @@ impl<'b, 'c> ValueWriter for JsonValueWriter<'b, 'c> {
     }
 }
 
+fn duplicate_key(key: &str) -> ValidationError {
+    ValidationError::invalid(format!(
+        "group `{key}` collides with another group or value of the same name"
+    ))
+}
+
+/// How observations of a metric are written
+#[derive(Clone, Copy)]
+struct ObservationFormat {
+    multiplicity: Option<u64>,
+    float_precision: FloatPrecision,
+}
+
 /// Push a comma followed by an observation (for array items after the first).
-fn push_observation_comma(buf: &mut String, obs: Observation, multiplicity: Option<u64>) {
+fn push_observation_comma(buf: &mut String, obs: Observation, format: ObservationFormat) {
     buf.push(',');
-    push_observation(buf, obs, multiplicity);
+    push_observation(buf, obs, format);
 }
 
 /// Push a scalar observation value into the buffer.
-fn push_observation(buf: &mut String, obs: Observation, multiplicity: Option<u64>) {
+fn push_observation(buf: &mut String, obs: Observation, format: ObservationFormat) {
     match obs {
         Observation::Unsigned(v) => {
             buf.push_str(itoa::Buffer::new().format(v));
         }
         Observation::Floating(v) => {
-            push_float(buf, v);
+            push_float(buf, format.float_precision.round(v));
         }
         Observation::Repeated { total, occurrences } => {
-            let mult = multiplicity.unwrap_or(1);
+            let mult = format.multiplicity.unwrap_or(1);
             buf.push_str("{\"total\":");
-            push_float(buf, total);
+            push_float(buf, format.float_precision.round(total));
             buf.push_str(",\"count\":");
             buf.push_str(itoa::Buffer::new().format(occurrences.saturating_mul(mult)));
             buf.push('}');
@@ -430,6 +696,11 @@ mod tests {
         serde_json::from_str(json).unwrap()
     }
 
+    #[test]
+    fn json_conforms() {
+        metrique_writer::test_util::conformance::FormatConformance::new(Json::new).run();
+    }
+
     #[test]
     fn test_simple_entry() {
         let mut format = Json::new();
@@ -449,6 +720,143 @@ mod tests {
             }"#
             ),
         );
+        // the names of values outside of groups are not copied
+        assert!(format.groups.keys.is_empty());
+        assert!(format.groups.names.is_empty());
+    }
+
+    struct GroupedEntry;
+    impl Entry for GroupedEntry {
+        fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+            writer.timestamp(SystemTime::UNIX_EPOCH + Duration::from_secs(1705312800));
+            writer.value("Operation", "GetItem");
+            writer.begin_group("Cache", "Cache");
+            writer.value("CacheHits", &3u64);
+            writer.begin_group("CacheEviction", "CacheEviction");
+            writer.value("CacheEvictionCount", &1u64);
+            writer.value("CacheEvictionReason", "Size");
+            writer.end_group();
+            writer.value("CacheRegion", "us-east-1");
+            writer.end_group();
+            // a group without values is not written
+            writer.begin_group("Empty", "Empty");
+            writer.end_group();
+            writer.value("Count", &10u64);
+            // groups the entry doesn't end are closed
+            writer.begin_group("Retry", "Retry");
+            writer.value("RetryAttempts", &2u64);
+        }
+    }
+
+    #[test]
+    fn test_grouped_entry() {
+        let mut format = Json::new();
+        let mut output = Vec::new();
+        format.format(&GroupedEntry, &mut output).unwrap();
+
+        assert_eq!(
+            parse_output(&output),
+            expected(
+                r#"{
+                "timestamp": 1705312800000,
+                "metrics": {
+                    "Cache": {
+                        "Hits": { "value": 3 },
+                        "Eviction": { "Count": { "value": 1 } }
+                    },
+                    "Count": { "value": 10 },
+                    "Retry": { "Attempts": { "value": 2 } }
+                },
+                "properties": {
+                    "Operation": "GetItem",
+                    "Cache": { "Eviction": { "Reason": "Size" }, "Region": "us-east-1" }
+                }
+            }"#
+            ),
+        );
+    }
+
+    #[test]
+    fn test_group_key_collisions_are_rejected() {
+        let mut format = Json::new();
+        let mut output = Vec::new();
+
+        struct ValueThenGroup;
+        impl Entry for ValueThenGroup {
+            fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+                writer.value("Cache", &1u64);
+                writer.begin_group("Cache", "Cache");
+                writer.value("CacheHits", &3u64);
+                writer.end_group();
+            }
+        }
+        let err = format.format(&ValueThenGroup, &mut output).unwrap_err();
+        assert!(err.to_string().contains("group `Cache` collides"), "{err}");
+
+        struct GroupThenValue;
+        impl Entry for GroupThenValue {
+            fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+                writer.begin_group("Cache", "Cache");
+                writer.value("CacheRegion", "us-east-1");
+                writer.end_group();
+                writer.value("Cache", "enabled");
+            }
+        }
+        assert!(format.format(&GroupThenValue, &mut output).is_err());
+
+        struct DuplicateGroups;
+        impl Entry for DuplicateGroups {
+            fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+                for region in ["us-east-1", "us-west-2"] {
+                    writer.begin_group("Cache", "Cache");
+                    writer.value("CacheRegion", region);
+                    writer.end_group();
+                }
+            }
+        }
+        assert!(format.format(&DuplicateGroups, &mut output).is_err());
+
+        // a value whose name starts with the key of a group doesn't collide with it
+        struct PrefixThenGroup;
+        impl Entry for PrefixThenGroup {
+            fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+                writer.value("CacheSize", &1u64);
+                writer.begin_group("Cache", "Cache");
+                writer.value("CacheHits", &3u64);
+                writer.end_group();
+            }
+        }
+        format.format(&PrefixThenGroup, &mut output).unwrap();
+
+        // the same group key in `metrics` and `properties`, or in different parents, is fine
+        struct DistinctObjects;
+        impl Entry for DistinctObjects {
+            fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+                writer.value("Cache", "enabled");
+                writer.begin_group("Cache", "Cache");
+                writer.value("CacheHits", &3u64);
+                writer.begin_group("CacheRetry", "CacheRetry");
+                writer.value("CacheRetryCount", &1u64);
+                writer.end_group();
+                writer.end_group();
+                writer.begin_group("Retry", "Retry");
+                writer.value("RetryCount", &2u64);
+                writer.end_group();
+            }
+        }
+        output.clear();
+        format.format(&DistinctObjects, &mut output).unwrap();
+        let output = parse_output(&output);
+        assert_eq!(
+            output["metrics"],
+            expected(
+                r#"{
+                    "Cache": { "Hits": { "value": 3 }, "Retry": { "Count": { "value": 1 } } },
+                    "Retry": { "Count": { "value": 2 } }
+                }"#
+            )
+        );
+        assert_eq!(output["properties"], expected(r#"{ "Cache": "enabled" }"#));
     }
 
     struct RepeatedEntry;
@@ -703,6 +1111,27 @@ mod tests {
         assert!(json["metrics"]["neg_inf_val"]["value"].as_f64().unwrap() < -1e300);
     }
 
+    #[test]
+    fn test_float_precision() {
+        struct PreciseEntry;
+        impl Entry for PreciseEntry {
+            fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+                writer.value("float", &(100.0f64 / 3.0));
+                writer.value("integer", &123456u64);
+                writer.value("nan_val", &f64::NAN);
+            }
+        }
+
+        let mut format = Json::new().float_precision(FloatPrecision::DecimalPlaces(1));
+        let mut output = Vec::new();
+        format.format(&PreciseEntry, &mut output).unwrap();
+
+        let s = String::from_utf8(output).unwrap();
+        assert!(s.contains(r#""float":{"value":33.3}"#), "{s}");
+        assert!(s.contains(r#""integer":{"value":123456}"#), "{s}");
+        assert!(s.contains(r#""nan_val":{"value":null}"#), "{s}");
+    }
+
     #[test]
     fn test_buffer_reuse() {
         let mut format = Json::new();
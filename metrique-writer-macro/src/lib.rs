@@ -67,6 +67,9 @@ synstructure::decl_derive!([$name, attributes(entry)] =>
     ///     cloneable and impl `Into<Cow<'static, str>>`!
     ///  * `#[entry(format = FORMATTER)]` to format the field using a custom format, which should be a type
     ///    implementing `ValueFormatter`.
+    ///  * `#[entry(unit = UNIT)]` to write the field with the unit `UNIT`, a type implementing `UnitTag` like
+    ///    `Millisecond`, converting from the field's own unit like `WithUnit` does. `unit = Custom("{label}")` attaches
+    ///    a user-defined unit label to a unitless value, like `WithCustomUnit`. Can't be combined with `format`.
     ///
     /// # Enums
     ///
@@ -155,6 +158,37 @@ struct ParsedFieldMetricAttr {
     flatten: Option<SpannedValue<()>>,
     timestamp: Option<SpannedValue<()>>,
     format: Option<SpannedValue<Path>>,
+    unit: Option<SpannedValue<UnitAttr>>,
+}
+
+// The value of `#[entry(unit = ...)]`
+enum UnitAttr {
+    // A `UnitTag` type, like `Millisecond`
+    Tag(Path),
+    // A user-defined unit label, `Custom("Widgets")`
+    Custom(syn::LitStr),
+}
+
+impl darling::FromMeta for UnitAttr {
+    fn from_expr(expr: &syn::Expr) -> darling::Result<Self> {
+        match expr {
+            syn::Expr::Call(call) if matches!(&*call.func, syn::Expr::Path(func) if func.path.is_ident("Custom")) => {
+                match call.args.iter().collect::<Vec<_>>().as_slice() {
+                    [
+                        syn::Expr::Lit(syn::ExprLit {
+                            lit: syn::Lit::Str(label),
+                            ..
+                        }),
+                    ] if !label.value().is_empty() => Ok(UnitAttr::Custom(label.clone())),
+                    _ => Err(darling::Error::custom(
+                        r#"expected a non-empty unit label like `Custom("Widgets")`"#,
+                    )
+                    .with_span(&call.span())),
+                }
+            }
+            _ => Path::from_expr(expr).map(UnitAttr::Tag),
+        }
+    }
 }
 
 // Validated per-field attributes
@@ -165,6 +199,7 @@ enum FieldMetricAttr {
     NamedValue {
         name: Option<SpannedValue<String>>,
         format: Option<SpannedValue<Path>>,
+        unit: Option<SpannedValue<UnitAttr>>,
         sample_group: Option<Span>,
     },
 }
@@ -176,6 +211,7 @@ impl FieldMetricAttr {
                 name,
                 sample_group,
                 format,
+                unit,
                 ignore: None,
                 flatten: None,
                 timestamp: None,
@@ -185,10 +221,17 @@ impl FieldMetricAttr {
                 {
                     return Err(syn::Error::new(name.span(), "`name` can't be empty"));
                 }
+                if let (Some(_), Some(unit)) = (&format, &unit) {
+                    return Err(syn::Error::new(
+                        unit.span(),
+                        "can't combine `unit` and `format`, apply the unit in the `ValueFormatter` instead",
+                    ));
+                }
                 Ok(Self::NamedValue {
                     name,
                     sample_group: sample_group.map(|g| g.span()),
                     format,
+                    unit,
                 })
             }
 
@@ -199,6 +242,7 @@ impl FieldMetricAttr {
                 flatten: None,
                 timestamp: None,
                 format: None,
+                unit: None,
             } => Ok(Self::Ignore),
 
             ParsedFieldMetricAttr {
@@ -208,6 +252,7 @@ impl FieldMetricAttr {
                 flatten: Some(_flatten),
                 timestamp: None,
                 format: None,
+                unit: None,
             } => Ok(Self::Flatten),
 
             ParsedFieldMetricAttr {
@@ -217,11 +262,12 @@ impl FieldMetricAttr {
                 flatten: None,
                 timestamp: Some(timestamp),
                 format: None,
+                unit: None,
             } => Ok(Self::Timestamp(timestamp.span())),
 
             _ => Err(syn::Error::new(
                 field_span,
-                "can only combine `name` and `sample_group` in `#[entry]`",
+                "can only combine `name`, `sample_group`, `format` and `unit` in `#[entry]`",
             )),
         }
     }
@@ -399,6 +445,7 @@ impl FieldSet {
                 name,
                 sample_group,
                 format,
+                unit,
             } => {
                 let name = Literal::string(&if let Some(name) = name {
                     self.namer.specified(&name)?
@@ -406,14 +453,24 @@ impl FieldSet {
                     self.namer.unspecified(field)?
                 });
 
-                let field_tokens: TokenStream = match format {
-                    None => field.to_token_stream(),
-                    Some(format) => {
+                let field_tokens: TokenStream = match (format, unit.as_deref()) {
+                    (Some(format), _) => {
                         let format = &*format;
                         quote_spanned! {field.binding.span() =>
                             &#krate::core::value::FormattedValue::<_, #format, _>::new(#field)
                         }
                     }
+                    (None, Some(UnitAttr::Tag(unit))) => {
+                        quote_spanned! {unit.span() =>
+                            &#krate::core::unit::WithUnit::<_, #unit>::from(#field)
+                        }
+                    }
+                    (None, Some(UnitAttr::Custom(label))) => {
+                        quote_spanned! {label.span() =>
+                            &#krate::core::unit::WithCustomUnit::new(#field, #label)
+                        }
+                    }
+                    (None, None) => field.to_token_stream(),
                 };
                 self.writes.push(quote_spanned! {field.binding.span()=>
                     #krate::core::entry::EntryWriter::value(writer, #name, #field_tokens);
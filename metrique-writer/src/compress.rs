@@ -0,0 +1,270 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Compression of formatted entries, for archival destinations with a high volume of entries.
+//!
+//! The codecs are enabled by the `gzip` and `zstd` features. Entries are compressed in batches:
+//! every flush of the destination (which the [`BackgroundQueue`] does every
+//! [flush interval](crate::sink::BackgroundQueueBuilder::flush_interval)) writes the entries
+//! written since the previous flush as one self-contained gzip member or zstd frame. A
+//! concatenation of members or frames is itself a valid gzip or zstd stream, so the output can be
+//! read back with `zcat` or `zstdcat`, and a destination that is cut short (for example by a crash)
+//! only loses its last batch.
+//!
+//! Use [`CompressedWriter`] around a file destination, and
+//! [`SocketWriter::with_compression`](crate::socket::SocketWriter::with_compression) for a
+//! network destination:
+//!
+//! ```
+//! # #[cfg(feature = "zstd")] {
+//! use metrique_writer::{BoxEntry, FormatExt, sink::BackgroundQueue};
+//! use metrique_writer::compress::{CompressedWriter, Compression};
+//! use metrique_writer_format_emf::Emf;
+//!
+//! let dir = tempfile::tempdir().unwrap();
+//! let file = std::fs::File::create(dir.path().join("metrics.log.zst")).unwrap();
+//! let stream = Emf::all_validations("MyApp".into(), vec![vec![]])
+//!     .output_to(CompressedWriter::new(file, Compression::zstd()));
+//! let (queue, _join) = BackgroundQueue::<BoxEntry>::new(stream);
+//! # }
+//! ```
+//!
+//! Note that [`EntryIoStream::bytes_written`](crate::EntryIoStream::bytes_written) and the
+//! [`max_batch_bytes`](crate::sink::BackgroundQueueBuilder::max_batch_bytes) of the queue count
+//! the bytes *before* compression.
+//!
+//! [`BackgroundQueue`]: crate::sink::BackgroundQueue
+
+use std::{fmt, io};
+
+/// The default size of the uncompressed data after which a batch is compressed and written even
+/// without a flush, see [`CompressedWriter::with_max_batch_size`]
+pub const DEFAULT_MAX_BATCH_SIZE: usize = 1024 * 1024;
+
+/// A compression codec and level
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Compression {
+    /// No compression, the data is written as-is
+    #[default]
+    None,
+    /// gzip, with a level between 0 (fastest) and 9 (smallest). Requires the `gzip` feature.
+    #[cfg(feature = "gzip")]
+    Gzip {
+        /// The compression level
+        level: u32,
+    },
+    /// zstd, with a level between 1 (fastest) and 22 (smallest). Requires the `zstd` feature.
+    #[cfg(feature = "zstd")]
+    Zstd {
+        /// The compression level
+        level: i32,
+    },
+}
+
+impl Compression {
+    /// gzip with the default level, 6
+    #[cfg(feature = "gzip")]
+    pub const fn gzip() -> Self {
+        Self::Gzip { level: 6 }
+    }
+
+    /// zstd with the default level, 3
+    #[cfg(feature = "zstd")]
+    pub const fn zstd() -> Self {
+        Self::Zstd { level: 3 }
+    }
+
+    /// Compress `data` into a single gzip member or zstd frame
+    pub fn compress(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        match *self {
+            Self::None => Ok(data.to_vec()),
+            #[cfg(feature = "gzip")]
+            Self::Gzip { level } => {
+                use io::Write as _;
+                let mut encoder = flate2::write::GzEncoder::new(
+                    Vec::with_capacity(data.len() / 4),
+                    flate2::Compression::new(level),
+                );
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+            #[cfg(feature = "zstd")]
+            Self::Zstd { level } => zstd::bulk::compress(data, level),
+        }
+    }
+}
+
+/// An [`io::Write`] that compresses the data written to it in batches, see the
+/// [module docs](self).
+///
+/// The data written since the last batch is compressed and written to the inner writer on every
+/// flush, once it reaches the [max batch size](Self::with_max_batch_size), and when the writer is
+/// dropped. If compressing or writing a batch fails, the batch is dropped. Since the data of a
+/// write is buffered before its batch is written, the write still succeeds, and the error is
+/// returned by the next [`flush`](io::Write::flush).
+pub struct CompressedWriter<W: io::Write> {
+    inner: W,
+    compression: Compression,
+    batch: Vec<u8>,
+    max_batch_size: usize,
+    /// The error of the last batch that failed to be written outside of a flush
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write> CompressedWriter<W> {
+    /// Compress the data written to `inner` with `compression`
+    pub fn new(inner: W, compression: Compression) -> Self {
+        Self {
+            inner,
+            compression,
+            batch: Vec::new(),
+            max_batch_size: DEFAULT_MAX_BATCH_SIZE,
+            error: None,
+        }
+    }
+
+    /// Set the size of the uncompressed data after which a batch is written even without a flush.
+    ///
+    /// Defaults to [`DEFAULT_MAX_BATCH_SIZE`]. Larger batches compress better, but use more memory.
+    pub fn with_max_batch_size(mut self, max_batch_size: usize) -> Self {
+        assert!(max_batch_size > 0, "max_batch_size must not be zero");
+        self.max_batch_size = max_batch_size;
+        self
+    }
+
+    /// The inner writer
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    fn write_batch(&mut self) -> io::Result<()> {
+        if self.batch.is_empty() {
+            return Ok(());
+        }
+        // drop the batch even on failure, to keep memory bounded
+        let compressed = self.compression.compress(&self.batch);
+        self.batch.clear();
+        self.inner.write_all(&compressed?)
+    }
+}
+
+impl<W: io::Write> io::Write for CompressedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.batch.extend_from_slice(buf);
+        if self.batch.len() >= self.max_batch_size
+            && let Err(err) = self.write_batch()
+        {
+            // `buf` was consumed, the error is returned by the next flush
+            self.error = Some(err);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let deferred = self.error.take();
+        self.write_batch()?;
+        if let Some(err) = deferred {
+            return Err(err);
+        }
+        self.inner.flush()
+    }
+}
+
+impl<W: io::Write> Drop for CompressedWriter<W> {
+    fn drop(&mut self) {
+        let _ = io::Write::flush(self);
+    }
+}
+
+impl<W: io::Write + fmt::Debug> fmt::Debug for CompressedWriter<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompressedWriter")
+            .field("inner", &self.inner)
+            .field("compression", &self.compression)
+            .field("pending", &self.batch.len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+
+    use super::*;
+
+    #[test]
+    fn no_compression_batches_until_flush() {
+        let mut writer = CompressedWriter::new(Vec::new(), Compression::None);
+        writer.write_all(b"a\nb\n").unwrap();
+        assert!(writer.get_ref().is_empty());
+        writer.flush().unwrap();
+        assert_eq!(writer.get_ref(), b"a\nb\n");
+    }
+
+    #[test]
+    fn writes_batch_once_full() {
+        let mut writer =
+            CompressedWriter::new(Vec::new(), Compression::None).with_max_batch_size(4);
+        writer.write_all(b"ab").unwrap();
+        assert!(writer.get_ref().is_empty());
+        writer.write_all(b"cd").unwrap();
+        assert_eq!(writer.get_ref(), b"abcd");
+    }
+
+    #[test]
+    fn failed_batches_are_reported_by_flush() {
+        struct Failing;
+        impl Write for Failing {
+            fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+                Err(io::Error::other("disk full"))
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut writer = CompressedWriter::new(Failing, Compression::None).with_max_batch_size(4);
+        assert_eq!(writer.write(b"abcd").unwrap(), 4);
+        assert_eq!(writer.flush().unwrap_err().to_string(), "disk full");
+        writer.flush().unwrap();
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn gzip_batches_decompress_as_one_stream() {
+        let mut writer = CompressedWriter::new(Vec::new(), Compression::gzip());
+        writer.write_all(b"first\n").unwrap();
+        writer.flush().unwrap();
+        writer.write_all(b"second\n").unwrap();
+        writer.flush().unwrap();
+
+        let mut output = String::new();
+        flate2::read::MultiGzDecoder::new(&writer.get_ref()[..])
+            .read_to_string(&mut output)
+            .unwrap();
+        assert_eq!(output, "first\nsecond\n");
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd_batches_decompress_as_one_stream() {
+        let mut writer = CompressedWriter::new(Vec::new(), Compression::zstd());
+        let line = b"{\"Operation\":\"GetItem\",\"Latency\":12}\n";
+        for _ in 0..100 {
+            writer.write_all(line).unwrap();
+        }
+        writer.flush().unwrap();
+        writer.write_all(b"last\n").unwrap();
+        writer.flush().unwrap();
+        assert!(writer.get_ref().len() < line.len() * 10);
+
+        let mut output = Vec::new();
+        zstd::stream::read::Decoder::new(&writer.get_ref()[..])
+            .unwrap()
+            .read_to_end(&mut output)
+            .unwrap();
+        assert_eq!(output.len(), line.len() * 100 + 5);
+        assert!(output.ends_with(b"last\n"));
+    }
+}
@@ -0,0 +1,209 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::time::SystemTime;
+
+use metrique_writer_core::{
+    Entry, EntryWriter, Observation, Unit, Value, ValueWriter, entry::SampleGroupElement,
+    value::MetricFlags,
+};
+use smallvec::SmallVec;
+
+use crate::CowStr;
+
+/// Builds an [`Entry`] at runtime, for entries whose fields aren't known at compile time, like
+/// the metrics of plugins or scripts.
+///
+/// The built [`DynamicEntry`] is written like any other entry, by the same sinks and formats.
+/// Fields are written in the order they were added.
+///
+/// ```
+/// # use metrique_writer::{Unit, Observation, entry::EntryBuilder, unit::NegativeScale};
+/// # use metrique_writer::test_util::to_test_entry;
+/// let mut builder = EntryBuilder::new()
+///     .sample_group("Plugin", "resize")
+///     .property("Version", "1.2");
+/// for (name, value) in [("ImagesResized", 3), ("ImagesSkipped", 1)] {
+///     builder = builder.metric(name, Observation::Unsigned(value), Unit::Count);
+/// }
+/// let entry = builder
+///     .metric("Latency", Observation::Floating(12.5), Unit::Second(NegativeScale::Milli))
+///     .build();
+///
+/// let entry = to_test_entry(&entry);
+/// assert_eq!(entry.values["Plugin"], "resize");
+/// assert_eq!(entry.metrics["ImagesResized"], 3);
+/// assert_eq!(entry.metrics["Latency"].unit, Unit::Second(NegativeScale::Milli));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct EntryBuilder {
+    entry: DynamicEntry,
+}
+
+impl EntryBuilder {
+    /// Create a builder for an empty entry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the timestamp of the entry
+    pub fn timestamp(mut self, timestamp: SystemTime) -> Self {
+        self.entry.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Add a string property
+    pub fn property(mut self, name: impl Into<CowStr>, value: impl Into<CowStr>) -> Self {
+        self.entry
+            .fields
+            .push((name.into(), DynamicValue::String(value.into())));
+        self
+    }
+
+    /// Add a string property that is also a sample group, see
+    /// [`SampleGroup`](metrique_writer_core::SampleGroup)
+    pub fn sample_group(mut self, name: impl Into<CowStr>, value: impl Into<CowStr>) -> Self {
+        let (name, value) = (name.into(), value.into());
+        self.entry.sample_group.push((name.clone(), value.clone()));
+        self.entry.fields.push((name, DynamicValue::String(value)));
+        self
+    }
+
+    /// Add a metric with a single observation
+    pub fn metric(self, name: impl Into<CowStr>, observation: Observation, unit: Unit) -> Self {
+        self.distribution(name, [observation], unit)
+    }
+
+    /// Add a metric with several observations, for formats that support distributions
+    pub fn distribution(
+        mut self,
+        name: impl Into<CowStr>,
+        observations: impl IntoIterator<Item = Observation>,
+        unit: Unit,
+    ) -> Self {
+        self.entry.fields.push((
+            name.into(),
+            DynamicValue::Metric {
+                observations: observations.into_iter().collect(),
+                unit,
+            },
+        ));
+        self
+    }
+
+    /// Build the entry
+    pub fn build(self) -> DynamicEntry {
+        self.entry
+    }
+}
+
+/// An [`Entry`] built at runtime by an [`EntryBuilder`]
+#[derive(Debug, Clone, Default)]
+pub struct DynamicEntry {
+    timestamp: Option<SystemTime>,
+    fields: Vec<(CowStr, DynamicValue)>,
+    sample_group: Vec<SampleGroupElement>,
+}
+
+#[derive(Debug, Clone)]
+enum DynamicValue {
+    String(CowStr),
+    Metric {
+        observations: SmallVec<[Observation; 1]>,
+        unit: Unit,
+    },
+}
+
+impl Value for DynamicValue {
+    fn write(&self, writer: impl ValueWriter) {
+        match self {
+            Self::String(value) => writer.string(value),
+            Self::Metric { observations, unit } => writer.metric(
+                observations.iter().copied(),
+                *unit,
+                [],
+                MetricFlags::empty(),
+            ),
+        }
+    }
+}
+
+impl Entry for DynamicEntry {
+    fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+        if let Some(timestamp) = self.timestamp {
+            writer.timestamp(timestamp);
+        }
+        for (name, value) in &self.fields {
+            writer.value(&**name, value);
+        }
+    }
+
+    fn sample_group(&self) -> impl Iterator<Item = SampleGroupElement> {
+        self.sample_group.iter().cloned()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use metrique_writer_core::unit::PositiveScale;
+
+    use super::*;
+    use crate::test_util::to_test_entry;
+
+    #[test]
+    fn builds_entry() {
+        let timestamp = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let entry = EntryBuilder::new()
+            .timestamp(timestamp)
+            .sample_group("Operation", "Resize")
+            .property("Plugin", String::from("images"))
+            .metric("Count", Observation::Unsigned(2), Unit::Count)
+            .distribution(
+                "Size",
+                [Observation::Unsigned(10), Observation::Unsigned(20)],
+                Unit::Byte(PositiveScale::Kilo),
+            )
+            .build();
+
+        assert_eq!(
+            entry.sample_group().collect::<Vec<_>>(),
+            [("Operation".into(), "Resize".into())]
+        );
+        let entry = to_test_entry(&entry);
+        assert_eq!(entry.timestamp, Some(timestamp));
+        assert_eq!(entry.values["Operation"], "Resize");
+        assert_eq!(entry.values["Plugin"], "images");
+        assert_eq!(entry.metrics["Count"], 2);
+        assert_eq!(entry.metrics["Count"].unit, Unit::Count);
+        assert_eq!(
+            entry.metrics["Size"].distribution,
+            [Observation::Unsigned(10), Observation::Unsigned(20)]
+        );
+        assert_eq!(entry.metrics["Size"].unit, Unit::Byte(PositiveScale::Kilo));
+    }
+
+    #[test]
+    fn formats_as_emf() {
+        use metrique_writer_core::format::Format;
+        use metrique_writer_format_emf::Emf;
+
+        let entry = EntryBuilder::new()
+            .timestamp(UNIX_EPOCH)
+            .property("Plugin", "images")
+            .metric("Count", Observation::Unsigned(2), Unit::Count)
+            .build();
+        let mut output = vec![];
+        Emf::no_validations("Ns".into(), vec![vec![]])
+            .format(&entry, &mut output)
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(
+            output.contains(r#"{"Name":"Count","Unit":"Count"}"#),
+            "{output}"
+        );
+        assert!(output.contains(r#""Count":2"#), "{output}");
+        assert!(output.contains(r#""Plugin":"images""#), "{output}");
+    }
+}
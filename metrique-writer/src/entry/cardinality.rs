@@ -0,0 +1,382 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::CowStr;
+use metrique_writer_core::{
+    Entry, EntryConfig, EntryWriter, MetricFlags, Observation, Unit, ValidationError, Value,
+    ValueWriter,
+    entry::{Priority, SampleGroupElement},
+};
+use std::{
+    borrow::Cow,
+    cell::Cell,
+    collections::{HashMap, HashSet},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::SystemTime,
+};
+
+/// The value that replaces dimension values past the limit of a [`CardinalityGuard`], unless
+/// changed with [`CardinalityGuard::with_overflow_value`].
+pub const DEFAULT_OVERFLOW_VALUE: &str = "OVERFLOW";
+
+/// Limits the number of distinct values emitted for a set of dimension keys.
+///
+/// The guard remembers the values it has seen for each tracked key. Once a key has reached
+/// `max_distinct_values`, any *new* value of that key is replaced with `OVERFLOW` (see
+/// [`DEFAULT_OVERFLOW_VALUE`]). Values that were seen before the limit was reached keep being
+/// emitted as-is. [`overflow_count`] counts the entries in which at least one value was replaced.
+///
+/// This protects against unbounded dimensions, for example a user ID that accidentally ends up in
+/// a dimension, which can make metric storage costs explode.
+///
+/// A key is checked both when it is written as a string property (which is how EMF dimension sets
+/// refer to dimensions) and when it is attached to a metric as a (class, instance) dimension. An
+/// entry that writes an overflowing value in both places is still counted once.
+///
+/// The guard is cheaply cloneable, and clones share the same state, so a clone can be kept to
+/// monitor [`overflow_count`] after passing the guard to
+/// [`EntryIoStreamExt::guard_cardinality`] or [`FormatExt::guard_cardinality`].
+///
+/// ```
+/// # use metrique_writer::{
+/// #    EntryIoStream,
+/// #    format::{FormatExt as _},
+/// #    entry::CardinalityGuard,
+/// # };
+/// # use metrique_writer_format_emf::Emf;
+/// # use std::io;
+/// fn set_up_emf(out: impl io::Write, guard: CardinalityGuard) -> impl EntryIoStream {
+///     Emf::all_validations("MyApp".into(), vec![vec!["Operation".into(), "Customer".into()]])
+///         .guard_cardinality(guard)
+///         .output_to(out)
+/// }
+///
+/// let guard = CardinalityGuard::new(["Operation", "Customer"], 1000);
+/// let stream = set_up_emf(io::sink(), guard.clone());
+/// // ... later, for example from a periodic health check:
+/// if guard.overflow_count() > 0 {
+///     eprintln!("some dimension values were replaced with OVERFLOW");
+/// }
+/// ```
+///
+/// [`overflow_count`]: CardinalityGuard::overflow_count
+/// [`EntryIoStreamExt::guard_cardinality`]: crate::EntryIoStreamExt::guard_cardinality
+/// [`FormatExt::guard_cardinality`]: crate::format::FormatExt::guard_cardinality
+#[derive(Debug, Clone)]
+pub struct CardinalityGuard {
+    keys: Arc<HashSet<CowStr>>,
+    max_distinct_values: usize,
+    overflow_value: CowStr,
+    state: Arc<CardinalityState>,
+}
+
+#[derive(Debug, Default)]
+struct CardinalityState {
+    seen: Mutex<HashMap<CowStr, HashSet<Box<str>>>>,
+    overflow_count: AtomicU64,
+}
+
+impl CardinalityGuard {
+    /// Create a guard that allows at most `max_distinct_values` distinct values for each of `keys`.
+    pub fn new(
+        keys: impl IntoIterator<Item = impl Into<CowStr>>,
+        max_distinct_values: usize,
+    ) -> Self {
+        Self {
+            keys: Arc::new(keys.into_iter().map(Into::into).collect()),
+            max_distinct_values,
+            overflow_value: Cow::Borrowed(DEFAULT_OVERFLOW_VALUE),
+            state: Default::default(),
+        }
+    }
+
+    /// Replace values past the limit with `overflow_value` instead of `OVERFLOW`.
+    pub fn with_overflow_value(mut self, overflow_value: impl Into<CowStr>) -> Self {
+        self.overflow_value = overflow_value.into();
+        self
+    }
+
+    /// The number of entries in which at least one value was replaced with the overflow value so
+    /// far.
+    pub fn overflow_count(&self) -> u64 {
+        self.state.overflow_count.load(Ordering::Relaxed)
+    }
+
+    /// The number of distinct values seen so far for `key`, not counting the overflow value.
+    pub fn distinct_values(&self, key: &str) -> usize {
+        self.lock_seen().get(key).map_or(0, HashSet::len)
+    }
+
+    /// Forget all the values seen so far, for example when starting a new billing period.
+    ///
+    /// This does not reset [`overflow_count`](Self::overflow_count).
+    pub fn reset(&self) {
+        self.lock_seen().clear();
+    }
+
+    fn lock_seen(&self) -> std::sync::MutexGuard<'_, HashMap<CowStr, HashSet<Box<str>>>> {
+        // the map is always consistent, so it is fine to ignore poisoning
+        self.state
+            .seen
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn tracked_key(&self, key: &str) -> Option<&str> {
+        self.keys.get(key).map(|key| &**key)
+    }
+
+    /// Return `value`, or the overflow value if `key` is tracked and already has too many
+    /// distinct values, in which case `overflowed` is set.
+    fn check<'s>(&'s self, key: &str, value: &'s str, overflowed: &Cell<bool>) -> &'s str {
+        if !self.keys.contains(key) {
+            return value;
+        }
+        let mut seen = self.lock_seen();
+        let values = match seen.get_mut(key) {
+            Some(values) => values,
+            None => seen.entry(Cow::Owned(key.to_owned())).or_default(),
+        };
+        if values.contains(value) {
+            value
+        } else if values.len() < self.max_distinct_values {
+            values.insert(value.into());
+            value
+        } else {
+            overflowed.set(true);
+            &self.overflow_value
+        }
+    }
+}
+
+/// An [`Entry`] whose dimension values are limited by a [`CardinalityGuard`].
+///
+/// This is normally created by [`EntryIoStreamExt::guard_cardinality`] or
+/// [`FormatExt::guard_cardinality`].
+///
+/// [`EntryIoStreamExt::guard_cardinality`]: crate::EntryIoStreamExt::guard_cardinality
+/// [`FormatExt::guard_cardinality`]: crate::format::FormatExt::guard_cardinality
+#[derive(Debug)]
+pub struct WithCardinalityGuard<'g, E> {
+    entry: E,
+    guard: &'g CardinalityGuard,
+}
+
+impl<'g, E> WithCardinalityGuard<'g, E> {
+    /// Limit the dimension values of `entry` with `guard`.
+    pub fn new(entry: E, guard: &'g CardinalityGuard) -> Self {
+        Self { entry, guard }
+    }
+}
+
+impl<E: Entry> Entry for WithCardinalityGuard<'_, E> {
+    fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+        struct EntryWriterWrapper<'g, W> {
+            writer: W,
+            guard: &'g CardinalityGuard,
+            // whether a value of the entry was replaced with the overflow value
+            overflowed: bool,
+        }
+
+        impl<'a, W: EntryWriter<'a>> EntryWriter<'a> for EntryWriterWrapper<'a, W> {
+            fn timestamp(&mut self, timestamp: SystemTime) {
+                self.writer.timestamp(timestamp);
+            }
+
+            fn value(&mut self, name: impl Into<Cow<'a, str>>, value: &(impl Value + ?Sized)) {
+                let name: Cow<'a, str> = name.into();
+                let wrapper = ValueWrapper {
+                    value,
+                    key: self.guard.tracked_key(&name),
+                    guard: self.guard,
+                    overflowed: Cell::new(false),
+                };
+                self.writer.value(name, &wrapper);
+                self.overflowed |= wrapper.overflowed.get();
+            }
+
+            fn config(&mut self, config: &'a dyn EntryConfig) {
+                self.writer.config(config);
+            }
+
+            fn begin_group(
+                &mut self,
+                name: impl Into<Cow<'a, str>>,
+                prefix: impl Into<Cow<'a, str>>,
+            ) {
+                self.writer.begin_group(name, prefix);
+            }
+
+            fn end_group(&mut self) {
+                self.writer.end_group();
+            }
+        }
+
+        let mut writer = EntryWriterWrapper {
+            writer,
+            guard: self.guard,
+            overflowed: false,
+        };
+        self.entry.write(&mut writer);
+        if writer.overflowed {
+            (self.guard.state.overflow_count).fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn sample_group(&self) -> impl Iterator<Item = SampleGroupElement> {
+        self.entry.sample_group()
+    }
+
+    fn priority(&self) -> Priority {
+        self.entry.priority()
+    }
+}
+
+struct ValueWrapper<'v, 'g, V: ?Sized> {
+    value: &'v V,
+    // the name of the value, if it is a tracked key
+    key: Option<&'g str>,
+    guard: &'g CardinalityGuard,
+    overflowed: Cell<bool>,
+}
+
+impl<V: Value + ?Sized> Value for ValueWrapper<'_, '_, V> {
+    fn write(&self, writer: impl ValueWriter) {
+        struct ValueWriterWrapper<'g, 'o, W> {
+            writer: W,
+            key: Option<&'g str>,
+            guard: &'g CardinalityGuard,
+            overflowed: &'o Cell<bool>,
+        }
+
+        impl<W: ValueWriter> ValueWriter for ValueWriterWrapper<'_, '_, W> {
+            fn string(self, value: &str) {
+                match self.key {
+                    Some(key) => {
+                        (self.writer).string(self.guard.check(key, value, self.overflowed))
+                    }
+                    None => self.writer.string(value),
+                }
+            }
+
+            fn metric<'a>(
+                self,
+                distribution: impl IntoIterator<Item = Observation>,
+                unit: Unit,
+                dimensions: impl IntoIterator<Item = (&'a str, &'a str)>,
+                flags: MetricFlags<'_>,
+            ) {
+                let (guard, overflowed) = (self.guard, self.overflowed);
+                self.writer.metric(
+                    distribution,
+                    unit,
+                    dimensions
+                        .into_iter()
+                        .map(|(class, instance)| (class, guard.check(class, instance, overflowed))),
+                    flags,
+                )
+            }
+
+            fn error(self, error: ValidationError) {
+                self.writer.error(error)
+            }
+        }
+
+        self.value.write(ValueWriterWrapper {
+            writer,
+            key: self.key,
+            guard: self.guard,
+            overflowed: &self.overflowed,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn overflows_past_limit() {
+        let guard = CardinalityGuard::new(["UserId"], 2);
+        let overflowed = Cell::new(false);
+        assert_eq!(guard.check("UserId", "a", &overflowed), "a");
+        assert_eq!(guard.check("UserId", "b", &overflowed), "b");
+        assert!(!overflowed.get());
+        assert_eq!(guard.check("UserId", "c", &overflowed), "OVERFLOW");
+        assert!(overflowed.take());
+        // values seen before the limit keep working
+        assert_eq!(guard.check("UserId", "a", &overflowed), "a");
+        // untracked keys are never limited
+        assert_eq!(guard.check("RequestId", "c", &overflowed), "c");
+        assert!(!overflowed.get());
+        assert_eq!(guard.distinct_values("UserId"), 2);
+
+        guard.reset();
+        assert_eq!(guard.check("UserId", "c", &overflowed), "c");
+        assert!(!overflowed.get());
+    }
+
+    #[test]
+    fn guards_properties_and_metric_dimensions() {
+        use metrique_writer_core::value::WithDimensions;
+
+        struct TestEntry {
+            user: &'static str,
+            request_id: &'static str,
+        }
+        impl Entry for TestEntry {
+            fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+                writer.value("UserId", self.user);
+                writer.value("RequestId", self.request_id);
+                writer.value("Latency", &WithDimensions::new(1u64, "UserId", self.user));
+            }
+        }
+
+        let guard = CardinalityGuard::new(["UserId"], 1);
+        let entry = |user, request_id| {
+            crate::test_util::to_test_entry(WithCardinalityGuard::new(
+                TestEntry { user, request_id },
+                &guard,
+            ))
+        };
+        let first = entry("alice", "r1");
+        assert_eq!(first.values["UserId"], "alice");
+        assert_eq!(
+            first.metrics["Latency"].dimensions,
+            [("UserId".to_string(), "alice".to_string())]
+        );
+
+        let second = entry("bob", "r2");
+        assert_eq!(second.values["UserId"], "OVERFLOW");
+        assert_eq!(second.values["RequestId"], "r2");
+        assert_eq!(
+            second.metrics["Latency"].dimensions,
+            [("UserId".to_string(), "OVERFLOW".to_string())]
+        );
+        // the property and the dimension of the entry overflowed, which counts as one entry
+        assert_eq!(guard.overflow_count(), 1);
+
+        entry("carol", "r3");
+        assert_eq!(guard.overflow_count(), 2);
+        entry("alice", "r4");
+        assert_eq!(guard.overflow_count(), 2);
+    }
+
+    #[test]
+    fn clones_share_state() {
+        let guard = CardinalityGuard::new(["UserId"], 0).with_overflow_value("Other");
+        let clone = guard.clone();
+        struct User;
+        impl Entry for User {
+            fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+                writer.value("UserId", "a");
+            }
+        }
+        let entry = crate::test_util::to_test_entry(WithCardinalityGuard::new(User, &clone));
+        assert_eq!(entry.values["UserId"], "Other");
+        assert_eq!(guard.overflow_count(), 1);
+    }
+}
@@ -154,6 +154,18 @@ impl<E: Entry, const N: usize> Entry for WithGlobalDimensions<E, N> {
             fn config(&mut self, config: &'a dyn EntryConfig) {
                 self.writer.config(config);
             }
+
+            fn begin_group(
+                &mut self,
+                name: impl Into<Cow<'a, str>>,
+                prefix: impl Into<Cow<'a, str>>,
+            ) {
+                self.writer.begin_group(name, prefix);
+            }
+
+            fn end_group(&mut self) {
+                self.writer.end_group();
+            }
         }
 
         self.entry.write(&mut EntryWriterWrapper {
@@ -263,7 +275,7 @@ mod test {
 
         let mut converted_global_dimensions: Vec<(&str, &str)> = Vec::new();
         for (c, i) in global_dimensions_entry.global_dimensions() {
-            converted_global_dimensions.push((&c, &i));
+            converted_global_dimensions.push((c, i));
         }
 
         assert_eq!(
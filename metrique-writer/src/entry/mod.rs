@@ -3,7 +3,17 @@
 
 //! Contains various utilities for [Entry](crate::Entry)
 
+mod builder;
+mod cardinality;
 mod dimensions;
 mod map;
+pub(crate) mod size;
+mod snapshot;
+pub use builder::{DynamicEntry, EntryBuilder};
+pub use cardinality::{CardinalityGuard, DEFAULT_OVERFLOW_VALUE, WithCardinalityGuard};
 pub use dimensions::WithGlobalDimensions;
 pub use map::EnumMapEntry;
+pub use size::{CLOUDWATCH_LOGS_MAX_EVENT_SIZE, EntrySizeEstimate, EntrySizeLimit};
+pub use snapshot::{EntrySnapshot, MetricSnapshot};
+
+pub use metrique_writer_core::entry::Priority;
@@ -0,0 +1,504 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    borrow::Cow,
+    ops::Range,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::SystemTime,
+};
+
+use metrique_writer_core::{
+    Entry, EntryConfig, EntryWriter, MetricFlags, Observation, Unit, ValidationError, Value,
+    ValueWriter,
+    entry::{Priority, SampleGroupElement},
+};
+
+/// The maximum size of a CloudWatch Logs event, in bytes
+pub const CLOUDWATCH_LOGS_MAX_EVENT_SIZE: usize = 256 * 1024;
+
+// the bytes around every field, like the quotes, colon and comma in JSON
+const FIELD_OVERHEAD: usize = 6;
+// the digits of a floating point number, which are not worth formatting to count them
+const FLOAT_SIZE: usize = 24;
+
+/// A format-independent estimate of the size of an [`Entry`] once formatted, without formatting
+/// it.
+///
+/// The estimate counts the names and values of every field, and is in the same order of magnitude
+/// as the size of the entry in text formats like EMF or JSON, which can be up to about twice as
+/// large (for example, EMF repeats the names of metrics in its metadata). Use it to find the
+/// fields that make an entry large; [`EntrySizeLimit`] measures the actual formatted size.
+///
+/// ```
+/// # use metrique_writer::{Entry, entry::EntrySizeEstimate};
+/// #[derive(Entry)]
+/// struct RequestMetrics {
+///     operation: &'static str,
+///     response_body: String,
+/// }
+///
+/// let estimate = EntrySizeEstimate::of(&RequestMetrics {
+///     operation: "GetItem",
+///     response_body: "x".repeat(1000),
+/// });
+/// assert!(estimate.total() > 1000);
+/// assert_eq!(estimate.largest(1).next().unwrap().0, "response_body");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct EntrySizeEstimate {
+    fields: Vec<(String, usize)>,
+}
+
+impl EntrySizeEstimate {
+    /// Estimate the size of `entry`
+    pub fn of(entry: &impl Entry) -> Self {
+        struct Estimator {
+            fields: Vec<(String, usize)>,
+        }
+
+        impl<'a> EntryWriter<'a> for Estimator {
+            fn timestamp(&mut self, _timestamp: SystemTime) {}
+
+            fn value(&mut self, name: impl Into<Cow<'a, str>>, value: &(impl Value + ?Sized)) {
+                let name = name.into().into_owned();
+                let size = name.len() + FIELD_OVERHEAD + value_size(value);
+                self.fields.push((name, size));
+            }
+
+            fn config(&mut self, _config: &'a dyn EntryConfig) {}
+        }
+
+        let mut estimator = Estimator { fields: Vec::new() };
+        entry.write(&mut estimator);
+        Self {
+            fields: estimator.fields,
+        }
+    }
+
+    /// The estimated size of the whole entry, in bytes
+    pub fn total(&self) -> usize {
+        self.fields.iter().map(|(_, size)| size).sum()
+    }
+
+    /// The estimated size of every field, in the order they are written
+    pub fn fields(&self) -> &[(String, usize)] {
+        &self.fields
+    }
+
+    /// The `n` largest fields, largest first
+    pub fn largest(&self, n: usize) -> impl Iterator<Item = (&str, usize)> {
+        let mut fields: Vec<_> = self
+            .fields
+            .iter()
+            .map(|(name, size)| (name.as_str(), *size))
+            .collect();
+        fields.sort_by_key(|(_, size)| std::cmp::Reverse(*size));
+        fields.into_iter().take(n)
+    }
+}
+
+/// What a value writes, found by writing it to a [`ValueWriter`] that records nothing but this
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ValueKind {
+    String,
+    Metric,
+    Error,
+}
+
+fn value_kind(value: &(impl Value + ?Sized)) -> Option<ValueKind> {
+    struct Probe<'k>(&'k mut Option<ValueKind>);
+
+    impl ValueWriter for Probe<'_> {
+        fn string(self, _value: &str) {
+            *self.0 = Some(ValueKind::String);
+        }
+
+        fn metric<'a>(
+            self,
+            _distribution: impl IntoIterator<Item = Observation>,
+            _unit: Unit,
+            _dimensions: impl IntoIterator<Item = (&'a str, &'a str)>,
+            _flags: MetricFlags<'_>,
+        ) {
+            *self.0 = Some(ValueKind::Metric);
+        }
+
+        fn error(self, _error: ValidationError) {
+            *self.0 = Some(ValueKind::Error);
+        }
+    }
+
+    let mut kind = None;
+    value.write(Probe(&mut kind));
+    kind
+}
+
+fn value_size(value: &(impl Value + ?Sized)) -> usize {
+    struct Sizer<'s>(&'s mut usize);
+
+    impl ValueWriter for Sizer<'_> {
+        fn string(self, value: &str) {
+            *self.0 = value.len();
+        }
+
+        fn metric<'a>(
+            self,
+            distribution: impl IntoIterator<Item = Observation>,
+            unit: Unit,
+            dimensions: impl IntoIterator<Item = (&'a str, &'a str)>,
+            _flags: MetricFlags<'_>,
+        ) {
+            let observations: usize = distribution
+                .into_iter()
+                .map(|observation| match observation {
+                    Observation::Unsigned(value) => {
+                        value.checked_ilog10().unwrap_or(0) as usize + 2
+                    }
+                    Observation::Floating(_) => FLOAT_SIZE,
+                    Observation::Repeated { .. } => 2 * FLOAT_SIZE,
+                    _ => FLOAT_SIZE,
+                })
+                .sum();
+            let dimensions: usize = dimensions
+                .into_iter()
+                .map(|(class, instance)| class.len() + instance.len() + FIELD_OVERHEAD)
+                .sum();
+            *self.0 = observations + unit.name().len() + dimensions;
+        }
+
+        fn error(self, _error: ValidationError) {}
+    }
+
+    let mut size = 0;
+    value.write(Sizer(&mut size));
+    size
+}
+
+/// A limit on the formatted size of entries, see
+/// [`FormatExt::limit_entry_size`](crate::format::FormatExt::limit_entry_size).
+///
+/// Entries larger than the limit are rejected with a validation error naming their largest
+/// fields, unless [splitting](Self::split_metrics) is enabled. Clones share the same counters.
+#[derive(Debug, Clone)]
+pub struct EntrySizeLimit {
+    max_bytes: usize,
+    split_metrics: bool,
+    state: Arc<EntrySizeLimitState>,
+}
+
+#[derive(Debug, Default)]
+struct EntrySizeLimitState {
+    rejected_count: AtomicU64,
+    split_count: AtomicU64,
+}
+
+impl EntrySizeLimit {
+    /// Limit the formatted size of every entry to `max_bytes`
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            max_bytes,
+            split_metrics: false,
+            state: Default::default(),
+        }
+    }
+
+    /// Limit entries to [`CLOUDWATCH_LOGS_MAX_EVENT_SIZE`], the largest event accepted by
+    /// CloudWatch Logs
+    pub fn cloudwatch_logs() -> Self {
+        Self::new(CLOUDWATCH_LOGS_MAX_EVENT_SIZE)
+    }
+
+    /// Split entries larger than the limit into several entries instead of rejecting them.
+    ///
+    /// Every part has the timestamp and all the properties of the entry, and some of its metrics.
+    /// Entries that are still too large when each part has a single metric, for example because
+    /// of a large property, are rejected.
+    pub fn split_metrics(mut self) -> Self {
+        self.split_metrics = true;
+        self
+    }
+
+    /// The maximum size of an entry, in bytes
+    pub fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    /// The number of entries rejected so far
+    pub fn rejected_count(&self) -> u64 {
+        self.state.rejected_count.load(Ordering::Relaxed)
+    }
+
+    /// The number of entries split so far
+    pub fn split_count(&self) -> u64 {
+        self.state.split_count.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn splits(&self) -> bool {
+        self.split_metrics
+    }
+
+    pub(crate) fn record_split(&self) {
+        self.state.split_count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record that `entry`, formatted to `size` bytes, was rejected, and return the error
+    pub(crate) fn reject(&self, entry: &impl Entry, size: usize) -> ValidationError {
+        self.state.rejected_count.fetch_add(1, Ordering::Relaxed);
+        let largest = EntrySizeEstimate::of(entry)
+            .largest(3)
+            .map(|(name, size)| format!("{name} (~{size} bytes)"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        ValidationError::invalid(format!(
+            "entry of {size} bytes exceeds the limit of {} bytes, largest fields: {largest}",
+            self.max_bytes
+        ))
+    }
+}
+
+/// The number of metrics written by `entry`
+pub(crate) fn metric_count(entry: &impl Entry) -> usize {
+    struct Counter(usize);
+
+    impl<'a> EntryWriter<'a> for Counter {
+        fn timestamp(&mut self, _timestamp: SystemTime) {}
+
+        fn value(&mut self, _name: impl Into<Cow<'a, str>>, value: &(impl Value + ?Sized)) {
+            if value_kind(value) == Some(ValueKind::Metric) {
+                self.0 += 1;
+            }
+        }
+
+        fn config(&mut self, _config: &'a dyn EntryConfig) {}
+    }
+
+    let mut counter = Counter(0);
+    entry.write(&mut counter);
+    counter.0
+}
+
+/// An [`Entry`] that writes every property of `entry`, but only the metrics with an index (in
+/// the order they are written) in `metrics`
+pub(crate) struct MetricSubset<'e, E> {
+    pub(crate) entry: &'e E,
+    pub(crate) metrics: Range<usize>,
+}
+
+impl<E: Entry> Entry for MetricSubset<'_, E> {
+    fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+        struct Filter<'r, W> {
+            writer: W,
+            metrics: &'r Range<usize>,
+            next_metric: usize,
+        }
+
+        impl<'a, W: EntryWriter<'a>> EntryWriter<'a> for Filter<'a, W> {
+            fn timestamp(&mut self, timestamp: SystemTime) {
+                self.writer.timestamp(timestamp);
+            }
+
+            fn value(&mut self, name: impl Into<Cow<'a, str>>, value: &(impl Value + ?Sized)) {
+                if value_kind(value) == Some(ValueKind::Metric) {
+                    let index = self.next_metric;
+                    self.next_metric += 1;
+                    if !self.metrics.contains(&index) {
+                        return;
+                    }
+                }
+                self.writer.value(name, value);
+            }
+
+            fn config(&mut self, config: &'a dyn EntryConfig) {
+                self.writer.config(config);
+            }
+
+            fn begin_group(
+                &mut self,
+                name: impl Into<Cow<'a, str>>,
+                prefix: impl Into<Cow<'a, str>>,
+            ) {
+                self.writer.begin_group(name, prefix);
+            }
+
+            fn end_group(&mut self) {
+                self.writer.end_group();
+            }
+        }
+
+        self.entry.write(&mut Filter {
+            writer,
+            metrics: &self.metrics,
+            next_metric: 0,
+        })
+    }
+
+    fn sample_group(&self) -> impl Iterator<Item = SampleGroupElement> {
+        self.entry.sample_group()
+    }
+
+    fn priority(&self) -> Priority {
+        self.entry.priority()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct TestEntry {
+        operation: &'static str,
+        body: String,
+        metrics: Vec<(&'static str, u64)>,
+    }
+
+    impl Entry for TestEntry {
+        fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+            writer.value("Operation", self.operation);
+            for (name, value) in &self.metrics {
+                writer.value(*name, value);
+            }
+            writer.value("Body", &self.body);
+        }
+    }
+
+    #[test]
+    fn estimates_field_sizes() {
+        let entry = TestEntry {
+            operation: "GetItem",
+            body: "x".repeat(100),
+            metrics: vec![("Latency", 1234)],
+        };
+        let estimate = EntrySizeEstimate::of(&entry);
+        assert_eq!(
+            estimate.fields(),
+            [
+                ("Operation".to_string(), 9 + 6 + 7),
+                ("Latency".to_string(), 7 + 6 + 5 + 4),
+                ("Body".to_string(), 4 + 6 + 100),
+            ]
+        );
+        assert_eq!(estimate.total(), 22 + 22 + 110);
+        assert_eq!(
+            estimate.largest(2).collect::<Vec<_>>(),
+            [("Body", 110), ("Operation", 22)]
+        );
+    }
+
+    #[test]
+    fn subset_keeps_properties_and_selected_metrics() {
+        let entry = TestEntry {
+            operation: "GetItem",
+            body: "body".into(),
+            metrics: vec![("A", 1), ("B", 2), ("C", 3)],
+        };
+        assert_eq!(metric_count(&entry), 3);
+        let subset = crate::test_util::to_test_entry(MetricSubset {
+            entry: &entry,
+            metrics: 1..3,
+        });
+        assert_eq!(subset.values["Operation"], "GetItem");
+        assert_eq!(subset.values["Body"], "body");
+        assert!(!subset.metrics.contains_key("A"));
+        assert_eq!(subset.metrics["B"], 2);
+        assert_eq!(subset.metrics["C"], 3);
+    }
+
+    /// Formats every field as a `name=value` line, and every entry as a line of `---`
+    struct LineFormat;
+
+    impl metrique_writer_core::format::Format for LineFormat {
+        fn format(
+            &mut self,
+            entry: &impl Entry,
+            output: &mut impl std::io::Write,
+        ) -> Result<(), metrique_writer_core::IoStreamError> {
+            let entry = crate::test_util::to_test_entry(entry);
+            let mut lines: Vec<_> = entry
+                .values
+                .iter()
+                .map(|(name, value)| format!("{name}={value}\n"))
+                .chain(
+                    entry
+                        .metrics
+                        .iter()
+                        .map(|(name, metric)| format!("{name}={}\n", metric.as_u64())),
+                )
+                .collect();
+            lines.sort();
+            for line in lines {
+                output.write_all(line.as_bytes())?;
+            }
+            output.write_all(b"---\n")?;
+            Ok(())
+        }
+    }
+
+    fn large_entry() -> TestEntry {
+        TestEntry {
+            operation: "GetItem",
+            body: "body".into(),
+            metrics: vec![("A", 1), ("B", 2), ("C", 3), ("D", 4)],
+        }
+    }
+
+    #[test]
+    fn rejects_large_entries() {
+        use crate::format::FormatExt;
+        use metrique_writer_core::{IoStreamError, format::Format};
+
+        let limit = EntrySizeLimit::new(32);
+        let mut format = LineFormat.limit_entry_size(limit.clone());
+        let mut output = Vec::new();
+        let small = TestEntry {
+            metrics: vec![],
+            ..large_entry()
+        };
+        format.format(&small, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output.clone()).unwrap(),
+            "Body=body\nOperation=GetItem\n---\n"
+        );
+
+        output.clear();
+        let err = format.format(&large_entry(), &mut output).unwrap_err();
+        let IoStreamError::Validation(err) = err else {
+            panic!("unexpected error {err:?}");
+        };
+        let message = err.to_string();
+        assert!(
+            message.contains("entry of 48 bytes exceeds the limit of 32 bytes"),
+            "{message}"
+        );
+        assert!(message.contains("Operation (~22 bytes)"), "{message}");
+        assert!(output.is_empty());
+        assert_eq!(limit.rejected_count(), 1);
+        assert_eq!(limit.split_count(), 0);
+    }
+
+    #[test]
+    fn splits_large_entries() {
+        use crate::format::FormatExt;
+        use metrique_writer_core::format::Format;
+
+        let limit = EntrySizeLimit::new(40).split_metrics();
+        let mut format = LineFormat.limit_entry_size(limit.clone());
+        let mut output = Vec::new();
+        format.format(&large_entry(), &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "A=1\nB=2\nBody=body\nOperation=GetItem\n---\n\
+             Body=body\nC=3\nD=4\nOperation=GetItem\n---\n"
+        );
+        assert_eq!(limit.split_count(), 1);
+
+        // the properties alone are over the limit
+        let limit = EntrySizeLimit::new(20).split_metrics();
+        let mut format = LineFormat.limit_entry_size(limit.clone());
+        assert!(format.format(&large_entry(), &mut Vec::new()).is_err());
+        assert_eq!(limit.rejected_count(), 1);
+    }
+}
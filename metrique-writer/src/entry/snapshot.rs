@@ -0,0 +1,415 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{borrow::Cow, collections::BTreeMap, time::SystemTime};
+
+use metrique_writer_core::{
+    Entry, EntryConfig, EntryWriter, MetricFlags, Observation, Unit, ValidationError,
+    ValidationErrorBuilder, Value, ValueWriter,
+};
+
+/// An owned representation of the properties and metrics that an [`Entry`] writes.
+///
+/// Unlike the formatters, which turn an entry into bytes for a specific backend, a snapshot keeps
+/// the typed values, units and dimensions of the entry so that applications can embed it in their
+/// own payloads, for example in a debugging API response or in an audit record. With the
+/// `serde-json` feature, [`EntrySnapshot::to_json`] converts it into a [`serde_json::Value`].
+///
+/// ```
+/// # use std::time::Duration;
+/// # use metrique_writer::{Entry, Observation, Unit, unit::{Millisecond, UnitTag}};
+/// # use metrique_writer::entry::EntrySnapshot;
+/// #[derive(Entry)]
+/// struct RequestMetrics {
+///     operation: &'static str,
+///     latency: Duration,
+/// }
+///
+/// let snapshot = EntrySnapshot::new(&RequestMetrics {
+///     operation: "Get",
+///     latency: Duration::from_millis(5),
+/// })
+/// .unwrap();
+/// assert_eq!(snapshot.properties["operation"], "Get");
+/// assert_eq!(snapshot.metrics["latency"].unit, Millisecond::UNIT);
+/// assert_eq!(snapshot.metrics["latency"].observations, [Observation::Floating(5.0)]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+#[non_exhaustive]
+pub struct EntrySnapshot {
+    /// The timestamp of the entry, if it has one
+    pub timestamp: Option<SystemTime>,
+    /// The string properties of the entry, by name
+    pub properties: BTreeMap<String, String>,
+    /// The metrics of the entry, by name
+    pub metrics: BTreeMap<String, MetricSnapshot>,
+}
+
+/// A metric in an [`EntrySnapshot`]
+#[derive(Debug, Clone, PartialEq, Default)]
+#[non_exhaustive]
+pub struct MetricSnapshot {
+    /// The observations of the metric, more than one for distributions
+    pub observations: Vec<Observation>,
+    /// The unit of the metric
+    pub unit: Unit,
+    /// The dimensions of the metric, in the order they were written
+    pub dimensions: Vec<(String, String)>,
+}
+
+impl EntrySnapshot {
+    /// Take a snapshot of the properties and metrics that `entry` writes.
+    ///
+    /// Returns an error if any value of the entry fails validation, in which case a formatter
+    /// would also refuse to write it.
+    pub fn new(entry: &impl Entry) -> Result<Self, ValidationError> {
+        let mut writer = SnapshotWriter {
+            snapshot: Self::default(),
+            errors: ValidationError::builder(),
+        };
+        entry.write(&mut writer);
+        writer.errors.build()?;
+        Ok(writer.snapshot)
+    }
+
+    /// Convert this snapshot into a JSON object.
+    ///
+    /// The object has a `Timestamp` in milliseconds since the Unix epoch when the entry has a
+    /// timestamp, a `Properties` object of strings and a `Metrics` object in which each metric
+    /// has `Values`, `Unit` and `Dimensions`. Single observations are written as numbers,
+    /// repeated observations as `{"Total": .., "Occurrences": ..}` and non-finite floats as
+    /// `null`.
+    ///
+    /// ```
+    /// # use metrique_writer::{Entry, entry::EntrySnapshot, value::WithDimension};
+    /// #[derive(Entry)]
+    /// struct RequestMetrics {
+    ///     operation: &'static str,
+    ///     requests: WithDimension<u64>,
+    /// }
+    ///
+    /// let entry = RequestMetrics {
+    ///     operation: "Get",
+    ///     requests: WithDimension::new(1, "Region", "us-east-1"),
+    /// };
+    /// assert_eq!(
+    ///     EntrySnapshot::new(&entry).unwrap().to_json(),
+    ///     serde_json::json!({
+    ///         "Properties": { "operation": "Get" },
+    ///         "Metrics": {
+    ///             "requests": {
+    ///                 "Values": [1],
+    ///                 "Unit": "None",
+    ///                 "Dimensions": { "Region": "us-east-1" },
+    ///             },
+    ///         },
+    ///     }),
+    /// );
+    /// ```
+    #[cfg(feature = "serde-json")]
+    pub fn to_json(&self) -> serde_json::Value {
+        use serde_json::{Map, Value as Json};
+
+        let mut object = Map::new();
+        if let Some(timestamp) = self.timestamp {
+            let millis = timestamp
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0, |d| d.as_millis() as u64);
+            object.insert("Timestamp".into(), millis.into());
+        }
+        let properties = self
+            .properties
+            .iter()
+            .map(|(name, value)| (name.clone(), Json::from(value.as_str())))
+            .collect();
+        object.insert("Properties".into(), Json::Object(properties));
+        let metrics = self
+            .metrics
+            .iter()
+            .map(|(name, metric)| (name.clone(), metric.to_json()))
+            .collect();
+        object.insert("Metrics".into(), Json::Object(metrics));
+        Json::Object(object)
+    }
+
+    /// Read a snapshot back from the JSON object written by [`EntrySnapshot::to_json`].
+    ///
+    /// Returns `None` if `json` doesn't have that shape. [`Unit::Custom`] units are read as
+    /// [`Unit::None`], since only the name of the unit is written, and non-finite floats are
+    /// read as NaN.
+    ///
+    /// ```
+    /// # use metrique_writer::{Entry, entry::EntrySnapshot};
+    /// #[derive(Entry)]
+    /// struct RequestMetrics {
+    ///     operation: &'static str,
+    ///     retries: u64,
+    /// }
+    ///
+    /// let snapshot = EntrySnapshot::new(&RequestMetrics { operation: "Get", retries: 2 }).unwrap();
+    /// assert_eq!(EntrySnapshot::from_json(&snapshot.to_json()), Some(snapshot));
+    /// ```
+    #[cfg(feature = "serde-json")]
+    pub fn from_json(json: &serde_json::Value) -> Option<Self> {
+        let timestamp = match json.get("Timestamp") {
+            Some(millis) => {
+                Some(SystemTime::UNIX_EPOCH + std::time::Duration::from_millis(millis.as_u64()?))
+            }
+            None => None,
+        };
+        let properties = json
+            .get("Properties")?
+            .as_object()?
+            .iter()
+            .map(|(name, value)| Some((name.clone(), value.as_str()?.to_owned())))
+            .collect::<Option<_>>()?;
+        let metrics = json
+            .get("Metrics")?
+            .as_object()?
+            .iter()
+            .map(|(name, metric)| Some((name.clone(), MetricSnapshot::from_json(metric)?)))
+            .collect::<Option<_>>()?;
+        Some(Self {
+            timestamp,
+            properties,
+            metrics,
+        })
+    }
+}
+
+#[cfg(feature = "serde-json")]
+impl MetricSnapshot {
+    fn to_json(&self) -> serde_json::Value {
+        use serde_json::{Value as Json, json};
+
+        let observation = |observation: &Observation| match *observation {
+            Observation::Unsigned(v) => Json::from(v),
+            Observation::Repeated { total, occurrences } => {
+                json!({ "Total": total, "Occurrences": occurrences })
+            }
+            single => Json::from(single.total()),
+        };
+        let dimensions: serde_json::Map<_, _> = self
+            .dimensions
+            .iter()
+            .map(|(key, value)| (key.clone(), Json::from(value.as_str())))
+            .collect();
+        json!({
+            "Values": self.observations.iter().map(observation).collect::<Vec<_>>(),
+            "Unit": self.unit.name(),
+            "Dimensions": dimensions,
+        })
+    }
+
+    fn from_json(json: &serde_json::Value) -> Option<Self> {
+        use serde_json::Value as Json;
+
+        let number = |value: &Json| match value {
+            Json::Null => Some(Observation::Floating(f64::NAN)),
+            Json::Number(n) => Some(match n.as_u64() {
+                Some(v) => Observation::Unsigned(v),
+                None => Observation::Floating(n.as_f64()?),
+            }),
+            _ => None,
+        };
+        let observation = |value: &Json| match value.get("Total") {
+            Some(total) => Some(Observation::Repeated {
+                total: total.as_f64().unwrap_or(f64::NAN),
+                occurrences: value.get("Occurrences")?.as_u64()?,
+            }),
+            None => number(value),
+        };
+        let observations = json
+            .get("Values")?
+            .as_array()?
+            .iter()
+            .map(observation)
+            .collect::<Option<_>>()?;
+        let unit = Unit::from_name(json.get("Unit")?.as_str()?).unwrap_or_default();
+        let dimensions = json
+            .get("Dimensions")?
+            .as_object()?
+            .iter()
+            .map(|(key, value)| Some((key.clone(), value.as_str()?.to_owned())))
+            .collect::<Option<_>>()?;
+        Some(Self {
+            observations,
+            unit,
+            dimensions,
+        })
+    }
+}
+
+#[cfg(feature = "serde-json")]
+impl From<EntrySnapshot> for serde_json::Value {
+    fn from(snapshot: EntrySnapshot) -> Self {
+        snapshot.to_json()
+    }
+}
+
+/// A snapshot is itself an [`Entry`], which writes the same timestamp, properties and metrics
+/// as the entry it was taken from. Format-specific configuration is not kept.
+impl Entry for EntrySnapshot {
+    fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+        if let Some(timestamp) = self.timestamp {
+            writer.timestamp(timestamp);
+        }
+        for (name, value) in &self.properties {
+            writer.value(name.as_str(), value.as_str());
+        }
+        for (name, metric) in &self.metrics {
+            writer.value(name.as_str(), metric);
+        }
+    }
+}
+
+impl Value for MetricSnapshot {
+    fn write(&self, writer: impl ValueWriter) {
+        writer.metric(
+            self.observations.iter().copied(),
+            self.unit,
+            self.dimensions
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.as_str())),
+            MetricFlags::empty(),
+        );
+    }
+}
+
+struct SnapshotWriter {
+    snapshot: EntrySnapshot,
+    errors: ValidationErrorBuilder,
+}
+
+impl<'a> EntryWriter<'a> for SnapshotWriter {
+    fn timestamp(&mut self, timestamp: SystemTime) {
+        self.snapshot.timestamp = Some(timestamp);
+    }
+
+    fn value(&mut self, name: impl Into<Cow<'a, str>>, value: &(impl Value + ?Sized)) {
+        let name = name.into();
+        value.write(SnapshotValueWriter {
+            name: &name,
+            writer: self,
+        });
+    }
+
+    fn config(&mut self, _config: &'a dyn EntryConfig) {
+        // snapshots have no format-specific configuration
+    }
+}
+
+struct SnapshotValueWriter<'w> {
+    name: &'w str,
+    writer: &'w mut SnapshotWriter,
+}
+
+impl ValueWriter for SnapshotValueWriter<'_> {
+    fn string(self, value: &str) {
+        self.writer
+            .snapshot
+            .properties
+            .insert(self.name.to_owned(), value.to_owned());
+    }
+
+    fn metric<'a>(
+        self,
+        distribution: impl IntoIterator<Item = Observation>,
+        unit: Unit,
+        dimensions: impl IntoIterator<Item = (&'a str, &'a str)>,
+        _flags: MetricFlags<'_>,
+    ) {
+        let metric = MetricSnapshot {
+            observations: distribution.into_iter().collect(),
+            unit,
+            dimensions: dimensions
+                .into_iter()
+                .map(|(key, value)| (key.to_owned(), value.to_owned()))
+                .collect(),
+        };
+        self.writer
+            .snapshot
+            .metrics
+            .insert(self.name.to_owned(), metric);
+    }
+
+    fn error(self, error: ValidationError) {
+        self.writer.errors.extend_mut(error.for_field(self.name));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use metrique_writer_core::{Observation, ValidationError, Value, ValueWriter};
+
+    use super::EntrySnapshot;
+    use crate::{
+        Entry,
+        unit::{Millisecond, UnitTag},
+        value::Distribution,
+    };
+
+    struct Invalid;
+
+    impl Value for Invalid {
+        fn write(&self, writer: impl ValueWriter) {
+            writer.error(ValidationError::invalid("not today"));
+        }
+    }
+
+    #[derive(Entry)]
+    struct Metrics {
+        #[entry(timestamp)]
+        timestamp: SystemTime,
+        #[entry(unit = Millisecond)]
+        latencies: Distribution<u64, 2>,
+        skipped: Option<u64>,
+    }
+
+    #[test]
+    fn snapshot_distribution_and_timestamp() {
+        let timestamp = SystemTime::UNIX_EPOCH + Duration::from_millis(1_500);
+        let snapshot = EntrySnapshot::new(&Metrics {
+            timestamp,
+            latencies: [3, 4].into_iter().collect(),
+            skipped: None,
+        })
+        .unwrap();
+        assert_eq!(snapshot.timestamp, Some(timestamp));
+        let metric = &snapshot.metrics["latencies"];
+        assert_eq!(
+            metric.observations,
+            [Observation::Unsigned(3), Observation::Unsigned(4)]
+        );
+        assert_eq!(metric.unit, Millisecond::UNIT);
+        assert!(!snapshot.metrics.contains_key("skipped"));
+
+        #[cfg(feature = "serde-json")]
+        assert_eq!(
+            snapshot.to_json(),
+            serde_json::json!({
+                "Timestamp": 1500,
+                "Properties": {},
+                "Metrics": {
+                    "latencies": { "Values": [3, 4], "Unit": "Milliseconds", "Dimensions": {} },
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn snapshot_reports_validation_errors() {
+        #[derive(Entry)]
+        struct WithInvalid {
+            invalid: Invalid,
+        }
+
+        let err = EntrySnapshot::new(&WithInvalid { invalid: Invalid }).unwrap_err();
+        assert!(
+            err.to_string().contains("for `invalid`: not today"),
+            "{err}"
+        );
+    }
+}
@@ -10,13 +10,16 @@ use metrique_writer_core::{
     stream::{EntryIoStream, IoStreamError},
 };
 
-pub use metrique_writer_core::format::Format;
+pub use metrique_writer_core::format::{FloatPrecision, Format};
 use smallvec::SmallVec;
 
 use crate::{
     CowStr,
-    entry::WithGlobalDimensions,
-    stream::{MergeGlobalDimensions, MergeGlobals},
+    entry::{
+        CardinalityGuard, EntrySizeLimit, WithCardinalityGuard, WithGlobalDimensions,
+        size::{MetricSubset, metric_count},
+    },
+    stream::{GuardCardinality, MergeGlobalDimensions, MergeGlobals},
 };
 
 /// Extension trait for [`Format`]. This adds methods that use types not
@@ -103,6 +106,7 @@ pub trait FormatExt: Format {
         FormattedEntryIoStream {
             format: self,
             output,
+            bytes_written: 0,
         }
     }
 
@@ -157,6 +161,7 @@ pub trait FormatExt: Format {
         FormattedMakeWriterEntryIoStream {
             format: self,
             output,
+            bytes_written: 0,
         }
     }
 
@@ -247,25 +252,202 @@ pub trait FormatExt: Format {
             global_dimensions_denylist: global_dimensions_denylist.unwrap_or_default(),
         }
     }
+
+    /// Limit the number of distinct values of the dimension keys tracked by `guard`, replacing new
+    /// values past the limit with an overflow value. See [`CardinalityGuard`].
+    ///
+    /// There is intentionally both a [`EntryIoStreamExt::guard_cardinality`] and a
+    /// [`FormatExt::guard_cardinality`], which implement exactly the same functionality,
+    /// to allow using in interfaces that accept an [`EntryIoStream`] as well as interfaces
+    /// that accept a [`Format`].
+    ///
+    /// ```
+    /// # use metrique_writer::{
+    /// #    EntryIoStream,
+    /// #    entry::CardinalityGuard,
+    /// #    format::{FormatExt as _},
+    /// # };
+    /// # use metrique_writer_format_emf::Emf;
+    /// # use std::io;
+    /// fn set_up_emf(out: impl io::Write) -> impl EntryIoStream {
+    ///     Emf::all_validations("MyApp".into(), vec![vec!["Customer".into()]])
+    ///         .guard_cardinality(CardinalityGuard::new(["Customer"], 1000))
+    ///         .output_to(out)
+    /// }
+    /// ```
+    ///
+    /// [`EntryIoStreamExt::guard_cardinality`]: crate::EntryIoStreamExt::guard_cardinality
+    fn guard_cardinality(self, guard: CardinalityGuard) -> GuardCardinality<Self>
+    where
+        Self: Sized,
+    {
+        GuardCardinality {
+            stream: self,
+            guard,
+        }
+    }
+
+    /// Limit the formatted size of entries to the byte ceiling of `limit`, for destinations that
+    /// drop or truncate large events, like CloudWatch Logs. See [`EntrySizeLimit`].
+    ///
+    /// Every entry is formatted to a buffer before it is written to the output. Entries over the
+    /// limit are rejected with a [validation error](IoStreamError::Validation) that names their
+    /// largest fields, or are split into several entries with
+    /// [`EntrySizeLimit::split_metrics`].
+    ///
+    /// ```
+    /// # use metrique_writer::{
+    /// #    EntryIoStream,
+    /// #    entry::EntrySizeLimit,
+    /// #    format::{FormatExt as _},
+    /// # };
+    /// # use metrique_writer_format_emf::Emf;
+    /// # use std::io;
+    /// fn set_up_emf(out: impl io::Write) -> impl EntryIoStream {
+    ///     Emf::all_validations("MyApp".into(), vec![vec![]])
+    ///         .limit_entry_size(EntrySizeLimit::cloudwatch_logs().split_metrics())
+    ///         .output_to(out)
+    /// }
+    /// ```
+    fn limit_entry_size(self, limit: EntrySizeLimit) -> LimitEntrySize<Self>
+    where
+        Self: Sized,
+    {
+        LimitEntrySize {
+            format: self,
+            limit,
+            buffer: Vec::new(),
+        }
+    }
 }
 impl<T: Format + ?Sized> FormatExt for T {}
 
+/// A [`Format`] that limits the size of entries, see [`FormatExt::limit_entry_size`]
+#[derive(Debug)]
+pub struct LimitEntrySize<F> {
+    format: F,
+    limit: EntrySizeLimit,
+    buffer: Vec<u8>,
+}
+
+impl<F> LimitEntrySize<F> {
+    /// The limit, to read its counters
+    pub fn limit(&self) -> &EntrySizeLimit {
+        &self.limit
+    }
+}
+
+impl<F: Format> LimitEntrySize<F> {
+    /// Format `entry` split in `parts` parts with contiguous ranges of its `metrics` metrics, and
+    /// return the end offset of every part in the buffer, or `None` if a part is over the limit
+    fn format_parts(
+        &mut self,
+        entry: &impl Entry,
+        metrics: usize,
+        parts: usize,
+    ) -> Result<Option<Vec<usize>>, IoStreamError> {
+        self.buffer.clear();
+        let mut ends = Vec::with_capacity(parts);
+        for part in 0..parts {
+            let start = self.buffer.len();
+            let subset = MetricSubset {
+                entry,
+                metrics: part * metrics / parts..(part + 1) * metrics / parts,
+            };
+            self.format.format(&subset, &mut self.buffer)?;
+            if self.buffer.len() - start > self.limit.max_bytes() {
+                return Ok(None);
+            }
+            ends.push(self.buffer.len());
+        }
+        Ok(Some(ends))
+    }
+}
+
+impl<F: Format> Format for LimitEntrySize<F> {
+    fn format(
+        &mut self,
+        entry: &impl Entry,
+        output: &mut impl io::Write,
+    ) -> Result<(), IoStreamError> {
+        self.buffer.clear();
+        self.format.format(entry, &mut self.buffer)?;
+        let size = self.buffer.len();
+        if size <= self.limit.max_bytes() {
+            output.write_all(&self.buffer)?;
+            return Ok(());
+        }
+
+        if self.limit.splits() {
+            let metrics = metric_count(entry);
+            let mut parts = size.div_ceil(self.limit.max_bytes().max(1)).max(2);
+            while parts <= metrics {
+                if let Some(ends) = self.format_parts(entry, metrics, parts)? {
+                    // write the parts one by one, since some outputs expect one entry per write
+                    let mut start = 0;
+                    for end in ends {
+                        output.write_all(&self.buffer[start..end])?;
+                        start = end;
+                    }
+                    self.limit.record_split();
+                    return Ok(());
+                }
+                if parts == metrics {
+                    break;
+                }
+                parts = (parts * 2).min(metrics);
+            }
+        }
+
+        Err(IoStreamError::Validation(self.limit.reject(entry, size)))
+    }
+}
+
 /// This struct combines a [Format] and an [std::io::Write]
 /// to get an [EntryIoStream].
 #[derive(Debug)]
 pub struct FormattedEntryIoStream<F, O> {
     format: F,
     output: O,
+    bytes_written: u64,
 }
 
 impl<F: Format, O: io::Write> EntryIoStream for FormattedEntryIoStream<F, O> {
     fn next(&mut self, entry: &impl Entry) -> Result<(), IoStreamError> {
-        self.format.format(entry, &mut self.output)
+        self.format.format(
+            entry,
+            &mut CountingWriter {
+                inner: &mut self.output,
+                count: &mut self.bytes_written,
+            },
+        )
     }
 
     fn flush(&mut self) -> io::Result<()> {
         self.output.flush()
     }
+
+    fn bytes_written(&self) -> Option<u64> {
+        Some(self.bytes_written)
+    }
+}
+
+// Counts the bytes that made it to the output, for `EntryIoStream::bytes_written`
+struct CountingWriter<'a, W> {
+    inner: W,
+    count: &'a mut u64,
+}
+
+impl<W: io::Write> io::Write for CountingWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        *self.count += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
 }
 
 impl<F: Format, G: Entry> Format for MergeGlobals<F, G> {
@@ -298,6 +480,17 @@ impl<F: Format, const N: usize> Format for MergeGlobalDimensions<F, N> {
     }
 }
 
+impl<F: Format> Format for GuardCardinality<F> {
+    fn format(
+        &mut self,
+        entry: &impl Entry,
+        output: &mut impl io::Write,
+    ) -> Result<(), IoStreamError> {
+        self.stream
+            .format(&WithCardinalityGuard::new(entry, &self.guard), output)
+    }
+}
+
 #[derive(Debug)]
 #[cfg(feature = "tracing-subscriber-03")]
 /// This struct combines a [Format] and an [tracing_subscriber::fmt::MakeWriter]
@@ -305,6 +498,7 @@ impl<F: Format, const N: usize> Format for MergeGlobalDimensions<F, N> {
 pub struct FormattedMakeWriterEntryIoStream<F, O> {
     format: F,
     output: O,
+    bytes_written: u64,
 }
 
 #[cfg(feature = "tracing-subscriber-03")]
@@ -312,11 +506,21 @@ impl<F: Format, O: for<'a> tracing_subscriber::fmt::MakeWriter<'a>> EntryIoStrea
     for FormattedMakeWriterEntryIoStream<F, O>
 {
     fn next(&mut self, entry: &impl Entry) -> Result<(), IoStreamError> {
-        self.format.format(entry, &mut self.output.make_writer())
+        self.format.format(
+            entry,
+            &mut CountingWriter {
+                inner: self.output.make_writer(),
+                count: &mut self.bytes_written,
+            },
+        )
     }
 
     fn flush(&mut self) -> io::Result<()> {
         // tracing-subscriber formatters do not need or support flushing
         Ok(())
     }
+
+    fn bytes_written(&self) -> Option<u64> {
+        Some(self.bytes_written)
+    }
 }
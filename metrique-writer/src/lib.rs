@@ -7,7 +7,7 @@
 
 pub use metrique_writer_core::entry::{BoxEntry, Entry, EntryConfig, EntryWriter};
 pub use metrique_writer_core::global::GlobalEntrySink;
-pub use metrique_writer_core::sink::{AnyEntrySink, BoxEntrySink, EntrySink};
+pub use metrique_writer_core::sink::{AnyEntrySink, BoxEntrySink, DynEntrySink, EntrySink};
 pub use metrique_writer_core::stream::{EntryIoStream, IoStreamError};
 pub use metrique_writer_core::unit::{Convert, Unit};
 pub use metrique_writer_core::value::{
@@ -18,11 +18,13 @@ pub use metrique_writer_macro::Entry;
 
 pub use crate::sink::AttachGlobalEntrySinkExt;
 
+pub mod compress;
 pub mod entry;
 pub mod format;
 pub(crate) mod rate_limit;
 pub mod sample;
 pub mod sink;
+pub mod socket;
 pub mod stream;
 #[cfg(feature = "test-util")]
 pub mod test_util;
@@ -32,6 +34,7 @@ pub mod value;
 pub use metrique_writer_core as core;
 
 pub use format::FormatExt;
+pub use metrique_writer_core::error_handler;
 pub use metrique_writer_core::global::AttachGlobalEntrySink;
 pub use metrique_writer_core::unit;
 pub use stream::EntryIoStreamExt;
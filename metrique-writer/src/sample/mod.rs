@@ -200,6 +200,17 @@ impl<F, R> FixedFractionSample<F, R> {
         assert!(rate.is_finite() && 0.0 < rate && rate <= 1.0);
         Self { format, rate, rng }
     }
+
+    /// The fraction of entries that is kept
+    pub fn rate(&self) -> f32 {
+        self.rate
+    }
+
+    /// Change the fraction of entries that is kept, which must be in `(0, 1]`
+    pub fn set_rate(&mut self, rate: f32) {
+        assert!(rate.is_finite() && 0.0 < rate && rate <= 1.0);
+        self.rate = rate;
+    }
 }
 
 impl<F: SampledFormat, R: RngCore> Format for FixedFractionSample<F, R> {
@@ -2,21 +2,27 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use std::{
+    panic::{self, AssertUnwindSafe},
     sync::{
-        Arc,
-        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU64, Ordering},
     },
+    task::{Context, Poll, Waker},
     thread,
-    time::{Duration, Instant},
+    time::{Duration, Instant, SystemTime},
 };
 
 use crossbeam_queue::ArrayQueue;
 use crossbeam_utils::sync::{Parker, Unparker};
 use metrique_writer_core::{
-    BoxEntrySink, EntryIoStream, IoStreamError, ValidationError, sink::FlushWait,
+    BoxEntrySink, EntryIoStream, EntryWriter, IoStreamError, ValidationError,
+    entry::Priority,
+    error_handler::{self, PipelineError},
+    sink::{AppendWait, FlushWait, TryAppendError},
+    unit::{AsBytes, AsCount},
 };
 
-use crate::{Entry, EntryIoStreamExt, EntrySink, rate_limit::rate_limited};
+use crate::{Entry, EntryIoStreamExt, EntrySink, rate_limit::rate_limited, value::VecDistribution};
 
 use super::metrics::{
     DescribedMetric, GlobalRecorderVersion, LocalRecorderVersion, MetricRecorder, MetricsRsType,
@@ -26,22 +32,73 @@ use super::metrics::{
 /// Builder for [`BackgroundQueue`]
 pub struct BackgroundQueueBuilder {
     capacity: usize,
+    high_priority_capacity: Option<usize>,
     thread_name: String,
     metric_name: Option<String>,
     metric_recorder: Option<Box<dyn MetricRecorder>>,
     flush_interval: Duration,
+    max_batch_entries: Option<usize>,
+    max_batch_bytes: Option<u64>,
     shutdown_timeout: Duration,
+    self_metrics: Option<SelfMetricsConfig>,
+    io_error_policy: IoErrorPolicy,
+    error_hook: Option<ErrorHook>,
+}
+
+type ErrorHook = Box<dyn Fn(&IoStreamError) + Send>;
+
+/// What a [`BackgroundQueue`] does when writing an entry to, or flushing, its output stream fails with an IO error.
+///
+/// Set with [`BackgroundQueueBuilder::io_error_policy`]. Validation errors are not affected, since writing the same
+/// entry again would fail the same way: the entry is always dropped and counted in `metrique_validation_errors`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum IoErrorPolicy {
+    /// Drop the entry and count it in `metrique_io_errors`. This is the default.
+    #[default]
+    Drop,
+    /// Try again up to `max_retries` times, then drop the entry like [`IoErrorPolicy::Drop`].
+    ///
+    /// The background thread sleeps for `initial_backoff` before the first retry, and doubles the wait before every
+    /// following retry, up to `max_backoff`. The queue isn't drained while waiting, so long waits can make it overflow.
+    ///
+    /// Retrying an entry after an error can duplicate the part of the entry that was written before the error.
+    Retry {
+        /// The maximum number of retries of a single write or flush
+        max_retries: u32,
+        /// The wait before the first retry
+        initial_backoff: Duration,
+        /// The longest wait between two retries
+        max_backoff: Duration,
+    },
+    /// Panic the background thread, which stops writing entries. The panic is propagated when the
+    /// [`BackgroundQueueJoinHandle`] is dropped.
+    ///
+    /// This is meant for services that would rather crash than run without metrics.
+    Panic,
+}
+
+struct SelfMetricsConfig {
+    interval: Duration,
+    // `None` writes the entries to the queue's own output stream
+    sink: Option<Box<dyn EntrySink<BackgroundQueueMetrics> + Send>>,
 }
 
 impl Default for BackgroundQueueBuilder {
     fn default() -> Self {
         Self {
             capacity: 64 * 1024,
+            high_priority_capacity: None,
             thread_name: "metric-background-queue".into(),
             metric_name: None,
             metric_recorder: None,
             flush_interval: Duration::from_secs(1),
+            max_batch_entries: None,
+            max_batch_bytes: None,
             shutdown_timeout: Duration::from_secs(30),
+            self_metrics: None,
+            io_error_policy: IoErrorPolicy::Drop,
+            error_hook: None,
         }
     }
 }
@@ -57,6 +114,7 @@ impl Default for BackgroundQueueBuilder {
 /// 4. `metrique_io_errors` - the amount of IO errors encountered emitting metrics.
 /// 5. `metrique_validation_errors` - the amount of validation errors encountered emitting metrics.
 /// 6. `metrique_queue_overflows` - the count of metrics being lost due to a full queue.
+/// 7. `metrique_entry_panics` - the count of entries that panicked while being written, and were dropped.
 pub const BACKGROUND_QUEUE_METRICS: &[DescribedMetric] = &[
     DescribedMetric {
         name: "metrique_idle_percent",
@@ -94,6 +152,12 @@ pub const BACKGROUND_QUEUE_METRICS: &[DescribedMetric] = &[
         r#type: MetricsRsType::Counter,
         description: "Number of metrics lost due to the queue being full",
     },
+    DescribedMetric {
+        name: "metrique_entry_panics",
+        unit: MetricsRsUnit::Count,
+        r#type: MetricsRsType::Counter,
+        description: "Number of entries dropped because they panicked while being written",
+    },
 ];
 
 impl BackgroundQueueBuilder {
@@ -120,7 +184,28 @@ impl BackgroundQueueBuilder {
         self
     }
 
+    /// Gives entries a lane per [`Priority`], so that the entries that matter most, like the entries of failed
+    /// requests, survive when the queue falls behind. Unset by default, so the priority of entries is ignored.
+    ///
+    /// [`Priority::High`] entries go to a separate lane of `high_capacity` entries, which the background thread drains
+    /// before the main queue. [`Priority::Low`] entries are dropped instead of appended once the main queue is three
+    /// quarters full, which leaves the rest of it to the other entries. Like entries dropped because the queue is
+    /// full, they count as `metrique_queue_overflows`.
+    ///
+    /// So that a flood of high-priority entries can't starve the others, the background thread writes at least one
+    /// entry of the main queue for every `high_capacity` high-priority entries.
+    ///
+    /// Entries have the [`Priority::Normal`] priority unless their [`Entry::priority`] says otherwise. Use a
+    /// [`Prioritize`](crate::sink::Prioritize) processor to choose it when appending.
+    pub fn priority_lanes(mut self, high_capacity: usize) -> Self {
+        assert!(high_capacity > 0);
+        self.high_priority_capacity = Some(high_capacity);
+        self
+    }
+
     /// Thread name assigned to the background thread that reads from the queue.
+    ///
+    /// Defaults to `metric-background-queue`.
     pub fn thread_name(mut self, name: impl Into<String>) -> Self {
         let name = name.into();
         assert!(!name.is_empty());
@@ -349,6 +434,37 @@ impl BackgroundQueueBuilder {
         self
     }
 
+    /// Flush the writer as soon as `max_entries` entries were written since the last flush, rather than waiting for
+    /// the [flush interval].
+    ///
+    /// Unset by default, so the writer is only flushed every flush interval.
+    ///
+    /// Smaller batches reduce the time entries spend buffered in the writer, at the cost of more frequent IO. This is
+    /// mostly useful for output streams that buffer entries in memory until they are flushed, like network streams.
+    ///
+    /// [flush interval]: BackgroundQueueBuilder::flush_interval
+    pub fn max_batch_entries(mut self, max_entries: usize) -> Self {
+        assert!(max_entries > 0, "max_batch_entries must not be zero");
+        self.max_batch_entries = Some(max_entries);
+        self
+    }
+
+    /// Flush the writer as soon as `max_bytes` bytes were written since the last flush, rather than waiting for the
+    /// [flush interval].
+    ///
+    /// Unset by default, so the writer is only flushed every flush interval.
+    ///
+    /// This bounds the amount of data buffered in the writer between flushes. It has no effect if the stream doesn't
+    /// keep track of the bytes it wrote, see [`EntryIoStream::bytes_written`]. Streams created with
+    /// [`FormatExt::output_to`](crate::FormatExt::output_to) do.
+    ///
+    /// [flush interval]: BackgroundQueueBuilder::flush_interval
+    pub fn max_batch_bytes(mut self, max_bytes: u64) -> Self {
+        assert!(max_bytes > 0, "max_batch_bytes must not be zero");
+        self.max_batch_bytes = Some(max_bytes);
+        self
+    }
+
     /// Sets how long the background thread will try to drain remaining metric entries once starting to shut down.
     ///
     /// Defaults to 30 seconds.
@@ -365,6 +481,73 @@ impl BackgroundQueueBuilder {
         self
     }
 
+    /// Sets what happens when writing an entry to, or flushing, the output stream fails with an IO error, like a
+    /// broken pipe to a log agent.
+    ///
+    /// Defaults to [`IoErrorPolicy::Drop`].
+    pub fn io_error_policy(mut self, policy: IoErrorPolicy) -> Self {
+        if let IoErrorPolicy::Retry {
+            initial_backoff,
+            max_backoff,
+            ..
+        } = &policy
+        {
+            assert!(
+                initial_backoff <= max_backoff,
+                "initial_backoff must not be greater than max_backoff"
+            );
+        }
+        self.io_error_policy = policy;
+        self
+    }
+
+    /// Calls `hook` on the background thread with every error writing an entry to, or flushing, the output stream,
+    /// including the errors of writes that are retried under [`IoErrorPolicy::Retry`].
+    ///
+    /// This is called in addition to the rate-limited [`tracing`] events and the error counts, for example to raise
+    /// an alarm when entries are being lost. The hook should return quickly, since the queue isn't drained while it
+    /// runs.
+    pub fn on_error(mut self, hook: impl Fn(&IoStreamError) + Send + 'static) -> Self {
+        self.error_hook = Some(Box::new(hook));
+        self
+    }
+
+    /// Periodically write a [`BackgroundQueueMetrics`] entry describing the queue itself (queue length high-water
+    /// mark, entries appended, dropped and written, bytes written, errors and flush latency) to the queue's own output
+    /// stream.
+    ///
+    /// The entry is written by the background thread after a flush once at least `interval` has passed since the
+    /// previous one, so the effective interval is rounded up to a multiple of the [flush interval]. A final entry is
+    /// written when the queue shuts down.
+    ///
+    /// Use [`BackgroundQueueBuilder::self_metrics_sink`] to send these entries somewhere else instead.
+    ///
+    /// [flush interval]: BackgroundQueueBuilder::flush_interval
+    pub fn self_metrics(mut self, interval: Duration) -> Self {
+        self.self_metrics = Some(SelfMetricsConfig {
+            interval,
+            sink: None,
+        });
+        self
+    }
+
+    /// Like [`BackgroundQueueBuilder::self_metrics`], but appends the [`BackgroundQueueMetrics`] entries to `sink`
+    /// rather than to the queue's own output stream.
+    ///
+    /// This is useful to keep the pipeline metrics in a separate log, or to still see them when the output stream is
+    /// the thing that is failing.
+    pub fn self_metrics_sink(
+        mut self,
+        interval: Duration,
+        sink: impl EntrySink<BackgroundQueueMetrics> + Send + 'static,
+    ) -> Self {
+        self.self_metrics = Some(SelfMetricsConfig {
+            interval,
+            sink: Some(Box::new(sink)),
+        });
+        self
+    }
+
     /// Build a [`BackgroundQueue`] for writing metric entries of type `T` to the given stream.
     ///
     /// Returns both the queue and a [`BackgroundQueueJoinHandle`] that can be used to cleanly flush all remaining
@@ -403,18 +586,41 @@ impl BackgroundQueueBuilder {
         let inner = Arc::new(Inner {
             name: self.metric_name.unwrap_or_else(|| self.thread_name.clone()),
             queue: ArrayQueue::new(self.capacity),
+            high_priority_queue: self.high_priority_capacity.map(ArrayQueue::new),
+            overflows: AtomicU64::new(0),
             unparker: unparker.clone(),
             flush_queue_sender,
+            capacity_waiters: Mutex::new(vec![]),
+            has_capacity_waiters: AtomicBool::new(false),
+            closed: AtomicBool::new(false),
             recorder: self.metric_recorder,
         });
         let shutdown_signal = Arc::new(AtomicBool::new(false));
 
         let receiver = Receiver {
-            metrics_emitted: 0,
-            metric_validation_errors: 0,
-            metric_io_errors: 0,
+            counters: QueueCounters::default(),
+            batch: BatchState {
+                max_entries: self.max_batch_entries,
+                max_bytes: self.max_batch_bytes,
+                entries: 0,
+                bytes_at_flush: stream.bytes_written(),
+            },
+            self_metrics: self.self_metrics.map(|config| SelfMetricsState {
+                config,
+                counters: QueueCounters::default(),
+                entries_popped: 0,
+                last_emit: Instant::now(),
+                queue_len_high_water_mark: 0,
+                last_queue_len: 0,
+                last_overflows: 0,
+                last_bytes_written: stream.bytes_written(),
+                flush_latency: VecDistribution::default(),
+            }),
             stream,
             inner: Arc::clone(&inner),
+            high_priority_streak: 0,
+            io_error_policy: self.io_error_policy,
+            error_hook: self.error_hook,
             flush_interval: self.flush_interval,
             shutdown_timeout: self.shutdown_timeout,
             shutdown_signal: Arc::clone(&shutdown_signal),
@@ -442,7 +648,9 @@ impl BackgroundQueueBuilder {
 /// Entries are appended to a shared queue that's drained by a background thread. See [`BackgroundQueueBuilder::build`].
 /// Cloning is cheap and still appends to the same shared queue.
 ///
-/// Emits [`tracing`] errors periodically if a [`IoStreamError`] occurs, but doesn't stop writing.
+/// Emits [`tracing`] errors periodically if a [`IoStreamError`] occurs, but doesn't stop writing. Entries that panic while
+/// being written are dropped and counted in `metrique_entry_panics`, and the background thread keeps writing the
+/// following entries. A partially written entry can still reach the output stream.
 pub struct BackgroundQueue<T>(Arc<Inner<T>>);
 
 impl<T: Entry + Send + 'static> BackgroundQueue<T> {
@@ -462,10 +670,20 @@ struct Inner<E> {
     // Note we use crossbeam's ArrayQueue rather than std::sync::mpsc because we want ring buffer behavior. That is, the
     // oldest entries should be dropped when the queue is full.
     queue: ArrayQueue<E>,
+    // the lane of `Priority::High` entries, if the queue has priority lanes
+    high_priority_queue: Option<ArrayQueue<E>>,
+    // number of entries dropped because the queue was full, only used for self-metrics
+    overflows: AtomicU64,
     // queue for flush wakers. This is not the fast-path so it does not use a ring buffer
     flush_queue_sender: std::sync::mpsc::Sender<FlushSignal>,
     // The unparker allows appending threads to cheaply wake up the background writing thread
     unparker: Unparker,
+    // wakers of `append_async` futures waiting for the queue to have capacity. This is not the fast-path, and
+    // `has_capacity_waiters` allows the background thread to skip the lock when nobody is waiting.
+    capacity_waiters: Mutex<Vec<Waker>>,
+    has_capacity_waiters: AtomicBool,
+    // set once the background thread stops draining the queue, so waiters don't wait forever
+    closed: AtomicBool,
     // metric recorder
     recorder: Option<Box<dyn MetricRecorder>>,
 }
@@ -492,6 +710,18 @@ impl<T: Entry + Send + 'static> EntrySink<T> for BackgroundQueue<T> {
         self.0.push(entry)
     }
 
+    fn try_append(&self, entry: T) -> Result<(), TryAppendError> {
+        self.0.try_push(entry)
+    }
+
+    fn append_async(&self, entry: T) -> AppendWait {
+        let inner = Arc::clone(&self.0);
+        let mut entry = Some(entry);
+        AppendWait::from_future(std::future::poll_fn(move |cx| {
+            inner.poll_push(cx, &mut entry)
+        }))
+    }
+
     fn flush_async(&self) -> FlushWait {
         self.0.flush_async()
     }
@@ -520,26 +750,145 @@ impl Drop for BackgroundQueueJoinHandle {
     }
 }
 
-impl<E> Inner<E> {
+impl<E: Entry> Inner<E> {
     fn push(&self, entry: E) {
         // force_push causes the oldest entry to be dropped if the queue is full. We want this since the more recent
         // metrics are more valuable when describing the state of the service!
-        if self.queue.force_push(entry).is_some() {
-            if let Some(recorder) = self.recorder.as_ref() {
-                recorder.increment_counter("metrique_queue_overflows", &self.name, 1);
-            }
-            rate_limited!(
-                Duration::from_secs(1),
-                tracing::error!(
-                    "background metric queue has fallen behind, metrics will be missing"
-                )
-            );
+        match self.lane(&entry) {
+            Some(queue) if queue.force_push(entry).is_none() => {}
+            _ => self.overflowed(),
         }
         // Note that we're not enormously concerned about the ordering guarantees between the queue push and the unpark
         // signal. That's because the writer thread will at most wait for flush_interval before waking itself up.
         self.unparker.unpark();
     }
 
+    fn overflowed(&self) {
+        self.overflows.fetch_add(1, Ordering::Relaxed);
+        if let Some(recorder) = self.recorder.as_ref() {
+            recorder.increment_counter("metrique_queue_overflows", &self.name, 1);
+        }
+        error_handler::report_error(&PipelineError::QueueFull { sink: &self.name });
+        rate_limited!(
+            Duration::from_secs(1),
+            tracing::error!("background metric queue has fallen behind, metrics will be missing")
+        );
+    }
+
+    /// The lane `entry` goes to, or `None` if it's a low-priority entry that is shed because the main queue is
+    /// nearly full
+    fn lane(&self, entry: &E) -> Option<&ArrayQueue<E>> {
+        let Some(high_priority_queue) = &self.high_priority_queue else {
+            return Some(&self.queue);
+        };
+        match entry.priority() {
+            Priority::High => Some(high_priority_queue),
+            Priority::Normal => Some(&self.queue),
+            Priority::Low => {
+                let shed_at = self.queue.capacity() - self.queue.capacity() / 4;
+                (self.queue.len() < shed_at).then_some(&self.queue)
+            }
+        }
+    }
+
+    /// Pop the next entry, from the high-priority lane first. `high_priority_streak` counts the high-priority
+    /// entries popped in a row, so that at least one entry of the main queue is popped every
+    /// `high_priority_queue.capacity()` entries.
+    fn pop(&self, high_priority_streak: &mut usize) -> Option<E> {
+        let Some(high_priority_queue) = &self.high_priority_queue else {
+            return self.queue.pop();
+        };
+        if *high_priority_streak < high_priority_queue.capacity()
+            && let Some(entry) = high_priority_queue.pop()
+        {
+            *high_priority_streak += 1;
+            return Some(entry);
+        }
+        *high_priority_streak = 0;
+        self.queue.pop().or_else(|| high_priority_queue.pop())
+    }
+
+    fn len(&self) -> usize {
+        self.queue.len() + self.high_priority_queue.as_ref().map_or(0, ArrayQueue::len)
+    }
+
+    /// The number of pops after which all entries currently queued have been popped, see P2 of [`WakerTracker`]
+    fn pops_to_drain(&self) -> usize {
+        let capacity = self.queue.capacity();
+        match &self.high_priority_queue {
+            // every `high_capacity` high-priority entries, one entry of the main queue is popped
+            Some(high_priority_queue) => high_priority_queue
+                .capacity()
+                .saturating_mul(capacity + 1)
+                .saturating_add(capacity),
+            None => capacity,
+        }
+    }
+
+    fn try_push(&self, entry: E) -> Result<(), TryAppendError> {
+        let result = match self.lane(&entry) {
+            Some(queue) => queue.push(entry).map_err(|_| TryAppendError::Full),
+            None => Err(TryAppendError::Full),
+        };
+        if result.is_err() {
+            self.overflowed();
+        }
+        self.unparker.unpark();
+        result
+    }
+
+    fn poll_push(&self, cx: &mut Context<'_>, entry: &mut Option<E>) -> Poll<()> {
+        let Some(value) = entry.take() else {
+            return Poll::Ready(());
+        };
+        let Some(queue) = self.lane(&value) else {
+            // shed like `append` would, waiting would only delay the other entries
+            self.overflowed();
+            return Poll::Ready(());
+        };
+        let value = match queue.push(value) {
+            Ok(()) => {
+                self.unparker.unpark();
+                return Poll::Ready(());
+            }
+            Err(value) => value,
+        };
+        self.capacity_waiters
+            .lock()
+            .unwrap()
+            .push(cx.waker().clone());
+        self.has_capacity_waiters.store(true, Ordering::SeqCst);
+        // retry after registering the waker, in case the queue was drained in the meantime
+        let result = queue.push(value);
+        self.unparker.unpark();
+        match result {
+            Ok(()) => Poll::Ready(()),
+            // checked after registering the waker, since `close` doesn't wake wakers registered after it
+            Err(_) if self.closed.load(Ordering::SeqCst) => {
+                // nobody will drain the queue anymore, drop the entry like `append` would
+                self.overflowed();
+                Poll::Ready(())
+            }
+            Err(value) => {
+                *entry = Some(value);
+                Poll::Pending
+            }
+        }
+    }
+
+    fn wake_capacity_waiters(&self) {
+        if self.has_capacity_waiters.swap(false, Ordering::SeqCst) {
+            let wakers = std::mem::take(&mut *self.capacity_waiters.lock().unwrap());
+            wakers.into_iter().for_each(Waker::wake);
+        }
+    }
+
+    fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        self.has_capacity_waiters.store(true, Ordering::SeqCst);
+        self.wake_capacity_waiters();
+    }
+
     fn flush_async(&self) -> FlushWait {
         let (channel, receiver) = tokio::sync::oneshot::channel();
         self.flush_queue_sender.send(FlushSignal { channel }).ok();
@@ -552,11 +901,16 @@ impl<E> Inner<E> {
 
 // Background thread struct that receives entries from the shared queue.
 struct Receiver<S, E> {
-    metrics_emitted: u64,
-    metric_validation_errors: u64,
-    metric_io_errors: u64,
+    // counters reported to the metric recorder, reset on every flush
+    counters: QueueCounters,
+    batch: BatchState,
+    self_metrics: Option<SelfMetricsState>,
     stream: S,
     inner: Arc<Inner<E>>,
+    // high-priority entries popped in a row, see `Inner::pop`
+    high_priority_streak: usize,
+    io_error_policy: IoErrorPolicy,
+    error_hook: Option<ErrorHook>,
     flush_interval: Duration,
     shutdown_timeout: Duration,
     shutdown_signal: Arc<AtomicBool>,
@@ -588,6 +942,8 @@ struct Receiver<S, E> {
 //  processed. We use the queue's capacity, since it is guaranteed that all entries
 // currently in the queue have been popped queue after capacity entries have been
 // popped (.len() would work here as well, but len of a queue is a non-standard function).
+// With priority lanes, high-priority entries can overtake the others, so the bound is
+// larger, see `Inner::pops_to_drain`.
 
 struct WakerTracker {
     waiting_wakers: Vec<FlushSignal>,
@@ -666,9 +1022,10 @@ impl<S: EntryIoStream, E: Entry> Receiver<S, E> {
             let mut idle_duration = Duration::ZERO;
             loop {
                 let (status, entry_count) = self.drain_until_deadline(next_flush);
+                self.inner.wake_capacity_waiters();
 
                 waker_tracker.handle_waiting_wakers(
-                    || inner.queue.capacity(),
+                    || inner.pops_to_drain(),
                     || self.flush_stream(),
                     status,
                     entry_count,
@@ -699,8 +1056,9 @@ impl<S: EntryIoStream, E: Entry> Receiver<S, E> {
             }
 
             self.flush_stream();
+            self.write_self_metrics(false);
             if let Some(recorder) = &self.inner.recorder {
-                let queue_len = self.inner.queue.len().try_into().unwrap_or(u32::MAX);
+                let queue_len = self.inner.len().try_into().unwrap_or(u32::MAX);
                 let total_duration = loop_start.elapsed();
                 let idle_percent: u32 = idle_duration
                     .as_micros()
@@ -725,12 +1083,22 @@ impl<S: EntryIoStream, E: Entry> Receiver<S, E> {
     }
 
     fn drain_until_deadline(&mut self, deadline: Instant) -> (DrainResult, usize) {
+        if let Some(state) = &mut self.self_metrics {
+            state.queue_len_high_water_mark = state.queue_len_high_water_mark.max(self.inner.len());
+        }
         // Most write() activites consume < 1us. We don't need to recheck the timeline after every write to still keep
         // a reasonably accurate flush interval. Instead, we'll check the clock every 32 entries if we're still seeing
         // entries remaining in the queue.
         let mut count = 0usize;
-        while let Some(entry) = self.inner.queue.pop() {
+        while let Some(entry) = self.inner.pop(&mut self.high_priority_streak) {
+            // cheap check so waiters don't have to wait for the whole queue to be drained
+            if self.inner.has_capacity_waiters.load(Ordering::Relaxed) {
+                self.inner.wake_capacity_waiters();
+            }
             self.consume(entry);
+            if self.batch.is_full(&self.stream) {
+                self.flush_stream();
+            }
 
             count += 1;
             if count.is_multiple_of(32) && Instant::now() >= deadline {
@@ -754,8 +1122,8 @@ impl<S: EntryIoStream, E: Entry> Receiver<S, E> {
             match self.stream.report_error(
                 "metric entry could not be formatted correctly, call tracing_subscriber::fmt::init to see more detailed information"
             ) {
-                Ok(()) => self.metrics_emitted += 1,
-                Err(IoStreamError::Io(_)) => self.metric_io_errors += 1,
+                Ok(()) => self.count(|c| c.metrics_emitted += 1),
+                Err(IoStreamError::Io(_)) => self.count(|c| c.io_errors += 1),
                 Err(IoStreamError::Validation(_)) => {}
             }
         } else {
@@ -763,17 +1131,66 @@ impl<S: EntryIoStream, E: Entry> Receiver<S, E> {
         }
     }
 
+    fn count(&mut self, f: impl Fn(&mut QueueCounters)) {
+        f(&mut self.counters);
+        if let Some(state) = &mut self.self_metrics {
+            f(&mut state.counters);
+        }
+    }
+
     fn consume(&mut self, entry: E) {
-        match self.stream.next(&entry) {
+        if let Some(state) = &mut self.self_metrics {
+            state.entries_popped += 1;
+        }
+        let mut panicked = false;
+        let result = self.with_error_policy(|stream| {
+            // A panic in an `Entry` or `Value` implementation would otherwise kill the background thread, and with
+            // it all metrics for the rest of the process. Drop the entry instead, as if it failed validation.
+            panic::catch_unwind(AssertUnwindSafe(|| stream.next(&entry))).unwrap_or_else(
+                |payload| {
+                    panicked = true;
+                    Err(IoStreamError::Validation(ValidationError::invalid(
+                        format!(
+                            "panicked while writing the entry: {}",
+                            panic_message(&*payload)
+                        ),
+                    )))
+                },
+            )
+        });
+        match result {
             Ok(()) => {
-                self.metrics_emitted += 1;
+                self.batch.entries += 1;
+                self.count(|c| c.metrics_emitted += 1);
+            }
+            Err(err) => {
+                error_handler::report_error(&PipelineError::from_stream_error(
+                    &self.inner.name,
+                    &err,
+                ));
+                self.report_consume_error(err, panicked);
+            }
+        }
+    }
+
+    fn report_consume_error(&mut self, err: IoStreamError, panicked: bool) {
+        match err {
+            IoStreamError::Validation(err) if panicked => {
+                self.count(|c| c.entry_panics += 1);
+                rate_limited!(
+                    Duration::from_secs(1),
+                    tracing::error!(
+                        ?err,
+                        "metric entry panicked while being written, dropping it"
+                    )
+                )
             }
-            Err(IoStreamError::Validation(err)) => {
-                self.metric_validation_errors += 1;
+            IoStreamError::Validation(err) => {
+                self.count(|c| c.validation_errors += 1);
                 rate_limited!(Duration::from_secs(1), self.report_validation_error(err))
             }
-            Err(IoStreamError::Io(err)) => {
-                self.metric_io_errors += 1;
+            IoStreamError::Io(err) => {
+                self.count(|c| c.io_errors += 1);
                 rate_limited!(
                     Duration::from_secs(1),
                     tracing::error!(?err, "couldn't append to metric stream")
@@ -782,9 +1199,64 @@ impl<S: EntryIoStream, E: Entry> Receiver<S, E> {
         }
     }
 
+    // Runs `op` on the stream, reporting its errors to the error hook and handling IO errors according to the
+    // `IoErrorPolicy`. Returns the error of the last attempt if it still failed.
+    fn with_error_policy(
+        &mut self,
+        mut op: impl FnMut(&mut S) -> Result<(), IoStreamError>,
+    ) -> Result<(), IoStreamError> {
+        let mut retries = 0;
+        loop {
+            let result = op(&mut self.stream);
+            let Err(err) = &result else {
+                return result;
+            };
+            if let Some(hook) = &self.error_hook {
+                hook(err);
+            }
+            let IoStreamError::Io(io_err) = err else {
+                return result;
+            };
+            match &self.io_error_policy {
+                IoErrorPolicy::Drop => return result,
+                IoErrorPolicy::Panic => {
+                    // don't leave `append_async` callers waiting for a queue that won't be drained anymore
+                    self.inner.close();
+                    panic!("couldn't write to metric stream: {io_err}");
+                }
+                IoErrorPolicy::Retry {
+                    max_retries,
+                    initial_backoff,
+                    max_backoff,
+                } => {
+                    if retries >= *max_retries {
+                        return result;
+                    }
+                    let backoff = initial_backoff
+                        .saturating_mul(2u32.saturating_pow(retries))
+                        .min(*max_backoff);
+                    rate_limited!(
+                        Duration::from_secs(1),
+                        tracing::warn!(?io_err, ?backoff, "retrying write to metric stream")
+                    );
+                    thread::sleep(backoff);
+                    retries += 1;
+                }
+            }
+        }
+    }
+
     fn flush_stream(&mut self) {
-        if let Err(err) = self.stream.flush() {
-            self.metric_io_errors += 1;
+        let flush_start = Instant::now();
+        let result = self.with_error_policy(|stream| stream.flush().map_err(IoStreamError::Io));
+        self.batch.entries = 0;
+        self.batch.bytes_at_flush = self.stream.bytes_written();
+        if let Some(state) = &mut self.self_metrics {
+            state.flush_latency.add(flush_start.elapsed());
+        }
+        if let Err(err) = result {
+            self.count(|c| c.io_errors += 1);
+            error_handler::report_error(&PipelineError::from_stream_error(&self.inner.name, &err));
             rate_limited!(
                 Duration::from_secs(1),
                 tracing::warn!(?err, "couldn't flush metric stream")
@@ -792,6 +1264,7 @@ impl<S: EntryIoStream, E: Entry> Receiver<S, E> {
         }
 
         if let Some(recorder) = &self.inner.recorder {
+            let counters = std::mem::take(&mut self.counters);
             // intentionally use the metric macros here, so if a new global recorder is
             // installed after the background queue is created, [most] metrics won't be lost
             //
@@ -800,33 +1273,211 @@ impl<S: EntryIoStream, E: Entry> Receiver<S, E> {
             recorder.increment_counter(
                 "metrique_metrics_emitted",
                 &self.inner.name,
-                std::mem::take(&mut self.metrics_emitted),
+                counters.metrics_emitted,
             );
+            recorder.increment_counter("metrique_io_errors", &self.inner.name, counters.io_errors);
             recorder.increment_counter(
-                "metrique_io_errors",
+                "metrique_validation_errors",
                 &self.inner.name,
-                std::mem::take(&mut self.metric_io_errors),
+                counters.validation_errors,
             );
             recorder.increment_counter(
-                "metrique_validation_errors",
+                "metrique_entry_panics",
                 &self.inner.name,
-                std::mem::take(&mut self.metric_validation_errors),
+                counters.entry_panics,
             );
         }
     }
 
+    // Writes a `BackgroundQueueMetrics` entry if self-metrics are enabled and the interval has passed (or on shutdown)
+    fn write_self_metrics(&mut self, force: bool) {
+        let Some(state) = &mut self.self_metrics else {
+            return;
+        };
+        let now = Instant::now();
+        if !force && now.duration_since(state.last_emit) < state.config.interval {
+            return;
+        }
+        state.last_emit = now;
+
+        let queue_len = self.inner.len();
+        let overflows = self.inner.overflows.load(Ordering::Relaxed);
+        let entries_dropped = overflows - state.last_overflows;
+        // whatever is in the queue now was either appended since the last entry or was already there
+        let entries_appended =
+            (std::mem::take(&mut state.entries_popped) + entries_dropped + queue_len as u64)
+                .saturating_sub(state.last_queue_len as u64);
+        let bytes_written = self.stream.bytes_written();
+        let counters = std::mem::take(&mut state.counters);
+        let entry = BackgroundQueueMetrics {
+            timestamp: SystemTime::now(),
+            queue: self.inner.name.clone(),
+            queue_len_high_water_mark: std::mem::replace(
+                &mut state.queue_len_high_water_mark,
+                queue_len,
+            ) as u64,
+            entries_appended,
+            entries_dropped,
+            entries_written: counters.metrics_emitted,
+            // streams may count from zero again, for example after reopening their output
+            bytes_written: bytes_written
+                .map(|bytes| bytes.saturating_sub(state.last_bytes_written.unwrap_or(0))),
+            io_errors: counters.io_errors,
+            validation_errors: counters.validation_errors,
+            entry_panics: counters.entry_panics,
+            flush_latency: std::mem::take(&mut state.flush_latency),
+        };
+        state.last_queue_len = queue_len;
+        state.last_overflows = overflows;
+        state.last_bytes_written = bytes_written;
+
+        match &state.config.sink {
+            Some(sink) => sink.append(entry),
+            None => {
+                if let Err(err) = self.stream.next(&entry) {
+                    rate_limited!(
+                        Duration::from_secs(1),
+                        tracing::warn!(?err, "couldn't write background queue self-metrics")
+                    );
+                }
+            }
+        }
+    }
+
     fn shut_down(mut self) {
         let deadline = Instant::now() + self.shutdown_timeout;
         let (status, _count) = self.drain_until_deadline(deadline);
         if status == DrainResult::HitDeadline {
             tracing::warn!("unable to drain metrics queue while shutting down");
         }
+        self.write_self_metrics(true);
         self.flush_stream();
+        self.inner.close();
         drop(self.stream); // Close the file before we report we're done!
         tracing::info!("background metric log writing has shut down");
     }
 }
 
+// Entries and bytes written since the last flush, for `max_batch_entries` and `max_batch_bytes`
+struct BatchState {
+    max_entries: Option<usize>,
+    max_bytes: Option<u64>,
+    entries: usize,
+    bytes_at_flush: Option<u64>,
+}
+
+impl BatchState {
+    fn is_full(&self, stream: &impl EntryIoStream) -> bool {
+        if self.max_entries.is_some_and(|max| self.entries >= max) {
+            return true;
+        }
+        match (self.max_bytes, stream.bytes_written()) {
+            (Some(max), Some(bytes)) => {
+                bytes.saturating_sub(self.bytes_at_flush.unwrap_or(0)) >= max
+            }
+            _ => false,
+        }
+    }
+}
+
+#[derive(Default)]
+struct QueueCounters {
+    metrics_emitted: u64,
+    io_errors: u64,
+    validation_errors: u64,
+    entry_panics: u64,
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&'static str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "Box<dyn Any>"
+    }
+}
+
+struct SelfMetricsState {
+    config: SelfMetricsConfig,
+    // counters since the last self-metrics entry
+    counters: QueueCounters,
+    entries_popped: u64,
+    queue_len_high_water_mark: usize,
+    flush_latency: VecDistribution<Duration>,
+    last_emit: Instant,
+    // values as of the last self-metrics entry
+    last_queue_len: usize,
+    last_overflows: u64,
+    last_bytes_written: Option<u64>,
+}
+
+/// Entry describing the health of a [`BackgroundQueue`] over the last interval, written when enabled by
+/// [`BackgroundQueueBuilder::self_metrics`] or [`BackgroundQueueBuilder::self_metrics_sink`].
+///
+/// The metric names match the ones in [`BACKGROUND_QUEUE_METRICS`] where they overlap. All counts are deltas since
+/// the previous entry.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct BackgroundQueueMetrics {
+    /// When the entry was created.
+    pub timestamp: SystemTime,
+    /// The queue's metric name, see [`BackgroundQueueBuilder::metric_name`]. Written as the `queue` property.
+    pub queue: String,
+    /// The longest queue length observed by the background thread (`metrique_queue_len_max`).
+    pub queue_len_high_water_mark: u64,
+    /// Entries appended to the queue (`metrique_entries_appended`).
+    pub entries_appended: u64,
+    /// Entries lost because the queue was full (`metrique_queue_overflows`).
+    pub entries_dropped: u64,
+    /// Entries successfully written to the output stream (`metrique_metrics_emitted`).
+    pub entries_written: u64,
+    /// Bytes written to the output stream (`metrique_bytes_written`), if the stream keeps track of it. See
+    /// [`EntryIoStream::bytes_written`].
+    pub bytes_written: Option<u64>,
+    /// IO errors writing or flushing the output stream (`metrique_io_errors`).
+    pub io_errors: u64,
+    /// Entries that failed to format (`metrique_validation_errors`).
+    pub validation_errors: u64,
+    /// Entries that panicked while being written, and were dropped (`metrique_entry_panics`).
+    pub entry_panics: u64,
+    /// The duration of each flush of the output stream (`metrique_flush_latency`).
+    pub flush_latency: VecDistribution<Duration>,
+}
+
+impl Entry for BackgroundQueueMetrics {
+    fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+        writer.timestamp(self.timestamp);
+        writer.value("queue", &self.queue);
+        writer.value(
+            "metrique_queue_len_max",
+            &AsCount::from(self.queue_len_high_water_mark),
+        );
+        writer.value(
+            "metrique_entries_appended",
+            &AsCount::from(self.entries_appended),
+        );
+        writer.value(
+            "metrique_queue_overflows",
+            &AsCount::from(self.entries_dropped),
+        );
+        writer.value(
+            "metrique_metrics_emitted",
+            &AsCount::from(self.entries_written),
+        );
+        if let Some(bytes_written) = self.bytes_written {
+            writer.value("metrique_bytes_written", &AsBytes::from(bytes_written));
+        }
+        writer.value("metrique_io_errors", &AsCount::from(self.io_errors));
+        writer.value(
+            "metrique_validation_errors",
+            &AsCount::from(self.validation_errors),
+        );
+        writer.value("metrique_entry_panics", &AsCount::from(self.entry_panics));
+        writer.value("metrique_flush_latency", &self.flush_latency);
+    }
+}
+
 /// Does describe_metrics for this global recorder, which makes your units visible.
 /// Call it with a recorder type, to allow it to autodetect your metrics.rs version
 ///
@@ -853,7 +1504,12 @@ enum DrainResult {
 }
 
 #[cfg(test)]
-#[allow(deprecated)]
+#[allow(
+    deprecated,
+    clippy::mutable_key_type,
+    clippy::neg_cmp_op_on_partial_ord,
+    clippy::redundant_closure_call
+)]
 mod tests {
     use std::{
         future::Future,
@@ -923,6 +1579,142 @@ mod tests {
         }
     }
 
+    #[test]
+    fn try_append_rejects_new_entries_when_full() {
+        test_all_queues! {
+            |builder| builder.capacity(10),
+            |output, queue, handle| {
+                let mut accepted = 0;
+                // hold lock so writer can't make progress
+                {
+                    let _locked = output.lock().unwrap();
+                    // the background queue can pick up one entry before getting blocked on the mutex,
+                    // give it a chance to so that it doesn't make room in the queue later on
+                    let deadline = Instant::now() + Duration::from_secs(1);
+                    loop {
+                        if queue.try_append(TestEntry(accepted)).is_ok() {
+                            accepted += 1;
+                        } else if accepted == 11 || Instant::now() >= deadline {
+                            break;
+                        } else {
+                            std::thread::yield_now();
+                        }
+                    }
+                    assert_eq!(queue.try_append(TestEntry(100)), Err(TryAppendError::Full));
+                }
+                handle.shut_down();
+
+                assert!((10..=11).contains(&accepted));
+                assert_eq!(output.lock().unwrap().values, (0..accepted).collect::<Vec<_>>());
+            }
+        }
+    }
+
+    #[test]
+    fn priority_lanes_write_high_priority_first_and_shed_low_priority() {
+        use crate::sink::WithPriority;
+
+        test_all_queues! {
+            |builder| builder.capacity(8).priority_lanes(4),
+            |output, queue, handle| {
+                // hold lock so writer can't make progress
+                {
+                    let _locked = output.lock().unwrap();
+                    for i in 0..8 {
+                        queue.append(WithPriority::new(TestEntry(i), Priority::Normal));
+                    }
+                    // the main queue is at least three quarters full
+                    queue.append(WithPriority::new(TestEntry(200), Priority::Low));
+                    queue.append(WithPriority::new(TestEntry(100), Priority::High));
+                    queue.append(WithPriority::new(TestEntry(101), Priority::High));
+                }
+                handle.shut_down();
+
+                // the background queue can pick up one entry before getting blocked on the mutex
+                let values = &output.lock().unwrap().values;
+                let high = values.iter().position(|&v| v == 101).unwrap();
+                assert!(high <= 2, "{values:?}");
+                assert!(!values.contains(&200), "{values:?}");
+                assert!((0..8).all(|i| values.contains(&i)), "{values:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn priorities_are_ignored_without_priority_lanes() {
+        use crate::sink::WithPriority;
+
+        test_all_queues! {
+            |builder| builder.capacity(8),
+            |output, queue, handle| {
+                {
+                    let _locked = output.lock().unwrap();
+                    for i in 0..8 {
+                        let priority = [Priority::Low, Priority::High][i as usize % 2];
+                        queue.append(WithPriority::new(TestEntry(i), priority));
+                    }
+                }
+                handle.shut_down();
+                assert_eq!(output.lock().unwrap().values, (0..8).collect::<Vec<_>>());
+            }
+        }
+    }
+
+    #[test]
+    fn append_async_waits_for_capacity() {
+        test_all_queues! {
+            |builder| builder.capacity(2),
+            |output, queue, handle| {
+                std::thread::scope(|scope| {
+                    // hold lock so writer can't make progress until the producer is waiting
+                    let locked = output.lock().unwrap();
+                    let producer = scope.spawn(|| {
+                        for i in 0..20 {
+                            futures::executor::block_on(queue.append_async(TestEntry(i)));
+                        }
+                    });
+                    std::thread::sleep(Duration::from_millis(10));
+                    drop(locked);
+                    producer.join().unwrap();
+                });
+                handle.shut_down();
+                assert_eq!(output.lock().unwrap().values, (0..20).collect::<Vec<_>>());
+            }
+        }
+    }
+
+    #[test]
+    fn rejected_entries_are_counted_as_overflows() {
+        let output: Arc<Mutex<TestStream>> = Default::default();
+        let (queue, handle) = BackgroundQueueBuilder::new()
+            .capacity(2)
+            .build(Arc::clone(&output));
+        let overflows = || queue.0.overflows.load(Ordering::Relaxed);
+        {
+            // hold lock so writer can't make progress
+            let _locked = output.lock().unwrap();
+            // the writer can pick up one entry before getting blocked on the lock, give it a
+            // chance to so that it doesn't make room in the queue later on
+            queue.append(TestEntry(0));
+            let deadline = Instant::now() + Duration::from_secs(1);
+            while queue.0.len() > 0 && Instant::now() < deadline {
+                std::thread::yield_now();
+            }
+            while queue.try_append(TestEntry(0)).is_ok() {}
+            assert_eq!(overflows(), 1);
+            assert_eq!(queue.try_append(TestEntry(1)), Err(TryAppendError::Full));
+            assert_eq!(overflows(), 2);
+
+            // the queue is full and won't be drained once closed, so the entry is dropped
+            // instead of waiting forever
+            queue.0.close();
+            futures::executor::block_on(queue.append_async(TestEntry(100)));
+            assert_eq!(overflows(), 3);
+        }
+        handle.shut_down();
+        assert!(!output.lock().unwrap().values.contains(&100));
+    }
+
     #[test]
     fn writes_all_entries_from_multiple_threads() {
         test_all_queues! {
@@ -946,6 +1738,264 @@ mod tests {
         }
     }
 
+    #[test]
+    fn self_metrics_sink_reports_queue_activity() {
+        let self_metrics = crate::sink::VecEntrySink::default();
+        test_all_queues! {
+            |builder| builder.capacity(10).self_metrics_sink(Duration::from_secs(3600), self_metrics.clone()),
+            |output, queue, handle| {
+                // hold lock so writer can't make progress
+                {
+                    let _locked = output.lock().unwrap();
+                    for i in 0..20 {
+                        queue.append(TestEntry(i));
+                    }
+                }
+                handle.shut_down();
+
+                // the interval is long, so only the final entry on shutdown is written
+                let entries = self_metrics.drain();
+                assert_eq!(entries.len(), 1);
+                let entry = &entries[0];
+                let written = output.lock().unwrap().values.len() as u64;
+                assert_eq!(entry.entries_appended, 20);
+                assert_eq!(entry.entries_written, written);
+                assert_eq!(entry.entries_dropped, 20 - written);
+                assert!((1..=10).contains(&entry.queue_len_high_water_mark));
+                assert_eq!(entry.io_errors, 0);
+                assert_eq!(entry.validation_errors, 0);
+                assert_eq!(entry.bytes_written, None);
+                assert!(!entry.flush_latency.values().is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn self_metrics_written_to_own_stream() {
+        use crate::format::FormatExt;
+        use metrique_writer_core::test_stream::{DummyFormat, TestSink};
+
+        let output = TestSink::default();
+        let (queue, handle) = BackgroundQueueBuilder::new()
+            .self_metrics(Duration::from_secs(3600))
+            .metric_name("my-queue")
+            .build(DummyFormat.output_to(output.clone()));
+        for i in 0..3 {
+            queue.append(TestEntry(i));
+        }
+        handle.shut_down();
+
+        let output = output.dump();
+        assert!(output.contains(r#"("queue", "my-queue")"#), "{output}");
+        assert!(
+            output.contains(r#"("metrique_entries_appended", "[Unsigned(3)] Count []")"#),
+            "{output}"
+        );
+        assert!(
+            output.contains(r#"("metrique_metrics_emitted", "[Unsigned(3)] Count []")"#),
+            "{output}"
+        );
+        assert!(
+            output.contains(r#"("metrique_bytes_written", "#),
+            "{output}"
+        );
+    }
+
+    #[test]
+    fn keeps_writing_after_an_entry_panics() {
+        struct MaybePanic(TestEntry);
+
+        impl Entry for MaybePanic {
+            fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+                if self.0.0 == 1 {
+                    panic!("can't write {}", self.0.0);
+                }
+                self.0.write(writer)
+            }
+        }
+
+        let self_metrics = crate::sink::VecEntrySink::default();
+        let output: Arc<Mutex<TestStream>> = Default::default();
+        let (queue, handle) = BackgroundQueueBuilder::new()
+            .self_metrics_sink(Duration::from_secs(3600), self_metrics.clone())
+            .build(Arc::clone(&output));
+        for i in 0..3 {
+            queue.append(MaybePanic(TestEntry(i)));
+        }
+        handle.shut_down();
+
+        assert_eq!(output.lock().unwrap().values, [0, 2]);
+        let entries = self_metrics.drain();
+        assert_eq!(entries[0].entry_panics, 1);
+        assert_eq!(entries[0].entries_written, 2);
+        assert_eq!(entries[0].validation_errors, 0);
+    }
+
+    // waits until the background thread wrote `count` values, without shutting it down
+    fn wait_for_values(output: &Mutex<TestStream>, count: usize) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while output.lock().unwrap().values.len() < count {
+            assert!(Instant::now() < deadline, "timed out waiting for values");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn flushes_after_max_batch_entries() {
+        let output: Arc<Mutex<TestStream>> = Default::default();
+        let (queue, handle) = BackgroundQueueBuilder::new()
+            .flush_interval(Duration::from_secs(59))
+            .max_batch_entries(4)
+            .build(Arc::clone(&output));
+        for i in 0..10 {
+            queue.append(TestEntry(i));
+        }
+        wait_for_values(&output, 10);
+        {
+            let output = output.lock().unwrap();
+            assert_eq!(output.flushes, 2);
+            assert_eq!(output.values_flushed, 8);
+        }
+        handle.shut_down();
+        assert_eq!(output.lock().unwrap().values_flushed, 10);
+    }
+
+    #[test]
+    fn flushes_after_max_batch_bytes() {
+        // pretends every entry is 100 bytes, and records the bytes written at every flush
+        #[derive(Clone, Default)]
+        struct SizedStream(Arc<Mutex<(u64, Vec<u64>)>>);
+
+        impl EntryIoStream for SizedStream {
+            fn next(&mut self, _entry: &impl Entry) -> Result<(), IoStreamError> {
+                self.0.lock().unwrap().0 += 100;
+                Ok(())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                let (bytes, flushed) = &mut *self.0.lock().unwrap();
+                flushed.push(*bytes);
+                Ok(())
+            }
+
+            fn bytes_written(&self) -> Option<u64> {
+                Some(self.0.lock().unwrap().0)
+            }
+        }
+
+        let output = SizedStream::default();
+        let (queue, handle) = BackgroundQueueBuilder::new()
+            .flush_interval(Duration::from_secs(59))
+            .max_batch_bytes(250)
+            .build(output.clone());
+        for i in 0..7 {
+            queue.append(TestEntry(i));
+        }
+        handle.shut_down();
+        // flushed after 3 and 6 entries, then when shutting down
+        let flushed = output.0.lock().unwrap().1.clone();
+        assert_eq!(flushed[..2], [300, 600]);
+        assert_eq!(flushed.last(), Some(&700));
+    }
+
+    // fails its first `failures` writes with an IO error
+    struct FlakyStream {
+        inner: Arc<Mutex<TestStream>>,
+        failures: usize,
+    }
+
+    impl EntryIoStream for FlakyStream {
+        fn next(&mut self, entry: &impl Entry) -> Result<(), IoStreamError> {
+            if self.failures > 0 {
+                self.failures -= 1;
+                return Err(IoStreamError::Io(std::io::ErrorKind::BrokenPipe.into()));
+            }
+            self.inner.next(entry)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            self.inner.flush()
+        }
+    }
+
+    fn flaky_queue(
+        builder: BackgroundQueueBuilder,
+        failures: usize,
+    ) -> (
+        BackgroundQueue<TestEntry>,
+        BackgroundQueueJoinHandle,
+        Arc<Mutex<TestStream>>,
+        Arc<AtomicU64>,
+    ) {
+        let output: Arc<Mutex<TestStream>> = Default::default();
+        let errors = Arc::new(AtomicU64::new(0));
+        let hook_errors = Arc::clone(&errors);
+        let (queue, handle) = builder
+            .on_error(move |err| {
+                assert!(matches!(err, IoStreamError::Io(_)));
+                hook_errors.fetch_add(1, Ordering::Relaxed);
+            })
+            .build(FlakyStream {
+                inner: Arc::clone(&output),
+                failures,
+            });
+        (queue, handle, output, errors)
+    }
+
+    #[test]
+    fn drops_entries_on_io_errors_by_default() {
+        let (queue, handle, output, errors) = flaky_queue(BackgroundQueueBuilder::new(), 1);
+        for i in 0..3 {
+            queue.append(TestEntry(i));
+        }
+        handle.shut_down();
+        assert_eq!(output.lock().unwrap().values, vec![1, 2]);
+        assert_eq!(errors.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn retries_io_errors() {
+        let policy = IoErrorPolicy::Retry {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(2),
+        };
+        let (queue, handle, output, errors) = flaky_queue(
+            BackgroundQueueBuilder::new().io_error_policy(policy.clone()),
+            2,
+        );
+        for i in 0..3 {
+            queue.append(TestEntry(i));
+        }
+        handle.shut_down();
+        assert_eq!(output.lock().unwrap().values, vec![0, 1, 2]);
+        assert_eq!(errors.load(Ordering::Relaxed), 2);
+
+        // gives up after `max_retries`
+        let (queue, handle, output, errors) =
+            flaky_queue(BackgroundQueueBuilder::new().io_error_policy(policy), 4);
+        for i in 0..3 {
+            queue.append(TestEntry(i));
+        }
+        handle.shut_down();
+        assert_eq!(output.lock().unwrap().values, vec![1, 2]);
+        assert_eq!(errors.load(Ordering::Relaxed), 4);
+    }
+
+    #[test]
+    fn panics_on_io_errors() {
+        let (queue, handle, _output, errors) = flaky_queue(
+            BackgroundQueueBuilder::new().io_error_policy(IoErrorPolicy::Panic),
+            1,
+        );
+        queue.append(TestEntry(0));
+        let shut_down = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            handle.shut_down();
+        }));
+        assert!(shut_down.is_err());
+        assert_eq!(errors.load(Ordering::Relaxed), 1);
+    }
+
     #[test]
     fn allows_stream_errors() {
         test_all_queues! {
@@ -1102,7 +2152,7 @@ mod tests {
                 #[cfg(feature = "metrics-rs-024")]
                 {
                     recorder = Some(Arc::new(metrics_util_020::debugging::DebuggingRecorder::new()));
-                    metrics_024::with_local_recorder(recorder.as_ref().unwrap(), || describe_sink_metrics::<dyn metrics_024::Recorder>());
+                    metrics_024::with_local_recorder(recorder.as_ref().unwrap(), describe_sink_metrics::<dyn metrics_024::Recorder>);
                     builder = builder.metrics_recorder_local::<dyn metrics_024::Recorder, _>(recorder.clone().unwrap()).metric_name("my_queue");
                 }
                 builder
@@ -1129,11 +2179,10 @@ mod tests {
                         panic!("never flushed");
                     }
 
-                    if waker.is_awake() {
-                        if let Poll::Ready(()) = Pin::new(&mut flush).poll(&mut cx) {
+                    if waker.is_awake()
+                        && let Poll::Ready(()) = Pin::new(&mut flush).poll(&mut cx) {
                             break
                         }
-                    }
                 }
                 assert!(output.lock().unwrap().flushes > 0);
                 assert!(output.lock().unwrap().values_flushed > 0);
@@ -1166,7 +2215,7 @@ mod tests {
                     assert!(idle_percent_desc.is_some());
                     match idle_percent_hist {
                         DebugValue::Histogram(hist) => {
-                            assert!(hist.len() > 0);
+                            assert!(!hist.is_empty());
                             assert!(hist.iter().all(|v| v.0 >= 0.0 && v.0 <= 100.0));
                         }
                         bad => panic!("bad value {:?}", bad),
@@ -1177,7 +2226,7 @@ mod tests {
                     assert!(queue_length_desc.is_some());
                     match queue_length_hist {
                         DebugValue::Histogram(hist) => {
-                            assert!(hist.len() > 0);
+                            assert!(!hist.is_empty());
                             assert!(hist.iter().all(|v| v.0 >= 0.0));
                         }
                         bad => panic!("bad value {:?}", bad),
@@ -1277,11 +2326,10 @@ mod tests {
                         panic!("never flushed");
                     }
 
-                    if waker.is_awake() {
-                        if let Poll::Ready(()) = Pin::new(&mut flush).poll(&mut cx) {
+                    if waker.is_awake()
+                        && let Poll::Ready(()) = Pin::new(&mut flush).poll(&mut cx) {
                             break
                         }
-                    }
                 }
                 assert!(output.lock().unwrap().flushes > 0);
                 assert!(output.lock().unwrap().values_flushed > 0);
@@ -1302,11 +2350,10 @@ mod tests {
                         panic!("never flushed");
                     }
 
-                    if waker.is_awake() {
-                        if let Poll::Ready(()) = Pin::new(&mut other_flush).poll(&mut cx) {
+                    if waker.is_awake()
+                        && let Poll::Ready(()) = Pin::new(&mut other_flush).poll(&mut cx) {
                             break
                         }
-                    }
                 }
 
                 if !check_metrics() {
@@ -1348,11 +2395,10 @@ mod tests {
                         panic!("never flushed");
                     }
 
-                    if waker.is_awake() {
-                        if let Poll::Ready(()) = Pin::new(&mut flush).poll(&mut cx) {
+                    if waker.is_awake()
+                        && let Poll::Ready(()) = Pin::new(&mut flush).poll(&mut cx) {
                             break
                         }
-                    }
                 }
                 assert!(output.lock().unwrap().flushes > 0);
                 assert_eq!(output.lock().unwrap().values, vec![1u64]);
@@ -0,0 +1,203 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    AnyEntrySink, Entry,
+    error_handler::{self, PipelineError},
+    format::Format,
+    stream::IoStreamError,
+};
+
+use super::FlushWait;
+
+/// A sink that formats entries into an in-memory buffer, which the application takes and sends
+/// itself.
+///
+/// This is meant for environments without background threads or blocking IO, like edge functions
+/// compiled to `wasm32-unknown-unknown`, where entries can only leave the process through the
+/// platform's own APIs (for example, a `fetch` request at the end of the request handler).
+/// Use the `wasm` feature of `metrique` so that timers and timestamps work on these targets, and
+/// give entries a timestamp, since formats fall back to `SystemTime::now()` which panics there.
+///
+/// Cloning will provide another reference to the same underlying buffer.
+///
+/// # Example
+/// ```
+/// # use std::time::SystemTime;
+/// use metrique_writer::{Entry, EntrySink, sink::BufferSink};
+/// use metrique_writer_format_emf::Emf;
+///
+/// #[derive(Entry)]
+/// struct RequestMetrics {
+///     #[entry(timestamp)]
+///     start: SystemTime,
+///     items: u64,
+/// }
+///
+/// let sink = BufferSink::new(Emf::no_validations("MyApp".into(), vec![vec![]]));
+/// sink.append(RequestMetrics { start: SystemTime::UNIX_EPOCH, items: 3 });
+///
+/// // at the end of the request, send the formatted entries, e.g. as the body of a `fetch`
+/// let body: Vec<u8> = sink.take();
+/// assert!(String::from_utf8(body).unwrap().contains(r#""items":3"#));
+/// assert!(sink.is_empty());
+/// ```
+pub struct BufferSink<F> {
+    state: Arc<Mutex<BufferState<F>>>,
+}
+
+struct BufferState<F> {
+    format: F,
+    buffer: Vec<u8>,
+}
+
+impl<F> Clone for BufferSink<F> {
+    fn clone(&self) -> Self {
+        Self {
+            state: Arc::clone(&self.state),
+        }
+    }
+}
+
+impl<F> std::fmt::Debug for BufferSink<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BufferSink")
+            .field("len", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F: Format> BufferSink<F> {
+    /// Create a new, empty [`BufferSink`] that formats entries with `format`
+    pub fn new(format: F) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(BufferState {
+                format,
+                buffer: Vec::new(),
+            })),
+        }
+    }
+}
+
+impl<F> BufferSink<F> {
+    /// Takes the formatted entries out of the buffer, leaving it empty.
+    ///
+    /// The sink can still be used afterwards.
+    pub fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut self.state.lock().unwrap().buffer)
+    }
+
+    /// Returns the number of buffered bytes
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().buffer.len()
+    }
+
+    /// Returns true if no entries are buffered
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<F: Format> AnyEntrySink for BufferSink<F> {
+    fn append_any(&self, entry: impl Entry + Send + 'static) {
+        let mut state = self.state.lock().unwrap();
+        let BufferState { format, buffer } = &mut *state;
+        let len = buffer.len();
+        match format.format(&entry, buffer) {
+            Ok(()) => {}
+            Err(err) => {
+                // don't leave a partially formatted entry in the buffer
+                buffer.truncate(len);
+                error_handler::report_error(&PipelineError::from_stream_error("buffer", &err));
+                match err {
+                    IoStreamError::Validation(err) => {
+                        tracing::error!(?err, "metric entry couldn't be formatted correctly");
+                    }
+                    IoStreamError::Io(err) => {
+                        tracing::error!(?err, "couldn't append to metric buffer");
+                    }
+                }
+            }
+        }
+    }
+
+    fn flush_async(&self) -> FlushWait {
+        // entries are buffered until they are taken
+        FlushWait::ready()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use metrique_writer_format_emf::Emf;
+
+    use super::*;
+    use crate::{EntrySink, ValidationError, ValueWriter};
+
+    #[derive(Entry)]
+    struct TestEntry {
+        #[entry(timestamp)]
+        timestamp: SystemTime,
+        count: u64,
+    }
+
+    fn entry(count: u64) -> TestEntry {
+        TestEntry {
+            timestamp: SystemTime::UNIX_EPOCH,
+            count,
+        }
+    }
+
+    #[test]
+    fn buffers_entries_until_taken() {
+        let sink = BufferSink::new(Emf::no_validations("Ns".into(), vec![vec![]]));
+        let handle = sink.clone();
+        sink.append(entry(1));
+        sink.append(entry(2));
+
+        let output = String::from_utf8(handle.take()).unwrap();
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(lines.len(), 2, "{output}");
+        assert!(lines[0].contains(r#""count":1"#), "{output}");
+        assert!(lines[1].contains(r#""count":2"#), "{output}");
+        assert!(sink.is_empty());
+
+        sink.append(entry(3));
+        assert!(
+            String::from_utf8(sink.take())
+                .unwrap()
+                .contains(r#""count":3"#)
+        );
+    }
+
+    #[test]
+    fn drops_invalid_entries() {
+        struct Invalid;
+        impl crate::Value for Invalid {
+            fn write(&self, writer: impl ValueWriter) {
+                writer.error(ValidationError::invalid("always invalid"));
+            }
+        }
+        #[derive(Entry)]
+        struct InvalidEntry {
+            #[entry(timestamp)]
+            timestamp: SystemTime,
+            count: u64,
+            invalid: Invalid,
+        }
+
+        let sink = BufferSink::new(Emf::no_validations("Ns".into(), vec![vec![]]));
+        sink.append(entry(1));
+        let len = sink.len();
+        sink.append(InvalidEntry {
+            timestamp: SystemTime::UNIX_EPOCH,
+            count: 2,
+            invalid: Invalid,
+        });
+        assert_eq!(sink.len(), len);
+    }
+}
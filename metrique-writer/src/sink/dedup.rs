@@ -0,0 +1,280 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    borrow::Cow,
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant, SystemTime},
+};
+
+use metrique_writer_core::{
+    EntryConfig, EntrySink, EntryWriter, MetricFlags, Observation, Unit, ValidationError, Value,
+    ValueWriter,
+};
+
+use crate::{CowStr, Entry};
+
+use super::{AppendWait, FlushWait, TryAppendError};
+
+/// An [`EntrySink`] that drops entries whose idempotency key was already seen within a time window.
+///
+/// This is meant for at-least-once processing pipelines, where the same work item can be processed
+/// (and reported) more than once. The idempotency key is the value of the string property named
+/// `key_field`, for example a message ID. Entries that don't have that property are always forwarded.
+///
+/// A key is remembered for `window` after the *first* entry with that key, so memory usage is
+/// proportional to the number of distinct keys seen within `window`.
+///
+/// Cloning a [`DeduplicateSink`] shares the set of seen keys between the clones.
+///
+/// # Example
+/// ```
+/// # use std::time::Duration;
+/// # use metrique_writer::{Entry, EntrySink, sink::{DeduplicateSink, VecEntrySink}};
+/// #[derive(Entry)]
+/// struct WorkItem {
+///     message_id: String,
+///     processed: u64,
+/// }
+///
+/// let inner = VecEntrySink::new();
+/// let sink = DeduplicateSink::new(inner.clone(), "message_id", Duration::from_secs(300));
+/// for message_id in ["a", "b", "a"] {
+///     sink.append(WorkItem { message_id: message_id.into(), processed: 1 });
+/// }
+/// assert_eq!(inner.drain().len(), 2);
+/// assert_eq!(sink.duplicates_dropped(), 1);
+/// ```
+pub struct DeduplicateSink<S> {
+    sink: S,
+    key_field: CowStr,
+    window: Duration,
+    state: Arc<Mutex<DeduplicateState>>,
+}
+
+#[derive(Default)]
+struct DeduplicateState {
+    // key => time the key was first seen
+    seen: HashMap<Box<str>, Instant>,
+    // keys in the order they were first seen, for expiry
+    order: VecDeque<(Instant, Box<str>)>,
+    duplicates_dropped: u64,
+}
+
+impl DeduplicateState {
+    /// Returns true if `key` was not seen within `window`, and records it
+    fn insert(&mut self, key: &str, now: Instant, window: Duration) -> bool {
+        while let Some((first_seen, _)) = self.order.front() {
+            if now.saturating_duration_since(*first_seen) < window {
+                break;
+            }
+            let (first_seen, expired) = self.order.pop_front().unwrap();
+            if self.seen.get(&expired) == Some(&first_seen) {
+                self.seen.remove(&expired);
+            }
+        }
+        if self.seen.contains_key(key) {
+            self.duplicates_dropped += 1;
+            return false;
+        }
+        if !window.is_zero() {
+            self.seen.insert(key.into(), now);
+            self.order.push_back((now, key.into()));
+        }
+        true
+    }
+}
+
+impl<S> DeduplicateSink<S> {
+    /// Wrap `sink`, dropping entries whose `key_field` property was already seen within `window`
+    pub fn new(sink: S, key_field: impl Into<CowStr>, window: Duration) -> Self {
+        Self {
+            sink,
+            key_field: key_field.into(),
+            window,
+            state: Default::default(),
+        }
+    }
+
+    /// The number of entries dropped as duplicates so far
+    pub fn duplicates_dropped(&self) -> u64 {
+        self.lock_state().duplicates_dropped
+    }
+
+    /// Return the wrapped sink
+    pub fn into_inner(self) -> S {
+        self.sink
+    }
+
+    fn lock_state(&self) -> MutexGuard<'_, DeduplicateState> {
+        // the state is always consistent, so it is fine to ignore poisoning
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Returns true if `entry` should be forwarded to the wrapped sink
+    fn admit(&self, entry: &impl Entry) -> bool {
+        let mut key_writer = KeyWriter {
+            key_field: &self.key_field,
+            key: None,
+        };
+        entry.write(&mut key_writer);
+        match key_writer.key {
+            Some(key) => self.lock_state().insert(&key, Instant::now(), self.window),
+            None => true,
+        }
+    }
+}
+
+impl<S: Clone> Clone for DeduplicateSink<S> {
+    fn clone(&self) -> Self {
+        Self {
+            sink: self.sink.clone(),
+            key_field: self.key_field.clone(),
+            window: self.window,
+            state: Arc::clone(&self.state),
+        }
+    }
+}
+
+impl<S: std::fmt::Debug> std::fmt::Debug for DeduplicateSink<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DeduplicateSink")
+            .field("sink", &self.sink)
+            .field("key_field", &self.key_field)
+            .field("window", &self.window)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<E: Entry, S: EntrySink<E>> EntrySink<E> for DeduplicateSink<S> {
+    fn append(&self, entry: E) {
+        if self.admit(&entry) {
+            self.sink.append(entry);
+        }
+    }
+
+    fn try_append(&self, entry: E) -> Result<(), TryAppendError> {
+        if self.admit(&entry) {
+            self.sink.try_append(entry)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn append_async(&self, entry: E) -> AppendWait {
+        if self.admit(&entry) {
+            self.sink.append_async(entry)
+        } else {
+            AppendWait::ready()
+        }
+    }
+
+    fn flush_async(&self) -> FlushWait {
+        self.sink.flush_async()
+    }
+}
+
+/// Extracts the string value of `key_field` from an entry
+struct KeyWriter<'k> {
+    key_field: &'k str,
+    key: Option<String>,
+}
+
+impl<'a> EntryWriter<'a> for KeyWriter<'_> {
+    fn timestamp(&mut self, _timestamp: SystemTime) {}
+
+    fn value(&mut self, name: impl Into<Cow<'a, str>>, value: &(impl Value + ?Sized)) {
+        if name.into() == self.key_field {
+            value.write(KeyValueWriter(&mut self.key));
+        }
+    }
+
+    fn config(&mut self, _config: &'a dyn EntryConfig) {}
+}
+
+struct KeyValueWriter<'w>(&'w mut Option<String>);
+
+impl ValueWriter for KeyValueWriter<'_> {
+    fn string(self, value: &str) {
+        *self.0 = Some(value.to_owned());
+    }
+
+    fn metric<'a>(
+        self,
+        _distribution: impl IntoIterator<Item = Observation>,
+        _unit: Unit,
+        _dimensions: impl IntoIterator<Item = (&'a str, &'a str)>,
+        _flags: MetricFlags<'_>,
+    ) {
+        // only string properties can be idempotency keys
+    }
+
+    fn error(self, _error: ValidationError) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sink::VecEntrySink;
+
+    struct WorkItem {
+        message_id: Option<&'static str>,
+        attempt: u64,
+    }
+
+    impl Entry for WorkItem {
+        fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+            writer.value("attempt", &self.attempt);
+            if let Some(message_id) = self.message_id {
+                writer.value("MessageId", message_id);
+            }
+        }
+    }
+
+    fn attempts(sink: &VecEntrySink<WorkItem>) -> Vec<u64> {
+        sink.drain().into_iter().map(|item| item.attempt).collect()
+    }
+
+    #[test]
+    fn drops_duplicates_within_window() {
+        let inner = VecEntrySink::new();
+        let sink = DeduplicateSink::new(inner.clone(), "MessageId", Duration::from_secs(3600));
+        let clone = sink.clone();
+        for (attempt, message_id) in [Some("a"), Some("b"), Some("a"), None, None]
+            .into_iter()
+            .enumerate()
+        {
+            clone.append(WorkItem {
+                message_id,
+                attempt: attempt as u64,
+            });
+        }
+        assert_eq!(attempts(&inner), [0, 1, 3, 4]);
+        assert_eq!(sink.duplicates_dropped(), 1);
+    }
+
+    #[test]
+    fn forgets_keys_after_window() {
+        let inner = VecEntrySink::new();
+        let sink = DeduplicateSink::new(inner.clone(), "MessageId", Duration::ZERO);
+        for attempt in 0..2 {
+            sink.append(WorkItem {
+                message_id: Some("a"),
+                attempt,
+            });
+        }
+        assert_eq!(attempts(&inner), [0, 1]);
+
+        let mut state = DeduplicateState::default();
+        let start = Instant::now();
+        let window = Duration::from_secs(10);
+        assert!(state.insert("a", start, window));
+        assert!(!state.insert("a", start + Duration::from_secs(9), window));
+        assert!(state.insert("a", start + Duration::from_secs(10), window));
+        assert_eq!(state.seen.len(), 1);
+        assert_eq!(state.order.len(), 1);
+    }
+}
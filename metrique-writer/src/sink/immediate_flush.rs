@@ -7,6 +7,7 @@ use metrique_writer_core::entry::BoxEntry;
 
 use crate::{
     AnyEntrySink, BoxEntrySink, Entry, EntrySink,
+    error_handler::{self, PipelineError},
     stream::{EntryIoStream, IoStreamError},
 };
 
@@ -189,13 +190,15 @@ struct SinkState<S> {
 
 impl<S: EntryIoStream> SinkState<S> {
     fn append<E: Entry>(&mut self, entry: &E) {
-        match self.stream.next(entry) {
-            Ok(()) => {}
-            Err(IoStreamError::Validation(err)) => {
-                tracing::error!(?err, "metric entry couldn't be formatted correctly");
-            }
-            Err(IoStreamError::Io(err)) => {
-                tracing::error!(?err, "couldn't append to metric stream");
+        if let Err(err) = self.stream.next(entry) {
+            error_handler::report_error(&PipelineError::from_stream_error(&self.name, &err));
+            match err {
+                IoStreamError::Validation(err) => {
+                    tracing::error!(?err, "metric entry couldn't be formatted correctly");
+                }
+                IoStreamError::Io(err) => {
+                    tracing::error!(?err, "couldn't append to metric stream");
+                }
             }
         }
 
@@ -204,14 +207,20 @@ impl<S: EntryIoStream> SinkState<S> {
     }
 
     fn flush(&mut self) {
-        let start = Instant::now();
+        // only read the clock if the flush time is recorded, since `Instant::now()` panics on
+        // targets without a clock like `wasm32-unknown-unknown`
+        let start = self.recorder.as_ref().map(|_| Instant::now());
 
         if let Err(err) = self.stream.flush() {
+            error_handler::report_error(&PipelineError::Io {
+                sink: &self.name,
+                error: &err,
+            });
             tracing::warn!(?err, "couldn't flush metric stream");
         }
 
         // Record flush time metric if recorder is configured
-        if let Some(recorder) = &self.recorder {
+        if let (Some(recorder), Some(start)) = (&self.recorder, start) {
             let flush_time_ms = start.elapsed().as_millis() as u32;
             recorder.record_histogram("metrique_flush_time_ms", &self.name, flush_time_ms);
         }
@@ -404,10 +413,7 @@ mod tests {
     #[test]
     fn handles_io_errors() {
         let output: Arc<Mutex<TestStream>> = Default::default();
-        output.lock().unwrap().error = Some(IoStreamError::Io(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            "test error",
-        )));
+        output.lock().unwrap().error = Some(IoStreamError::Io(std::io::Error::other("test error")));
         let sink = FlushImmediately::<TestEntry, _>::new(Arc::clone(&output));
 
         // Should not panic
@@ -9,22 +9,48 @@ use crate::Entry;
 
 #[cfg(feature = "background-queue")]
 mod background;
+mod buffer;
+mod dedup;
 mod immediate_flush;
 mod metrics;
+mod process;
+mod route;
+mod sanitize;
+#[cfg(feature = "serde-json")]
+mod spill;
 
 #[cfg(feature = "background-queue")]
 pub use background::{BACKGROUND_QUEUE_METRICS, describe_sink_metrics};
 #[cfg(feature = "background-queue")]
-pub use background::{BackgroundQueue, BackgroundQueueBuilder, BackgroundQueueJoinHandle};
+pub use background::{
+    BackgroundQueue, BackgroundQueueBuilder, BackgroundQueueJoinHandle, BackgroundQueueMetrics,
+    IoErrorPolicy,
+};
+pub use buffer::BufferSink;
+pub use dedup::DeduplicateSink;
 pub use immediate_flush::{
     AnyFlushImmediately, FlushImmediately, FlushImmediatelyBuilder,
     describe_immediate_flush_metrics,
 };
-pub use metrique_writer_core::sink::{AnyEntrySink, AppendOnDrop, FlushWait};
+pub use metrique_writer_core::sink::{
+    AnyEntrySink, AppendOnDrop, AppendWait, DynEntrySink, FlushWait, TryAppendError,
+};
 use metrique_writer_core::{BoxEntrySink, EntryIoStream, EntrySink};
 pub use metrique_writer_core::{
     global::AttachGlobalEntrySink, global::AttachHandle, global_entry_sink,
 };
+pub use process::{
+    Chain, DEFAULT_REDACTED_VALUE, DefaultTimestamp, EntryProcessor, Prioritize, ProcessSink,
+    Redact, Redacted, SampleEntries, SampleGroupRateLimit, SequenceNumbers, StaticFields,
+    TruncateStrings, WithDefaultTimestamp, WithPriority, WithSequenceNumber, WithStaticFields,
+    WithTruncatedStrings,
+};
+#[cfg(feature = "tracing")]
+pub use process::{LOG_SUMMARY_TARGET, LogSummary};
+pub use route::DestinationRouter;
+pub use sanitize::{Sanitize, SanitizeRules, Sanitized};
+#[cfg(feature = "serde-json")]
+pub use spill::{DEFAULT_MAX_SPILL_FILE_BYTES, SpillSink, Spilled, recover_spilled_entries};
 
 /// Extension trait for `AttachGlobalEntrySink`, containing functions that use
 /// types that are not present in [`metrique_writer_core`].
@@ -144,6 +170,67 @@ impl AnyEntrySink for DevNullSink {
     }
 }
 
+/// An [EntrySink] that calls a closure for every appended entry.
+///
+/// This is useful for small tools and tests that want to process entries inline, for example
+/// to pipe them into custom storage, without implementing [EntrySink] themselves. The closure
+/// is called synchronously from [`EntrySink::append`], so it should not block for long.
+///
+/// [`FnEntrySink`] is [`Clone`] if the closure is, so capture shared state behind an [`Arc`].
+///
+/// # Example
+/// ```
+/// # use std::sync::{Arc, Mutex};
+/// # use metrique_writer::{Entry, EntrySink, sink::FnEntrySink};
+/// #[derive(Entry)]
+/// struct MyEntry { counter: u64 }
+///
+/// let total = Arc::new(Mutex::new(0));
+/// let sink = FnEntrySink::new({
+///     let total = Arc::clone(&total);
+///     move |entry: MyEntry| *total.lock().unwrap() += entry.counter
+/// });
+/// sink.append(MyEntry { counter: 21 });
+/// sink.append(MyEntry { counter: 21 });
+/// assert_eq!(*total.lock().unwrap(), 42);
+/// ```
+///
+/// To use it as a [`BoxEntrySink`], accept a [`BoxEntry`](crate::BoxEntry) in the closure:
+/// ```
+/// # use metrique_writer::{BoxEntry, BoxEntrySink, sink::FnEntrySink};
+/// let sink = BoxEntrySink::new(FnEntrySink::new(|_entry: BoxEntry| {}));
+/// ```
+#[derive(Clone, Copy)]
+pub struct FnEntrySink<F>(F);
+
+impl<F> FnEntrySink<F> {
+    /// Create a new [`FnEntrySink`] that calls `f` for every appended entry
+    pub const fn new(f: F) -> Self {
+        Self(f)
+    }
+
+    /// Return the wrapped closure
+    pub fn into_inner(self) -> F {
+        self.0
+    }
+}
+
+impl<F> std::fmt::Debug for FnEntrySink<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("FnEntrySink").finish_non_exhaustive()
+    }
+}
+
+impl<E: Entry, F: Fn(E)> EntrySink<E> for FnEntrySink<F> {
+    fn append(&self, entry: E) {
+        (self.0)(entry)
+    }
+
+    fn flush_async(&self) -> FlushWait {
+        FlushWait::ready()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::time::SystemTime;
@@ -202,6 +289,25 @@ mod tests {
         assert!(!sink.contains_entry(|_| true));
     }
 
+    #[test]
+    fn test_fn_entry_sink() {
+        let seen = Arc::new(Mutex::new(vec![]));
+        let sink = FnEntrySink::new({
+            let seen = Arc::clone(&seen);
+            move |entry: TestEntry| seen.lock().unwrap().push(entry.counter)
+        });
+        let cloned = sink.clone();
+        for counter in [1, 2] {
+            cloned.append(TestEntry {
+                timestamp: SystemTime::now(),
+                counter,
+                status: "OK".into(),
+            });
+        }
+        futures::executor::block_on(EntrySink::<TestEntry>::flush_async(&sink));
+        assert_eq!(*seen.lock().unwrap(), [1, 2]);
+    }
+
     #[test]
     fn test_null_entry_sink() {
         let sink = DevNullSink::new();
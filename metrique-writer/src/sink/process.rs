@@ -0,0 +1,1753 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    fmt,
+    sync::{
+        Arc, Mutex, MutexGuard,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant, SystemTime},
+};
+
+#[cfg(feature = "tracing")]
+use metrique_writer_core::unit::NegativeScale;
+use metrique_writer_core::{
+    EntryConfig, EntrySink, EntryWriter, MetricFlags, Observation, Unit, ValidationError, Value,
+    ValueWriter,
+    entry::{Priority, SampleGroupElement},
+    value::{DEFAULT_TRUNCATION_MARKER, Truncated},
+};
+use rand::Rng;
+use smallvec::SmallVec;
+
+use crate::{CowStr, Entry};
+
+use super::{AppendWait, FlushWait, TryAppendError};
+
+/// Inspects, changes or drops entries before they reach a sink. See [`ProcessSink`].
+///
+/// A processor takes ownership of every entry appended to the sink and returns the entry to
+/// append instead, which is normally a wrapper around the original entry that changes what it
+/// writes, or [`None`] to drop it.
+///
+/// Processors are implemented for every entry type they support, normally all of them:
+/// ```
+/// # use metrique_writer::{Entry, EntryWriter, sink::EntryProcessor};
+/// /// Drops entries that don't write a `Operation` property
+/// struct RequireOperation;
+///
+/// impl<E: Entry> EntryProcessor<E> for RequireOperation {
+///     type Output = E;
+///
+///     fn process(&self, entry: E) -> Option<E> {
+///         struct HasOperation(bool);
+///         impl<'a> EntryWriter<'a> for HasOperation {
+///             fn timestamp(&mut self, _timestamp: std::time::SystemTime) {}
+///             fn value(
+///                 &mut self,
+///                 name: impl Into<std::borrow::Cow<'a, str>>,
+///                 _value: &(impl metrique_writer::Value + ?Sized),
+///             ) {
+///                 self.0 |= name.into() == "Operation";
+///             }
+///             fn config(&mut self, _config: &'a dyn metrique_writer::EntryConfig) {}
+///         }
+///
+///         let mut has_operation = HasOperation(false);
+///         entry.write(&mut has_operation);
+///         has_operation.0.then_some(entry)
+///     }
+/// }
+/// ```
+pub trait EntryProcessor<E: Entry> {
+    /// The entry that is appended to the sink instead of `E`
+    type Output: Entry;
+
+    /// Process `entry`, returning the entry to append, or [`None`] to drop it
+    fn process(&self, entry: E) -> Option<Self::Output>;
+}
+
+/// The processor that forwards entries unchanged
+impl<E: Entry> EntryProcessor<E> for () {
+    type Output = E;
+
+    fn process(&self, entry: E) -> Option<E> {
+        Some(entry)
+    }
+}
+
+/// Two processors run one after the other, created by [`ProcessSink::processor`].
+#[derive(Debug, Clone)]
+pub struct Chain<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<E: Entry, A: EntryProcessor<E>, B: EntryProcessor<A::Output>> EntryProcessor<E>
+    for Chain<A, B>
+{
+    type Output = B::Output;
+
+    fn process(&self, entry: E) -> Option<Self::Output> {
+        self.second.process(self.first.process(entry)?)
+    }
+}
+
+/// An [`EntrySink`] that runs every entry through a chain of [`EntryProcessor`]s before appending
+/// it to the wrapped sink.
+///
+/// Processors run in the order they were added, each one seeing the output of the previous one.
+/// Once a processor drops an entry, the later processors don't see it.
+///
+/// # Example
+/// ```
+/// # use metrique_writer::{Entry, EntrySink, sink::{ProcessSink, Redact, StaticFields, VecEntrySink}};
+/// # use metrique_writer::test_util::to_test_entry;
+/// # use std::collections::BTreeMap;
+/// #[derive(Entry)]
+/// #[entry(rename_all = "PascalCase")]
+/// struct RequestMetrics {
+///     operation: &'static str,
+///     email: String,
+/// }
+///
+/// let inner = VecEntrySink::new();
+/// let sink = ProcessSink::new(inner.clone())
+///     .processor(Redact::new(["Email"]))
+///     .processor(StaticFields::new(BTreeMap::from([("Region", "us-east-1")])));
+/// sink.append(RequestMetrics { operation: "Get", email: "jane@example.com".into() });
+///
+/// let entry = to_test_entry(inner.drain().pop().unwrap());
+/// assert_eq!(entry.values["Email"], "<redacted>");
+/// assert_eq!(entry.values["Region"], "us-east-1");
+/// ```
+#[derive(Debug, Clone)]
+pub struct ProcessSink<S, P = ()> {
+    sink: S,
+    processor: P,
+}
+
+impl<S> ProcessSink<S> {
+    /// Wrap `sink`, without any processors yet
+    pub fn new(sink: S) -> Self {
+        Self {
+            sink,
+            processor: (),
+        }
+    }
+}
+
+impl<S, P> ProcessSink<S, P> {
+    /// Add `processor` after the processors already added
+    pub fn processor<Q>(self, processor: Q) -> ProcessSink<S, Chain<P, Q>> {
+        ProcessSink {
+            sink: self.sink,
+            processor: Chain {
+                first: self.processor,
+                second: processor,
+            },
+        }
+    }
+
+    /// Return the wrapped sink
+    pub fn into_inner(self) -> S {
+        self.sink
+    }
+}
+
+impl<E: Entry, P: EntryProcessor<E>, S: EntrySink<P::Output>> EntrySink<E> for ProcessSink<S, P> {
+    fn append(&self, entry: E) {
+        if let Some(entry) = self.processor.process(entry) {
+            self.sink.append(entry);
+        }
+    }
+
+    fn try_append(&self, entry: E) -> Result<(), TryAppendError> {
+        match self.processor.process(entry) {
+            Some(entry) => self.sink.try_append(entry),
+            None => Ok(()),
+        }
+    }
+
+    fn append_async(&self, entry: E) -> AppendWait {
+        match self.processor.process(entry) {
+            Some(entry) => self.sink.append_async(entry),
+            None => AppendWait::ready(),
+        }
+    }
+
+    fn flush_async(&self) -> FlushWait {
+        self.sink.flush_async()
+    }
+}
+
+/// The value that replaces redacted values, unless changed with [`Redact::with_replacement`].
+pub const DEFAULT_REDACTED_VALUE: &str = "<redacted>";
+
+/// An [`EntryProcessor`] that replaces, hashes or drops the values of sensitive properties, for
+/// example email addresses, before they are written.
+///
+/// A value is redacted if its emitted name (after `rename_all` and prefixes) is one of the names
+/// passed to [`Redact::new`], or matches one of the glob patterns added with
+/// [`Redact::with_pattern`] (or, with the `regex` feature, one of the regular expressions added
+/// with `Redact::with_regex`). Metric dimensions whose class matches are redacted the same way.
+/// Metric values themselves are kept.
+///
+/// By default, redacted values are replaced with `<redacted>` (see [`DEFAULT_REDACTED_VALUE`]).
+/// [`Redact::hash_values`] replaces them with a keyed hash instead, so that entries about the same
+/// customer can still be correlated, and [`Redact::drop_values`] leaves them out entirely.
+///
+/// ```
+/// # use metrique_writer::{Entry, sink::{EntryProcessor, Redact}};
+/// # use metrique_writer::test_util::to_test_entry;
+/// #[derive(Entry)]
+/// #[entry(rename_all = "PascalCase")]
+/// struct RequestMetrics {
+///     customer_email: &'static str,
+///     billing_email: &'static str,
+///     client_ip: &'static str,
+///     operation: &'static str,
+/// }
+///
+/// let redact = Redact::new(["ClientIp"]).with_pattern("*Email").drop_values();
+/// let entry = to_test_entry(redact.process(RequestMetrics {
+///     customer_email: "jane@example.com",
+///     billing_email: "billing@example.com",
+///     client_ip: "192.0.2.1",
+///     operation: "Get",
+/// }).unwrap());
+/// assert!(!entry.values.contains_key("CustomerEmail"));
+/// assert!(!entry.values.contains_key("BillingEmail"));
+/// assert!(!entry.values.contains_key("ClientIp"));
+/// assert_eq!(entry.values["Operation"], "Get");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Redact {
+    state: Arc<RedactState>,
+}
+
+#[derive(Debug, Clone)]
+struct RedactState {
+    names: HashSet<CowStr>,
+    patterns: Vec<NamePattern>,
+    action: RedactAction,
+}
+
+#[derive(Debug, Clone)]
+enum NamePattern {
+    Glob(CowStr),
+    #[cfg(feature = "regex")]
+    Regex(regex_lite::Regex),
+}
+
+impl NamePattern {
+    fn matches(&self, name: &str) -> bool {
+        match self {
+            NamePattern::Glob(glob) => glob_matches(glob.as_bytes(), name.as_bytes()),
+            #[cfg(feature = "regex")]
+            NamePattern::Regex(regex) => regex.is_match(name),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum RedactAction {
+    Replace(CowStr),
+    Hash([u8; 16]),
+    Drop,
+}
+
+impl RedactState {
+    fn matches(&self, name: &str) -> bool {
+        self.names.contains(name) || self.patterns.iter().any(|pattern| pattern.matches(name))
+    }
+
+    /// The value that replaces `value`, or [`None`] if it is dropped
+    fn redact<'s>(&'s self, value: &str) -> Option<Cow<'s, str>> {
+        match &self.action {
+            RedactAction::Replace(replacement) => Some(Cow::Borrowed(replacement)),
+            RedactAction::Hash(key) => Some(Cow::Owned(format!(
+                "{:016x}",
+                siphash24(key, value.as_bytes())
+            ))),
+            RedactAction::Drop => None,
+        }
+    }
+}
+
+/// Matches `name` against a glob where `*` matches any sequence of characters and `?` matches
+/// any single character
+fn glob_matches(glob: &[u8], name: &[u8]) -> bool {
+    let (mut g, mut n) = (0, 0);
+    // the position of the last `*` in `glob`, and the position in `name` it matched up to
+    let mut star = None;
+    while n < name.len() {
+        match glob.get(g) {
+            Some(b'*') => {
+                star = Some((g, n));
+                g += 1;
+            }
+            Some(&c) if c == b'?' || c == name[n] => {
+                g += 1;
+                n += 1;
+            }
+            _ => match star {
+                // let the last `*` match one more character
+                Some((star_g, star_n)) => {
+                    star = Some((star_g, star_n + 1));
+                    g = star_g + 1;
+                    n = star_n + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    glob[g..].iter().all(|&c| c == b'*')
+}
+
+/// SipHash-2-4 of `value`, keyed by `key`: a pseudorandom function, so hashes reveal nothing about
+/// the values to anybody who doesn't know the key
+fn siphash24(key: &[u8; 16], value: &[u8]) -> u64 {
+    fn sip_round(v: &mut [u64; 4]) {
+        v[0] = v[0].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(13) ^ v[0];
+        v[0] = v[0].rotate_left(32);
+        v[2] = v[2].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(16) ^ v[2];
+        v[0] = v[0].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(21) ^ v[0];
+        v[2] = v[2].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(17) ^ v[2];
+        v[2] = v[2].rotate_left(32);
+    }
+    fn compress(v: &mut [u64; 4], m: u64) {
+        v[3] ^= m;
+        sip_round(v);
+        sip_round(v);
+        v[0] ^= m;
+    }
+
+    let k0 = u64::from_le_bytes(key[..8].try_into().unwrap());
+    let k1 = u64::from_le_bytes(key[8..].try_into().unwrap());
+    let mut v = [
+        k0 ^ 0x736f_6d65_7073_6575,
+        k1 ^ 0x646f_7261_6e64_6f6d,
+        k0 ^ 0x6c79_6765_6e65_7261,
+        k1 ^ 0x7465_6462_7974_6573,
+    ];
+    let mut chunks = value.chunks_exact(8);
+    for chunk in &mut chunks {
+        compress(&mut v, u64::from_le_bytes(chunk.try_into().unwrap()));
+    }
+    // the last block holds the remaining bytes, and the low byte of the length in its top byte
+    let mut last = [0; 8];
+    last[..chunks.remainder().len()].copy_from_slice(chunks.remainder());
+    compress(
+        &mut v,
+        u64::from_le_bytes(last) | ((value.len() as u64) << 56),
+    );
+    v[2] ^= 0xff;
+    for _ in 0..4 {
+        sip_round(&mut v);
+    }
+    v[0] ^ v[1] ^ v[2] ^ v[3]
+}
+
+impl Redact {
+    /// Redact the properties and dimensions named `names`
+    pub fn new(names: impl IntoIterator<Item = impl Into<CowStr>>) -> Self {
+        Self {
+            state: Arc::new(RedactState {
+                names: names.into_iter().map(Into::into).collect(),
+                patterns: vec![],
+                action: RedactAction::Replace(Cow::Borrowed(DEFAULT_REDACTED_VALUE)),
+            }),
+        }
+    }
+
+    /// Also redact the properties and dimensions whose name matches the glob `pattern`, where `*`
+    /// matches any sequence of characters and `?` matches any single character, for example
+    /// `"*Email*"`. Matching is case-sensitive.
+    pub fn with_pattern(self, pattern: impl Into<CowStr>) -> Self {
+        self.update(|state| state.patterns.push(NamePattern::Glob(pattern.into())))
+    }
+
+    /// Also redact the properties and dimensions whose name matches `regex`.
+    ///
+    /// Note that unanchored regular expressions match anywhere in the name.
+    #[cfg(feature = "regex")]
+    pub fn with_regex(self, regex: regex_lite::Regex) -> Self {
+        self.update(|state| state.patterns.push(NamePattern::Regex(regex)))
+    }
+
+    /// Replace redacted values with `replacement` instead of `<redacted>`
+    pub fn with_replacement(self, replacement: impl Into<CowStr>) -> Self {
+        self.update(|state| state.action = RedactAction::Replace(replacement.into()))
+    }
+
+    /// Replace redacted values with a 16-digit hexadecimal hash of the value keyed by `key`, so
+    /// that equal values can still be correlated.
+    ///
+    /// The hash is SipHash-2-4, a keyed pseudorandom function: without `key`, hashes can't be
+    /// linked back to their values, even for values from a small set like IP addresses. Use a
+    /// random key, keep it secret, and prefer [`Redact::drop_values`] when correlation isn't
+    /// needed.
+    pub fn hash_values(self, key: [u8; 16]) -> Self {
+        self.update(|state| state.action = RedactAction::Hash(key))
+    }
+
+    /// Leave redacted properties out of the entry. Redacted metric dimensions are removed.
+    pub fn drop_values(self) -> Self {
+        self.update(|state| state.action = RedactAction::Drop)
+    }
+
+    fn update(self, f: impl FnOnce(&mut RedactState)) -> Self {
+        let mut state = Arc::unwrap_or_clone(self.state);
+        f(&mut state);
+        Self {
+            state: Arc::new(state),
+        }
+    }
+}
+
+impl<E: Entry> EntryProcessor<E> for Redact {
+    type Output = Redacted<E>;
+
+    fn process(&self, entry: E) -> Option<Redacted<E>> {
+        Some(Redacted {
+            entry,
+            state: Arc::clone(&self.state),
+        })
+    }
+}
+
+/// An [`Entry`] whose sensitive values are redacted, created by [`Redact`].
+#[derive(Debug)]
+pub struct Redacted<E> {
+    entry: E,
+    state: Arc<RedactState>,
+}
+
+impl<E> Redacted<E> {
+    /// Return the original entry
+    pub fn into_inner(self) -> E {
+        self.entry
+    }
+}
+
+impl<E: Entry> Entry for Redacted<E> {
+    fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+        struct EntryWriterWrapper<'r, W> {
+            writer: W,
+            state: &'r RedactState,
+        }
+
+        impl<'a, W: EntryWriter<'a>> EntryWriter<'a> for EntryWriterWrapper<'a, W> {
+            fn timestamp(&mut self, timestamp: SystemTime) {
+                self.writer.timestamp(timestamp);
+            }
+
+            fn value(&mut self, name: impl Into<Cow<'a, str>>, value: &(impl Value + ?Sized)) {
+                let name: Cow<'a, str> = name.into();
+                let wrapper = RedactedValue {
+                    value,
+                    redact_strings: self.state.matches(&name),
+                    state: self.state,
+                };
+                self.writer.value(name, &wrapper)
+            }
+
+            fn config(&mut self, config: &'a dyn EntryConfig) {
+                self.writer.config(config);
+            }
+
+            fn begin_group(
+                &mut self,
+                name: impl Into<Cow<'a, str>>,
+                prefix: impl Into<Cow<'a, str>>,
+            ) {
+                self.writer.begin_group(name, prefix);
+            }
+
+            fn end_group(&mut self) {
+                self.writer.end_group();
+            }
+        }
+
+        self.entry.write(&mut EntryWriterWrapper {
+            writer,
+            state: &self.state,
+        })
+    }
+
+    fn sample_group(&self) -> impl Iterator<Item = SampleGroupElement> {
+        self.entry.sample_group()
+    }
+
+    fn priority(&self) -> Priority {
+        self.entry.priority()
+    }
+}
+
+struct RedactedValue<'v, 'r, V: ?Sized> {
+    value: &'v V,
+    // true if the value is a redacted property
+    redact_strings: bool,
+    state: &'r RedactState,
+}
+
+impl<V: Value + ?Sized> Value for RedactedValue<'_, '_, V> {
+    fn write(&self, writer: impl ValueWriter) {
+        struct ValueWriterWrapper<'r, W> {
+            writer: W,
+            redact_strings: bool,
+            state: &'r RedactState,
+        }
+
+        impl<W: ValueWriter> ValueWriter for ValueWriterWrapper<'_, W> {
+            fn string(self, value: &str) {
+                if !self.redact_strings {
+                    self.writer.string(value)
+                } else if let Some(redacted) = self.state.redact(value) {
+                    self.writer.string(&redacted)
+                }
+            }
+
+            fn metric<'a>(
+                self,
+                distribution: impl IntoIterator<Item = Observation>,
+                unit: Unit,
+                dimensions: impl IntoIterator<Item = (&'a str, &'a str)>,
+                flags: MetricFlags<'_>,
+            ) {
+                let state = self.state;
+                let dimensions: SmallVec<[(&str, Cow<'_, str>); 2]> = dimensions
+                    .into_iter()
+                    .filter_map(|(class, instance)| {
+                        if state.matches(class) {
+                            Some((class, state.redact(instance)?))
+                        } else {
+                            Some((class, Cow::Borrowed(instance)))
+                        }
+                    })
+                    .collect();
+                self.writer.metric(
+                    distribution,
+                    unit,
+                    dimensions
+                        .iter()
+                        .map(|(class, instance)| (*class, &**instance)),
+                    flags,
+                )
+            }
+
+            fn error(self, error: ValidationError) {
+                self.writer.error(error)
+            }
+        }
+
+        self.value.write(ValueWriterWrapper {
+            writer,
+            redact_strings: self.redact_strings,
+            state: self.state,
+        })
+    }
+}
+
+/// An [`EntryProcessor`] that truncates every string property of an entry to at most `max_len`
+/// characters, so that a single huge value, like an error message, can't blow the size limit of
+/// the entry.
+///
+/// Truncated values end with `...` (see [`DEFAULT_TRUNCATION_MARKER`]), unless changed with
+/// [`TruncateStrings::with_marker`]. The marker counts towards `max_len`. Metrics and their
+/// dimensions are kept unchanged. To truncate a single field instead, use
+/// `#[metrics(max_len = ...)]` or [`Truncated`].
+///
+/// ```
+/// # use metrique_writer::{Entry, sink::{EntryProcessor, TruncateStrings}};
+/// # use metrique_writer::test_util::to_test_entry;
+/// #[derive(Entry)]
+/// #[entry(rename_all = "PascalCase")]
+/// struct RequestMetrics {
+///     operation: &'static str,
+///     error: String,
+/// }
+///
+/// let truncate = TruncateStrings::new(16).with_marker("[...]");
+/// let entry = to_test_entry(truncate.process(RequestMetrics {
+///     operation: "Get",
+///     error: "connection reset by peer while reading the response".into(),
+/// }).unwrap());
+/// assert_eq!(entry.values["Operation"], "Get");
+/// assert_eq!(entry.values["Error"], "connection [...]");
+/// ```
+///
+/// [`DEFAULT_TRUNCATION_MARKER`]: crate::value::DEFAULT_TRUNCATION_MARKER
+/// [`Truncated`]: crate::value::Truncated
+#[derive(Debug, Clone)]
+pub struct TruncateStrings {
+    state: Arc<TruncateState>,
+}
+
+#[derive(Debug, Clone)]
+struct TruncateState {
+    max_len: usize,
+    marker: CowStr,
+}
+
+impl TruncateStrings {
+    /// Truncate string properties to at most `max_len` characters
+    pub fn new(max_len: usize) -> Self {
+        Self {
+            state: Arc::new(TruncateState {
+                max_len,
+                marker: Cow::Borrowed(DEFAULT_TRUNCATION_MARKER),
+            }),
+        }
+    }
+
+    /// End truncated values with `marker` instead of [`DEFAULT_TRUNCATION_MARKER`]
+    ///
+    /// [`DEFAULT_TRUNCATION_MARKER`]: crate::value::DEFAULT_TRUNCATION_MARKER
+    pub fn with_marker(self, marker: impl Into<CowStr>) -> Self {
+        let mut state = Arc::unwrap_or_clone(self.state);
+        state.marker = marker.into();
+        Self {
+            state: Arc::new(state),
+        }
+    }
+}
+
+impl<E: Entry> EntryProcessor<E> for TruncateStrings {
+    type Output = WithTruncatedStrings<E>;
+
+    fn process(&self, entry: E) -> Option<WithTruncatedStrings<E>> {
+        Some(WithTruncatedStrings {
+            entry,
+            state: Arc::clone(&self.state),
+        })
+    }
+}
+
+/// An [`Entry`] whose string properties are truncated, created by [`TruncateStrings`].
+#[derive(Debug)]
+pub struct WithTruncatedStrings<E> {
+    entry: E,
+    state: Arc<TruncateState>,
+}
+
+impl<E> WithTruncatedStrings<E> {
+    /// Return the original entry
+    pub fn into_inner(self) -> E {
+        self.entry
+    }
+}
+
+impl<E: Entry> Entry for WithTruncatedStrings<E> {
+    fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+        struct EntryWriterWrapper<'t, W> {
+            writer: W,
+            state: &'t TruncateState,
+        }
+
+        impl<'a, W: EntryWriter<'a>> EntryWriter<'a> for EntryWriterWrapper<'a, W> {
+            fn timestamp(&mut self, timestamp: SystemTime) {
+                self.writer.timestamp(timestamp);
+            }
+
+            fn value(&mut self, name: impl Into<Cow<'a, str>>, value: &(impl Value + ?Sized)) {
+                let truncated =
+                    Truncated::new(value, self.state.max_len).with_marker(&self.state.marker);
+                self.writer.value(name, &truncated)
+            }
+
+            fn config(&mut self, config: &'a dyn EntryConfig) {
+                self.writer.config(config);
+            }
+
+            fn begin_group(
+                &mut self,
+                name: impl Into<Cow<'a, str>>,
+                prefix: impl Into<Cow<'a, str>>,
+            ) {
+                self.writer.begin_group(name, prefix);
+            }
+
+            fn end_group(&mut self) {
+                self.writer.end_group();
+            }
+        }
+
+        self.entry.write(&mut EntryWriterWrapper {
+            writer,
+            state: &self.state,
+        })
+    }
+
+    fn sample_group(&self) -> impl Iterator<Item = SampleGroupElement> {
+        self.entry.sample_group()
+    }
+
+    fn priority(&self) -> Priority {
+        self.entry.priority()
+    }
+}
+
+/// An [`EntryProcessor`] that adds the same fields to every entry, for example the region or
+/// the host name.
+///
+/// The fields can be any [`Entry`], like a `#[derive(Entry)]` struct or a
+/// [`BTreeMap`](std::collections::BTreeMap) of names to values. They are written before the
+/// fields of the entry itself. Unlike [`EntryIoStreamExt::merge_globals`], this works in front of
+/// any sink rather than on a stream.
+///
+/// [`EntryIoStreamExt::merge_globals`]: crate::EntryIoStreamExt::merge_globals
+#[derive(Debug)]
+pub struct StaticFields<G> {
+    fields: Arc<G>,
+}
+
+impl<G> Clone for StaticFields<G> {
+    fn clone(&self) -> Self {
+        Self {
+            fields: Arc::clone(&self.fields),
+        }
+    }
+}
+
+impl<G: Entry> StaticFields<G> {
+    /// Add `fields` to every entry
+    pub fn new(fields: G) -> Self {
+        Self {
+            fields: Arc::new(fields),
+        }
+    }
+}
+
+impl<E: Entry, G: Entry> EntryProcessor<E> for StaticFields<G> {
+    type Output = WithStaticFields<E, G>;
+
+    fn process(&self, entry: E) -> Option<WithStaticFields<E, G>> {
+        Some(WithStaticFields {
+            entry,
+            fields: Arc::clone(&self.fields),
+        })
+    }
+}
+
+/// An [`Entry`] with some extra fields, created by [`StaticFields`].
+#[derive(Debug)]
+pub struct WithStaticFields<E, G> {
+    entry: E,
+    fields: Arc<G>,
+}
+
+impl<E, G> WithStaticFields<E, G> {
+    /// Return the original entry
+    pub fn into_inner(self) -> E {
+        self.entry
+    }
+}
+
+impl<E: Entry, G: Entry> Entry for WithStaticFields<E, G> {
+    fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+        self.fields.write(writer);
+        self.entry.write(writer);
+    }
+
+    fn sample_group(&self) -> impl Iterator<Item = SampleGroupElement> {
+        self.fields.sample_group().chain(self.entry.sample_group())
+    }
+
+    fn priority(&self) -> Priority {
+        self.entry.priority()
+    }
+}
+
+/// An [`EntryProcessor`] that keeps a random fraction of the entries and drops the rest.
+///
+/// Unlike the samplers in [`sample`](crate::sample), this does not upweight the kept entries, so
+/// aggregates like sums and counts will be scaled down by the sample rate. Use it for entries that
+/// are only used for debugging or for their properties, and prefer
+/// [`SampledFormatExt`](crate::sample::SampledFormatExt) for metrics.
+#[derive(Debug, Clone, Copy)]
+pub struct SampleEntries {
+    rate: f32,
+}
+
+impl SampleEntries {
+    /// Keep each entry with probability `rate`, which must be in `(0, 1]`
+    pub fn new(rate: f32) -> Self {
+        assert!(rate.is_finite() && 0.0 < rate && rate <= 1.0);
+        Self { rate }
+    }
+}
+
+impl<E: Entry> EntryProcessor<E> for SampleEntries {
+    type Output = E;
+
+    fn process(&self, entry: E) -> Option<E> {
+        (rand::rng().random::<f32>() <= self.rate).then_some(entry)
+    }
+}
+
+/// An [`EntryProcessor`] that sets the [`Priority`] of every entry, for queues with priority
+/// lanes like a [`BackgroundQueue`] built with [`priority_lanes`].
+///
+/// `classify` is called with every entry and returns its priority, which replaces the priority
+/// of the entry itself.
+///
+/// ```
+/// # use metrique_writer::{Entry, entry::Priority, sink::{EntryProcessor, Prioritize}};
+/// #[derive(Entry)]
+/// struct RequestMetrics {
+///     operation: &'static str,
+///     failed: bool,
+/// }
+///
+/// let prioritize = Prioritize::new(|entry: &RequestMetrics| match entry {
+///     RequestMetrics { failed: true, .. } => Priority::High,
+///     RequestMetrics { operation: "Ping", .. } => Priority::Low,
+///     _ => Priority::Normal,
+/// });
+/// let entry = prioritize.process(RequestMetrics { operation: "Get", failed: true }).unwrap();
+/// assert_eq!(entry.priority(), Priority::High);
+/// ```
+///
+/// [`BackgroundQueue`]: crate::sink::BackgroundQueue
+/// [`priority_lanes`]: crate::sink::BackgroundQueueBuilder::priority_lanes
+pub struct Prioritize<F> {
+    classify: Arc<F>,
+}
+
+impl<F> Clone for Prioritize<F> {
+    fn clone(&self) -> Self {
+        Self {
+            classify: Arc::clone(&self.classify),
+        }
+    }
+}
+
+impl<F> fmt::Debug for Prioritize<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Prioritize").finish_non_exhaustive()
+    }
+}
+
+impl<F> Prioritize<F> {
+    /// Set the priority of every entry to `classify(&entry)`
+    pub fn new(classify: F) -> Self {
+        Self {
+            classify: Arc::new(classify),
+        }
+    }
+}
+
+impl<E: Entry, F: Fn(&E) -> Priority> EntryProcessor<E> for Prioritize<F> {
+    type Output = WithPriority<E>;
+
+    fn process(&self, entry: E) -> Option<WithPriority<E>> {
+        let priority = (self.classify)(&entry);
+        Some(WithPriority::new(entry, priority))
+    }
+}
+
+/// An [`Entry`] with a [`Priority`] that replaces its own, created by [`Prioritize`] or
+/// [`WithPriority::new`].
+#[derive(Debug, Clone)]
+pub struct WithPriority<E> {
+    entry: E,
+    priority: Priority,
+}
+
+impl<E> WithPriority<E> {
+    /// Give `entry` the priority `priority`
+    pub fn new(entry: E, priority: Priority) -> Self {
+        Self { entry, priority }
+    }
+
+    /// Return the original entry
+    pub fn into_inner(self) -> E {
+        self.entry
+    }
+}
+
+impl<E: Entry> Entry for WithPriority<E> {
+    fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+        self.entry.write(writer)
+    }
+
+    fn sample_group(&self) -> impl Iterator<Item = SampleGroupElement> {
+        self.entry.sample_group()
+    }
+
+    fn priority(&self) -> Priority {
+        self.priority
+    }
+}
+
+/// An [`EntryProcessor`] that writes a sequence number property into every entry.
+///
+/// Sequence numbers increase by one for every entry appended to the sink, in the order the entries
+/// are appended, so that downstream systems can detect entries that were reordered or lost on
+/// the way, for example by a [`BackgroundQueue`] that is appended to from several threads. The
+/// property is called `sequence_number` unless renamed with [`SequenceNumbers::with_name`].
+///
+/// Clones share the same counter. Put it after any processor that drops entries on purpose, like
+/// [`SampleEntries`], so that gaps only come from lost entries. Sequence numbers restart with the
+/// process, so combine them with a [`StaticFields`] property that identifies the process.
+///
+/// ```
+/// # use metrique_writer::{Entry, EntrySink, sink::{ProcessSink, SequenceNumbers, VecEntrySink}};
+/// # use metrique_writer::test_util::to_test_entry;
+/// #[derive(Entry)]
+/// struct RequestMetrics {
+///     operation: &'static str,
+/// }
+///
+/// let entries = VecEntrySink::new();
+/// let sink = ProcessSink::new(entries.clone()).processor(SequenceNumbers::new());
+/// sink.append(RequestMetrics { operation: "Get" });
+/// sink.append(RequestMetrics { operation: "Put" });
+/// let entries: Vec<_> = entries.drain().into_iter().map(to_test_entry).collect();
+/// assert_eq!(entries[0].values["sequence_number"], "0");
+/// assert_eq!(entries[1].values["sequence_number"], "1");
+/// ```
+///
+/// [`BackgroundQueue`]: crate::sink::BackgroundQueue
+#[derive(Debug, Clone)]
+pub struct SequenceNumbers {
+    state: Arc<SequenceState>,
+}
+
+#[derive(Debug)]
+struct SequenceState {
+    name: CowStr,
+    next: AtomicU64,
+}
+
+impl Default for SequenceNumbers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SequenceNumbers {
+    /// Number entries from `0`, in a `sequence_number` property
+    pub fn new() -> Self {
+        Self::starting_at(0)
+    }
+
+    /// Number entries from `first`, in a `sequence_number` property
+    pub fn starting_at(first: u64) -> Self {
+        Self {
+            state: Arc::new(SequenceState {
+                name: Cow::Borrowed("sequence_number"),
+                next: AtomicU64::new(first),
+            }),
+        }
+    }
+
+    /// Write the sequence number in a property called `name`
+    pub fn with_name(self, name: impl Into<CowStr>) -> Self {
+        let next = self.state.next.load(Ordering::Relaxed);
+        Self {
+            state: Arc::new(SequenceState {
+                name: name.into(),
+                next: AtomicU64::new(next),
+            }),
+        }
+    }
+}
+
+impl<E: Entry> EntryProcessor<E> for SequenceNumbers {
+    type Output = WithSequenceNumber<E>;
+
+    fn process(&self, entry: E) -> Option<WithSequenceNumber<E>> {
+        Some(WithSequenceNumber {
+            entry,
+            sequence_number: self.state.next.fetch_add(1, Ordering::Relaxed),
+            state: Arc::clone(&self.state),
+        })
+    }
+}
+
+/// An [`Entry`] with a sequence number, created by [`SequenceNumbers`].
+#[derive(Debug)]
+pub struct WithSequenceNumber<E> {
+    entry: E,
+    sequence_number: u64,
+    state: Arc<SequenceState>,
+}
+
+impl<E> WithSequenceNumber<E> {
+    /// The sequence number of the entry
+    pub fn sequence_number(&self) -> u64 {
+        self.sequence_number
+    }
+
+    /// Return the original entry
+    pub fn into_inner(self) -> E {
+        self.entry
+    }
+}
+
+impl<E: Entry> Entry for WithSequenceNumber<E> {
+    fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+        writer.value(&*self.state.name, &self.sequence_number.to_string());
+        self.entry.write(writer);
+    }
+
+    fn sample_group(&self) -> impl Iterator<Item = SampleGroupElement> {
+        self.entry.sample_group()
+    }
+
+    fn priority(&self) -> Priority {
+        self.entry.priority()
+    }
+}
+
+/// An [`EntryProcessor`] that gives the entries that don't write a timestamp the time they were
+/// appended to the sink.
+///
+/// Formats fall back to [`SystemTime::now`] when they write an entry without a timestamp, which
+/// for a [`BackgroundQueue`] is however long the entry waited in the queue after it was appended.
+/// Under load, that delay skews the timestamps. This processor reads the time on the appending
+/// thread instead, from [`SystemTime::now`] or from the clock passed to
+/// [`DefaultTimestamp::with_clock`], for example a fixed time in tests.
+///
+/// Entries that write their own timestamp, like `#[metrics(timestamp)]` fields, keep it.
+///
+/// ```
+/// # use std::time::{Duration, SystemTime};
+/// # use metrique_writer::{Entry, EntrySink, sink::{DefaultTimestamp, ProcessSink, VecEntrySink}};
+/// # use metrique_writer::test_util::to_test_entry;
+/// #[derive(Entry)]
+/// struct RequestMetrics {
+///     operation: &'static str,
+/// }
+///
+/// let appended_at = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+/// let entries = VecEntrySink::new();
+/// let sink = ProcessSink::new(entries.clone())
+///     .processor(DefaultTimestamp::with_clock(move || appended_at));
+/// sink.append(RequestMetrics { operation: "Get" });
+/// let entry = to_test_entry(entries.drain().pop().unwrap());
+/// assert_eq!(entry.timestamp, Some(appended_at));
+/// ```
+///
+/// [`BackgroundQueue`]: crate::sink::BackgroundQueue
+#[derive(Clone)]
+pub struct DefaultTimestamp {
+    clock: Arc<dyn Fn() -> SystemTime + Send + Sync>,
+}
+
+impl fmt::Debug for DefaultTimestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DefaultTimestamp").finish_non_exhaustive()
+    }
+}
+
+impl Default for DefaultTimestamp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DefaultTimestamp {
+    /// Timestamp entries with [`SystemTime::now`] when they are appended
+    pub fn new() -> Self {
+        Self::with_clock(SystemTime::now)
+    }
+
+    /// Timestamp entries with the time returned by `clock` when they are appended.
+    ///
+    /// `clock` runs on the thread that appends the entry, so it can read a thread-local or
+    /// runtime-local time source.
+    pub fn with_clock(clock: impl Fn() -> SystemTime + Send + Sync + 'static) -> Self {
+        Self {
+            clock: Arc::new(clock),
+        }
+    }
+}
+
+impl<E: Entry> EntryProcessor<E> for DefaultTimestamp {
+    type Output = WithDefaultTimestamp<E>;
+
+    fn process(&self, entry: E) -> Option<WithDefaultTimestamp<E>> {
+        Some(WithDefaultTimestamp {
+            entry,
+            timestamp: (self.clock)(),
+        })
+    }
+}
+
+/// An [`Entry`] with a timestamp to use if it doesn't write one, created by [`DefaultTimestamp`].
+#[derive(Debug)]
+pub struct WithDefaultTimestamp<E> {
+    entry: E,
+    timestamp: SystemTime,
+}
+
+impl<E> WithDefaultTimestamp<E> {
+    /// The timestamp that is written if the entry doesn't write its own
+    pub fn default_timestamp(&self) -> SystemTime {
+        self.timestamp
+    }
+
+    /// Return the original entry
+    pub fn into_inner(self) -> E {
+        self.entry
+    }
+}
+
+impl<E: Entry> Entry for WithDefaultTimestamp<E> {
+    fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+        struct EntryWriterWrapper<W> {
+            writer: W,
+            wrote_timestamp: bool,
+        }
+
+        impl<'a, W: EntryWriter<'a>> EntryWriter<'a> for EntryWriterWrapper<W> {
+            fn timestamp(&mut self, timestamp: SystemTime) {
+                self.wrote_timestamp = true;
+                self.writer.timestamp(timestamp);
+            }
+
+            fn value(&mut self, name: impl Into<Cow<'a, str>>, value: &(impl Value + ?Sized)) {
+                self.writer.value(name, value)
+            }
+
+            fn config(&mut self, config: &'a dyn EntryConfig) {
+                self.writer.config(config);
+            }
+
+            fn begin_group(
+                &mut self,
+                name: impl Into<Cow<'a, str>>,
+                prefix: impl Into<Cow<'a, str>>,
+            ) {
+                self.writer.begin_group(name, prefix);
+            }
+
+            fn end_group(&mut self) {
+                self.writer.end_group();
+            }
+        }
+
+        let mut wrapper = EntryWriterWrapper {
+            writer,
+            wrote_timestamp: false,
+        };
+        self.entry.write(&mut wrapper);
+        // formats read the timestamp when the entry is finished, so it can come last
+        if !wrapper.wrote_timestamp {
+            wrapper.writer.timestamp(self.timestamp);
+        }
+    }
+
+    fn sample_group(&self) -> impl Iterator<Item = SampleGroupElement> {
+        self.entry.sample_group()
+    }
+
+    fn priority(&self) -> Priority {
+        self.entry.priority()
+    }
+}
+
+/// An [`EntryProcessor`] that lets through at most a fixed number of entries per second for each
+/// sample group, and drops the rest.
+///
+/// A single hot key, like one customer hammering one operation, can otherwise produce enough
+/// entries to overwhelm the log agent. Entries are grouped by the values returned by
+/// [`Entry::sample_group`] (for `#[metrics]` structs, the `#[metrics(sample_group)]` fields, like
+/// the operation), and every group gets its own budget, so quiet groups are not affected by a
+/// noisy one. Entries without a sample group share a single budget.
+///
+/// Each group can send a burst of up to one second's worth of entries, after which entries are let
+/// through at the configured rate. Clones share their budgets and the count of dropped entries,
+/// see [`SampleGroupRateLimit::entries_dropped`].
+///
+/// Like [`SampleEntries`], this does not upweight the kept entries, so put it in front of sinks
+/// whose entries are mostly read for their properties, or where losing the excess of a storm is
+/// preferable to losing the log agent.
+///
+/// ```
+/// # use metrique_writer::{Entry, EntrySink, sink::{ProcessSink, SampleGroupRateLimit, VecEntrySink}};
+/// #[derive(Entry)]
+/// struct RequestMetrics {
+///     #[entry(sample_group)]
+///     operation: &'static str,
+/// }
+///
+/// let entries = VecEntrySink::new();
+/// let limit = SampleGroupRateLimit::new(10);
+/// let sink = ProcessSink::new(entries.clone()).processor(limit.clone());
+/// for _ in 0..100 {
+///     sink.append(RequestMetrics { operation: "Get" });
+/// }
+/// sink.append(RequestMetrics { operation: "Put" });
+/// // at least a burst of 10 `Get`s and the `Put`, plus whatever the `Get` budget refilled while
+/// // the loop ran
+/// let kept = entries.drain().len() as u64;
+/// assert!(kept >= 11);
+/// assert_eq!(limit.entries_dropped(), 101 - kept);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SampleGroupRateLimit {
+    state: Arc<RateLimitState>,
+}
+
+#[derive(Debug)]
+struct RateLimitState {
+    entries_per_second: f64,
+    buckets: Mutex<RateLimitBuckets>,
+    entries_dropped: AtomicU64,
+}
+
+#[derive(Debug)]
+struct RateLimitBuckets {
+    // sample group => token bucket
+    buckets: HashMap<Box<[SampleGroupElement]>, TokenBucket>,
+    last_sweep: Instant,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Buckets that weren't used for this long are full again, so forgetting them changes nothing
+const RATE_LIMIT_BURST: Duration = Duration::from_secs(1);
+
+impl RateLimitState {
+    fn lock_buckets(&self) -> MutexGuard<'_, RateLimitBuckets> {
+        // the buckets are always consistent, so it is fine to ignore poisoning
+        self.buckets
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Returns true if an entry of `group` can be let through at `now`, and takes its token
+    fn admit(&self, group: Box<[SampleGroupElement]>, now: Instant) -> bool {
+        let capacity = self.entries_per_second * RATE_LIMIT_BURST.as_secs_f64();
+        let mut buckets = self.lock_buckets();
+        if now.saturating_duration_since(buckets.last_sweep) >= RATE_LIMIT_BURST {
+            // forget idle groups, so that high-cardinality groups don't grow the map forever
+            buckets.buckets.retain(|_, bucket| {
+                now.saturating_duration_since(bucket.updated) < RATE_LIMIT_BURST
+            });
+            buckets.last_sweep = now;
+        }
+        let bucket = buckets.buckets.entry(group).or_insert(TokenBucket {
+            tokens: capacity,
+            updated: now,
+        });
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.entries_per_second).min(capacity);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            drop(buckets);
+            self.entries_dropped.fetch_add(1, Ordering::Relaxed);
+            false
+        }
+    }
+}
+
+impl SampleGroupRateLimit {
+    /// Let through at most `max_entries_per_second` entries per second for each sample group.
+    ///
+    /// # Panics
+    /// Panics if `max_entries_per_second` is 0.
+    pub fn new(max_entries_per_second: u32) -> Self {
+        assert!(
+            max_entries_per_second > 0,
+            "max_entries_per_second must be positive"
+        );
+        Self {
+            state: Arc::new(RateLimitState {
+                entries_per_second: f64::from(max_entries_per_second),
+                buckets: Mutex::new(RateLimitBuckets {
+                    buckets: HashMap::new(),
+                    last_sweep: Instant::now(),
+                }),
+                entries_dropped: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// The number of entries dropped for exceeding the rate so far
+    pub fn entries_dropped(&self) -> u64 {
+        self.state.entries_dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl<E: Entry> EntryProcessor<E> for SampleGroupRateLimit {
+    type Output = E;
+
+    fn process(&self, entry: E) -> Option<E> {
+        self.state
+            .admit(entry.sample_group().collect(), Instant::now())
+            .then_some(entry)
+    }
+}
+
+/// The `tracing` target of the lines logged by [`LogSummary`]
+#[cfg(feature = "tracing")]
+pub const LOG_SUMMARY_TARGET: &str = "metrique::summary";
+
+/// An [`EntryProcessor`] that logs a compact, single-line summary of every entry with
+/// `tracing::info!`, and forwards the entry unchanged.
+///
+/// The summary lists the fields passed to [`LogSummary::new`], in that order, as
+/// `Name=value` pairs, like `Operation=GetItem Status=200 Latency=12ms`, so that on-call engineers
+/// can grep the application logs for an entry without parsing its metrics format. Fields are
+/// matched by their emitted name (after `rename_all` and prefixes) and skipped if the entry doesn't
+/// write them. Durations and percentages get a unit suffix (`ms`, `us`, `s`, `%`), and metrics
+/// with several observations are summed. String values that are empty or contain whitespace, `=`,
+/// `"` or control characters are quoted, with `"`, `\` and control characters escaped like in a
+/// Rust string literal, so that every line parses back into the same pairs.
+///
+/// The lines are logged with the `metrique::summary` target (see [`LOG_SUMMARY_TARGET`]), so they
+/// can be enabled or routed separately from the other logs. This requires the `tracing` feature.
+///
+/// ```
+/// # use std::time::Duration;
+/// # use metrique_writer::{Entry, EntrySink, sink::{LogSummary, ProcessSink, VecEntrySink}};
+/// #[derive(Entry)]
+/// #[entry(rename_all = "PascalCase")]
+/// struct RequestMetrics {
+///     operation: &'static str,
+///     status: u64,
+///     latency: Duration,
+///     request_id: &'static str,
+/// }
+///
+/// let summary = LogSummary::new(["Operation", "Status", "Latency"]);
+/// let entry = RequestMetrics {
+///     operation: "GetItem",
+///     status: 200,
+///     latency: Duration::from_millis(12),
+///     request_id: "8f3c",
+/// };
+/// assert_eq!(summary.summary(&entry), "Operation=GetItem Status=200 Latency=12ms");
+///
+/// // log every entry on its way to the sink
+/// let sink = ProcessSink::new(VecEntrySink::new()).processor(summary);
+/// sink.append(entry);
+/// ```
+#[cfg(feature = "tracing")]
+#[derive(Debug, Clone)]
+pub struct LogSummary {
+    fields: Arc<[CowStr]>,
+}
+
+#[cfg(feature = "tracing")]
+impl LogSummary {
+    /// Summarize entries with the values of `fields`, in that order
+    pub fn new(fields: impl IntoIterator<Item = impl Into<CowStr>>) -> Self {
+        Self {
+            fields: fields.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// The summary line of `entry`, as it is logged
+    pub fn summary(&self, entry: &impl Entry) -> String {
+        let mut writer = SummaryWriter {
+            fields: &self.fields,
+            values: vec![None; self.fields.len()],
+        };
+        entry.write(&mut writer);
+        let mut line = String::new();
+        for (field, value) in self.fields.iter().zip(writer.values) {
+            if let Some(value) = value {
+                if !line.is_empty() {
+                    line.push(' ');
+                }
+                line.push_str(field);
+                line.push('=');
+                line.push_str(&value);
+            }
+        }
+        line
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl<E: Entry> EntryProcessor<E> for LogSummary {
+    type Output = E;
+
+    fn process(&self, entry: E) -> Option<E> {
+        if tracing::enabled!(target: LOG_SUMMARY_TARGET, tracing::Level::INFO) {
+            tracing::info!(target: LOG_SUMMARY_TARGET, "{}", self.summary(&entry));
+        }
+        Some(entry)
+    }
+}
+
+/// Collects the values of the summarized fields of an entry
+#[cfg(feature = "tracing")]
+struct SummaryWriter<'f> {
+    fields: &'f [CowStr],
+    values: Vec<Option<String>>,
+}
+
+#[cfg(feature = "tracing")]
+impl<'a> EntryWriter<'a> for SummaryWriter<'_> {
+    fn timestamp(&mut self, _timestamp: SystemTime) {}
+
+    fn value(&mut self, name: impl Into<Cow<'a, str>>, value: &(impl Value + ?Sized)) {
+        let name = name.into();
+        if let Some(index) = self.fields.iter().position(|field| *field == name) {
+            value.write(SummaryValueWriter(&mut self.values[index]));
+        }
+    }
+
+    fn config(&mut self, _config: &'a dyn EntryConfig) {}
+}
+
+#[cfg(feature = "tracing")]
+struct SummaryValueWriter<'w>(&'w mut Option<String>);
+
+#[cfg(feature = "tracing")]
+impl ValueWriter for SummaryValueWriter<'_> {
+    fn string(self, value: &str) {
+        let quote = value.is_empty()
+            || value
+                .chars()
+                .any(|c| c.is_whitespace() || c.is_control() || c == '=' || c == '"');
+        *self.0 = Some(if quote {
+            format!("{value:?}")
+        } else {
+            value.to_owned()
+        });
+    }
+
+    fn metric<'a>(
+        self,
+        distribution: impl IntoIterator<Item = Observation>,
+        unit: Unit,
+        _dimensions: impl IntoIterator<Item = (&'a str, &'a str)>,
+        _flags: MetricFlags<'_>,
+    ) {
+        let mut unsigned = Some(0u64);
+        let mut total = 0.0;
+        let mut any = false;
+        for observation in distribution {
+            any = true;
+            match observation {
+                Observation::Unsigned(value) => {
+                    unsigned = unsigned.map(|sum| sum.saturating_add(value));
+                    total += value as f64;
+                }
+                Observation::Floating(value) => {
+                    unsigned = None;
+                    total += value;
+                }
+                Observation::Repeated { total: value, .. } => {
+                    unsigned = None;
+                    total += value;
+                }
+                _ => unsigned = None,
+            }
+        }
+        if !any {
+            return;
+        }
+        let suffix = match unit {
+            Unit::Second(NegativeScale::Micro) => "us",
+            Unit::Second(NegativeScale::Milli) => "ms",
+            Unit::Second(NegativeScale::One) => "s",
+            Unit::Percent => "%",
+            _ => "",
+        };
+        *self.0 = Some(match unsigned {
+            Some(value) => format!("{value}{suffix}"),
+            None => format!("{total}{suffix}"),
+        });
+    }
+
+    fn error(self, _error: ValidationError) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use metrique_writer_core::value::WithDimensions;
+
+    use super::*;
+    use crate::{sink::VecEntrySink, test_util::to_test_entry};
+
+    #[derive(Entry)]
+    #[entry(rename_all = "PascalCase")]
+    struct TestEntry {
+        email: &'static str,
+        operation: &'static str,
+        latency: WithDimensions<u64, 1>,
+    }
+
+    fn entry() -> TestEntry {
+        TestEntry {
+            email: "jane@example.com",
+            operation: "Get",
+            latency: WithDimensions::new(5, "Email", "jane@example.com"),
+        }
+    }
+
+    #[test]
+    fn redacts_properties_and_dimensions() {
+        let entry = to_test_entry(Redact::new(["Email"]).process(entry()).unwrap());
+        assert_eq!(entry.values["Email"], "<redacted>");
+        assert_eq!(entry.values["Operation"], "Get");
+        assert_eq!(entry.metrics["Latency"], 5);
+        assert_eq!(
+            entry.metrics["Latency"].dimensions,
+            [("Email".to_string(), "<redacted>".to_string())]
+        );
+
+        let redact = Redact::new(["Operation"]).with_replacement("***");
+        let entry = to_test_entry(redact.process(super::tests::entry()).unwrap());
+        assert_eq!(entry.values["Operation"], "***");
+        assert_eq!(entry.values["Email"], "jane@example.com");
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn log_summary_lists_present_fields_in_order() {
+        #[derive(Entry)]
+        #[entry(rename_all = "PascalCase")]
+        struct Request {
+            operation: &'static str,
+            status: Option<u64>,
+            latency: Duration,
+            ratio: f64,
+        }
+
+        let summary = LogSummary::new(["Latency", "Status", "Operation", "Ratio", "Missing"]);
+        let request = Request {
+            operation: "Get",
+            status: None,
+            latency: Duration::from_micros(1500),
+            ratio: 0.5,
+        };
+        assert_eq!(
+            summary.summary(&request),
+            "Latency=1.5ms Operation=Get Ratio=0.5"
+        );
+        // the entry is forwarded unchanged
+        let forwarded = to_test_entry(summary.process(entry()).unwrap());
+        assert_eq!(forwarded.values["Operation"], "Get");
+        assert_eq!(
+            LogSummary::new(["Operation", "Latency"]).summary(&entry()),
+            "Operation=Get Latency=5"
+        );
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn log_summary_quotes_values_that_would_break_the_line() {
+        #[derive(Entry)]
+        #[entry(rename_all = "PascalCase")]
+        struct Request {
+            note: &'static str,
+            query: &'static str,
+            empty: &'static str,
+            path: &'static str,
+        }
+
+        let summary = LogSummary::new(["Note", "Query", "Empty", "Path"]);
+        let request = Request {
+            note: "a b\nc",
+            query: "id=\"7\"",
+            empty: "",
+            path: "/items/7",
+        };
+        assert_eq!(
+            summary.summary(&request),
+            r#"Note="a b\nc" Query="id=\"7\"" Empty="" Path=/items/7"#
+        );
+    }
+
+    #[test]
+    fn glob_patterns() {
+        assert!(glob_matches(b"*Email*", b"CustomerEmailAddress"));
+        assert!(glob_matches(b"*Email", b"Email"));
+        assert!(glob_matches(b"User?d", b"UserId"));
+        assert!(glob_matches(b"a*b*c", b"aXbYbZc"));
+        assert!(glob_matches(b"*", b""));
+        assert!(!glob_matches(b"*Email", b"EmailCount"));
+        assert!(!glob_matches(b"User?d", b"Userd"));
+        assert!(!glob_matches(b"email", b"Email"));
+    }
+
+    #[test]
+    fn sequence_numbers_are_shared_by_clones() {
+        let numbers = SequenceNumbers::starting_at(5).with_name("Seq");
+        let clone = numbers.clone();
+        assert_eq!(numbers.process(entry()).unwrap().sequence_number(), 5);
+        let entry = to_test_entry(clone.process(entry()).unwrap());
+        assert_eq!(entry.values["Seq"], "6");
+        assert_eq!(entry.values["Operation"], "Get");
+    }
+
+    #[test]
+    fn default_timestamp_only_when_missing() {
+        use metrique_writer_format_emf::Emf;
+
+        use crate::format::Format;
+
+        #[derive(Entry)]
+        struct Timestamped {
+            #[entry(timestamp)]
+            timestamp: SystemTime,
+        }
+
+        let appended_at = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000);
+        let default = DefaultTimestamp::with_clock(move || appended_at);
+        let entry = to_test_entry(default.process(entry()).unwrap());
+        assert_eq!(entry.timestamp, Some(appended_at));
+
+        let own = Timestamped {
+            timestamp: SystemTime::UNIX_EPOCH,
+        };
+        let processed = default.process(own).unwrap();
+        assert_eq!(processed.default_timestamp(), appended_at);
+        // a second timestamp would fail validation
+        let mut output = vec![];
+        Emf::all_validations("Ns".into(), vec![vec![]])
+            .format(&processed, &mut output)
+            .unwrap();
+        assert!(
+            String::from_utf8(output)
+                .unwrap()
+                .contains(r#""Timestamp":0"#)
+        );
+    }
+
+    #[test]
+    fn hashes_or_drops_matching_values() {
+        let redact = Redact::new(Vec::<&str>::new())
+            .with_pattern("Em*")
+            .hash_values([42; 16]);
+        let first = to_test_entry(redact.process(entry()).unwrap());
+        let second = to_test_entry(redact.process(entry()).unwrap());
+        let hashed = &first.values["Email"];
+        assert_eq!(hashed.len(), 16);
+        assert_ne!(hashed, "jane@example.com");
+        // hashes are stable, so equal values can be correlated
+        assert_eq!(hashed, &second.values["Email"]);
+        assert_eq!(
+            first.metrics["Latency"].dimensions,
+            [("Email".to_string(), hashed.clone())]
+        );
+        let other_key = to_test_entry(redact.hash_values([43; 16]).process(entry()).unwrap());
+        assert_ne!(hashed, &other_key.values["Email"]);
+
+        let redact = Redact::new(["Email"]).drop_values();
+        let entry = to_test_entry(redact.process(entry()).unwrap());
+        assert!(!entry.values.contains_key("Email"));
+        assert_eq!(entry.values["Operation"], "Get");
+        assert_eq!(entry.metrics["Latency"], 5);
+        assert!(entry.metrics["Latency"].dimensions.is_empty());
+    }
+
+    #[test]
+    fn siphash_matches_the_reference_and_depends_on_the_key() {
+        // the test vector of the SipHash paper
+        let key: [u8; 16] = std::array::from_fn(|i| i as u8);
+        let message: Vec<u8> = (0..15).collect();
+        assert_eq!(siphash24(&key, &message), 0xa129_ca61_49be_45e5);
+
+        // a known (value, hash) pair under one key says nothing about the hashes under another
+        let other_key: [u8; 16] = std::array::from_fn(|i| 0xf0 | i as u8);
+        for value in [
+            "",
+            "192.0.2.1",
+            "192.0.2.2",
+            "jane@example.com",
+            "a longer value, over 8 bytes",
+        ] {
+            assert_ne!(
+                siphash24(&key, value.as_bytes()),
+                siphash24(&other_key, value.as_bytes()),
+                "{value}"
+            );
+        }
+        assert_ne!(siphash24(&key, b"192.0.2.1"), siphash24(&key, b"192.0.2.2"));
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn regex_patterns() {
+        let redact = Redact::new(Vec::<&str>::new())
+            .with_regex(regex_lite::Regex::new("^(Email|Op)").unwrap());
+        let entry = to_test_entry(redact.process(entry()).unwrap());
+        assert_eq!(entry.values["Email"], "<redacted>");
+        assert_eq!(entry.values["Operation"], "<redacted>");
+    }
+
+    #[test]
+    fn rate_limit_per_sample_group() {
+        let limit = SampleGroupRateLimit::new(2);
+        let state = &limit.state;
+        let group = |operation: &'static str| -> Box<[SampleGroupElement]> {
+            Box::new([(Cow::Borrowed("Operation"), Cow::Borrowed(operation))])
+        };
+        let start = Instant::now();
+        // a burst of one second's worth, then nothing until the bucket refills
+        assert!(state.admit(group("Get"), start));
+        assert!(state.admit(group("Get"), start));
+        assert!(!state.admit(group("Get"), start));
+        // other groups have their own budget
+        assert!(state.admit(group("Put"), start));
+        assert!(state.admit(Box::new([]), start));
+        let half_second = start + Duration::from_millis(500);
+        assert!(state.admit(group("Get"), half_second));
+        assert!(!state.admit(group("Get"), half_second));
+        assert_eq!(limit.entries_dropped(), 2);
+
+        // idle groups are forgotten once they are full again
+        let later = start + Duration::from_secs(5);
+        assert!(state.admit(group("Get"), later));
+        assert_eq!(state.lock_buckets().buckets.len(), 1);
+    }
+
+    #[test]
+    fn processors_run_in_order() {
+        let inner = VecEntrySink::new();
+        let sink = ProcessSink::new(inner.clone())
+            // the static field is added before redaction, so it is redacted too
+            .processor(StaticFields::new(BTreeMap::from([("Region", "us-east-1")])))
+            .processor(Redact::new(["Region"]))
+            .processor(SampleEntries::new(1.0));
+        sink.append(entry());
+        let entry = to_test_entry(inner.drain().pop().unwrap());
+        assert_eq!(entry.values["Region"], "<redacted>");
+        assert_eq!(entry.values["Email"], "jane@example.com");
+    }
+
+    #[test]
+    fn dropped_entries_stop_the_chain() {
+        struct DropAll;
+        impl<E: Entry> EntryProcessor<E> for DropAll {
+            type Output = E;
+            fn process(&self, _entry: E) -> Option<E> {
+                None
+            }
+        }
+
+        let inner = VecEntrySink::<TestEntry>::new();
+        let sink = ProcessSink::new(inner.clone()).processor(DropAll);
+        sink.append(entry());
+        assert!(sink.try_append(entry()).is_ok());
+        assert!(inner.drain().is_empty());
+    }
+}
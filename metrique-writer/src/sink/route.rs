@@ -0,0 +1,192 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{any::Any, borrow::Cow, time::SystemTime};
+
+use metrique_writer_core::{EntryConfig, EntrySink, EntryWriter, Value, config::EntryDestination};
+
+use crate::Entry;
+
+use super::{AppendWait, FlushWait, TryAppendError};
+
+/// An [`EntrySink`] that sends every entry to the sink of its [`EntryDestination`].
+///
+/// This allows a single process to emit entries intended for several CloudWatch accounts,
+/// regions or log groups. Each destination has its own sink, for example writing to a different
+/// file or socket that a CloudWatch Agent configured for that account and region reads from.
+/// Entries are sent to the first route whose destination [matches](EntryDestination::matches)
+/// the destination of the entry, and to the default sink if no route matches or the entry has no
+/// destination.
+///
+/// # Example
+/// ```
+/// # use metrique_writer::{Entry, EntrySink, core::config::EntryDestination};
+/// # use metrique_writer::sink::{DestinationRouter, VecEntrySink};
+/// #[derive(Entry)]
+/// struct TenantMetrics {
+///     #[entry(flatten)]
+///     destination: EntryDestination,
+///     requests: u64,
+/// }
+///
+/// let (default, eu) = (VecEntrySink::new(), VecEntrySink::new());
+/// let sink = DestinationRouter::new(default.clone())
+///     .route(EntryDestination::new().with_region("eu-west-1"), eu.clone());
+///
+/// sink.append(TenantMetrics {
+///     destination: EntryDestination::new().with_account_id("111122223333").with_region("eu-west-1"),
+///     requests: 1,
+/// });
+/// sink.append(TenantMetrics { destination: EntryDestination::new(), requests: 2 });
+/// assert_eq!(eu.drain()[0].requests, 1);
+/// assert_eq!(default.drain()[0].requests, 2);
+/// ```
+#[derive(Clone, Debug)]
+pub struct DestinationRouter<S> {
+    routes: Vec<(EntryDestination, S)>,
+    default: S,
+}
+
+impl<S> DestinationRouter<S> {
+    /// Create a router that sends entries to `default` until routes are added
+    pub fn new(default: S) -> Self {
+        Self {
+            routes: Vec::new(),
+            default,
+        }
+    }
+
+    /// Send entries whose destination matches `destination` to `sink`.
+    ///
+    /// Routes are tried in the order they were added.
+    pub fn route(mut self, destination: EntryDestination, sink: S) -> Self {
+        self.routes.push((destination, sink));
+        self
+    }
+
+    fn sink_for(&self, entry: &impl Entry) -> &S {
+        let mut writer = DestinationWriter(None);
+        entry.write(&mut writer);
+        let Some(destination) = writer.0 else {
+            return &self.default;
+        };
+        self.routes
+            .iter()
+            .find(|(route, _)| route.matches(&destination))
+            .map_or(&self.default, |(_, sink)| sink)
+    }
+}
+
+impl<E: Entry, S: EntrySink<E>> EntrySink<E> for DestinationRouter<S> {
+    fn append(&self, entry: E) {
+        self.sink_for(&entry).append(entry)
+    }
+
+    fn try_append(&self, entry: E) -> Result<(), TryAppendError> {
+        self.sink_for(&entry).try_append(entry)
+    }
+
+    fn append_async(&self, entry: E) -> AppendWait {
+        self.sink_for(&entry).append_async(entry)
+    }
+
+    fn flush_async(&self) -> FlushWait {
+        let waits: Vec<_> = self
+            .routes
+            .iter()
+            .map(|(_, sink)| sink.flush_async())
+            .chain([self.default.flush_async()])
+            .collect();
+        FlushWait::from_future(async move {
+            for wait in waits {
+                wait.await;
+            }
+        })
+    }
+}
+
+/// Extracts the [`EntryDestination`] config of an entry
+struct DestinationWriter(Option<EntryDestination>);
+
+impl<'a> EntryWriter<'a> for DestinationWriter {
+    fn timestamp(&mut self, _timestamp: SystemTime) {}
+
+    fn value(&mut self, _name: impl Into<Cow<'a, str>>, _value: &(impl Value + ?Sized)) {}
+
+    fn config(&mut self, config: &'a dyn EntryConfig) {
+        if let Some(destination) = (config as &dyn Any).downcast_ref::<EntryDestination>() {
+            self.0 = Some(destination.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sink::VecEntrySink;
+
+    struct TestEntry {
+        destination: Option<EntryDestination>,
+        id: u64,
+    }
+
+    impl Entry for TestEntry {
+        fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+            if let Some(destination) = &self.destination {
+                writer.config(destination);
+            }
+            writer.value("Id", &self.id);
+        }
+    }
+
+    fn ids(sink: &VecEntrySink<TestEntry>) -> Vec<u64> {
+        sink.drain().into_iter().map(|entry| entry.id).collect()
+    }
+
+    #[test]
+    fn routes_by_destination() {
+        let (default, account, region) = (
+            VecEntrySink::new(),
+            VecEntrySink::new(),
+            VecEntrySink::new(),
+        );
+        let sink = DestinationRouter::new(default.clone())
+            .route(
+                EntryDestination::new().with_account_id("111111111111"),
+                account.clone(),
+            )
+            .route(
+                EntryDestination::new().with_region("eu-west-1"),
+                region.clone(),
+            );
+
+        for (id, destination) in [
+            None,
+            Some(EntryDestination::new().with_account_id("111111111111")),
+            Some(
+                EntryDestination::new()
+                    .with_account_id("111111111111")
+                    .with_region("eu-west-1"),
+            ),
+            Some(
+                EntryDestination::new()
+                    .with_account_id("222222222222")
+                    .with_region("eu-west-1"),
+            ),
+            Some(EntryDestination::new().with_account_id("222222222222")),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            sink.append(TestEntry {
+                destination,
+                id: id as u64,
+            });
+        }
+
+        assert_eq!(ids(&default), [0, 4]);
+        // the first matching route wins
+        assert_eq!(ids(&account), [1, 2]);
+        assert_eq!(ids(&region), [3]);
+    }
+}
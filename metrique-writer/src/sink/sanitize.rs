@@ -0,0 +1,462 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{borrow::Cow, collections::HashSet, fmt, sync::Arc, time::SystemTime};
+
+use metrique_writer_core::{
+    EntryConfig, EntryWriter, MetricFlags, Observation, Unit, ValidationError, Value, ValueWriter,
+    entry::{Priority, SampleGroupElement},
+};
+use smallvec::SmallVec;
+
+use crate::{CowStr, Entry};
+
+use super::EntryProcessor;
+
+/// The characters and lengths a format accepts in names and dimension values, used by
+/// [`Sanitize`].
+///
+/// Names are the names of properties and metrics, and the classes of metric dimensions. Dimension
+/// values are the instances of metric dimensions, and the values of the properties passed to
+/// [`Sanitize::with_dimensions`].
+#[derive(Clone, Copy)]
+pub struct SanitizeRules {
+    name: fn(usize, char) -> bool,
+    dimension_value: fn(char) -> bool,
+    max_name_len: Option<usize>,
+    max_dimension_value_len: Option<usize>,
+}
+
+impl fmt::Debug for SanitizeRules {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SanitizeRules")
+            .field("max_name_len", &self.max_name_len)
+            .field("max_dimension_value_len", &self.max_dimension_value_len)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SanitizeRules {
+    /// Accept the names whose characters all pass `is_valid`, which is called with the index (in
+    /// characters) and the character. Dimension values and lengths are not constrained until
+    /// changed with the other methods.
+    pub fn new(is_valid: fn(usize, char) -> bool) -> Self {
+        Self {
+            name: is_valid,
+            dimension_value: |_| true,
+            max_name_len: None,
+            max_dimension_value_len: None,
+        }
+    }
+
+    /// The CloudWatch constraints of EMF: names and dimension values are printable ASCII, names
+    /// don't start with `:` and have at most 255 characters, and dimension values have at most
+    /// 1024 characters.
+    pub fn emf() -> Self {
+        Self::new(|index, c| is_printable_ascii(c) && !(index == 0 && c == ':'))
+            .with_dimension_values(is_printable_ascii)
+            .with_max_name_len(255)
+            .with_max_dimension_value_len(1024)
+    }
+
+    /// The Prometheus label name charset, `[a-zA-Z_][a-zA-Z0-9_]*`, for all names, which makes
+    /// them valid metric names too. Dimension (label) values can be any string.
+    pub fn prometheus() -> Self {
+        Self::new(|index, c| {
+            c.is_ascii_alphabetic() || c == '_' || (index > 0 && c.is_ascii_digit())
+        })
+    }
+
+    /// Accept the dimension values whose characters all pass `is_valid`
+    pub fn with_dimension_values(mut self, is_valid: fn(char) -> bool) -> Self {
+        self.dimension_value = is_valid;
+        self
+    }
+
+    /// Accept names of at most `max_len` characters
+    pub fn with_max_name_len(mut self, max_len: usize) -> Self {
+        self.max_name_len = Some(max_len);
+        self
+    }
+
+    /// Accept dimension values of at most `max_len` characters
+    pub fn with_max_dimension_value_len(mut self, max_len: usize) -> Self {
+        self.max_dimension_value_len = Some(max_len);
+        self
+    }
+}
+
+fn is_printable_ascii(c: char) -> bool {
+    c.is_ascii() && !c.is_ascii_control()
+}
+
+/// An [`EntryProcessor`] that makes the names and dimension values of entries valid for a format,
+/// by replacing the characters the format doesn't accept and truncating the strings that are too
+/// long.
+///
+/// Names in `#[metrics]` structs are checked at compile time, but names and dimensions provided
+/// at runtime, like the keys of [`Flex`] fields or the dimensions of a metric, can contain anything.
+/// A format fails the whole entry on a name it can't write, or worse, writes something the backend
+/// rejects later.
+///
+/// Invalid characters are replaced with `_`, unless changed with [`Sanitize::with_replacement`].
+/// With [`Sanitize::strict`], the entry fails validation instead, which is reported like any other
+/// invalid entry.
+///
+/// ```
+/// # use metrique_writer::{Entry, sink::{EntryProcessor, Sanitize, SanitizeRules}};
+/// # use metrique_writer::test_util::to_test_entry;
+/// # use std::collections::BTreeMap;
+/// let tags = BTreeMap::from([("customer tier", "gold"), ("région", "eu-west-3")]);
+/// let entry = to_test_entry(Sanitize::new(SanitizeRules::prometheus()).process(tags).unwrap());
+/// assert_eq!(entry.values["customer_tier"], "gold");
+/// assert_eq!(entry.values["r_gion"], "eu-west-3");
+/// ```
+///
+/// [`Flex`]: https://docs.rs/metrique/latest/metrique/flex/struct.Flex.html
+#[derive(Debug, Clone)]
+pub struct Sanitize {
+    state: Arc<SanitizeState>,
+}
+
+#[derive(Debug, Clone)]
+struct SanitizeState {
+    rules: SanitizeRules,
+    replacement: char,
+    strict: bool,
+    dimensions: HashSet<CowStr>,
+}
+
+impl Sanitize {
+    /// Sanitize entries according to `rules`, for example [`SanitizeRules::emf`]
+    pub fn new(rules: SanitizeRules) -> Self {
+        Self {
+            state: Arc::new(SanitizeState {
+                rules,
+                replacement: '_',
+                strict: false,
+                dimensions: HashSet::new(),
+            }),
+        }
+    }
+
+    /// Replace invalid characters with `replacement` instead of `_`
+    pub fn with_replacement(self, replacement: char) -> Self {
+        self.update(|state| state.replacement = replacement)
+    }
+
+    /// Fail the validation of entries with invalid names or dimension values instead of fixing
+    /// them
+    pub fn strict(self) -> Self {
+        self.update(|state| state.strict = true)
+    }
+
+    /// Also sanitize the values of the string properties called `names` as dimension values, for
+    /// example the properties of EMF dimension sets
+    pub fn with_dimensions(self, names: impl IntoIterator<Item = impl Into<CowStr>>) -> Self {
+        self.update(|state| state.dimensions.extend(names.into_iter().map(Into::into)))
+    }
+
+    fn update(self, f: impl FnOnce(&mut SanitizeState)) -> Self {
+        let mut state = Arc::unwrap_or_clone(self.state);
+        f(&mut state);
+        Self {
+            state: Arc::new(state),
+        }
+    }
+}
+
+impl SanitizeState {
+    fn name<'s>(&self, name: &'s str) -> Result<Cow<'s, str>, ValidationError> {
+        self.sanitize("name", name, self.rules.name, self.rules.max_name_len)
+    }
+
+    fn dimension_value<'s>(&self, value: &'s str) -> Result<Cow<'s, str>, ValidationError> {
+        let is_valid = self.rules.dimension_value;
+        self.sanitize(
+            "dimension value",
+            value,
+            |_, c| is_valid(c),
+            self.rules.max_dimension_value_len,
+        )
+    }
+
+    fn sanitize<'s>(
+        &self,
+        kind: &str,
+        value: &'s str,
+        is_valid: impl Fn(usize, char) -> bool,
+        max_len: Option<usize>,
+    ) -> Result<Cow<'s, str>, ValidationError> {
+        let max_len = max_len.unwrap_or(usize::MAX);
+        let invalid = value
+            .chars()
+            .enumerate()
+            .find(|&(index, c)| index >= max_len || !is_valid(index, c));
+        let Some((index, c)) = invalid else {
+            return Ok(Cow::Borrowed(value));
+        };
+        if self.strict {
+            return Err(if index >= max_len {
+                ValidationError::invalid(format!(
+                    "{kind} `{value}` is longer than {max_len} characters"
+                ))
+            } else {
+                ValidationError::invalid(format!("invalid character {c:?} in {kind} `{value}`"))
+            });
+        }
+        Ok(Cow::Owned(
+            value
+                .chars()
+                .take(max_len)
+                .enumerate()
+                .map(|(index, c)| {
+                    if is_valid(index, c) {
+                        c
+                    } else {
+                        self.replacement
+                    }
+                })
+                .collect(),
+        ))
+    }
+}
+
+impl<E: Entry> EntryProcessor<E> for Sanitize {
+    type Output = Sanitized<E>;
+
+    fn process(&self, entry: E) -> Option<Sanitized<E>> {
+        Some(Sanitized {
+            entry,
+            state: Arc::clone(&self.state),
+        })
+    }
+}
+
+/// An [`Entry`] whose names and dimension values are sanitized, created by [`Sanitize`].
+#[derive(Debug)]
+pub struct Sanitized<E> {
+    entry: E,
+    state: Arc<SanitizeState>,
+}
+
+impl<E> Sanitized<E> {
+    /// Return the original entry
+    pub fn into_inner(self) -> E {
+        self.entry
+    }
+}
+
+impl<E: Entry> Entry for Sanitized<E> {
+    fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+        struct EntryWriterWrapper<'s, W> {
+            writer: W,
+            state: &'s SanitizeState,
+        }
+
+        impl<'a, W: EntryWriter<'a>> EntryWriter<'a> for EntryWriterWrapper<'a, W> {
+            fn timestamp(&mut self, timestamp: SystemTime) {
+                self.writer.timestamp(timestamp);
+            }
+
+            fn value(&mut self, name: impl Into<Cow<'a, str>>, value: &(impl Value + ?Sized)) {
+                let name: Cow<'a, str> = name.into();
+                let dimension = self.state.dimensions.contains(&*name);
+                let sanitized = match self.state.name(&name) {
+                    Ok(Cow::Borrowed(_)) => name,
+                    Ok(Cow::Owned(sanitized)) => Cow::Owned(sanitized),
+                    Err(error) => return self.writer.value(name, &Rejected(error)),
+                };
+                let wrapper = SanitizedValue {
+                    value,
+                    dimension,
+                    state: self.state,
+                };
+                self.writer.value(sanitized, &wrapper)
+            }
+
+            fn config(&mut self, config: &'a dyn EntryConfig) {
+                self.writer.config(config);
+            }
+
+            fn begin_group(
+                &mut self,
+                name: impl Into<Cow<'a, str>>,
+                prefix: impl Into<Cow<'a, str>>,
+            ) {
+                self.writer.begin_group(name, prefix);
+            }
+
+            fn end_group(&mut self) {
+                self.writer.end_group();
+            }
+        }
+
+        self.entry.write(&mut EntryWriterWrapper {
+            writer,
+            state: &self.state,
+        })
+    }
+
+    fn sample_group(&self) -> impl Iterator<Item = SampleGroupElement> {
+        self.entry.sample_group()
+    }
+
+    fn priority(&self) -> Priority {
+        self.entry.priority()
+    }
+}
+
+/// A value that fails validation, written in place of a value with an invalid name
+struct Rejected(ValidationError);
+
+impl Value for Rejected {
+    fn write(&self, writer: impl ValueWriter) {
+        writer.error(self.0.clone())
+    }
+}
+
+/// A sanitized metric dimension, `(class, instance)`
+type Dimension<'a> = (Cow<'a, str>, Cow<'a, str>);
+
+struct SanitizedValue<'v, 's, V: ?Sized> {
+    value: &'v V,
+    // true if the value is a dimension property
+    dimension: bool,
+    state: &'s SanitizeState,
+}
+
+impl<V: Value + ?Sized> Value for SanitizedValue<'_, '_, V> {
+    fn write(&self, writer: impl ValueWriter) {
+        struct ValueWriterWrapper<'s, W> {
+            writer: W,
+            dimension: bool,
+            state: &'s SanitizeState,
+        }
+
+        impl<W: ValueWriter> ValueWriter for ValueWriterWrapper<'_, W> {
+            fn string(self, value: &str) {
+                if !self.dimension {
+                    return self.writer.string(value);
+                }
+                match self.state.dimension_value(value) {
+                    Ok(value) => self.writer.string(&value),
+                    Err(error) => self.writer.error(error),
+                }
+            }
+
+            fn metric<'a>(
+                self,
+                distribution: impl IntoIterator<Item = Observation>,
+                unit: Unit,
+                dimensions: impl IntoIterator<Item = (&'a str, &'a str)>,
+                flags: MetricFlags<'_>,
+            ) {
+                let state = self.state;
+                let dimensions: Result<SmallVec<[Dimension<'_>; 2]>, _> = dimensions
+                    .into_iter()
+                    .map(|(class, instance)| {
+                        Ok((state.name(class)?, state.dimension_value(instance)?))
+                    })
+                    .collect();
+                match dimensions {
+                    Ok(dimensions) => self.writer.metric(
+                        distribution,
+                        unit,
+                        dimensions
+                            .iter()
+                            .map(|(class, instance)| (&**class, &**instance)),
+                        flags,
+                    ),
+                    Err(error) => self.writer.error(error),
+                }
+            }
+
+            fn error(self, error: ValidationError) {
+                self.writer.error(error)
+            }
+        }
+
+        self.value.write(ValueWriterWrapper {
+            writer,
+            dimension: self.dimension,
+            state: self.state,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use metrique_writer_core::{
+        Entry, EntryWriter, MetricFlags, Observation, Unit, Value, ValueWriter,
+    };
+
+    use super::{Sanitize, SanitizeRules};
+    use crate::{sink::EntryProcessor, test_util::to_test_entry};
+
+    struct Latency;
+
+    impl Value for Latency {
+        fn write(&self, writer: impl ValueWriter) {
+            writer.metric(
+                [Observation::Unsigned(5)],
+                Unit::None,
+                [(":Host", "hôte-1")],
+                MetricFlags::empty(),
+            )
+        }
+    }
+
+    struct Request {
+        tenant: String,
+    }
+
+    impl Entry for Request {
+        fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+            writer.value(&*self.tenant, "active");
+            writer.value("Tenant", &*self.tenant);
+            writer.value("Latency", &Latency);
+        }
+    }
+
+    #[test]
+    fn emf_rules_fix_names_and_dimensions() {
+        let sanitize = Sanitize::new(SanitizeRules::emf()).with_dimensions(["Tenant"]);
+        let entry = to_test_entry(
+            sanitize
+                .process(Request {
+                    tenant: "ünicode\n".into(),
+                })
+                .unwrap(),
+        );
+        assert_eq!(entry.values["_nicode_"], "active");
+        assert_eq!(entry.values["Tenant"], "_nicode_");
+        let latency = &entry.metrics["Latency"];
+        assert_eq!(latency.dimensions, [("_Host".into(), "h_te-1".into())]);
+    }
+
+    #[test]
+    fn long_names_are_truncated() {
+        let sanitize =
+            Sanitize::new(SanitizeRules::emf().with_max_name_len(4)).with_replacement('-');
+        let entry = to_test_entry(
+            sanitize
+                .process(BTreeMap::from([(":Operation", "Get")]))
+                .unwrap(),
+        );
+        assert_eq!(entry.values["-Ope"], "Get");
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid character ' ' in name `tier name`")]
+    fn strict_fails_validation() {
+        let sanitize = Sanitize::new(SanitizeRules::prometheus()).strict();
+        to_test_entry(
+            sanitize
+                .process(BTreeMap::from([("tier name", "gold")]))
+                .unwrap(),
+        );
+    }
+}
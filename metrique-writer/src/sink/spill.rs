@@ -0,0 +1,620 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex, MutexGuard,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use metrique_writer_core::{
+    EntrySink, EntryWriter,
+    entry::{Priority, SampleGroupElement},
+    error_handler::{self, PipelineError},
+};
+
+use crate::{Entry, entry::EntrySnapshot};
+
+use super::{AppendWait, FlushWait, TryAppendError};
+
+/// The extension of the files written by [`SpillSink`]
+const SPILL_FILE_EXTENSION: &str = "spill";
+
+/// The size at which [`SpillSink`] starts a new spill file, unless changed with
+/// [`SpillSink::max_file_bytes`]
+pub const DEFAULT_MAX_SPILL_FILE_BYTES: u64 = 16 * 1024 * 1024;
+
+/// The size at which a spill file whose entries are all done is emptied before the next write,
+/// instead of truncating it every time its last entry is done
+const IDLE_TRUNCATE_BYTES: u64 = 64 * 1024;
+
+/// An [`EntrySink`] that writes every entry to a spill file on disk until the wrapped sink is done
+/// with it, so that entries still queued when the process crashes, aborts or is OOM-killed can be
+/// recovered with [`recover_spilled_entries`] and emitted on the next startup.
+///
+/// This is mostly useful in front of a [`BackgroundQueue`](crate::sink::BackgroundQueue): the
+/// telemetry of the moments before a crash is what you need to debug it, and it is exactly what is
+/// sitting in the queue when the process dies.
+///
+/// Each entry is written to the spill file as one line of JSON (see [`EntrySnapshot::to_json`])
+/// before it is forwarded to the wrapped sink as a [`Spilled`] entry. Once the wrapped sink drops
+/// the entry, because it was written or because it was dropped on overflow, it is marked as done.
+/// Spill files are emptied lazily: once all entries of a file are done and it grew past 64 KiB, it
+/// is truncated before the next write. A file is removed once it was rotated out (see
+/// [`SpillSink::max_file_bytes`]) and all of its entries are done, and when the [`SpillSink`] and
+/// all of its entries are dropped. The files of a process that exits normally are therefore gone,
+/// while those of a process that crashed contain the entries that were still queued.
+///
+/// Keep in mind that:
+/// - Spill files are written with plain `write` calls, without `fsync`, so they survive the process
+///   crashing or being killed, but not the machine losing power.
+/// - Spilling happens on the thread that appends the entry: it takes a snapshot of the entry,
+///   serializes it to JSON and writes it to the file with a blocking `write` call, while holding a
+///   lock shared by all clones of the sink. That is a syscall per entry on the request path, which
+///   is fine for request-level metrics but not for high-frequency entries.
+/// - Delivery is at-least-once: entries that were done but not truncated away yet (up to 64 KiB of
+///   them), as well as an entry written just before the crash but not marked as done yet, are
+///   recovered and emitted again.
+/// - Recovered entries are [`EntrySnapshot`]s, which keep the properties, metrics and timestamp of
+///   the original entry, but not its [`EntryConfig`](crate::EntryConfig) or sample group.
+/// - Each process needs its own directory (or must only recover after all other processes using it
+///   exited), since recovering removes every spill file of the directory.
+///
+/// Cloning a [`SpillSink`] shares its spill files between the clones.
+///
+/// # Example
+/// ```
+/// # use metrique_writer::{Entry, EntrySink, sink::{SpillSink, recover_spilled_entries}};
+/// # use metrique_writer::test_util::{TestEntrySink, test_entry_sink};
+/// # let dir = tempfile::tempdir().unwrap();
+/// # let spill_dir = dir.path();
+/// #[derive(Entry)]
+/// struct RequestMetrics {
+///     operation: &'static str,
+/// }
+///
+/// // in a service, this would be a `BackgroundQueue`
+/// let TestEntrySink { inspector, sink } = test_entry_sink();
+///
+/// // on startup, before creating the spill sink, emit what the previous process left behind
+/// for entry in recover_spilled_entries(spill_dir).unwrap() {
+///     sink.append(entry);
+/// }
+///
+/// let sink = SpillSink::new(sink, spill_dir).unwrap();
+/// sink.append(RequestMetrics { operation: "Get" });
+/// assert_eq!(inspector.entries()[0].values["operation"], "Get");
+/// ```
+pub struct SpillSink<S> {
+    sink: S,
+    state: Arc<SpillState>,
+}
+
+struct SpillState {
+    dir: PathBuf,
+    // used as the sink name when reporting errors
+    name: String,
+    max_file_bytes: u64,
+    current: Mutex<Arc<SpillFile>>,
+}
+
+/// A spill file and the entries written to it that are not done yet
+struct SpillFile {
+    path: PathBuf,
+    state: Mutex<SpillFileState>,
+}
+
+struct SpillFileState {
+    file: File,
+    len: u64,
+    // entries written to the file that the wrapped sink didn't drop yet
+    outstanding: u64,
+    // set once the file was rotated out, and won't be written to again
+    rotated: bool,
+}
+
+impl SpillFile {
+    fn create(dir: &Path) -> io::Result<Self> {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let path = dir.join(format!(
+            "{}-{nanos}-{}.{SPILL_FILE_EXTENSION}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = OpenOptions::new()
+            .append(true)
+            .create_new(true)
+            .open(&path)?;
+        Ok(Self {
+            path,
+            state: Mutex::new(SpillFileState {
+                file,
+                len: 0,
+                outstanding: 0,
+                rotated: false,
+            }),
+        })
+    }
+
+    fn lock_state(&self) -> MutexGuard<'_, SpillFileState> {
+        // the state is only changed after the file operations succeed, so it is always consistent
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Write one spilled entry to the file. Returns the new length of the file.
+    fn write(&self, line: &[u8]) -> io::Result<u64> {
+        let mut state = self.lock_state();
+        if state.outstanding == 0 && state.len >= IDLE_TRUNCATE_BYTES {
+            // everything in the file is done, start it over rather than letting it grow until it
+            // is rotated out
+            state.file.set_len(0)?;
+            state.len = 0;
+        }
+        // a single write call, so a crash can at worst leave a partial last line behind
+        state.file.write_all(line)?;
+        state.len += line.len() as u64;
+        state.outstanding += 1;
+        Ok(state.len)
+    }
+
+    fn rotate(&self) {
+        let mut state = self.lock_state();
+        state.rotated = true;
+        if state.outstanding == 0 {
+            drop(state);
+            self.remove();
+        }
+    }
+
+    /// Mark one entry of the file as done
+    fn done(&self) {
+        let mut state = self.lock_state();
+        state.outstanding -= 1;
+        // the current file is emptied lazily on the next write, see `write`
+        if state.outstanding == 0 && state.rotated {
+            drop(state);
+            self.remove();
+        }
+    }
+
+    fn remove(&self) {
+        // a missing file is fine, it only means that somebody recovered it already
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        // all entries hold a reference to their file, so all of them are done by now
+        self.remove();
+    }
+}
+
+impl<S> SpillSink<S> {
+    /// Wrap `sink`, spilling entries to files in `dir` until `sink` is done with them.
+    ///
+    /// `dir` is created if it doesn't exist. Call [`recover_spilled_entries`] on it first to emit
+    /// the entries of a previous process.
+    pub fn new(sink: S, dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        let current = SpillFile::create(&dir)?;
+        Ok(Self {
+            sink,
+            state: Arc::new(SpillState {
+                name: format!("spill:{}", dir.display()),
+                dir,
+                max_file_bytes: DEFAULT_MAX_SPILL_FILE_BYTES,
+                current: Mutex::new(Arc::new(current)),
+            }),
+        })
+    }
+
+    /// Start a new spill file once the current one reaches `max_file_bytes`, defaults to
+    /// [`DEFAULT_MAX_SPILL_FILE_BYTES`].
+    ///
+    /// Spill files are emptied once all of their entries are done, so this only matters when the
+    /// wrapped sink keeps entries for a long time, for example because it is falling behind.
+    /// Spill files are removed once rotated out and done, so this bounds the disk space held by
+    /// entries that are done but share a file with entries that aren't.
+    pub fn max_file_bytes(self, max_file_bytes: u64) -> Self {
+        let state = Arc::unwrap_or_clone(self.state);
+        Self {
+            sink: self.sink,
+            state: Arc::new(SpillState {
+                max_file_bytes,
+                ..state
+            }),
+        }
+    }
+
+    /// Return the wrapped sink
+    pub fn into_inner(self) -> S {
+        self.sink
+    }
+
+    /// Write `entry` to the current spill file. If that fails, the entry is forwarded without
+    /// being spilled.
+    fn spill<E: Entry>(&self, entry: E) -> Spilled<E> {
+        let file = match EntrySnapshot::new(&entry) {
+            Ok(mut snapshot) => {
+                // recovered entries are emitted later, so they need their original timestamp
+                snapshot.timestamp.get_or_insert_with(SystemTime::now);
+                let mut line = snapshot.to_json().to_string().into_bytes();
+                line.push(b'\n');
+                self.state.write(&line)
+            }
+            // entries that fail validation would be rejected by the formatter anyway
+            Err(_) => None,
+        };
+        Spilled { entry, file }
+    }
+}
+
+impl SpillState {
+    fn lock_current(&self) -> MutexGuard<'_, Arc<SpillFile>> {
+        // the current file is only replaced once the new one is created, so it is always valid
+        self.current
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Write `line` to the current spill file, rotating it if it is full. Returns the file.
+    fn write(&self, line: &[u8]) -> Option<Arc<SpillFile>> {
+        let mut current = self.lock_current();
+        let len = match current.write(line) {
+            Ok(len) => len,
+            Err(error) => {
+                self.report_error(&error);
+                return None;
+            }
+        };
+        let file = Arc::clone(&current);
+        if len >= self.max_file_bytes {
+            match SpillFile::create(&self.dir) {
+                Ok(next) => {
+                    *current = Arc::new(next);
+                    file.rotate();
+                }
+                // the line is in the current file, which stays full so the rotation is retried on
+                // the next write
+                Err(error) => self.report_error(&error),
+            }
+        }
+        Some(file)
+    }
+
+    fn report_error(&self, error: &io::Error) {
+        error_handler::report_error(&PipelineError::Io {
+            sink: &self.name,
+            error,
+        });
+    }
+}
+
+impl Clone for SpillState {
+    // used by the consuming builders when the state is shared
+    fn clone(&self) -> Self {
+        Self {
+            dir: self.dir.clone(),
+            name: self.name.clone(),
+            max_file_bytes: self.max_file_bytes,
+            current: Mutex::new(Arc::clone(&self.lock_current())),
+        }
+    }
+}
+
+impl<S: Clone> Clone for SpillSink<S> {
+    fn clone(&self) -> Self {
+        Self {
+            sink: self.sink.clone(),
+            state: Arc::clone(&self.state),
+        }
+    }
+}
+
+impl<S: std::fmt::Debug> std::fmt::Debug for SpillSink<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SpillSink")
+            .field("sink", &self.sink)
+            .field("dir", &self.state.dir)
+            .field("max_file_bytes", &self.state.max_file_bytes)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<E: Entry, S: EntrySink<Spilled<E>>> EntrySink<E> for SpillSink<S> {
+    fn append(&self, entry: E) {
+        self.sink.append(self.spill(entry));
+    }
+
+    fn try_append(&self, entry: E) -> Result<(), TryAppendError> {
+        self.sink.try_append(self.spill(entry))
+    }
+
+    fn append_async(&self, entry: E) -> AppendWait {
+        self.sink.append_async(self.spill(entry))
+    }
+
+    fn flush_async(&self) -> FlushWait {
+        self.sink.flush_async()
+    }
+}
+
+/// An entry that was written to a spill file by [`SpillSink`], and is marked as done in that file
+/// when dropped.
+pub struct Spilled<E> {
+    entry: E,
+    // `None` if the entry couldn't be spilled
+    file: Option<Arc<SpillFile>>,
+}
+
+impl<E> Spilled<E> {
+    /// Returns the wrapped entry
+    pub fn entry(&self) -> &E {
+        &self.entry
+    }
+}
+
+impl<E> Drop for Spilled<E> {
+    fn drop(&mut self) {
+        if let Some(file) = self.file.take() {
+            file.done();
+        }
+    }
+}
+
+impl<E: Entry> Entry for Spilled<E> {
+    fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+        self.entry.write(writer);
+    }
+
+    fn sample_group(&self) -> impl Iterator<Item = SampleGroupElement> {
+        self.entry.sample_group()
+    }
+
+    fn priority(&self) -> Priority {
+        self.entry.priority()
+    }
+}
+
+impl<E: std::fmt::Debug> std::fmt::Debug for Spilled<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Spilled")
+            .field("entry", &self.entry)
+            .field("spilled", &self.file.is_some())
+            .finish()
+    }
+}
+
+/// Read the entries left behind in the spill files of `dir` by a [`SpillSink`] of a previous
+/// process, and remove those files.
+///
+/// Call this on startup, before creating the [`SpillSink`], and append the returned entries to
+/// your sink. Lines that can't be parsed, like the partial last line of a file written while the
+/// process crashed, are skipped. Returns an empty list if `dir` doesn't exist.
+pub fn recover_spilled_entries(dir: impl AsRef<Path>) -> io::Result<Vec<EntrySnapshot>> {
+    let dir = dir.as_ref();
+    let read_dir = match fs::read_dir(dir) {
+        Ok(read_dir) => read_dir,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(error) => return Err(error),
+    };
+    let mut paths = vec![];
+    for dir_entry in read_dir {
+        let path = dir_entry?.path();
+        if path
+            .extension()
+            .is_some_and(|ext| ext == SPILL_FILE_EXTENSION)
+        {
+            paths.push(path);
+        }
+    }
+    // file names start with the pid and creation time, so this recovers roughly in order
+    paths.sort();
+
+    let mut entries = vec![];
+    for path in paths {
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            // recovered concurrently, or removed by the process that wrote it
+            Err(error) if error.kind() == io::ErrorKind::NotFound => continue,
+            Err(error) => return Err(error),
+        };
+        for line in BufReader::new(file).split(b'\n') {
+            let line = line?;
+            if let Some(snapshot) = serde_json::from_slice(&line)
+                .ok()
+                .and_then(|json| EntrySnapshot::from_json(&json))
+            {
+                entries.push(snapshot);
+            }
+        }
+        match fs::remove_file(&path) {
+            Ok(()) => {}
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::sink::VecEntrySink;
+
+    struct Request {
+        operation: &'static str,
+        timestamp: Option<SystemTime>,
+    }
+
+    impl Entry for Request {
+        fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+            if let Some(timestamp) = self.timestamp {
+                writer.timestamp(timestamp);
+            }
+            writer.value("Operation", self.operation);
+        }
+    }
+
+    fn request(operation: &'static str) -> Request {
+        Request {
+            operation,
+            timestamp: Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+        }
+    }
+
+    fn operations(entries: &[EntrySnapshot]) -> Vec<&str> {
+        entries
+            .iter()
+            .map(|entry| entry.properties["Operation"].as_str())
+            .collect()
+    }
+
+    #[test]
+    fn recovers_entries_that_were_not_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let inner = VecEntrySink::new();
+        let sink = SpillSink::new(inner.clone(), dir.path()).unwrap();
+        sink.append(request("Get"));
+        sink.append(request("Put"));
+        sink.append(Request {
+            operation: "List",
+            timestamp: None,
+        });
+
+        // simulate a crash: the queued entries are never dropped
+        let queued = inner.drain();
+        std::mem::forget(queued);
+        std::mem::forget(sink);
+
+        let recovered = recover_spilled_entries(dir.path()).unwrap();
+        assert_eq!(operations(&recovered), ["Get", "Put", "List"]);
+        assert_eq!(
+            recovered[0].timestamp,
+            Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+        );
+        // entries without a timestamp are stamped when spilled
+        assert!(recovered[2].timestamp.is_some());
+        // the spill files are removed
+        assert!(recover_spilled_entries(dir.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn empties_spill_files_once_entries_are_done() {
+        let dir = tempfile::tempdir().unwrap();
+        let inner = VecEntrySink::new();
+        let sink = SpillSink::new(inner.clone(), dir.path()).unwrap();
+        let path = fs::read_dir(dir.path())
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        while fs::metadata(&path).unwrap().len() < IDLE_TRUNCATE_BYTES {
+            sink.append(request("Get"));
+            drop(inner.drain());
+        }
+        sink.append(request("Put"));
+
+        std::mem::forget(inner.drain());
+        std::mem::forget(sink);
+        let recovered = recover_spilled_entries(dir.path()).unwrap();
+        assert_eq!(operations(&recovered), ["Put"]);
+    }
+
+    #[test]
+    fn removes_rotated_spill_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let inner = VecEntrySink::new();
+        let sink = SpillSink::new(inner.clone(), dir.path())
+            .unwrap()
+            .max_file_bytes(1);
+        let spill_files = || fs::read_dir(dir.path()).unwrap().count();
+        sink.append(request("Get"));
+        sink.append(request("Put"));
+        // one rotated file per entry, plus the current one
+        assert_eq!(spill_files(), 3);
+
+        let mut queued = inner.drain();
+        queued.truncate(1);
+        assert_eq!(spill_files(), 2);
+        drop(queued);
+        assert_eq!(spill_files(), 1);
+        drop(sink);
+        assert_eq!(spill_files(), 0);
+    }
+
+    #[test]
+    fn keeps_spilled_entries_when_rotation_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let spill_dir = dir.path().join("spill");
+        let inner = VecEntrySink::new();
+        let sink = SpillSink::new(inner.clone(), &spill_dir)
+            .unwrap()
+            .max_file_bytes(1);
+        // the current file stays open, but no new one can be created
+        fs::remove_dir_all(&spill_dir).unwrap();
+        sink.append(request("Get"));
+        let first = inner.drain().pop().unwrap();
+        let file = first.file.clone().expect("the entry was spilled");
+        assert!(Arc::ptr_eq(&file, &sink.state.lock_current()));
+        assert_eq!(file.lock_state().outstanding, 1);
+
+        // the rotation is retried on the next write
+        fs::create_dir(&spill_dir).unwrap();
+        sink.append(request("Put"));
+        let second = inner.drain().pop().unwrap();
+        assert!(Arc::ptr_eq(second.file.as_ref().unwrap(), &file));
+        assert!(!Arc::ptr_eq(&file, &sink.state.lock_current()));
+        assert_eq!(file.lock_state().outstanding, 2);
+
+        drop((first, second));
+        assert_eq!(file.lock_state().outstanding, 0);
+        assert!(file.lock_state().rotated);
+    }
+
+    #[test]
+    fn skips_partial_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let inner = VecEntrySink::new();
+        let sink = SpillSink::new(inner.clone(), dir.path()).unwrap();
+        sink.append(request("Get"));
+        std::mem::forget(inner.drain());
+        std::mem::forget(sink);
+
+        let path = fs::read_dir(dir.path())
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(br#"{"Properties":{"Operation":"Pu"#)
+            .unwrap();
+
+        let recovered = recover_spilled_entries(dir.path()).unwrap();
+        assert_eq!(operations(&recovered), ["Get"]);
+    }
+
+    #[test]
+    fn recovering_a_missing_directory_is_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let recovered = recover_spilled_entries(dir.path().join("missing")).unwrap();
+        assert!(recovered.is_empty());
+    }
+}
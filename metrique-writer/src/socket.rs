@@ -0,0 +1,449 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Line-protocol destinations that ship formatted entries over a TCP or Unix domain socket.
+//!
+//! This is meant for sidecar-agent architectures, where a local agent (such as the CloudWatch
+//! agent, Vector, or Fluent Bit) accepts newline-delimited entries on a socket. A [`SocketWriter`]
+//! is an [`io::Write`] destination for [`FormatExt::output_to`], so it works with any
+//! line-based format, such as EMF or JSON lines.
+//!
+//! ```no_run
+//! use metrique_writer::{BoxEntry, FormatExt, sink::BackgroundQueue, socket::SocketWriter};
+//! use metrique_writer_format_emf::Emf;
+//!
+//! let stream = Emf::all_validations("MyApp".into(), vec![vec![]])
+//!     .output_to(SocketWriter::tcp("127.0.0.1:25888"));
+//! let (queue, _join) = BackgroundQueue::<BoxEntry>::new(stream);
+//! ```
+//!
+//! [`FormatExt::output_to`]: crate::FormatExt::output_to
+
+use std::{
+    fmt, io,
+    net::{TcpStream, ToSocketAddrs},
+    time::{Duration, Instant},
+};
+
+#[cfg(unix)]
+use std::{os::unix::net::UnixStream, path::PathBuf};
+
+use crate::compress::{Compression, DEFAULT_MAX_BATCH_SIZE};
+
+/// The default minimum delay between reconnection attempts, see [`SocketWriter::with_backoff`]
+pub const DEFAULT_MIN_BACKOFF: Duration = Duration::from_millis(100);
+/// The default maximum delay between reconnection attempts, see [`SocketWriter::with_backoff`]
+pub const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// The default write timeout, see [`SocketWriter::with_write_timeout`]
+pub const DEFAULT_WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+/// The default TCP connect timeout, see [`SocketWriter::with_connect_timeout`]
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// An [`io::Write`] that sends complete lines to a TCP or Unix domain socket, reconnecting with
+/// exponential backoff when the connection fails.
+///
+/// Written bytes are buffered until a newline, and only complete lines are sent, so a reconnection
+/// never results in a torn entry on the new connection. If sending a line fails, the connection is
+/// dropped along with the complete lines that were being sent, and the next write tries to
+/// reconnect once the backoff delay has elapsed. An incomplete line is kept, to be sent once it is
+/// complete. Since the written bytes were already buffered, the write that failed to send them
+/// still succeeds, and the error is returned by the next [`flush`](io::Write::flush), or by the
+/// next write that starts a new line, so that it is reported by the sink. Sending during the
+/// backoff delay fails immediately with [`io::ErrorKind::NotConnected`] rather than blocking.
+///
+/// The connection is established lazily, on the first write.
+pub struct SocketWriter {
+    address: Address,
+    connection: Option<Connection>,
+    pending: Vec<u8>,
+    /// The error of the last failed send, returned by the next write or flush
+    error: Option<io::Error>,
+    min_backoff: Duration,
+    max_backoff: Duration,
+    write_timeout: Option<Duration>,
+    connect_timeout: Duration,
+    compression: Compression,
+    backoff: Duration,
+    next_attempt: Option<Instant>,
+    reconnects: u64,
+}
+
+#[derive(Debug, Clone)]
+enum Address {
+    Tcp(String),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+enum Connection {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl SocketWriter {
+    /// Send lines to the TCP socket at `address`, for example `"127.0.0.1:25888"`.
+    ///
+    /// The address is resolved on every connection attempt, so DNS names follow changes in the
+    /// resolved address when reconnecting.
+    pub fn tcp(address: impl Into<String>) -> Self {
+        Self::new(Address::Tcp(address.into()))
+    }
+
+    /// Send lines to the Unix domain (stream) socket at `path`.
+    #[cfg(unix)]
+    pub fn unix(path: impl Into<PathBuf>) -> Self {
+        Self::new(Address::Unix(path.into()))
+    }
+
+    fn new(address: Address) -> Self {
+        Self {
+            address,
+            connection: None,
+            pending: Vec::new(),
+            error: None,
+            min_backoff: DEFAULT_MIN_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+            write_timeout: Some(DEFAULT_WRITE_TIMEOUT),
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            compression: Compression::None,
+            backoff: DEFAULT_MIN_BACKOFF,
+            next_attempt: None,
+            reconnects: 0,
+        }
+    }
+
+    /// Set the delay between reconnection attempts. The delay starts at `min`, doubles after every
+    /// failed attempt up to `max`, and goes back to `min` once a connection succeeds.
+    ///
+    /// Defaults to [`DEFAULT_MIN_BACKOFF`] and [`DEFAULT_MAX_BACKOFF`].
+    pub fn with_backoff(mut self, min: Duration, max: Duration) -> Self {
+        self.min_backoff = min;
+        self.max_backoff = max.max(min);
+        self.backoff = min;
+        self
+    }
+
+    /// Set the timeout for sending a line, after which the connection is considered failed.
+    /// `None` blocks until the peer accepts the data.
+    ///
+    /// Defaults to [`DEFAULT_WRITE_TIMEOUT`].
+    pub fn with_write_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.write_timeout = timeout;
+        self
+    }
+
+    /// Set the timeout for establishing a TCP connection, per resolved address. Connecting to a
+    /// Unix domain socket doesn't wait for the peer.
+    ///
+    /// Defaults to [`DEFAULT_CONNECT_TIMEOUT`].
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Compress the lines sent to the socket, see [`crate::compress`].
+    ///
+    /// With compression, complete lines are buffered until the writer is flushed (or until
+    /// [`DEFAULT_MAX_BATCH_SIZE`] bytes are buffered), and then sent as one gzip member or zstd
+    /// frame, so the peer must accept a compressed stream. When sending a batch fails, all the
+    /// lines in the batch are dropped.
+    ///
+    /// Defaults to [`Compression::None`].
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Connect eagerly, rather than on the first write.
+    pub fn connect(mut self) -> io::Result<Self> {
+        self.ensure_connected()?;
+        Ok(self)
+    }
+
+    /// Returns true if the writer currently has an open connection
+    pub fn is_connected(&self) -> bool {
+        self.connection.is_some()
+    }
+
+    /// The number of times a connection was re-established after a failure
+    pub fn reconnects(&self) -> u64 {
+        self.reconnects
+    }
+
+    fn ensure_connected(&mut self) -> io::Result<&mut Connection> {
+        if self.connection.is_none() {
+            let now = Instant::now();
+            if self.next_attempt.is_some_and(|next| now < next) {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotConnected,
+                    format!("waiting to reconnect to {}", self.address),
+                ));
+            }
+            match self
+                .address
+                .connect(self.connect_timeout, self.write_timeout)
+            {
+                Ok(connection) => {
+                    if self.next_attempt.is_some() {
+                        self.reconnects += 1;
+                    }
+                    self.connection = Some(connection);
+                    self.backoff = self.min_backoff;
+                    self.next_attempt = None;
+                }
+                Err(err) => {
+                    self.schedule_reconnect(now);
+                    return Err(err);
+                }
+            }
+        }
+        Ok(self.connection.as_mut().unwrap())
+    }
+
+    fn schedule_reconnect(&mut self, now: Instant) {
+        self.connection = None;
+        self.next_attempt = Some(now + self.backoff);
+        self.backoff = self.backoff.saturating_mul(2).min(self.max_backoff);
+    }
+
+    /// Send all the complete lines in `pending`
+    fn send_complete_lines(&mut self) -> io::Result<()> {
+        let Some(last_newline) = self.pending.iter().rposition(|b| *b == b'\n') else {
+            return Ok(());
+        };
+        // on failure, drop the lines rather than retrying them, to keep memory bounded
+        let lines: Vec<u8> = self.pending.drain(..=last_newline).collect();
+        let lines = if self.compression == Compression::None {
+            lines
+        } else {
+            self.compression.compress(&lines)?
+        };
+        let result = self
+            .ensure_connected()
+            .and_then(|connection| connection.write_all(&lines));
+        if result.is_err() && self.connection.is_some() {
+            self.schedule_reconnect(Instant::now());
+        }
+        result
+    }
+}
+
+impl io::Write for SocketWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // report a failed send before taking a new line, never in the middle of one, so that the
+        // rest of the line isn't dropped after its start was buffered
+        if self.pending.last().is_none_or(|b| *b == b'\n')
+            && let Some(err) = self.error.take()
+        {
+            return Err(err);
+        }
+        self.pending.extend_from_slice(buf);
+        // compressed lines are sent in batches, on flush
+        let send = if self.compression == Compression::None {
+            buf.contains(&b'\n')
+        } else {
+            self.pending.len() >= DEFAULT_MAX_BATCH_SIZE
+        };
+        if send {
+            // only the complete lines are dropped on failure, an incomplete line is kept so the
+            // next line doesn't start mid-entry. `buf` is buffered either way, so the error is
+            // returned later.
+            if let Err(err) = self.send_complete_lines() {
+                self.error = Some(err);
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+        if self.compression != Compression::None {
+            self.send_complete_lines()?;
+        }
+        match &mut self.connection {
+            Some(connection) => connection.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl fmt::Debug for SocketWriter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SocketWriter")
+            .field("address", &self.address)
+            .field("connected", &self.is_connected())
+            .field("reconnects", &self.reconnects)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Address::Tcp(address) => write!(f, "tcp://{address}"),
+            #[cfg(unix)]
+            Address::Unix(path) => write!(f, "unix://{}", path.display()),
+        }
+    }
+}
+
+impl Address {
+    fn connect(
+        &self,
+        connect_timeout: Duration,
+        write_timeout: Option<Duration>,
+    ) -> io::Result<Connection> {
+        match self {
+            Address::Tcp(address) => {
+                let mut last_err = None;
+                for addr in address.to_socket_addrs()? {
+                    match TcpStream::connect_timeout(&addr, connect_timeout) {
+                        Ok(stream) => {
+                            stream.set_nodelay(true)?;
+                            stream.set_write_timeout(write_timeout)?;
+                            return Ok(Connection::Tcp(stream));
+                        }
+                        Err(err) => last_err = Some(err),
+                    }
+                }
+                Err(last_err.unwrap_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("`{address}` did not resolve to any address"),
+                    )
+                }))
+            }
+            #[cfg(unix)]
+            Address::Unix(path) => {
+                let stream = UnixStream::connect(path)?;
+                stream.set_write_timeout(write_timeout)?;
+                Ok(Connection::Unix(stream))
+            }
+        }
+    }
+}
+
+impl Connection {
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        use io::Write;
+        match self {
+            Connection::Tcp(stream) => stream.write_all(buf),
+            #[cfg(unix)]
+            Connection::Unix(stream) => stream.write_all(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        use io::Write;
+        match self {
+            Connection::Tcp(stream) => stream.flush(),
+            #[cfg(unix)]
+            Connection::Unix(stream) => stream.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    #[test]
+    fn sends_complete_lines_over_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut writer = SocketWriter::tcp(listener.local_addr().unwrap().to_string());
+        writer.write_all(b"{\"a\":").unwrap();
+        // nothing is sent until the line is complete, so no connection yet
+        assert!(!writer.is_connected());
+        writer.write_all(b"1}\n{\"b\":2}\n{\"c\"").unwrap();
+        writer.flush().unwrap();
+
+        let (peer, _) = listener.accept().unwrap();
+        let mut lines = BufReader::new(peer).lines();
+        assert_eq!(lines.next().unwrap().unwrap(), "{\"a\":1}");
+        assert_eq!(lines.next().unwrap().unwrap(), "{\"b\":2}");
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn sends_compressed_batches_on_flush() {
+        use std::io::Read;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut writer = SocketWriter::tcp(listener.local_addr().unwrap().to_string())
+            .with_compression(Compression::gzip());
+        writer.write_all(b"{\"a\":1}\n{\"b\":2}\n").unwrap();
+        // complete lines are held until the flush
+        assert!(!writer.is_connected());
+        writer.flush().unwrap();
+        writer.write_all(b"{\"c\":3}\n").unwrap();
+        writer.flush().unwrap();
+        drop(writer);
+
+        let (peer, _) = listener.accept().unwrap();
+        let mut output = String::new();
+        flate2::read::MultiGzDecoder::new(peer)
+            .read_to_string(&mut output)
+            .unwrap();
+        assert_eq!(output, "{\"a\":1}\n{\"b\":2}\n{\"c\":3}\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn reconnects_over_unix_socket() {
+        use std::os::unix::net::UnixListener;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("agent.sock");
+        let mut writer = SocketWriter::unix(&path).with_backoff(Duration::ZERO, Duration::ZERO);
+
+        // no listener yet: the complete line is dropped and the error reported by the flush, but
+        // the start of the next line is kept
+        assert_eq!(writer.write(b"lost\nfir").unwrap(), 8);
+        assert!(!writer.is_connected());
+        assert!(writer.flush().is_err());
+        writer.flush().unwrap();
+
+        let listener = UnixListener::bind(&path).unwrap();
+        writer.write_all(b"st\n").unwrap();
+        assert_eq!(writer.reconnects(), 1);
+        let (peer, _) = listener.accept().unwrap();
+        let mut lines = BufReader::new(peer).lines();
+        assert_eq!(lines.next().unwrap().unwrap(), "first");
+    }
+
+    #[test]
+    fn waits_for_backoff_before_reconnecting() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        drop(listener);
+
+        let mut writer =
+            SocketWriter::tcp(address).with_backoff(Duration::from_secs(3600), Duration::MAX);
+        assert_eq!(writer.write(b"a\n").unwrap(), 2);
+        // the next line isn't taken while the failure to send `a` is reported
+        let err = writer.write(b"b\n").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::ConnectionRefused);
+        assert_eq!(writer.write(b"b\n").unwrap(), 2);
+        let err = writer.flush().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotConnected);
+    }
+
+    #[test]
+    fn reports_failed_sends_between_lines() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        drop(listener);
+
+        let mut writer = SocketWriter::tcp(address).with_backoff(Duration::ZERO, Duration::ZERO);
+        assert_eq!(writer.write(b"a\nb").unwrap(), 3);
+        // the error isn't returned in the middle of `b`, which would tear it
+        assert_eq!(writer.write(b"c").unwrap(), 1);
+        assert!(writer.write(b"\n").is_ok());
+        assert!(writer.write(b"d\n").is_err());
+    }
+}
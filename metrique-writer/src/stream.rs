@@ -8,7 +8,10 @@ use std::{collections::HashSet, io};
 use metrique_writer_core::{Entry, config::MetriqueValidationError};
 use smallvec::SmallVec;
 
-use crate::{CowStr, entry::WithGlobalDimensions};
+use crate::{
+    CowStr,
+    entry::{CardinalityGuard, WithCardinalityGuard, WithGlobalDimensions},
+};
 
 pub use metrique_writer_core::{EntryIoStream, IoStreamError};
 
@@ -105,6 +108,42 @@ pub trait EntryIoStreamExt: EntryIoStream {
         }
     }
 
+    /// Limit the number of distinct values of the dimension keys tracked by `guard`, replacing new
+    /// values past the limit with an overflow value. See [`CardinalityGuard`].
+    ///
+    /// There is intentionally both a [`EntryIoStreamExt::guard_cardinality`] and a
+    /// [`FormatExt::guard_cardinality`], which implement exactly the same functionality,
+    /// to allow using in interfaces that accept an [`EntryIoStream`] as well as interfaces
+    /// that accept a [`Format`].
+    ///
+    /// ```
+    /// # use metrique_writer::{
+    /// #    EntryIoStream,
+    /// #    EntryIoStreamExt as _,
+    /// #    entry::CardinalityGuard,
+    /// #    format::{FormatExt as _},
+    /// # };
+    /// # use metrique_writer_format_emf::Emf;
+    /// # use std::io;
+    /// fn set_up_emf(out: impl io::Write) -> impl EntryIoStream {
+    ///     Emf::all_validations("MyApp".into(), vec![vec!["Customer".into()]])
+    ///         .output_to(out)
+    ///         .guard_cardinality(CardinalityGuard::new(["Customer"], 1000))
+    /// }
+    /// ```
+    ///
+    /// [`Format`]: crate::format::Format
+    /// [`FormatExt::guard_cardinality`]: crate::format::FormatExt::guard_cardinality
+    fn guard_cardinality(self, guard: CardinalityGuard) -> GuardCardinality<Self>
+    where
+        Self: Sized,
+    {
+        GuardCardinality {
+            stream: self,
+            guard,
+        }
+    }
+
     /// See [`tee()`].
     fn tee<S>(self, other: S) -> Tee<Self, S>
     where
@@ -176,6 +215,13 @@ impl<S1: EntryIoStream, S2: EntryIoStream> EntryIoStream for Tee<S1, S2> {
         let r2 = self.s2.flush();
         r1.and(r2)
     }
+
+    fn bytes_written(&self) -> Option<u64> {
+        match (self.s1.bytes_written(), self.s2.bytes_written()) {
+            (None, None) => None,
+            (b1, b2) => Some(b1.unwrap_or(0) + b2.unwrap_or(0)),
+        }
+    }
 }
 
 /// See [`EntryIoStreamExt::merge_globals`] or [`FormatExt::merge_globals`].
@@ -195,6 +241,10 @@ impl<S: EntryIoStream, G: Entry> EntryIoStream for MergeGlobals<S, G> {
     fn flush(&mut self) -> io::Result<()> {
         self.stream.flush()
     }
+
+    fn bytes_written(&self) -> Option<u64> {
+        self.stream.bytes_written()
+    }
 }
 
 /// See [`EntryIoStreamExt::merge_global_dimensions`] or [`FormatExt::merge_global_dimensions`].
@@ -225,6 +275,41 @@ impl<S: EntryIoStream, const N: usize> EntryIoStream for MergeGlobalDimensions<S
     fn flush(&mut self) -> io::Result<()> {
         self.stream.flush()
     }
+
+    fn bytes_written(&self) -> Option<u64> {
+        self.stream.bytes_written()
+    }
+}
+
+/// See [`EntryIoStreamExt::guard_cardinality`] or [`FormatExt::guard_cardinality`].
+///
+/// [`FormatExt::guard_cardinality`]: crate::format::FormatExt::guard_cardinality
+#[derive(Debug, Clone)]
+pub struct GuardCardinality<S> {
+    pub(crate) stream: S,
+    pub(crate) guard: CardinalityGuard,
+}
+
+impl<S> GuardCardinality<S> {
+    /// The guard used by this stream
+    pub fn guard(&self) -> &CardinalityGuard {
+        &self.guard
+    }
+}
+
+impl<S: EntryIoStream> EntryIoStream for GuardCardinality<S> {
+    fn next(&mut self, entry: &impl Entry) -> Result<(), IoStreamError> {
+        self.stream
+            .next(&WithCardinalityGuard::new(entry, &self.guard))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+
+    fn bytes_written(&self) -> Option<u64> {
+        self.stream.bytes_written()
+    }
 }
 
 /// An EntryIoStream that drops all entries sent to it
@@ -8,19 +8,24 @@
 //! For usage examples, see [`test_entry_sink`] and `examples/testing.rs`
 
 use std::{
-    collections::HashMap,
+    any::Any,
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt,
     sync::{Arc, Mutex},
-    time::SystemTime,
+    time::{Duration, SystemTime},
 };
 
 use metrique_core::{CloseEntry, InflectableEntry};
 use metrique_writer_core::{
     MetricFlags,
-    entry::SampleGroupElement,
+    config::EntryDimensions,
+    entry::{Priority, SampleGroupElement},
     value::{FlagConstructor, ForceFlag, MetricOptions},
 };
 use ordered_float::OrderedFloat;
 
+pub mod conformance;
+
 use crate::{
     AnyEntrySink, BoxEntrySink, Entry, EntryWriter, Observation, Unit, ValueWriter, format::Format,
     sink::FlushWait,
@@ -112,6 +117,242 @@ impl TestEntry {
             metrics: Default::default(),
         }
     }
+
+    /// Render this entry as deterministic, human-readable text, meant for snapshot tests
+    /// (for example with `insta::assert_snapshot!`).
+    ///
+    /// Properties and metrics are sorted by name, floating point observations that are whole
+    /// numbers are rendered without a fractional part, and the timestamp is redacted so that
+    /// snapshots don't change from run to run. Use [`TestEntry::render_with`] to render the
+    /// timestamp differently.
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use metrique_writer::{Entry, test_util::to_test_entry};
+    /// #[derive(Entry)]
+    /// struct MyEntry {
+    ///     operation: &'static str,
+    ///     latency: Duration,
+    ///     ratio: f64,
+    /// }
+    ///
+    /// let entry = to_test_entry(MyEntry {
+    ///     operation: "Get",
+    ///     latency: Duration::from_millis(5),
+    ///     ratio: 0.5,
+    /// });
+    /// assert_eq!(
+    ///     entry.render(),
+    ///     "\
+    /// properties:
+    ///   operation: \"Get\"
+    /// metrics:
+    ///   latency: 5 Milliseconds
+    ///   ratio: 0.5
+    /// "
+    /// );
+    /// ```
+    pub fn render(&self) -> String {
+        self.render_with(RenderTimestamp::Redacted)
+    }
+
+    /// Like [`TestEntry::render`], with the given handling of the timestamp
+    pub fn render_with(&self, timestamp: RenderTimestamp) -> String {
+        use std::fmt::Write;
+
+        let mut out = String::new();
+        match (self.timestamp, timestamp) {
+            (None, _) | (Some(_), RenderTimestamp::Omitted) => {}
+            (Some(_), RenderTimestamp::Redacted) => out.push_str("timestamp: [redacted]\n"),
+            (Some(ts), RenderTimestamp::EpochMillis) => {
+                let millis = ts
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .map_or(0, |d| d.as_millis());
+                writeln!(out, "timestamp: {millis}").unwrap();
+            }
+        }
+        if !self.values.is_empty() {
+            out.push_str("properties:\n");
+            for (name, value) in self.values.iter().collect::<BTreeMap<_, _>>() {
+                writeln!(out, "  {name}: {value:?}").unwrap();
+            }
+        }
+        if !self.metrics.is_empty() {
+            out.push_str("metrics:\n");
+            for (name, metric) in self.metrics.iter().collect::<BTreeMap<_, _>>() {
+                writeln!(out, "  {name}: {}", render_metric(metric)).unwrap();
+            }
+        }
+        out
+    }
+}
+
+fn render_metric(metric: &Metric) -> String {
+    use std::fmt::Write;
+
+    let mut out = String::new();
+    match &metric.distribution[..] {
+        [observation] => out.push_str(&render_observation(observation)),
+        distribution => {
+            let rendered: Vec<_> = distribution.iter().map(render_observation).collect();
+            write!(out, "[{}]", rendered.join(", ")).unwrap();
+        }
+    }
+    if metric.unit != Unit::None {
+        write!(out, " {}", metric.unit).unwrap();
+    }
+    if !metric.dimensions.is_empty() {
+        let dimensions: Vec<_> = metric
+            .dimensions
+            .iter()
+            .map(|(key, value)| format!("{key}={value}"))
+            .collect();
+        write!(out, " {{{}}}", dimensions.join(", ")).unwrap();
+    }
+    if metric.test_flag {
+        out.push_str(" [test_flag]");
+    }
+    out
+}
+
+/// Panics unless `actual` and `expected` are equal, listing only the fields that differ.
+///
+/// Unlike `assert_eq!`, which prints the debug output of both entries, the panic message has one
+/// line per differing property, metric or timestamp. Metrics are shown with their unit and
+/// dimensions (like in [`TestEntry::render`]), and a metric whose unit differs is called out, so
+/// that a `5 Milliseconds` that became `5000 Microseconds` is easy to spot.
+///
+/// ```should_panic
+/// # use std::time::Duration;
+/// # use metrique_writer::{Entry, test_util::{assert_entries_eq, to_test_entry}};
+/// #[derive(Entry)]
+/// struct RequestMetrics {
+///     operation: &'static str,
+///     latency: Duration,
+/// }
+///
+/// let actual = to_test_entry(RequestMetrics {
+///     operation: "Get",
+///     latency: Duration::from_millis(5),
+/// });
+/// let expected = to_test_entry(RequestMetrics {
+///     operation: "Put",
+///     latency: Duration::from_millis(5),
+/// });
+/// // panics with:
+/// // entries differ (1 difference):
+/// //   property `operation`: "Get", expected "Put"
+/// assert_entries_eq(&actual, &expected);
+/// ```
+#[track_caller]
+pub fn assert_entries_eq(actual: &TestEntry, expected: &TestEntry) {
+    let differences = entry_differences(actual, expected);
+    if !differences.is_empty() {
+        panic!(
+            "entries differ ({} difference{}):\n  {}",
+            differences.len(),
+            if differences.len() == 1 { "" } else { "s" },
+            differences.join("\n  ")
+        );
+    }
+}
+
+fn entry_differences(actual: &TestEntry, expected: &TestEntry) -> Vec<String> {
+    let mut differences = Vec::new();
+    if actual.timestamp != expected.timestamp {
+        let render = |timestamp: Option<SystemTime>| match timestamp {
+            None => "none".to_owned(),
+            Some(timestamp) => match timestamp.duration_since(SystemTime::UNIX_EPOCH) {
+                Ok(since_epoch) => format!("{}ms since epoch", since_epoch.as_millis()),
+                Err(_) => format!("{timestamp:?}"),
+            },
+        };
+        differences.push(format!(
+            "timestamp: {}, expected {}",
+            render(actual.timestamp),
+            render(expected.timestamp)
+        ));
+    }
+    let names: BTreeSet<_> = actual.values.keys().chain(expected.values.keys()).collect();
+    for name in names {
+        match (actual.values.get(name), expected.values.get(name)) {
+            (Some(actual), Some(expected)) if actual != expected => differences.push(format!(
+                "property `{name}`: {actual:?}, expected {expected:?}"
+            )),
+            (Some(actual), None) => {
+                differences.push(format!("property `{name}`: {actual:?}, not expected"))
+            }
+            (None, Some(expected)) => {
+                differences.push(format!("property `{name}`: missing, expected {expected:?}"))
+            }
+            _ => {}
+        }
+    }
+    let names: BTreeSet<_> = actual
+        .metrics
+        .keys()
+        .chain(expected.metrics.keys())
+        .collect();
+    for name in names {
+        match (actual.metrics.get(name), expected.metrics.get(name)) {
+            (Some(actual), Some(expected)) if actual != expected => {
+                let mut difference = format!(
+                    "metric `{name}`: {}, expected {}",
+                    render_metric(actual),
+                    render_metric(expected)
+                );
+                if actual.unit != expected.unit {
+                    difference.push_str(&format!(
+                        " (unit is {}, expected {})",
+                        actual.unit, expected.unit
+                    ));
+                }
+                differences.push(difference);
+            }
+            (Some(actual), None) => differences.push(format!(
+                "metric `{name}`: {}, not expected",
+                render_metric(actual)
+            )),
+            (None, Some(expected)) => differences.push(format!(
+                "metric `{name}`: missing, expected {}",
+                render_metric(expected)
+            )),
+            _ => {}
+        }
+    }
+    differences
+}
+
+/// How [`TestEntry::render_with`] renders the timestamp of an entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RenderTimestamp {
+    /// Render `timestamp: [redacted]` if the entry has a timestamp
+    Redacted,
+    /// Do not render the timestamp
+    Omitted,
+    /// Render the timestamp as milliseconds since the Unix epoch
+    EpochMillis,
+}
+
+fn render_observation(observation: &Observation) -> String {
+    match observation {
+        Observation::Unsigned(v) => v.to_string(),
+        Observation::Repeated { total, occurrences } => {
+            format!("{} over {occurrences}", render_f64(*total))
+        }
+        single => render_f64(single.total()),
+    }
+}
+
+// render whole numbers without a fractional part, so that a value does not change rendering
+// when it moves between an integer and a float type
+fn render_f64(v: f64) -> String {
+    if v.is_finite() && v.fract() == 0.0 && v.abs() < 1e15 {
+        (v as i64).to_string()
+    } else {
+        v.to_string()
+    }
 }
 
 /// A representation of a metric value for testing.
@@ -182,15 +423,7 @@ impl Metric {
 
     /// Returns the total number of observations, correctly accounting for `Repeated`
     pub fn num_observations(&self) -> u64 {
-        self.distribution
-            .iter()
-            .map(|obs| match obs {
-                Observation::Unsigned(_) => 1,
-                Observation::Floating(_) => 1,
-                Observation::Repeated { occurrences, .. } => *occurrences,
-                _ => unreachable!("Observation is non_exhaustive"),
-            })
-            .sum()
+        self.distribution.iter().map(Observation::occurrences).sum()
     }
 
     /// Returns all observations in a sorted Vec of f64, flatten repeated obsevations
@@ -320,6 +553,140 @@ impl ValueWriter for TestValueWriter<'_> {
     }
 }
 
+/// The names, units and dimensions of an [`Entry`], without its values.
+///
+/// This is used by the tests generated by `#[metrics(generate_tests(...))]`, which compare
+/// the schema of a sample entry against an expected table so that renames that would break
+/// dashboards fail tests. It can also be used directly:
+///
+/// ```
+/// # use std::time::Duration;
+/// # use metrique_writer::{Entry, MetricValue, Unit, value::WithDimension};
+/// # use metrique_writer::unit::{Millisecond, UnitTag};
+/// # use metrique_writer::test_util::EntrySchema;
+/// #[derive(Entry)]
+/// struct MyEntry {
+///     operation: &'static str,
+///     latency: Duration,
+///     requests: WithDimension<u64>,
+/// }
+///
+/// let entry = MyEntry {
+///     operation: "Foo",
+///     latency: Duration::from_millis(5),
+///     requests: 1u64.with_dimension("Operation", "Foo"),
+/// };
+/// assert_eq!(
+///     EntrySchema::of(entry),
+///     EntrySchema::new()
+///         .property("operation")
+///         .metric("latency", Millisecond::UNIT, &[])
+///         .metric("requests", Unit::None, &["Operation"]),
+/// );
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct EntrySchema {
+    /// Metric names, with their unit and the names of their dimensions
+    pub metrics: BTreeMap<String, MetricSchema>,
+    /// Property (string value) names
+    pub properties: BTreeSet<String>,
+    /// Entry-level dimension sets, as set by `#[metrics(emf::dimension_sets = ...)]`
+    pub dimension_sets: Vec<Vec<String>>,
+}
+
+/// The unit and dimension names of a metric in an [`EntrySchema`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct MetricSchema {
+    /// The unit of the metric
+    pub unit: Unit,
+    /// The names of the dimensions of the metric, sorted
+    pub dimensions: Vec<String>,
+}
+
+impl EntrySchema {
+    /// Create an empty [`EntrySchema`], to be filled in with the expected names
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the schema of `entry`
+    pub fn of(entry: impl Entry) -> Self {
+        let mut schema = Self::new();
+        entry.write(&mut schema);
+        schema
+    }
+
+    /// Adds a metric with the given unit and dimension names
+    pub fn metric(mut self, name: impl Into<String>, unit: Unit, dimensions: &[&str]) -> Self {
+        let mut dimensions: Vec<String> = dimensions.iter().map(|d| d.to_string()).collect();
+        dimensions.sort();
+        self.metrics
+            .insert(name.into(), MetricSchema { unit, dimensions });
+        self
+    }
+
+    /// Adds a property
+    pub fn property(mut self, name: impl Into<String>) -> Self {
+        self.properties.insert(name.into());
+        self
+    }
+
+    /// Adds an entry-level dimension set
+    pub fn dimension_set(mut self, dimensions: &[&str]) -> Self {
+        self.dimension_sets
+            .push(dimensions.iter().map(|d| d.to_string()).collect());
+        self
+    }
+}
+
+impl<'a> EntryWriter<'a> for EntrySchema {
+    fn timestamp(&mut self, _timestamp: SystemTime) {}
+
+    fn value(
+        &mut self,
+        name: impl Into<std::borrow::Cow<'a, str>>,
+        value: &(impl crate::Value + ?Sized),
+    ) {
+        let mut raw_value = TestValue::Unset;
+        value.write(TestValueWriter {
+            inner: &mut raw_value,
+        });
+        match raw_value {
+            TestValue::Property(_) => {
+                self.properties.insert(name.into().into_owned());
+            }
+            TestValue::Metric(metric) => {
+                let mut dimensions: Vec<String> = metric
+                    .dimensions
+                    .into_iter()
+                    .map(|(name, _)| name)
+                    .collect();
+                dimensions.sort();
+                self.metrics.insert(
+                    name.into().into_owned(),
+                    MetricSchema {
+                        unit: metric.unit,
+                        dimensions,
+                    },
+                );
+            }
+            TestValue::Unset => {}
+        }
+    }
+
+    fn config(&mut self, config: &'a dyn metrique_writer_core::EntryConfig) {
+        if let Some(dimensions) = (config as &dyn Any).downcast_ref::<EntryDimensions>() {
+            self.dimension_sets.extend(
+                dimensions
+                    .dim_sets()
+                    .map(|set| set.map(|d| d.to_string()).collect()),
+            );
+        }
+    }
+}
+
 /// Converts an [`Entry`] into a `TestEntry` that can be introspected
 ///
 /// > NOTE: This method is probably not what you want. For testing an individual metric,
@@ -456,6 +823,729 @@ impl AnyEntrySink for Inspector {
     }
 }
 
+/// A test sink that captures entries and can be queried across all of them.
+///
+/// Unlike [`Inspector`], which only returns the captured [`TestEntry`]s, `CapturingSink` also
+/// records the [sample group](Entry::sample_group) of each entry and provides aggregate queries,
+/// so tests don't need to manually drain and walk through the entries. Cloning will provide another
+/// reference to the same underlying sink.
+///
+/// This requires that the `test-util` feature be enabled.
+///
+/// # Example
+/// ```
+/// use metrique_writer::test_util::CapturingSink;
+/// use metrique_writer::{Entry, EntrySink, assert_emitted};
+///
+/// #[derive(Entry)]
+/// struct RequestMetrics {
+///     #[entry(sample_group)]
+///     operation: &'static str,
+///     request_count: u64,
+/// }
+///
+/// let sink = CapturingSink::new();
+/// sink.append(RequestMetrics { operation: "Get", request_count: 1 });
+/// sink.append(RequestMetrics { operation: "Put", request_count: 2 });
+/// sink.append(RequestMetrics { operation: "Put", request_count: 3 });
+///
+/// assert_eq!(sink.entries_with(&[("operation", "Put")]).len(), 2);
+/// assert_eq!(sink.metric("request_count").sum(), 6.0);
+/// assert_eq!(sink.metric("request_count").max(), Some(3.0));
+/// assert_eq!(sink.metric("request_count").count(), 3);
+/// assert_emitted!(sink, "operation" == "Get");
+/// assert_emitted!(sink, "request_count" == 3);
+/// ```
+#[derive(Default, Clone, Debug)]
+pub struct CapturingSink {
+    entries: Arc<Mutex<Vec<CapturedEntry>>>,
+}
+
+#[derive(Debug)]
+struct CapturedEntry {
+    entry: TestEntry,
+    sample_group: Vec<(String, String)>,
+}
+
+impl CapturingSink {
+    /// Create a new, empty [`CapturingSink`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `f` with a new [`CapturingSink`], and return the sink with the entries that `f` appended.
+    ///
+    /// This is a convenient way to scope a sink to the code under test before checking it with
+    /// [`assert_metrics!`](crate::assert_metrics).
+    pub fn capture(f: impl FnOnce(&CapturingSink)) -> Self {
+        let sink = Self::new();
+        f(&sink);
+        sink
+    }
+
+    /// Return all the captured entries, in the order they were appended
+    pub fn entries(&self) -> Vec<TestEntry> {
+        self.entries_where(|_| true)
+    }
+
+    /// Return the captured entries whose sample group contains all of the given `(name, value)` pairs
+    pub fn entries_with(&self, sample_group: &[(&str, &str)]) -> Vec<TestEntry> {
+        self.entries_where(|captured| {
+            sample_group.iter().all(|(name, value)| {
+                captured
+                    .sample_group
+                    .iter()
+                    .any(|(n, v)| n == name && v == value)
+            })
+        })
+    }
+
+    fn entries_where(&self, mut f: impl FnMut(&CapturedEntry) -> bool) -> Vec<TestEntry> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .iter()
+            .filter(|captured| f(captured))
+            .map(|captured| captured.entry.clone())
+            .collect()
+    }
+
+    /// Return the number of captured entries
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    /// Return true if no entries have been captured
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove all captured entries, returning them
+    pub fn drain(&self) -> Vec<TestEntry> {
+        std::mem::take(&mut *self.entries.lock().unwrap())
+            .into_iter()
+            .map(|captured| captured.entry)
+            .collect()
+    }
+
+    /// Return the values of the metric `name` across all captured entries.
+    ///
+    /// Entries that did not emit `name` are skipped.
+    pub fn metric(&self, name: &str) -> MetricQuery {
+        let entries = self.entries.lock().unwrap();
+        MetricQuery {
+            name: name.to_owned(),
+            metrics: entries
+                .iter()
+                .filter_map(|captured| captured.entry.metrics.get(name).cloned())
+                .collect(),
+        }
+    }
+
+    /// Panics unless some captured entry satisfies all of the `conditions`.
+    ///
+    /// This is normally called through [`assert_metrics!`](crate::assert_metrics).
+    #[track_caller]
+    pub fn assert_matches(&self, conditions: &[Condition<'_>]) {
+        let entries = self.entries();
+        if entries
+            .iter()
+            .any(|entry| conditions.iter().all(|condition| condition.holds_in(entry)))
+        {
+            return;
+        }
+        let expected: Vec<_> = conditions.iter().map(ToString::to_string).collect();
+        let mut message = format!(
+            "no captured entry matched all of {expected:?} ({} captured entries)",
+            entries.len()
+        );
+        for (index, entry) in entries.iter().enumerate() {
+            let failed: Vec<_> = conditions
+                .iter()
+                .filter(|condition| !condition.holds_in(entry))
+                .map(ToString::to_string)
+                .collect();
+            message.push_str(&format!("\nentry {index} failed {failed:?}: {entry:#?}"));
+        }
+        panic!("{message}");
+    }
+
+    /// Panics unless some captured entry contains a metric or property called `name`
+    /// for which `matches` returns true.
+    ///
+    /// This is normally called through [`assert_emitted!`](crate::assert_emitted).
+    #[track_caller]
+    pub fn assert_emitted(&self, name: &str, matches: impl Fn(&TestEntry) -> bool) {
+        let entries = self.entries();
+        let emitted =
+            |entry: &TestEntry| entry.metrics.contains_key(name) || entry.values.contains_key(name);
+        if !entries.iter().any(|entry| emitted(entry) && matches(entry)) {
+            let found: Vec<_> = entries.iter().filter(|entry| emitted(entry)).collect();
+            panic!(
+                "no matching entry emitted `{name}` ({} captured entries). Entries that emitted `{name}`: {found:#?}",
+                entries.len()
+            );
+        }
+    }
+}
+
+impl AnyEntrySink for CapturingSink {
+    fn append_any(&self, entry: impl Entry + Send + 'static) {
+        let sample_group = entry
+            .sample_group()
+            .map(|(name, value)| (name.into_owned(), value.into_owned()))
+            .collect();
+        self.entries.lock().unwrap().push(CapturedEntry {
+            entry: to_test_entry(entry),
+            sample_group,
+        });
+    }
+
+    fn flush_async(&self) -> FlushWait {
+        FlushWait::ready()
+    }
+}
+
+/// The values of one metric across the entries of a [`CapturingSink`], returned by [`CapturingSink::metric`].
+#[derive(Debug, Clone)]
+pub struct MetricQuery {
+    name: String,
+    metrics: Vec<Metric>,
+}
+
+impl MetricQuery {
+    /// Returns the metric as emitted by each entry that emitted it
+    pub fn values(&self) -> &[Metric] {
+        &self.metrics
+    }
+
+    /// Returns the number of entries that emitted this metric
+    pub fn entries(&self) -> usize {
+        self.metrics.len()
+    }
+
+    /// Returns the total number of observations, accounting for repeated observations
+    pub fn count(&self) -> u64 {
+        self.metrics.iter().map(Metric::num_observations).sum()
+    }
+
+    /// Returns the sum of all observations
+    pub fn sum(&self) -> f64 {
+        self.metrics
+            .iter()
+            .flat_map(|metric| &metric.distribution)
+            .map(Observation::total)
+            .sum()
+    }
+
+    /// Returns the smallest observation, or `None` if the metric was never emitted
+    pub fn min(&self) -> Option<f64> {
+        self.observations().first().copied()
+    }
+
+    /// Returns the largest observation, or `None` if the metric was never emitted
+    pub fn max(&self) -> Option<f64> {
+        self.observations().last().copied()
+    }
+
+    /// Returns all observations in a sorted Vec of f64, flattening repeated observations
+    /// like [`Metric::flatten_and_sort`]
+    pub fn observations(&self) -> Vec<f64> {
+        let mut out: Vec<f64> = self
+            .metrics
+            .iter()
+            .flat_map(Metric::flatten_and_sort)
+            .collect();
+        out.sort_by_key(|f| OrderedFloat(*f));
+        out
+    }
+
+    /// Returns the name of the metric
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// A value that [`assert_emitted!`](crate::assert_emitted) can compare against an emitted
+/// metric or property.
+pub trait EmittedValue {
+    /// Returns true if `entry` emitted `name` with this value
+    fn emitted_in(&self, entry: &TestEntry, name: &str) -> bool;
+}
+
+macro_rules! impl_emitted_metric {
+    ($($ty:ty),*) => {
+        $(
+            impl EmittedValue for $ty {
+                fn emitted_in(&self, entry: &TestEntry, name: &str) -> bool {
+                    entry.metrics.get(name).is_some_and(|metric| {
+                        metric.distribution.len() == 1 && metric == self
+                    })
+                }
+            }
+        )*
+    };
+}
+
+impl_emitted_metric!(u64, f64, bool);
+
+impl EmittedValue for &str {
+    fn emitted_in(&self, entry: &TestEntry, name: &str) -> bool {
+        entry.values.get(name).is_some_and(|value| value == self)
+    }
+}
+
+impl EmittedValue for String {
+    fn emitted_in(&self, entry: &TestEntry, name: &str) -> bool {
+        self.as_str().emitted_in(entry, name)
+    }
+}
+
+/// Asserts that a [`CapturingSink`] captured an entry that emitted a metric or property.
+///
+/// - `assert_emitted!(sink, "Name")` asserts that some entry emitted `Name`.
+/// - `assert_emitted!(sink, "Name" == value)` asserts that some entry emitted `Name` with the
+///   given value. `value` can be a `u64`, `f64` or `bool` for metrics, or a string for properties.
+///
+/// On failure, the panic message lists the captured entries that emitted `Name`.
+///
+/// See [`CapturingSink`] for an example.
+#[macro_export]
+macro_rules! assert_emitted {
+    ($sink:expr, $name:literal == $value:expr $(,)?) => {
+        $crate::test_util::CapturingSink::assert_emitted(&$sink, $name, |entry| {
+            $crate::test_util::EmittedValue::emitted_in(&$value, entry, $name)
+        })
+    };
+    ($sink:expr, $name:expr $(,)?) => {
+        $crate::test_util::CapturingSink::assert_emitted(&$sink, $name, |_| true)
+    };
+}
+
+/// How a [`Condition`] compares the emitted value to the expected value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    /// `==`
+    Eq,
+    /// `!=`
+    Ne,
+    /// `<`
+    Lt,
+    /// `<=`
+    Le,
+    /// `>`
+    Gt,
+    /// `>=`
+    Ge,
+}
+
+impl Comparison {
+    /// Returns true if `emitted` compares to `expected` in this way
+    pub fn holds<T: PartialOrd + ?Sized>(self, emitted: &T, expected: &T) -> bool {
+        match self {
+            Self::Eq => emitted == expected,
+            Self::Ne => emitted != expected,
+            Self::Lt => emitted < expected,
+            Self::Le => emitted <= expected,
+            Self::Gt => emitted > expected,
+            Self::Ge => emitted >= expected,
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            Self::Eq => "==",
+            Self::Ne => "!=",
+            Self::Lt => "<",
+            Self::Le => "<=",
+            Self::Gt => ">",
+            Self::Ge => ">=",
+        }
+    }
+}
+
+/// A value that [`assert_metrics!`](crate::assert_metrics) can compare against an emitted
+/// metric or property.
+///
+/// Numbers and `bool`s are compared against every observation of a metric, strings against a
+/// property, and [`Duration`]s against every observation of a metric with a time unit, after
+/// converting the duration to that unit.
+pub trait ExpectedValue: fmt::Debug {
+    /// Returns true if `entry` emitted `name`, and the emitted value compares to this value
+    /// with `comparison`
+    fn compares_in(&self, entry: &TestEntry, name: &str, comparison: Comparison) -> bool;
+}
+
+fn observations_compare(
+    entry: &TestEntry,
+    name: &str,
+    comparison: Comparison,
+    expected: impl Fn(&Metric) -> Option<f64>,
+) -> bool {
+    entry.metrics.get(name).is_some_and(|metric| {
+        let observations = metric.flatten_and_sort();
+        expected(metric).is_some_and(|expected| {
+            !observations.is_empty()
+                && observations
+                    .iter()
+                    .all(|observation| comparison.holds(observation, &expected))
+        })
+    })
+}
+
+impl ExpectedValue for u64 {
+    fn compares_in(&self, entry: &TestEntry, name: &str, comparison: Comparison) -> bool {
+        observations_compare(entry, name, comparison, |_| Some(*self as f64))
+    }
+}
+
+impl ExpectedValue for f64 {
+    fn compares_in(&self, entry: &TestEntry, name: &str, comparison: Comparison) -> bool {
+        observations_compare(entry, name, comparison, |_| Some(*self))
+    }
+}
+
+impl ExpectedValue for bool {
+    fn compares_in(&self, entry: &TestEntry, name: &str, comparison: Comparison) -> bool {
+        u64::from(*self).compares_in(entry, name, comparison)
+    }
+}
+
+impl ExpectedValue for Duration {
+    fn compares_in(&self, entry: &TestEntry, name: &str, comparison: Comparison) -> bool {
+        observations_compare(entry, name, comparison, |metric| match metric.unit {
+            Unit::Second(scale) => Some(self.as_secs_f64() * scale.reduction_factor() as f64),
+            _ => None,
+        })
+    }
+}
+
+impl ExpectedValue for &str {
+    fn compares_in(&self, entry: &TestEntry, name: &str, comparison: Comparison) -> bool {
+        entry
+            .values
+            .get(name)
+            .is_some_and(|value| comparison.holds(value.as_str(), self))
+    }
+}
+
+impl ExpectedValue for String {
+    fn compares_in(&self, entry: &TestEntry, name: &str, comparison: Comparison) -> bool {
+        self.as_str().compares_in(entry, name, comparison)
+    }
+}
+
+/// A condition on one metric or property of an entry, checked by [`CapturingSink::assert_matches`].
+///
+/// Conditions are normally written with [`assert_metrics!`](crate::assert_metrics).
+#[derive(Clone, Copy)]
+pub struct Condition<'a> {
+    name: &'a str,
+    comparison: Comparison,
+    expected: &'a dyn ExpectedValue,
+}
+
+impl<'a> Condition<'a> {
+    /// Create a condition that `name` was emitted and compares to `expected` with `comparison`
+    pub fn new<T: ExpectedValue>(name: &'a str, comparison: Comparison, expected: &'a T) -> Self {
+        Self {
+            name,
+            comparison,
+            expected,
+        }
+    }
+
+    /// Returns true if `entry` satisfies this condition
+    pub fn holds_in(&self, entry: &TestEntry) -> bool {
+        self.expected.compares_in(entry, self.name, self.comparison)
+    }
+}
+
+impl fmt::Display for Condition<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "`{}` {} {:?}",
+            self.name,
+            self.comparison.symbol(),
+            self.expected
+        )
+    }
+}
+
+impl fmt::Debug for Condition<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+/// Asserts that a [`CapturingSink`] captured an entry that satisfies all of the given conditions.
+///
+/// Each condition is written `"Name" <op> value`, where `<op>` is one of `==`, `!=`, `<`, `<=`,
+/// `>` or `>=`, and `value` is a literal, a variable or a parenthesized expression:
+///
+/// - Numbers and `bool`s are compared against every observation of the metric `Name`.
+/// - Strings are compared against the property `Name`.
+/// - A number followed by `ns`, `us`, `ms` or `s` is a duration, which is converted to the unit
+///   of the metric `Name` before comparing. Metrics that don't have a time unit never match.
+///
+/// Unlike [`assert_emitted!`](crate::assert_emitted), all the conditions must hold for the same
+/// entry. On failure, the panic message lists the conditions that each captured entry failed.
+///
+/// ```
+/// use std::time::Duration;
+/// use metrique_writer::test_util::CapturingSink;
+/// use metrique_writer::{Entry, EntrySink, assert_metrics};
+///
+/// #[derive(Entry)]
+/// #[entry(rename_all = "PascalCase")]
+/// struct RequestMetrics {
+///     operation: &'static str,
+///     number_of_ducks: u64,
+///     latency: Duration,
+/// }
+///
+/// let sink = CapturingSink::capture(|sink| {
+///     // in a real test, this would be the code under test
+///     sink.append(RequestMetrics {
+///         operation: "CountDucks",
+///         number_of_ducks: 3,
+///         latency: Duration::from_millis(20),
+///     });
+/// });
+///
+/// assert_metrics!(sink, "Operation" == "CountDucks", "Latency" <= 50 ms);
+/// assert_metrics!(sink, "NumberOfDucks" >= 1, "NumberOfDucks" < 10, "Latency" > 0.01 s);
+/// ```
+#[macro_export]
+macro_rules! assert_metrics {
+    ($sink:expr, $($name:literal $op:tt $value:tt $($unit:ident)?),+ $(,)?) => {
+        $crate::test_util::CapturingSink::assert_matches(&$sink, &[$(
+            $crate::test_util::Condition::new(
+                $name,
+                $crate::__assert_metrics_comparison!($op),
+                &$crate::__assert_metrics_value!($value $($unit)?),
+            )
+        ),+])
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __assert_metrics_comparison {
+    (==) => {
+        $crate::test_util::Comparison::Eq
+    };
+    (!=) => {
+        $crate::test_util::Comparison::Ne
+    };
+    (<) => {
+        $crate::test_util::Comparison::Lt
+    };
+    (<=) => {
+        $crate::test_util::Comparison::Le
+    };
+    (>) => {
+        $crate::test_util::Comparison::Gt
+    };
+    (>=) => {
+        $crate::test_util::Comparison::Ge
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __assert_metrics_value {
+    ($value:tt) => {
+        $value
+    };
+    ($value:tt ns) => {
+        ::std::time::Duration::from_secs_f64(($value) as f64 / 1e9)
+    };
+    ($value:tt us) => {
+        ::std::time::Duration::from_secs_f64(($value) as f64 / 1e6)
+    };
+    ($value:tt ms) => {
+        ::std::time::Duration::from_secs_f64(($value) as f64 / 1e3)
+    };
+    ($value:tt s) => {
+        ::std::time::Duration::from_secs_f64(($value) as f64)
+    };
+}
+
+/// Rewrites the timestamps of entries to fixed values, so that golden tests of formatted output
+/// or captured entries are stable from run to run.
+///
+/// The canonical timestamp of an entry is replaced by the frozen time, and the properties
+/// registered with [`FreezeTime::property`], like `#[metrics(timestamp(property))]` fields, are
+/// replaced by the frozen time in milliseconds since the Unix epoch (their default format).
+/// Wrap entries with [`FreezeTime::entry`] before they are formatted, or rewrite entries that were
+/// already captured with [`FreezeTime::apply`].
+///
+/// ```
+/// # use std::time::{Duration, SystemTime, UNIX_EPOCH};
+/// # use metrique_writer::{Entry, format::Format, test_util::{freeze_time, to_test_entry}};
+/// # use metrique_writer_format_emf::Emf;
+/// #[derive(Entry)]
+/// struct RequestMetrics {
+///     #[entry(timestamp)]
+///     timestamp: SystemTime,
+///     start_time: String,
+///     items: u64,
+/// }
+///
+/// let request = || RequestMetrics {
+///     timestamp: SystemTime::now(),
+///     start_time: "1712345678901.0".into(),
+///     items: 3,
+/// };
+/// let freeze = freeze_time(UNIX_EPOCH + Duration::from_secs(1)).property("start_time");
+///
+/// let mut output = vec![];
+/// Emf::no_validations("MyApp".into(), vec![vec![]])
+///     .format(&freeze.entry(request()), &mut output)
+///     .unwrap();
+/// let output = String::from_utf8(output).unwrap();
+/// assert!(output.contains(r#""Timestamp":1000"#));
+/// assert!(output.contains(r#""start_time":"1000.0""#));
+///
+/// // entries that were already captured
+/// let mut entry = to_test_entry(request());
+/// freeze.apply(&mut entry);
+/// assert_eq!(entry.timestamp, Some(UNIX_EPOCH + Duration::from_secs(1)));
+/// assert_eq!(entry.values["start_time"], "1000.0");
+/// ```
+#[derive(Debug, Clone)]
+pub struct FreezeTime {
+    at: SystemTime,
+    properties: BTreeSet<String>,
+}
+
+/// Create a [`FreezeTime`] that rewrites timestamps to `at`
+pub fn freeze_time(at: SystemTime) -> FreezeTime {
+    FreezeTime {
+        at,
+        properties: BTreeSet::new(),
+    }
+}
+
+impl FreezeTime {
+    /// Also rewrite the time-valued property `name` (the name as emitted, after inflection)
+    pub fn property(mut self, name: impl Into<String>) -> Self {
+        self.properties.insert(name.into());
+        self
+    }
+
+    /// Wrap `entry` so that it is written with frozen timestamps.
+    ///
+    /// The frozen timestamp is written even if `entry` has no timestamp, since formats would
+    /// otherwise use the current time.
+    pub fn entry<E: Entry>(&self, entry: E) -> FrozenEntry<E> {
+        FrozenEntry {
+            freeze: self.clone(),
+            entry,
+        }
+    }
+
+    /// Rewrite the timestamps of an entry that was already captured.
+    ///
+    /// The timestamp is only rewritten if the entry has one.
+    pub fn apply(&self, entry: &mut TestEntry) {
+        if let Some(timestamp) = &mut entry.timestamp {
+            *timestamp = self.at;
+        }
+        for name in &self.properties {
+            if let Some(value) = entry.values.0.get_mut(name) {
+                *value = self.property_value();
+            }
+        }
+    }
+
+    fn property_value(&self) -> String {
+        let millis = self
+            .at
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64()
+            * 1000.0;
+        format!("{millis:?}")
+    }
+}
+
+/// An entry with frozen timestamps, see [`FreezeTime::entry`]
+#[derive(Debug, Clone)]
+pub struct FrozenEntry<E> {
+    freeze: FreezeTime,
+    entry: E,
+}
+
+impl<E> FrozenEntry<E> {
+    /// Returns the wrapped entry
+    pub fn into_inner(self) -> E {
+        self.entry
+    }
+}
+
+impl<E: Entry> Entry for FrozenEntry<E> {
+    fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+        let mut writer = FreezeWriter {
+            freeze: &self.freeze,
+            property_value: self.freeze.property_value(),
+            writer,
+        };
+        writer.writer.timestamp(self.freeze.at);
+        self.entry.write(&mut writer);
+    }
+
+    fn sample_group(&self) -> impl Iterator<Item = SampleGroupElement> {
+        self.entry.sample_group()
+    }
+
+    fn priority(&self) -> Priority {
+        self.entry.priority()
+    }
+}
+
+struct FreezeWriter<'f, W> {
+    freeze: &'f FreezeTime,
+    property_value: String,
+    writer: W,
+}
+
+impl<'a, W: EntryWriter<'a>> EntryWriter<'a> for FreezeWriter<'_, &mut W> {
+    fn timestamp(&mut self, _timestamp: SystemTime) {
+        // the frozen timestamp was already written
+    }
+
+    fn value(
+        &mut self,
+        name: impl Into<std::borrow::Cow<'a, str>>,
+        value: &(impl crate::Value + ?Sized),
+    ) {
+        let name = name.into();
+        if self.freeze.properties.contains(&*name) {
+            self.writer.value(name, &*self.property_value);
+        } else {
+            self.writer.value(name, value);
+        }
+    }
+
+    fn config(&mut self, config: &'a dyn metrique_writer_core::EntryConfig) {
+        self.writer.config(config);
+    }
+
+    fn begin_group(
+        &mut self,
+        name: impl Into<std::borrow::Cow<'a, str>>,
+        prefix: impl Into<std::borrow::Cow<'a, str>>,
+    ) {
+        self.writer.begin_group(name, prefix);
+    }
+
+    fn end_group(&mut self) {
+        self.writer.end_group();
+    }
+}
+
 /// A sink that captures rendered output for format-aware testing.
 pub struct RenderQueue<F>(Arc<Mutex<(F, Vec<String>)>>);
 
@@ -529,6 +1619,240 @@ mod tests {
         request_count: u64,
     }
 
+    #[derive(Entry)]
+    struct SampledMetrics {
+        #[entry(sample_group)]
+        operation: &'static str,
+        latency: crate::value::VecDistribution<u64>,
+    }
+
+    #[derive(Entry)]
+    struct RenderMetrics {
+        #[entry(timestamp)]
+        timestamp: SystemTime,
+        operation: &'static str,
+        status: String,
+        latency: std::time::Duration,
+        sizes: crate::value::VecDistribution<f64>,
+        retries: crate::value::WithDimension<u64>,
+        flagged: TestFlag<u64>,
+        missing: Option<u64>,
+    }
+
+    #[test]
+    fn render_is_canonical() {
+        let entry = to_test_entry(RenderMetrics {
+            timestamp: SystemTime::UNIX_EPOCH + std::time::Duration::from_millis(1500),
+            operation: "Get",
+            status: "multi\nline".into(),
+            latency: std::time::Duration::from_micros(1500),
+            sizes: [1.0, 0.1, -0.0, f64::INFINITY].into_iter().collect(),
+            retries: crate::MetricValue::with_dimension(2, "Operation", "Get"),
+            flagged: 1.into(),
+            missing: None,
+        });
+        assert_eq!(
+            entry.render(),
+            "\
+timestamp: [redacted]
+properties:
+  operation: \"Get\"
+  status: \"multi\\nline\"
+metrics:
+  flagged: 1 [test_flag]
+  latency: 1.5 Milliseconds
+  retries: 2 {Operation=Get}
+  sizes: [1, 0.1, 0, inf]
+"
+        );
+        assert!(
+            entry
+                .render_with(RenderTimestamp::EpochMillis)
+                .starts_with("timestamp: 1500\nproperties:")
+        );
+        assert!(
+            entry
+                .render_with(RenderTimestamp::Omitted)
+                .starts_with("properties:")
+        );
+    }
+
+    #[test]
+    fn entry_differences_are_listed_by_field() {
+        #[derive(Entry)]
+        struct Actual {
+            operation: &'static str,
+            latency: std::time::Duration,
+            request_count: u64,
+            extra: &'static str,
+        }
+
+        #[derive(Entry)]
+        struct Expected {
+            operation: &'static str,
+            latency: crate::unit::AsMicroseconds<std::time::Duration>,
+            request_count: u64,
+            retries: u64,
+        }
+
+        let actual = to_test_entry(Actual {
+            operation: "Get",
+            latency: std::time::Duration::from_millis(5),
+            request_count: 1,
+            extra: "x",
+        });
+        let expected = to_test_entry(Expected {
+            operation: "Put",
+            latency: std::time::Duration::from_millis(5).into(),
+            request_count: 1,
+            retries: 0,
+        });
+        assert_eq!(
+            entry_differences(&actual, &expected),
+            [
+                "property `extra`: \"x\", not expected",
+                "property `operation`: \"Get\", expected \"Put\"",
+                "metric `latency`: 5 Milliseconds, expected 5000 Microseconds \
+                 (unit is Milliseconds, expected Microseconds)",
+                "metric `retries`: missing, expected 0",
+            ]
+        );
+        assert!(entry_differences(&actual, &actual.clone()).is_empty());
+        assert_entries_eq(&expected, &expected.clone());
+    }
+
+    #[test]
+    fn freeze_time_rewrites_timestamps() {
+        let at = SystemTime::UNIX_EPOCH + std::time::Duration::from_millis(1500);
+        let freeze = freeze_time(at).property("operation");
+
+        let entry = to_test_entry(freeze.entry(SampledMetrics {
+            operation: "Get",
+            latency: [1].into_iter().collect(),
+        }));
+        // the timestamp is written even if the entry has none
+        assert_eq!(entry.timestamp, Some(at));
+        assert_eq!(entry.values["operation"], "1500.0");
+        assert_eq!(entry.metrics["latency"], 1);
+
+        let frozen = freeze.entry(RenderMetrics {
+            timestamp: SystemTime::now(),
+            operation: "Get",
+            status: "ok".into(),
+            latency: std::time::Duration::from_millis(1),
+            sizes: Default::default(),
+            retries: 1.into(),
+            flagged: 1.into(),
+            missing: None,
+        });
+        assert_eq!(to_test_entry(&frozen).timestamp, Some(at));
+        assert_eq!(
+            frozen.sample_group().count(),
+            frozen.into_inner().sample_group().count()
+        );
+    }
+
+    #[test]
+    fn capturing_sink_queries() {
+        let sink = CapturingSink::new();
+        assert!(sink.is_empty());
+        sink.append(SampledMetrics {
+            operation: "Get",
+            latency: [1, 5].into_iter().collect(),
+        });
+        sink.append(SampledMetrics {
+            operation: "Put",
+            latency: [3].into_iter().collect(),
+        });
+        sink.append(TestMetrics {
+            operation: "Put",
+            request_count: 1,
+        });
+
+        assert_eq!(sink.len(), 3);
+        assert_eq!(sink.entries_with(&[("operation", "Get")]).len(), 1);
+        // `TestMetrics` has no sample group
+        assert_eq!(sink.entries_with(&[("operation", "Put")]).len(), 1);
+        assert_eq!(sink.entries_with(&[]).len(), 3);
+
+        let latency = sink.metric("latency");
+        assert_eq!(latency.entries(), 2);
+        assert_eq!(latency.count(), 3);
+        assert_eq!(latency.sum(), 9.0);
+        assert_eq!(latency.min(), Some(1.0));
+        assert_eq!(latency.max(), Some(5.0));
+        assert_eq!(sink.metric("missing").max(), None);
+
+        crate::assert_emitted!(sink, "latency");
+        crate::assert_emitted!(sink, "request_count" == 1);
+        crate::assert_emitted!(sink, "operation" == String::from("Put"));
+
+        assert_eq!(sink.drain().len(), 3);
+        assert!(sink.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "no matching entry emitted `request_count` (1 captured entries)")]
+    fn assert_emitted_fails_on_wrong_value() {
+        let sink = CapturingSink::new();
+        sink.append(TestMetrics {
+            operation: "Get",
+            request_count: 1,
+        });
+        crate::assert_emitted!(sink, "request_count" == 2);
+    }
+
+    #[test]
+    fn assert_metrics_matches_one_entry() {
+        let sink = CapturingSink::capture(|sink| {
+            sink.append(SampledMetrics {
+                operation: "Get",
+                latency: [1, 5].into_iter().collect(),
+            });
+            sink.append(RenderMetrics {
+                timestamp: SystemTime::UNIX_EPOCH,
+                operation: "Put",
+                status: "OK".into(),
+                latency: std::time::Duration::from_micros(1_500),
+                sizes: [0.5].into_iter().collect(),
+                retries: crate::value::WithDimension::new(2, "Region", "us-east-1"),
+                flagged: TestFlag::from(0),
+                missing: None,
+            });
+        });
+
+        let expected = String::from("Get");
+        crate::assert_metrics!(
+            sink,
+            "operation" == expected,
+            "latency" >= 1,
+            "latency" < 5.5
+        );
+        crate::assert_metrics!(
+            sink,
+            "operation" != "Get",
+            "latency" == 1500 us,
+            "latency" <= 2 ms,
+            "latency" > 0.001 s,
+            "retries" == (1 + 1),
+            "flagged" == false,
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "no captured entry matched all of [\"`operation` == \\\"Get\\\"\", \"`latency` < 1ms\"] (1 captured entries)\nentry 0 failed [\"`latency` < 1ms\"]"
+    )]
+    fn assert_metrics_fails_when_no_entry_matches_all() {
+        let sink = CapturingSink::new();
+        sink.append(SampledMetrics {
+            operation: "Get",
+            latency: [1, 5].into_iter().collect(),
+        });
+        // `latency` has no time unit
+        crate::assert_metrics!(sink, "operation" == "Get", "latency" < 1 ms);
+    }
+
     #[test]
     #[should_panic(expected = "key 'wrong_name' not found. Available keys: [\"request_count\"]")]
     fn test_metric_map_missing_key_error() {
@@ -0,0 +1,509 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! A conformance suite for [`Format`] implementations, so that formats maintained outside of this
+//! repository can check that they handle every kind of entry.
+//!
+//! [`FormatConformance`] formats every [`ConformanceCase`], which together cover properties
+//! (including characters that need escaping), every [`Unit`], unsigned, floating point and
+//! repeated observations, distributions, dimensions and timestamps, and checks that:
+//!
+//! - every valid case is formatted without error, into a non-empty output
+//! - formatting an entry with a timestamp is deterministic, and doesn't depend on the entries
+//!   formatted before (formats may stamp entries without a timestamp with the current time)
+//! - an entry that reports a [`ValidationError`] fails with [`IoStreamError::Validation`]
+//! - if the format can be parsed back (see [`FormatConformance::parse_with`]), the parsed entry has
+//!   the properties, metric totals, units and timestamp of the original
+//!
+//! Cases that a format deliberately doesn't support, like per-metric dimensions for a format that
+//! requires dimensions to be declared for the whole entry, can be skipped by name.
+//!
+//! ```
+//! use metrique_writer::test_util::conformance::FormatConformance;
+//! use metrique_writer_format_emf::Emf;
+//!
+//! FormatConformance::new(|| Emf::all_validations("MyApp".into(), vec![vec![]]))
+//!     // EMF only supports per-metric dimensions with split entries
+//!     .skip("dimensions")
+//!     .run();
+//! ```
+
+use std::{
+    borrow::Cow,
+    fmt,
+    time::{Duration, SystemTime},
+};
+
+use metrique_writer_core::{
+    EntryWriter, MetricFlags, Observation, Unit, ValidationError, Value, ValueWriter,
+    stream::IoStreamError,
+    unit::{NegativeScale, PositiveScale},
+};
+
+use super::{TestEntry, to_test_entry};
+use crate::{Entry, format::Format};
+
+type Parser = Box<dyn Fn(&[u8]) -> Result<Vec<TestEntry>, String>>;
+
+/// Runs the [conformance cases](ConformanceCase) against a [`Format`], see the
+/// [module docs](self).
+pub struct FormatConformance<F> {
+    make_format: Box<dyn FnMut() -> F>,
+    parse: Option<Parser>,
+    skipped: Vec<Cow<'static, str>>,
+}
+
+impl<F: Format> FormatConformance<F> {
+    /// Check the formats created by `make_format`. Every case is formatted with a new format.
+    pub fn new(make_format: impl FnMut() -> F + 'static) -> Self {
+        Self {
+            make_format: Box::new(make_format),
+            parse: None,
+            skipped: Vec::new(),
+        }
+    }
+
+    /// Parse the output of every valid case with `parse`, and check that it contains one entry
+    /// that matches the case.
+    ///
+    /// The parsed entry must have every property and metric of the case, with the same value,
+    /// unit and total of observations, and the same timestamp (to the millisecond). It may have
+    /// other fields, like the fields a format adds to every entry.
+    pub fn parse_with<E: fmt::Display>(
+        mut self,
+        parse: impl Fn(&[u8]) -> Result<Vec<TestEntry>, E> + 'static,
+    ) -> Self {
+        self.parse = Some(Box::new(move |output| {
+            parse(output).map_err(|err| err.to_string())
+        }));
+        self
+    }
+
+    /// Don't run the case called `name`, see [`ConformanceCase::name`]. Running the suite panics if
+    /// no case is called `name`, so that a misspelled or removed case isn't silently run.
+    pub fn skip(mut self, name: impl Into<Cow<'static, str>>) -> Self {
+        self.skipped.push(name.into());
+        self
+    }
+
+    /// Run every case that isn't skipped, and return the failures, as `"case: reason"`
+    ///
+    /// # Panics
+    ///
+    /// If a name passed to [`FormatConformance::skip`] isn't the name of a case.
+    #[track_caller]
+    pub fn failures(mut self) -> Vec<String> {
+        let cases = ConformanceCase::all();
+        for name in &self.skipped {
+            assert!(
+                cases.iter().any(|case| case.name == *name),
+                "skipped conformance case `{name}` doesn't exist, the cases are: {}",
+                cases
+                    .iter()
+                    .map(|case| case.name)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+        let mut failures = Vec::new();
+        for case in cases {
+            if self.skipped.iter().any(|name| *name == case.name) {
+                continue;
+            }
+            if let Err(reason) = self.check(&case) {
+                failures.push(format!("{}: {reason}", case.name));
+            }
+        }
+        failures
+    }
+
+    /// Run every case that isn't skipped, and panic with the list of failures if there are any
+    #[track_caller]
+    pub fn run(self) {
+        let failures = self.failures();
+        if !failures.is_empty() {
+            panic!(
+                "format conformance failed ({} cases):\n  {}",
+                failures.len(),
+                failures.join("\n  ")
+            );
+        }
+    }
+
+    fn format(&mut self, format: &mut F, case: &ConformanceCase) -> Result<Vec<u8>, IoStreamError> {
+        let mut output = Vec::new();
+        format.format(case, &mut output)?;
+        Ok(output)
+    }
+
+    fn check(&mut self, case: &ConformanceCase) -> Result<(), String> {
+        let mut format = (self.make_format)();
+        let result = self.format(&mut format, case);
+        if !case.valid {
+            return match result {
+                Err(IoStreamError::Validation(_)) => Ok(()),
+                Err(IoStreamError::Io(err)) => Err(format!(
+                    "expected a validation error, got an I/O error: {err}"
+                )),
+                Ok(_) => Err("expected a validation error, but the entry was formatted".into()),
+            };
+        }
+        let output = result.map_err(|err| format!("formatting failed: {err}"))?;
+        if output.is_empty() {
+            return Err("the output is empty".into());
+        }
+        // format it again, after another entry, with the same format. Entries without a timestamp
+        // may be stamped with the current time.
+        let other = ConformanceCase::all()
+            .into_iter()
+            .find(|other| other.valid && other.name != case.name)
+            .expect("there are several valid cases");
+        self.format(&mut format, &other)
+            .map_err(|err| format!("formatting case {} failed: {err}", other.name))?;
+        let again = self
+            .format(&mut format, case)
+            .map_err(|err| format!("formatting again failed: {err}"))?;
+        if case.timestamp.is_some() && again != output {
+            return Err(format!(
+                "formatting again gave a different output:\n    {}\n    {}",
+                String::from_utf8_lossy(&output).trim_end(),
+                String::from_utf8_lossy(&again).trim_end()
+            ));
+        }
+        if let Some(parse) = &self.parse {
+            let parsed =
+                parse(&output).map_err(|err| format!("parsing the output failed: {err}"))?;
+            let [parsed] = &parsed[..] else {
+                return Err(format!("parsed {} entries, expected 1", parsed.len()));
+            };
+            matches_case(parsed, &to_test_entry(case))?;
+        }
+        Ok(())
+    }
+}
+
+impl<F> fmt::Debug for FormatConformance<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FormatConformance")
+            .field("parse", &self.parse.is_some())
+            .field("skipped", &self.skipped)
+            .finish_non_exhaustive()
+    }
+}
+
+fn matches_case(parsed: &TestEntry, expected: &TestEntry) -> Result<(), String> {
+    let millis = |timestamp: SystemTime| {
+        timestamp
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |since_epoch| since_epoch.as_millis())
+    };
+    if let Some(timestamp) = expected.timestamp
+        && parsed.timestamp.map(millis) != Some(millis(timestamp))
+    {
+        return Err(format!(
+            "parsed timestamp {:?}, expected {timestamp:?}",
+            parsed.timestamp
+        ));
+    }
+    for (name, value) in expected.values.iter() {
+        match parsed.values.get(name) {
+            Some(parsed) if parsed == value => {}
+            parsed => {
+                return Err(format!(
+                    "parsed property `{name}` {parsed:?}, expected {value:?}"
+                ));
+            }
+        }
+    }
+    for (name, metric) in expected.metrics.iter() {
+        let Some(parsed) = parsed.metrics.get(name) else {
+            return Err(format!("parsed no metric `{name}`"));
+        };
+        if parsed.unit != metric.unit {
+            return Err(format!(
+                "parsed metric `{name}` with unit {}, expected {}",
+                parsed.unit, metric.unit
+            ));
+        }
+        let (parsed_total, total) = (total(&parsed.distribution), total(&metric.distribution));
+        if (parsed_total - total).abs() > total.abs() * 1e-9 {
+            return Err(format!(
+                "parsed metric `{name}` with total {parsed_total}, expected {total}"
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn total(distribution: &[Observation]) -> f64 {
+    distribution.iter().map(Observation::total).sum()
+}
+
+/// An entry of the conformance suite, see the [module docs](self).
+///
+/// Cases can also be formatted directly, for checks that are specific to a format.
+#[derive(Debug, Clone)]
+pub struct ConformanceCase {
+    name: &'static str,
+    valid: bool,
+    timestamp: Option<SystemTime>,
+    properties: Vec<(&'static str, &'static str)>,
+    metrics: Vec<CaseMetric>,
+}
+
+#[derive(Debug, Clone)]
+struct CaseMetric {
+    name: &'static str,
+    observations: Vec<Observation>,
+    unit: Unit,
+    dimensions: Vec<(&'static str, &'static str)>,
+    error: Option<&'static str>,
+}
+
+fn metric(name: &'static str, observations: impl Into<Vec<Observation>>) -> CaseMetric {
+    CaseMetric {
+        name,
+        observations: observations.into(),
+        unit: Unit::None,
+        dimensions: Vec::new(),
+        error: None,
+    }
+}
+
+impl CaseMetric {
+    fn unit(self, unit: Unit) -> Self {
+        Self { unit, ..self }
+    }
+
+    fn dimensions(self, dimensions: impl Into<Vec<(&'static str, &'static str)>>) -> Self {
+        Self {
+            dimensions: dimensions.into(),
+            ..self
+        }
+    }
+}
+
+impl ConformanceCase {
+    fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            valid: true,
+            timestamp: Some(SystemTime::UNIX_EPOCH + Duration::from_millis(1_700_000_000_123)),
+            properties: Vec::new(),
+            metrics: Vec::new(),
+        }
+    }
+
+    fn property(mut self, name: &'static str, value: &'static str) -> Self {
+        self.properties.push((name, value));
+        self
+    }
+
+    fn metric(mut self, metric: CaseMetric) -> Self {
+        self.metrics.push(metric);
+        self
+    }
+
+    /// Every case of the suite
+    pub fn all() -> Vec<Self> {
+        use Observation::{Floating, Repeated, Unsigned};
+
+        let units = [
+            Unit::None,
+            Unit::Count,
+            Unit::Percent,
+            Unit::Second(NegativeScale::Micro),
+            Unit::Second(NegativeScale::Milli),
+            Unit::Second(NegativeScale::One),
+            Unit::Byte(PositiveScale::One),
+            Unit::Byte(PositiveScale::Kilo),
+            Unit::Byte(PositiveScale::Mega),
+            Unit::Byte(PositiveScale::Giga),
+            Unit::Byte(PositiveScale::Tera),
+            Unit::BytePerSecond(PositiveScale::One),
+            Unit::BytePerSecond(PositiveScale::Kilo),
+            Unit::BytePerSecond(PositiveScale::Mega),
+            Unit::BytePerSecond(PositiveScale::Giga),
+            Unit::BytePerSecond(PositiveScale::Tera),
+            Unit::Bit(PositiveScale::One),
+            Unit::Bit(PositiveScale::Kilo),
+            Unit::Bit(PositiveScale::Mega),
+            Unit::Bit(PositiveScale::Giga),
+            Unit::Bit(PositiveScale::Tera),
+            Unit::BitPerSecond(PositiveScale::One),
+            Unit::BitPerSecond(PositiveScale::Kilo),
+            Unit::BitPerSecond(PositiveScale::Mega),
+            Unit::BitPerSecond(PositiveScale::Giga),
+            Unit::BitPerSecond(PositiveScale::Tera),
+        ];
+        let every_unit = units
+            .into_iter()
+            .fold(ConformanceCase::new("every_unit"), |case, unit| {
+                case.metric(metric(unit.name(), [Unsigned(1)]).unit(unit))
+            });
+
+        vec![
+            ConformanceCase::new("properties").property("Operation", "Get"),
+            ConformanceCase::new("escaped_properties")
+                .property("Quote", "say \"hi\"")
+                .property("Backslash", "C:\\temp")
+                .property("Newline", "line 1\nline 2\ttabbed")
+                .property("Unicode", "caf\u{e9} \u{1f980}")
+                .property("Empty", ""),
+            ConformanceCase::new("unsigned_metrics")
+                .metric(metric("Zero", [Unsigned(0)]))
+                .metric(metric("Requests", [Unsigned(42)]))
+                .metric(metric("Large", [Unsigned(u32::MAX.into())])),
+            ConformanceCase::new("floating_metrics")
+                .metric(metric("Ratio", [Floating(0.25)]))
+                .metric(metric("Whole", [Floating(3.0)]))
+                .metric(metric("Small", [Floating(1.5e-7)])),
+            ConformanceCase::new("distributions")
+                .metric(metric("Latency", [Unsigned(3), Unsigned(5), Unsigned(5)]))
+                .metric(metric("Sizes", [Floating(0.5), Floating(2.5)])),
+            ConformanceCase::new("repeated_observations").metric(metric(
+                "Latency",
+                [
+                    Repeated {
+                        total: 30.0,
+                        occurrences: 3,
+                    },
+                    Unsigned(4),
+                ],
+            )),
+            every_unit,
+            ConformanceCase::new("custom_unit")
+                .metric(metric("Widgets", [Unsigned(2)]).unit(Unit::Custom("Widgets"))),
+            ConformanceCase::new("dimensions")
+                .property("Operation", "Get")
+                .metric(metric("Requests", [Unsigned(1)]).dimensions([("Host", "h1")]))
+                .metric(
+                    metric("Errors", [Unsigned(0)])
+                        .dimensions([("Host", "h1"), ("Region", "us-east-1")]),
+                ),
+            ConformanceCase {
+                timestamp: None,
+                ..ConformanceCase::new("no_timestamp").metric(metric("Requests", [Unsigned(1)]))
+            },
+            ConformanceCase::new("everything")
+                .property("Operation", "Get")
+                .property("Status", "200")
+                .metric(
+                    metric("Latency", [Floating(12.5), Unsigned(3)])
+                        .unit(Unit::Second(NegativeScale::Milli)),
+                )
+                .metric(metric("Bytes", [Unsigned(512)]).unit(Unit::Byte(PositiveScale::One))),
+            ConformanceCase {
+                valid: false,
+                ..ConformanceCase::new("validation_error")
+                    .property("Operation", "Get")
+                    .metric(CaseMetric {
+                        error: Some("the value is invalid"),
+                        ..metric("Requests", [Unsigned(1)])
+                    })
+            },
+        ]
+    }
+
+    /// The name of this case, as passed to [`FormatConformance::skip`]
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// False if formatting this case must fail with a validation error
+    pub fn is_valid(&self) -> bool {
+        self.valid
+    }
+}
+
+impl Entry for ConformanceCase {
+    fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+        if let Some(timestamp) = self.timestamp {
+            writer.timestamp(timestamp);
+        }
+        for (name, value) in &self.properties {
+            writer.value(*name, *value);
+        }
+        for metric in &self.metrics {
+            writer.value(metric.name, metric);
+        }
+    }
+}
+
+impl Value for CaseMetric {
+    fn write(&self, writer: impl ValueWriter) {
+        if let Some(error) = self.error {
+            writer.error(ValidationError::invalid(error));
+            return;
+        }
+        writer.metric(
+            self.observations.iter().copied(),
+            self.unit,
+            self.dimensions.iter().copied(),
+            MetricFlags::empty(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::*;
+
+    /// Writes the names of the fields, ignoring their values and errors
+    struct NamesOnly;
+
+    impl Format for NamesOnly {
+        fn format(
+            &mut self,
+            entry: &impl Entry,
+            output: &mut impl io::Write,
+        ) -> Result<(), IoStreamError> {
+            struct Names(Vec<String>);
+            impl<'a> EntryWriter<'a> for Names {
+                fn timestamp(&mut self, _timestamp: SystemTime) {}
+                fn value(&mut self, name: impl Into<Cow<'a, str>>, _value: &(impl Value + ?Sized)) {
+                    self.0.push(name.into().into_owned());
+                }
+                fn config(&mut self, _config: &'a dyn metrique_writer_core::EntryConfig) {}
+            }
+            let mut names = Names(Vec::new());
+            entry.write(&mut names);
+            writeln!(output, "{}", names.0.join(",")).map_err(IoStreamError::Io)
+        }
+    }
+
+    #[test]
+    fn reports_failing_cases() {
+        assert_eq!(
+            FormatConformance::new(|| NamesOnly).failures(),
+            ["validation_error: expected a validation error, but the entry was formatted"]
+        );
+        assert!(
+            FormatConformance::new(|| NamesOnly)
+                .skip("validation_error")
+                .failures()
+                .is_empty()
+        );
+        let failures = FormatConformance::new(|| NamesOnly)
+            .skip("validation_error")
+            .parse_with(|_| Ok::<_, String>(vec![]))
+            .failures();
+        assert!(
+            failures
+                .iter()
+                .all(|failure| failure.ends_with("parsed 0 entries, expected 1"))
+        );
+        assert_eq!(failures.len(), ConformanceCase::all().len() - 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "skipped conformance case `dimension` doesn't exist")]
+    fn unknown_skipped_cases_panic() {
+        FormatConformance::new(|| NamesOnly)
+            .skip("validation_error")
+            .skip("dimension")
+            .failures();
+    }
+}
@@ -6,10 +6,12 @@
 mod distribution;
 
 pub use distribution::{Distribution, Mean, VecDistribution};
-pub use metrique_writer_core::value::{FlagConstructor, ForceFlag};
 pub use metrique_writer_core::value::{
-    FormattedValue, Lifted, NotLifted, ToString, ValueFormatter,
+    BoolAsProperty, FormattedValue, Lifted, NoneAsNull, NoneAsZero, NotLifted, OmitZero,
+    OptionalValue, ToString, ValueFormatter,
 };
+pub use metrique_writer_core::value::{DEFAULT_TRUNCATION_MARKER, Truncated, truncate};
+pub use metrique_writer_core::value::{FlagConstructor, ForceFlag};
 pub use metrique_writer_core::value::{MetricFlags, MetricOptions, MetricValue};
 pub use metrique_writer_core::value::{Observation, Value, ValueWriter};
 pub use metrique_writer_core::value::{WithDimension, WithDimensions, WithVecDimensions};
@@ -0,0 +1,132 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+// The error handler is process-wide, so everything that uses it is checked in a single test.
+
+use std::{
+    io,
+    sync::{Arc, Mutex, mpsc},
+    time::SystemTime,
+};
+
+use metrique_writer::{
+    AnyEntrySink, Entry, EntryIoStream, EntrySink, IoStreamError, ValidationError, Value,
+    ValueWriter,
+    error_handler::{self, PipelineError},
+    sink::{BackgroundQueueBuilder, BufferSink, FlushImmediatelyBuilder},
+};
+use metrique_writer_format_emf::Emf;
+
+struct Invalid;
+
+impl Value for Invalid {
+    fn write(&self, writer: impl ValueWriter) {
+        writer.error(ValidationError::invalid("always invalid"));
+    }
+}
+
+#[derive(Entry)]
+struct InvalidEntry {
+    #[entry(timestamp)]
+    timestamp: SystemTime,
+    invalid: Invalid,
+}
+
+#[derive(Entry)]
+struct ValidEntry {
+    count: u64,
+}
+
+/// A stream whose writes fail
+struct BrokenPipe;
+
+impl EntryIoStream for BrokenPipe {
+    fn next(&mut self, _entry: &impl Entry) -> Result<(), IoStreamError> {
+        Err(io::Error::from(io::ErrorKind::BrokenPipe).into())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A stream that blocks the background thread until `release` is sent something
+struct Blocked {
+    release: mpsc::Receiver<()>,
+}
+
+impl EntryIoStream for Blocked {
+    fn next(&mut self, _entry: &impl Entry) -> Result<(), IoStreamError> {
+        let _ = self.release.recv();
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn sinks_report_errors_to_the_handler() {
+    let reported = Arc::new(Mutex::new(vec![]));
+    error_handler::set_error_handler({
+        let reported = Arc::clone(&reported);
+        move |error: &PipelineError<'_>| {
+            let kind = match error {
+                PipelineError::Validation { .. } => "validation",
+                PipelineError::Io { .. } => "io",
+                PipelineError::QueueFull { .. } => "queue full",
+                _ => "unknown",
+            };
+            reported
+                .lock()
+                .unwrap()
+                .push((kind, error.sink().to_owned(), error.drops_entry()));
+        }
+    });
+    let take = || std::mem::take(&mut *reported.lock().unwrap());
+
+    let buffer = BufferSink::new(Emf::all_validations("Ns".into(), vec![vec![]]));
+    buffer.append_any(InvalidEntry {
+        timestamp: SystemTime::UNIX_EPOCH,
+        invalid: Invalid,
+    });
+    assert_eq!(take(), [("validation", "buffer".to_owned(), true)]);
+
+    let immediate = FlushImmediatelyBuilder::new()
+        .metric_name("broken")
+        .build::<ValidEntry, _>(BrokenPipe);
+    immediate.append(ValidEntry { count: 1 });
+    assert_eq!(take(), [("io", "broken".to_owned(), false)]);
+
+    let (release, receiver) = mpsc::channel();
+    let (queue, handle) = BackgroundQueueBuilder::new()
+        .capacity(1)
+        .metric_name("blocked")
+        .build::<ValidEntry>(Blocked { release: receiver });
+    // the first entry blocks the background thread, the second fills the queue and the third
+    // pushes the second out, unless the thread hasn't popped the first one yet
+    for count in 0..4 {
+        queue.append(ValidEntry { count });
+    }
+    let full = take();
+    assert!(!full.is_empty());
+    assert!(
+        full.iter()
+            .all(|error| *error == ("queue full", "blocked".to_owned(), true)),
+        "{full:?}"
+    );
+    for _ in 0..4 {
+        release.send(()).ok();
+    }
+    drop(queue);
+    handle.shut_down();
+
+    // a panicking handler doesn't take the sink down
+    error_handler::set_error_handler(|_: &PipelineError<'_>| panic!("broken handler"));
+    immediate.append(ValidEntry { count: 2 });
+
+    error_handler::clear_error_handler();
+    immediate.append(ValidEntry { count: 3 });
+    assert!(take().is_empty());
+}
@@ -44,7 +44,7 @@ fn test_sink_records_entries() {
     assert_eq!(handle.entries().len(), 1);
     // check coercions & auto equality & auto ord
     assert_eq!(handle.entries()[0].metrics["a"], 1);
-    assert_eq!(handle.entries()[0].metrics["a"].as_bool(), true);
+    assert!(handle.entries()[0].metrics["a"].as_bool());
     assert_eq!(handle.entries()[0].metrics["a"], true);
     assert!(handle.entries()[0].metrics["a"] > 0);
 
@@ -120,3 +120,40 @@ fn render_queue_captures_emf_output() {
         "each appended entry should appear in the display"
     );
 }
+
+#[test]
+fn entry_derive_field_attributes() {
+    use std::time::Duration;
+
+    use metrique_writer::unit::{Byte, Millisecond, Second, Unit, UnitTag};
+
+    #[derive(Entry)]
+    #[entry(rename_all = "PascalCase")]
+    struct Request {
+        #[entry(unit = Second)]
+        latency: Duration,
+        #[entry(name = "Size", unit = Byte)]
+        request_size: u64,
+        #[entry(unit = Custom("Widgets"))]
+        widgets: u64,
+        #[entry(name = "Op", format = metrique_writer::value::ToString)]
+        operation: u32,
+        #[entry(unit = Millisecond)]
+        missing: Option<Duration>,
+    }
+
+    let entry = to_test_entry(Request {
+        latency: Duration::from_millis(1500),
+        request_size: 12,
+        widgets: 3,
+        operation: 7,
+        missing: None,
+    });
+    assert_eq!(entry.metrics["Latency"], 1.5);
+    assert_eq!(entry.metrics["Latency"].unit, Second::UNIT);
+    assert_eq!(entry.metrics["Size"], 12);
+    assert_eq!(entry.metrics["Size"].unit, Byte::UNIT);
+    assert_eq!(entry.metrics["Widgets"].unit, Unit::Custom("Widgets"));
+    assert_eq!(entry.values["Op"], "7");
+    assert!(!entry.metrics.contains_key("Missing"));
+}
@@ -60,7 +60,7 @@ struct StorageLayer;
 
 impl StorageLayer {
     fn read(&self, key: &str) -> (Vec<u8>, ReadMetrics) {
-        let cache_hit = key.len() % 2 == 0;
+        let cache_hit = key.len().is_multiple_of(2);
         let data = vec![0u8; 1024];
 
         let metrics = ReadMetrics {
@@ -251,7 +251,7 @@ async fn unset_then_set_example() {
     .append_on_drop(ServiceMetrics::sink());
 
     // Simulate business logic that determines values at runtime
-    let items_to_process = vec!["item1", "item2", "item3"];
+    let items_to_process = ["item1", "item2", "item3"];
     let request_came_from_api = true;
 
     // Process items and set count
@@ -0,0 +1,203 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Metrics for operations that are retried.
+//!
+//! [`Attempts`] is meant to be flattened into a metrics struct, and records every attempt of an
+//! operation with [`Attempts::record_attempt`] and the time spent waiting between attempts with
+//! [`Attempts::add_retry_delay`]. It records:
+//!
+//! 1. An `Attempts` count, the number of recorded attempts.
+//! 2. A `RetryDelay` duration, the total time spent waiting between attempts.
+//! 3. A `MaxAttemptLatency` duration, the latency of the slowest attempt, only if there was an
+//!    attempt.
+//! 4. A `LastErrorCode` property, the [`ErrorCode`] of the last attempt that failed, only if an
+//!    attempt failed.
+//!
+//! The names follow the `rename_all` of the containing struct, and a `prefix` can be used to tell
+//! apart the attempts of several operations.
+//!
+//! ```rust
+//! use std::borrow::Cow;
+//! use std::time::Duration;
+//! use metrique::attempts::{Attempts, ErrorCode};
+//! use metrique::unit_of_work::metrics;
+//!
+//! enum DbError {
+//!     Busy,
+//! }
+//!
+//! impl ErrorCode for DbError {
+//!     fn error_code(&self) -> Cow<'_, str> {
+//!         match self {
+//!             DbError::Busy => "Busy".into(),
+//!         }
+//!     }
+//! }
+//!
+//! #[metrics(rename_all = "PascalCase")]
+//! struct RequestMetrics {
+//!     #[metrics(flatten, prefix = "Db")]
+//!     db: Attempts,
+//! }
+//!
+//! let mut metrics = RequestMetrics { db: Attempts::new() };
+//! metrics.db.record_attempt(&Err::<(), _>(DbError::Busy), Duration::from_millis(30));
+//! metrics.db.add_retry_delay(Duration::from_millis(100));
+//! metrics.db.record_attempt(&Ok::<(), DbError>(()), Duration::from_millis(10));
+//!
+//! let entry = metrique::test_util::test_metric(metrics);
+//! assert_eq!(entry.metrics["DbAttempts"], 2);
+//! assert_eq!(entry.metrics["DbRetryDelay"], 100);
+//! assert_eq!(entry.metrics["DbMaxAttemptLatency"], 30);
+//! assert_eq!(entry.values["DbLastErrorCode"], "Busy");
+//! ```
+
+use std::borrow::Cow;
+use std::time::Duration;
+
+use metrique_core::{CloseValue, InflectableEntry, NameStyle};
+use metrique_writer::EntryWriter;
+
+use crate::names::inflected_name;
+
+/// A short, low-cardinality code for an error, recorded by [`Attempts`] as `LastErrorCode`.
+pub trait ErrorCode {
+    /// The code of this error, like `ThrottlingException`
+    fn error_code(&self) -> Cow<'_, str>;
+}
+
+impl<E: ErrorCode + ?Sized> ErrorCode for &E {
+    fn error_code(&self) -> Cow<'_, str> {
+        (**self).error_code()
+    }
+}
+
+impl<E: ErrorCode + ?Sized> ErrorCode for Box<E> {
+    fn error_code(&self) -> Cow<'_, str> {
+        (**self).error_code()
+    }
+}
+
+/// The [`ErrorKind`](std::io::ErrorKind) of the error, like `TimedOut`
+impl ErrorCode for std::io::Error {
+    fn error_code(&self) -> Cow<'_, str> {
+        format!("{:?}", self.kind()).into()
+    }
+}
+
+/// Records the attempts of a retried operation, see the [module docs](self).
+#[derive(Debug, Clone, Default)]
+pub struct Attempts {
+    count: u64,
+    retry_delay: Duration,
+    max_latency: Option<Duration>,
+    last_error_code: Option<String>,
+}
+
+impl Attempts {
+    /// Create an `Attempts` with no attempts
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record an attempt that took `latency` and returned `result`
+    pub fn record_attempt<T, E: ErrorCode>(&mut self, result: &Result<T, E>, latency: Duration) {
+        self.count += 1;
+        self.max_latency = Some(self.max_latency.map_or(latency, |max| max.max(latency)));
+        if let Err(error) = result {
+            self.last_error_code = Some(error.error_code().into_owned());
+        }
+    }
+
+    /// Add time spent waiting before retrying, like the backoff between two attempts
+    pub fn add_retry_delay(&mut self, delay: Duration) {
+        self.retry_delay += delay;
+    }
+
+    /// The number of recorded attempts
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// The code of the last error, if an attempt failed
+    pub fn last_error_code(&self) -> Option<&str> {
+        self.last_error_code.as_deref()
+    }
+}
+
+impl CloseValue for &Attempts {
+    type Closed = Attempts;
+
+    fn close(self) -> Self::Closed {
+        self.clone()
+    }
+}
+
+impl CloseValue for Attempts {
+    type Closed = Attempts;
+
+    fn close(self) -> Self::Closed {
+        self
+    }
+}
+
+inflected_name!(AttemptsName, "attempts", "Attempts", "attempts", "attempts");
+inflected_name!(
+    RetryDelayName,
+    "retry_delay",
+    "RetryDelay",
+    "retry_delay",
+    "retry-delay"
+);
+inflected_name!(
+    MaxAttemptLatencyName,
+    "max_attempt_latency",
+    "MaxAttemptLatency",
+    "max_attempt_latency",
+    "max-attempt-latency"
+);
+inflected_name!(
+    LastErrorCodeName,
+    "last_error_code",
+    "LastErrorCode",
+    "last_error_code",
+    "last-error-code"
+);
+
+impl<NS: NameStyle> InflectableEntry<NS> for Attempts {
+    fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+        writer.value(AttemptsName::value::<NS>(), &self.count);
+        writer.value(RetryDelayName::value::<NS>(), &self.retry_delay);
+        writer.value(MaxAttemptLatencyName::value::<NS>(), &self.max_latency);
+        writer.value(LastErrorCodeName::value::<NS>(), &self.last_error_code);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::Attempts;
+
+    #[test]
+    fn keeps_max_latency_and_last_error() {
+        let mut attempts = Attempts::new();
+        let timed_out = std::io::Error::from(std::io::ErrorKind::TimedOut);
+        attempts.record_attempt(&Err::<(), _>(timed_out), Duration::from_millis(5));
+        attempts.record_attempt(&Ok::<_, std::io::Error>(()), Duration::from_millis(3));
+        assert_eq!(attempts.count(), 2);
+        assert_eq!(attempts.max_latency, Some(Duration::from_millis(5)));
+        assert_eq!(attempts.last_error_code(), Some("TimedOut"));
+
+        let entry = metrique_writer::test_util::to_test_entry(crate::RootEntry::new(attempts));
+        assert_eq!(entry.metrics["attempts"], 2);
+        assert_eq!(entry.metrics["retry_delay"], 0);
+
+        let entry =
+            metrique_writer::test_util::to_test_entry(crate::RootEntry::new(Attempts::new()));
+        assert_eq!(entry.metrics["attempts"], 0);
+        assert!(!entry.metrics.contains_key("max_attempt_latency"));
+        assert!(!entry.values.contains_key("last_error_code"));
+    }
+}
@@ -0,0 +1,294 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Metrics for AWS SDK calls, enabled by the `aws` feature.
+//!
+//! [`sdk_error`] classifies the [`SdkError`] of an AWS SDK call into the standard counters every
+//! AWS-calling service records. It is used with `#[metrics(flatten, with = ...)]` on an
+//! `Option<SdkError<E>>` or `Result<T, SdkError<E>>` field, like `#[metrics(error)]`, and records:
+//!
+//! 1. `Failure`, `Throttle`, `Timeout` and `Fault` counts, each `0` or `1`. Every error is a
+//!    `Failure`, and at most one of `Throttle`, `Timeout` and `Fault` is `1`, according to its
+//!    [`SdkErrorKind`].
+//! 2. An `ErrorCode` property, only if there is an error. This is the error code returned by the
+//!    service (like `ThrottlingException`), or the kind of the failure when the service did not
+//!    return an error (like `DispatchFailure`).
+//!
+//! The names follow the `rename_all` of the containing struct, and a `prefix` can be used to tell
+//! apart several SDK calls.
+//!
+//! ```rust
+//! use aws_smithy_runtime_api::client::orchestrator::HttpResponse;
+//! use aws_smithy_runtime_api::client::result::SdkError;
+//! use aws_smithy_runtime_api::http::StatusCode;
+//! use aws_smithy_types::body::SdkBody;
+//! use aws_smithy_types::error::ErrorMetadata;
+//! use metrique::unit_of_work::metrics;
+//!
+//! #[metrics(rename_all = "PascalCase")]
+//! struct RequestMetrics {
+//!     #[metrics(flatten, with = metrique::aws::sdk_error, prefix = "DynamoDb")]
+//!     get_item: Option<SdkError<ErrorMetadata, HttpResponse>>,
+//! }
+//!
+//! let throttled = SdkError::service_error(
+//!     ErrorMetadata::builder().code("ProvisionedThroughputExceededException").build(),
+//!     HttpResponse::new(StatusCode::try_from(400).unwrap(), SdkBody::empty()),
+//! );
+//! let entry = metrique::test_util::test_metric(RequestMetrics {
+//!     get_item: Some(throttled),
+//! });
+//! assert_eq!(entry.metrics["DynamoDbFailure"], 1);
+//! assert_eq!(entry.metrics["DynamoDbThrottle"], 1);
+//! assert_eq!(entry.metrics["DynamoDbFault"], 0);
+//! assert_eq!(
+//!     entry.values["DynamoDbErrorCode"],
+//!     "ProvisionedThroughputExceededException"
+//! );
+//! ```
+//!
+//! [`MetricsInterceptor`] is an SDK interceptor that records the calls of a client, with their
+//! attempts, throttles and the time spent in each phase, into an [`SdkCallMetrics`] field through a
+//! [`FieldHandle`](crate::FieldHandle), so that calls are accounted in the entry of the caller
+//! without wrapping each of them.
+
+use aws_smithy_runtime_api::client::orchestrator::HttpResponse;
+use aws_smithy_runtime_api::client::result::SdkError;
+use aws_smithy_types::error::metadata::ProvideErrorMetadata;
+use metrique_core::{InflectableEntry, NameStyle};
+use metrique_writer::EntryWriter;
+
+use crate::error::AsError;
+use crate::names::inflected_name;
+
+mod interceptor;
+
+pub use interceptor::{MetricsInterceptor, SdkCallMetrics, SdkCallMetricsEntry};
+
+/// Error codes that AWS services return when a request is throttled
+pub const THROTTLING_ERROR_CODES: &[&str] = &[
+    "Throttling",
+    "ThrottlingException",
+    "ThrottledException",
+    "RequestThrottledException",
+    "TooManyRequestsException",
+    "ProvisionedThroughputExceededException",
+    "TransactionInProgressException",
+    "RequestLimitExceeded",
+    "BandwidthLimitExceeded",
+    "LimitExceededException",
+    "RequestThrottled",
+    "SlowDown",
+    "PriorRequestNotComplete",
+    "EC2ThrottledException",
+];
+
+/// How an [`SdkError`] is counted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SdkErrorKind {
+    /// The service throttled the request, with a [throttling error code](THROTTLING_ERROR_CODES)
+    /// or a `429` status
+    Throttle,
+    /// The request timed out, or the connection timed out while dispatching it
+    Timeout,
+    /// The service returned a `5xx` status, or the request could not be dispatched or its
+    /// response could not be parsed
+    Fault,
+    /// Any other error, like a validation error returned by the service or a request that
+    /// could not be constructed
+    Client,
+}
+
+impl SdkErrorKind {
+    /// Classify `error`
+    pub fn classify<E: ProvideErrorMetadata>(error: &SdkError<E, HttpResponse>) -> Self {
+        match error {
+            SdkError::TimeoutError(_) => SdkErrorKind::Timeout,
+            SdkError::DispatchFailure(failure) if failure.is_timeout() => SdkErrorKind::Timeout,
+            SdkError::DispatchFailure(_) | SdkError::ResponseError(_) => SdkErrorKind::Fault,
+            SdkError::ServiceError(service_error) => {
+                let status = service_error.raw().status().as_u16();
+                let throttling_code = error
+                    .code()
+                    .is_some_and(|code| THROTTLING_ERROR_CODES.contains(&code));
+                if throttling_code || status == 429 {
+                    SdkErrorKind::Throttle
+                } else if (500..600).contains(&status) {
+                    SdkErrorKind::Fault
+                } else {
+                    SdkErrorKind::Client
+                }
+            }
+            _ => SdkErrorKind::Client,
+        }
+    }
+}
+
+/// The closed value of an [`SdkError`] field, see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct SdkErrorEntry {
+    error: Option<RecordedSdkError>,
+}
+
+#[derive(Debug, Clone)]
+struct RecordedSdkError {
+    kind: SdkErrorKind,
+    code: String,
+}
+
+impl SdkErrorEntry {
+    /// Record the [`SdkError`] in `value`, if any
+    pub fn new<T, E>(value: &T) -> Self
+    where
+        T: AsError<Error = SdkError<E, HttpResponse>> + ?Sized,
+        E: ProvideErrorMetadata,
+    {
+        let error = value.as_error().map(|error| RecordedSdkError {
+            kind: SdkErrorKind::classify(error),
+            code: error_code(error).to_owned(),
+        });
+        Self { error }
+    }
+
+    /// The kind of the recorded error, if there is one
+    pub fn kind(&self) -> Option<SdkErrorKind> {
+        self.error.as_ref().map(|error| error.kind)
+    }
+}
+
+fn error_code<E: ProvideErrorMetadata>(error: &SdkError<E, HttpResponse>) -> &str {
+    match error {
+        SdkError::ConstructionFailure(_) => "ConstructionFailure",
+        SdkError::TimeoutError(_) => "TimeoutError",
+        SdkError::DispatchFailure(_) => "DispatchFailure",
+        SdkError::ResponseError(_) => "ResponseError",
+        _ => error.code().unwrap_or("Unknown"),
+    }
+}
+
+/// The same code as the `ErrorCode` property, for use with [`Attempts`](crate::attempts::Attempts)
+impl<E: ProvideErrorMetadata> crate::attempts::ErrorCode for SdkError<E, HttpResponse> {
+    fn error_code(&self) -> std::borrow::Cow<'_, str> {
+        error_code(self).into()
+    }
+}
+
+inflected_name!(FailureName, "failure", "Failure", "failure", "failure");
+inflected_name!(ThrottleName, "throttle", "Throttle", "throttle", "throttle");
+inflected_name!(TimeoutName, "timeout", "Timeout", "timeout", "timeout");
+inflected_name!(FaultName, "fault", "Fault", "fault", "fault");
+inflected_name!(
+    ErrorCodeName,
+    "error_code",
+    "ErrorCode",
+    "error_code",
+    "error-code"
+);
+
+impl<NS: NameStyle> InflectableEntry<NS> for SdkErrorEntry {
+    fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+        let kind = self.kind();
+        writer.value(FailureName::value::<NS>(), &u64::from(kind.is_some()));
+        for (name, counted) in [
+            (ThrottleName::value::<NS>(), SdkErrorKind::Throttle),
+            (TimeoutName::value::<NS>(), SdkErrorKind::Timeout),
+            (FaultName::value::<NS>(), SdkErrorKind::Fault),
+        ] {
+            writer.value(name, &u64::from(kind == Some(counted)));
+        }
+        if let Some(error) = &self.error {
+            writer.value(ErrorCodeName::value::<NS>(), &error.code);
+        }
+    }
+}
+
+/// Use with `#[metrics(flatten, with = metrique::aws::sdk_error)]` on an `Option<SdkError<E>>`
+/// or `Result<T, SdkError<E>>` field, see the [module docs](self).
+pub mod sdk_error {
+    use aws_smithy_runtime_api::client::orchestrator::HttpResponse;
+    use aws_smithy_runtime_api::client::result::SdkError;
+    use aws_smithy_types::error::metadata::ProvideErrorMetadata;
+
+    use crate::error::AsError;
+
+    /// The closed value of the field
+    pub type Closed = super::SdkErrorEntry;
+
+    /// Close the field, classifying its error
+    pub fn close<T, E>(value: &T) -> Closed
+    where
+        T: AsError<Error = SdkError<E, HttpResponse>> + ?Sized,
+        E: ProvideErrorMetadata,
+    {
+        Closed::new(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use aws_smithy_runtime_api::client::result::{ConnectorError, SdkError};
+    use aws_smithy_runtime_api::http::StatusCode;
+    use aws_smithy_types::body::SdkBody;
+    use aws_smithy_types::error::ErrorMetadata;
+
+    use super::*;
+
+    fn service_error(code: Option<&str>, status: u16) -> SdkError<ErrorMetadata, HttpResponse> {
+        let mut meta = ErrorMetadata::builder();
+        if let Some(code) = code {
+            meta = meta.code(code);
+        }
+        SdkError::service_error(
+            meta.build(),
+            HttpResponse::new(StatusCode::try_from(status).unwrap(), SdkBody::empty()),
+        )
+    }
+
+    #[test]
+    fn classifies_sdk_errors() {
+        let timeout: SdkError<ErrorMetadata, HttpResponse> = SdkError::timeout_error("slow");
+        let connect_timeout: SdkError<ErrorMetadata, HttpResponse> =
+            SdkError::dispatch_failure(ConnectorError::timeout("slow".into()));
+        let io: SdkError<ErrorMetadata, HttpResponse> =
+            SdkError::dispatch_failure(ConnectorError::io("reset".into()));
+        let construction: SdkError<ErrorMetadata, HttpResponse> =
+            SdkError::construction_failure("missing field");
+        for (error, kind, code) in [
+            (timeout, SdkErrorKind::Timeout, "TimeoutError"),
+            (connect_timeout, SdkErrorKind::Timeout, "DispatchFailure"),
+            (io, SdkErrorKind::Fault, "DispatchFailure"),
+            (construction, SdkErrorKind::Client, "ConstructionFailure"),
+            (
+                service_error(Some("ThrottlingException"), 400),
+                SdkErrorKind::Throttle,
+                "ThrottlingException",
+            ),
+            (service_error(None, 429), SdkErrorKind::Throttle, "Unknown"),
+            (
+                service_error(Some("SlowDown"), 503),
+                SdkErrorKind::Throttle,
+                "SlowDown",
+            ),
+            (
+                service_error(Some("InternalServerError"), 500),
+                SdkErrorKind::Fault,
+                "InternalServerError",
+            ),
+            (
+                service_error(Some("ValidationException"), 400),
+                SdkErrorKind::Client,
+                "ValidationException",
+            ),
+        ] {
+            assert_eq!(SdkErrorKind::classify(&error), kind, "{code}");
+            assert_eq!(error_code(&error), code);
+        }
+    }
+
+    #[test]
+    fn no_error_records_zero_counts() {
+        let entry = SdkErrorEntry::new(&Ok::<(), SdkError<ErrorMetadata, HttpResponse>>(()));
+        assert_eq!(entry.kind(), None);
+    }
+}
@@ -0,0 +1,374 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use aws_smithy_runtime_api::box_error::BoxError;
+use aws_smithy_runtime_api::client::interceptors::Intercept;
+use aws_smithy_runtime_api::client::interceptors::context::{
+    AfterDeserializationInterceptorContextRef, BeforeDeserializationInterceptorContextRef,
+    BeforeSerializationInterceptorContextRef, BeforeTransmitInterceptorContextRef,
+    FinalizerInterceptorContextRef,
+};
+use aws_smithy_runtime_api::client::retries::classifiers::{
+    ClassifyRetry, RetryAction, RetryReason,
+};
+use aws_smithy_runtime_api::client::runtime_components::RuntimeComponents;
+use aws_smithy_types::config_bag::{ConfigBag, Storable, StoreReplace};
+use aws_smithy_types::retry::ErrorKind;
+use metrique_core::{CloseValue, InflectableEntry, NameStyle};
+use metrique_writer::EntryWriter;
+
+use crate::FieldHandle;
+use crate::names::inflected_name;
+use crate::timers::DurationCounter;
+
+/// The client metrics of the AWS SDK calls made with a [`MetricsInterceptor`].
+///
+/// Flatten it into a metric, usually with a `prefix` per service, and pass a [`FieldHandle`] to
+/// it to the interceptor. It records:
+///
+/// 1. `Calls`, `Attempts`, `Throttles` and `Failures` counts. Every attempt whose response is
+///    throttled counts as a throttle, even if a retry then succeeds, and a call that returns an
+///    error counts as a failure.
+/// 2. `SerializationTime`, `TransmitTime`, `DeserializationTime` and `Latency` durations, summed
+///    over the calls (and, for transmit and deserialization, over their attempts). `Latency` is
+///    the time from the start to the end of each call, including the retry backoff.
+///
+/// See [`MetricsInterceptor`] for an example.
+#[derive(Debug, Default)]
+pub struct SdkCallMetrics {
+    calls: AtomicU64,
+    attempts: AtomicU64,
+    throttles: AtomicU64,
+    failures: AtomicU64,
+    serialization_time: DurationCounter,
+    transmit_time: DurationCounter,
+    deserialization_time: DurationCounter,
+    latency: DurationCounter,
+}
+
+impl SdkCallMetrics {
+    /// Create an `SdkCallMetrics` with no calls
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of started calls
+    pub fn calls(&self) -> u64 {
+        self.calls.load(Ordering::Relaxed)
+    }
+
+    /// The number of attempts, over all calls
+    pub fn attempts(&self) -> u64 {
+        self.attempts.load(Ordering::Relaxed)
+    }
+
+    /// The number of throttled attempts, over all calls
+    pub fn throttles(&self) -> u64 {
+        self.throttles.load(Ordering::Relaxed)
+    }
+
+    /// The number of calls that returned an error
+    pub fn failures(&self) -> u64 {
+        self.failures.load(Ordering::Relaxed)
+    }
+}
+
+/// The closed value of [`SdkCallMetrics`]
+#[derive(Debug, Clone)]
+pub struct SdkCallMetricsEntry {
+    calls: u64,
+    attempts: u64,
+    throttles: u64,
+    failures: u64,
+    serialization_time: Duration,
+    transmit_time: Duration,
+    deserialization_time: Duration,
+    latency: Duration,
+}
+
+impl CloseValue for &SdkCallMetrics {
+    type Closed = SdkCallMetricsEntry;
+
+    fn close(self) -> Self::Closed {
+        SdkCallMetricsEntry {
+            calls: self.calls(),
+            attempts: self.attempts(),
+            throttles: self.throttles(),
+            failures: self.failures(),
+            serialization_time: self.serialization_time.total(),
+            transmit_time: self.transmit_time.total(),
+            deserialization_time: self.deserialization_time.total(),
+            latency: self.latency.total(),
+        }
+    }
+}
+
+impl CloseValue for SdkCallMetrics {
+    type Closed = SdkCallMetricsEntry;
+
+    fn close(self) -> Self::Closed {
+        (&self).close()
+    }
+}
+
+inflected_name!(CallsName, "calls", "Calls", "calls", "calls");
+inflected_name!(AttemptsName, "attempts", "Attempts", "attempts", "attempts");
+inflected_name!(
+    ThrottlesName,
+    "throttles",
+    "Throttles",
+    "throttles",
+    "throttles"
+);
+inflected_name!(FailuresName, "failures", "Failures", "failures", "failures");
+inflected_name!(
+    SerializationTimeName,
+    "serialization_time",
+    "SerializationTime",
+    "serialization_time",
+    "serialization-time"
+);
+inflected_name!(
+    TransmitTimeName,
+    "transmit_time",
+    "TransmitTime",
+    "transmit_time",
+    "transmit-time"
+);
+inflected_name!(
+    DeserializationTimeName,
+    "deserialization_time",
+    "DeserializationTime",
+    "deserialization_time",
+    "deserialization-time"
+);
+inflected_name!(LatencyName, "latency", "Latency", "latency", "latency");
+
+impl<NS: NameStyle> InflectableEntry<NS> for SdkCallMetricsEntry {
+    fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+        writer.value(CallsName::value::<NS>(), &self.calls);
+        writer.value(AttemptsName::value::<NS>(), &self.attempts);
+        writer.value(ThrottlesName::value::<NS>(), &self.throttles);
+        writer.value(FailuresName::value::<NS>(), &self.failures);
+        writer.value(
+            SerializationTimeName::value::<NS>(),
+            &self.serialization_time,
+        );
+        writer.value(TransmitTimeName::value::<NS>(), &self.transmit_time);
+        writer.value(
+            DeserializationTimeName::value::<NS>(),
+            &self.deserialization_time,
+        );
+        writer.value(LatencyName::value::<NS>(), &self.latency);
+    }
+}
+
+/// An AWS SDK interceptor that records the calls it sees into an [`SdkCallMetrics`].
+///
+/// The interceptor holds a [`FieldHandle`], so the entry that contains the metrics is only
+/// appended once the interceptor is dropped. Add it to the calls of one unit of work with
+/// `customize()`, rather than to the config of a long-lived client.
+///
+/// A throttled attempt is one that received an HTTP 429 response, or that a retry classifier of
+/// the client classifies as a throttling error, which covers the throttling error codes of AWS
+/// services.
+///
+/// ```rust,ignore
+/// use metrique::aws::{MetricsInterceptor, SdkCallMetrics};
+/// use metrique::unit_of_work::metrics;
+///
+/// #[metrics(rename_all = "PascalCase")]
+/// struct RequestMetrics {
+///     #[metrics(flatten, prefix = "DynamoDb", handle)]
+///     dynamodb: SdkCallMetrics,
+/// }
+///
+/// async fn handle_request(client: &aws_sdk_dynamodb::Client, sink: metrique::DefaultSink) {
+///     let metrics = RequestMetrics {
+///         dynamodb: SdkCallMetrics::new(),
+///     }
+///     .append_on_drop(sink);
+///     let dynamodb = metrics.handles().dynamodb;
+///
+///     let item = client
+///         .get_item()
+///         .table_name("users")
+///         .customize()
+///         .interceptor(MetricsInterceptor::new(dynamodb))
+///         .send()
+///         .await;
+///     // records DynamoDbCalls, DynamoDbAttempts, DynamoDbThrottles, DynamoDbLatency, ...
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct MetricsInterceptor {
+    metrics: FieldHandle<SdkCallMetrics>,
+}
+
+impl MetricsInterceptor {
+    /// An interceptor that records into `metrics`
+    pub fn new(metrics: FieldHandle<SdkCallMetrics>) -> Self {
+        Self { metrics }
+    }
+}
+
+/// When the current call started
+#[derive(Debug, Clone)]
+struct CallStart(Instant);
+
+impl Storable for CallStart {
+    type Storer = StoreReplace<Self>;
+}
+
+/// When the current phase (serialization, transmit or deserialization) started
+#[derive(Debug, Clone)]
+struct PhaseStart(Instant);
+
+impl Storable for PhaseStart {
+    type Storer = StoreReplace<Self>;
+}
+
+fn start_phase(cfg: &mut ConfigBag) {
+    cfg.interceptor_state()
+        .store_put(PhaseStart(Instant::now()));
+}
+
+/// Add the time since the phase started to `counter`, and end the phase
+fn end_phase(cfg: &mut ConfigBag, counter: &DurationCounter) {
+    if let Some(PhaseStart(start)) = cfg.load::<PhaseStart>().cloned() {
+        counter.add(start.elapsed());
+        cfg.interceptor_state().unset::<PhaseStart>();
+    }
+}
+
+fn is_throttled(
+    context: &FinalizerInterceptorContextRef<'_>,
+    components: &RuntimeComponents,
+) -> bool {
+    if context
+        .response()
+        .is_some_and(|response| response.status().as_u16() == 429)
+    {
+        return true;
+    }
+    components.retry_classifiers().any(|classifier| {
+        matches!(
+            classifier.classify_retry(context.inner()),
+            RetryAction::RetryIndicated(RetryReason::RetryableError {
+                kind: ErrorKind::ThrottlingError,
+                ..
+            })
+        )
+    })
+}
+
+impl Intercept for MetricsInterceptor {
+    fn name(&self) -> &'static str {
+        "MetricsInterceptor"
+    }
+
+    fn read_before_execution(
+        &self,
+        _context: &BeforeSerializationInterceptorContextRef<'_>,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        self.metrics.calls.fetch_add(1, Ordering::Relaxed);
+        cfg.interceptor_state().store_put(CallStart(Instant::now()));
+        Ok(())
+    }
+
+    fn read_before_serialization(
+        &self,
+        _context: &BeforeSerializationInterceptorContextRef<'_>,
+        _runtime_components: &RuntimeComponents,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        start_phase(cfg);
+        Ok(())
+    }
+
+    fn read_after_serialization(
+        &self,
+        _context: &BeforeTransmitInterceptorContextRef<'_>,
+        _runtime_components: &RuntimeComponents,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        end_phase(cfg, &self.metrics.serialization_time);
+        Ok(())
+    }
+
+    fn read_before_attempt(
+        &self,
+        _context: &BeforeTransmitInterceptorContextRef<'_>,
+        _runtime_components: &RuntimeComponents,
+        _cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        self.metrics.attempts.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn read_before_transmit(
+        &self,
+        _context: &BeforeTransmitInterceptorContextRef<'_>,
+        _runtime_components: &RuntimeComponents,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        start_phase(cfg);
+        Ok(())
+    }
+
+    fn read_after_transmit(
+        &self,
+        _context: &BeforeDeserializationInterceptorContextRef<'_>,
+        _runtime_components: &RuntimeComponents,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        end_phase(cfg, &self.metrics.transmit_time);
+        start_phase(cfg);
+        Ok(())
+    }
+
+    fn read_after_deserialization(
+        &self,
+        _context: &AfterDeserializationInterceptorContextRef<'_>,
+        _runtime_components: &RuntimeComponents,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        end_phase(cfg, &self.metrics.deserialization_time);
+        Ok(())
+    }
+
+    fn read_after_attempt(
+        &self,
+        context: &FinalizerInterceptorContextRef<'_>,
+        runtime_components: &RuntimeComponents,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        // an attempt whose transmit failed (like a timeout) ends without `read_after_transmit`
+        if context.response().is_none() {
+            end_phase(cfg, &self.metrics.transmit_time);
+        }
+        if is_throttled(context, runtime_components) {
+            self.metrics.throttles.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+
+    fn read_after_execution(
+        &self,
+        context: &FinalizerInterceptorContextRef<'_>,
+        _runtime_components: &RuntimeComponents,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        if let Some(CallStart(start)) = cfg.load::<CallStart>() {
+            self.metrics.latency.add(start.elapsed());
+        }
+        if !matches!(context.output_or_error(), Some(Ok(_))) {
+            self.metrics.failures.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+}
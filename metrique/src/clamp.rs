@@ -0,0 +1,141 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Support for the `#[metrics(clamp(...))]` field attribute.
+//!
+//! `#[metrics(clamp(min = ..., max = ...))]` bounds the closed value of a field, which protects
+//! aggregations from sentinel values (like `u64::MAX`) that would otherwise end up in dashboards.
+//! The bounds are expressions of the closed type of the field, and at least one of them must be set.
+//!
+//! By default, out-of-range values are replaced with the nearest bound. With
+//! `out_of_range = "drop"`, they are not emitted at all.
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use metrique::unit::Millisecond;
+//! use metrique::unit_of_work::metrics;
+//!
+//! #[metrics(rename_all = "PascalCase")]
+//! struct RequestMetrics {
+//!     #[metrics(clamp(max = 100))]
+//!     retries: u64,
+//!     #[metrics(clamp(max = Duration::from_secs(60), out_of_range = "drop"), unit = Millisecond)]
+//!     latency: Duration,
+//! }
+//!
+//! let entry = metrique::test_util::test_metric(RequestMetrics {
+//!     retries: u64::MAX,
+//!     latency: Duration::MAX,
+//! });
+//! assert_eq!(entry.metrics["Retries"], 100);
+//! assert!(!entry.metrics.contains_key("Latency"));
+//! ```
+//!
+//! Clamping is supported for the primitive numeric types, [`Duration`], and [`Option`]s of those.
+//! `None` is always considered in range.
+//!
+//! [`Duration`]: std::time::Duration
+
+use std::time::Duration;
+
+/// A closed value that can be used with `#[metrics(clamp(...))]`
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` can't be used with `#[metrics(clamp(...))]`",
+    note = "`clamp` applies to the closed value of the field, which must be a primitive number, a `Duration`, or an `Option` of those"
+)]
+pub trait Clamp: Sized {
+    /// The type of the `min` and `max` bounds
+    type Bound;
+
+    /// Returns true if the value is within `min` and `max` (both inclusive)
+    fn is_in_range(&self, min: Option<&Self::Bound>, max: Option<&Self::Bound>) -> bool;
+
+    /// Replace the value with the nearest bound if it is out of range.
+    ///
+    /// Must not panic, even if `min` is greater than `max`.
+    fn clamp_to(self, min: Option<&Self::Bound>, max: Option<&Self::Bound>) -> Self;
+}
+
+macro_rules! impl_clamp {
+    ($($ty:ty),*) => {
+        $(
+            impl Clamp for $ty {
+                type Bound = $ty;
+
+                fn is_in_range(&self, min: Option<&$ty>, max: Option<&$ty>) -> bool {
+                    min.is_none_or(|min| self >= min) && max.is_none_or(|max| self <= max)
+                }
+
+                fn clamp_to(self, min: Option<&$ty>, max: Option<&$ty>) -> Self {
+                    match (min, max) {
+                        (Some(min), _) if self < *min => *min,
+                        (_, Some(max)) if self > *max => *max,
+                        _ => self,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_clamp!(
+    u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64, Duration
+);
+
+impl<T: Clamp> Clamp for Option<T> {
+    type Bound = T::Bound;
+
+    fn is_in_range(&self, min: Option<&T::Bound>, max: Option<&T::Bound>) -> bool {
+        self.as_ref()
+            .is_none_or(|value| value.is_in_range(min, max))
+    }
+
+    fn clamp_to(self, min: Option<&T::Bound>, max: Option<&T::Bound>) -> Self {
+        self.map(|value| value.clamp_to(min, max))
+    }
+}
+
+/// Replace `value` with the nearest bound if it is out of range. Used by `#[metrics(clamp(...))]`.
+pub fn clamp<T: Clamp>(value: T, min: Option<T::Bound>, max: Option<T::Bound>) -> T {
+    value.clamp_to(min.as_ref(), max.as_ref())
+}
+
+/// Return `None` if `value` is out of range. Used by `#[metrics(clamp(..., out_of_range = "drop"))]`.
+pub fn drop_out_of_range<T: Clamp>(
+    value: T,
+    min: Option<T::Bound>,
+    max: Option<T::Bound>,
+) -> Option<T> {
+    value
+        .is_in_range(min.as_ref(), max.as_ref())
+        .then_some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clamp_numbers() {
+        assert_eq!(clamp(u64::MAX, None, Some(100)), 100);
+        assert_eq!(clamp(-5i32, Some(0), None), 0);
+        assert_eq!(clamp(7u8, Some(0), Some(10)), 7);
+        assert_eq!(clamp(f64::INFINITY, Some(0.0), Some(1.0)), 1.0);
+        assert_eq!(clamp(Some(200u64), None, Some(100)), Some(100));
+        assert_eq!(clamp(None::<u64>, Some(1), Some(100)), None);
+        // inverted bounds must not panic
+        assert_eq!(clamp(5u64, Some(10), Some(1)), 10);
+    }
+
+    #[test]
+    fn drop_out_of_range_values() {
+        assert_eq!(drop_out_of_range(u64::MAX, None, Some(100)), None);
+        assert_eq!(drop_out_of_range(100u64, None, Some(100)), Some(100));
+        assert_eq!(drop_out_of_range(f64::NAN, Some(0.0), None), None);
+        assert_eq!(
+            drop_out_of_range(Duration::MAX, None, Some(Duration::from_secs(1))),
+            None
+        );
+        assert_eq!(drop_out_of_range(None::<u64>, Some(1), None), Some(None));
+    }
+}
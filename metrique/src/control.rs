@@ -0,0 +1,223 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! [`SinkControl`] reconfigures a running metrics pipeline: where it writes, how much it samples
+//! and whether verbose fields are emitted, without tearing down and recreating the global sink.
+//!
+//! Build the pipeline from the [`ControlledOutput`] and [`ControlledSample`] of a `SinkControl`,
+//! and keep a clone of it wherever the configuration changes, for example in the callback of a
+//! dynamic configuration service:
+//!
+//! ```
+//! use metrique::ServiceMetrics;
+//! use metrique::control::SinkControl;
+//! use metrique::emf::Emf;
+//! use metrique::writer::{AttachGlobalEntrySinkExt, FormatExt, GlobalEntrySink};
+//!
+//! let control = SinkControl::new(std::io::stdout());
+//! let format = Emf::builder("Ns".to_string(), vec![vec![]]).build().with_sampling();
+//! let _handle = ServiceMetrics::attach_to_stream(control.sample(format).output_to(control.output()));
+//!
+//! // later, when the configuration changes
+//! control.set_sample_rate(0.1);
+//! control.set_output(std::io::sink());
+//! control.set_verbose(true);
+//! ```
+
+use std::{
+    fmt, io,
+    sync::{
+        Arc, Mutex, MutexGuard,
+        atomic::{AtomicU32, Ordering},
+    },
+};
+
+use metrique_writer::{
+    Entry, IoStreamError,
+    format::Format,
+    sample::{FixedFractionSample, SampledFormat},
+};
+
+/// A handle that changes the destination, sample rate and verbosity of a running pipeline.
+///
+/// Clones control the same pipeline. See the [module docs](self) for an example.
+#[derive(Clone)]
+pub struct SinkControl {
+    state: Arc<ControlState>,
+}
+
+struct ControlState {
+    output: Mutex<OutputSlot>,
+    // the bits of an `f32` in `(0, 1]`
+    sample_rate: AtomicU32,
+}
+
+struct OutputSlot {
+    current: Box<dyn io::Write + Send>,
+    // the output that replaces `current` at the next flush
+    next: Option<Box<dyn io::Write + Send>>,
+}
+
+impl fmt::Debug for SinkControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SinkControl")
+            .field("sample_rate", &self.sample_rate())
+            .finish_non_exhaustive()
+    }
+}
+
+impl SinkControl {
+    /// Control a pipeline that writes to `output`, and keeps every entry
+    pub fn new(output: impl io::Write + Send + 'static) -> Self {
+        Self {
+            state: Arc::new(ControlState {
+                output: Mutex::new(OutputSlot {
+                    current: Box::new(output),
+                    next: None,
+                }),
+                sample_rate: AtomicU32::new(1.0f32.to_bits()),
+            }),
+        }
+    }
+
+    /// The output to write the pipeline to, which forwards to the output set with
+    /// [`SinkControl::set_output`]
+    pub fn output(&self) -> ControlledOutput {
+        ControlledOutput {
+            state: Arc::clone(&self.state),
+        }
+    }
+
+    /// Sample the entries formatted with `format` at the rate set with
+    /// [`SinkControl::set_sample_rate`]
+    pub fn sample<F: SampledFormat>(&self, format: F) -> ControlledSample<F> {
+        ControlledSample {
+            format: FixedFractionSample::new(format, 1.0),
+            state: Arc::clone(&self.state),
+        }
+    }
+
+    /// Write to `output` instead of the current output.
+    ///
+    /// The switch happens when the pipeline next flushes its output (a [`BackgroundQueue`]
+    /// flushes periodically), after the current output was flushed, so that entries are never
+    /// split between outputs.
+    ///
+    /// [`BackgroundQueue`]: crate::writer::sink::BackgroundQueue
+    pub fn set_output(&self, output: impl io::Write + Send + 'static) {
+        self.state.lock_output().next = Some(Box::new(output));
+    }
+
+    /// Keep a random fraction `rate` of the entries, which must be in `(0, 1]`. The kept entries
+    /// are upweighted by the format, see [`SampledFormat`].
+    ///
+    /// This applies to the entries formatted after the call.
+    ///
+    /// # Panics
+    /// Panics if `rate` is not in `(0, 1]`.
+    pub fn set_sample_rate(&self, rate: f32) {
+        assert!(
+            rate.is_finite() && 0.0 < rate && rate <= 1.0,
+            "sample rate must be in (0, 1]"
+        );
+        self.state
+            .sample_rate
+            .store(rate.to_bits(), Ordering::Relaxed);
+    }
+
+    /// The fraction of entries that is kept
+    pub fn sample_rate(&self) -> f32 {
+        self.state.sample_rate()
+    }
+
+    /// Enable or disable `#[metrics(verbose)]` fields, see [`verbose::set_enabled`].
+    ///
+    /// Unlike the output and the sample rate, verbosity is global to the process.
+    ///
+    /// [`verbose::set_enabled`]: crate::verbose::set_enabled
+    pub fn set_verbose(&self, enabled: bool) {
+        crate::verbose::set_enabled(enabled);
+    }
+}
+
+impl ControlState {
+    fn lock_output(&self) -> MutexGuard<'_, OutputSlot> {
+        // the slot is always consistent, so it is fine to ignore poisoning
+        self.output
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn sample_rate(&self) -> f32 {
+        f32::from_bits(self.sample_rate.load(Ordering::Relaxed))
+    }
+}
+
+/// An [`io::Write`] that writes to the output of a [`SinkControl`], returned by
+/// [`SinkControl::output`].
+pub struct ControlledOutput {
+    state: Arc<ControlState>,
+}
+
+impl fmt::Debug for ControlledOutput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ControlledOutput").finish_non_exhaustive()
+    }
+}
+
+impl io::Write for ControlledOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.state.lock_output().current.write(buf)
+    }
+
+    fn write_vectored(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+        self.state.lock_output().current.write_vectored(bufs)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.state.lock_output().current.write_all(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let mut output = self.state.lock_output();
+        let result = output.current.flush();
+        if let Some(next) = output.next.take() {
+            output.current = next;
+        }
+        result
+    }
+}
+
+/// A [`Format`] that samples entries at the rate of a [`SinkControl`], returned by
+/// [`SinkControl::sample`].
+///
+/// At a rate of 1, entries are formatted without sampling.
+pub struct ControlledSample<F> {
+    format: FixedFractionSample<F>,
+    state: Arc<ControlState>,
+}
+
+impl<F> fmt::Debug for ControlledSample<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ControlledSample")
+            .field("rate", &self.format.rate())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F: SampledFormat> Format for ControlledSample<F> {
+    fn format(
+        &mut self,
+        entry: &impl Entry,
+        output: &mut impl io::Write,
+    ) -> Result<(), IoStreamError> {
+        let rate = self.state.sample_rate();
+        if rate >= 1.0 {
+            return self.format.format_mut().format(entry, output);
+        }
+        if rate != self.format.rate() {
+            self.format.set_rate(rate);
+        }
+        self.format.format(entry, output)
+    }
+}
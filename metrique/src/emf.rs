@@ -5,7 +5,7 @@
 
 use metrique_writer_core::Entry;
 
-pub use metrique_writer_core::config::EntryDimensions;
+pub use metrique_writer_core::config::{EntryDestination, EntryDimensions};
 
 #[cfg(feature = "emf")]
 pub use metrique_writer_format_emf::{
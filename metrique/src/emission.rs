@@ -0,0 +1,53 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! A process-wide switch to stop emitting metrics at runtime.
+//!
+//! When emission is disabled, guards created by [`append_and_close`] (and by `append_on_drop`)
+//! drop their entries instead of closing them and appending them to their sink. This lets an
+//! operator shed the cost of telemetry during an incident without redeploying:
+//!
+//! ```
+//! # use metrique::unit_of_work::metrics;
+//! # use metrique::writer::sink::VecEntrySink;
+//! #[metrics]
+//! struct RequestMetrics {
+//!     operation: &'static str,
+//! }
+//!
+//! let sink = VecEntrySink::new();
+//! metrique::emission::disable();
+//! // nothing is closed or appended when this guard drops
+//! drop(RequestMetrics { operation: "Ping" }.append_on_drop(sink.clone()));
+//! assert!(sink.drain().is_empty());
+//! metrique::emission::enable();
+//! ```
+//!
+//! The switch is read once when a guard drops, so it also applies to guards that were created
+//! before it was flipped. Entries appended to sinks directly are not affected.
+//!
+//! [`append_and_close`]: crate::append_and_close
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Stop emitting metrics, until [`enable`] is called
+pub fn disable() {
+    set_enabled(false);
+}
+
+/// Emit metrics again after [`disable`]. Emission is enabled by default.
+pub fn enable() {
+    set_enabled(true);
+}
+
+/// Enable or disable emitting metrics
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Returns true if metrics are emitted, which is the default
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
@@ -0,0 +1,223 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Support for the `#[metrics(error)]` field attribute.
+//!
+//! `#[metrics(error)]` can be applied to an `Option<E>` or a `Result<T, E>` field, where `E: Display`.
+//! It records:
+//!
+//! 1. A `Failure` count, which is `1` if the field contains an error and `0` otherwise. Use
+//!    `#[metrics(error(fault))]` to call it `Fault` instead.
+//! 2. An `ErrorType` property containing the name of the error type, only if there is an error.
+//!    This is the last path segment of the type name without generics, so it is `Box` for a
+//!    `Box<dyn Error>` and the enum name (not the variant) for an enum. Implement [`AsError`] with
+//!    a custom [`AsError::error_type`] to record something more specific.
+//! 3. With `#[metrics(error(message))]`, an `ErrorMessage` property containing the [`Display`] output
+//!    of the error, truncated to 256 characters (or `message_max_len`), only if there is an error.
+//!
+//! The names follow the `rename_all` of the containing struct, and `prefix` or `exact_prefix` can be
+//! used on the field to tell apart several error fields.
+//!
+//! ```rust
+//! use metrique::unit_of_work::metrics;
+//!
+//! #[derive(Debug)]
+//! struct ThrottlingError;
+//!
+//! impl std::fmt::Display for ThrottlingError {
+//!     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+//!         f.write_str("request was throttled")
+//!     }
+//! }
+//!
+//! #[metrics(rename_all = "PascalCase")]
+//! struct RequestMetrics {
+//!     #[metrics(error(message))]
+//!     result: Result<(), ThrottlingError>,
+//!     #[metrics(error(fault), prefix = "Dependency")]
+//!     dependency_error: Option<std::io::Error>,
+//! }
+//!
+//! let entry = metrique::test_util::test_metric(RequestMetrics {
+//!     result: Err(ThrottlingError),
+//!     dependency_error: None,
+//! });
+//! assert_eq!(entry.metrics["Failure"], 1);
+//! assert_eq!(entry.values["ErrorType"], "ThrottlingError");
+//! assert_eq!(entry.values["ErrorMessage"], "request was throttled");
+//! assert_eq!(entry.metrics["DependencyFault"], 0);
+//! assert!(!entry.values.contains_key("DependencyErrorType"));
+//! ```
+//!
+//! [`Display`]: std::fmt::Display
+
+use std::fmt::Display;
+
+use metrique_core::{InflectableEntry, NameStyle};
+use metrique_writer::EntryWriter;
+
+use crate::names::inflected_name;
+
+/// The default maximum length, in characters, of the `ErrorMessage` property.
+pub const DEFAULT_MESSAGE_MAX_LEN: usize = 256;
+
+/// A value that may contain an error, which can be used with `#[metrics(error)]`.
+///
+/// This is implemented for `Option<E>` and `Result<T, E>`.
+pub trait AsError {
+    /// The error type
+    type Error: Display + ?Sized;
+
+    /// Return the error, if there is one
+    fn as_error(&self) -> Option<&Self::Error>;
+
+    /// Return the `ErrorType` to record. Only called if [`as_error`](AsError::as_error)
+    /// returned an error.
+    ///
+    /// Defaults to the name of [`Self::Error`] without its path or generics.
+    fn error_type(&self) -> &'static str {
+        short_type_name::<Self::Error>()
+    }
+}
+
+impl<E: Display> AsError for Option<E> {
+    type Error = E;
+
+    fn as_error(&self) -> Option<&E> {
+        self.as_ref()
+    }
+}
+
+impl<T, E: Display> AsError for Result<T, E> {
+    type Error = E;
+
+    fn as_error(&self) -> Option<&E> {
+        self.as_ref().err()
+    }
+}
+
+impl<T: AsError + ?Sized> AsError for &T {
+    type Error = T::Error;
+
+    fn as_error(&self) -> Option<&Self::Error> {
+        (**self).as_error()
+    }
+
+    fn error_type(&self) -> &'static str {
+        (**self).error_type()
+    }
+}
+
+/// The name of the count recorded by an [`ErrorEntry`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorCount {
+    /// Record a `Failure` count
+    Failure,
+    /// Record a `Fault` count
+    Fault,
+}
+
+/// The closed value of a `#[metrics(error)]` field.
+#[derive(Debug, Clone)]
+pub struct ErrorEntry {
+    count: ErrorCount,
+    error: Option<RecordedError>,
+}
+
+#[derive(Debug, Clone)]
+struct RecordedError {
+    error_type: &'static str,
+    message: Option<String>,
+}
+
+impl ErrorEntry {
+    /// Record the error in `value`, if any. `message_max_len` is the maximum length of the
+    /// error message in characters, or `None` to not record the message.
+    pub fn new<T: AsError + ?Sized>(
+        value: &T,
+        count: ErrorCount,
+        message_max_len: Option<usize>,
+    ) -> Self {
+        let error = value.as_error().map(|error| RecordedError {
+            error_type: value.error_type(),
+            message: message_max_len.map(|max_len| truncate(error.to_string(), max_len)),
+        });
+        Self { count, error }
+    }
+
+    /// Returns true if an error was recorded
+    pub fn is_error(&self) -> bool {
+        self.error.is_some()
+    }
+}
+
+// `my_crate::errors::MyError<T>` => `MyError`
+fn short_type_name<T: ?Sized>() -> &'static str {
+    let name = std::any::type_name::<T>();
+    let name = name.split('<').next().unwrap_or(name);
+    name.rsplit("::").next().unwrap_or(name)
+}
+
+fn truncate(mut message: String, max_len: usize) -> String {
+    if let Some((index, _)) = message.char_indices().nth(max_len) {
+        message.truncate(index);
+    }
+    message
+}
+
+inflected_name!(FailureName, "failure", "Failure", "failure", "failure");
+inflected_name!(FaultName, "fault", "Fault", "fault", "fault");
+inflected_name!(
+    ErrorTypeName,
+    "error_type",
+    "ErrorType",
+    "error_type",
+    "error-type"
+);
+inflected_name!(
+    ErrorMessageName,
+    "error_message",
+    "ErrorMessage",
+    "error_message",
+    "error-message"
+);
+
+impl<NS: NameStyle> InflectableEntry<NS> for ErrorEntry {
+    fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+        let count_name = match self.count {
+            ErrorCount::Failure => FailureName::value::<NS>(),
+            ErrorCount::Fault => FaultName::value::<NS>(),
+        };
+        writer.value(count_name, &u64::from(self.error.is_some()));
+        if let Some(error) = &self.error {
+            writer.value(ErrorTypeName::value::<NS>(), error.error_type);
+            if let Some(message) = &error.message {
+                writer.value(ErrorMessageName::value::<NS>(), message);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_type_name_strips_path_and_generics() {
+        assert_eq!(short_type_name::<std::io::Error>(), "Error");
+        assert_eq!(short_type_name::<Vec<std::io::Error>>(), "Vec");
+        assert_eq!(short_type_name::<str>(), "str");
+        assert_eq!(
+            short_type_name::<Box<dyn std::error::Error + Send + Sync>>(),
+            "Box"
+        );
+    }
+
+    #[test]
+    fn truncate_respects_char_boundaries() {
+        assert_eq!(truncate("héllo".to_string(), 2), "hé");
+        assert_eq!(truncate("hello".to_string(), 10), "hello");
+        assert_eq!(truncate("hello".to_string(), 0), "");
+    }
+}
@@ -0,0 +1,362 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! [`FanIn`] collects the metrics of many parallel subtasks into a single parent entry.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::time::SystemTime;
+
+use metrique_core::{CloseValue, FieldDescriptor, InflectableEntry, NameStyle};
+use metrique_writer_core::{
+    EntryConfig, EntryWriter, MetricFlags, Observation, Unit, ValidationError, Value, ValueWriter,
+};
+
+use crate::slot::{OnParentDrop, SlotGuard, Waiting, make_slot};
+
+/// How a [`FanIn`] emits the metrics of its subtasks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FanInMode {
+    /// Emit every metric once, with the observations of all subtasks as a distribution.
+    ///
+    /// String properties take the value from the first subtask that sets them, and per-metric
+    /// dimensions are taken from the first subtask that records the metric.
+    #[default]
+    Merged,
+    /// Emit the metrics of every subtask separately, suffixing names with `.{index}`, where
+    /// `index` is the order in which the subtask's handle was [opened](FanIn::open).
+    ///
+    /// This creates a metric name per subtask, so it should only be used when the number of
+    /// subtasks is small and bounded.
+    Indexed,
+}
+
+/// [`FanIn`] lets N parallel subtasks each fill in their own copy of a subfield struct, which are
+/// combined into the parent entry when it closes.
+///
+/// Every call to [`FanIn::open`] returns a [`SlotGuard`] that can be sent to a subtask, like
+/// [`Slot::open`]. When the guard is dropped, its value is closed and sent back to the `FanIn`.
+/// When the parent entry closes, the values that were sent back are combined according to the
+/// [`FanInMode`]:
+/// - [`FanInMode::Merged`] (the default) emits every metric once, with one observation per
+///   subtask. For example, the latencies of 3 parallel downstream calls are emitted as a single
+///   `Latency` metric with 3 values.
+/// - [`FanInMode::Indexed`] emits the metrics of every subtask separately, as `Latency.0`,
+///   `Latency.1`, and so on.
+///
+/// As with [`Slot`], the values of subtasks that are still running when the parent closes are
+/// skipped. Use [`OnParentDrop::Wait`] or [`FanIn::wait_for_data`] to wait for them.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use metrique::{FanIn, OnParentDrop, ServiceMetrics, SlotGuard};
+/// use metrique::timers::Timer;
+/// use metrique::unit::Millisecond;
+/// use metrique::unit_of_work::metrics;
+/// use metrique::writer::GlobalEntrySink;
+///
+/// #[metrics(rename_all = "PascalCase")]
+/// struct RequestMetrics {
+///     operation: &'static str,
+///     #[metrics(flatten, prefix = "shard_")]
+///     shards: FanIn<ShardMetrics>,
+/// }
+///
+/// #[metrics(subfield)]
+/// struct ShardMetrics {
+///     #[metrics(unit = Millisecond)]
+///     latency: Timer,
+///     items: usize,
+/// }
+///
+/// async fn handle_request() {
+///     let mut metrics = RequestMetrics {
+///         operation: "Scatter",
+///         shards: FanIn::default(),
+///     }
+///     .append_on_drop(ServiceMetrics::sink());
+///
+///     for shard in 0..4 {
+///         let guard = metrics.flush_guard();
+///         let shard_metrics = metrics.shards.open(
+///             ShardMetrics { latency: Timer::start_now(), items: 0 },
+///             OnParentDrop::Wait(guard),
+///         );
+///         tokio::task::spawn(query_shard(shard, shard_metrics));
+///     }
+///     // `ShardLatency` and `ShardItems` are emitted with one observation per shard
+/// }
+///
+/// async fn query_shard(shard: usize, mut metrics: SlotGuard<ShardMetrics>) {
+///     tokio::time::sleep(Duration::from_millis(10)).await;
+///     metrics.items += shard;
+/// }
+/// ```
+///
+/// [`Slot`]: crate::Slot
+/// [`Slot::open`]: crate::Slot::open
+pub struct FanIn<T: CloseValue> {
+    mode: FanInMode,
+    data: Vec<Option<T::Closed>>,
+    // index into `data`, and the channel that will fill it
+    waiting: Vec<(usize, Waiting<T::Closed>)>,
+}
+
+impl<T: CloseValue> FanIn<T> {
+    /// Create a [`FanIn`] that emits the values of its subtasks according to `mode`
+    pub fn new(mode: FanInMode) -> Self {
+        Self {
+            mode,
+            data: Vec::new(),
+            waiting: Vec::new(),
+        }
+    }
+
+    /// Create a [`FanIn`] that emits every metric once, see [`FanInMode::Merged`]
+    pub fn merged() -> Self {
+        Self::new(FanInMode::Merged)
+    }
+
+    /// Create a [`FanIn`] that emits every subtask separately, see [`FanInMode::Indexed`]
+    pub fn indexed() -> Self {
+        Self::new(FanInMode::Indexed)
+    }
+
+    /// Open a new handle for a subtask, starting from `initial_value`.
+    ///
+    /// When the returned [`SlotGuard`] is dropped, its value is closed and collected by this
+    /// [`FanIn`]. See [`Slot::open`](crate::Slot::open) for the meaning of `mode`.
+    pub fn open(&mut self, initial_value: T, mode: OnParentDrop) -> SlotGuard<T> {
+        let (mut guard, waiting) = make_slot(initial_value);
+        guard.parent_drop_mode = mode;
+        self.waiting.push((self.data.len(), waiting));
+        self.data.push(None);
+        guard
+    }
+
+    /// The number of handles that were opened
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Returns true if no handles were opened
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Wait until every [`SlotGuard`] opened so far closes.
+    ///
+    /// Returns the values of the subtasks, in the order they were opened. The value of a subtask
+    /// whose guard panicked is `None`.
+    pub async fn wait_for_data(&mut self) -> &mut [Option<T::Closed>] {
+        for (index, waiting) in self.waiting.drain(..) {
+            self.data[index] = waiting.wait_for_value().await;
+        }
+        &mut self.data
+    }
+}
+
+impl<T: CloseValue> Default for FanIn<T> {
+    fn default() -> Self {
+        Self::new(FanInMode::default())
+    }
+}
+
+impl<T: CloseValue> Debug for FanIn<T>
+where
+    T::Closed: Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FanIn")
+            .field("mode", &self.mode)
+            .field("opened", &self.len())
+            .field("pending", &self.waiting.len())
+            .field("data", &self.data)
+            .finish()
+    }
+}
+
+#[diagnostic::do_not_recommend]
+impl<T: CloseValue> CloseValue for FanIn<T> {
+    type Closed = FanInEntry<T::Closed>;
+
+    fn close(mut self) -> Self::Closed {
+        for (index, waiting) in self.waiting {
+            self.data[index] = waiting.take_value();
+        }
+        FanInEntry {
+            mode: self.mode,
+            children: (self.data.into_iter().enumerate())
+                .filter_map(|(index, child)| Some((index, child?)))
+                .collect(),
+        }
+    }
+}
+
+/// The closed value of a [`FanIn`]
+#[derive(Debug)]
+pub struct FanInEntry<E> {
+    mode: FanInMode,
+    // (index the handle was opened at, closed value)
+    children: Vec<(usize, E)>,
+}
+
+impl<NS: NameStyle, E: InflectableEntry<NS>> InflectableEntry<NS> for FanInEntry<E> {
+    fn write<'a>(&'a self, w: &mut impl EntryWriter<'a>) {
+        match self.mode {
+            FanInMode::Indexed => {
+                for (index, child) in &self.children {
+                    child.write(&mut IndexedWriter { writer: w, index });
+                }
+            }
+            FanInMode::Merged => {
+                let mut merged = MergeWriter::default();
+                for (_, child) in &self.children {
+                    child.write(&mut merged);
+                }
+                for (name, value) in merged.values {
+                    w.value(name, &value);
+                }
+            }
+        }
+    }
+
+    fn describe(f: &mut dyn FnMut(FieldDescriptor)) {
+        E::describe(f)
+    }
+
+    fn schema_version() -> Option<u64> {
+        E::schema_version()
+    }
+}
+
+/// Suffixes every name with the index of the subtask
+struct IndexedWriter<'w, W> {
+    writer: &'w mut W,
+    index: &'w usize,
+}
+
+impl<'a, W: EntryWriter<'a>> EntryWriter<'a> for IndexedWriter<'_, W> {
+    fn timestamp(&mut self, _timestamp: SystemTime) {
+        // the parent entry owns the timestamp
+    }
+
+    fn value(&mut self, name: impl Into<Cow<'a, str>>, value: &(impl Value + ?Sized)) {
+        let name = format!("{}.{}", name.into(), self.index);
+        self.writer.value(name, value);
+    }
+
+    fn config(&mut self, config: &'a dyn EntryConfig) {
+        self.writer.config(config);
+    }
+
+    fn begin_group(&mut self, name: impl Into<Cow<'a, str>>, prefix: impl Into<Cow<'a, str>>) {
+        self.writer.begin_group(name, prefix);
+    }
+
+    fn end_group(&mut self) {
+        self.writer.end_group();
+    }
+}
+
+/// Combines the values written by every subtask, keeping the order names were first written in
+#[derive(Default)]
+struct MergeWriter<'a> {
+    values: Vec<(Cow<'a, str>, MergedValue)>,
+    index: HashMap<Cow<'a, str>, usize>,
+}
+
+enum MergedValue {
+    Empty,
+    String(String),
+    Metric {
+        observations: Vec<Observation>,
+        unit: Unit,
+        dimensions: Vec<(String, String)>,
+    },
+    Error(ValidationError),
+}
+
+impl<'a> EntryWriter<'a> for MergeWriter<'a> {
+    fn timestamp(&mut self, _timestamp: SystemTime) {
+        // the parent entry owns the timestamp
+    }
+
+    fn value(&mut self, name: impl Into<Cow<'a, str>>, value: &(impl Value + ?Sized)) {
+        let name = name.into();
+        let index = match self.index.get(&name) {
+            Some(index) => *index,
+            None => {
+                self.values.push((name.clone(), MergedValue::Empty));
+                self.index.insert(name, self.values.len() - 1);
+                self.values.len() - 1
+            }
+        };
+        value.write(&mut self.values[index].1);
+    }
+
+    fn config(&mut self, _config: &'a dyn EntryConfig) {
+        // configs are forwarded by the parent entry
+    }
+}
+
+impl ValueWriter for &mut MergedValue {
+    fn string(self, value: &str) {
+        if let MergedValue::Empty = self {
+            *self = MergedValue::String(value.to_owned());
+        }
+    }
+
+    fn metric<'a>(
+        self,
+        distribution: impl IntoIterator<Item = Observation>,
+        unit: Unit,
+        dimensions: impl IntoIterator<Item = (&'a str, &'a str)>,
+        _flags: MetricFlags<'_>,
+    ) {
+        match self {
+            MergedValue::Empty => {
+                *self = MergedValue::Metric {
+                    observations: distribution.into_iter().collect(),
+                    unit,
+                    dimensions: dimensions
+                        .into_iter()
+                        .map(|(k, v)| (k.to_owned(), v.to_owned()))
+                        .collect(),
+                }
+            }
+            MergedValue::Metric { observations, .. } => observations.extend(distribution),
+            MergedValue::String(_) | MergedValue::Error(_) => {}
+        }
+    }
+
+    fn error(self, error: ValidationError) {
+        if !matches!(self, MergedValue::Error(_)) {
+            *self = MergedValue::Error(error);
+        }
+    }
+}
+
+impl Value for MergedValue {
+    fn write(&self, writer: impl ValueWriter) {
+        match self {
+            MergedValue::Empty => {}
+            MergedValue::String(value) => writer.string(value),
+            MergedValue::Metric {
+                observations,
+                unit,
+                dimensions,
+            } => writer.metric(
+                observations.iter().copied(),
+                *unit,
+                dimensions.iter().map(|(k, v)| (&**k, &**v)),
+                MetricFlags::empty(),
+            ),
+            MergedValue::Error(error) => writer.error(error.clone()),
+        }
+    }
+}
@@ -0,0 +1,180 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! [`FieldHandle`] gives async tasks and components access to a single field or subfield of a
+//! metric, without handing them the whole entry.
+
+use std::fmt::{self, Debug};
+use std::ops::Deref;
+use std::sync::Arc;
+
+use metrique_core::{CloseEntry, Counter};
+use metrique_writer_core::EntrySink;
+
+use crate::{AppendAndCloseOnDropHandle, RootMetric};
+
+/// A cheap, cloneable handle to one field or subfield of a metric that was appended with
+/// `append_on_drop`.
+///
+/// Field handles are created with [`AppendAndCloseOnDropHandle::project`], and narrowed further
+/// with [`FieldHandle::project`]. For the fields marked with `#[metrics(handle)]`, the [`metrics`]
+/// macro generates a `<MyMetrics>Handles` struct with one [`FieldHandle`] per field, returned by
+/// `handles()` on the guard or on its handle. A field handle derefs to its field, so the field normally is a type that
+/// is updated through a shared reference, like a [`Counter`] or an atomic. Tasks can then update
+/// the fields they are given without holding (and navigating) the whole entry.
+///
+/// Like an [`AppendAndCloseOnDropHandle`], a field handle keeps the entry open: it is closed and
+/// appended once the guard, its handles and all field handles are dropped.
+///
+/// # Example
+/// ```
+/// use metrique::{Counter, CounterHandle, ServiceMetrics, unit_of_work::metrics};
+/// use metrique::writer::GlobalEntrySink;
+/// use tokio::task::JoinSet;
+///
+/// #[metrics(rename_all = "PascalCase")]
+/// struct RequestMetrics {
+///     operation: &'static str,
+///     #[metrics(handle)]
+///     cache_hits: Counter,
+///     #[metrics(handle)]
+///     cache_misses: Counter,
+/// }
+///
+/// async fn handle_request(keys: Vec<String>) {
+///     let handles = RequestMetrics {
+///         operation: "BatchGet",
+///         cache_hits: Counter::default(),
+///         cache_misses: Counter::default(),
+///     }
+///     .append_on_drop(ServiceMetrics::sink())
+///     .handles();
+///
+///     let lookups: JoinSet<_> = keys
+///         .into_iter()
+///         .map(|key| lookup(key, handles.cache_hits.clone(), handles.cache_misses.clone()))
+///         .collect();
+///     lookups.join_all().await;
+///     // the entry is emitted once `handles` and the clones are dropped
+/// }
+///
+/// async fn lookup(key: String, hits: CounterHandle, misses: CounterHandle) {
+///     if key.starts_with("cached-") {
+///         hits.increment();
+///     } else {
+///         misses.increment();
+///     }
+/// }
+/// ```
+///
+/// [`metrics`]: crate::unit_of_work::metrics
+pub struct FieldHandle<T: ?Sized + 'static> {
+    field: Arc<dyn Project<T>>,
+}
+
+/// A [`FieldHandle`] to a [`Counter`] field
+pub type CounterHandle = FieldHandle<Counter>;
+
+/// Implemented by the [`metrics`] macro for metrics that have fields with `#[metrics(handle)]`.
+///
+/// Call `handles()` on the guard or on its handle instead of using this trait directly.
+///
+/// [`metrics`]: crate::unit_of_work::metrics
+pub trait FieldHandles: CloseEntry + Sized {
+    /// The generated `<MyMetrics>Handles` struct, with a [`FieldHandle`] for every field with
+    /// `#[metrics(handle)]`
+    type Handles;
+
+    /// Create the field handles of the entry behind `handle`
+    fn field_handles<S: EntrySink<RootMetric<Self>> + Send + Sync + 'static>(
+        handle: &AppendAndCloseOnDropHandle<Self, S>,
+    ) -> Self::Handles;
+}
+
+trait Project<T: ?Sized>: Send + Sync {
+    fn get(&self) -> &T;
+}
+
+struct Projection<E: CloseEntry, S: EntrySink<RootMetric<E>>, T: ?Sized> {
+    handle: AppendAndCloseOnDropHandle<E, S>,
+    project: fn(&E) -> &T,
+}
+
+impl<E, S, T> Project<T> for Projection<E, S, T>
+where
+    E: CloseEntry + Send + Sync,
+    S: EntrySink<RootMetric<E>> + Send + Sync,
+    T: ?Sized,
+{
+    fn get(&self) -> &T {
+        (self.project)(&self.handle)
+    }
+}
+
+impl<T: ?Sized + 'static> FieldHandle<T> {
+    /// A handle to the field of the entry behind `handle` that `project` returns.
+    ///
+    /// This is what [`AppendAndCloseOnDropHandle::project`] uses.
+    pub fn new<E, S>(handle: AppendAndCloseOnDropHandle<E, S>, project: fn(&E) -> &T) -> Self
+    where
+        E: CloseEntry + Send + Sync + 'static,
+        S: EntrySink<RootMetric<E>> + Send + Sync + 'static,
+    {
+        Self {
+            field: Arc::new(Projection { handle, project }),
+        }
+    }
+
+    /// Narrow this handle down to the part of the field that `project` returns, for example to a
+    /// field of a flattened subfield. The new handle keeps the entry open like this one does.
+    ///
+    /// ```
+    /// # use metrique::{Counter, CounterHandle, FieldHandle};
+    /// # #[metrique::unit_of_work::metrics(subfield)]
+    /// # struct CacheMetrics { hits: Counter }
+    /// fn hits(cache: &FieldHandle<CacheMetrics>) -> CounterHandle {
+    ///     cache.project(|cache| &cache.hits)
+    /// }
+    /// ```
+    pub fn project<U: ?Sized + 'static>(&self, project: fn(&T) -> &U) -> FieldHandle<U> {
+        FieldHandle {
+            field: Arc::new(Nested {
+                parent: self.clone(),
+                project,
+            }),
+        }
+    }
+}
+
+struct Nested<T: ?Sized + 'static, U: ?Sized> {
+    parent: FieldHandle<T>,
+    project: fn(&T) -> &U,
+}
+
+impl<T: ?Sized + 'static, U: ?Sized> Project<U> for Nested<T, U> {
+    fn get(&self) -> &U {
+        (self.project)(&self.parent)
+    }
+}
+
+impl<T: ?Sized + 'static> Deref for FieldHandle<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.field.get()
+    }
+}
+
+impl<T: ?Sized + 'static> Clone for FieldHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            field: self.field.clone(),
+        }
+    }
+}
+
+impl<T: Debug + ?Sized + 'static> Debug for FieldHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("FieldHandle").field(&&**self).finish()
+    }
+}
@@ -35,6 +35,10 @@
 //!     dynamic_count: Flex::new(field_name).with_value(42),
 //! };
 //! ```
+//!
+//! Unlike the names of regular fields, runtime keys are not checked at compile time. If they come
+//! from outside the service, put a [`Sanitize`](metrique_writer::sink::Sanitize) processor in front
+//! of the sink to replace the characters the format doesn't accept.
 use std::borrow::Cow;
 
 use metrique_core::{CloseValue, InflectableEntry, NameStyle};
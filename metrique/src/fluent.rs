@@ -0,0 +1,9 @@
+//! Fluent Bit / Fluentd forward protocol integration.
+//!
+//! This module re-exports the forward protocol types from
+//! [`metrique-writer-format-fluent`](https://docs.rs/metrique-writer-format-fluent).
+
+#[cfg(feature = "fluent")]
+pub use metrique_writer_format_fluent::{
+    DEFAULT_ACK_TIMEOUT, DEFAULT_MAX_BATCH_BYTES, DEFAULT_SEND_TIMEOUT, Fluent, FluentForward,
+};
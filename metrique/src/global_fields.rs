@@ -0,0 +1,123 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Process-wide constant properties, added to every [`RootEntry`].
+//!
+//! Properties that are the same for every entry of a process, like the service name, version,
+//! availability zone or instance id, can be registered once with [`set`] rather than being added
+//! as a field to every metric struct. Every [`RootEntry`] created afterwards, including the
+//! entries appended by [`append_on_drop`], writes them after its own fields.
+//!
+//! ```rust
+//! use metrique::{CloseValue, RootEntry};
+//! use metrique::test_util::to_test_entry;
+//! use metrique::unit_of_work::metrics;
+//!
+//! #[metrics(rename_all = "PascalCase")]
+//! struct RequestMetrics {
+//!     operation: &'static str,
+//! }
+//!
+//! metrique::global_fields::set("Service", "Inventory");
+//! metrique::global_fields::set("AvailabilityZone", "us-east-1a");
+//!
+//! let entry = to_test_entry(RootEntry::new(RequestMetrics { operation: "GetItem" }.close()));
+//! assert_eq!(entry.values["Operation"], "GetItem");
+//! assert_eq!(entry.values["Service"], "Inventory");
+//! assert_eq!(entry.values["AvailabilityZone"], "us-east-1a");
+//! # metrique::global_fields::clear();
+//! ```
+//!
+//! The fields are captured when the [`RootEntry`] is created (for [`append_on_drop`], when the
+//! guard is dropped), so changing them doesn't affect entries that are already waiting in a
+//! queue. The names are written as-is, and should not
+//! collide with the names of the fields of the entries. To use a global field as an EMF
+//! dimension, add its name to the dimension sets of the format.
+//!
+//! To add constant fields to the entries of a single sink instead, use
+//! [`FormatExt::merge_globals`](crate::writer::FormatExt::merge_globals).
+//!
+//! [`RootEntry`]: crate::RootEntry
+//! [`append_on_drop`]: crate::append_and_close
+
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+
+use metrique_writer_core::{Entry, EntryWriter};
+
+static FIELDS: RwLock<Option<Arc<GlobalFields>>> = RwLock::new(None);
+// lets `current` skip the lock when no field was ever set, which is the common case
+static HAS_FIELDS: AtomicBool = AtomicBool::new(false);
+
+/// A snapshot of the global fields, see the [module docs](self)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GlobalFields {
+    fields: Vec<(Cow<'static, str>, Cow<'static, str>)>,
+}
+
+impl GlobalFields {
+    /// The fields, in the order they were first set
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.fields
+            .iter()
+            .map(|(name, value)| (name.as_ref(), value.as_ref()))
+    }
+
+    /// Returns true if there are no fields
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+}
+
+impl Entry for GlobalFields {
+    fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+        for (name, value) in &self.fields {
+            writer.value(&**name, &**value);
+        }
+    }
+}
+
+fn update(f: impl FnOnce(&mut Vec<(Cow<'static, str>, Cow<'static, str>)>)) {
+    // the fields stay consistent even if a thread panicked while holding the lock
+    let mut guard = FIELDS
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let mut fields = guard
+        .as_ref()
+        .map(|current| current.fields.clone())
+        .unwrap_or_default();
+    f(&mut fields);
+    HAS_FIELDS.store(!fields.is_empty(), Ordering::Release);
+    *guard = (!fields.is_empty()).then(|| Arc::new(GlobalFields { fields }));
+}
+
+/// Set the global field `name` to `value`, replacing its previous value if it was already set
+pub fn set(name: impl Into<Cow<'static, str>>, value: impl Into<Cow<'static, str>>) {
+    let (name, value) = (name.into(), value.into());
+    update(|fields| match fields.iter_mut().find(|(n, _)| *n == name) {
+        Some((_, current)) => *current = value,
+        None => fields.push((name, value)),
+    });
+}
+
+/// Remove the global field `name`, if it was set
+pub fn remove(name: &str) {
+    update(|fields| fields.retain(|(n, _)| n != name));
+}
+
+/// Remove every global field
+pub fn clear() {
+    update(Vec::clear);
+}
+
+/// The current global fields, or `None` if there are none
+pub fn current() -> Option<Arc<GlobalFields>> {
+    if !HAS_FIELDS.load(Ordering::Acquire) {
+        return None;
+    }
+    FIELDS
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone()
+}
@@ -137,7 +137,6 @@ impl<T> DerefMut for Parent<T> {
 #[cfg(test)]
 mod test {
     use core::{
-        assert_eq,
         ops::Drop,
         sync::atomic::{AtomicBool, Ordering},
     };
@@ -172,7 +171,7 @@ mod test {
         let (tester, is_dropped) = IsDropped::new();
         let primary = Parent::new(tester);
         drop(primary);
-        assert_eq!(is_dropped.load(Ordering::Relaxed), true);
+        assert!(is_dropped.load(Ordering::Relaxed));
     }
 
     #[test]
@@ -181,13 +180,13 @@ mod test {
         let primary = Parent::new(tester);
         let guard_1 = primary.new_guard();
         let guard_2 = primary.new_guard();
-        assert_eq!(is_dropped.load(Ordering::Relaxed), false);
+        assert!(!is_dropped.load(Ordering::Relaxed));
         drop(guard_1);
-        assert_eq!(is_dropped.load(Ordering::Relaxed), false);
+        assert!(!is_dropped.load(Ordering::Relaxed));
         drop(primary);
-        assert_eq!(is_dropped.load(Ordering::Relaxed), false);
+        assert!(!is_dropped.load(Ordering::Relaxed));
         drop(guard_2);
-        assert_eq!(is_dropped.load(Ordering::Relaxed), true);
+        assert!(is_dropped.load(Ordering::Relaxed));
     }
 
     #[test]
@@ -197,11 +196,11 @@ mod test {
         let drop_all = primary.force_drop_guard();
         drop(drop_all);
         // the primary is still alive
-        assert_eq!(is_dropped.load(Ordering::Relaxed), false);
+        assert!(!is_dropped.load(Ordering::Relaxed));
 
         // now it is not
         drop(primary);
-        assert_eq!(is_dropped.load(Ordering::Relaxed), true);
+        assert!(is_dropped.load(Ordering::Relaxed));
     }
 
     #[test]
@@ -211,13 +210,13 @@ mod test {
         let drop_all_1 = primary.force_drop_guard();
         let drop_all_2 = primary.force_drop_guard();
         // the primary is still alive
-        assert_eq!(is_dropped.load(Ordering::Relaxed), false);
+        assert!(!is_dropped.load(Ordering::Relaxed));
         drop(drop_all_1);
         drop(drop_all_2);
 
-        assert_eq!(is_dropped.load(Ordering::Relaxed), false);
+        assert!(!is_dropped.load(Ordering::Relaxed));
         drop(primary);
-        assert_eq!(is_dropped.load(Ordering::Relaxed), true);
+        assert!(is_dropped.load(Ordering::Relaxed));
     }
 
     #[test]
@@ -226,11 +225,11 @@ mod test {
         let primary = Parent::new(tester);
         let drop_all = primary.force_drop_guard();
 
-        assert_eq!(is_dropped.load(Ordering::Relaxed), false);
+        assert!(!is_dropped.load(Ordering::Relaxed));
 
         // having the drop_all alive does not prevent primary from dropping
         drop(primary);
-        assert_eq!(is_dropped.load(Ordering::Relaxed), true);
+        assert!(is_dropped.load(Ordering::Relaxed));
 
         drop(drop_all);
     }
@@ -244,25 +243,25 @@ mod test {
         let _guard_2 = sut.new_guard();
 
         // Everything is alive
-        assert_eq!(is_dropped.load(Ordering::Relaxed), false);
+        assert!(!is_dropped.load(Ordering::Relaxed));
 
         // Create a drop guard, that when dropped, will ignore any other open guards
         let force_drop_guard = sut.force_drop_guard();
 
         // Creating this doesn't drop the Parent
-        assert_eq!(is_dropped.load(Ordering::Relaxed), false);
+        assert!(!is_dropped.load(Ordering::Relaxed));
 
         // Dropping the primary doesn't cause a drop, we still have two guards alive
         drop(sut);
-        assert_eq!(is_dropped.load(Ordering::Relaxed), false);
+        assert!(!is_dropped.load(Ordering::Relaxed));
 
         // Dropping one guard doesn't cause a drop (there is still one guard left)
         drop(_guard_1);
-        assert_eq!(is_dropped.load(Ordering::Relaxed), false);
+        assert!(!is_dropped.load(Ordering::Relaxed));
 
         // now the force drop guard is dropped & sut is dropped
         drop(force_drop_guard);
-        assert_eq!(is_dropped.load(Ordering::Relaxed), true);
+        assert!(is_dropped.load(Ordering::Relaxed));
 
         drop(_guard_2);
     }
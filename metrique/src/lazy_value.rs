@@ -0,0 +1,162 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! [`LazyValue`] is a field that is set at most once, from anywhere holding one of its handles.
+
+use std::fmt::{self, Debug};
+use std::sync::{Arc, OnceLock, Weak};
+
+use metrique_core::{CloseValue, CloseValueRef};
+
+/// A metric field whose value is discovered later, deep in a call tree, and set at most once.
+///
+/// Pass a [`LazyValueHandle`] (from [`LazyValue::handle`]) down to the code that discovers the
+/// value, which sets it with [`LazyValueHandle::set`]. Handles are cheap to clone, are `Send` and
+/// `Sync` when `T` is `Send` and `Sync`, and don't need access to the rest of the entry. The first
+/// value set wins, and later values are returned to the caller.
+///
+/// When the entry closes, the field closes into the value that was set, or into the default passed
+/// to [`LazyValue::with_default`], or is left out of the entry if neither exists. Reading the value
+/// at close doesn't take a lock, but waits for a handle that is setting the value at the same time.
+/// Handles that outlive the entry can't set the value anymore.
+///
+/// # Example
+/// ```
+/// use metrique::{LazyValue, LazyValueHandle, ServiceMetrics, unit_of_work::metrics};
+/// use metrique::writer::GlobalEntrySink;
+///
+/// #[metrics(rename_all = "PascalCase")]
+/// struct RequestMetrics {
+///     operation: &'static str,
+///     cache_tier: LazyValue<&'static str>,
+///     partition: LazyValue<u64>,
+/// }
+///
+/// fn handle_request() {
+///     let metrics = RequestMetrics {
+///         operation: "Get",
+///         cache_tier: LazyValue::with_default("none"),
+///         partition: LazyValue::new(),
+///     }
+///     .append_on_drop(ServiceMetrics::sink());
+///     lookup(metrics.cache_tier.handle());
+///     // `CacheTier` is "memory" when the entry is emitted, and `Partition` is left out
+/// }
+///
+/// fn lookup(cache_tier: LazyValueHandle<&'static str>) {
+///     // ... deep in the call tree
+///     let _ = cache_tier.set("memory");
+/// }
+/// ```
+pub struct LazyValue<T> {
+    value: Arc<OnceLock<T>>,
+    default: Option<T>,
+}
+
+impl<T> LazyValue<T> {
+    /// A value that is left out of the entry if it is never set
+    pub fn new() -> Self {
+        Self {
+            value: Arc::new(OnceLock::new()),
+            default: None,
+        }
+    }
+
+    /// A value that closes into `default` if it is never set
+    pub fn with_default(default: T) -> Self {
+        Self {
+            value: Arc::new(OnceLock::new()),
+            default: Some(default),
+        }
+    }
+
+    /// Return a handle that can set the value
+    pub fn handle(&self) -> LazyValueHandle<T> {
+        LazyValueHandle {
+            value: Arc::downgrade(&self.value),
+        }
+    }
+
+    /// Set the value, unless it was already set, in which case `value` is returned
+    pub fn set(&self, value: T) -> Result<(), T> {
+        self.value.set(value)
+    }
+
+    /// The value, if it was set
+    pub fn get(&self) -> Option<&T> {
+        self.value.get()
+    }
+}
+
+impl<T> Default for LazyValue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Debug> Debug for LazyValue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LazyValue")
+            .field("value", &self.value.get())
+            .field("default", &self.default)
+            .finish()
+    }
+}
+
+impl<T: CloseValue> CloseValue for LazyValue<T> {
+    type Closed = Option<T::Closed>;
+
+    fn close(self) -> Self::Closed {
+        // handles only hold a strong reference for the duration of `set`, which doesn't run user
+        // code, so wait for them to finish rather than missing a value that is (or was) set
+        let mut value = self.value;
+        let value = loop {
+            match Arc::try_unwrap(value) {
+                Ok(value) => break value.into_inner(),
+                Err(shared) => {
+                    value = shared;
+                    std::thread::yield_now();
+                }
+            }
+        };
+        value.or(self.default).map(T::close)
+    }
+}
+
+impl<T: CloseValueRef> CloseValue for &'_ LazyValue<T> {
+    type Closed = Option<T::Closed>;
+
+    fn close(self) -> Self::Closed {
+        self.value.get().or(self.default.as_ref()).map(T::close_ref)
+    }
+}
+
+/// A handle to set the value of a [`LazyValue`], returned by [`LazyValue::handle`].
+pub struct LazyValueHandle<T> {
+    value: Weak<OnceLock<T>>,
+}
+
+impl<T> LazyValueHandle<T> {
+    /// Set the value, unless it was already set or the entry was already closed, in which case
+    /// `value` is returned
+    pub fn set(&self, value: T) -> Result<(), T> {
+        match self.value.upgrade() {
+            Some(lazy) => lazy.set(value),
+            None => Err(value),
+        }
+    }
+}
+
+impl<T> Clone for LazyValueHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            value: self.value.clone(),
+        }
+    }
+}
+
+impl<T> Debug for LazyValueHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LazyValueHandle").finish_non_exhaustive()
+    }
+}
@@ -7,22 +7,46 @@
 // not bumping the MSRV for collapsible_if
 #![allow(clippy::collapsible_if)]
 
+pub mod attempts;
+#[cfg(feature = "aws")]
+pub mod aws;
+pub mod clamp;
+pub mod control;
 pub mod emf;
+pub mod emission;
+pub mod error;
+pub mod fan_in;
+pub mod field_handle;
 pub mod flex;
+#[cfg(feature = "fluent")]
+pub mod fluent;
+pub mod global_fields;
 pub mod instrument;
 #[cfg(feature = "json")]
 pub mod json;
 mod keep_alive;
+pub mod lazy_value;
 #[cfg(feature = "local-format")]
 pub mod local;
+mod names;
+pub mod operation_detail;
+pub mod outcome;
+pub mod scoped_fields;
+pub mod sharded;
+pub mod trace_context;
+pub mod verbose;
 
 /// Provides timing utilities for metrics, including timestamps and duration measurements.
 ///
 /// This module contains types for recording timestamps and measuring durations:
 /// - `Timestamp`: Records a point in time, typically when an event occurs
 /// - `TimestampOnClose`: Records the time when a metric record is closed
-/// - `Timer`: Automatically starts timing when created and stops when dropped
+/// - `Timer`: Automatically starts timing when created and stops when dropped. It can be paused to exclude idle time
 /// - `Stopwatch`: Manually controlled timer that must be explicitly started
+/// - `LapTimer`: Splits a unit of work into named laps, emitting one metric per lap
+/// - `DurationCounter`: Accumulates the time of several scopes (`TimeSlice` guards) into one field, using `&self`
+/// - `DurationStats`: Records many durations and emits their count, sum, min, max, p50 and p99
+/// - `Spanned`: The time between two `std::time::Instant`s captured elsewhere, with an optional start time
 ///
 /// # Examples
 ///
@@ -75,6 +99,8 @@ pub mod _guide {
     pub mod testing {}
 }
 
+pub use fan_in::{FanIn, FanInMode};
+pub use field_handle::{CounterHandle, FieldHandle, FieldHandles};
 use metrique_core::CloseEntry;
 use metrique_writer_core::Entry;
 use metrique_writer_core::EntryWriter;
@@ -82,6 +108,8 @@ use metrique_writer_core::entry::SampleGroupElement;
 pub use slot::{FlushGuard, ForceFlushGuard, LazySlot, OnParentDrop, Slot, SlotGuard};
 
 pub use flex::Flex;
+pub use lazy_value::{LazyValue, LazyValueHandle};
+pub use sharded::ShardedCounter;
 
 use core::ops::Deref;
 use core::ops::DerefMut;
@@ -93,7 +121,7 @@ use std::fmt::Debug;
 use std::sync::Arc;
 
 pub use metrique_core::{
-    CloseValue, CloseValueRef, Counter, CounterGuard, InflectableEntry, NameStyle,
+    CloseValue, CloseValueRef, Counter, CounterGuard, FieldDescriptor, InflectableEntry, NameStyle,
 };
 
 /// Unit types and utilities for metrics.
@@ -107,7 +135,7 @@ pub mod unit {
         Bit, BitPerSecond, Byte, BytePerSecond, Count, Gigabit, GigabitPerSecond, Gigabyte,
         GigabytePerSecond, Kilobit, KilobitPerSecond, Kilobyte, KilobytePerSecond, Megabit,
         MegabitPerSecond, Megabyte, MegabytePerSecond, Microsecond, Millisecond, None, Percent,
-        Second, Terabit, TerabitPerSecond, Terabyte, TerabytePerSecond,
+        Ratio, Second, Terabit, TerabitPerSecond, Terabyte, TerabytePerSecond, WithCustomUnit,
     };
     use metrique_writer_core::{MetricValue, unit::WithUnit};
     /// Internal trait to attach units when closing values
@@ -135,8 +163,14 @@ pub mod format {
 #[cfg(feature = "test-util")]
 pub mod test_util {
     pub use crate::writer::test_util::{
-        Inspector, Metric, TestEntry, TestEntrySink, test_entry_sink, test_metric, to_test_entry,
+        CapturingSink, Comparison, Condition, EmittedValue, EntrySchema, ExpectedValue, FreezeTime,
+        FrozenEntry, Inspector, Metric, MetricQuery, MetricSchema, RenderTimestamp, TestEntry,
+        TestEntrySink, assert_entries_eq, conformance, freeze_time, test_entry_sink, test_metric,
+        to_test_entry,
     };
+    pub use metrique_writer::{assert_emitted, assert_metrics};
+    #[cfg(feature = "emf")]
+    pub use metrique_writer_format_emf::test_util::{EmfParseError, parse_emf, parse_emf_lines};
 }
 
 /// Unit of work metrics macros and utilities.
@@ -165,6 +199,34 @@ pub mod unit_of_work {
 /// entry sink that can be used to append closed metrics entries.
 pub type DefaultSink = metrique_writer_core::sink::BoxEntrySink;
 
+/// A type-erased sink for the closed entries of the metric `M`.
+///
+/// Unlike [`DefaultSink`], this can erase sinks that only accept one entry type, like a
+/// `BackgroundQueue<RootMetric<M>>`, so that guards can be stored without naming the sink type.
+/// Create it with [`DynEntrySink::new`](crate::writer::DynEntrySink::new):
+///
+/// ```
+/// use metrique::{DynSink, RootMetric, unit_of_work::metrics};
+/// use metrique::writer::{DynEntrySink, sink::VecEntrySink};
+///
+/// #[metrics]
+/// struct RequestMetrics {
+///     retries: usize,
+/// }
+///
+/// struct Request {
+///     metrics: RequestMetricsGuard<DynSink<RequestMetrics>>,
+/// }
+///
+/// let sink = VecEntrySink::<RootMetric<RequestMetrics>>::default();
+/// let request = Request {
+///     metrics: RequestMetrics { retries: 0 }.append_on_drop(DynEntrySink::new(sink.clone())),
+/// };
+/// drop(request);
+/// assert_eq!(sink.drain().len(), 1);
+/// ```
+pub type DynSink<M> = metrique_writer_core::sink::DynEntrySink<RootMetric<M>>;
+
 /// A wrapper that appends and closes an entry when dropped.
 ///
 /// This struct holds a metric entry and a sink. When the struct is dropped,
@@ -364,6 +426,21 @@ impl<E: CloseEntry + Send + Sync + 'static, S: EntrySink<RootMetric<E>> + Send +
             inner: std::sync::Arc::new(self),
         }
     }
+
+    /// Turn this guard into the [`FieldHandle`]s of the fields with `#[metrics(handle)]`, in the
+    /// `<my metrics struct>Handles` struct generated by the [`metrics`] macro.
+    ///
+    /// The entry is appended once all field handles are dropped. Use [`Self::handle`] first and
+    /// [`AppendAndCloseOnDropHandle::handles`] to also keep access to the rest of the entry. See
+    /// [`FieldHandle`] for an example.
+    ///
+    /// [`metrics`]: crate::unit_of_work::metrics
+    pub fn handles(self) -> E::Handles
+    where
+        E: FieldHandles,
+    {
+        self.handle().handles()
+    }
 }
 
 #[derive(Debug)]
@@ -403,6 +480,9 @@ impl<E: CloseEntry, S: EntrySink<RootMetric<E>>> DerefMut for AppendAndCloseOnDr
 impl<E: CloseEntry, S: EntrySink<RootMetric<E>>> Drop for AppendAndCloseOnDropInner<E, S> {
     fn drop(&mut self) {
         let entry = self.entry.take().expect("only drop calls this");
+        if !emission::is_enabled() {
+            return;
+        }
         let entry = entry.close();
         self.sink.append(RootEntry::new(entry));
     }
@@ -421,6 +501,67 @@ pub struct AppendAndCloseOnDropHandle<E: CloseEntry, S: EntrySink<RootMetric<E>>
     inner: Arc<AppendAndCloseOnDrop<E, S>>,
 }
 
+impl<E: CloseEntry + Send + Sync + 'static, S: EntrySink<RootMetric<E>> + Send + Sync + 'static>
+    AppendAndCloseOnDropHandle<E, S>
+{
+    /// Return the [`FieldHandle`]s of the fields with `#[metrics(handle)]`, in the
+    /// `<my metrics struct>Handles` struct generated by the [`metrics`] macro.
+    ///
+    /// [`metrics`]: crate::unit_of_work::metrics
+    pub fn handles(&self) -> E::Handles
+    where
+        E: FieldHandles,
+    {
+        E::field_handles(self)
+    }
+
+    /// Return a [`FieldHandle`] to the part of the entry that `project` returns, usually a
+    /// flattened subfield, which keeps the entry open like this handle does.
+    ///
+    /// This lets components receive access to only their portion of the metrics, rather than to
+    /// the whole entry. `project` is a non-capturing closure or a function.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use metrique::{Counter, FieldHandle, ServiceMetrics};
+    /// use metrique::unit_of_work::metrics;
+    /// use metrique::writer::GlobalEntrySink;
+    ///
+    /// #[metrics(rename_all = "PascalCase")]
+    /// struct RequestMetrics {
+    ///     operation: &'static str,
+    ///     #[metrics(flatten)]
+    ///     cache: CacheMetrics,
+    /// }
+    ///
+    /// #[metrics(subfield)]
+    /// #[derive(Default)]
+    /// struct CacheMetrics {
+    ///     hits: Counter,
+    ///     misses: Counter,
+    /// }
+    ///
+    /// fn handle_request() {
+    ///     let handle = RequestMetrics {
+    ///         operation: "Get",
+    ///         cache: CacheMetrics::default(),
+    ///     }
+    ///     .append_on_drop(ServiceMetrics::sink())
+    ///     .handle();
+    ///     // the cache only sees its own metrics
+    ///     lookup(handle.project(|metrics| &metrics.cache));
+    /// }
+    ///
+    /// fn lookup(metrics: FieldHandle<CacheMetrics>) {
+    ///     metrics.hits.increment();
+    /// }
+    /// ```
+    pub fn project<T: ?Sized + 'static>(&self, project: fn(&E) -> &T) -> FieldHandle<T> {
+        FieldHandle::new(self.clone(), project)
+    }
+}
+
 impl<E: CloseEntry, S: EntrySink<RootMetric<E>>> Clone for AppendAndCloseOnDropHandle<E, S> {
     fn clone(&self) -> Self {
         Self {
@@ -451,6 +592,8 @@ impl<E: CloseEntry, S: EntrySink<RootMetric<E>>> std::ops::Deref
 /// # Returns
 ///
 /// An [`AppendAndCloseOnDrop`] wrapper that will close and append the entry when dropped.
+/// If emission is turned off with [`emission::disable`] when it drops, the entry is discarded
+/// instead.
 ///
 /// The [`metrics`] macro generates a type alias to [`AppendAndCloseOnDrop`] named
 /// `<my metrics struct>Guard`. When using the macro, it is recommended to refer
@@ -631,23 +774,56 @@ pub type RootMetric<E> = RootEntry<<E as CloseValue>::Closed>;
 /// # }
 /// ```
 ///
+/// A [`RootEntry`] also writes the process-wide [global fields](crate::global_fields) and the
+/// [scoped fields](crate::scoped_fields) that were set when it was created, and with the `xray`
+/// or `tracing-opentelemetry` feature, the current [trace context](crate::trace_context).
+///
 /// [closing over]: crate::CloseEntry
 /// [`EntrySink`]: metrique_writer::EntrySink
 /// [`metrics`]: crate::unit_of_work::metrics
 pub struct RootEntry<M: InflectableEntry> {
     metric: M,
+    scoped: Option<Arc<scoped_fields::ScopedFields>>,
+    globals: Option<Arc<global_fields::GlobalFields>>,
+    #[cfg(any(feature = "xray", feature = "tracing-opentelemetry"))]
+    trace: Option<trace_context::TraceContext>,
 }
 
 impl<M: InflectableEntry> RootEntry<M> {
     /// create a new [`RootEntry`]
     pub fn new(metric: M) -> Self {
-        Self { metric }
+        Self {
+            metric,
+            scoped: scoped_fields::current(),
+            globals: global_fields::current(),
+            #[cfg(any(feature = "xray", feature = "tracing-opentelemetry"))]
+            trace: trace_context::current(),
+        }
+    }
+
+    /// The closed metric, for example to choose the [`Priority`] of the entry in a
+    /// [`Prioritize`] processor
+    ///
+    /// [`Priority`]: metrique_writer::entry::Priority
+    /// [`Prioritize`]: metrique_writer::sink::Prioritize
+    pub fn metric(&self) -> &M {
+        &self.metric
     }
 }
 
 impl<M: InflectableEntry> Entry for RootEntry<M> {
     fn write<'a>(&'a self, w: &mut impl EntryWriter<'a>) {
         self.metric.write(w);
+        if let Some(scoped) = &self.scoped {
+            scoped.write(w);
+        }
+        if let Some(globals) = &self.globals {
+            globals.write(w);
+        }
+        #[cfg(any(feature = "xray", feature = "tracing-opentelemetry"))]
+        if let Some(trace) = &self.trace {
+            trace.write(w);
+        }
     }
 
     fn sample_group(&self) -> impl Iterator<Item = SampleGroupElement> {
@@ -655,18 +831,101 @@ impl<M: InflectableEntry> Entry for RootEntry<M> {
     }
 }
 
+/// List the [`FieldDescriptor`]s of a metric, for exporters that support metadata such as
+/// Prometheus `HELP` text.
+///
+/// Fields are described when their struct has `#[metrics(doc_as_description)]`, in which case the
+/// doc comments of the fields are used as descriptions. Names are the final emitted names.
+///
+/// ```
+/// use metrique::unit_of_work::metrics;
+///
+/// #[metrics(doc_as_description)]
+/// struct RequestMetrics {
+///     /// Number of retries before the request succeeded
+///     retries: usize,
+/// }
+///
+/// let fields = metrique::describe_fields::<RequestMetrics>();
+/// assert_eq!(fields[0].name, "retries");
+/// assert_eq!(fields[0].description, "Number of retries before the request succeeded");
+/// ```
+pub fn describe_fields<T: CloseEntry>() -> Vec<FieldDescriptor> {
+    let mut fields = vec![];
+    <T::Closed as InflectableEntry>::describe(&mut |field| fields.push(field));
+    fields
+}
+
+/// The schema version of a metric, set with `#[metrics(version = N)]`.
+///
+/// The version is also written as the `schema_version` property of every entry, so that
+/// consumers can tell entries of different schemas apart.
+///
+/// ```
+/// use metrique::unit_of_work::metrics;
+///
+/// #[metrics(version = 2)]
+/// struct RequestMetrics {
+///     retries: usize,
+/// }
+///
+/// assert_eq!(metrique::schema_version::<RequestMetrics>(), Some(2));
+/// ```
+pub fn schema_version<T: CloseEntry>() -> Option<u64> {
+    <T::Closed as InflectableEntry>::schema_version()
+}
+
+/// The error returned by the `FromStr` implementation generated by
+/// `#[metrics(value(string, from_str))]` when the input is not the name of any variant.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownVariantError {
+    type_name: &'static str,
+    input: String,
+}
+
+impl UnknownVariantError {
+    #[doc(hidden)]
+    pub fn new(type_name: &'static str, input: &str) -> Self {
+        Self {
+            type_name,
+            input: input.to_owned(),
+        }
+    }
+
+    /// The string that failed to parse
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+}
+
+impl std::fmt::Display for UnknownVariantError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "`{}` is not a valid {}", self.input, self.type_name)
+    }
+}
+
+impl std::error::Error for UnknownVariantError {}
+
 #[cfg(feature = "service-metrics")]
 pub use metrique_service_metrics::ServiceMetrics;
 
+#[cfg(all(feature = "service-metrics", feature = "emf"))]
+pub use metrique_service_metrics::{MetricsDestination, ServiceMetricsBuilder};
+
 #[cfg(feature = "metrics-rs-bridge")]
 pub use metrique_metricsrs as metrics_rs;
 
 pub use metrique_core::concat;
 
+pub use metrique_writer::error_handler::{PipelineError, set_error_handler};
+
+#[cfg(feature = "http")]
+pub use metrique_core::http;
+
 /// Re-exports of [metrique_writer]
 pub mod writer {
     pub use metrique_writer::GlobalEntrySink;
-    pub use metrique_writer::{AnyEntrySink, BoxEntrySink, EntrySink};
+    pub use metrique_writer::{AnyEntrySink, BoxEntrySink, DynEntrySink, EntrySink};
     pub use metrique_writer::{BoxEntry, EntryConfig, EntryWriter, core::Entry};
     pub use metrique_writer::{Convert, Unit};
     pub use metrique_writer::{EntryIoStream, IoStreamError};
@@ -678,7 +937,7 @@ pub mod writer {
 
     pub use metrique_writer::AttachGlobalEntrySinkExt;
     pub use metrique_writer::{AttachGlobalEntrySink, EntryIoStreamExt, FormatExt};
-    pub use metrique_writer::{entry, format, sample, sink, stream, value};
+    pub use metrique_writer::{entry, error_handler, format, sample, sink, socket, stream, value};
 
     #[cfg(feature = "test-util")]
     #[doc(hidden)] // prefer the metrique::test_util re-export
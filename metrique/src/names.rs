@@ -0,0 +1,53 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Helpers for hand-written [`InflectableEntry`] implementations.
+//!
+//! [`InflectableEntry`]: metrique_core::InflectableEntry
+
+/// Declare a type with a `value::<NS>()` function that returns the given name,
+/// inflected (and prefixed) according to the name style `NS`. The names are passed
+/// as `(preserve, PascalCase, snake_case, kebab-case)`.
+macro_rules! inflected_name {
+    ($name:ident, $preserve:literal, $pascal:literal, $snake:literal, $kebab:literal) => {
+        struct $name;
+        impl ::metrique_core::concat::InflectedName for $name {
+            const PRESERVE: &'static str = $preserve;
+            const PASCAL_CASE: &'static str = $pascal;
+            const SNAKE_CASE: &'static str = $snake;
+            const KEBAB_CASE: &'static str = $kebab;
+        }
+        impl $name {
+            fn value<NS: ::metrique_core::NameStyle>() -> ::std::borrow::Cow<'static, str> {
+                ::metrique_core::concat::inflected_name::<NS, Self>()
+            }
+        }
+    };
+}
+
+pub(crate) use inflected_name;
+
+inflected_name!(StyleMarker, "\0i", "\0p", "\0s", "\0k");
+
+/// Inflect (and prefix) `name`, which is only known at runtime, according to the name style `NS`.
+///
+/// `name` should be in `snake_case`, like a field name.
+pub(crate) fn inflect_runtime_name<NS: metrique_core::NameStyle>(name: &str) -> String {
+    // the marker tells apart the name style, and everything before it is the inflected prefix
+    let marker = StyleMarker::value::<NS>();
+    let (prefix, style) = marker.split_at(marker.len() - 2);
+    let mut out = String::with_capacity(prefix.len() + name.len());
+    out.push_str(prefix);
+    match style {
+        "\0p" => {
+            for word in name.split('_').filter(|word| !word.is_empty()) {
+                let mut chars = word.chars();
+                out.extend(chars.next().map(|c| c.to_ascii_uppercase()));
+                out.extend(chars);
+            }
+        }
+        "\0k" => out.extend(name.chars().map(|c| if c == '_' { '-' } else { c })),
+        _ => out.push_str(name),
+    }
+    out
+}
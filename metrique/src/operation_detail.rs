@@ -0,0 +1,193 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! [`OperationDetail`] lets a metric hold operation-specific metrics that are selected at runtime,
+//! for frameworks that dispatch operations dynamically and can't list every detail type in one
+//! enum.
+//!
+//! A parent metric holds an `Option<Box<dyn OperationDetail>>` field with
+//! `#[metrics(flatten_entry)]`, created from an [`OperationDetailRegistry`] keyed by operation
+//! name. The handler of the operation downcasts the detail to its own metrics type to update it,
+//! and the detail is written into the parent entry when it closes.
+//!
+//! ```
+//! use metrique::operation_detail::{OperationDetail, OperationDetailRegistry};
+//! use metrique::unit_of_work::metrics;
+//!
+//! #[metrics(rename_all = "PascalCase")]
+//! struct RequestMetrics {
+//!     operation: String,
+//!     #[metrics(flatten_entry)]
+//!     detail: Option<Box<dyn OperationDetail>>,
+//! }
+//!
+//! #[metrics(rename_all = "PascalCase")]
+//! #[derive(Default)]
+//! struct GetObjectMetrics {
+//!     bytes_read: u64,
+//! }
+//!
+//! #[metrics(rename_all = "PascalCase")]
+//! #[derive(Default)]
+//! struct ListObjectsMetrics {
+//!     keys_listed: u64,
+//! }
+//!
+//! let registry = OperationDetailRegistry::new()
+//!     .register::<GetObjectMetrics>("GetObject")
+//!     .register::<ListObjectsMetrics>("ListObjects");
+//!
+//! // the framework creates the metrics of the operation it dispatches to
+//! let operation = "GetObject";
+//! let mut metrics = RequestMetrics {
+//!     operation: operation.into(),
+//!     detail: registry.create(operation),
+//! };
+//!
+//! // the handler of the operation updates its own metrics
+//! let detail = metrics.detail.as_mut().and_then(|detail| detail.downcast_mut());
+//! if let Some(detail) = detail {
+//!     let detail: &mut GetObjectMetrics = detail;
+//!     detail.bytes_read += 1024;
+//! }
+//!
+//! let entry = metrique::test_util::test_metric(metrics);
+//! assert_eq!(entry.values["Operation"], "GetObject");
+//! assert_eq!(entry.metrics["BytesRead"], 1024);
+//! ```
+//!
+//! The detail is written like a root entry, so its fields are named by its own `rename_all`, and
+//! the `rename_all` and `prefix` of the parent don't apply to them.
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+
+use metrique_core::{CloseValue, InflectableEntry};
+use metrique_writer_core::entry::SampleGroupElement;
+use metrique_writer_core::{BoxEntry, Entry, EntryWriter};
+
+/// Metrics of an operation, held by their parent metric as a `Box<dyn OperationDetail>`.
+///
+/// This is implemented for every metric that closes into an entry, like the structs with
+/// `#[metrics]`. See the [module docs](self) for an example.
+pub trait OperationDetail: Any + Send + Sync {
+    /// Close this detail into the entry that is written into its parent
+    fn close_detail(self: Box<Self>) -> OperationDetailEntry;
+}
+
+impl<T> OperationDetail for T
+where
+    T: CloseValue + Any + Send + Sync,
+    T::Closed: InflectableEntry + Send + 'static,
+{
+    fn close_detail(self: Box<Self>) -> OperationDetailEntry {
+        OperationDetailEntry(BoxEntry::new(Detail((*self).close())))
+    }
+}
+
+impl dyn OperationDetail {
+    /// Returns the detail as a `T`, if it is one
+    pub fn downcast_ref<T: OperationDetail>(&self) -> Option<&T> {
+        (self as &dyn Any).downcast_ref()
+    }
+
+    /// Returns the detail as a mutable `T`, if it is one
+    pub fn downcast_mut<T: OperationDetail>(&mut self) -> Option<&mut T> {
+        (self as &mut dyn Any).downcast_mut()
+    }
+}
+
+impl Debug for dyn OperationDetail {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OperationDetail").finish_non_exhaustive()
+    }
+}
+
+impl CloseValue for Box<dyn OperationDetail> {
+    type Closed = OperationDetailEntry;
+
+    fn close(self) -> Self::Closed {
+        self.close_detail()
+    }
+}
+
+/// The closed form of a `Box<dyn OperationDetail>`, written into its parent with
+/// `#[metrics(flatten_entry)]`
+pub struct OperationDetailEntry(BoxEntry);
+
+impl Entry for OperationDetailEntry {
+    fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+        self.0.write(writer);
+    }
+
+    fn sample_group(&self) -> impl Iterator<Item = SampleGroupElement> {
+        self.0.sample_group()
+    }
+}
+
+impl Debug for OperationDetailEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OperationDetailEntry")
+            .finish_non_exhaustive()
+    }
+}
+
+/// Writes a closed metric with its own names, like a root entry but without the global and scoped
+/// fields that the root entry of the parent already writes
+struct Detail<M>(M);
+
+impl<M: InflectableEntry> Entry for Detail<M> {
+    fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+        self.0.write(writer);
+    }
+
+    fn sample_group(&self) -> impl Iterator<Item = SampleGroupElement> {
+        self.0.sample_group()
+    }
+}
+
+/// Creates the [`OperationDetail`] of an operation from its name.
+///
+/// See the [module docs](self) for an example.
+#[derive(Default, Clone)]
+pub struct OperationDetailRegistry {
+    factories: HashMap<String, fn() -> Box<dyn OperationDetail>>,
+}
+
+impl OperationDetailRegistry {
+    /// An empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a `T::default()` as the detail of `operation`, replacing any previous registration
+    pub fn register<T: OperationDetail + Default>(self, operation: impl Into<String>) -> Self {
+        self.register_with(operation, || Box::new(T::default()))
+    }
+
+    /// Create the detail of `operation` with `factory`, replacing any previous registration
+    pub fn register_with(
+        mut self,
+        operation: impl Into<String>,
+        factory: fn() -> Box<dyn OperationDetail>,
+    ) -> Self {
+        self.factories.insert(operation.into(), factory);
+        self
+    }
+
+    /// Create the detail of `operation`, or `None` if no detail is registered for it
+    pub fn create(&self, operation: &str) -> Option<Box<dyn OperationDetail>> {
+        self.factories.get(operation).map(|factory| factory())
+    }
+}
+
+impl Debug for OperationDetailRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut operations: Vec<&str> = self.factories.keys().map(String::as_str).collect();
+        operations.sort_unstable();
+        f.debug_struct("OperationDetailRegistry")
+            .field("operations", &operations)
+            .finish()
+    }
+}
@@ -0,0 +1,175 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! A shared success/error/fault/throttle convention for units of work.
+//!
+//! [`Outcome`] is meant to be flattened into a metrics struct. It records four counts,
+//! `Success`, `Error`, `Fault` and `Throttle`, exactly one of which is `1` once the outcome
+//! is set. The names follow the `rename_all` of the containing struct, and can be prefixed with
+//! `#[metrics(flatten, prefix = "...")]`.
+//!
+//! Errors are classified by implementing [`ClassifyError`] for your error type:
+//!
+//! ```rust
+//! use metrique::outcome::{ClassifyError, Outcome, OutcomeKind};
+//! use metrique::unit_of_work::metrics;
+//!
+//! enum MyError {
+//!     InvalidInput,
+//!     TooManyRequests,
+//!     DatabaseUnavailable,
+//! }
+//!
+//! impl ClassifyError for MyError {
+//!     fn classify(&self) -> OutcomeKind {
+//!         match self {
+//!             MyError::InvalidInput => OutcomeKind::Error,
+//!             MyError::TooManyRequests => OutcomeKind::Throttle,
+//!             MyError::DatabaseUnavailable => OutcomeKind::Fault,
+//!         }
+//!     }
+//! }
+//!
+//! #[metrics(rename_all = "PascalCase")]
+//! struct RequestMetrics {
+//!     #[metrics(flatten)]
+//!     outcome: Outcome,
+//! }
+//!
+//! let mut metrics = RequestMetrics { outcome: Outcome::new() };
+//! let result: Result<(), MyError> = Err(MyError::TooManyRequests);
+//! metrics.outcome.set_result(&result);
+//!
+//! let entry = metrique::test_util::test_metric(metrics);
+//! assert_eq!(entry.metrics["Success"], 0);
+//! assert_eq!(entry.metrics["Error"], 0);
+//! assert_eq!(entry.metrics["Fault"], 0);
+//! assert_eq!(entry.metrics["Throttle"], 1);
+//! ```
+//!
+//! If the outcome is never set (for example, because the unit of work was cancelled),
+//! all four counts are `0`.
+
+use metrique_core::{CloseValue, InflectableEntry, NameStyle};
+use metrique_writer::EntryWriter;
+
+use crate::names::inflected_name;
+
+/// The classification of the result of a unit of work
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum OutcomeKind {
+    /// The unit of work succeeded
+    Success,
+    /// The unit of work failed because of the caller, for example because of invalid input
+    Error,
+    /// The unit of work failed because of the service or one of its dependencies
+    Fault,
+    /// The unit of work was rejected because of rate limiting or load shedding
+    Throttle,
+}
+
+/// Classifies an error into an [`OutcomeKind`], for use with [`Outcome::set_result`].
+pub trait ClassifyError {
+    /// Classify this error. This would normally not return [`OutcomeKind::Success`].
+    fn classify(&self) -> OutcomeKind;
+}
+
+impl<E: ClassifyError + ?Sized> ClassifyError for &E {
+    fn classify(&self) -> OutcomeKind {
+        (**self).classify()
+    }
+}
+
+impl<E: ClassifyError + ?Sized> ClassifyError for Box<E> {
+    fn classify(&self) -> OutcomeKind {
+        (**self).classify()
+    }
+}
+
+/// Records the outcome of a unit of work as `Success`, `Error`, `Fault` and `Throttle` counts.
+///
+/// See the [module documentation](self) for an example.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Outcome {
+    kind: Option<OutcomeKind>,
+}
+
+impl Outcome {
+    /// Create an `Outcome` that is not set yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the outcome, replacing any previous one
+    pub fn set(&mut self, kind: OutcomeKind) {
+        self.kind = Some(kind);
+    }
+
+    /// Set the outcome to [`OutcomeKind::Success`]
+    pub fn set_success(&mut self) {
+        self.set(OutcomeKind::Success);
+    }
+
+    /// Set the outcome by classifying `error`
+    pub fn set_error<E: ClassifyError + ?Sized>(&mut self, error: &E) {
+        self.set(error.classify());
+    }
+
+    /// Set the outcome to [`OutcomeKind::Success`] if `result` is `Ok`, or by classifying
+    /// the error otherwise
+    pub fn set_result<T, E: ClassifyError>(&mut self, result: &Result<T, E>) {
+        match result {
+            Ok(_) => self.set_success(),
+            Err(error) => self.set_error(error),
+        }
+    }
+
+    /// The outcome, if it has been set
+    pub fn kind(&self) -> Option<OutcomeKind> {
+        self.kind
+    }
+}
+
+impl From<OutcomeKind> for Outcome {
+    fn from(kind: OutcomeKind) -> Self {
+        Self { kind: Some(kind) }
+    }
+}
+
+impl CloseValue for &Outcome {
+    type Closed = OutcomeEntry;
+
+    fn close(self) -> Self::Closed {
+        OutcomeEntry { kind: self.kind }
+    }
+}
+
+impl CloseValue for Outcome {
+    type Closed = OutcomeEntry;
+
+    fn close(self) -> Self::Closed {
+        OutcomeEntry { kind: self.kind }
+    }
+}
+
+/// The closed value of an [`Outcome`]
+#[derive(Debug, Clone, Copy)]
+pub struct OutcomeEntry {
+    kind: Option<OutcomeKind>,
+}
+
+inflected_name!(SuccessName, "success", "Success", "success", "success");
+inflected_name!(ErrorName, "error", "Error", "error", "error");
+inflected_name!(FaultName, "fault", "Fault", "fault", "fault");
+inflected_name!(ThrottleName, "throttle", "Throttle", "throttle", "throttle");
+
+impl<NS: NameStyle> InflectableEntry<NS> for OutcomeEntry {
+    fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+        let count = |kind| u64::from(self.kind == Some(kind));
+        writer.value(SuccessName::value::<NS>(), &count(OutcomeKind::Success));
+        writer.value(ErrorName::value::<NS>(), &count(OutcomeKind::Error));
+        writer.value(FaultName::value::<NS>(), &count(OutcomeKind::Fault));
+        writer.value(ThrottleName::value::<NS>(), &count(OutcomeKind::Throttle));
+    }
+}
@@ -0,0 +1,184 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Request-scoped properties, added to every [`RootEntry`] created within a scope.
+//!
+//! Correlation ids like a trace id or a tenant id are often known by the code that handles a
+//! request, but not by every metric struct appended while handling it. [`scope`] runs a future
+//! with a set of [`ScopedFields`], and every [`RootEntry`] created while the future runs,
+//! including the entries appended by [`append_on_drop`], writes them after its own fields.
+//! [`sync_scope`] does the same for synchronous code on the current thread.
+//!
+//! ```rust
+//! use metrique::scoped_fields::{self, ScopedFields};
+//! use metrique::test_util::{TestEntrySink, test_entry_sink};
+//! use metrique::unit_of_work::metrics;
+//!
+//! #[metrics(rename_all = "PascalCase")]
+//! struct CacheMetrics {
+//!     hit: bool,
+//! }
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() {
+//! let TestEntrySink { inspector, sink } = test_entry_sink();
+//! let fields = ScopedFields::new()
+//!     .with("TraceId", "1-5759e988-bd862e3fe1be46a994272793")
+//!     .with("TenantId", String::from("tenant-42"));
+//! scoped_fields::scope(fields, async {
+//!     // deep inside the request handling, without access to the trace id
+//!     CacheMetrics { hit: true }.append_on_drop(sink.clone());
+//! })
+//! .await;
+//!
+//! let entry = &inspector.entries()[0];
+//! assert_eq!(entry.values["TraceId"], "1-5759e988-bd862e3fe1be46a994272793");
+//! assert_eq!(entry.values["TenantId"], "tenant-42");
+//! # }
+//! ```
+//!
+//! Scopes nest: a scope inherits the fields of the scope it is created in, and its own fields
+//! replace inherited fields with the same name. Like every task-local, the fields are not
+//! inherited by spawned tasks; wrap the spawned future with [`in_current_scope`] to keep them.
+//!
+//! The fields are captured when the [`RootEntry`] is created. For [`append_on_drop`], this is
+//! when the guard (or its last handle) is dropped, so entries that outlive the request, for
+//! example because a handle was moved to a background task, don't get the fields. The names are
+//! written as-is, and should not collide with the names of the fields of the entries or of the
+//! [global fields](crate::global_fields).
+//!
+//! [`RootEntry`]: crate::RootEntry
+//! [`append_on_drop`]: crate::append_and_close
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use metrique_writer_core::{Entry, EntryWriter};
+
+tokio::task_local! {
+    static TASK_FIELDS: Arc<ScopedFields>;
+}
+
+thread_local! {
+    static THREAD_FIELDS: RefCell<Option<Arc<ScopedFields>>> = const { RefCell::new(None) };
+}
+
+// orders the scopes by creation. When a task scope and a thread scope are both active, the one
+// created last was created inside the other, and already contains its fields.
+static NEXT_SCOPE: AtomicU64 = AtomicU64::new(0);
+
+// set once the first scope is created, so that processes that never use scoped fields skip the
+// task-local and thread-local lookups for every `RootEntry`
+static SCOPE_CREATED: AtomicBool = AtomicBool::new(false);
+
+/// A set of request-scoped fields, see the [module docs](self)
+#[derive(Debug, Clone, Default)]
+pub struct ScopedFields {
+    fields: Vec<(Cow<'static, str>, Cow<'static, str>)>,
+    // set when the fields are installed in a scope
+    seq: u64,
+}
+
+impl ScopedFields {
+    /// An empty set of fields
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the field `name`, replacing its previous value if it was already added
+    pub fn with(
+        mut self,
+        name: impl Into<Cow<'static, str>>,
+        value: impl Into<Cow<'static, str>>,
+    ) -> Self {
+        self.insert(name.into(), value.into());
+        self
+    }
+
+    /// The fields, in the order they were first added
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.fields
+            .iter()
+            .map(|(name, value)| (name.as_ref(), value.as_ref()))
+    }
+
+    /// Returns true if there are no fields
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    fn insert(&mut self, name: Cow<'static, str>, value: Cow<'static, str>) {
+        match self.fields.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, current)) => *current = value,
+            None => self.fields.push((name, value)),
+        }
+    }
+
+    /// The fields of the current scope, followed by `self`
+    fn inherit(self) -> Arc<Self> {
+        SCOPE_CREATED.store(true, Ordering::Relaxed);
+        let mut fields = current()
+            .map(|outer| outer.fields.clone())
+            .unwrap_or_default();
+        let mut merged = Self {
+            fields: Vec::with_capacity(fields.len() + self.fields.len()),
+            seq: NEXT_SCOPE.fetch_add(1, Ordering::Relaxed),
+        };
+        for (name, value) in fields.drain(..).chain(self.fields) {
+            merged.insert(name, value);
+        }
+        Arc::new(merged)
+    }
+}
+
+impl Entry for ScopedFields {
+    fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+        for (name, value) in &self.fields {
+            writer.value(&**name, &**value);
+        }
+    }
+}
+
+/// Run `future` with `fields` added to the fields of the current scope
+pub fn scope<F: Future>(fields: ScopedFields, future: F) -> impl Future<Output = F::Output> {
+    TASK_FIELDS.scope(fields.inherit(), future)
+}
+
+/// Run `future` with the fields of the current scope, for example in a spawned task
+pub fn in_current_scope<F: Future>(future: F) -> impl Future<Output = F::Output> {
+    scope(ScopedFields::new(), future)
+}
+
+/// Run `f` on the current thread with `fields` added to the fields of the current scope
+pub fn sync_scope<R>(fields: ScopedFields, f: impl FnOnce() -> R) -> R {
+    struct Restore(Option<Arc<ScopedFields>>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            THREAD_FIELDS.with(|current| *current.borrow_mut() = self.0.take());
+        }
+    }
+
+    let fields = fields.inherit();
+    let _restore = Restore(THREAD_FIELDS.with(|current| current.replace(Some(fields))));
+    f()
+}
+
+/// The fields of the current scope, or `None` outside of any scope
+pub fn current() -> Option<Arc<ScopedFields>> {
+    // code running in a scope runs after the scope was created on the same thread, or on a thread
+    // the task was handed to, which synchronizes with it, so it always sees the store
+    if !SCOPE_CREATED.load(Ordering::Relaxed) {
+        return None;
+    }
+    let task = TASK_FIELDS.try_with(Arc::clone).ok();
+    let thread = THREAD_FIELDS.with(|current| current.borrow().clone());
+    let fields = match (task, thread) {
+        (Some(task), Some(thread)) => Some(if task.seq > thread.seq { task } else { thread }),
+        (task, thread) => task.or(thread),
+    };
+    fields.filter(|fields| !fields.is_empty())
+}
@@ -0,0 +1,152 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! [`ShardedCounter`] is a [`Counter`](crate::Counter) for fields that many threads increment at
+//! the same time.
+
+use std::fmt::{self, Debug};
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use metrique_core::CloseValue;
+
+/// The most shards a [`ShardedCounter`] uses by default
+const MAX_DEFAULT_SHARDS: usize = 64;
+
+/// A counter that keeps one shard per thread (up to the number of shards) and merges them when
+/// the entry closes.
+///
+/// A [`Counter`](crate::Counter) is a single atomic, so when many threads increment the same
+/// counter within one unit of work, for example per-item counters in a parallel batch job, they
+/// all contend on the same cache line. A `ShardedCounter` spreads the increments over shards on
+/// separate cache lines, picked by the incrementing thread, and adds them up at close. Reading
+/// the value with [`ShardedCounter::sum`] is as expensive as closing it, so prefer a `Counter` for
+/// fields that are read often or updated from a single thread.
+///
+/// By default, there is one shard per CPU (see [`std::thread::available_parallelism`]), up to 64.
+/// Each shard takes 128 bytes, so a counter takes up to 8 KiB. Use [`ShardedCounter::with_shards`]
+/// to use fewer shards, for example for entries that are created at a high rate.
+///
+/// # Example
+/// ```
+/// use metrique::{ServiceMetrics, ShardedCounter, unit_of_work::metrics};
+/// use metrique::writer::GlobalEntrySink;
+///
+/// #[metrics(rename_all = "PascalCase")]
+/// struct BatchMetrics {
+///     items_processed: ShardedCounter,
+///     items_failed: ShardedCounter,
+/// }
+///
+/// fn process_batch(items: Vec<u64>) {
+///     let metrics = BatchMetrics {
+///         items_processed: ShardedCounter::new(),
+///         items_failed: ShardedCounter::new(),
+///     }
+///     .append_on_drop(ServiceMetrics::sink());
+///     std::thread::scope(|scope| {
+///         for chunk in items.chunks(1024) {
+///             let metrics = &metrics;
+///             scope.spawn(move || {
+///                 for item in chunk {
+///                     if *item % 7 == 0 {
+///                         metrics.items_failed.increment();
+///                     }
+///                     metrics.items_processed.increment();
+///                 }
+///             });
+///         }
+///     });
+/// }
+/// ```
+pub struct ShardedCounter {
+    shards: Box<[Shard]>,
+}
+
+// 128 bytes covers the adjacent-line prefetcher of x86_64 and the cache lines of recent ARM cores
+#[repr(align(128))]
+#[derive(Default)]
+struct Shard(AtomicU64);
+
+impl ShardedCounter {
+    /// A counter starting at 0, with one shard per CPU (up to 64)
+    pub fn new() -> Self {
+        // `available_parallelism` reads cgroup limits on Linux, so only call it once
+        static DEFAULT_SHARDS: OnceLock<usize> = OnceLock::new();
+        let shards = *DEFAULT_SHARDS.get_or_init(|| {
+            std::thread::available_parallelism()
+                .map_or(1, |cpus| cpus.get())
+                .min(MAX_DEFAULT_SHARDS)
+        });
+        Self::with_shards(shards)
+    }
+
+    /// A counter starting at 0, with `shards` shards (at least 1)
+    pub fn with_shards(shards: usize) -> Self {
+        Self {
+            shards: (0..shards.max(1)).map(|_| Shard::default()).collect(),
+        }
+    }
+
+    /// Add 1 to this counter
+    pub fn increment(&self) {
+        self.add(1);
+    }
+
+    /// Increase the value of this counter by `i`
+    pub fn add(&self, i: u64) {
+        self.shard().0.fetch_add(i, Ordering::Relaxed);
+    }
+
+    /// The current value of this counter, the sum of its shards
+    pub fn sum(&self) -> u64 {
+        self.shards
+            .iter()
+            .map(|shard| shard.0.load(Ordering::Relaxed))
+            .fold(0, u64::wrapping_add)
+    }
+
+    fn shard(&self) -> &Shard {
+        // threads are numbered in the order they first increment any sharded counter, process
+        // wide, and two threads share a shard when their numbers are equal modulo `shards`. So up
+        // to `shards` threads that started incrementing one right after the other get different
+        // shards, but any other threads may share one.
+        static NEXT_THREAD: AtomicUsize = AtomicUsize::new(0);
+        thread_local! {
+            static THREAD: usize = NEXT_THREAD.fetch_add(1, Ordering::Relaxed);
+        }
+        let thread = THREAD.with(|thread| *thread);
+        &self.shards[thread % self.shards.len()]
+    }
+}
+
+impl Default for ShardedCounter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for ShardedCounter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShardedCounter")
+            .field("sum", &self.sum())
+            .field("shards", &self.shards.len())
+            .finish()
+    }
+}
+
+impl CloseValue for &'_ ShardedCounter {
+    type Closed = u64;
+
+    fn close(self) -> Self::Closed {
+        self.sum()
+    }
+}
+
+impl CloseValue for ShardedCounter {
+    type Closed = u64;
+
+    fn close(self) -> Self::Closed {
+        self.sum()
+    }
+}
@@ -11,7 +11,7 @@ use std::ops::DerefMut;
 use std::unreachable;
 use tokio::sync::oneshot;
 
-fn make_slot<T: CloseValue>(initial_value: T) -> (SlotGuard<T>, Waiting<T::Closed>) {
+pub(crate) fn make_slot<T: CloseValue>(initial_value: T) -> (SlotGuard<T>, Waiting<T::Closed>) {
     let (tx, rx) = oneshot::channel();
     (
         SlotGuard {
@@ -247,7 +247,7 @@ impl<T: CloseValue> CloseValue for Slot<T> {
 /// This struct is used internally by `Slot` to wait for a value to be sent back
 /// from a `SlotGuard` when it is dropped.
 #[derive(Debug)]
-struct Waiting<T> {
+pub(crate) struct Waiting<T> {
     rx: oneshot::Receiver<T>,
 }
 
@@ -256,7 +256,7 @@ impl<T> Waiting<T> {
     ///
     /// Returns `Some(T)` if the value is available, or `None` if the sender
     /// has not yet sent a value or has been dropped.
-    fn take_value(mut self) -> Option<T> {
+    pub(crate) fn take_value(mut self) -> Option<T> {
         self.rx.try_recv().ok()
     }
 
@@ -264,7 +264,7 @@ impl<T> Waiting<T> {
     ///
     /// Returns `Some(T)` if the value is received, or `None` if the sender
     /// was dropped without sending a value.
-    async fn wait_for_value(self) -> Option<T> {
+    pub(crate) async fn wait_for_value(self) -> Option<T> {
         self.rx.await.ok()
     }
 }
@@ -279,7 +279,7 @@ impl<T> Waiting<T> {
 /// guard is dropped.
 pub struct SlotGuard<T: CloseValue> {
     slot: SlotI<T>,
-    parent_drop_mode: OnParentDrop,
+    pub(crate) parent_drop_mode: OnParentDrop,
 }
 
 impl<T: Debug + CloseValue> Debug for SlotGuard<T> {
@@ -427,8 +427,8 @@ mod test {
     fn test_parent_is_closed() {
         let mut slot: Slot<TestCloseable> = Slot::default();
         let guard = slot.open(OnParentDrop::Discard).unwrap();
-        assert_eq!(guard.parent_is_closed(), false);
+        assert!(!guard.parent_is_closed());
         drop(slot);
-        assert_eq!(guard.parent_is_closed(), true);
+        assert!(guard.parent_is_closed());
     }
 }
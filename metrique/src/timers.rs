@@ -2,14 +2,20 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use std::{
+    borrow::Cow,
+    collections::BTreeMap,
     marker::PhantomData,
     ops::AddAssign,
-    sync::{Arc, Mutex},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
     time::{Duration, UNIX_EPOCH},
 };
 
-use metrique_core::CloseValue;
+use metrique_core::{CloseValue, InflectableEntry, NameStyle};
 use metrique_timesource::{Instant, SystemTime, TimeSource, time_source};
+use metrique_writer_core::EntryWriter;
 use metrique_writer_core::{
     Value,
     unit::{Millisecond, Second},
@@ -17,6 +23,8 @@ use metrique_writer_core::{
 use metrique_writer_core::{unit::Microsecond, value::ValueFormatter};
 use timestamp_to_str::TimestampToStr;
 
+use crate::names::inflect_runtime_name;
+
 /// Timestamp of a metric entry
 ///
 /// This type should with `#[metrics(timestamp)]` attribute on the root of your metrics entry.
@@ -163,6 +171,72 @@ impl From<TimestampValue> for std::time::SystemTime {
     }
 }
 
+/// A closed value that can be used with `#[metrics(timestamp(property))]`.
+///
+/// `timestamp(property)` emits a secondary timestamp, like the start time of a request, as a
+/// property formatted like the canonical timestamp. The canonical `#[metrics(timestamp)]` stays unique.
+///
+/// ```rust
+/// use std::time::{Duration, SystemTime, UNIX_EPOCH};
+/// use metrique::timers::{EpochSeconds, Timestamp};
+/// use metrique::unit_of_work::metrics;
+///
+/// #[metrics(rename_all = "PascalCase")]
+/// struct RequestMetrics {
+///     #[metrics(timestamp)]
+///     timestamp: Timestamp,
+///     #[metrics(timestamp(property))]
+///     start_time: SystemTime,
+///     #[metrics(timestamp(property), format = EpochSeconds)]
+///     first_byte_time: Option<SystemTime>,
+/// }
+///
+/// let entry = metrique::test_util::test_metric(RequestMetrics {
+///     timestamp: Timestamp::now(),
+///     start_time: UNIX_EPOCH + Duration::from_secs(1),
+///     first_byte_time: Some(UNIX_EPOCH + Duration::from_millis(1500)),
+/// });
+/// assert_eq!(entry.values["StartTime"], "1000.0");
+/// assert_eq!(entry.values["FirstByteTime"], "1.5");
+/// ```
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` can't be used with `#[metrics(timestamp(property))]`",
+    note = "`timestamp(property)` applies to the closed value of the field, which must be a `TimestampValue` (the closed value of `Timestamp`), a `SystemTime`, or an `Option` of those"
+)]
+pub trait IntoTimestampValue {
+    /// The type stored in the closed entry
+    type Output;
+
+    /// Convert the closed value to a timestamp
+    fn into_timestamp_value(self) -> Self::Output;
+}
+
+impl IntoTimestampValue for TimestampValue {
+    type Output = TimestampValue;
+
+    fn into_timestamp_value(self) -> Self::Output {
+        self
+    }
+}
+
+impl IntoTimestampValue for std::time::SystemTime {
+    type Output = TimestampValue;
+
+    fn into_timestamp_value(self) -> Self::Output {
+        TimestampValue {
+            duration_since_epoch: self.duration_since(UNIX_EPOCH).unwrap_or_default(),
+        }
+    }
+}
+
+impl<T: IntoTimestampValue> IntoTimestampValue for Option<T> {
+    type Output = Option<T::Output>;
+
+    fn into_timestamp_value(self) -> Self::Output {
+        self.map(T::into_timestamp_value)
+    }
+}
+
 #[doc(hidden)]
 pub struct TimestampFormat<Unit> {
     u: PhantomData<Unit>,
@@ -221,19 +295,22 @@ mod timestamp_to_str {
 /// They are started automatically and stop automatically when dropped (unless you call `Timer::stop` first.)
 /// If you want a timer you can control explicitly, use [`Stopwatch`]
 ///
-/// Unlike [`Stopwatch`], timer records a single continuous span of time. It cannot be restarted after it is stopped.
+/// Unlike [`Stopwatch`], timer records a single span of time. It cannot be restarted after it is stopped.
+/// Time spent between [`Timer::pause`] and [`Timer::resume`] is excluded from the span, which is useful
+/// to exclude time that is controlled by the client, such as long-poll idle time.
 #[derive(Debug)]
 pub struct Timer {
     start: Instant,
     duration: Option<Duration>,
+    // time since `start` at which the timer was paused, if it is paused
+    paused_at: Option<Duration>,
+    // total time spent paused, excluding the current pause
+    paused: Duration,
 }
 
 impl Default for Timer {
     fn default() -> Self {
-        Self {
-            start: time_source().instant(),
-            duration: None,
-        }
+        Self::start_now_with_timesource(time_source())
     }
 }
 
@@ -255,6 +332,23 @@ impl Timer {
         Self::start_now_with_timesource(time_source())
     }
 
+    /// Creates a new timer that starts immediately using the process-wide coarse clock,
+    /// [`TimeSource::coarse`].
+    ///
+    /// The coarse clock is a cached time that a background thread updates every millisecond, so
+    /// starting and stopping the timer is cheaper than reading the system clock, at the cost of
+    /// millisecond resolution. Prefer this for timers on hot paths where `Instant::now()` shows up
+    /// in profiles.
+    ///
+    /// Note that this ignores any time source installed with
+    /// [`set_time_source`](metrique_timesource::set_time_source).
+    ///
+    /// This requires that the `coarse-clock` feature be enabled.
+    #[cfg(feature = "coarse-clock")]
+    pub fn start_now_coarse() -> Self {
+        Self::start_now_with_timesource(TimeSource::coarse())
+    }
+
     /// Creates a new timer that starts immediately using the specified time source.
     ///
     /// This is useful for testing with a mock time source.
@@ -272,6 +366,8 @@ impl Timer {
         Self {
             start: timesource.instant(),
             duration: None,
+            paused_at: None,
+            paused: Duration::ZERO,
         }
     }
 
@@ -301,17 +397,65 @@ impl Timer {
             return duration;
         }
 
-        let time = self.start.elapsed();
+        let time = self.elapsed();
         self.duration = Some(time);
         time
     }
+
+    /// Pauses the timer. Time spent paused is not included in the recorded duration.
+    ///
+    /// Pausing a paused or stopped timer has no effect. If the timer is stopped or closed while
+    /// paused, it records the duration up to the pause.
+    ///
+    /// # Example
+    /// ```
+    /// use metrique::timers::Timer;
+    /// use std::thread::sleep;
+    /// use std::time::Duration;
+    ///
+    /// let mut timer = Timer::start_now();
+    /// // handle the request...
+    /// timer.pause();
+    /// // wait for the client, which shouldn't count towards the latency
+    /// sleep(Duration::from_millis(50));
+    /// timer.resume();
+    /// // finish handling the request...
+    /// let elapsed = timer.stop();
+    /// assert!(elapsed < Duration::from_millis(50));
+    /// ```
+    pub fn pause(&mut self) {
+        if self.duration.is_none() && self.paused_at.is_none() {
+            self.paused_at = Some(self.start.elapsed());
+        }
+    }
+
+    /// Resumes a timer paused by [`Timer::pause`]. Resuming a timer that is not paused has no effect.
+    pub fn resume(&mut self) {
+        if let Some(paused_at) = self.paused_at.take() {
+            self.paused += self.start.elapsed().saturating_sub(paused_at);
+        }
+    }
+
+    /// Returns true if the timer is paused
+    pub fn is_paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+
+    /// The elapsed time, excluding the time spent paused
+    fn elapsed(&self) -> Duration {
+        let end = match self.paused_at {
+            Some(paused_at) => paused_at,
+            None => self.start.elapsed(),
+        };
+        end.saturating_sub(self.paused)
+    }
 }
 
 impl CloseValue for &'_ Timer {
     type Closed = Duration;
 
     fn close(self) -> Self::Closed {
-        self.duration.unwrap_or_else(|| self.start.elapsed())
+        self.duration.unwrap_or_else(|| self.elapsed())
     }
 }
 
@@ -851,6 +995,734 @@ impl CloseValue for Stopwatch {
     }
 }
 
+/// A duration accumulator that can be shared between scopes and tasks, like a [`Counter`] for time
+///
+/// Each call to [`DurationCounter::time_scope`] returns a [`TimeSlice`] guard that adds the time
+/// until it is dropped into the counter, and [`DurationCounter::time_closure`] does the same for
+/// a closure. Since these only need `&self`, the same field can accumulate time from several
+/// places in one unit of work, including from concurrent tasks through a [`Handle`] or an `Arc`.
+///
+/// When closed, a `DurationCounter` records the total accumulated duration.
+///
+/// ```
+/// use metrique::timers::DurationCounter;
+/// use metrique::unit::Millisecond;
+/// use metrique::unit_of_work::metrics;
+///
+/// #[metrics(rename_all = "PascalCase")]
+/// struct RequestMetrics {
+///     #[metrics(unit = Millisecond)]
+///     serialization_time: DurationCounter,
+/// }
+///
+/// let metrics = RequestMetrics {
+///     serialization_time: DurationCounter::new(),
+/// };
+/// let header = metrics.serialization_time.time_closure(|| "header".to_string());
+/// {
+///     let _slice = metrics.serialization_time.time_scope();
+///     // serialize the body...
+/// }
+/// ```
+///
+/// [`Counter`]: crate::Counter
+/// [`Handle`]: crate::AppendAndCloseOnDrop::handle
+#[derive(Debug, Default)]
+pub struct DurationCounter {
+    nanos: AtomicU64,
+}
+
+impl DurationCounter {
+    /// Create a new, empty [`DurationCounter`]
+    pub const fn new() -> Self {
+        Self {
+            nanos: AtomicU64::new(0),
+        }
+    }
+
+    /// Add `duration` to this counter
+    pub fn add(&self, duration: Duration) {
+        let nanos = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+        self.nanos
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |total| {
+                Some(total.saturating_add(nanos))
+            })
+            .ok();
+    }
+
+    /// Start timing a scope. The elapsed time is added to this counter when the returned
+    /// [`TimeSlice`] is dropped or stopped.
+    pub fn time_scope(&self) -> TimeSlice<'_> {
+        self.time_scope_with_timesource(time_source())
+    }
+
+    /// Like [`DurationCounter::time_scope`], using the specified time source
+    pub fn time_scope_with_timesource(&self, time_source: TimeSource) -> TimeSlice<'_> {
+        TimeSlice {
+            start: Some(time_source.instant()),
+            counter: self,
+        }
+    }
+
+    /// Call `f`, adding the time it takes to this counter
+    pub fn time_closure<R>(&self, f: impl FnOnce() -> R) -> R {
+        let _slice = self.time_scope();
+        f()
+    }
+
+    /// The total accumulated duration so far
+    pub fn total(&self) -> Duration {
+        Duration::from_nanos(self.nanos.load(Ordering::Relaxed))
+    }
+}
+
+impl CloseValue for &'_ DurationCounter {
+    type Closed = Duration;
+
+    fn close(self) -> Self::Closed {
+        self.total()
+    }
+}
+
+impl CloseValue for DurationCounter {
+    type Closed = Duration;
+
+    fn close(self) -> Self::Closed {
+        self.total()
+    }
+}
+
+/// A guard that adds the time it was held to a [`DurationCounter`]
+///
+/// Returned by [`DurationCounter::time_scope`]. The time is added when the guard is dropped, or
+/// when [`TimeSlice::stop`] is called.
+#[must_use = "the time slice ends when the guard is dropped"]
+#[derive(Debug)]
+pub struct TimeSlice<'a> {
+    start: Option<Instant>,
+    counter: &'a DurationCounter,
+}
+
+impl TimeSlice<'_> {
+    /// End the slice, adding its duration to the counter, and return that duration
+    pub fn stop(mut self) -> Duration {
+        self.finish()
+    }
+
+    /// End the slice without adding its duration to the counter
+    pub fn discard(mut self) {
+        self.start = None;
+    }
+
+    fn finish(&mut self) -> Duration {
+        match self.start.take() {
+            Some(start) => {
+                let elapsed = start.elapsed();
+                self.counter.add(elapsed);
+                elapsed
+            }
+            None => Duration::ZERO,
+        }
+    }
+}
+
+impl Drop for TimeSlice<'_> {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+/// A guard that attributes the time it was held to one of several fields, depending on the
+/// outcome of the work it timed
+///
+/// An `OutcomeTimer` is started with a default outcome and a function that records the elapsed
+/// time for an outcome, for example by setting the `Option<Duration>` field that matches it.
+/// The outcome can be changed with [`OutcomeTimer::set_outcome`] or when finishing the timer with
+/// [`OutcomeTimer::finish`]. When the guard is dropped, the elapsed time is recorded for the
+/// current outcome, so that early returns are recorded with the default outcome. Only the
+/// matching field is set, instead of keeping a timer per outcome and zeroing all but one of them.
+///
+/// ```
+/// use std::time::Duration;
+/// use metrique::timers::OutcomeTimer;
+/// use metrique::unit_of_work::metrics;
+///
+/// enum Outcome {
+///     Success,
+///     Error,
+/// }
+///
+/// #[metrics(rename_all = "PascalCase")]
+/// #[derive(Default)]
+/// struct RequestMetrics {
+///     success_latency: Option<Duration>,
+///     error_latency: Option<Duration>,
+/// }
+///
+/// fn handle(metrics: &mut RequestMetrics) -> Result<(), String> {
+///     let timer = OutcomeTimer::start_now(Outcome::Error, |outcome, elapsed| match outcome {
+///         Outcome::Success => metrics.success_latency = Some(elapsed),
+///         Outcome::Error => metrics.error_latency = Some(elapsed),
+///     });
+///     // returning early with `?` records `ErrorLatency`...
+///     timer.finish(Outcome::Success);
+///     Ok(())
+/// }
+///
+/// let mut metrics = RequestMetrics::default();
+/// handle(&mut metrics).unwrap();
+/// let entry = metrique::test_util::test_metric(metrics);
+/// assert!(entry.metrics.contains_key("SuccessLatency"));
+/// assert!(!entry.metrics.contains_key("ErrorLatency"));
+/// ```
+///
+/// To add up the time of several scopes per outcome, record into [`DurationCounter`]s instead.
+#[must_use = "the timer stops when the guard is dropped"]
+pub struct OutcomeTimer<O, F: FnOnce(O, Duration)> {
+    start: Instant,
+    outcome: Option<O>,
+    record: Option<F>,
+}
+
+impl<O, F: FnOnce(O, Duration)> OutcomeTimer<O, F> {
+    /// Start timing now, using the default time source. The time is recorded for `outcome`
+    /// unless another outcome is set before the guard is dropped.
+    pub fn start_now(outcome: O, record: F) -> Self {
+        Self::start_now_with_timesource(time_source(), outcome, record)
+    }
+
+    /// Like [`OutcomeTimer::start_now`], using the specified time source
+    pub fn start_now_with_timesource(time_source: TimeSource, outcome: O, record: F) -> Self {
+        Self {
+            start: time_source.instant(),
+            outcome: Some(outcome),
+            record: Some(record),
+        }
+    }
+
+    /// Set the outcome that the time is recorded for, replacing the previous one
+    pub fn set_outcome(&mut self, outcome: O) {
+        self.outcome = Some(outcome);
+    }
+
+    /// Stop the timer, recording the elapsed time for `outcome`, and return the elapsed time
+    pub fn finish(mut self, outcome: O) -> Duration {
+        self.outcome = Some(outcome);
+        self.record()
+    }
+
+    /// Stop the timer without recording the elapsed time
+    pub fn discard(mut self) {
+        self.record = None;
+    }
+
+    fn record(&mut self) -> Duration {
+        let elapsed = self.start.elapsed();
+        if let (Some(outcome), Some(record)) = (self.outcome.take(), self.record.take()) {
+            record(outcome, elapsed);
+        }
+        elapsed
+    }
+}
+
+impl<O, F: FnOnce(O, Duration)> Drop for OutcomeTimer<O, F> {
+    fn drop(&mut self) {
+        self.record();
+    }
+}
+
+impl<O: std::fmt::Debug, F: FnOnce(O, Duration)> std::fmt::Debug for OutcomeTimer<O, F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OutcomeTimer")
+            .field("start", &self.start)
+            .field("outcome", &self.outcome)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A monotonic timer that splits a unit of work into named laps
+///
+/// Every call to [`LapTimer::lap`] ends the current lap, records its duration under the given
+/// label, and starts the next one. When closed, a `LapTimer` emits one metric per label, named
+/// after the label (inflected like a field name, so labels should be `snake_case`) and prefixed
+/// with the prefix of the field. This replaces a struct with one [`Timer`] per phase.
+///
+/// Recording the same label more than once adds up the durations, so a `LapTimer` can be used
+/// inside of a loop. The time after the last lap is not recorded.
+///
+/// `LapTimer` must be flattened into its parent:
+///
+/// ```
+/// use metrique::timers::LapTimer;
+/// use metrique::unit_of_work::metrics;
+///
+/// #[metrics(rename_all = "PascalCase")]
+/// struct RequestMetrics {
+///     operation: &'static str,
+///     #[metrics(flatten, prefix = "time_")]
+///     phases: LapTimer,
+/// }
+///
+/// let mut metrics = RequestMetrics {
+///     operation: "PutItem",
+///     phases: LapTimer::start_now(),
+/// };
+/// // deserialize the request...
+/// metrics.phases.lap("deserialize");
+/// // write to the database...
+/// metrics.phases.lap("db_write");
+///
+/// let entry = metrique::test_util::test_metric(metrics);
+/// assert!(entry.metrics.contains_key("TimeDeserialize"));
+/// assert!(entry.metrics.contains_key("TimeDbWrite"));
+/// ```
+#[derive(Debug)]
+pub struct LapTimer {
+    time_source: TimeSource,
+    lap_start: Instant,
+    laps: Vec<(Cow<'static, str>, Duration)>,
+}
+
+impl Default for LapTimer {
+    fn default() -> Self {
+        Self::start_now()
+    }
+}
+
+impl LapTimer {
+    /// Creates a new lap timer whose first lap starts immediately, using the default time source.
+    pub fn start_now() -> Self {
+        Self::start_now_with_timesource(time_source())
+    }
+
+    /// Creates a new lap timer whose first lap starts immediately, using the specified time source.
+    pub fn start_now_with_timesource(time_source: TimeSource) -> Self {
+        Self {
+            lap_start: time_source.instant(),
+            time_source,
+            laps: Vec::new(),
+        }
+    }
+
+    /// Ends the current lap, records its duration under `label`, and starts the next lap.
+    ///
+    /// Returns the duration of the lap that just ended. If `label` was already recorded, the
+    /// duration is added to it.
+    pub fn lap(&mut self, label: impl Into<Cow<'static, str>>) -> Duration {
+        let now = self.time_source.instant();
+        let elapsed = now.saturating_duration_since(&self.lap_start);
+        self.lap_start = now;
+        let label = label.into();
+        match self
+            .laps
+            .iter_mut()
+            .find(|(existing, _)| *existing == label)
+        {
+            Some((_, total)) => *total += elapsed,
+            None => self.laps.push((label, elapsed)),
+        }
+        elapsed
+    }
+
+    /// Starts a new lap without recording the current one, for example to exclude time spent
+    /// waiting between phases.
+    pub fn skip(&mut self) {
+        self.lap_start = self.time_source.instant();
+    }
+
+    /// Returns the recorded laps, in the order their labels were first recorded
+    pub fn laps(&self) -> impl Iterator<Item = (&str, Duration)> {
+        self.laps
+            .iter()
+            .map(|(label, duration)| (&**label, *duration))
+    }
+}
+
+impl CloseValue for LapTimer {
+    type Closed = LapTimerEntry;
+
+    fn close(self) -> Self::Closed {
+        LapTimerEntry { laps: self.laps }
+    }
+}
+
+/// The closed value of a [`LapTimer`], with one metric per lap
+#[derive(Debug)]
+pub struct LapTimerEntry {
+    laps: Vec<(Cow<'static, str>, Duration)>,
+}
+
+impl<NS: NameStyle> InflectableEntry<NS> for LapTimerEntry {
+    fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+        for (label, duration) in &self.laps {
+            writer.value(inflect_runtime_name::<NS>(label), duration);
+        }
+    }
+}
+
+/// The time between two [`std::time::Instant`]s that were captured elsewhere
+///
+/// This is useful for code that already keeps track of when an operation started and ended (for
+/// example, a connection pool or a protocol library), and would otherwise have to adopt [`Timer`]
+/// just to emit the difference. When closed, a `Spanned` emits the time between `start` and `end`
+/// as `duration` (saturating to zero if `end` is before `start`). If the wall-clock time of the
+/// start was attached with [`Spanned::with_start_time`], it is also emitted as a `start_time`
+/// property, in epoch milliseconds.
+///
+/// `Spanned` must be flattened into its parent, usually with a prefix:
+///
+/// ```
+/// use std::time::{Duration, Instant, SystemTime};
+/// use metrique::timers::Spanned;
+/// use metrique::unit_of_work::metrics;
+///
+/// #[metrics(rename_all = "PascalCase")]
+/// struct RequestMetrics {
+///     #[metrics(flatten, prefix = "connect_")]
+///     connect: Spanned,
+/// }
+///
+/// let start = Instant::now();
+/// let end = start + Duration::from_millis(15);
+/// let entry = metrique::test_util::test_metric(RequestMetrics {
+///     connect: Spanned::new(start, end).with_start_time(SystemTime::UNIX_EPOCH),
+/// });
+/// assert_eq!(entry.metrics["ConnectDuration"], 15);
+/// assert_eq!(entry.values["ConnectStartTime"], "0.0");
+/// ```
+///
+/// To only emit the duration as a regular field, a [`Range`] of instants can be used directly,
+/// since it closes into its duration.
+///
+/// [`Range`]: std::ops::Range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Spanned {
+    start: std::time::Instant,
+    end: std::time::Instant,
+    start_time: Option<std::time::SystemTime>,
+}
+
+impl Spanned {
+    /// Create a span from `start` to `end`
+    pub fn new(start: std::time::Instant, end: std::time::Instant) -> Self {
+        Self {
+            start,
+            end,
+            start_time: None,
+        }
+    }
+
+    /// Attach the wall-clock time at which the span started, which is emitted as `start_time`
+    pub fn with_start_time(mut self, start_time: std::time::SystemTime) -> Self {
+        self.start_time = Some(start_time);
+        self
+    }
+
+    /// The time between the start and the end of the span, or zero if the end is before the start
+    pub fn duration(&self) -> Duration {
+        self.end.saturating_duration_since(self.start)
+    }
+}
+
+impl From<std::ops::Range<std::time::Instant>> for Spanned {
+    fn from(range: std::ops::Range<std::time::Instant>) -> Self {
+        Self::new(range.start, range.end)
+    }
+}
+
+impl CloseValue for Spanned {
+    type Closed = SpannedEntry;
+
+    fn close(self) -> Self::Closed {
+        (&self).close()
+    }
+}
+
+impl CloseValue for &'_ Spanned {
+    type Closed = SpannedEntry;
+
+    fn close(self) -> Self::Closed {
+        SpannedEntry {
+            duration: self.duration(),
+            start_time: self.start_time.into_timestamp_value(),
+        }
+    }
+}
+
+/// The closed value of a [`Spanned`]
+#[derive(Debug, Clone, Copy)]
+pub struct SpannedEntry {
+    duration: Duration,
+    start_time: Option<TimestampValue>,
+}
+
+crate::names::inflected_name!(DurationName, "duration", "Duration", "duration", "duration");
+crate::names::inflected_name!(
+    StartTimeName,
+    "start_time",
+    "StartTime",
+    "start_time",
+    "start-time"
+);
+
+impl<NS: NameStyle> InflectableEntry<NS> for SpannedEntry {
+    fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+        writer.value(DurationName::value::<NS>(), &self.duration);
+        writer.value(StartTimeName::value::<NS>(), &self.start_time);
+    }
+}
+
+/// Statistics of many durations recorded within one unit of work
+///
+/// Every call to [`DurationStats::record`] (or every [`DurationStatsSlice`] returned by
+/// [`DurationStats::time_scope`]) adds one duration, for example the latency of every page of a
+/// paginated downstream call. When closed, a `DurationStats` emits the `count`, `sum`, `min`,
+/// `max`, `p50` and `p99` of the recorded durations, prefixed with the prefix of the field. The
+/// durations are in milliseconds and the count has no unit. If nothing was recorded, only the
+/// count (of zero) is emitted.
+///
+/// The percentiles are approximate: durations are counted in buckets that are at most 1/16
+/// (6.25%) wide, which keeps the memory use bounded no matter how many durations are recorded.
+/// The count, sum, min and max are exact. Like [`DurationCounter`], recording only needs `&self`,
+/// so it works through a [`Handle`] and from concurrent tasks.
+///
+/// `DurationStats` must be flattened into its parent, usually with a prefix:
+///
+/// ```
+/// use std::time::Duration;
+/// use metrique::timers::DurationStats;
+/// use metrique::unit_of_work::metrics;
+///
+/// #[metrics(rename_all = "PascalCase")]
+/// struct RequestMetrics {
+///     #[metrics(flatten, prefix = "list_page_")]
+///     list_pages: DurationStats,
+/// }
+///
+/// let metrics = RequestMetrics {
+///     list_pages: DurationStats::new(),
+/// };
+/// for page in [12, 15, 40] {
+///     metrics.list_pages.record(Duration::from_millis(page));
+/// }
+///
+/// let entry = metrique::test_util::test_metric(metrics);
+/// assert_eq!(entry.metrics["ListPageCount"], 3);
+/// assert_eq!(entry.metrics["ListPageSum"], 67);
+/// assert_eq!(entry.metrics["ListPageMax"], 40);
+/// ```
+///
+/// [`Handle`]: crate::AppendAndCloseOnDrop::handle
+#[derive(Debug, Default)]
+pub struct DurationStats {
+    histogram: Mutex<DurationHistogram>,
+}
+
+#[derive(Debug, Default, Clone)]
+struct DurationHistogram {
+    count: u64,
+    sum_nanos: u64,
+    min_nanos: u64,
+    max_nanos: u64,
+    // bucket => number of durations in it
+    buckets: BTreeMap<u16, u64>,
+}
+
+/// The number of sub-buckets per power of two is `2^SUB_BUCKET_BITS`
+const SUB_BUCKET_BITS: u32 = 4;
+
+/// The bucket of a duration of `nanos`. Values below `2^SUB_BUCKET_BITS` get a bucket each, and
+/// every power of two above is split into `2^SUB_BUCKET_BITS` buckets.
+fn duration_bucket(nanos: u64) -> u16 {
+    let sub_buckets = 1 << SUB_BUCKET_BITS;
+    if nanos < sub_buckets {
+        return nanos as u16;
+    }
+    let exponent = u64::BITS - 1 - nanos.leading_zeros();
+    let sub_bucket = (nanos >> (exponent - SUB_BUCKET_BITS)) & (sub_buckets - 1);
+    ((u64::from(exponent - SUB_BUCKET_BITS + 1) << SUB_BUCKET_BITS) + sub_bucket) as u16
+}
+
+/// The middle of the durations that fall into `bucket`, in nanoseconds
+fn duration_bucket_midpoint(bucket: u16) -> u64 {
+    let sub_buckets = 1 << SUB_BUCKET_BITS;
+    let bucket = u64::from(bucket);
+    if bucket < sub_buckets {
+        return bucket;
+    }
+    let shift = (bucket >> SUB_BUCKET_BITS) - 1;
+    let lower = (sub_buckets + (bucket & (sub_buckets - 1))) << shift;
+    lower + ((1 << shift) >> 1)
+}
+
+impl DurationHistogram {
+    fn record(&mut self, nanos: u64) {
+        if self.count == 0 {
+            self.min_nanos = nanos;
+            self.max_nanos = nanos;
+        } else {
+            self.min_nanos = self.min_nanos.min(nanos);
+            self.max_nanos = self.max_nanos.max(nanos);
+        }
+        self.count += 1;
+        self.sum_nanos = self.sum_nanos.saturating_add(nanos);
+        *self.buckets.entry(duration_bucket(nanos)).or_default() += 1;
+    }
+
+    /// The approximate `quantile` (in `[0, 1]`) of the recorded durations
+    fn quantile(&self, quantile: f64) -> Option<Duration> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = ((quantile * self.count as f64).ceil() as u64).clamp(1, self.count);
+        let mut seen = 0;
+        for (&bucket, &count) in &self.buckets {
+            seen += count;
+            if seen >= rank {
+                let nanos = duration_bucket_midpoint(bucket).clamp(self.min_nanos, self.max_nanos);
+                return Some(Duration::from_nanos(nanos));
+            }
+        }
+        Some(Duration::from_nanos(self.max_nanos))
+    }
+
+    fn close(&self) -> DurationStatsEntry {
+        let recorded = |nanos| (self.count > 0).then(|| Duration::from_nanos(nanos));
+        DurationStatsEntry {
+            count: self.count,
+            sum: recorded(self.sum_nanos),
+            min: recorded(self.min_nanos),
+            max: recorded(self.max_nanos),
+            p50: self.quantile(0.5),
+            p99: self.quantile(0.99),
+        }
+    }
+}
+
+impl DurationStats {
+    /// Create a new, empty [`DurationStats`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record `duration`
+    pub fn record(&self, duration: Duration) {
+        let nanos = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+        self.lock().record(nanos);
+    }
+
+    /// Start timing a scope. The elapsed time is recorded when the returned
+    /// [`DurationStatsSlice`] is dropped or stopped.
+    pub fn time_scope(&self) -> DurationStatsSlice<'_> {
+        self.time_scope_with_timesource(time_source())
+    }
+
+    /// Like [`DurationStats::time_scope`], using the specified time source
+    pub fn time_scope_with_timesource(&self, time_source: TimeSource) -> DurationStatsSlice<'_> {
+        DurationStatsSlice {
+            start: Some(time_source.instant()),
+            stats: self,
+        }
+    }
+
+    /// The number of durations recorded so far
+    pub fn count(&self) -> u64 {
+        self.lock().count
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, DurationHistogram> {
+        // the histogram is always consistent, so it is fine to ignore poisoning
+        self.histogram
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl CloseValue for &'_ DurationStats {
+    type Closed = DurationStatsEntry;
+
+    fn close(self) -> Self::Closed {
+        self.lock().close()
+    }
+}
+
+impl CloseValue for DurationStats {
+    type Closed = DurationStatsEntry;
+
+    fn close(self) -> Self::Closed {
+        (&self).close()
+    }
+}
+
+/// A guard that records the time it was held in a [`DurationStats`]
+///
+/// Returned by [`DurationStats::time_scope`]. The time is recorded when the guard is dropped, or
+/// when [`DurationStatsSlice::stop`] is called.
+#[must_use = "the time slice ends when the guard is dropped"]
+#[derive(Debug)]
+pub struct DurationStatsSlice<'a> {
+    start: Option<Instant>,
+    stats: &'a DurationStats,
+}
+
+impl DurationStatsSlice<'_> {
+    /// End the slice, recording its duration, and return that duration
+    pub fn stop(mut self) -> Duration {
+        self.finish()
+    }
+
+    /// End the slice without recording its duration
+    pub fn discard(mut self) {
+        self.start = None;
+    }
+
+    fn finish(&mut self) -> Duration {
+        match self.start.take() {
+            Some(start) => {
+                let elapsed = start.elapsed();
+                self.stats.record(elapsed);
+                elapsed
+            }
+            None => Duration::ZERO,
+        }
+    }
+}
+
+impl Drop for DurationStatsSlice<'_> {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+/// The closed value of a [`DurationStats`]
+#[derive(Debug, Clone, Copy)]
+pub struct DurationStatsEntry {
+    count: u64,
+    sum: Option<Duration>,
+    min: Option<Duration>,
+    max: Option<Duration>,
+    p50: Option<Duration>,
+    p99: Option<Duration>,
+}
+
+crate::names::inflected_name!(CountName, "count", "Count", "count", "count");
+crate::names::inflected_name!(SumName, "sum", "Sum", "sum", "sum");
+crate::names::inflected_name!(MinName, "min", "Min", "min", "min");
+crate::names::inflected_name!(MaxName, "max", "Max", "max", "max");
+crate::names::inflected_name!(P50Name, "p50", "P50", "p50", "p50");
+crate::names::inflected_name!(P99Name, "p99", "P99", "p99", "p99");
+
+impl<NS: NameStyle> InflectableEntry<NS> for DurationStatsEntry {
+    fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+        writer.value(CountName::value::<NS>(), &self.count);
+        writer.value(SumName::value::<NS>(), &self.sum);
+        writer.value(MinName::value::<NS>(), &self.min);
+        writer.value(MaxName::value::<NS>(), &self.max);
+        writer.value(P50Name::value::<NS>(), &self.p50);
+        writer.value(P99Name::value::<NS>(), &self.p99);
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::time::{Duration, UNIX_EPOCH};
@@ -858,7 +1730,7 @@ mod test {
     use metrique_core::CloseValue;
     use metrique_timesource::{TimeSource, set_time_source};
 
-    use crate::timers::{Stopwatch, Timer};
+    use crate::timers::{DurationCounter, DurationStats, LapTimer, OutcomeTimer, Stopwatch, Timer};
 
     #[tokio::test(start_paused = true)]
     async fn timer_stop_is_idempotent() {
@@ -873,6 +1745,31 @@ mod test {
         assert_eq!(first_stop, second_stop);
     }
 
+    #[tokio::test(start_paused = true)]
+    async fn timer_excludes_paused_time() {
+        let _ts = set_time_source(TimeSource::tokio(UNIX_EPOCH));
+        let mut timer = Timer::start_now();
+        tokio::time::advance(Duration::from_secs(1)).await;
+        timer.pause();
+        assert!(timer.is_paused());
+        tokio::time::advance(Duration::from_secs(10)).await;
+        // pausing again doesn't move the pause start
+        timer.pause();
+        assert_eq!((&timer).close(), Duration::from_secs(1));
+        tokio::time::advance(Duration::from_secs(10)).await;
+        timer.resume();
+        assert!(!timer.is_paused());
+        tokio::time::advance(Duration::from_secs(2)).await;
+        assert_eq!((&timer).close(), Duration::from_secs(3));
+
+        timer.pause();
+        tokio::time::advance(Duration::from_secs(5)).await;
+        assert_eq!(timer.stop(), Duration::from_secs(3));
+        timer.resume();
+        tokio::time::advance(Duration::from_secs(5)).await;
+        assert_eq!(timer.stop(), Duration::from_secs(3));
+    }
+
     #[tokio::test(start_paused = true)]
     async fn stopwatch_can_start_multiple_times() {
         let _ts = set_time_source(TimeSource::tokio(UNIX_EPOCH));
@@ -1006,4 +1903,116 @@ mod test {
         guard.discard();
         assert_eq!(stopwatch.duration, Some(Duration::from_secs(1)));
     }
+
+    #[tokio::test(start_paused = true)]
+    async fn lap_timer_records_and_accumulates_laps() {
+        let _ts = set_time_source(TimeSource::tokio(UNIX_EPOCH));
+        let mut laps = LapTimer::start_now();
+        tokio::time::advance(Duration::from_secs(1)).await;
+        assert_eq!(laps.lap("parse"), Duration::from_secs(1));
+        for _ in 0..2 {
+            tokio::time::advance(Duration::from_secs(2)).await;
+            laps.lap("query");
+            tokio::time::advance(Duration::from_secs(5)).await;
+            laps.skip();
+        }
+        assert_eq!(
+            laps.laps().collect::<Vec<_>>(),
+            [
+                ("parse", Duration::from_secs(1)),
+                ("query", Duration::from_secs(4))
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn duration_counter_accumulates_time_slices() {
+        let _ts = set_time_source(TimeSource::tokio(UNIX_EPOCH));
+        let counter = DurationCounter::new();
+        let slice = counter.time_scope();
+        let nested = counter.time_scope();
+        tokio::time::advance(Duration::from_secs(1)).await;
+        drop(slice);
+        assert_eq!(counter.total(), Duration::from_secs(1));
+        nested.discard();
+        assert_eq!(counter.total(), Duration::from_secs(1));
+
+        let slice = counter.time_scope();
+        tokio::time::advance(Duration::from_secs(2)).await;
+        assert_eq!(slice.stop(), Duration::from_secs(2));
+        counter.add(Duration::from_millis(500));
+        assert_eq!(counter.close(), Duration::from_millis(3500));
+    }
+
+    #[test]
+    fn duration_buckets_are_within_a_sixteenth() {
+        for nanos in (0..10_000).chain([1 << 20, 123_456_789, u64::MAX / 3, u64::MAX]) {
+            let midpoint = super::duration_bucket_midpoint(super::duration_bucket(nanos));
+            assert!(
+                midpoint.abs_diff(nanos) <= nanos / 16,
+                "{nanos} => {midpoint}"
+            );
+        }
+        assert!(super::duration_bucket(u64::MAX) < u16::MAX);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn duration_stats_close_into_statistics() {
+        let _ts = set_time_source(TimeSource::tokio(UNIX_EPOCH));
+        let stats = DurationStats::new();
+        let empty = (&stats).close();
+        assert_eq!(empty.count, 0);
+        assert!(empty.sum.is_none() && empty.p50.is_none());
+
+        for millis in 1..=100 {
+            stats.record(Duration::from_millis(millis));
+        }
+        let slice = stats.time_scope();
+        tokio::time::advance(Duration::from_secs(1)).await;
+        assert_eq!(slice.stop(), Duration::from_secs(1));
+        stats.time_scope().discard();
+        assert_eq!(stats.count(), 101);
+
+        let closed = stats.close();
+        assert_eq!(closed.count, 101);
+        assert_eq!(closed.sum, Some(Duration::from_millis(5050 + 1000)));
+        assert_eq!(closed.min, Some(Duration::from_millis(1)));
+        assert_eq!(closed.max, Some(Duration::from_secs(1)));
+        let p50 = closed.p50.unwrap().as_secs_f64() * 1000.0;
+        assert!((48.0..=54.0).contains(&p50), "{p50}");
+        let p99 = closed.p99.unwrap().as_secs_f64() * 1000.0;
+        assert!((95.0..=106.0).contains(&p99), "{p99}");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn outcome_timer_records_the_final_outcome() {
+        let _ts = set_time_source(TimeSource::tokio(UNIX_EPOCH));
+        let mut recorded = vec![];
+
+        let mut timer = OutcomeTimer::start_now("error", |outcome, elapsed| {
+            recorded.push((outcome, elapsed))
+        });
+        tokio::time::advance(Duration::from_secs(1)).await;
+        timer.set_outcome("throttle");
+        drop(timer);
+
+        let timer = OutcomeTimer::start_now("error", |outcome, elapsed| {
+            recorded.push((outcome, elapsed))
+        });
+        tokio::time::advance(Duration::from_secs(2)).await;
+        assert_eq!(timer.finish("success"), Duration::from_secs(2));
+
+        let timer = OutcomeTimer::start_now("error", |outcome, elapsed| {
+            recorded.push((outcome, elapsed))
+        });
+        timer.discard();
+
+        assert_eq!(
+            recorded,
+            [
+                ("throttle", Duration::from_secs(1)),
+                ("success", Duration::from_secs(2))
+            ]
+        );
+    }
 }
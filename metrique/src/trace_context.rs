@@ -0,0 +1,277 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Trace and span ids, written as properties of entries to pivot from metrics to traces.
+//!
+//! Entries that carry the id of the trace they were recorded in can be joined with the trace,
+//! for example in CloudWatch Logs Insights or the X-Ray console. The ids are written as the
+//! [`TRACE_ID`] and [`SPAN_ID`] properties.
+//!
+//! With the `xray` or `tracing-opentelemetry` feature, every [`RootEntry`] captures the
+//! [current] trace context when it is created, which is when the entry is closed and
+//! appended:
+//!
+//! - `xray`: the X-Ray trace header of the current AWS Lambda invocation. Set it at the start of
+//!   every invocation with [`set_xray_trace_header`], from the `xray_trace_id` of the invocation
+//!   context, or with [`load_xray_trace_env`], from the `_X_AMZN_TRACE_ID` environment variable.
+//!   The header is parsed once per invocation rather than for every entry.
+//! - `tracing-opentelemetry`: the OpenTelemetry trace and span ids of the current `tracing` span,
+//!   when the subscriber has a [`tracing_opentelemetry`] layer. The ids of `tracing` itself are
+//!   process-local and reused, so they are never written.
+//!
+//! Services that receive the trace context in a request header can parse it with
+//! [`TraceContext::from_traceparent`] (W3C trace context) or
+//! [`TraceContext::from_xray_header`], and add it to every entry of the request with
+//! [`scoped_fields`](crate::scoped_fields):
+//!
+//! ```rust
+//! use metrique::scoped_fields;
+//! use metrique::trace_context::TraceContext;
+//!
+//! # async fn handle_request() {}
+//! async fn serve(traceparent_header: Option<&str>) {
+//!     match traceparent_header.and_then(TraceContext::from_traceparent) {
+//!         Some(context) => {
+//!             scoped_fields::scope(context.to_scoped_fields(), handle_request()).await
+//!         }
+//!         None => handle_request().await,
+//!     }
+//! }
+//! ```
+//!
+//! Don't do both for the same entries, or the properties are written twice.
+//!
+//! [`RootEntry`]: crate::RootEntry
+
+#[cfg(feature = "xray")]
+use std::sync::{PoisonError, RwLock};
+
+use metrique_writer_core::{Entry, EntryWriter};
+
+use crate::scoped_fields::ScopedFields;
+
+/// The name of the trace id property
+pub const TRACE_ID: &str = "TraceId";
+/// The name of the span id property
+pub const SPAN_ID: &str = "SpanId";
+
+/// The environment variable holding the X-Ray trace header of the current AWS Lambda invocation
+#[cfg(feature = "xray")]
+const XRAY_TRACE_ENV: &str = "_X_AMZN_TRACE_ID";
+
+/// The trace context of the current AWS Lambda invocation, see [`set_xray_trace_header`]
+#[cfg(feature = "xray")]
+static XRAY_CONTEXT: RwLock<Option<TraceContext>> = RwLock::new(None);
+
+/// The ids of a trace and of the current span in it, see the [module docs](self)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TraceContext {
+    /// The id of the trace
+    pub trace_id: Option<String>,
+    /// The id of the current span, or of the X-Ray segment
+    pub span_id: Option<String>,
+}
+
+fn is_hex(s: &str, len: usize) -> bool {
+    s.len() == len && s.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+impl TraceContext {
+    /// Parse a W3C [`traceparent`](https://www.w3.org/TR/trace-context/#traceparent-header)
+    /// header, like `00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01`.
+    ///
+    /// Returns `None` if the header is malformed or has an all-zero id.
+    pub fn from_traceparent(header: &str) -> Option<Self> {
+        let mut parts = header.trim().split('-');
+        let (version, trace_id, span_id, flags) =
+            (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+        // later versions may add fields, version 00 has exactly 4
+        if !is_hex(version, 2) || version == "ff" || (version == "00" && parts.next().is_some()) {
+            return None;
+        }
+        if !is_hex(trace_id, 32) || !is_hex(span_id, 16) || !is_hex(flags, 2) {
+            return None;
+        }
+        if trace_id.bytes().all(|b| b == b'0') || span_id.bytes().all(|b| b == b'0') {
+            return None;
+        }
+        Some(Self {
+            trace_id: Some(trace_id.to_ascii_lowercase()),
+            span_id: Some(span_id.to_ascii_lowercase()),
+        })
+    }
+
+    /// Parse an X-Ray trace header, like
+    /// `Root=1-5759e988-bd862e3fe1be46a994272793;Parent=53995c3f42cd8ad8;Sampled=1`.
+    ///
+    /// Returns `None` if the header has no `Root`.
+    pub fn from_xray_header(header: &str) -> Option<Self> {
+        let mut context = Self::default();
+        for part in header.split(';') {
+            match part.trim().split_once('=') {
+                Some(("Root", root)) if !root.is_empty() => context.trace_id = Some(root.into()),
+                Some(("Parent", parent)) if !parent.is_empty() => {
+                    context.span_id = Some(parent.into())
+                }
+                _ => {}
+            }
+        }
+        context.trace_id.is_some().then_some(context)
+    }
+
+    /// The ids as [`ScopedFields`], to add them to the entries of a scope
+    pub fn to_scoped_fields(&self) -> ScopedFields {
+        let mut fields = ScopedFields::new();
+        if let Some(trace_id) = &self.trace_id {
+            fields = fields.with(TRACE_ID, trace_id.clone());
+        }
+        if let Some(span_id) = &self.span_id {
+            fields = fields.with(SPAN_ID, span_id.clone());
+        }
+        fields
+    }
+}
+
+impl Entry for TraceContext {
+    fn write<'a>(&'a self, writer: &mut impl EntryWriter<'a>) {
+        if let Some(trace_id) = &self.trace_id {
+            writer.value(TRACE_ID, trace_id.as_str());
+        }
+        if let Some(span_id) = &self.span_id {
+            writer.value(SPAN_ID, span_id.as_str());
+        }
+    }
+}
+
+/// Set the X-Ray trace header of the current AWS Lambda invocation, which every
+/// [`RootEntry`](crate::RootEntry) created from now on captures. `None`, or a header without a
+/// `Root`, clears it.
+///
+/// Call this at the start of every invocation, for example with the `xray_trace_id` of the
+/// invocation context. Since a Lambda execution environment handles one invocation at a time,
+/// the trace context is process-wide.
+#[cfg(feature = "xray")]
+pub fn set_xray_trace_header(header: Option<&str>) {
+    let context = header.and_then(TraceContext::from_xray_header);
+    *XRAY_CONTEXT.write().unwrap_or_else(PoisonError::into_inner) = context;
+}
+
+/// Set the trace context of the current AWS Lambda invocation from the `_X_AMZN_TRACE_ID`
+/// environment variable, see [`set_xray_trace_header`].
+#[cfg(feature = "xray")]
+pub fn load_xray_trace_env() {
+    set_xray_trace_header(std::env::var(XRAY_TRACE_ENV).ok().as_deref());
+}
+
+/// The trace context captured by every [`RootEntry`](crate::RootEntry), from the sources
+/// enabled by the `xray` and `tracing-opentelemetry` features.
+///
+/// The X-Ray trace header takes precedence over the current `tracing` span. Returns `None` if
+/// neither feature is enabled, or there is no trace context.
+pub fn current() -> Option<TraceContext> {
+    #[cfg(feature = "xray")]
+    if let Some(context) = XRAY_CONTEXT
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+    {
+        return Some(context);
+    }
+    #[cfg(feature = "tracing-opentelemetry")]
+    if let Some(context) = current_opentelemetry() {
+        return Some(context);
+    }
+    None
+}
+
+/// The OpenTelemetry span context of the current `tracing` span, if it is sampled or recording
+/// into a [`tracing_opentelemetry`] layer
+#[cfg(feature = "tracing-opentelemetry")]
+fn current_opentelemetry() -> Option<TraceContext> {
+    use opentelemetry::trace::TraceContextExt;
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+    let context = tracing::Span::current().context();
+    let span = context.span();
+    let span_context = span.span_context();
+    span_context.is_valid().then(|| TraceContext {
+        trace_id: Some(span_context.trace_id().to_string()),
+        span_id: Some(span_context.span_id().to_string()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_traceparent() {
+        let context = TraceContext::from_traceparent(
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+        )
+        .unwrap();
+        assert_eq!(
+            context.trace_id.as_deref(),
+            Some("4bf92f3577b34da6a3ce929d0e0e4736")
+        );
+        assert_eq!(context.span_id.as_deref(), Some("00f067aa0ba902b7"));
+
+        for invalid in [
+            "",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e473-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e473g-00f067aa0ba902b7-01",
+        ] {
+            assert_eq!(TraceContext::from_traceparent(invalid), None, "{invalid}");
+        }
+        // future versions may have more fields
+        assert!(
+            TraceContext::from_traceparent(
+                "01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra"
+            )
+            .is_some()
+        );
+    }
+
+    #[test]
+    fn parses_xray_header() {
+        let context = TraceContext::from_xray_header(
+            "Root=1-5759e988-bd862e3fe1be46a994272793;Parent=53995c3f42cd8ad8;Sampled=1",
+        )
+        .unwrap();
+        assert_eq!(
+            context.trace_id.as_deref(),
+            Some("1-5759e988-bd862e3fe1be46a994272793")
+        );
+        assert_eq!(context.span_id.as_deref(), Some("53995c3f42cd8ad8"));
+
+        let context =
+            TraceContext::from_xray_header("Root=1-5759e988-bd862e3fe1be46a994272793").unwrap();
+        assert_eq!(context.span_id, None);
+        assert_eq!(
+            TraceContext::from_xray_header("Parent=53995c3f42cd8ad8"),
+            None
+        );
+    }
+
+    #[cfg(feature = "xray")]
+    #[test]
+    fn captures_the_xray_trace_header_of_the_invocation() {
+        set_xray_trace_header(Some(
+            "Root=1-5759e988-bd862e3fe1be46a994272793;Parent=53995c3f42cd8ad8;Sampled=1",
+        ));
+        let context = current().unwrap();
+        assert_eq!(
+            context.trace_id.as_deref(),
+            Some("1-5759e988-bd862e3fe1be46a994272793")
+        );
+        assert_eq!(context.span_id.as_deref(), Some("53995c3f42cd8ad8"));
+
+        set_xray_trace_header(None);
+        assert_eq!(current(), None);
+    }
+}
@@ -0,0 +1,100 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Support for the `#[metrics(verbose)]` field attribute.
+//!
+//! Fields marked `#[metrics(verbose)]` are only emitted when verbose metrics are enabled. This keeps
+//! entries small in steady state, while still allowing detailed diagnostics to be turned on when
+//! investigating an issue.
+//!
+//! Whether verbose metrics are enabled is checked when the entry is closed. It is controlled by
+//! [`set_enabled`], or, if that was never called, by the [`ENV_VAR`] environment variable
+//! (`METRIQUE_VERBOSE=1` or `METRIQUE_VERBOSE=true`). Verbose metrics are disabled by default.
+//!
+//! ```rust
+//! use metrique::unit_of_work::metrics;
+//!
+//! #[metrics(rename_all = "PascalCase")]
+//! struct RequestMetrics {
+//!     operation: &'static str,
+//!     #[metrics(verbose)]
+//!     cache_lookups: u64,
+//! }
+//!
+//! let metrics = || RequestMetrics {
+//!     operation: "GetItem",
+//!     cache_lookups: 3,
+//! };
+//!
+//! metrique::verbose::set_enabled(false);
+//! let entry = metrique::test_util::test_metric(metrics());
+//! assert!(!entry.metrics.contains_key("CacheLookups"));
+//!
+//! metrique::verbose::set_enabled(true);
+//! let entry = metrique::test_util::test_metric(metrics());
+//! assert_eq!(entry.metrics["CacheLookups"], 3);
+//! ```
+//!
+//! The flag is global to the process. When a verbose field is disabled, it is dropped without
+//! being closed.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// The environment variable that enables verbose metrics, unless [`set_enabled`] was called.
+///
+/// It is read once, the first time [`is_enabled`] is called.
+pub const ENV_VAR: &str = "METRIQUE_VERBOSE";
+
+const UNSET: u8 = 0;
+const DISABLED: u8 = 1;
+const ENABLED: u8 = 2;
+
+static STATE: AtomicU8 = AtomicU8::new(UNSET);
+
+/// Returns true if fields marked `#[metrics(verbose)]` should be emitted.
+pub fn is_enabled() -> bool {
+    match STATE.load(Ordering::Relaxed) {
+        ENABLED => true,
+        DISABLED => false,
+        _ => {
+            let from_env = if enabled_by_env() { ENABLED } else { DISABLED };
+            // don't overwrite a concurrent `set_enabled`
+            match STATE.compare_exchange(UNSET, from_env, Ordering::Relaxed, Ordering::Relaxed) {
+                Ok(_) => from_env == ENABLED,
+                Err(current) => current == ENABLED,
+            }
+        }
+    }
+}
+
+/// Enable or disable verbose metrics, overriding [`ENV_VAR`].
+///
+/// This affects entries closed after the call, so it can be toggled at runtime (for example, from
+/// an admin endpoint) without restarting the process.
+pub fn set_enabled(enabled: bool) {
+    STATE.store(if enabled { ENABLED } else { DISABLED }, Ordering::Relaxed);
+}
+
+fn enabled_by_env() -> bool {
+    std::env::var(ENV_VAR).is_ok_and(|value| parse_flag(&value))
+}
+
+fn parse_flag(value: &str) -> bool {
+    let value = value.trim();
+    value == "1" || value.eq_ignore_ascii_case("true")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_flag;
+
+    #[test]
+    fn parses_env_flag() {
+        for value in ["1", "true", "TRUE", " true\n"] {
+            assert!(parse_flag(value), "{value:?}");
+        }
+        for value in ["", "0", "false", "yes", "verbose"] {
+            assert!(!parse_flag(value), "{value:?}");
+        }
+    }
+}
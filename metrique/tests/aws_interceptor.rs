@@ -0,0 +1,120 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use aws_smithy_runtime_api::client::interceptors::Intercept;
+use aws_smithy_runtime_api::client::interceptors::context::{
+    Error, Input, InterceptorContext, Output,
+};
+use aws_smithy_runtime_api::client::orchestrator::{HttpResponse, OrchestratorError};
+use aws_smithy_runtime_api::client::runtime_components::RuntimeComponentsBuilder;
+use aws_smithy_runtime_api::http::StatusCode;
+use aws_smithy_types::body::SdkBody;
+use aws_smithy_types::config_bag::ConfigBag;
+
+use metrique::aws::{MetricsInterceptor, SdkCallMetrics};
+use metrique::test_util::{Inspector, TestEntrySink, test_entry_sink};
+use metrique::unit_of_work::metrics;
+
+#[metrics(rename_all = "PascalCase")]
+struct RequestMetrics {
+    #[metrics(flatten, prefix = "Ddb")]
+    ddb: SdkCallMetrics,
+}
+
+fn response(status: u16) -> HttpResponse {
+    HttpResponse::new(StatusCode::try_from(status).unwrap(), SdkBody::empty())
+}
+
+fn interceptor() -> (Inspector, MetricsInterceptor) {
+    let TestEntrySink { inspector, sink } = test_entry_sink();
+    let handle = RequestMetrics {
+        ddb: SdkCallMetrics::new(),
+    }
+    .append_on_drop(sink)
+    .handle();
+    let interceptor = MetricsInterceptor::new(handle.project(|metrics| &metrics.ddb));
+    (inspector, interceptor)
+}
+
+#[test]
+fn records_attempts_throttles_and_failures() {
+    let (inspector, interceptor) = interceptor();
+
+    let components = RuntimeComponentsBuilder::for_tests().build().unwrap();
+    let mut cfg = ConfigBag::base();
+    let mut context = InterceptorContext::new(Input::doesnt_matter());
+    interceptor
+        .read_before_execution(&(&context).into(), &mut cfg)
+        .unwrap();
+    interceptor
+        .read_before_serialization(&(&context).into(), &components, &mut cfg)
+        .unwrap();
+    interceptor
+        .read_after_serialization(&(&context).into(), &components, &mut cfg)
+        .unwrap();
+
+    // a throttled attempt, then a failed attempt that is not retried
+    for status in [429, 500] {
+        interceptor
+            .read_before_attempt(&(&context).into(), &components, &mut cfg)
+            .unwrap();
+        interceptor
+            .read_before_transmit(&(&context).into(), &components, &mut cfg)
+            .unwrap();
+        context.set_response(response(status));
+        interceptor
+            .read_after_transmit(&(&context).into(), &components, &mut cfg)
+            .unwrap();
+        context.set_output_or_error(Err(OrchestratorError::operation(Error::doesnt_matter())));
+        interceptor
+            .read_after_deserialization(&(&context).into(), &components, &mut cfg)
+            .unwrap();
+        interceptor
+            .read_after_attempt(&(&context).into(), &components, &mut cfg)
+            .unwrap();
+    }
+    interceptor
+        .read_after_execution(&(&context).into(), &components, &mut cfg)
+        .unwrap();
+
+    assert!(inspector.entries().is_empty());
+    drop(interceptor);
+
+    let entry = inspector.get(0);
+    assert_eq!(entry.metrics["DdbCalls"], 1);
+    assert_eq!(entry.metrics["DdbAttempts"], 2);
+    assert_eq!(entry.metrics["DdbThrottles"], 1);
+    assert_eq!(entry.metrics["DdbFailures"], 1);
+    assert!(entry.metrics.contains_key("DdbTransmitTime"));
+    assert!(entry.metrics.contains_key("DdbLatency"));
+}
+
+#[test]
+fn successful_calls_are_not_failures() {
+    let (inspector, interceptor) = interceptor();
+
+    let components = RuntimeComponentsBuilder::for_tests().build().unwrap();
+    let mut cfg = ConfigBag::base();
+    let mut context = InterceptorContext::new(Input::doesnt_matter());
+    interceptor
+        .read_before_execution(&(&context).into(), &mut cfg)
+        .unwrap();
+    interceptor
+        .read_before_attempt(&(&context).into(), &components, &mut cfg)
+        .unwrap();
+    context.set_response(response(200));
+    context.set_output_or_error(Ok(Output::doesnt_matter()));
+    interceptor
+        .read_after_attempt(&(&context).into(), &components, &mut cfg)
+        .unwrap();
+    interceptor
+        .read_after_execution(&(&context).into(), &components, &mut cfg)
+        .unwrap();
+    drop(interceptor);
+
+    let entry = inspector.get(0);
+    assert_eq!(entry.metrics["DdbCalls"], 1);
+    assert_eq!(entry.metrics["DdbAttempts"], 1);
+    assert_eq!(entry.metrics["DdbThrottles"], 0);
+    assert_eq!(entry.metrics["DdbFailures"], 0);
+}
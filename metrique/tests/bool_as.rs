@@ -0,0 +1,77 @@
+use std::sync::Arc;
+
+use metrique::unit_of_work::metrics;
+use metrique::writer::test_util::test_metric;
+
+#[metrics(rename_all = "PascalCase")]
+struct FieldLevel {
+    #[metrics(bool_as = "property")]
+    cache_hit: bool,
+    #[metrics(bool_as = "property")]
+    retried: Option<bool>,
+    #[metrics(bool_as = "property")]
+    throttled: Option<bool>,
+    success: bool,
+}
+
+#[metrics(bool_as = "property")]
+struct ContainerLevel {
+    cache_hit: bool,
+    shared: Arc<bool>,
+    #[metrics(bool_as = "metric")]
+    success: bool,
+    count: usize,
+    #[metrics(flatten)]
+    nested: Nested,
+}
+
+#[metrics(subfield)]
+struct Nested {
+    nested_flag: bool,
+}
+
+#[metrics(value)]
+struct Flag(#[metrics(bool_as = "property")] bool);
+
+#[metrics]
+struct WithValue {
+    flag: Flag,
+}
+
+#[test]
+fn field_level_bool_as_property() {
+    let entry = test_metric(FieldLevel {
+        cache_hit: true,
+        retried: Some(false),
+        throttled: None,
+        success: true,
+    });
+    assert_eq!(entry.values["CacheHit"], "true");
+    assert_eq!(entry.values["Retried"], "false");
+    assert!(!entry.values.contains_key("Throttled"));
+    assert!(!entry.metrics.contains_key("CacheHit"));
+    assert_eq!(entry.metrics["Success"], 1);
+}
+
+#[test]
+fn container_level_bool_as_property() {
+    let entry = test_metric(ContainerLevel {
+        cache_hit: false,
+        shared: Arc::new(true),
+        success: true,
+        count: 3,
+        nested: Nested { nested_flag: true },
+    });
+    assert_eq!(entry.values["cache_hit"], "false");
+    assert_eq!(entry.values["shared"], "true");
+    assert_eq!(entry.metrics["success"], 1);
+    assert_eq!(entry.metrics["count"], 3);
+    // `bool_as` is not inherited by flattened subfields
+    assert_eq!(entry.metrics["nested_flag"], 1);
+}
+
+#[test]
+fn value_bool_as_property() {
+    let entry = test_metric(WithValue { flag: Flag(true) });
+    assert_eq!(entry.values["flag"], "true");
+}
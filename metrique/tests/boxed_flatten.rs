@@ -0,0 +1,68 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use metrique::unit_of_work::metrics;
+use metrique::writer::test_util::test_metric;
+
+#[metrics(rename_all = "PascalCase")]
+struct RequestMetrics {
+    operation: &'static str,
+    #[metrics(flatten)]
+    downstream: Box<DownstreamMetrics>,
+    #[metrics(flatten, prefix = "Cache")]
+    cache: Option<Box<CacheMetrics>>,
+}
+
+#[metrics(subfield_owned)]
+struct DownstreamMetrics {
+    calls: usize,
+    #[metrics(flatten)]
+    retries: Box<RetryMetrics>,
+}
+
+#[metrics(subfield)]
+struct RetryMetrics {
+    retries: usize,
+}
+
+#[metrics(subfield)]
+struct CacheMetrics {
+    hits: usize,
+}
+
+#[test]
+fn boxed_subfields_are_flattened() {
+    let entry = test_metric(RequestMetrics {
+        operation: "Get",
+        downstream: Box::new(DownstreamMetrics {
+            calls: 2,
+            retries: Box::new(RetryMetrics { retries: 1 }),
+        }),
+        cache: Some(Box::new(CacheMetrics { hits: 3 })),
+    });
+    assert_eq!(entry.values["Operation"], "Get");
+    assert_eq!(entry.metrics["Calls"], 2);
+    assert_eq!(entry.metrics["Retries"], 1);
+    assert_eq!(entry.metrics["CacheHits"], 3);
+}
+
+#[test]
+fn boxed_subfields_are_small() {
+    #[metrics(subfield)]
+    struct Large {
+        count: usize,
+        #[metrics(ignore)]
+        _buffer: [u64; 64],
+    }
+
+    #[metrics]
+    struct Parent {
+        #[metrics(flatten)]
+        large: Box<Large>,
+    }
+
+    assert_eq!(
+        std::mem::size_of::<Parent>(),
+        std::mem::size_of::<Box<Large>>()
+    );
+}
@@ -2,7 +2,7 @@ use rstest::rstest;
 use std::fs;
 use std::path::PathBuf;
 
-const MSRV: &'static str = "1.89.0";
+const MSRV: &str = "1.89.0";
 
 // return just major and minor versions of msrv
 fn msrv_major_minor() -> String {
@@ -105,7 +105,7 @@ fn test_cargo_toml_format(
     }
 
     // Check that each package has docs.rs metadata
-    if let Some(package) = package {
+    if let Some(_package) = package {
         let metadata = toml
             .get("package")
             .and_then(|p| p.get("metadata"))
@@ -0,0 +1,98 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::time::Duration;
+
+use metrique::test_util::test_metric;
+use metrique::timers::Timer;
+use metrique::unit::Millisecond;
+use metrique::unit_of_work::metrics;
+
+#[metrics(rename_all = "PascalCase")]
+struct RequestMetrics {
+    #[metrics(clamp(min = 0.0, max = 1000.0))]
+    queue_depth: f64,
+    #[metrics(clamp(max = 10))]
+    retries: Option<usize>,
+    #[metrics(clamp(max = Duration::from_secs(1), out_of_range = "drop"), unit = Millisecond)]
+    latency: Duration,
+    #[metrics(clamp(max = Duration::from_secs(1)), unit = Millisecond)]
+    timer: Timer,
+}
+
+#[metrics(subfield)]
+struct Sizes {
+    #[metrics(clamp(max = 100, out_of_range = "drop"))]
+    payload_size: u64,
+}
+
+#[metrics]
+enum Operation {
+    Upload {
+        #[metrics(clamp(max = 100))]
+        payload_size: u64,
+    },
+}
+
+#[test]
+fn clamp_replaces_out_of_range_values() {
+    let entry = test_metric(RequestMetrics {
+        queue_depth: -1.0,
+        retries: Some(usize::MAX),
+        latency: Duration::from_millis(5),
+        timer: Timer::start_now(),
+    });
+    assert_eq!(entry.metrics["QueueDepth"], 0);
+    assert_eq!(entry.metrics["Retries"], 10);
+    assert_eq!(entry.metrics["Latency"], 5);
+    assert!(entry.metrics["Timer"].as_u64() <= 1000);
+}
+
+#[test]
+fn clamp_keeps_in_range_values() {
+    let entry = test_metric(RequestMetrics {
+        queue_depth: 1000.0,
+        retries: None,
+        latency: Duration::from_secs(1),
+        timer: Timer::start_now(),
+    });
+    assert_eq!(entry.metrics["QueueDepth"], 1000);
+    assert!(!entry.metrics.contains_key("Retries"));
+    assert_eq!(entry.metrics["Latency"], 1000);
+}
+
+#[test]
+fn clamp_drops_out_of_range_values() {
+    let entry = test_metric(RequestMetrics {
+        queue_depth: 0.0,
+        retries: None,
+        latency: Duration::MAX,
+        timer: Timer::start_now(),
+    });
+    assert!(!entry.metrics.contains_key("Latency"));
+
+    #[metrics]
+    struct Nested {
+        #[metrics(flatten)]
+        sizes: Sizes,
+    }
+    let entry = test_metric(Nested {
+        sizes: Sizes { payload_size: 101 },
+    });
+    assert!(!entry.metrics.contains_key("payload_size"));
+}
+
+#[test]
+fn clamp_in_enum_variant() {
+    #[metrics]
+    struct Root {
+        #[metrics(flatten)]
+        operation: Operation,
+    }
+    let entry = test_metric(Root {
+        operation: Operation::Upload {
+            payload_size: u64::MAX,
+        },
+    });
+    assert_eq!(entry.metrics["payload_size"], 100);
+}
@@ -0,0 +1,80 @@
+use metrique::unit_of_work::metrics;
+use metrique::writer::{Entry, test_util::to_test_entry};
+use metrique::{CloseValue, RootEntry};
+
+#[derive(Entry)]
+struct Request {
+    #[entry(sample_group)]
+    operation: &'static str,
+    latency_ms: u64,
+}
+
+#[derive(Entry)]
+struct Host {
+    #[entry(sample_group)]
+    region: &'static str,
+}
+
+#[derive(Entry)]
+#[entry(rename_all = "PascalCase")]
+struct Retry {
+    attempt_bytes: u64,
+}
+
+#[test]
+fn tuples_write_each_entry() {
+    let entry = (
+        Request {
+            operation: "Get",
+            latency_ms: 5,
+        },
+        Host {
+            region: "us-east-1",
+        },
+    );
+    let sample_group: Vec<_> = entry.sample_group().collect();
+    assert_eq!(
+        sample_group,
+        [
+            ("operation".into(), "Get".into()),
+            ("region".into(), "us-east-1".into())
+        ]
+    );
+
+    let entry = to_test_entry(entry);
+    assert_eq!(entry.values["operation"], "Get");
+    assert_eq!(entry.values["region"], "us-east-1");
+    assert_eq!(entry.metrics["latency_ms"], 5);
+}
+
+#[test]
+fn vecs_and_arrays_write_each_entry() {
+    let entry = to_test_entry(vec![Some(Retry { attempt_bytes: 1 }), None]);
+    assert_eq!(entry.metrics["AttemptBytes"], 1);
+
+    let entry = to_test_entry([Host {
+        region: "eu-west-1",
+    }]);
+    assert_eq!(entry.values["region"], "eu-west-1");
+}
+
+#[metrics(subfield)]
+struct CacheMetrics {
+    hits: usize,
+}
+
+#[metrics(subfield)]
+struct BackendMetrics {
+    calls: usize,
+}
+
+#[test]
+fn tuples_of_closed_metrics() {
+    let closed = (
+        CacheMetrics { hits: 2 }.close(),
+        vec![BackendMetrics { calls: 3 }.close()],
+    );
+    let entry = to_test_entry(RootEntry::new(closed));
+    assert_eq!(entry.metrics["hits"], 2);
+    assert_eq!(entry.metrics["calls"], 3);
+}
@@ -0,0 +1,96 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::borrow::Cow;
+
+use metrique::test_util::test_metric;
+use metrique::timers::Timer;
+use metrique::unit_of_work::metrics;
+
+#[metrics(value(string))]
+enum Tier {
+    Free,
+    Paid,
+}
+
+#[metrics(subfield)]
+struct CacheMetrics {
+    #[metrics(default = 10)]
+    capacity: u64,
+    hits: u64,
+}
+
+#[metrics(rename_all = "PascalCase")]
+struct RequestMetrics {
+    #[metrics(default = Tier::Free)]
+    tier: Tier,
+    #[metrics(default = Cow::Borrowed("unknown"))]
+    operation: Cow<'static, str>,
+    #[metrics(default)]
+    retries: u64,
+    errors: u64,
+    latency: Timer,
+    #[metrics(flatten, default = CacheMetrics { capacity: 20, ..Default::default() })]
+    cache: CacheMetrics,
+    #[metrics(flatten, prefix = "fallback_")]
+    fallback_cache: CacheMetrics,
+    #[metrics(ignore, default = 42)]
+    ignored: u32,
+}
+
+#[test]
+fn default_values() {
+    let metrics = RequestMetrics {
+        errors: 1,
+        ..Default::default()
+    };
+    assert_eq!(metrics.ignored, 42);
+
+    let entry = test_metric(metrics);
+    assert_eq!(entry.values["Tier"], "Free");
+    assert_eq!(entry.values["Operation"], "unknown");
+    assert_eq!(entry.metrics["Retries"], 0);
+    assert_eq!(entry.metrics["Errors"], 1);
+    assert!(entry.metrics.contains_key("Latency"));
+    assert_eq!(entry.metrics["Capacity"], 20);
+    assert_eq!(entry.metrics["FallbackCapacity"], 10);
+}
+
+#[test]
+fn default_values_in_subfield() {
+    let cache = CacheMetrics::default();
+    assert_eq!(cache.capacity, 10);
+    assert_eq!(cache.hits, 0);
+
+    let metrics = RequestMetrics {
+        tier: Tier::Paid,
+        ..Default::default()
+    };
+    assert_eq!(metrics.fallback_cache.capacity, 10);
+}
+
+#[metrics(value)]
+struct Attempts(#[metrics(default = 1)] u64);
+
+#[metrics]
+struct Borrowed<'a> {
+    #[metrics(default = "none")]
+    name: &'a str,
+    #[metrics(default = 3)]
+    count: u64,
+    #[metrics(default = Attempts(2))]
+    attempts: Attempts,
+    #[cfg(any())]
+    #[metrics(default = 4)]
+    disabled: u64,
+}
+
+#[test]
+fn default_values_in_tuple_and_borrowed_structs() {
+    assert_eq!(Attempts::default().0, 1);
+
+    let entry = test_metric(Borrowed::default());
+    assert_eq!(entry.values["name"], "none");
+    assert_eq!(entry.metrics["count"], 3);
+    assert_eq!(entry.metrics["attempts"], 2);
+}
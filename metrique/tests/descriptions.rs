@@ -0,0 +1,64 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::time::Duration;
+
+use metrique::unit_of_work::metrics;
+use metrique::{FieldDescriptor, describe_fields};
+
+#[metrics(rename_all = "PascalCase", doc_as_description)]
+struct RequestMetrics {
+    /// The operation being served
+    operation: &'static str,
+    /// Time spent waiting for the backend.
+    ///
+    /// Does not include retries.
+    #[metrics(name = "BackendTime")]
+    backend_latency: Duration,
+    // not a doc comment, so not described
+    retries: usize,
+    #[metrics(flatten, prefix = "cache_")]
+    cache: CacheMetrics,
+    #[metrics(flatten)]
+    undocumented: Undocumented,
+    #[metrics(flatten)]
+    optional: Option<CacheMetrics>,
+}
+
+#[metrics(subfield_owned, doc_as_description)]
+struct CacheMetrics {
+    /// Number of lookups that found the item
+    hits: usize,
+}
+
+#[metrics(subfield_owned)]
+struct Undocumented {
+    /// Not described, since `Undocumented` doesn't have `doc_as_description`
+    ignored: usize,
+}
+
+#[test]
+fn describes_documented_fields_with_final_names() {
+    assert_eq!(
+        describe_fields::<RequestMetrics>(),
+        [
+            FieldDescriptor::new("Operation", "The operation being served"),
+            FieldDescriptor::new(
+                "BackendTime",
+                "Time spent waiting for the backend.\n\nDoes not include retries."
+            ),
+            FieldDescriptor::new("CacheHits", "Number of lookups that found the item"),
+            FieldDescriptor::new("Hits", "Number of lookups that found the item"),
+        ]
+    );
+}
+
+#[test]
+fn no_descriptions_without_doc_as_description() {
+    #[metrics]
+    struct Plain {
+        /// Not described
+        field: usize,
+    }
+    assert!(describe_fields::<Plain>().is_empty());
+}
@@ -100,3 +100,42 @@ async fn test_dimensions_merged_with_global_queue() {
         ]
     );
 }
+
+macro_rules! wide_metrics {
+    (metrics: [$($metric:ident),* $(,)?], properties: [$($property:ident),* $(,)?]) => {
+        // 60 metrics and 60 string properties: only the metrics count towards the EMF limit of 100
+        #[metrics]
+        #[derive(Default)]
+        struct WideMetrics {
+            $($metric: usize,)*
+            $($property: &'static str,)*
+        }
+    };
+}
+
+wide_metrics! {
+    metrics: [
+        metric_0, metric_1, metric_2, metric_3, metric_4, metric_5, metric_6, metric_7, metric_8, metric_9,
+        metric_10, metric_11, metric_12, metric_13, metric_14, metric_15, metric_16, metric_17, metric_18, metric_19,
+        metric_20, metric_21, metric_22, metric_23, metric_24, metric_25, metric_26, metric_27, metric_28, metric_29,
+        metric_30, metric_31, metric_32, metric_33, metric_34, metric_35, metric_36, metric_37, metric_38, metric_39,
+        metric_40, metric_41, metric_42, metric_43, metric_44, metric_45, metric_46, metric_47, metric_48, metric_49,
+        metric_50, metric_51, metric_52, metric_53, metric_54, metric_55, metric_56, metric_57, metric_58, metric_59,
+    ],
+    properties: [
+        property_0, property_1, property_2, property_3, property_4, property_5, property_6, property_7, property_8, property_9,
+        property_10, property_11, property_12, property_13, property_14, property_15, property_16, property_17, property_18, property_19,
+        property_20, property_21, property_22, property_23, property_24, property_25, property_26, property_27, property_28, property_29,
+        property_30, property_31, property_32, property_33, property_34, property_35, property_36, property_37, property_38, property_39,
+        property_40, property_41, property_42, property_43, property_44, property_45, property_46, property_47, property_48, property_49,
+        property_50, property_51, property_52, property_53, property_54, property_55, property_56, property_57, property_58, property_59,
+    ]
+}
+
+#[test]
+fn string_properties_do_not_count_towards_the_metric_limit() {
+    let entry =
+        metrique::writer::test_util::to_test_entry(RootEntry::new(WideMetrics::default().close()));
+    assert_eq!(entry.metrics.len(), 60);
+    assert_eq!(entry.values.len(), 60);
+}
@@ -0,0 +1,44 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+// The switch is process-wide, so everything that flips it is checked in a single test.
+
+use metrique::test_util::{TestEntrySink, test_entry_sink};
+use metrique::unit_of_work::metrics;
+
+#[metrics(rename_all = "PascalCase")]
+struct RequestMetrics {
+    operation: &'static str,
+}
+
+#[test]
+fn disabled_emission_drops_entries() {
+    let TestEntrySink { inspector, sink } = test_entry_sink();
+    assert!(metrique::emission::is_enabled());
+
+    // guards created before the switch is flipped are dropped too
+    let before = RequestMetrics {
+        operation: "Before",
+    }
+    .append_on_drop(sink.clone());
+    metrique::emission::disable();
+    drop(before);
+    drop(
+        RequestMetrics {
+            operation: "Disabled",
+        }
+        .append_on_drop(sink.clone()),
+    );
+    assert!(inspector.entries().is_empty());
+
+    metrique::emission::enable();
+    drop(
+        RequestMetrics {
+            operation: "Enabled",
+        }
+        .append_on_drop(sink.clone()),
+    );
+    let entries = inspector.entries();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].values["Operation"], "Enabled");
+}
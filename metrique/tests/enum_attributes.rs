@@ -1,6 +1,8 @@
 // Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: Apache-2.0
 
+#![allow(clippy::enum_variant_names)]
+
 use metrique::{
     test_util::test_metric,
     unit_of_work::metrics,
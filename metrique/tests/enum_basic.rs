@@ -148,6 +148,151 @@ fn test_tuple_variant_field_prefix() {
     assert_eq!(entry2.metrics["other"], 30);
 }
 
+// Tuple variant with several flattened fields emits the union of their metrics
+#[metrics(subfield)]
+pub struct RequestInfo {
+    #[metrics(sample_group)]
+    operation: &'static str,
+    request_count: u32,
+}
+
+#[metrics(subfield)]
+pub struct CacheMetrics {
+    #[metrics(sample_group)]
+    cache: &'static str,
+    hit: bool,
+}
+
+#[metrics(rename_all = "PascalCase")]
+enum MultiFieldTupleEnum {
+    Cached(
+        #[metrics(flatten)] RequestInfo,
+        #[metrics(flatten, prefix = "cache_")] CacheMetrics,
+        #[metrics(flatten_entry, no_close)] EntryMetrics,
+        #[metrics(ignore)] u64,
+    ),
+    Uncached(#[metrics(flatten)] RequestInfo),
+}
+
+#[test]
+fn test_tuple_variant_multiple_flattened_fields() {
+    let entry = test_metric(MultiFieldTupleEnum::Cached(
+        RequestInfo {
+            operation: "Get",
+            request_count: 1,
+        },
+        CacheMetrics {
+            cache: "local",
+            hit: true,
+        },
+        EntryMetrics {
+            count: 2,
+            name: "entry".to_string(),
+        },
+        3,
+    ));
+
+    assert_eq!(entry.values["Operation"], "Get");
+    assert_eq!(entry.metrics["RequestCount"], 1);
+    assert_eq!(entry.values["CacheCache"], "local");
+    assert_eq!(entry.metrics["CacheHit"], 1);
+    assert_eq!(entry.metrics["count"], 2);
+    assert_eq!(entry.values["name"], "entry");
+    assert_eq!(entry.metrics.len() + entry.values.len(), 6);
+
+    let entry = test_metric(MultiFieldTupleEnum::Uncached(RequestInfo {
+        operation: "Put",
+        request_count: 4,
+    }));
+    assert_eq!(entry.metrics["RequestCount"], 4);
+    assert!(!entry.metrics.contains_key("CacheHit"));
+}
+
+#[test]
+fn test_tuple_variant_multiple_flattened_fields_sample_group() {
+    use metrique::{CloseValue, RootEntry};
+
+    let metric = MultiFieldTupleEnum::Cached(
+        RequestInfo {
+            operation: "Get",
+            request_count: 1,
+        },
+        CacheMetrics {
+            cache: "local",
+            hit: false,
+        },
+        EntryMetrics {
+            count: 0,
+            name: String::new(),
+        },
+        0,
+    );
+    let sample_group: Vec<_> = RootEntry::new(metric.close())
+        .sample_group()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    assert_eq!(
+        sample_group,
+        [
+            ("Operation".to_string(), "Get".to_string()),
+            // like for structs, the field prefix does not apply to sample group names
+            ("Cache".to_string(), "local".to_string()),
+        ]
+    );
+}
+
+// Subfield enums with unit variants, flattened into a struct
+#[metrics(subfield)]
+enum ConnectionState {
+    Idle,
+    Connecting { attempts: u32 },
+    Closed,
+}
+
+#[metrics(subfield, tag(name = "Phase"))]
+enum Phase {
+    Starting,
+    Running,
+}
+
+#[metrics(rename_all = "PascalCase")]
+struct ConnectionMetrics {
+    #[metrics(flatten)]
+    state: ConnectionState,
+    #[metrics(flatten)]
+    phase: Phase,
+    requests: u32,
+}
+
+#[test]
+fn test_flattened_unit_variants() {
+    let entry = test_metric(ConnectionMetrics {
+        state: ConnectionState::Idle,
+        phase: Phase::Starting,
+        requests: 1,
+    });
+    // the unit variant emits nothing, except for the tag
+    assert_eq!(entry.metrics.len(), 1);
+    assert_eq!(entry.metrics["Requests"], 1);
+    assert_eq!(entry.values.len(), 1);
+    assert_eq!(entry.values["Phase"], "Starting");
+
+    let entry = test_metric(ConnectionMetrics {
+        state: ConnectionState::Connecting { attempts: 3 },
+        phase: Phase::Running,
+        requests: 2,
+    });
+    assert_eq!(entry.metrics["Attempts"], 3);
+    assert_eq!(entry.values["Phase"], "Running");
+
+    let entry = test_metric(ConnectionMetrics {
+        state: ConnectionState::Closed,
+        phase: Phase::Running,
+        requests: 3,
+    });
+    assert!(!entry.metrics.contains_key("Attempts"));
+}
+
 // Container prefix + struct variant fields (verify prefix applies)
 #[metrics(prefix = "api_")]
 enum ContainerPrefixStruct {
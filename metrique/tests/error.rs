@@ -0,0 +1,155 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{fmt, sync::Arc};
+
+use metrique::error::AsError;
+use metrique::test_util::test_metric;
+use metrique::unit_of_work::metrics;
+
+#[derive(Debug)]
+enum DownstreamError {
+    Throttled,
+    Timeout,
+}
+
+impl fmt::Display for DownstreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DownstreamError::Throttled => f.write_str("the request was throttled"),
+            DownstreamError::Timeout => f.write_str("the request timed out"),
+        }
+    }
+}
+
+#[metrics(rename_all = "PascalCase")]
+struct RequestMetrics {
+    operation: &'static str,
+    #[metrics(error(message_max_len = 11))]
+    result: Result<usize, DownstreamError>,
+    #[metrics(error(fault), exact_prefix = "Dependency.")]
+    dependency: Option<DownstreamError>,
+}
+
+#[test]
+fn error_field_records_failure_type_and_message() {
+    let entry = test_metric(RequestMetrics {
+        operation: "Get",
+        result: Err(DownstreamError::Throttled),
+        dependency: Some(DownstreamError::Timeout),
+    });
+    assert_eq!(entry.metrics["Failure"], 1);
+    assert_eq!(entry.values["ErrorType"], "DownstreamError");
+    assert_eq!(entry.values["ErrorMessage"], "the request");
+    assert_eq!(entry.metrics["Dependency.Fault"], 1);
+    assert_eq!(entry.values["Dependency.ErrorType"], "DownstreamError");
+    assert!(!entry.values.contains_key("Dependency.ErrorMessage"));
+}
+
+#[test]
+fn error_field_records_zero_without_error() {
+    let entry = test_metric(RequestMetrics {
+        operation: "Get",
+        result: Ok(5),
+        dependency: None,
+    });
+    assert_eq!(entry.metrics["Failure"], 0);
+    assert_eq!(entry.metrics["Dependency.Fault"], 0);
+    assert_eq!(
+        entry.values.keys().collect::<Vec<_>>(),
+        vec!["Operation"],
+        "error properties are only written when there is an error"
+    );
+}
+
+#[metrics(subfield)]
+struct DependencyMetrics {
+    #[metrics(error(message))]
+    error: Option<DownstreamError>,
+}
+
+#[metrics(rename_all = "kebab-case")]
+struct ParentMetrics {
+    #[metrics(flatten, prefix = "dependency-")]
+    dependency: Arc<DependencyMetrics>,
+}
+
+#[test]
+fn error_field_is_inflected_in_subfield() {
+    let entry = test_metric(ParentMetrics {
+        dependency: Arc::new(DependencyMetrics {
+            error: Some(DownstreamError::Throttled),
+        }),
+    });
+    assert_eq!(entry.metrics["dependency-failure"], 1);
+    assert_eq!(entry.values["dependency-error-type"], "DownstreamError");
+    assert_eq!(
+        entry.values["dependency-error-message"],
+        "the request was throttled"
+    );
+}
+
+#[metrics(rename_all = "PascalCase")]
+enum OperationMetrics {
+    Get {
+        #[metrics(error)]
+        result: Result<(), DownstreamError>,
+    },
+}
+
+#[test]
+fn error_field_in_enum_variant() {
+    let entry = test_metric(OperationMetrics::Get {
+        result: Err(DownstreamError::Timeout),
+    });
+    assert_eq!(entry.metrics["Failure"], 1);
+    assert_eq!(entry.values["ErrorType"], "DownstreamError");
+}
+
+#[metrics(rename_all = "PascalCase")]
+struct BoxedErrorMetrics {
+    #[metrics(error)]
+    result: Result<(), Box<dyn std::error::Error + Send + Sync>>,
+}
+
+#[test]
+fn boxed_error_type_is_box() {
+    let entry = test_metric(BoxedErrorMetrics {
+        result: Err("the request was throttled".into()),
+    });
+    assert_eq!(entry.metrics["Failure"], 1);
+    assert_eq!(entry.values["ErrorType"], "Box");
+}
+
+struct DownstreamResult(Result<(), DownstreamError>);
+
+impl AsError for DownstreamResult {
+    type Error = DownstreamError;
+
+    fn as_error(&self) -> Option<&DownstreamError> {
+        self.0.as_ref().err()
+    }
+
+    fn error_type(&self) -> &'static str {
+        match self.0 {
+            Err(DownstreamError::Throttled) => "Throttled",
+            Err(DownstreamError::Timeout) => "Timeout",
+            Ok(()) => "",
+        }
+    }
+}
+
+#[metrics(rename_all = "PascalCase")]
+struct VariantMetrics {
+    #[metrics(error)]
+    result: DownstreamResult,
+}
+
+#[test]
+fn custom_error_type_is_recorded() {
+    let entry = test_metric(VariantMetrics {
+        result: DownstreamResult(Err(DownstreamError::Timeout)),
+    });
+    assert_eq!(entry.metrics["Failure"], 1);
+    assert_eq!(entry.values["ErrorType"], "Timeout");
+}
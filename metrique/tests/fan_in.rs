@@ -0,0 +1,131 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use metrique::test_util::test_metric;
+use metrique::unit::Millisecond;
+use metrique::unit_of_work::metrics;
+use metrique::writer::Observation;
+use metrique::writer::test_util::Metric;
+use metrique::{FanIn, OnParentDrop};
+
+#[metrics(rename_all = "PascalCase")]
+struct RequestMetrics {
+    operation: &'static str,
+    #[metrics(flatten, prefix = "shard_")]
+    shards: FanIn<ShardMetrics>,
+}
+
+#[metrics(subfield)]
+struct ShardMetrics {
+    #[metrics(unit = Millisecond)]
+    latency: u64,
+    region: &'static str,
+    items: Option<usize>,
+}
+
+fn values(metric: &Metric) -> Vec<u64> {
+    (metric.distribution.iter())
+        .map(|observation| match observation {
+            Observation::Unsigned(v) => *v,
+            other => panic!("unexpected observation {other:?}"),
+        })
+        .collect()
+}
+
+fn shard(latency: u64, region: &'static str, items: Option<usize>) -> ShardMetrics {
+    ShardMetrics {
+        latency,
+        region,
+        items,
+    }
+}
+
+#[test]
+fn merged_emits_one_observation_per_subtask() {
+    let mut metrics = RequestMetrics {
+        operation: "Scatter",
+        shards: FanIn::default(),
+    };
+    let first = metrics
+        .shards
+        .open(shard(10, "us-east-1", Some(1)), OnParentDrop::Discard);
+    let second = metrics
+        .shards
+        .open(shard(30, "us-west-2", None), OnParentDrop::Discard);
+    let mut third = metrics
+        .shards
+        .open(shard(20, "eu-west-1", Some(2)), OnParentDrop::Discard);
+    third.latency = 25;
+    drop((first, second, third));
+    // still running when the parent closes, so skipped
+    let _pending = metrics
+        .shards
+        .open(shard(99, "ap-south-1", Some(9)), OnParentDrop::Discard);
+    assert_eq!(metrics.shards.len(), 4);
+
+    let entry = test_metric(metrics);
+    assert_eq!(entry.values["Operation"], "Scatter");
+    assert_eq!(values(&entry.metrics["ShardLatency"]), [10, 30, 25]);
+    assert_eq!(
+        entry.metrics["ShardLatency"].unit,
+        metrique::writer::Unit::Second(metrique::writer::unit::NegativeScale::Milli)
+    );
+    assert_eq!(values(&entry.metrics["ShardItems"]), [1, 2]);
+    assert_eq!(entry.values["ShardRegion"], "us-east-1");
+}
+
+#[test]
+fn indexed_emits_every_subtask_separately() {
+    let mut metrics = RequestMetrics {
+        operation: "Scatter",
+        shards: FanIn::indexed(),
+    };
+    drop(
+        metrics
+            .shards
+            .open(shard(10, "us-east-1", Some(1)), OnParentDrop::Discard),
+    );
+    let _pending = metrics
+        .shards
+        .open(shard(99, "ap-south-1", Some(9)), OnParentDrop::Discard);
+    drop(
+        metrics
+            .shards
+            .open(shard(30, "us-west-2", None), OnParentDrop::Discard),
+    );
+
+    let entry = test_metric(metrics);
+    assert_eq!(entry.metrics["ShardLatency.0"], 10);
+    assert_eq!(entry.metrics["ShardItems.0"], 1);
+    assert_eq!(entry.values["ShardRegion.0"], "us-east-1");
+    assert_eq!(entry.metrics["ShardLatency.2"], 30);
+    assert_eq!(entry.values["ShardRegion.2"], "us-west-2");
+    assert!(!entry.metrics.contains_key("ShardItems.2"));
+    assert!(!entry.metrics.contains_key("ShardLatency.1"));
+}
+
+#[tokio::test]
+async fn wait_for_data_collects_spawned_subtasks() {
+    let mut shards = FanIn::<ShardMetrics>::default();
+    let mut tasks = vec![];
+    for i in 0..3 {
+        let mut guard = shards.open(shard(0, "us-east-1", None), OnParentDrop::Discard);
+        tasks.push(tokio::spawn(async move {
+            guard.latency = i * 10;
+        }));
+    }
+    let data = shards.wait_for_data().await;
+    assert_eq!(data.len(), 3);
+    assert!(data.iter().all(Option::is_some));
+    for task in tasks {
+        task.await.unwrap();
+    }
+
+    let entry = test_metric(RequestMetrics {
+        operation: "Scatter",
+        shards,
+    });
+    let mut latencies = values(&entry.metrics["ShardLatency"]);
+    latencies.sort();
+    assert_eq!(latencies, [0, 10, 20]);
+}
@@ -0,0 +1,114 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use metrique::test_util::{TestEntrySink, test_entry_sink};
+use metrique::unit_of_work::metrics;
+use metrique::{Counter, CounterHandle, FieldHandle};
+
+#[metrics(rename_all = "PascalCase")]
+struct RequestMetrics {
+    operation: &'static str,
+    #[metrics(handle)]
+    requests: Counter,
+    #[metrics(handle)]
+    pub throttled: AtomicBool,
+    errors: Counter,
+    #[metrics(flatten, prefix = "Cache")]
+    cache: CacheMetrics,
+}
+
+#[metrics(subfield)]
+#[derive(Default)]
+struct CacheMetrics {
+    hits: Counter,
+    misses: Counter,
+}
+
+fn request_metrics() -> RequestMetrics {
+    RequestMetrics {
+        operation: "Get",
+        requests: Counter::new(0),
+        throttled: AtomicBool::new(false),
+        errors: Counter::new(0),
+        cache: CacheMetrics::default(),
+    }
+}
+
+async fn call_downstream(requests: CounterHandle, throttled: FieldHandle<AtomicBool>) {
+    tokio::task::yield_now().await;
+    requests.increment();
+    throttled.store(true, Ordering::Relaxed);
+}
+
+#[tokio::test]
+async fn field_handles_update_fields_across_tasks() {
+    let TestEntrySink { inspector, sink } = test_entry_sink();
+    let handles = request_metrics().append_on_drop(sink).handles();
+
+    let tasks: Vec<_> = (0..4)
+        .map(|_| {
+            tokio::spawn(call_downstream(
+                handles.requests.clone(),
+                handles.throttled.clone(),
+            ))
+        })
+        .collect();
+    for task in tasks {
+        task.await.unwrap();
+    }
+    assert!(inspector.entries().is_empty());
+    drop(handles);
+
+    let entry = inspector.get(0);
+    assert_eq!(entry.metrics["Requests"], 4);
+    assert_eq!(entry.metrics["Throttled"], 1);
+    assert_eq!(entry.metrics["Errors"], 0);
+}
+
+#[test]
+fn field_handles_keep_the_entry_open() {
+    let TestEntrySink { inspector, sink } = test_entry_sink();
+    let handle = request_metrics().append_on_drop(sink).handle();
+    let requests = handle.handles().requests;
+    handle.errors.increment();
+    drop(handle);
+    // the field handle still holds the entry
+    assert!(inspector.entries().is_empty());
+    requests.add(2);
+    drop(requests);
+
+    let entry = inspector.get(0);
+    assert_eq!(entry.values["Operation"], "Get");
+    assert_eq!(entry.metrics["Requests"], 2);
+    assert_eq!(entry.metrics["Errors"], 1);
+}
+
+fn lookup(cache: FieldHandle<CacheMetrics>, hit: bool) {
+    let counter = if hit {
+        cache.project(|cache| &cache.hits)
+    } else {
+        cache.project(|cache| &cache.misses)
+    };
+    drop(cache);
+    counter.increment();
+}
+
+#[test]
+fn handles_project_to_subfields() {
+    let TestEntrySink { inspector, sink } = test_entry_sink();
+    let handle = request_metrics().append_on_drop(sink).handle();
+    let cache = handle.project(|metrics| &metrics.cache);
+    drop(handle);
+    lookup(cache.clone(), true);
+    lookup(cache.clone(), true);
+    lookup(cache.clone(), false);
+    assert_eq!(cache.hits.0.load(Ordering::Relaxed), 2);
+    assert!(inspector.entries().is_empty());
+    drop(cache);
+
+    let entry = inspector.get(0);
+    assert_eq!(entry.metrics["CacheHits"], 2);
+    assert_eq!(entry.metrics["CacheMisses"], 1);
+}
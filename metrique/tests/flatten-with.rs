@@ -0,0 +1,111 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::io;
+use std::net::SocketAddr;
+
+use metrique::test_util::test_metric;
+use metrique::unit_of_work::metrics;
+
+#[metrics(subfield_owned)]
+pub struct Peer {
+    ip: String,
+    port: u16,
+}
+
+/// Closes a foreign `SocketAddr` into a `Peer` entry
+mod peer_addr {
+    use metrique::CloseValue;
+    use std::net::SocketAddr;
+
+    pub type Closed = <super::Peer as CloseValue>::Closed;
+
+    pub fn close(addr: &SocketAddr) -> Closed {
+        super::Peer {
+            ip: addr.ip().to_string(),
+            port: addr.port(),
+        }
+        .close()
+    }
+}
+
+#[metrics(subfield_owned)]
+pub struct IoFailure {
+    kind: String,
+}
+
+mod io_error {
+    use metrique::CloseValue;
+
+    pub type Closed = <super::IoFailure as CloseValue>::Closed;
+
+    pub fn close(error: &std::io::Error) -> Closed {
+        super::IoFailure {
+            kind: format!("{:?}", error.kind()),
+        }
+        .close()
+    }
+}
+
+#[metrics(subfield)]
+struct Connection {
+    #[metrics(flatten, with = peer_addr)]
+    remote: SocketAddr,
+}
+
+#[metrics(rename_all = "PascalCase")]
+struct RequestMetrics {
+    #[metrics(flatten, with = peer_addr, prefix = "local_")]
+    local: SocketAddr,
+    #[metrics(flatten, prefix = "conn_")]
+    connection: Connection,
+    #[metrics(flatten, with = self::io_error, rename_all = "kebab-case")]
+    error: io::Error,
+}
+
+#[test]
+fn flatten_with_closes_foreign_types() {
+    let entry = test_metric(RequestMetrics {
+        local: "127.0.0.1:8080".parse().unwrap(),
+        connection: Connection {
+            remote: "[::1]:443".parse().unwrap(),
+        },
+        error: io::Error::from(io::ErrorKind::TimedOut),
+    });
+    assert_eq!(entry.values["LocalIp"], "127.0.0.1");
+    assert_eq!(entry.metrics["LocalPort"], 8080);
+    assert_eq!(entry.values["ConnIp"], "::1");
+    assert_eq!(entry.metrics["ConnPort"], 443);
+    assert_eq!(entry.values["kind"], "TimedOut");
+}
+
+#[metrics]
+enum Outcome {
+    Connected {
+        #[metrics(flatten, with = peer_addr)]
+        peer: SocketAddr,
+    },
+    Refused,
+}
+
+#[metrics(rename_all = "PascalCase")]
+struct EnumMetrics {
+    #[metrics(flatten)]
+    outcome: Outcome,
+}
+
+#[test]
+fn flatten_with_in_enum_variant() {
+    let entry = test_metric(EnumMetrics {
+        outcome: Outcome::Connected {
+            peer: "10.0.0.1:53".parse().unwrap(),
+        },
+    });
+    assert_eq!(entry.values["Ip"], "10.0.0.1");
+    assert_eq!(entry.metrics["Port"], 53);
+
+    let entry = test_metric(EnumMetrics {
+        outcome: Outcome::Refused,
+    });
+    assert!(!entry.values.contains_key("Ip"));
+}
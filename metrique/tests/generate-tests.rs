@@ -0,0 +1,75 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::time::Duration;
+
+use metrique::test_util::EntrySchema;
+use metrique::unit::{Byte, Millisecond};
+use metrique::unit_of_work::metrics;
+use metrique::writer::{Unit, unit::UnitTag};
+use metrique::{CloseValue, RootEntry};
+
+#[metrics(
+    rename_all = "PascalCase",
+    emf::dimension_sets = [["Operation"]],
+    generate_tests(
+        metric(name = "Latency", unit = Millisecond),
+        metric(name = "RequestCount"),
+        metric(name = "Download.PayloadSize", unit = Byte),
+        property = "Operation",
+        dimension_sets = [["Operation"]],
+    )
+)]
+#[derive(Default)]
+struct RequestMetrics {
+    operation: &'static str,
+    #[metrics(unit = Millisecond)]
+    latency: Duration,
+    request_count: usize,
+    #[metrics(flatten, exact_prefix = "Download.")]
+    download: DownloadMetrics,
+}
+
+#[metrics(subfield)]
+#[derive(Default)]
+struct DownloadMetrics {
+    #[metrics(unit = Byte)]
+    payload_size: usize,
+}
+
+fn sample_entry() -> OptionalMetrics {
+    OptionalMetrics {
+        retries: Some(2),
+        error: None,
+    }
+}
+
+#[metrics(generate_tests(sample = sample_entry, metric(name = "retries")))]
+struct OptionalMetrics {
+    retries: Option<usize>,
+    error: Option<usize>,
+}
+
+#[test]
+fn entry_schema_detects_renamed_metric() {
+    let actual = EntrySchema::of(RootEntry::new(RequestMetrics::default().close()));
+    let renamed = EntrySchema::new()
+        .metric("LatencyMs", Millisecond::UNIT, &[])
+        .metric("RequestCount", Unit::None, &[])
+        .metric("Download.PayloadSize", Byte::UNIT, &[])
+        .property("Operation")
+        .dimension_set(&["Operation"]);
+    assert_ne!(actual, renamed);
+}
+
+#[test]
+fn entry_schema_detects_changed_unit() {
+    let actual = EntrySchema::of(RootEntry::new(RequestMetrics::default().close()));
+    let changed = EntrySchema::new()
+        .metric("Latency", Unit::None, &[])
+        .metric("RequestCount", Unit::None, &[])
+        .metric("Download.PayloadSize", Byte::UNIT, &[])
+        .property("Operation")
+        .dimension_set(&["Operation"]);
+    assert_ne!(actual, changed);
+}
@@ -0,0 +1,66 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use metrique::test_util::{TestEntrySink, test_entry_sink, to_test_entry};
+use metrique::unit_of_work::metrics;
+use metrique::{CloseValue, Counter, RootEntry, global_fields};
+
+#[metrics(rename_all = "PascalCase")]
+struct RequestMetrics {
+    operation: &'static str,
+    items: Counter,
+}
+
+// the global fields are process-wide, so this is a single test
+#[test]
+fn global_fields_are_added_to_root_entries() {
+    let TestEntrySink { inspector, sink } = test_entry_sink();
+    let request = |operation| {
+        RequestMetrics {
+            operation,
+            items: Counter::default(),
+        }
+        .append_on_drop(sink.clone())
+    };
+
+    drop(request("Before"));
+    assert!(global_fields::current().is_none());
+
+    global_fields::set("Service", "Inventory");
+    global_fields::set("Version", String::from("1.2.3"));
+    global_fields::set("Service", "Catalog");
+    let fields = global_fields::current().unwrap();
+    assert_eq!(
+        fields.iter().collect::<Vec<_>>(),
+        [("Service", "Catalog"), ("Version", "1.2.3")]
+    );
+
+    // the fields are captured when the root entry is created
+    let pending = RootEntry::new(
+        RequestMetrics {
+            operation: "Pending",
+            items: Counter::new(2),
+        }
+        .close(),
+    );
+    global_fields::remove("Version");
+    drop(request("After"));
+    let pending = to_test_entry(pending);
+    assert_eq!(pending.values["Service"], "Catalog");
+    assert_eq!(pending.values["Version"], "1.2.3");
+    assert_eq!(pending.metrics["Items"], 2);
+
+    global_fields::clear();
+    assert!(global_fields::current().is_none());
+    drop(request("Cleared"));
+
+    let entries = inspector.entries();
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[0].values["Operation"], "Before");
+    assert!(!entries[0].values.contains_key("Service"));
+    assert_eq!(entries[1].values["Operation"], "After");
+    assert_eq!(entries[1].values["Service"], "Catalog");
+    assert!(!entries[1].values.contains_key("Version"));
+    assert_eq!(entries[2].values["Operation"], "Cleared");
+    assert!(!entries[2].values.contains_key("Service"));
+}
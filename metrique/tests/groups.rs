@@ -0,0 +1,129 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use metrique::emf::Emf;
+use metrique::json::Json;
+use metrique::test_util::test_metric;
+use metrique::unit_of_work::metrics;
+use metrique::writer::Entry;
+use metrique::writer::format::Format;
+use metrique::{CloseValue, RootEntry};
+
+#[metrics(rename_all = "PascalCase")]
+struct RequestMetrics {
+    operation: &'static str,
+    #[metrics(flatten, group = "cache")]
+    cache: CacheMetrics,
+    #[metrics(flatten, group = "downstream_call")]
+    downstream: DownstreamMetrics,
+}
+
+#[metrics(subfield)]
+#[derive(Default)]
+struct CacheMetrics {
+    hits: u64,
+    misses: u64,
+    region: &'static str,
+}
+
+#[metrics(subfield)]
+#[derive(Default)]
+struct DownstreamMetrics {
+    attempts: u64,
+    #[metrics(flatten, group = "dns")]
+    dns: DnsMetrics,
+}
+
+#[metrics(subfield)]
+#[derive(Default)]
+struct DnsMetrics {
+    lookups: u64,
+}
+
+fn request() -> RequestMetrics {
+    RequestMetrics {
+        operation: "Get",
+        cache: CacheMetrics {
+            hits: 3,
+            misses: 1,
+            region: "us-east-1",
+        },
+        downstream: DownstreamMetrics {
+            attempts: 2,
+            dns: DnsMetrics { lookups: 1 },
+        },
+    }
+}
+
+fn format(format: &mut impl Format, entry: &impl Entry) -> serde_json::Value {
+    let mut output = Vec::new();
+    format.format(entry, &mut output).unwrap();
+    serde_json::from_slice(&output).unwrap()
+}
+
+#[test]
+fn groups_are_flattened_with_their_prefix() {
+    let entry = test_metric(request());
+    assert_eq!(entry.values["Operation"], "Get");
+    assert_eq!(entry.metrics["CacheHits"], 3);
+    assert_eq!(entry.metrics["CacheMisses"], 1);
+    assert_eq!(entry.values["CacheRegion"], "us-east-1");
+    assert_eq!(entry.metrics["DownstreamCallAttempts"], 2);
+    assert_eq!(entry.metrics["DownstreamCallDnsLookups"], 1);
+
+    let emf = format(
+        &mut Emf::all_validations("Ns".into(), vec![vec![]]),
+        &RootEntry::new(request().close()),
+    );
+    assert_eq!(emf["CacheHits"], 3);
+    assert_eq!(emf["CacheRegion"], "us-east-1");
+    assert_eq!(emf["DownstreamCallDnsLookups"], 1);
+}
+
+#[test]
+fn groups_are_nested_in_json() {
+    let json = format(&mut Json::new(), &RootEntry::new(request().close()));
+    assert_eq!(
+        json["metrics"],
+        serde_json::json!({
+            "Cache": {
+                "Hits": { "value": 3 },
+                "Misses": { "value": 1 },
+            },
+            "DownstreamCall": {
+                "Attempts": { "value": 2 },
+                "Dns": { "Lookups": { "value": 1 } },
+            },
+        })
+    );
+    assert_eq!(
+        json["properties"],
+        serde_json::json!({
+            "Operation": "Get",
+            "Cache": { "Region": "us-east-1" },
+        })
+    );
+}
+
+#[metrics(rename_all = "snake_case")]
+struct SnakeMetrics {
+    #[metrics(flatten, group = "cache")]
+    cache: CacheMetrics,
+}
+
+#[test]
+fn group_names_follow_rename_all() {
+    let snake = SnakeMetrics {
+        cache: CacheMetrics {
+            hits: 1,
+            ..Default::default()
+        },
+    };
+    let entry = test_metric(SnakeMetrics {
+        cache: CacheMetrics::default(),
+    });
+    assert!(entry.metrics.contains_key("cache_hits"));
+
+    let json = format(&mut Json::new(), &RootEntry::new(snake.close()));
+    assert_eq!(json["metrics"]["cache"]["hits"]["value"], 1);
+}
@@ -25,19 +25,23 @@ pub struct TestMetrics {
 fn instrument_on_error() {
     let (is_odd, metrics) = try_odd(3).into_parts();
     assert_eq!(is_odd, Ok(true));
-    assert_eq!(metrics.error, false);
-    assert_eq!(metrics.success, true);
+    assert!(!metrics.error);
+    assert!(metrics.success);
 
     let (is_odd, metrics) = try_odd(4).into_parts();
     assert_eq!(is_odd, Err(4));
-    assert_eq!(metrics.error, true);
-    assert_eq!(metrics.success, false);
+    assert!(metrics.error);
+    assert!(!metrics.success);
 }
 
 fn try_odd(n: usize) -> instrument::Result<bool, usize, LookupBookMetrics> {
     Instrumented::instrument(LookupBookMetrics::default(), |metrics| {
         metrics.books_considered = 10;
-        if n % 2 == 0 { Err(n) } else { Ok(true) }
+        if n.is_multiple_of(2) {
+            Err(n)
+        } else {
+            Ok(true)
+        }
     })
     .on_error(|_res, metrics| metrics.error = true)
     .on_success(|_res, metrics| metrics.success = true)
@@ -54,14 +58,14 @@ async fn instrument_async() {
         .on_error(|_res, metrics| metrics.error = true)
         .into_parts();
     assert_eq!(res, Err(10));
-    assert_eq!(metrics.error, true);
+    assert!(metrics.error);
 }
 
 #[test]
 fn instrument_write_to() {
     let mut target: Option<LookupBookMetrics> = None;
     let result = try_odd(4).split_metrics_to(&mut target);
-    assert_eq!(target.unwrap().error, true);
+    assert!(target.unwrap().error);
     assert_eq!(result, Err(4))
 }
 
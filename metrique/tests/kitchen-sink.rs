@@ -207,14 +207,14 @@ fn flatten_flush_as_expected() {
     assert_eq!(entry.metrics["foo"], 1);
     // but nested can be
     assert_eq!(entry.metrics["B"], 1);
-    assert_eq!(entry.metrics["B"].test_flag, false);
+    assert!(!entry.metrics["B"].test_flag);
 
     assert_eq!(entry.metrics["F"], 0);
     assert_eq!(
         entry.metrics["F"].dimensions,
         vec![("Foo".to_string(), "Bar".to_string())]
     );
-    assert_eq!(entry.metrics["F"].test_flag, true);
+    assert!(entry.metrics["F"].test_flag);
     assert_eq!(entry.values["G"], "false");
 
     assert_eq!(entry.metrics["NestedMetric"], 2);
@@ -244,14 +244,14 @@ fn flatten_flush_as_expected_snake() {
     let entry = test_util::to_test_entry(&entries[0]);
 
     assert_eq!(entry.metrics["b"], 1);
-    assert_eq!(entry.metrics["b"].test_flag, false);
+    assert!(!entry.metrics["b"].test_flag);
 
     assert_eq!(entry.metrics["f"], 0);
     assert_eq!(
         entry.metrics["f"].dimensions,
         vec![("Foo".to_string(), "Bar".to_string())]
     );
-    assert_eq!(entry.metrics["f"].test_flag, true);
+    assert!(entry.metrics["f"].test_flag);
 
     assert_eq!(entry.metrics["nested_metric"], 2);
     assert_eq!(entry.metrics["nested_val_value"], 3);
@@ -314,14 +314,14 @@ fn flatten_flush_as_expected_kebab() {
     let entry = test_util::to_test_entry(&entries[0]);
 
     assert_eq!(entry.metrics["b"], 1);
-    assert_eq!(entry.metrics["b"].test_flag, false);
+    assert!(!entry.metrics["b"].test_flag);
 
     assert_eq!(entry.metrics["f"], 0);
     assert_eq!(
         entry.metrics["f"].dimensions,
         vec![("Foo".to_string(), "Bar".to_string())]
     );
-    assert_eq!(entry.metrics["f"].test_flag, true);
+    assert!(entry.metrics["f"].test_flag);
 
     assert_eq!(entry.metrics["nested-metric"], 2);
     assert_eq!(entry.metrics["nested-val-value"], 3);
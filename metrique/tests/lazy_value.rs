@@ -0,0 +1,84 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Barrier};
+
+use metrique::test_util::{TestEntrySink, test_entry_sink};
+use metrique::unit_of_work::metrics;
+use metrique::{CloseValue, LazyValue, LazyValueHandle};
+
+#[metrics(rename_all = "PascalCase")]
+struct RequestMetrics {
+    operation: &'static str,
+    cache_tier: LazyValue<&'static str>,
+    partition: LazyValue<u64>,
+    region: LazyValue<String>,
+    #[metrics(flatten)]
+    downstream: DownstreamMetrics,
+}
+
+#[metrics(subfield)]
+struct DownstreamMetrics {
+    endpoint: LazyValue<Arc<str>>,
+}
+
+fn lookup(cache_tier: LazyValueHandle<&'static str>, region: LazyValueHandle<String>) {
+    assert!(cache_tier.set("memory").is_ok());
+    assert_eq!(cache_tier.clone().set("disk"), Err("disk"));
+    std::thread::spawn(move || region.set("us-east-1".to_owned()).unwrap())
+        .join()
+        .unwrap();
+}
+
+#[test]
+fn lazy_values_are_set_through_handles() {
+    let TestEntrySink { inspector, sink } = test_entry_sink();
+    let metrics = RequestMetrics {
+        operation: "Get",
+        cache_tier: LazyValue::with_default("none"),
+        partition: LazyValue::new(),
+        region: LazyValue::new(),
+        downstream: DownstreamMetrics {
+            endpoint: LazyValue::with_default("unknown".into()),
+        },
+    }
+    .append_on_drop(sink);
+    lookup(metrics.cache_tier.handle(), metrics.region.handle());
+    assert_eq!(metrics.cache_tier.get(), Some(&"memory"));
+    let late = metrics.partition.handle();
+    drop(metrics);
+    // the entry is closed, so late values are handed back
+    assert_eq!(late.set(7), Err(7));
+
+    let entry = inspector.get(0);
+    assert_eq!(entry.values["CacheTier"], "memory");
+    assert_eq!(entry.values["Region"], "us-east-1");
+    assert_eq!(entry.values["Endpoint"], "unknown");
+    assert!(!entry.metrics.contains_key("Partition"));
+}
+
+#[test]
+fn value_is_kept_when_a_handle_sets_while_closing() {
+    for _ in 0..5000 {
+        let partition = LazyValue::<u64>::with_default(0);
+        assert!(partition.handle().set(1).is_ok());
+        let late = partition.handle();
+        let started = Arc::new(Barrier::new(2));
+        let closed = Arc::new(AtomicBool::new(false));
+        let setter = std::thread::spawn({
+            let (started, closed) = (started.clone(), closed.clone());
+            move || {
+                started.wait();
+                while !closed.load(Ordering::Relaxed) {
+                    // the value is already set, so every attempt fails
+                    assert_eq!(late.set(2), Err(2));
+                }
+            }
+        });
+        started.wait();
+        assert_eq!(partition.close(), Some(1));
+        closed.store(true, Ordering::Relaxed);
+        setter.join().unwrap();
+    }
+}
@@ -1,7 +1,12 @@
+#![allow(clippy::owned_cow)] // `Cow<String>` fields are tested on purpose
+
 use assert2::check;
 use metrique::{
-    test_util::{TestEntrySink, test_entry_sink, test_metric},
+    CloseValue, RootEntry,
+    emf::Emf,
+    test_util::{TestEntrySink, test_entry_sink, test_metric, to_test_entry},
     unit_of_work::metrics,
+    writer::{EntryIoStream, FormatExt},
 };
 use std::borrow::Cow;
 
@@ -34,17 +39,30 @@ struct UtilizeValue {
     v: MetricsValueLifetime<'static>,
 }
 
-/* Currently impossible -- we need a setting to not attempt to generate the `Ref` impl I think?
 #[metrics(value)]
 struct MetricsValueLifetimeCow<'a> {
     cow: Cow<'a, str>,
 }
-*/
+
+#[metrics(rename_all = "PascalCase")]
+struct BorrowedRequest<'a> {
+    operation: &'a str,
+    request_id: &'a [u8],
+    tenant: Option<MetricsValueLifetimeCow<'a>>,
+    #[metrics(flatten)]
+    client: &'a ClientMetrics<'a>,
+}
+
+#[metrics(subfield)]
+struct ClientMetrics<'a> {
+    client_name: Cow<'a, str>,
+    retries: usize,
+}
 
 #[test]
 fn metrics_work() {
     let foo = Foo {
-        a: Cow::Borrowed(&"123"),
+        a: Cow::Borrowed("123"),
         b: 5,
         c: Cow::Owned("1234".to_string()),
     };
@@ -57,7 +75,7 @@ fn metrics_work() {
 fn static_metrics_append_on_drop() {
     let TestEntrySink { inspector, sink } = test_entry_sink();
     let foo: Foo<'static> = Foo {
-        a: Cow::Borrowed(&"123"),
+        a: Cow::Borrowed("123"),
         b: 5,
         c: Cow::Owned("1234".to_string()),
     };
@@ -77,3 +95,45 @@ fn value_test() {
     };
     check!(test_metric(metric).values["v"] == "123");
 }
+
+#[test]
+fn borrowed_metrics_closed_before_borrow_ends() {
+    let operation = String::from("GetItem");
+    let request_id = b"req-\xff".to_vec();
+    let client = ClientMetrics {
+        client_name: Cow::Borrowed(&operation[3..]),
+        retries: 2,
+    };
+    let metrics = BorrowedRequest {
+        operation: &operation,
+        request_id: &request_id,
+        tenant: Some(MetricsValueLifetimeCow {
+            cow: Cow::Borrowed(&operation[..3]),
+        }),
+        client: &client,
+    };
+
+    let entry = to_test_entry(RootEntry::new(metrics.close()));
+    check!(entry.values["Operation"] == "GetItem");
+    check!(entry.values["RequestId"] == "req-\u{fffd}");
+    check!(entry.values["Tenant"] == "Get");
+    check!(entry.values["ClientName"] == "Item");
+    check!(entry.metrics["Retries"] == 2);
+
+    // borrowed entries can be written to a stream while the borrow lasts
+    let mut output = vec![];
+    let mut stream = Emf::no_validations("Ns".into(), vec![vec![]]).output_to(&mut output);
+    let metrics = BorrowedRequest {
+        operation: &operation,
+        request_id: &request_id,
+        tenant: None,
+        client: &client,
+    };
+    stream.next(&RootEntry::new(metrics.close())).unwrap();
+    drop(stream);
+    check!(
+        String::from_utf8(output)
+            .unwrap()
+            .contains(r#""Operation":"GetItem""#)
+    );
+}
@@ -0,0 +1,88 @@
+use std::time::Duration;
+
+use metrique::unit::Millisecond;
+use metrique::unit_of_work::metrics;
+use metrique::writer::test_util::test_metric;
+use metrique::writer::unit::{NegativeScale, Unit};
+
+#[metrics(rename_all = "PascalCase")]
+struct FieldLevel {
+    #[metrics(none_as = "zero", unit = Millisecond)]
+    latency: Option<Duration>,
+    #[metrics(none_as = "zero")]
+    retries: Option<u64>,
+    #[metrics(none_as = "null")]
+    region: Option<&'static str>,
+    #[metrics(none_as = "null")]
+    attempts: Option<u64>,
+    skipped: Option<u64>,
+}
+
+#[metrics(none_as = "zero")]
+struct ContainerLevel {
+    retries: Option<u64>,
+    #[metrics(none_as = "omit")]
+    operation: Option<&'static str>,
+    #[metrics(none_as = "null")]
+    attempts: Option<u64>,
+    count: usize,
+    #[metrics(flatten)]
+    nested: Nested,
+}
+
+#[metrics(subfield)]
+struct Nested {
+    nested_retries: Option<u64>,
+}
+
+#[test]
+fn field_level_none_as() {
+    let entry = test_metric(FieldLevel {
+        latency: None,
+        retries: Some(2),
+        region: None,
+        attempts: None,
+        skipped: None,
+    });
+    assert_eq!(entry.metrics["Latency"], 0);
+    assert_eq!(
+        entry.metrics["Latency"].unit,
+        Unit::Second(NegativeScale::Milli)
+    );
+    assert_eq!(entry.metrics["Retries"], 2);
+    assert_eq!(entry.values["Region"], "null");
+    assert_eq!(entry.values["Attempts"], "null");
+    assert!(!entry.metrics.contains_key("Skipped"));
+    assert!(!entry.values.contains_key("Skipped"));
+
+    let entry = test_metric(FieldLevel {
+        latency: Some(Duration::from_millis(5)),
+        retries: None,
+        region: Some("us-east-1"),
+        attempts: Some(3),
+        skipped: None,
+    });
+    assert_eq!(entry.metrics["Latency"], 5);
+    assert_eq!(entry.metrics["Retries"], 0);
+    assert_eq!(entry.values["Region"], "us-east-1");
+    assert_eq!(entry.metrics["Attempts"], 3);
+}
+
+#[test]
+fn container_level_none_as() {
+    let entry = test_metric(ContainerLevel {
+        retries: None,
+        operation: None,
+        attempts: None,
+        count: 1,
+        nested: Nested {
+            nested_retries: None,
+        },
+    });
+    assert_eq!(entry.metrics["retries"], 0);
+    assert!(!entry.values.contains_key("operation"));
+    assert_eq!(entry.values["attempts"], "null");
+    assert_eq!(entry.metrics["count"], 1);
+    // `none_as` is not inherited by flattened subfields
+    assert!(!entry.metrics.contains_key("nested_retries"));
+}
@@ -0,0 +1,72 @@
+use std::time::Duration;
+
+use metrique::unit::Millisecond;
+use metrique::unit_of_work::metrics;
+use metrique::writer::test_util::test_metric;
+
+#[metrics(omit_zeros, rename_all = "PascalCase")]
+struct RequestMetrics {
+    operation: &'static str,
+    throttles: usize,
+    faults: usize,
+    retries: Option<u64>,
+    #[metrics(unit = Millisecond)]
+    backoff: Duration,
+    ratio: f64,
+    #[metrics(none_as = "zero")]
+    attempts: Option<u64>,
+    #[metrics(flatten)]
+    cache: CacheMetrics,
+}
+
+#[metrics(subfield)]
+struct CacheMetrics {
+    cache_misses: usize,
+}
+
+#[test]
+fn zero_metrics_are_omitted() {
+    let entry = test_metric(RequestMetrics {
+        operation: "Get",
+        throttles: 0,
+        faults: 2,
+        retries: Some(0),
+        backoff: Duration::ZERO,
+        ratio: 0.0,
+        attempts: None,
+        cache: CacheMetrics { cache_misses: 0 },
+    });
+    assert_eq!(entry.values["Operation"], "Get");
+    assert_eq!(entry.metrics["Faults"], 2);
+    for omitted in ["Throttles", "Retries", "Backoff", "Ratio"] {
+        assert!(
+            !entry.metrics.contains_key(omitted),
+            "{omitted} was written"
+        );
+    }
+    // `none_as = "zero"` still writes its zero
+    assert_eq!(entry.metrics["Attempts"], 0);
+    // `omit_zeros` is not inherited by flattened subfields
+    assert_eq!(entry.metrics["CacheMisses"], 0);
+}
+
+#[test]
+fn non_zero_metrics_are_written() {
+    let entry = test_metric(RequestMetrics {
+        operation: "Get",
+        throttles: 1,
+        faults: 0,
+        retries: Some(3),
+        backoff: Duration::from_millis(20),
+        ratio: 0.5,
+        attempts: Some(1),
+        cache: CacheMetrics { cache_misses: 4 },
+    });
+    assert_eq!(entry.metrics["Throttles"], 1);
+    assert!(!entry.metrics.contains_key("Faults"));
+    assert_eq!(entry.metrics["Retries"], 3);
+    assert_eq!(entry.metrics["Backoff"], 20);
+    assert_eq!(entry.metrics["Ratio"], 0.5);
+    assert_eq!(entry.metrics["Attempts"], 1);
+    assert_eq!(entry.metrics["CacheMisses"], 4);
+}
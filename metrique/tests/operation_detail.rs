@@ -0,0 +1,84 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use metrique::Counter;
+use metrique::operation_detail::{OperationDetail, OperationDetailRegistry};
+use metrique::test_util::{TestEntrySink, test_entry_sink};
+use metrique::unit_of_work::metrics;
+
+#[metrics(rename_all = "PascalCase")]
+struct RequestMetrics {
+    operation: String,
+    #[metrics(flatten_entry)]
+    detail: Option<Box<dyn OperationDetail>>,
+}
+
+#[metrics(rename_all = "snake_case")]
+#[derive(Default)]
+struct PutObjectMetrics {
+    bytes_written: u64,
+    parts: Counter,
+}
+
+#[metrics]
+#[derive(Default)]
+struct DeleteObjectMetrics {
+    versions_deleted: u64,
+}
+
+fn registry() -> OperationDetailRegistry {
+    OperationDetailRegistry::new()
+        .register::<PutObjectMetrics>("PutObject")
+        .register::<DeleteObjectMetrics>("DeleteObject")
+}
+
+fn request(registry: &OperationDetailRegistry, operation: &str) -> RequestMetrics {
+    RequestMetrics {
+        operation: operation.into(),
+        detail: registry.create(operation),
+    }
+}
+
+#[test]
+fn operation_details_are_selected_at_runtime() {
+    let registry = registry();
+    let TestEntrySink { inspector, sink } = test_entry_sink();
+
+    let mut metrics = request(&registry, "PutObject").append_on_drop(sink.clone());
+    let detail = metrics.detail.as_mut().unwrap();
+    assert!(detail.downcast_ref::<DeleteObjectMetrics>().is_none());
+    let put: &mut PutObjectMetrics = detail.downcast_mut().unwrap();
+    put.bytes_written = 4096;
+    put.parts.add(2);
+    drop(metrics);
+
+    request(&registry, "GetObject").append_on_drop(sink);
+
+    let put = inspector.get(0);
+    assert_eq!(put.values["Operation"], "PutObject");
+    // the detail keeps its own names
+    assert_eq!(put.metrics["bytes_written"], 4096);
+    assert_eq!(put.metrics["parts"], 2);
+    let get = inspector.get(1);
+    assert_eq!(get.values["Operation"], "GetObject");
+    assert_eq!(get.metrics.len(), 0);
+}
+
+#[test]
+fn operation_details_can_be_sent_across_tasks() {
+    // parents of a detail can still be appended to background sinks and shared with handles
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<RequestMetrics>();
+}
+
+#[test]
+fn operation_detail_registry_lists_operations() {
+    assert_eq!(
+        format!("{:?}", registry()),
+        r#"OperationDetailRegistry { operations: ["DeleteObject", "PutObject"] }"#
+    );
+    let registry =
+        registry().register_with("PutObject", || Box::new(DeleteObjectMetrics::default()));
+    let detail = registry.create("PutObject").unwrap();
+    assert!(detail.downcast_ref::<DeleteObjectMetrics>().is_some());
+}
@@ -0,0 +1,102 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::sync::Arc;
+
+use metrique::outcome::{ClassifyError, Outcome, OutcomeKind};
+use metrique::test_util::{TestEntry, test_metric};
+use metrique::unit_of_work::metrics;
+
+#[derive(Debug)]
+struct DependencyError {
+    status: u16,
+}
+
+impl ClassifyError for DependencyError {
+    fn classify(&self) -> OutcomeKind {
+        match self.status {
+            429 => OutcomeKind::Throttle,
+            400..=499 => OutcomeKind::Error,
+            _ => OutcomeKind::Fault,
+        }
+    }
+}
+
+#[metrics(rename_all = "PascalCase")]
+struct RequestMetrics {
+    #[metrics(flatten)]
+    outcome: Outcome,
+}
+
+fn counts(entry: &TestEntry, names: [&str; 4]) -> [u64; 4] {
+    names.map(|name| entry.metrics[name].as_u64())
+}
+
+fn outcome_counts(outcome: Outcome) -> [u64; 4] {
+    let entry = test_metric(RequestMetrics { outcome });
+    counts(&entry, ["Success", "Error", "Fault", "Throttle"])
+}
+
+#[test]
+fn outcome_records_exactly_one_count() {
+    let result = |status| {
+        let mut outcome = Outcome::new();
+        let result: Result<(), DependencyError> = match status {
+            200 => Ok(()),
+            status => Err(DependencyError { status }),
+        };
+        outcome.set_result(&result);
+        outcome_counts(outcome)
+    };
+    assert_eq!(result(200), [1, 0, 0, 0]);
+    assert_eq!(result(404), [0, 1, 0, 0]);
+    assert_eq!(result(503), [0, 0, 1, 0]);
+    assert_eq!(result(429), [0, 0, 0, 1]);
+}
+
+#[test]
+fn unset_outcome_records_zeros() {
+    assert_eq!(outcome_counts(Outcome::new()), [0, 0, 0, 0]);
+}
+
+#[test]
+fn outcome_can_be_overwritten() {
+    let mut outcome = Outcome::from(OutcomeKind::Fault);
+    outcome.set_error(&DependencyError { status: 400 });
+    assert_eq!(outcome.kind(), Some(OutcomeKind::Error));
+    outcome.set_success();
+    assert_eq!(outcome_counts(outcome), [1, 0, 0, 0]);
+}
+
+#[metrics(subfield)]
+struct DependencyMetrics {
+    #[metrics(flatten)]
+    outcome: Outcome,
+}
+
+#[metrics(rename_all = "kebab-case")]
+struct ParentMetrics {
+    #[metrics(flatten, prefix = "dependency-")]
+    dependency: Arc<DependencyMetrics>,
+}
+
+#[test]
+fn outcome_in_prefixed_subfield() {
+    let entry = test_metric(ParentMetrics {
+        dependency: Arc::new(DependencyMetrics {
+            outcome: OutcomeKind::Throttle.into(),
+        }),
+    });
+    assert_eq!(
+        counts(
+            &entry,
+            [
+                "dependency-success",
+                "dependency-error",
+                "dependency-fault",
+                "dependency-throttle"
+            ]
+        ),
+        [0, 0, 0, 1]
+    );
+}
@@ -27,8 +27,10 @@ struct NestedB {
 
 #[test]
 fn renames_applied_transitively() {
-    let mut metric = Root::default();
-    metric.nested_b = Some(NestedB::default());
+    let metric = Root {
+        nested_b: Some(NestedB::default()),
+        ..Default::default()
+    };
     let entry = metric.close();
     let entry = to_test_entry(metrique::RootEntry::new(entry));
     let mut keys = entry.metrics.keys().collect::<Vec<_>>();
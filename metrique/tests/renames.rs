@@ -80,3 +80,70 @@ fn exact_prefix_metrics() {
     // a prefix doesn't apply when name is set
     assert_eq!(entry.values["name"], "abcd");
 }
+
+#[metrics(subfield, rename_all = "kebab-case")]
+struct LegacyMetrics {
+    legacy_count: usize,
+    #[metrics(flatten)]
+    nested: SnakeMetrics,
+}
+
+#[metrics(subfield, rename_all = "snake_case")]
+struct SnakeMetrics {
+    #[metrics(sample_group)]
+    operation: &'static str,
+    inner_count: usize,
+}
+
+#[metrics(rename_all = "snake_case")]
+struct MigratingMetrics {
+    // the flattened subtree is PascalCase, even though it asks for kebab-case and snake_case
+    #[metrics(flatten, rename_all = "PascalCase")]
+    legacy: LegacyMetrics,
+    #[metrics(flatten, rename_all = "kebab-case", prefix = "old_")]
+    prefixed: SubMetrics,
+    #[metrics(flatten)]
+    current: SnakeMetrics,
+}
+
+#[test]
+fn field_level_rename_all_overrides_subtree() {
+    let metrics = MigratingMetrics {
+        legacy: LegacyMetrics {
+            legacy_count: 1,
+            nested: SnakeMetrics {
+                operation: "Get",
+                inner_count: 2,
+            },
+        },
+        prefixed: SubMetrics { sub_field_a: 3 },
+        current: SnakeMetrics {
+            operation: "Put",
+            inner_count: 4,
+        },
+    };
+    let entry = RootEntry::new(metrics.close());
+    let sample_group: Vec<_> = metrique::writer::Entry::sample_group(&entry).collect();
+    assert_eq!(
+        sample_group,
+        vec![
+            ("Operation".into(), "Get".into()),
+            ("operation".into(), "Put".into())
+        ]
+    );
+
+    let entry = test_util::to_test_entry(entry);
+    let mut keys: Vec<_> = entry.metrics.keys().collect();
+    keys.sort();
+    assert_eq!(
+        keys,
+        vec![
+            "InnerCount",
+            "LegacyCount",
+            "inner_count",
+            "old-sub-field-a"
+        ]
+    );
+    assert_eq!(entry.values["Operation"], "Get");
+    assert_eq!(entry.values["operation"], "Put");
+}
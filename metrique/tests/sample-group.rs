@@ -98,3 +98,38 @@ fn test_sample_group_flatten_entry() {
     assert_eq!(inspector.get(0).values["Operation"], "CountGeese");
     assert_eq!(inspector.get(0).values["status"], "FAILURE");
 }
+
+#[metrics(rename_all = "PascalCase")]
+struct RoutedMetric {
+    #[metrics(sample_group)]
+    operation: Operation,
+    #[metrics(sample_group, no_emit)]
+    shard: &'static str,
+    number_of_birds: usize,
+}
+
+#[test]
+fn test_sample_group_no_emit() {
+    let metric = RoutedMetric {
+        operation: Operation::CountDucks,
+        shard: "shard-7",
+        number_of_birds: 3,
+    };
+    let entry = RootEntry::new(metric.close());
+    let sample_group = entry
+        .sample_group()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        sample_group,
+        vec![
+            ("Operation".to_string(), "CountDucks".to_string()),
+            ("Shard".to_string(), "shard-7".to_string())
+        ]
+    );
+    let TestEntrySink { inspector, sink } = test_entry_sink();
+    sink.append(entry);
+    assert_eq!(inspector.get(0).values["Operation"], "CountDucks");
+    assert!(!inspector.get(0).values.contains_key("Shard"));
+    assert_eq!(inspector.get(0).metrics["NumberOfBirds"], 3);
+}
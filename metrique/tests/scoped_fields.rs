@@ -0,0 +1,91 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use metrique::scoped_fields::{self, ScopedFields};
+use metrique::test_util::{TestEntrySink, test_entry_sink};
+use metrique::unit_of_work::metrics;
+use metrique::writer::BoxEntrySink;
+
+#[metrics(rename_all = "PascalCase")]
+struct RequestMetrics {
+    operation: &'static str,
+}
+
+fn append(operation: &'static str, sink: &BoxEntrySink) {
+    RequestMetrics { operation }.append_on_drop(sink.clone());
+}
+
+fn trace(id: &'static str) -> ScopedFields {
+    ScopedFields::new().with("TraceId", id)
+}
+
+#[tokio::test]
+async fn nested_scopes_inherit_fields() {
+    let TestEntrySink { inspector, sink } = test_entry_sink();
+    scoped_fields::scope(trace("outer").with("TenantId", "tenant-1"), async {
+        append("Outer", &sink);
+        scoped_fields::scope(trace("inner"), async {
+            append("Inner", &sink);
+        })
+        .await;
+        scoped_fields::sync_scope(ScopedFields::new().with("Shard", "7"), || {
+            append("Sync", &sink);
+        });
+    })
+    .await;
+    append("Outside", &sink);
+    assert!(scoped_fields::current().is_none());
+
+    let entries = inspector.entries();
+    assert_eq!(entries[0].values["TraceId"], "outer");
+    assert_eq!(entries[0].values["TenantId"], "tenant-1");
+    assert_eq!(entries[1].values["TraceId"], "inner");
+    assert_eq!(entries[1].values["TenantId"], "tenant-1");
+    assert_eq!(entries[2].values["TraceId"], "outer");
+    assert_eq!(entries[2].values["Shard"], "7");
+    assert!(!entries[3].values.contains_key("TraceId"));
+}
+
+#[tokio::test]
+async fn spawned_tasks_inherit_fields_with_in_current_scope() {
+    let TestEntrySink { inspector, sink } = test_entry_sink();
+    scoped_fields::scope(trace("request"), async {
+        let sink_ = sink.clone();
+        tokio::spawn(async move { append("Detached", &sink_) })
+            .await
+            .unwrap();
+        let sink_ = sink.clone();
+        tokio::spawn(scoped_fields::in_current_scope(async move {
+            append("Inherited", &sink_)
+        }))
+        .await
+        .unwrap();
+    })
+    .await;
+
+    let entries = inspector.entries();
+    assert_eq!(entries[0].values["Operation"], "Detached");
+    assert!(!entries[0].values.contains_key("TraceId"));
+    assert_eq!(entries[1].values["Operation"], "Inherited");
+    assert_eq!(entries[1].values["TraceId"], "request");
+}
+
+#[test]
+fn task_scope_inside_thread_scope() {
+    let TestEntrySink { inspector, sink } = test_entry_sink();
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    scoped_fields::sync_scope(ScopedFields::new().with("TenantId", "tenant-1"), || {
+        runtime.block_on(scoped_fields::scope(trace("request"), async {
+            append("Task", &sink);
+        }));
+        append("Thread", &sink);
+    });
+
+    let entries = inspector.entries();
+    assert_eq!(entries[0].values["TraceId"], "request");
+    assert_eq!(entries[0].values["TenantId"], "tenant-1");
+    assert!(!entries[1].values.contains_key("TraceId"));
+    assert_eq!(entries[1].values["TenantId"], "tenant-1");
+}
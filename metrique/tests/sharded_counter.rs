@@ -0,0 +1,56 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use metrique::test_util::{TestEntrySink, test_entry_sink};
+use metrique::unit_of_work::metrics;
+use metrique::{CloseValue, ShardedCounter};
+
+#[metrics(rename_all = "PascalCase")]
+struct BatchMetrics {
+    operation: &'static str,
+    items: ShardedCounter,
+    #[metrics(flatten)]
+    nested: NestedMetrics,
+}
+
+#[metrics(subfield)]
+struct NestedMetrics {
+    bytes: ShardedCounter,
+}
+
+#[test]
+fn sharded_counters_merge_shards_on_close() {
+    let TestEntrySink { inspector, sink } = test_entry_sink();
+    let metrics = BatchMetrics {
+        operation: "Batch",
+        items: ShardedCounter::new(),
+        nested: NestedMetrics {
+            bytes: ShardedCounter::with_shards(4),
+        },
+    }
+    .append_on_drop(sink);
+    std::thread::scope(|scope| {
+        for _ in 0..16 {
+            scope.spawn(|| {
+                for _ in 0..1000 {
+                    metrics.items.increment();
+                    metrics.nested.bytes.add(3);
+                }
+            });
+        }
+    });
+    assert_eq!(metrics.items.sum(), 16_000);
+    drop(metrics);
+
+    let entry = inspector.get(0);
+    assert_eq!(entry.metrics["Items"], 16_000);
+    assert_eq!(entry.metrics["Bytes"], 48_000);
+}
+
+#[test]
+fn sharded_counter_with_zero_shards_still_counts() {
+    let counter = ShardedCounter::with_shards(0);
+    counter.add(5);
+    counter.increment();
+    assert_eq!(counter.close(), 6);
+}
@@ -0,0 +1,89 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use metrique::control::SinkControl;
+use metrique::emf::Emf;
+use metrique::unit_of_work::metrics;
+use metrique::writer::{EntryIoStream, FormatExt};
+use metrique::{CloseValue, RootEntry};
+
+#[metrics(rename_all = "PascalCase")]
+struct RequestMetrics {
+    operation: &'static str,
+}
+
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    fn lines(&self) -> usize {
+        self.0.lock().unwrap().split(|b| *b == b'\n').count() - 1
+    }
+}
+
+impl io::Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn entry() -> RootEntry<RequestMetricsEntry> {
+    RootEntry::new(RequestMetrics { operation: "Get" }.close())
+}
+
+#[test]
+fn output_switches_at_flush() {
+    let first = SharedBuffer::default();
+    let second = SharedBuffer::default();
+    let control = SinkControl::new(first.clone());
+    let format = Emf::all_validations("Ns".to_string(), vec![vec![]]).with_sampling();
+    let mut stream = control.sample(format).output_to(control.output());
+
+    stream.next(&entry()).unwrap();
+    control.set_output(second.clone());
+    // the pending output only takes over once the current one is flushed
+    stream.next(&entry()).unwrap();
+    assert_eq!((first.lines(), second.lines()), (2, 0));
+
+    stream.flush().unwrap();
+    stream.next(&entry()).unwrap();
+    assert_eq!((first.lines(), second.lines()), (2, 1));
+}
+
+#[test]
+fn sample_rate_applies_to_later_entries() {
+    let output = SharedBuffer::default();
+    let control = SinkControl::new(output.clone());
+    let format = Emf::all_validations("Ns".to_string(), vec![vec![]]).with_sampling();
+    let mut stream = control.sample(format).output_to(control.output());
+    assert_eq!(control.sample_rate(), 1.0);
+
+    for _ in 0..100 {
+        stream.next(&entry()).unwrap();
+    }
+    assert_eq!(output.lines(), 100);
+
+    control.set_sample_rate(1e-6);
+    for _ in 0..100 {
+        stream.next(&entry()).unwrap();
+    }
+    assert!(output.lines() < 110, "{} lines", output.lines());
+
+    let sampled = output.lines();
+    control.set_sample_rate(1.0);
+    stream.next(&entry()).unwrap();
+    assert_eq!(output.lines(), sampled + 1);
+}
+
+#[test]
+#[should_panic = "sample rate must be in (0, 1]"]
+fn zero_sample_rate_panics() {
+    SinkControl::new(io::sink()).set_sample_rate(0.0);
+}
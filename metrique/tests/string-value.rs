@@ -1,3 +1,5 @@
+#![allow(clippy::enum_variant_names)]
+
 use metrique_timesource::{TimeSource, set_time_source};
 use std::time::{Duration, UNIX_EPOCH};
 
@@ -91,7 +93,7 @@ fn value_string_entry_auto_derives_debug_clone_copy() {
     let debug_str = format!("{:?}", closed);
     assert_eq!(debug_str, "Alpha");
 
-    let cloned = closed.clone();
+    let cloned = closed;
     assert_eq!(format!("{:?}", cloned), "Alpha");
 
     let copied = closed;
@@ -139,3 +141,102 @@ fn value_string_user_derives_after_metrics_preserved() {
     let closed = metrique::CloseValue::close(p);
     assert_eq!(format!("{:?}", closed), "Low");
 }
+
+#[test]
+fn shared_string_properties_in_subfield() {
+    use std::borrow::Cow;
+    use std::sync::Arc;
+
+    #[metrics(subfield)]
+    struct Shared {
+        region: Arc<str>,
+        host: Cow<'static, str>,
+    }
+
+    #[metrics]
+    struct Root {
+        #[metrics(flatten)]
+        shared: Arc<Shared>,
+        owned: Arc<str>,
+    }
+
+    let shared = Arc::new(Shared {
+        region: Arc::from("us-east-1"),
+        host: Cow::Borrowed("host-1"),
+    });
+    let entry = test_util::test_metric(Root {
+        shared: shared.clone(),
+        owned: Arc::from("owned"),
+    });
+    assert_eq!(entry.values["region"], "us-east-1");
+    assert_eq!(entry.values["host"], "host-1");
+    assert_eq!(entry.values["owned"], "owned");
+}
+
+#[test]
+fn value_string_display_and_from_str_match_metric_names() {
+    #[metrics(value(string, display, from_str), rename_all = "snake_case")]
+    #[derive(Debug, PartialEq)]
+    enum Operation {
+        GetItem,
+        #[metrics(name = "Put")]
+        PutItem,
+    }
+
+    assert_eq!(Operation::GetItem.to_string(), "get_item");
+    assert_eq!(Operation::PutItem.to_string(), "Put");
+    assert_eq!(format!("{:>9}", Operation::PutItem), "      Put");
+    assert_eq!("get_item".parse(), Ok(Operation::GetItem));
+    assert_eq!("Put".parse(), Ok(Operation::PutItem));
+
+    let err = "PutItem".parse::<Operation>().unwrap_err();
+    assert_eq!(err.input(), "PutItem");
+    assert_eq!(err.to_string(), "`PutItem` is not a valid Operation");
+
+    #[metrics]
+    struct Metrics {
+        operation: Operation,
+    }
+    let entry = metrique::test_util::test_metric(Metrics {
+        operation: "get_item".parse().unwrap(),
+    });
+    assert_eq!(entry.values["operation"], Operation::GetItem.to_string());
+}
+
+#[test]
+fn value_tuple_struct_with_ignored_and_selected_fields() {
+    struct RequestContext {
+        id: u64,
+    }
+
+    // the value field after an ignored one
+    #[metrics(value)]
+    struct AfterIgnored(
+        #[metrics(ignore)] RequestContext,
+        #[metrics(unit = Count)] u32,
+    );
+
+    // the value field selected by index, the other fields are ignored
+    #[metrics(value(field = 1), sample_group)]
+    struct Selected(RequestContext, &'static str, Vec<u8>);
+
+    #[metrics]
+    struct Metrics {
+        after_ignored: AfterIgnored,
+        #[metrics(sample_group)]
+        selected: Selected,
+    }
+
+    let metrics = Metrics {
+        after_ignored: AfterIgnored(RequestContext { id: 1 }, 5),
+        selected: Selected(RequestContext { id: 2 }, "GetItem", vec![1, 2, 3]),
+    };
+    // the ignored fields are still part of the struct
+    assert_eq!(metrics.after_ignored.0.id, 1);
+    assert_eq!((metrics.selected.0.id, metrics.selected.2.len()), (2, 3));
+
+    let entry = test_util::to_test_entry(RootEntry::new(metrics.close()));
+    assert_eq!(entry.metrics["after_ignored"], 5);
+    assert_eq!(entry.metrics["after_ignored"].unit, Unit::Count);
+    assert_eq!(entry.values["selected"], "GetItem");
+}
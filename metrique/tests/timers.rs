@@ -1,14 +1,16 @@
 // Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: Apache-2.0
 
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::ops::Range;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use metrique::writer::BoxEntrySink;
 use metrique::writer::test_util::{Inspector, TestEntrySink, test_entry_sink, to_test_entry};
 use metrique::{
     CloseValue, LazySlot, OnParentDrop, RootEntry,
     timers::{
-        EpochMicros, EpochMillis, EpochSeconds, Stopwatch, Timer, Timestamp, TimestampOnClose,
+        EpochMicros, EpochMillis, EpochSeconds, LapTimer, Spanned, Stopwatch, Timer, Timestamp,
+        TimestampOnClose,
     },
     unit::{Millisecond, Second},
     unit_of_work::metrics,
@@ -182,3 +184,100 @@ fn to_micros(ts: SystemTime) -> String {
         .as_micros()
         .to_string()
 }
+
+#[test]
+fn secondary_timestamps_as_properties() {
+    #[metrics(rename_all = "PascalCase")]
+    struct Request {
+        #[metrics(timestamp)]
+        timestamp: Timestamp,
+        #[metrics(timestamp(property))]
+        start_time: TimestampOnClose,
+        #[metrics(timestamp(property), format = EpochMicros, name = "FirstByte")]
+        first_byte_time: Option<SystemTime>,
+        #[metrics(timestamp(property))]
+        last_byte_time: Option<SystemTime>,
+    }
+
+    let ts = StaticTimeSource::at_time(UNIX_EPOCH + Duration::from_micros(1_001_001));
+    let _guard = set_time_source(TimeSource::custom(ts));
+    let entry = Request {
+        timestamp: Timestamp::now(),
+        start_time: TimestampOnClose::default(),
+        first_byte_time: Some(UNIX_EPOCH + Duration::from_micros(2_000_002)),
+        last_byte_time: None,
+    }
+    .close();
+    let entry = to_test_entry(RootEntry::new(entry));
+    assert_eq!(
+        entry.timestamp,
+        Some(UNIX_EPOCH + Duration::from_micros(1_001_001))
+    );
+    assert_eq!(entry.values["StartTime"], "1001.001");
+    assert_eq!(entry.values["FirstByte"], "2000002");
+    assert!(!entry.values.contains_key("LastByteTime"));
+}
+
+#[tokio::test(start_paused = true)]
+async fn lap_timer_names_follow_name_style() {
+    #[metrics(rename_all = "PascalCase")]
+    struct Request {
+        #[metrics(flatten, prefix = "time_")]
+        phases: LapTimer,
+        #[metrics(flatten, rename_all = "kebab-case")]
+        kebab: LapTimer,
+        #[metrics(flatten, exact_prefix = "Raw.")]
+        exact: LapTimer,
+    }
+
+    let _ts = set_time_source(TimeSource::tokio(UNIX_EPOCH));
+    let mut request = Request {
+        phases: LapTimer::start_now(),
+        kebab: LapTimer::start_now(),
+        exact: LapTimer::start_now(),
+    };
+    tokio::time::advance(Duration::from_millis(5)).await;
+    request.phases.lap("deserialize");
+    request.kebab.lap("db_write");
+    request.exact.lap("db_write");
+    tokio::time::advance(Duration::from_millis(7)).await;
+    request.phases.lap("db_write");
+
+    let entry = to_test_entry(RootEntry::new(request.close()));
+    assert_eq!(entry.metrics["TimeDeserialize"], 5);
+    assert_eq!(entry.metrics["TimeDbWrite"], 7);
+    assert_eq!(entry.metrics["db-write"], 5);
+    assert_eq!(entry.metrics["Raw.DbWrite"], 5);
+}
+
+#[test]
+fn spanned_and_instant_ranges() {
+    #[metrics(rename_all = "PascalCase")]
+    struct Request {
+        #[metrics(flatten, prefix = "connect_")]
+        connect: Spanned,
+        #[metrics(flatten, rename_all = "kebab-case", prefix = "tls_")]
+        tls: Spanned,
+        #[metrics(unit = Second)]
+        queue_time: Range<Instant>,
+        backwards: Range<Instant>,
+    }
+
+    let start = Instant::now();
+    let entry = to_test_entry(RootEntry::new(
+        Request {
+            connect: Spanned::new(start, start + Duration::from_millis(20))
+                .with_start_time(UNIX_EPOCH + Duration::from_secs(1)),
+            tls: (start..start + Duration::from_millis(3)).into(),
+            queue_time: start..start + Duration::from_millis(1500),
+            backwards: start + Duration::from_secs(1)..start,
+        }
+        .close(),
+    ));
+    assert_eq!(entry.metrics["ConnectDuration"], 20);
+    assert_eq!(entry.values["ConnectStartTime"], "1000.0");
+    assert_eq!(entry.metrics["tls-duration"], 3);
+    assert!(!entry.values.contains_key("tls-start-time"));
+    assert_eq!(entry.metrics["QueueTime"], 1.5);
+    assert_eq!(entry.metrics["Backwards"], 0);
+}
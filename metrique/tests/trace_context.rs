@@ -0,0 +1,64 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use metrique::scoped_fields;
+use metrique::test_util::{TestEntrySink, test_entry_sink};
+use metrique::trace_context::{SPAN_ID, TRACE_ID, TraceContext};
+use metrique::unit_of_work::metrics;
+use opentelemetry::trace::{TraceContextExt, TracerProvider};
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use tracing::subscriber::with_default;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::layer::SubscriberExt;
+
+#[metrics(rename_all = "PascalCase")]
+struct RequestMetrics {
+    operation: &'static str,
+}
+
+#[test]
+fn captures_opentelemetry_context_of_current_tracing_span() {
+    let TestEntrySink { inspector, sink } = test_entry_sink();
+    let tracer = SdkTracerProvider::builder().build().tracer("test");
+    let subscriber =
+        tracing_subscriber::registry().with(tracing_opentelemetry::layer().with_tracer(tracer));
+    with_default(subscriber, || {
+        let span = tracing::info_span!("request");
+        let _enter = span.enter();
+        RequestMetrics { operation: "Get" }.append_on_drop(sink.clone());
+
+        let context = span.context();
+        let span_context = context.span().span_context().clone();
+        let entry = &inspector.entries()[0];
+        assert_eq!(entry.values[TRACE_ID], span_context.trace_id().to_string());
+        assert_eq!(entry.values[SPAN_ID], span_context.span_id().to_string());
+        assert_eq!(entry.values[SPAN_ID].len(), 16);
+    });
+
+    // without a span, or without an OpenTelemetry layer, there is no trace context
+    RequestMetrics { operation: "Put" }.append_on_drop(sink.clone());
+    with_default(tracing_subscriber::registry(), || {
+        let _enter = tracing::info_span!("request").entered();
+        RequestMetrics { operation: "Put" }.append_on_drop(sink.clone());
+    });
+    for entry in &inspector.entries()[1..] {
+        assert!(!entry.values.contains_key(TRACE_ID));
+        assert!(!entry.values.contains_key(SPAN_ID));
+    }
+}
+
+#[tokio::test]
+async fn traceparent_in_scoped_fields() {
+    let TestEntrySink { inspector, sink } = test_entry_sink();
+    let context =
+        TraceContext::from_traceparent("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01")
+            .unwrap();
+    scoped_fields::scope(context.to_scoped_fields(), async {
+        RequestMetrics { operation: "Get" }.append_on_drop(sink.clone());
+    })
+    .await;
+
+    let entry = &inspector.entries()[0];
+    assert_eq!(entry.values[TRACE_ID], "4bf92f3577b34da6a3ce929d0e0e4736");
+    assert_eq!(entry.values[SPAN_ID], "00f067aa0ba902b7");
+}
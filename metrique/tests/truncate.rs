@@ -0,0 +1,91 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use metrique::test_util::{test_metric, to_test_entry};
+use metrique::unit_of_work::metrics;
+use metrique::writer::EntrySink;
+use metrique::writer::sink::{ProcessSink, TruncateStrings, VecEntrySink};
+use metrique::{CloseValue, RootEntry};
+
+#[metrics(rename_all = "PascalCase")]
+struct RequestMetrics {
+    operation: &'static str,
+    #[metrics(max_len = 10)]
+    message: String,
+    #[metrics(max_len = 10, truncation_marker = " [cut]")]
+    detail: Option<String>,
+    #[metrics(max_len = 10, truncation_marker = "")]
+    peer: &'static str,
+    #[metrics(max_len = 10)]
+    bytes: u64,
+}
+
+#[metrics]
+enum Outcome {
+    Failed {
+        #[metrics(max_len = 4)]
+        reason: String,
+    },
+}
+
+#[test]
+fn max_len_truncates_long_strings() {
+    let entry = test_metric(RequestMetrics {
+        operation: "GetObjectAttributes",
+        message: "connection reset by peer".into(),
+        detail: Some("while reading the response body".into()),
+        peer: "192.0.2.1:443",
+        bytes: 123_456_789_012,
+    });
+    // fields without `max_len` are left alone
+    assert_eq!(entry.values["Operation"], "GetObjectAttributes");
+    assert_eq!(entry.values["Message"], "connect...");
+    assert_eq!(entry.values["Detail"], "whil [cut]");
+    assert_eq!(entry.values["Peer"], "192.0.2.1:");
+    // metrics are never truncated
+    assert_eq!(entry.metrics["Bytes"], 123_456_789_012);
+}
+
+#[test]
+fn max_len_keeps_short_strings() {
+    let entry = test_metric(RequestMetrics {
+        operation: "Get",
+        message: "héllo".into(),
+        detail: None,
+        peer: "192.0.2.1",
+        bytes: 0,
+    });
+    assert_eq!(entry.values["Message"], "héllo");
+    assert!(!entry.values.contains_key("Detail"));
+    assert_eq!(entry.values["Peer"], "192.0.2.1");
+}
+
+#[test]
+fn max_len_on_enum_variant_fields() {
+    let entry = test_metric(Outcome::Failed {
+        reason: "throttled".into(),
+    });
+    assert_eq!(entry.values["reason"], "t...");
+}
+
+#[test]
+fn truncate_strings_processor_truncates_every_property() {
+    let entries = VecEntrySink::new();
+    let sink =
+        ProcessSink::new(entries.clone()).processor(TruncateStrings::new(12).with_marker("…"));
+    sink.append(RootEntry::new(
+        RequestMetrics {
+            operation: "GetObjectAttributes",
+            message: "reset".into(),
+            detail: None,
+            peer: "192.0.2.1",
+            bytes: 123_456_789_012,
+        }
+        .close(),
+    ));
+
+    let entry = to_test_entry(entries.drain().pop().unwrap());
+    assert_eq!(entry.values["Operation"], "GetObjectAt…");
+    assert_eq!(entry.values["Message"], "reset");
+    assert_eq!(entry.metrics["Bytes"], 123_456_789_012);
+}
@@ -16,6 +16,7 @@ fn matching_rust_version() -> bool {
 }
 
 #[test]
+#[allow(deprecated)] // `matching_rust_version` is deprecated on non-matching toolchains
 fn test_metrics_macro() {
     if !matching_rust_version() {
         eprintln!(
@@ -0,0 +1,16 @@
+use metrique::unit_of_work::metrics;
+
+#[metrics(subfield)]
+struct Node {
+    value: u64,
+    #[metrics(flatten)]
+    child: Option<Box<Node>>,
+}
+
+#[metrics(subfield)]
+enum Tree {
+    Leaf(#[metrics(flatten)] Node),
+    Branch(#[metrics(flatten)] Box<Self>),
+}
+
+fn main() {}
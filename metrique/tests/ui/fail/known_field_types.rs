@@ -0,0 +1,25 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use metrique::unit_of_work::metrics;
+
+// subfields are closed by reference, and `&String` does not implement `CloseValue`
+#[metrics(subfield)]
+struct ClientMetrics {
+    client_name: String,
+    region: Option<std::string::String>,
+}
+
+#[metrics(rename_all = "PascalCase")]
+struct RequestMetrics {
+    operation: &str,
+    delta: i64,
+    offset: Option<i32>,
+    // `no_close` fields are not checked
+    #[metrics(no_close)]
+    count: u64,
+    #[metrics(flatten)]
+    client: ClientMetrics,
+}
+
+fn main() {}
@@ -0,0 +1,22 @@
+use metrique::unit_of_work::metrics;
+
+#[metrics(value(field = 2))]
+struct SelectMissing(u32, u32);
+
+#[metrics(value(field = 0))]
+struct SelectIgnored(#[metrics(ignore)] u32, u32);
+
+#[metrics(value(field = 0))]
+struct SelectWithAttributes(u32, #[metrics(name = "Other")] u32);
+
+#[metrics(value(field = 0))]
+struct SelectNamed {
+    x: u32,
+}
+
+#[metrics(value(string, field = 0))]
+enum SelectString {
+    X,
+}
+
+fn main() {}
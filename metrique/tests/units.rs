@@ -0,0 +1,164 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::num::NonZeroU32;
+
+use metrique::emf::Emf;
+use metrique::test_util::test_metric;
+use metrique::unit::{BitPerSecond, Byte, Kilobyte, MegabytePerSecond, Percent, Ratio};
+use metrique::unit_of_work::metrics;
+use metrique::writer::Observation;
+use metrique::writer::format::Format;
+use metrique::writer::unit::{AsRatio, Unit};
+use metrique::{CloseValue, RootEntry};
+
+#[metrics(rename_all = "PascalCase")]
+struct RequestMetrics {
+    #[metrics(unit = Percent)]
+    cache_hit_rate: AsRatio<f64>,
+    #[metrics(unit = Ratio)]
+    load_factor: f64,
+    #[metrics(unit = Percent)]
+    cpu_utilization: f64,
+    #[metrics(flatten)]
+    shard: ShardMetrics,
+}
+
+#[metrics(subfield)]
+struct ShardMetrics {
+    #[metrics(unit = Percent)]
+    shard_hit_rate: AsRatio<f64>,
+}
+
+#[test]
+fn ratios_are_scaled_to_percent() {
+    let entry = test_metric(RequestMetrics {
+        cache_hit_rate: 0.25.into(),
+        load_factor: 0.75,
+        cpu_utilization: 42.0,
+        shard: ShardMetrics {
+            shard_hit_rate: 1.0.into(),
+        },
+    });
+
+    let hit_rate = &entry.metrics["CacheHitRate"];
+    assert_eq!(hit_rate.distribution, [Observation::Floating(25.0)]);
+    assert_eq!(hit_rate.unit, Unit::Percent);
+
+    // a ratio without conversion is emitted as-is, without a unit
+    let load_factor = &entry.metrics["LoadFactor"];
+    assert_eq!(load_factor.distribution, [Observation::Floating(0.75)]);
+    assert_eq!(load_factor.unit, Unit::None);
+
+    // plain values are assumed to already be percentages
+    let cpu = &entry.metrics["CpuUtilization"];
+    assert_eq!(cpu.distribution, [Observation::Floating(42.0)]);
+    assert_eq!(cpu.unit, Unit::Percent);
+
+    let shard_hit_rate = &entry.metrics["ShardHitRate"];
+    assert_eq!(shard_hit_rate.distribution, [Observation::Floating(100.0)]);
+    assert_eq!(shard_hit_rate.unit, Unit::Percent);
+}
+
+#[metrics(rename_all = "PascalCase")]
+struct TransferMetrics {
+    #[metrics(unit = Byte)]
+    body_len: usize,
+    #[metrics(unit = Kilobyte)]
+    chunk_size: NonZeroU32,
+    #[metrics(unit = Byte)]
+    total_transferred: u128,
+    #[metrics(unit = MegabytePerSecond)]
+    throughput: f64,
+    #[metrics(unit = BitPerSecond)]
+    link_speed: u64,
+}
+
+#[test]
+fn unsigned_and_rate_units_are_emitted_as_emf() {
+    let mut emf = Emf::all_validations("MyApp".to_string(), vec![vec![]]);
+    let mut output = vec![];
+    emf.format(
+        &RootEntry::new(
+            TransferMetrics {
+                body_len: 1024,
+                chunk_size: NonZeroU32::new(64).unwrap(),
+                total_transferred: u128::from(u64::MAX) + 1,
+                throughput: 12.5,
+                link_speed: 10_000_000_000,
+            }
+            .close(),
+        ),
+        &mut output,
+    )
+    .unwrap();
+
+    let output: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    let units: Vec<_> = output["_aws"]["CloudWatchMetrics"][0]["Metrics"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|metric| (metric["Name"].as_str().unwrap(), metric["Unit"].as_str()))
+        .collect();
+    assert_eq!(
+        units,
+        [
+            ("BodyLen", Some("Bytes")),
+            ("ChunkSize", Some("Kilobytes")),
+            ("TotalTransferred", Some("Bytes")),
+            ("Throughput", Some("Megabytes/Second")),
+            ("LinkSpeed", Some("Bits/Second")),
+        ]
+    );
+    assert_eq!(output["BodyLen"], 1024);
+    assert_eq!(output["ChunkSize"], 64);
+    assert_eq!(output["TotalTransferred"], 18446744073709551616.0);
+    assert_eq!(output["LinkSpeed"], 10_000_000_000u64);
+}
+
+#[metrics(rename_all = "PascalCase")]
+struct OrderMetrics {
+    #[metrics(unit = Custom("Widgets"))]
+    widgets_sold: u64,
+    #[metrics(unit = Custom("Count/Second"))]
+    order_rate: f64,
+    #[metrics(unit = Custom("Widgets"))]
+    widgets_returned: Option<u64>,
+}
+
+fn order() -> OrderMetrics {
+    OrderMetrics {
+        widgets_sold: 3,
+        order_rate: 0.5,
+        widgets_returned: None,
+    }
+}
+
+#[test]
+fn custom_units_are_attached() {
+    let entry = test_metric(order());
+    assert_eq!(entry.metrics["WidgetsSold"], 3);
+    assert_eq!(entry.metrics["WidgetsSold"].unit, Unit::Custom("Widgets"));
+    assert_eq!(
+        entry.metrics["OrderRate"].unit,
+        Unit::Custom("Count/Second")
+    );
+    assert!(!entry.metrics.contains_key("WidgetsReturned"));
+}
+
+#[test]
+fn custom_units_unknown_to_cloudwatch_are_dropped_from_emf() {
+    let mut emf = Emf::all_validations("MyApp".to_string(), vec![vec![]]);
+    let mut output = vec![];
+    emf.format(&RootEntry::new(order().close()), &mut output)
+        .unwrap();
+    let output: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(
+        output["_aws"]["CloudWatchMetrics"][0]["Metrics"],
+        serde_json::json!([
+            {"Name": "WidgetsSold"},
+            {"Name": "OrderRate", "Unit": "Count/Second"},
+        ])
+    );
+    assert_eq!(output["WidgetsSold"], 3);
+}
@@ -0,0 +1,64 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::time::Duration;
+
+use metrique::test_util::test_metric;
+use metrique::unit::Millisecond;
+use metrique::unit_of_work::metrics;
+use metrique::writer::Entry;
+use metrique::writer::unit::{NegativeScale, Unit};
+
+#[metrics(rename_all = "PascalCase")]
+struct RequestMetrics {
+    operation: &'static str,
+    #[metrics(verbose, unit = Millisecond)]
+    lock_wait: Duration,
+    #[metrics(verbose, flatten, prefix = "cache_")]
+    cache: CacheMetrics,
+    #[metrics(verbose, flatten_entry, no_close)]
+    debug: DebugEntry,
+}
+
+#[metrics(subfield_owned)]
+struct CacheMetrics {
+    hits: usize,
+}
+
+#[derive(Entry)]
+struct DebugEntry {
+    shard: u64,
+}
+
+fn request() -> RequestMetrics {
+    RequestMetrics {
+        operation: "GetItem",
+        lock_wait: Duration::from_millis(12),
+        cache: CacheMetrics { hits: 2 },
+        debug: DebugEntry { shard: 7 },
+    }
+}
+
+// a single test, since the verbose flag is global to the process
+#[test]
+fn verbose_fields_follow_runtime_flag() {
+    metrique::verbose::set_enabled(false);
+    assert!(!metrique::verbose::is_enabled());
+    let entry = test_metric(request());
+    assert_eq!(entry.values["Operation"], "GetItem");
+    for name in ["LockWait", "CacheHits", "shard"] {
+        assert!(!entry.metrics.contains_key(name), "{name} was emitted");
+    }
+
+    metrique::verbose::set_enabled(true);
+    assert!(metrique::verbose::is_enabled());
+    let entry = test_metric(request());
+    assert_eq!(entry.values["Operation"], "GetItem");
+    assert_eq!(entry.metrics["LockWait"], 12);
+    assert_eq!(
+        entry.metrics["LockWait"].unit,
+        Unit::Second(NegativeScale::Milli)
+    );
+    assert_eq!(entry.metrics["CacheHits"], 2);
+    assert_eq!(entry.metrics["shard"], 7);
+}
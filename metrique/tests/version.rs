@@ -0,0 +1,57 @@
+use metrique::unit_of_work::metrics;
+use metrique::writer::test_util::test_metric;
+
+#[metrics(version = 2, rename_all = "PascalCase")]
+struct RequestMetrics {
+    retries: usize,
+}
+
+#[metrics(subfield)]
+pub struct CacheMetrics {
+    hits: usize,
+}
+
+#[metrics(version = 3, tag(name = "operation"))]
+enum OperationMetrics {
+    Read {
+        #[metrics(flatten)]
+        cache: CacheMetrics,
+    },
+    Write,
+}
+
+#[metrics]
+struct Unversioned {
+    retries: usize,
+}
+
+#[test]
+fn version_is_written_as_property() {
+    let entry = test_metric(RequestMetrics { retries: 1 });
+    assert_eq!(entry.values["SchemaVersion"], "2");
+    assert_eq!(entry.metrics["Retries"], 1);
+    assert!(!entry.metrics.contains_key("SchemaVersion"));
+}
+
+#[test]
+fn version_is_written_for_every_variant() {
+    let entry = test_metric(OperationMetrics::Read {
+        cache: CacheMetrics { hits: 4 },
+    });
+    assert_eq!(entry.values["schema_version"], "3");
+    assert_eq!(entry.values["operation"], "Read");
+    assert_eq!(entry.metrics["hits"], 4);
+
+    let entry = test_metric(OperationMetrics::Write);
+    assert_eq!(entry.values["schema_version"], "3");
+}
+
+#[test]
+fn schema_version() {
+    assert_eq!(metrique::schema_version::<RequestMetrics>(), Some(2));
+    assert_eq!(metrique::schema_version::<OperationMetrics>(), Some(3));
+    assert_eq!(metrique::schema_version::<Unversioned>(), None);
+
+    let entry = test_metric(Unversioned { retries: 1 });
+    assert!(!entry.values.contains_key("schema_version"));
+}
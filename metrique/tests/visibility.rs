@@ -25,6 +25,27 @@ pub(crate) struct CrateVisibleMetrics {
     pub(crate) crate_visible: usize,
 }
 
+// `entry_vis` keeps the generated types out of the public API
+#[metrics(entry_vis = "pub(crate)")]
+pub(crate) struct CrateEntryMetrics {
+    count: usize,
+}
+
+#[metrics(subfield, entry_vis = "pub(crate)")]
+pub(crate) struct CrateEntrySubfield {
+    hits: usize,
+}
+
+#[metrics(entry_vis = "pub(crate)")]
+pub(crate) enum CrateEntryEnum {
+    Read {
+        bytes: usize,
+        #[metrics(flatten)]
+        cache: CrateEntrySubfield,
+    },
+    Write,
+}
+
 mod inner {
     use metrique::unit_of_work::metrics;
 
@@ -52,6 +73,24 @@ mod inner {
     }
 }
 
+#[test]
+fn entry_vis() {
+    let struct_sink = metrique::writer::sink::VecEntrySink::new();
+    let enum_sink = metrique::writer::sink::VecEntrySink::new();
+    let guard: CrateEntryMetricsGuard<_> =
+        CrateEntryMetrics { count: 1 }.append_on_drop(struct_sink.clone());
+    let handle: CrateEntryEnumHandle<_> = CrateEntryEnum::Read {
+        bytes: 2,
+        cache: CrateEntrySubfield { hits: 3 },
+    }
+    .append_on_drop(enum_sink.clone())
+    .handle();
+    drop((guard, handle));
+    let _entry: CrateEntryEnumEntry = metrique::CloseValue::close(CrateEntryEnum::Write);
+    assert_eq!(struct_sink.drain().len(), 1);
+    assert_eq!(enum_sink.drain().len(), 1);
+}
+
 fn main() {
     // Test public struct with mixed visibility fields
     let _public_metrics = PublicMetrics {